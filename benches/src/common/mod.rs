@@ -1,48 +1,45 @@
 use pay::prelude::*;
 use std::fs::File;
-use std::io::Write;
+use std::io::BufWriter;
 use std::path::Path;
 
+/// Configure the library's `DatasetGenerator` with the specified parameters
+///
+/// Whatever share the ratios leave over resolves open disputes.
+fn dataset(
+    num_transactions: usize,
+    num_clients: ClientId,
+    deposit_ratio: f64,
+    withdrawal_ratio: f64,
+    dispute_ratio: f64,
+) -> DatasetGenerator {
+    DatasetGenerator::new(num_transactions)
+        .with_clients(num_clients)
+        .with_deposit_ratio(deposit_ratio)
+        .with_withdrawal_ratio(withdrawal_ratio)
+        .with_dispute_ratio(dispute_ratio)
+}
+
 /// Generate a CSV dataset with the specified parameters
 #[allow(dead_code)]
 pub fn generate_csv_dataset(
     num_transactions: usize,
-    num_clients: u16,
+    num_clients: ClientId,
     deposit_ratio: f64,
     withdrawal_ratio: f64,
     dispute_ratio: f64,
 ) -> String {
-    let mut csv = String::from("type,client,tx,amount\n");
-    let mut tx_counter = 1u32;
-    let mut deposited_txs = Vec::new();
-
-    for i in 0..num_transactions {
-        let client_id = ((i % num_clients as usize) + 1) as u16;
-        let rand_val = i as f64 / num_transactions as f64;
-
-        if rand_val < deposit_ratio {
-            // Deposit
-            let amount = format!("{}.{:04}", (i % 1000) + 1, i % 10000);
-            csv.push_str(&format!("deposit,{},{},{}\n", client_id, tx_counter, amount));
-            deposited_txs.push((client_id, tx_counter));
-            tx_counter += 1;
-        } else if rand_val < deposit_ratio + withdrawal_ratio {
-            // Withdrawal (only if we have deposits for this client)
-            if !deposited_txs.is_empty() {
-                let amount = format!("{}.{:04}", (i % 100) + 1, i % 10000);
-                csv.push_str(&format!("withdrawal,{},{},{}\n", client_id, tx_counter, amount));
-                tx_counter += 1;
-            }
-        } else if rand_val < deposit_ratio + withdrawal_ratio + dispute_ratio {
-            // Dispute (reference a previous transaction)
-            if let Some(&(dep_client, dep_tx)) = deposited_txs.get(i % deposited_txs.len())
-                && dep_client == client_id {
-                csv.push_str(&format!("dispute,{},{},\n", client_id, dep_tx));
-            }
-        }
-    }
-
-    csv
+    let mut csv = Vec::new();
+    dataset(
+        num_transactions,
+        num_clients,
+        deposit_ratio,
+        withdrawal_ratio,
+        dispute_ratio,
+    )
+    .write_csv(&mut csv)
+    .expect("writing to memory cannot fail");
+    String::from_utf8(csv).expect("generated CSV is ASCII")
 }
 
 /// Generate CSV dataset and write to file
@@ -50,21 +47,20 @@ pub fn generate_csv_dataset(
 pub fn generate_csv_file<P: AsRef<Path>>(
     path: P,
     num_transactions: usize,
-    num_clients: u16,
+    num_clients: ClientId,
     deposit_ratio: f64,
     withdrawal_ratio: f64,
     dispute_ratio: f64,
 ) -> std::io::Result<()> {
-    let csv = generate_csv_dataset(
+    let file = BufWriter::new(File::create(path)?);
+    dataset(
         num_transactions,
         num_clients,
         deposit_ratio,
         withdrawal_ratio,
         dispute_ratio,
-    );
-    let mut file = File::create(path)?;
-    file.write_all(csv.as_bytes())?;
-    Ok(())
+    )
+    .write_csv(file)
 }
 
 /// Create standard fixture datasets
@@ -133,12 +129,17 @@ pub fn setup_processor() -> TransactionProcessor<FixedPoint, ConcurrentAccountMa
 
 /// Create a batch of deposit transactions for testing
 #[allow(dead_code)]
-pub fn create_deposit_batch(start_tx_id: u32, count: usize, client_id: u16) -> Vec<Transaction<FixedPoint>> {
+pub fn create_deposit_batch(
+    start_tx_id: TransactionId,
+    count: usize,
+    client_id: ClientId,
+) -> Vec<Transaction<FixedPoint>> {
     (0..count)
         .map(|i| Transaction::Deposit {
             client_id,
-            tx_id: start_tx_id + i as u32,
+            tx_id: start_tx_id + i as TransactionId,
             amount: FixedPoint::from_raw(10_000), // 1.0000
+            currency: None,
         })
         .collect()
 }
@@ -146,15 +147,14 @@ pub fn create_deposit_batch(start_tx_id: u32, count: usize, client_id: u16) -> V
 /// Create a batch of transactions with mixed types
 #[allow(dead_code)]
 pub fn create_mixed_batch(
-    start_tx_id: u32,
+    start_tx_id: TransactionId,
     count: usize,
-    num_clients: u16,
+    num_clients: ClientId,
 ) -> Vec<Transaction<FixedPoint>> {
     let mut transactions = Vec::with_capacity(count);
-    let mut tx_id = start_tx_id;
 
-    for i in 0..count {
-        let client_id = ((i % num_clients as usize) + 1) as u16;
+    for (i, tx_id) in (start_tx_id..).take(count).enumerate() {
+        let client_id = ((i % num_clients as usize) + 1) as ClientId;
         let tx_type = i % 10;
 
         let tx = match tx_type {
@@ -162,17 +162,18 @@ pub fn create_mixed_batch(
                 client_id,
                 tx_id,
                 amount: FixedPoint::from_raw(((i % 1000) + 1) as i64 * 10_000),
+                currency: None,
             },
             7..=9 => Transaction::Withdrawal {
                 client_id,
                 tx_id,
                 amount: FixedPoint::from_raw(((i % 100) + 1) as i64 * 10_000),
+                currency: None,
             },
             _ => unreachable!(),
         };
 
         transactions.push(tx);
-        tx_id += 1;
     }
 
     transactions
@@ -45,14 +45,16 @@ fn bench_concurrent_streams_scaling(c: &mut Criterion) {
                         // Create streams with disjoint client IDs (low contention)
                         let streams: Vec<_> = (0..num_streams)
                             .map(|stream_id| {
-                                let client_id = stream_id as u16 + 1;
-                                let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                                let client_id = stream_id as ClientId + 1;
+                                let start_tx_id =
+                                    (stream_id * transactions_per_stream) as TransactionId;
 
                                 let transactions: Vec<_> = (0..transactions_per_stream)
                                     .map(|i| Transaction::Deposit {
                                         client_id,
-                                        tx_id: start_tx_id + i as u32,
+                                        tx_id: start_tx_id + i as TransactionId,
                                         amount: FixedPoint::from_raw(10_000),
+                                        currency: None,
                                     })
                                     .collect();
 
@@ -118,13 +120,15 @@ fn bench_high_contention(c: &mut Criterion) {
                         // All streams access CLIENT 1 (maximum contention)
                         let streams: Vec<_> = (0..num_streams)
                             .map(|stream_id| {
-                                let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                                let start_tx_id =
+                                    (stream_id * transactions_per_stream) as TransactionId;
 
                                 let transactions: Vec<_> = (0..transactions_per_stream)
                                     .map(|i| Transaction::Deposit {
                                         client_id: 1,  // Same client for all streams!
-                                        tx_id: start_tx_id + i as u32,
+                                        tx_id: start_tx_id + i as TransactionId,
                                         amount: FixedPoint::from_raw(10_000),
+                                        currency: None,
                                     })
                                     .collect();
 
@@ -189,15 +193,17 @@ fn bench_low_contention(c: &mut Criterion) {
                         // Each stream has completely disjoint client IDs
                         let streams: Vec<_> = (0..num_streams)
                             .map(|stream_id| {
-                                // Use modulo to prevent u16 overflow while keeping disjoint ranges
-                                let base_client = ((stream_id * 100) % 60000) as u16;
-                                let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                                // Use modulo to prevent ClientId overflow while keeping disjoint ranges
+                                let base_client = ((stream_id * 100) % 60000) as ClientId;
+                                let start_tx_id =
+                                    (stream_id * transactions_per_stream) as TransactionId;
 
                                 let transactions: Vec<_> = (0..transactions_per_stream)
                                     .map(|i| Transaction::Deposit {
-                                        client_id: base_client + (i % 100) as u16,
-                                        tx_id: start_tx_id + i as u32,
+                                        client_id: base_client + (i % 100) as ClientId,
+                                        tx_id: start_tx_id + i as TransactionId,
                                         amount: FixedPoint::from_raw(10_000),
+                                        currency: None,
                                     })
                                     .collect();
 
@@ -259,8 +265,8 @@ fn bench_error_policy_concurrent(c: &mut Criterion) {
         // Create streams with some invalid transactions (insufficient funds)
         let streams: Vec<_> = (0..num_streams)
             .map(|stream_id| {
-                let client_id = stream_id as u16 + 1;
-                let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                let client_id = stream_id as ClientId + 1;
+                let start_tx_id = (stream_id * transactions_per_stream) as TransactionId;
 
                 let mut transactions = vec![];
                 // First deposit
@@ -268,14 +274,16 @@ fn bench_error_policy_concurrent(c: &mut Criterion) {
                     client_id,
                     tx_id: start_tx_id,
                     amount: FixedPoint::from_raw(10_000),
+                    currency: None,
                 });
 
                 // Then many withdrawals (most will fail due to insufficient funds)
                 for i in 1..transactions_per_stream {
                     transactions.push(Transaction::Withdrawal {
                         client_id,
-                        tx_id: start_tx_id + i as u32,
+                        tx_id: start_tx_id + i as TransactionId,
                         amount: FixedPoint::from_raw(5_000),
+                        currency: None,
                     });
                 }
 
@@ -334,26 +342,29 @@ fn bench_zipf_distribution(c: &mut Criterion) {
         let transaction_store = Arc::new(ConcurrentTransactionStore::<FixedPoint>::new());
 
         // Zipf-like distribution: 20% of clients get 80% of traffic
-        let hot_clients = 20u16;
-        let total_clients = 100u16;
+        let hot_clients = 20;
+        let total_clients = 100;
 
         let streams: Vec<_> = (0..num_streams)
             .map(|stream_id| {
-                let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                let start_tx_id = (stream_id * transactions_per_stream) as TransactionId;
 
                 let transactions: Vec<_> = (0..transactions_per_stream)
                     .map(|i| {
                         // 80% chance to hit hot clients
                         let client_id = if i % 5 < 4 {
-                            (i % hot_clients as usize) as u16 + 1
+                            (i % hot_clients as usize) as ClientId + 1
                         } else {
-                            hot_clients + ((i % (total_clients - hot_clients) as usize) as u16) + 1
+                            hot_clients
+                                + ((i % (total_clients - hot_clients) as usize) as ClientId)
+                                + 1
                         };
 
                         Transaction::Deposit {
                             client_id,
-                            tx_id: start_tx_id + i as u32,
+                            tx_id: start_tx_id + i as TransactionId,
                             amount: FixedPoint::from_raw(10_000),
+                            currency: None,
                         }
                     })
                     .collect();
@@ -193,9 +193,10 @@ fn bench_snapshot_generation(c: &mut Criterion) {
             for i in 0..num_accounts {
                 processor
                     .process_transaction(Transaction::Deposit {
-                        client_id: i as u16,
-                        tx_id: i as u32,
+                        client_id: i as ClientId,
+                        tx_id: i as TransactionId,
                         amount: FixedPoint::from_raw(10_000),
+                        currency: None,
                     })
                     .unwrap();
             }
@@ -329,9 +330,10 @@ fn bench_parsing_vs_processing(c: &mut Criterion) {
         let processor = common::setup_processor();
         let transactions: Vec<_> = (0..num_transactions)
             .map(|i| Transaction::Deposit {
-                client_id: ((i % num_clients as usize) + 1) as u16,
-                tx_id: i as u32,
+                client_id: ((i % num_clients as usize) + 1) as ClientId,
+                tx_id: i as TransactionId,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .collect();
         (processor, transactions)
@@ -348,6 +350,38 @@ fn bench_parsing_vs_processing(c: &mut Criterion) {
     });
 }
 
+/// Benchmark the sharded topology against the sequential fast path
+fn bench_sequential_fast_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sequential_fast_path");
+    let runtime = Runtime::new().unwrap();
+
+    let setup = || generate_csv_dataset(100_000, 1_000, 0.6, 0.3, 0.05);
+
+    for (mode, sequential) in [("process", false), ("process_sequential", true)] {
+        let bench = |csv_data| async move {
+            let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+            let transaction_store = Arc::new(ConcurrentTransactionStore::<FixedPoint>::new());
+
+            let stream = CsvTransactionStream::<FixedPoint>::new(Cursor::new(csv_data));
+            let processor = StreamProcessor::new(account_manager, transaction_store, SkipErrors)
+                .add_stream(stream);
+
+            let results = match sequential {
+                true => processor.process_sequential().await,
+                false => processor.process().await,
+            };
+            black_box(results);
+        };
+
+        group.bench_function(mode, |b| {
+            b.to_async(&runtime)
+                .iter_batched(setup, bench, BatchSize::SmallInput);
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_csv_pipeline_dataset_sizes,
@@ -356,6 +390,7 @@ criterion_group!(
     bench_snapshot_generation,
     bench_error_handling_overhead,
     bench_parsing_vs_processing,
+    bench_sequential_fast_path,
 );
 
 criterion_main!(benches);
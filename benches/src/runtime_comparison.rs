@@ -30,14 +30,16 @@ fn bench_runtime_comparison(c: &mut Criterion) {
                         // Create streams with disjoint client IDs (low contention)
                         let streams: Vec<_> = (0..num_streams)
                             .map(|stream_id| {
-                                let client_id = stream_id as u16 + 1;
-                                let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                                let client_id = stream_id as ClientId + 1;
+                                let start_tx_id =
+                                    (stream_id * transactions_per_stream) as TransactionId;
 
                                 let transactions: Vec<_> = (0..transactions_per_stream)
                                     .map(|i| Transaction::Deposit {
                                         client_id,
-                                        tx_id: start_tx_id + i as u32,
+                                        tx_id: start_tx_id + i as TransactionId,
                                         amount: FixedPoint::from_raw(10_000),
+                                        currency: None,
                                     })
                                     .collect();
 
@@ -100,14 +102,15 @@ fn bench_single_vs_multi(c: &mut Criterion) {
 
                 let streams: Vec<_> = (0..num_streams)
                     .map(|stream_id| {
-                        let client_id = stream_id as u16 + 1;
-                        let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                        let client_id = stream_id as ClientId + 1;
+                        let start_tx_id = (stream_id * transactions_per_stream) as TransactionId;
 
                         let transactions: Vec<_> = (0..transactions_per_stream)
                             .map(|i| Transaction::Deposit {
                                 client_id,
-                                tx_id: start_tx_id + i as u32,
+                                tx_id: start_tx_id + i as TransactionId,
                                 amount: FixedPoint::from_raw(10_000),
+                                currency: None,
                             })
                             .collect();
 
@@ -158,14 +161,15 @@ fn bench_single_vs_multi(c: &mut Criterion) {
 
                 let streams: Vec<_> = (0..num_streams)
                     .map(|stream_id| {
-                        let client_id = stream_id as u16 + 1;
-                        let start_tx_id = (stream_id * transactions_per_stream) as u32;
+                        let client_id = stream_id as ClientId + 1;
+                        let start_tx_id = (stream_id * transactions_per_stream) as TransactionId;
 
                         let transactions: Vec<_> = (0..transactions_per_stream)
                             .map(|i| Transaction::Deposit {
                                 client_id,
-                                tx_id: start_tx_id + i as u32,
+                                tx_id: start_tx_id + i as TransactionId,
                                 amount: FixedPoint::from_raw(10_000),
+                                currency: None,
                             })
                             .collect();
 
@@ -18,7 +18,7 @@ fn bench_account_entry_cold(c: &mut Criterion) {
                     |manager| {
                         // First access to each account (cold cache)
                         for i in 0..num_accounts {
-                            black_box(manager.entry(i as u16).unwrap());
+                            black_box(manager.entry(i as ClientId).unwrap());
                         }
                     },
                     BatchSize::SmallInput,
@@ -44,7 +44,7 @@ fn bench_account_entry_hot(c: &mut Criterion) {
                         let manager = ConcurrentAccountManager::<FixedPoint>::new();
                         // Warm up the cache
                         for i in 0..num_accounts {
-                            let _ = manager.entry(i as u16);
+                            let _ = manager.entry(i as ClientId);
                         }
                         manager
                     },
@@ -52,7 +52,35 @@ fn bench_account_entry_hot(c: &mut Criterion) {
                         // Hot access - repeatedly access same accounts
                         for _ in 0..100 {
                             for i in 0..num_accounts {
-                                black_box(manager.entry(i as u16).unwrap());
+                                black_box(manager.entry(i as ClientId).unwrap());
+                            }
+                        }
+                    },
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Benchmark hot account access on the dense Vec-indexed backend
+fn bench_dense_account_entry_hot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dense_account_entry_hot");
+
+    for num_accounts in [100, 1_000, 10_000] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_accounts),
+            &num_accounts,
+            |b, &num_accounts| {
+                b.iter_batched(
+                    DenseAccountManager::<FixedPoint>::new,
+                    |manager| {
+                        // Hot access - repeatedly access same accounts
+                        for _ in 0..100 {
+                            for i in 0..num_accounts {
+                                black_box(manager.entry(i as ClientId).unwrap());
                             }
                         }
                     },
@@ -110,7 +138,7 @@ fn bench_account_read(c: &mut Criterion) {
                         let manager = ConcurrentAccountManager::<FixedPoint>::new();
                         // Populate accounts
                         for i in 0..num_accounts {
-                            let mut entry = manager.entry(i as u16).unwrap();
+                            let mut entry = manager.entry(i as ClientId).unwrap();
                             entry
                                 .try_update(|acc| {
                                     operations::apply_deposit(acc, FixedPoint::from_raw(10_000))
@@ -122,7 +150,7 @@ fn bench_account_read(c: &mut Criterion) {
                     |manager| {
                         // Read all accounts
                         for i in 0..num_accounts {
-                            let entry = manager.entry(i as u16).unwrap();
+                            let entry = manager.entry(i as ClientId).unwrap();
                             black_box(entry.read());
                         }
                     },
@@ -146,13 +174,43 @@ fn bench_transaction_store_insert(c: &mut Criterion) {
             |b, &num_transactions| {
                 b.iter_batched(
                     ConcurrentTransactionStore::<FixedPoint>::new,
-                    |mut store| {
+                    |store| {
+                        for i in 0..num_transactions {
+                            let record = TransactionRecord::new(
+                                (i % 1000) as ClientId,
+                                FixedPoint::from_raw(10_000),
+                            );
+                            store.insert(i as TransactionId, record);
+                            black_box(());
+                        }
+                    },
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Benchmark transaction store inserts into a store sized for the input up front
+fn bench_transaction_store_insert_presized(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transaction_store_insert_presized");
+
+    for num_transactions in [100, 1_000, 10_000, 100_000] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_transactions),
+            &num_transactions,
+            |b, &num_transactions| {
+                b.iter_batched(
+                    || ConcurrentTransactionStore::<FixedPoint>::with_capacity(num_transactions),
+                    |store| {
                         for i in 0..num_transactions {
-                            let record = TransactionRecord {
-                                client_id: (i % 1000) as u16,
-                                amount: FixedPoint::from_raw(10_000),
-                            };
-                            store.insert(i as u32, record);
+                            let record = TransactionRecord::new(
+                                (i % 1000) as ClientId,
+                                FixedPoint::from_raw(10_000),
+                            );
+                            store.insert(i as TransactionId, record);
                             black_box(());
                         }
                     },
@@ -176,21 +234,21 @@ fn bench_transaction_store_get(c: &mut Criterion) {
             |b, &num_transactions| {
                 b.iter_batched(
                     || {
-                        let mut store = ConcurrentTransactionStore::<FixedPoint>::new();
+                        let store = ConcurrentTransactionStore::<FixedPoint>::new();
                         // Populate store
                         for i in 0..num_transactions {
-                            let record = TransactionRecord {
-                                client_id: (i % 1000) as u16,
-                                amount: FixedPoint::from_raw(10_000),
-                            };
-                            store.insert(i as u32, record);
+                            let record = TransactionRecord::new(
+                                (i % 1000) as ClientId,
+                                FixedPoint::from_raw(10_000),
+                            );
+                            store.insert(i as TransactionId, record);
                         }
                         store
                     },
                     |store| {
                         // Lookup all transactions
                         for i in 0..num_transactions {
-                            black_box(store.get(i as u32));
+                            black_box(store.get(i as TransactionId));
                         }
                     },
                     BatchSize::SmallInput,
@@ -213,21 +271,21 @@ fn bench_transaction_store_contains(c: &mut Criterion) {
             |b, &num_transactions| {
                 b.iter_batched(
                     || {
-                        let mut store = ConcurrentTransactionStore::<FixedPoint>::new();
+                        let store = ConcurrentTransactionStore::<FixedPoint>::new();
                         // Populate store
                         for i in 0..num_transactions {
-                            let record = TransactionRecord {
-                                client_id: (i % 1000) as u16,
-                                amount: FixedPoint::from_raw(10_000),
-                            };
-                            store.insert(i as u32, record);
+                            let record = TransactionRecord::new(
+                                (i % 1000) as ClientId,
+                                FixedPoint::from_raw(10_000),
+                            );
+                            store.insert(i as TransactionId, record);
                         }
                         store
                     },
                     |store| {
                         // Check contains for all transactions
                         for i in 0..num_transactions {
-                            black_box(store.contains(i as u32));
+                            black_box(store.contains(i as TransactionId));
                         }
                     },
                     BatchSize::SmallInput,
@@ -247,7 +305,7 @@ fn bench_mixed_account_ops(c: &mut Criterion) {
                 let manager = ConcurrentAccountManager::<FixedPoint>::new();
                 // Populate with initial deposits
                 for i in 0..100 {
-                    let mut entry = manager.entry(i as u16).unwrap();
+                    let mut entry = manager.entry(i as ClientId).unwrap();
                     entry
                         .try_update(|acc| {
                             operations::apply_deposit(acc, FixedPoint::from_raw(100_000))
@@ -259,7 +317,7 @@ fn bench_mixed_account_ops(c: &mut Criterion) {
             |manager| {
                 // Mixed workload: 70% reads, 30% updates
                 for i in 0..1_000 {
-                    let client_id = (i % 100) as u16;
+                    let client_id = (i % 100) as ClientId;
                     let entry = manager.entry(client_id).unwrap();
 
                     if i % 10 < 7 {
@@ -287,9 +345,11 @@ criterion_group!(
     benches,
     bench_account_entry_cold,
     bench_account_entry_hot,
+    bench_dense_account_entry_hot,
     bench_account_update,
     bench_account_read,
     bench_transaction_store_insert,
+    bench_transaction_store_insert_presized,
     bench_transaction_store_get,
     bench_transaction_store_contains,
     bench_mixed_account_ops,
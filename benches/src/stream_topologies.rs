@@ -356,12 +356,55 @@ fn bench_best_vs_worst_topology(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark the parse/process pipeline with and without a buffer
+///
+/// Tests one large CSV stream with:
+/// - Interleaved parsing and processing (default)
+/// - A bounded buffer between a reader task and the shard (`with_buffer_size`)
+fn bench_buffered_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("topology_buffered_pipeline");
+    let runtime = Runtime::new().unwrap();
+
+    for (name, buffer_size) in [("interleaved", None), ("buffered_1024", Some(1024))] {
+        let setup = || {
+            let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+            let transaction_store = Arc::new(ConcurrentTransactionStore::<FixedPoint>::new());
+            let csv_data = generate_csv_dataset(10_000, 1_000, 0.6, 0.3, 0.05);
+            (account_manager, transaction_store, csv_data)
+        };
+
+        let bench = |(account_manager, transaction_store, csv_data): (
+            Arc<ConcurrentAccountManager<FixedPoint>>,
+            Arc<ConcurrentTransactionStore<FixedPoint>>,
+            String,
+        )| async move {
+            let stream = CsvTransactionStream::<FixedPoint>::new(Cursor::new(csv_data));
+            let mut processor =
+                StreamProcessor::new(account_manager, transaction_store, SilentSkip)
+                    .add_stream(stream);
+            if let Some(n) = buffer_size {
+                processor = processor.with_buffer_size(n);
+            }
+
+            black_box(processor.process().await);
+        };
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), &name, |b, _| {
+            b.to_async(&runtime)
+                .iter_batched(setup, bench, BatchSize::SmallInput);
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_chain_vs_merge,
     bench_shard_scaling,
     bench_shard_assignment_strategies,
     bench_best_vs_worst_topology,
+    bench_buffered_pipeline,
 );
 
 criterion_main!(benches);
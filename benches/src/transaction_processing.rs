@@ -38,8 +38,9 @@ fn bench_withdrawal_throughput(c: &mut Criterion) {
                     for i in 0..count {
                         processor.process_transaction(Transaction::Deposit {
                             client_id: 1,
-                            tx_id: i as u32,
+                            tx_id: i as TransactionId,
                             amount: FixedPoint::from_raw(100_000),
+                            currency: None,
                         }).unwrap();
                     }
 
@@ -47,8 +48,9 @@ fn bench_withdrawal_throughput(c: &mut Criterion) {
                     let withdrawals: Vec<_> = (count..count * 2)
                         .map(|i| Transaction::Withdrawal {
                             client_id: 1,
-                            tx_id: i as u32,
+                            tx_id: i as TransactionId,
                             amount: FixedPoint::from_raw(10_000),
+                            currency: None,
                         })
                         .collect();
 
@@ -80,8 +82,9 @@ fn bench_dispute_workflow(c: &mut Criterion) {
                     for i in 0..count {
                         processor.process_transaction(Transaction::Deposit {
                             client_id: 1,
-                            tx_id: i as u32,
+                            tx_id: i as TransactionId,
                             amount: FixedPoint::from_raw(10_000),
+                            currency: None,
                         }).unwrap();
                     }
 
@@ -90,11 +93,11 @@ fn bench_dispute_workflow(c: &mut Criterion) {
                     for i in 0..count {
                         workflow.push(Transaction::Dispute {
                             client_id: 1,
-                            tx_id: i as u32,
+                            tx_id: i as TransactionId,
                         });
                         workflow.push(Transaction::Resolve {
                             client_id: 1,
-                            tx_id: i as u32,
+                            tx_id: i as TransactionId,
                         });
                     }
 
@@ -125,9 +128,10 @@ fn bench_chargeback_workflow(c: &mut Criterion) {
                     // Create separate accounts for each chargeback to avoid locking
                     for i in 0..count {
                         processor.process_transaction(Transaction::Deposit {
-                            client_id: (i + 1) as u16,
-                            tx_id: i as u32,
+                            client_id: (i + 1) as ClientId,
+                            tx_id: i as TransactionId,
                             amount: FixedPoint::from_raw(10_000),
+                            currency: None,
                         }).unwrap();
                     }
 
@@ -135,12 +139,12 @@ fn bench_chargeback_workflow(c: &mut Criterion) {
                     let mut workflow = Vec::with_capacity(count * 2);
                     for i in 0..count {
                         workflow.push(Transaction::Dispute {
-                            client_id: (i + 1) as u16,
-                            tx_id: i as u32,
+                            client_id: (i + 1) as ClientId,
+                            tx_id: i as TransactionId,
                         });
                         workflow.push(Transaction::Chargeback {
-                            client_id: (i + 1) as u16,
-                            tx_id: i as u32,
+                            client_id: (i + 1) as ClientId,
+                            tx_id: i as TransactionId,
                         });
                     }
 
@@ -196,6 +200,7 @@ fn bench_locked_account_overhead(c: &mut Criterion) {
                     client_id: 1,
                     tx_id: 1,
                     amount: FixedPoint::from_raw(10_000),
+                    currency: None,
                 }).unwrap();
                 processor.process_transaction(Transaction::Dispute {
                     client_id: 1,
@@ -213,8 +218,9 @@ fn bench_locked_account_overhead(c: &mut Criterion) {
                 for i in 0..1_000 {
                     black_box(processor.process_transaction(Transaction::Deposit {
                         client_id: 1,
-                        tx_id: (i + 100) as u32,
+                        tx_id: (i + 100) as TransactionId,
                         amount: FixedPoint::from_raw(10_000),
+                        currency: None,
                     }).ok());
                 }
             },
@@ -0,0 +1,11 @@
+fn main() {
+    println!("cargo:rerun-if-changed=proto");
+
+    // Generated into OUT_DIR and included by `server::grpc`
+    #[cfg(feature = "grpc")]
+    {
+        let descriptors =
+            protox::compile(["pay.proto"], ["proto"]).expect("invalid proto/pay.proto");
+        tonic_prost_build::compile_fds(descriptors).expect("failed to generate gRPC code");
+    }
+}
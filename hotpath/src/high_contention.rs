@@ -39,8 +39,8 @@ async fn run_zipf_workload() {
     let transactions_per_stream = 10_000;
 
     // Zipf distribution: 20 hot clients, 100 total clients
-    let hot_clients = 20u16;
-    let total_clients = 100u16;
+    let hot_clients = 20;
+    let total_clients = 100;
 
     // Shared state
     let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
@@ -85,6 +85,10 @@ async fn run_zipf_workload() {
     }
 
     println!("All streams completed");
+
+    // Run with `--features profiling,diagnostics` to see which shards and clients are hot
+    #[cfg(feature = "diagnostics")]
+    println!("{}", account_manager.contention_report());
 }
 
 #[hotpath::measure]
@@ -93,15 +97,15 @@ async fn process_stream(
     num_transactions: usize,
     account_manager: Arc<ConcurrentAccountManager<FixedPoint>>,
     transaction_store: Arc<ConcurrentTransactionStore<FixedPoint>>,
-    hot_clients: u16,
-    total_clients: u16,
+    hot_clients: ClientId,
+    total_clients: ClientId,
 ) {
     let mut processor = TransactionProcessor::new(
         Arc::clone(&account_manager),
         Arc::clone(&transaction_store),
     );
 
-    let base_tx_id = (stream_id * num_transactions) as u32;
+    let base_tx_id = (stream_id * num_transactions) as TransactionId;
     let mut tx_id = base_tx_id;
     let mut deposited_txs = Vec::new();
 
@@ -109,10 +113,10 @@ async fn process_stream(
         // Zipf distribution: 80% chance to hit hot clients (20% of total)
         let client_id = if i % 5 < 4 {
             // Hot clients (80% of traffic)
-            (i % hot_clients as usize) as u16 + 1
+            (i % hot_clients as usize) as ClientId + 1
         } else {
             // Cold clients (20% of traffic)
-            hot_clients + ((i % (total_clients - hot_clients) as usize) as u16) + 1
+            hot_clients + ((i % (total_clients - hot_clients) as usize) as ClientId) + 1
         };
 
         let tx_type = i % 10;
@@ -150,16 +154,17 @@ fn process_deposit(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
-    deposited_txs: &mut Vec<(u16, u32)>,
+    deposited_txs: &mut Vec<(ClientId, TransactionId)>,
 ) {
     let amount = FixedPoint::from_raw(((i % 1000) + 1) as i64 * 10_000);
     let _ = processor.process_transaction(Transaction::Deposit {
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
     deposited_txs.push((client_id, tx_id));
 }
@@ -171,8 +176,8 @@ fn process_withdrawal(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
 ) {
     let amount = FixedPoint::from_raw(((i % 100) + 1) as i64 * 10_000);
@@ -180,6 +185,7 @@ fn process_withdrawal(
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
 }
 
@@ -190,8 +196,8 @@ fn process_dispute(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
 ) {
     let _ = processor.process_transaction(Transaction::Dispute { client_id, tx_id });
 }
@@ -84,14 +84,14 @@ async fn process_stream(
         Arc::clone(&transaction_store),
     );
 
-    let base_client_id = (stream_id * 100) as u16;
-    let base_tx_id = (stream_id * num_transactions) as u32;
+    let base_client_id = (stream_id * 100) as ClientId;
+    let base_tx_id = (stream_id * num_transactions) as TransactionId;
 
     let mut tx_id = base_tx_id;
     let mut deposited_txs = Vec::new();
 
     for i in 0..num_transactions {
-        let client_id = base_client_id + (i % 100) as u16;
+        let client_id = base_client_id + (i % 100) as ClientId;
         let tx_type = i % 10;
 
         match tx_type {
@@ -127,16 +127,17 @@ fn process_deposit(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
-    deposited_txs: &mut Vec<(u16, u32)>,
+    deposited_txs: &mut Vec<(ClientId, TransactionId)>,
 ) {
     let amount = FixedPoint::from_raw(((i % 1000) + 1) as i64 * 10_000);
     let _ = processor.process_transaction(Transaction::Deposit {
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
     deposited_txs.push((client_id, tx_id));
 }
@@ -148,8 +149,8 @@ fn process_withdrawal(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
 ) {
     let amount = FixedPoint::from_raw(((i % 100) + 1) as i64 * 10_000);
@@ -157,6 +158,7 @@ fn process_withdrawal(
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
 }
 
@@ -167,8 +169,8 @@ fn process_dispute(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
 ) {
     let _ = processor.process_transaction(Transaction::Dispute { client_id, tx_id });
 }
@@ -40,13 +40,13 @@ fn run_workload(
         ConcurrentTransactionStore<FixedPoint>,
     >,
     num_transactions: usize,
-    num_clients: u16,
+    num_clients: ClientId,
 ) {
-    let mut tx_id = 0u32;
+    let mut tx_id = 0;
     let mut deposited_txs = Vec::new();
 
     for i in 0..num_transactions {
-        let client_id = (i % num_clients as usize) as u16 + 1;
+        let client_id = (i % num_clients as usize) as ClientId + 1;
         let tx_type = i % 10;
 
         match tx_type {
@@ -87,16 +87,17 @@ fn process_deposit(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
-    deposited_txs: &mut Vec<(u16, u32)>,
+    deposited_txs: &mut Vec<(ClientId, TransactionId)>,
 ) {
     let amount = FixedPoint::from_raw(((i % 1000) + 1) as i64 * 10_000);
     let _ = processor.process_transaction(Transaction::Deposit {
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
     deposited_txs.push((client_id, tx_id));
 }
@@ -108,8 +109,8 @@ fn process_withdrawal(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
 ) {
     let amount = FixedPoint::from_raw(((i % 100) + 1) as i64 * 10_000);
@@ -117,6 +118,7 @@ fn process_withdrawal(
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
 }
 
@@ -127,8 +129,8 @@ fn process_dispute(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
 ) {
     let _ = processor.process_transaction(Transaction::Dispute { client_id, tx_id });
 }
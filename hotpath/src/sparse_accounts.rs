@@ -92,9 +92,9 @@ async fn process_stream(
     // Simulates realistic production IDs: large, non-sequential, with gaps
     // Base offset: stream_id * 1,000,000 to ensure uniqueness across streams
     // Within stream: use prime number stepping to create sparse distribution
-    let base_offset = (stream_id * 1_000_000) as u16;
+    let base_offset = (stream_id * 1_000_000) as ClientId;
 
-    let base_tx_id = (stream_id * num_transactions) as u32;
+    let base_tx_id = (stream_id * num_transactions) as TransactionId;
     let mut tx_id = base_tx_id;
     let mut deposited_txs = Vec::new();
 
@@ -103,7 +103,7 @@ async fn process_stream(
         // - Not sequential (use prime 251 for stepping)
         // - Large gaps between IDs
         // - Realistic distribution seen in production systems
-        let sparse_offset = ((i * 251) % 10000) as u16;
+        let sparse_offset = ((i * 251) % 10000) as ClientId;
         let client_id = base_offset.wrapping_add(sparse_offset);
 
         // Ensure client_id is non-zero (account IDs start at 1)
@@ -144,16 +144,17 @@ fn process_deposit(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
-    deposited_txs: &mut Vec<(u16, u32)>,
+    deposited_txs: &mut Vec<(ClientId, TransactionId)>,
 ) {
     let amount = FixedPoint::from_raw(((i % 1000) + 1) as i64 * 10_000);
     let _ = processor.process_transaction(Transaction::Deposit {
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
     deposited_txs.push((client_id, tx_id));
 }
@@ -165,8 +166,8 @@ fn process_withdrawal(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
 ) {
     let amount = FixedPoint::from_raw(((i % 100) + 1) as i64 * 10_000);
@@ -174,6 +175,7 @@ fn process_withdrawal(
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
 }
 
@@ -184,8 +186,8 @@ fn process_dispute(
         Arc<ConcurrentAccountManager<FixedPoint>>,
         Arc<ConcurrentTransactionStore<FixedPoint>>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
 ) {
     let _ = processor.process_transaction(Transaction::Dispute { client_id, tx_id });
 }
@@ -40,14 +40,14 @@ fn run_workload(
         ConcurrentTransactionStore<FixedPoint>,
     >,
     num_transactions: usize,
-    num_clients: u16,
+    num_clients: ClientId,
 ) {
-    let mut tx_id = 0u32;
+    let mut tx_id = 0;
     let mut deposited_txs = Vec::new();
     let mut disputed_txs = Vec::new();
 
     for i in 0..num_transactions {
-        let client_id = (i % num_clients as usize) as u16 + 1;
+        let client_id = (i % num_clients as usize) as ClientId + 1;
         let tx_type = i % 10;
 
         match tx_type {
@@ -105,16 +105,17 @@ fn process_deposit(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
-    deposited_txs: &mut Vec<(u16, u32)>,
+    deposited_txs: &mut Vec<(ClientId, TransactionId)>,
 ) {
     let amount = FixedPoint::from_raw(((i % 1000) + 1) as i64 * 10_000);
     let _ = processor.process_transaction(Transaction::Deposit {
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
     deposited_txs.push((client_id, tx_id));
 }
@@ -126,8 +127,8 @@ fn process_withdrawal(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
     i: usize,
 ) {
     let amount = FixedPoint::from_raw(((i % 100) + 1) as i64 * 10_000);
@@ -135,6 +136,7 @@ fn process_withdrawal(
         client_id,
         tx_id,
         amount,
+        currency: None,
     });
 }
 
@@ -145,8 +147,8 @@ fn process_dispute(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
 ) {
     let _ = processor.process_transaction(Transaction::Dispute { client_id, tx_id });
 }
@@ -158,8 +160,8 @@ fn process_resolve(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
 ) {
     let _ = processor.process_transaction(Transaction::Resolve { client_id, tx_id });
 }
@@ -171,8 +173,8 @@ fn process_chargeback(
         ConcurrentAccountManager<FixedPoint>,
         ConcurrentTransactionStore<FixedPoint>,
     >,
-    client_id: u16,
-    tx_id: u32,
+    client_id: ClientId,
+    tx_id: TransactionId,
 ) {
     let _ = processor.process_transaction(Transaction::Chargeback { client_id, tx_id });
 }
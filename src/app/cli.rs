@@ -1,7 +1,56 @@
 use std::future::Future;
+use std::pin::Pin;
+
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::LevelFilter;
 
 use super::error::AppError;
 
+/// Boxed hook run on SIGINT/SIGTERM to write partial output
+type SignalSnapshotHook =
+    Box<dyn FnOnce(Writers) -> Pin<Box<dyn Future<Output = Result<(), AppError>> + Send>> + Send>;
+
+/// Boxed hook choosing the signal snapshot hook from the parsed configuration
+type SignalSnapshotSelector<Config> = Box<dyn FnOnce(&Config) -> Option<SignalSnapshotHook> + Send>;
+
+/// Boxed hook choosing a log level from the parsed configuration
+type LogLevelHook<Config> = Box<dyn FnOnce(&Config) -> Option<LevelFilter> + Send>;
+
+/// Layout of log lines written by `CliApp::with_tracing`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Multi-line, human-readable events
+    #[default]
+    Pretty,
+    /// One JSON object per event, for log collectors
+    Json,
+}
+
+/// How `CliApp` reports an error on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// `Error: <message>`
+    #[default]
+    Text,
+    /// One JSON object (see `AppError::to_json`)
+    Json,
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = String;
+
+    /// Parse `text` or `json`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "unknown error format '{name}' (expected text or json)"
+            )),
+        }
+    }
+}
+
 /// Buffered writers for stdout and stderr
 pub struct Writers {
     pub stdout: tokio::io::BufWriter<tokio::io::Stdout>,
@@ -11,13 +60,20 @@ pub struct Writers {
 /// Reusable CLI application runner that handles:
 /// - Tokio runtime creation and configuration
 /// - Argument parsing and validation
-/// - Signal handling (SIGINT, SIGTERM, SIGHUP)
+/// - Signal handling (SIGINT, SIGTERM, SIGHUP), with an optional snapshot hook
+/// - An optional tracing subscriber logging to stderr
 /// - Stdout/stderr buffering and flushing
-/// - Exit codes (0 = success, 1 = error, 130 = SIGINT, 143 = SIGTERM)
+/// - Errors on stderr as text or JSON (`--error-format text|json`)
+/// - Exit codes by `ErrorCategory` (0 = success, 2 = usage, 3 = I/O, 4 = parse,
+///   5 = processing, 128 + signal when interrupted: 130 = SIGINT, 143 = SIGTERM)
 pub struct CliApp<Config> {
     name: String,
     flush_on_signal: bool,
+    error_format: ErrorFormat,
+    signal_snapshot: Option<SignalSnapshotSelector<Config>>,
     worker_threads: Option<usize>,
+    tracing: Option<(LevelFilter, LogFormat)>,
+    log_level: Option<LogLevelHook<Config>>,
     args_parser: Box<dyn FnOnce(Vec<String>) -> Result<Config, AppError> + Send>,
 }
 
@@ -32,8 +88,12 @@ impl CliApp<Vec<String>> {
         Self {
             name: name.to_string(),
             flush_on_signal: false,
+            error_format: ErrorFormat::default(),
+            signal_snapshot: None,
             worker_threads: None,
-            args_parser: Box::new(|args| Ok(args)),
+            tracing: None,
+            log_level: None,
+            args_parser: Box::new(Ok),
         }
     }
 
@@ -64,7 +124,11 @@ impl CliApp<Vec<String>> {
         CliApp {
             name: self.name,
             flush_on_signal: self.flush_on_signal,
+            error_format: self.error_format,
+            signal_snapshot: None,
             worker_threads: self.worker_threads,
+            tracing: self.tracing,
+            log_level: None,
             args_parser: Box::new(parser),
         }
     }
@@ -80,6 +144,64 @@ impl<Config: Send + 'static> CliApp<Config> {
         self
     }
 
+    /// Report errors on stderr in `format` (defaults to `ErrorFormat::Text`)
+    ///
+    /// An `--error-format text|json` pair anywhere on the command line
+    /// overrides it; the pair is removed before the argument parser runs, so
+    /// every command accepts it. The exit code is `AppError::exit_code` in
+    /// either format.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // stderr: {"error":"io","message":"File not found: in.csv","exit_code":3}
+    /// CliApp::new("myapp")
+    ///     .with_error_format(ErrorFormat::Json)
+    ///     .run(main_fn);
+    /// ```
+    pub fn with_error_format(mut self, format: ErrorFormat) -> Self {
+        self.error_format = format;
+        self
+    }
+
+    /// Write a partial snapshot when interrupted by SIGINT or SIGTERM
+    ///
+    /// `select` sees the parsed configuration and returns the hook to run, or
+    /// `None` for commands with nothing to snapshot. The hook runs after the
+    /// main function is cancelled, with fresh writers, so an interrupted run
+    /// still produces usable output. Capture whatever state it needs (e.g. an
+    /// `Arc` of the account manager); stdout and stderr are flushed once it
+    /// returns and the process still exits with the signal's exit code. SIGHUP
+    /// exits without running the hook. Register it after `with_args`, which
+    /// changes the configuration type.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+    /// let snapshot_manager = manager.clone();
+    /// CliApp::new("myapp")
+    ///     .with_args(parse_args)
+    ///     .with_signal_snapshot(move |command: &Command| {
+    ///         command.processes().then_some(move |mut writers: Writers| async move {
+    ///             snapshot_manager.snapshot(&mut writers.stdout).await?;
+    ///             Ok(())
+    ///         })
+    ///     })
+    ///     .run(move |writers, command| run(writers, command, manager));
+    /// ```
+    pub fn with_signal_snapshot<F, H, Fut>(mut self, select: F) -> Self
+    where
+        F: FnOnce(&Config) -> Option<H> + Send + 'static,
+        H: FnOnce(Writers) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+    {
+        self.signal_snapshot = Some(Box::new(move |config| {
+            select(config).map(|hook| -> SignalSnapshotHook {
+                Box::new(move |writers| Box::pin(hook(writers)))
+            })
+        }));
+        self
+    }
+
     /// Set number of tokio worker threads
     ///
     /// Default: Number of CPU cores (from `std::thread::available_parallelism()`)
@@ -95,6 +217,46 @@ impl<Config: Send + 'static> CliApp<Config> {
         self
     }
 
+    /// Install a tracing subscriber logging to stderr
+    ///
+    /// Events at `level` and above are written in `format`; a `RUST_LOG`
+    /// environment variable overrides the level (e.g. `RUST_LOG=pay=debug`).
+    /// Logs never go to stdout, so they cannot corrupt a snapshot written
+    /// there. The subscriber is installed after argument parsing; nothing is
+    /// installed if the process already has a global subscriber.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// CliApp::new("myapp")
+    ///     .with_tracing(LevelFilter::INFO, LogFormat::Json)
+    ///     .run(main_fn);
+    /// ```
+    pub fn with_tracing(mut self, level: LevelFilter, format: LogFormat) -> Self {
+        self.tracing = Some((level, format));
+        self
+    }
+
+    /// Let the parsed configuration override the `with_tracing` level
+    ///
+    /// The hook returns `None` to keep the level given to `with_tracing`.
+    /// `RUST_LOG` still takes precedence over either.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// CliApp::new("myapp")
+    ///     .with_args(parse_args)
+    ///     .with_tracing(LevelFilter::OFF, LogFormat::Pretty)
+    ///     .with_log_level(|config: &MyConfig| config.verbose.then_some(LevelFilter::DEBUG))
+    ///     .run(main_fn);
+    /// ```
+    pub fn with_log_level<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&Config) -> Option<LevelFilter> + Send + 'static,
+    {
+        self.log_level = Some(Box::new(hook));
+        self
+    }
+
     /// Run the application (never returns)
     ///
     /// Creates a tokio runtime, parses arguments, sets up signal handling,
@@ -134,23 +296,32 @@ impl<Config: Send + 'static> CliApp<Config> {
             .expect("Failed to create tokio runtime");
 
         runtime.block_on(async move {
-            // Extract flush_on_signal before moving self
+            // Extract signal settings before moving self
             let flush_on_signal = self.flush_on_signal;
+            let signal_snapshot = self.signal_snapshot;
 
             // Parse arguments first (before entering tokio::select)
-            let args = std::env::args().collect();
+            let mut args = std::env::args().collect();
+            let error_format = match take_error_format(&mut args) {
+                Ok(format) => format.unwrap_or(self.error_format),
+                Err(e) => exit_with(&e, self.error_format),
+            };
             let config = match (self.args_parser)(args) {
                 Ok(cfg) => cfg,
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    std::process::exit(1);
-                }
+                Err(e) => exit_with(&e, error_format),
             };
 
-            let writers = Writers {
-                stdout: tokio::io::BufWriter::new(tokio::io::stdout()),
-                stderr: tokio::io::BufWriter::new(tokio::io::stderr()),
-            };
+            let signal_snapshot = signal_snapshot.and_then(|select| select(&config));
+
+            if let Some((level, format)) = self.tracing {
+                let level = self
+                    .log_level
+                    .and_then(|hook| hook(&config))
+                    .unwrap_or(level);
+                init_tracing(level, format);
+            }
+
+            let writers = Writers::new();
 
             let signal_fut = wait_for_signal();
 
@@ -161,17 +332,19 @@ impl<Config: Send + 'static> CliApp<Config> {
                         Ok(()) => {
                             std::process::exit(0);
                         }
-                        Err(e) => {
-                            eprintln!("Error: {}", e);
-                            std::process::exit(1);
-                        }
+                        Err(e) => exit_with(&e, error_format),
                     }
                 }
-                signal_code = signal_fut => {
-                    if flush_on_signal {
+                signal = signal_fut => {
+                    if flush_on_signal && error_format == ErrorFormat::Text {
                         eprintln!("Interrupted, attempting to flush partial results");
                     }
-                    std::process::exit(signal_code);
+                    if let Some(hook) = signal_snapshot
+                        && signal != SIGHUP
+                    {
+                        write_signal_snapshot(hook).await;
+                    }
+                    exit_with(&AppError::Interrupted(signal), error_format);
                 }
             }
         });
@@ -181,11 +354,75 @@ impl<Config: Send + 'static> CliApp<Config> {
             unreachable!()
         }
     }
+}
+
+/// Install the global subscriber, writing to stderr and honoring `RUST_LOG`
+fn init_tracing(level: LevelFilter, format: LogFormat) {
+    let filter = EnvFilter::builder()
+        .with_default_directive(level.into())
+        .from_env_lossy();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    // Ignore the error if a subscriber is already installed
+    let _ = match format {
+        LogFormat::Pretty => builder.pretty().try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
+}
+
+impl Writers {
+    fn new() -> Self {
+        Self {
+            stdout: tokio::io::BufWriter::new(tokio::io::stdout()),
+            stderr: tokio::io::BufWriter::new(tokio::io::stderr()),
+        }
+    }
+}
+
+/// Signal number of SIGHUP, which exits without the snapshot hook
+const SIGHUP: i32 = 1;
+
+/// Remove an `--error-format <format>` pair from `args`, returning the format
+fn take_error_format(args: &mut Vec<String>) -> Result<Option<ErrorFormat>, AppError> {
+    let Some(index) = args.iter().position(|arg| arg == "--error-format") else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(AppError::InvalidArguments(
+            "--error-format needs a value (text or json)".to_string(),
+        ));
+    }
+    let format = args[index + 1]
+        .parse()
+        .map_err(AppError::InvalidArguments)?;
+    args.drain(index..=index + 1);
+    Ok(Some(format))
+}
+
+/// Report `error` on stderr in `format` and exit with its exit code
+fn exit_with(error: &AppError, format: ErrorFormat) -> ! {
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {}", error),
+        ErrorFormat::Json => eprintln!("{}", error.to_json()),
+    }
+    std::process::exit(error.exit_code())
+}
+
+/// Run the signal snapshot hook and flush the standard streams
+async fn write_signal_snapshot(hook: SignalSnapshotHook) {
+    use tokio::io::AsyncWriteExt;
 
+    if let Err(e) = hook(Writers::new()).await {
+        eprintln!("Error writing snapshot on signal: {}", e);
+    }
+    let _ = tokio::io::stdout().flush().await;
+    let _ = tokio::io::stderr().flush().await;
 }
 
 /// Wait for any Unix signal (SIGINT, SIGTERM, SIGHUP) or Ctrl+C
-/// Returns the exit code to use (130 for SIGINT, 143 for SIGTERM, etc.)
+/// Returns the signal number (2 for SIGINT, 15 for SIGTERM, etc.)
 async fn wait_for_signal() -> i32 {
     #[cfg(unix)]
     {
@@ -198,18 +435,9 @@ async fn wait_for_signal() -> i32 {
         let mut sighup = signal(SignalKind::hangup()).expect("Failed to setup SIGHUP handler");
 
         tokio::select! {
-            _ = sigterm.recv() => {
-                eprintln!("Received SIGTERM");
-                143 // 128 + 15
-            }
-            _ = sigint.recv() => {
-                eprintln!("Received SIGINT");
-                130 // 128 + 2
-            }
-            _ = sighup.recv() => {
-                eprintln!("Received SIGHUP");
-                129 // 128 + 1
-            }
+            _ = sigterm.recv() => 15,
+            _ = sigint.recv() => 2,
+            _ = sighup.recv() => SIGHUP,
         }
     }
 
@@ -218,8 +446,7 @@ async fn wait_for_signal() -> i32 {
         tokio::signal::ctrl_c()
             .await
             .expect("Failed to setup Ctrl+C handler");
-        eprintln!("Received Ctrl+C");
-        130
+        2
     }
 }
 
@@ -241,12 +468,76 @@ mod tests {
         assert!(app.flush_on_signal);
     }
 
+    #[test]
+    fn cli_app_with_signal_snapshot() {
+        let app = || {
+            CliApp::new("test-app")
+                .with_args(|args| Ok(args.len()))
+                .with_signal_snapshot(|&args: &usize| {
+                    (args > 1).then_some(|_writers| async { Ok(()) })
+                })
+        };
+
+        // Only configurations the selector accepts get a hook
+        assert!((app().signal_snapshot.unwrap())(&2).is_some());
+        assert!((app().signal_snapshot.unwrap())(&1).is_none());
+    }
+
+    #[test]
+    fn signal_snapshot_is_dropped_by_the_argument_parser() {
+        let app = CliApp::new("test-app")
+            .with_signal_snapshot(|_: &Vec<String>| Some(|_writers| async { Ok(()) }))
+            .with_args(|args| Ok(args.len()));
+
+        // The selector took the raw arguments, so it cannot see the parsed config
+        assert!(app.signal_snapshot.is_none());
+    }
+
+    #[test]
+    fn error_format_flag_is_taken_from_args() {
+        let mut args: Vec<String> = ["pay", "--error-format", "json", "input.csv"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            take_error_format(&mut args).unwrap(),
+            Some(ErrorFormat::Json)
+        );
+        assert_eq!(args, vec!["pay", "input.csv"]);
+        assert_eq!(take_error_format(&mut args).unwrap(), None);
+
+        let mut args: Vec<String> = ["pay", "--error-format", "xml"].map(String::from).to_vec();
+        let error = take_error_format(&mut args).unwrap_err();
+        assert_eq!(error.exit_code(), 2);
+
+        let app = CliApp::new("test-app").with_error_format(ErrorFormat::Json);
+        assert_eq!(
+            app.with_args(|args| Ok(args.len())).error_format,
+            ErrorFormat::Json
+        );
+    }
+
     #[test]
     fn cli_app_with_worker_threads() {
         let app = CliApp::new("test-app").with_worker_threads(8);
         assert_eq!(app.worker_threads, Some(8));
     }
 
+    #[test]
+    fn cli_app_with_tracing() {
+        let app = CliApp::new("test-app").with_tracing(LevelFilter::INFO, LogFormat::Json);
+        assert_eq!(app.tracing, Some((LevelFilter::INFO, LogFormat::Json)));
+
+        // The settings survive the argument parser type transformation
+        let app = app
+            .with_args(|args| Ok(args.len()))
+            .with_log_level(|&count| (count > 1).then_some(LevelFilter::DEBUG));
+        assert_eq!(app.tracing, Some((LevelFilter::INFO, LogFormat::Json)));
+        assert_eq!(
+            app.log_level.map(|hook| hook(&2)),
+            Some(Some(LevelFilter::DEBUG))
+        );
+    }
+
     #[test]
     fn cli_app_builder_chain() {
         let app = CliApp::new("test-app")
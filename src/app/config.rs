@@ -0,0 +1,530 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use super::error::AppError;
+use crate::io::ColumnMapping;
+use crate::storage::SnapshotFormat;
+use crate::streaming::StreamCombinator;
+
+/// Which built-in `ErrorPolicy` a configured run uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicyKind {
+    /// `SilentSkip` (default)
+    #[default]
+    Silent,
+    /// `SkipErrors`
+    Skip,
+    /// `AbortOnError`
+    Abort,
+}
+
+impl FromStr for ErrorPolicyKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "silent" => Ok(Self::Silent),
+            "skip" => Ok(Self::Skip),
+            "abort" => Ok(Self::Abort),
+            _ => Err(format!(
+                "unknown error policy '{name}' (expected silent, skip or abort)"
+            )),
+        }
+    }
+}
+
+/// Settings for a batch run, usually loaded from a TOML file
+///
+/// Only the keys below are recognised; anything else is rejected so typos
+/// do not silently fall back to defaults.
+///
+/// ```toml
+/// inputs = ["partner-a.csv", "partner-b.csv.gz"]
+///
+/// [processing]
+/// shards = 4
+/// combinator = "merge"      # merge | chain | timestamp (alias by-timestamp)
+/// error_policy = "skip"     # silent | skip | abort
+/// verify_invariants = true  # fail if any account is corrupt
+///
+/// [columns]                 # input headers from legacy exports
+/// client = "customer_id"    # canonical column = "header in the file"
+///
+/// [output]
+/// path = "accounts.csv"     # stdout when omitted
+/// decimals = 4              # 0-4, or "input" to match the inputs
+/// trim_trailing_zeros = false
+/// delimiter = ","
+///
+/// [logging]
+/// level = "info"            # error | warn | info | debug | trace
+/// ```
+///
+/// The parser covers the subset of TOML needed here: tables, strings,
+/// integers, booleans and arrays (which may span lines).
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    pub inputs: Vec<String>,
+    pub shards: usize,
+    pub combinator: StreamCombinator,
+    pub error_policy: ErrorPolicyKind,
+    /// Check every account's invariants before writing the snapshot
+    pub verify_invariants: bool,
+    /// Input header columns to read under their canonical names
+    pub columns: ColumnMapping,
+    /// Snapshot destination; stdout when `None`
+    pub output: Option<String>,
+    pub format: SnapshotFormat,
+    /// Write amounts with as many decimals as the inputs had (`decimals = "input"`),
+    /// overriding the format's decimals
+    pub input_precision: bool,
+    /// Log to stderr at this level; no logging when `None`
+    pub log_level: Option<tracing::Level>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            shards: 1,
+            combinator: StreamCombinator::Merge,
+            error_policy: ErrorPolicyKind::default(),
+            verify_invariants: false,
+            columns: ColumnMapping::default(),
+            output: None,
+            format: SnapshotFormat::default(),
+            input_precision: false,
+            log_level: None,
+        }
+    }
+}
+
+impl RunConfig {
+    /// Read and parse a TOML config file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|_| AppError::FileNotFound(path.display().to_string()))?;
+        Self::from_toml(&text)
+    }
+
+    /// Parse a TOML document; missing keys keep their defaults
+    pub fn from_toml(text: &str) -> Result<Self, AppError> {
+        let mut config = Self::default();
+        for (line, table, key, value) in parse_toml(text)? {
+            config
+                .set(&table, &key, value)
+                .map_err(|e| AppError::Config(format!("line {line}: {e}")))?;
+        }
+        Ok(config)
+    }
+
+    /// Check the config describes a runnable job
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.inputs.is_empty() {
+            return Err(AppError::Config("no input files given".to_string()));
+        }
+        if self.shards == 0 {
+            return Err(AppError::Config("shards must be at least 1".to_string()));
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, table: &str, key: &str, value: Value) -> Result<(), String> {
+        match (table, key) {
+            ("", "inputs") => self.inputs = value.into_strings()?,
+            ("processing", "shards") => self.shards = value.into_usize()?,
+            ("processing", "combinator") => self.combinator = value.into_string()?.parse()?,
+            ("processing", "error_policy") => self.error_policy = value.into_string()?.parse()?,
+            ("processing", "verify_invariants") => self.verify_invariants = value.into_bool()?,
+            ("columns", canonical) => {
+                let columns = std::mem::take(&mut self.columns);
+                self.columns = columns.with_column(value.into_string()?, canonical);
+                self.columns.validate().map_err(|e| e.to_string())?;
+            }
+            ("output", "path") => self.output = Some(value.into_string()?),
+            ("output", "decimals") => match value {
+                Value::String(s) if s == "input" => self.input_precision = true,
+                value => {
+                    self.input_precision = false;
+                    self.format = self.format.clone().with_decimals(value.into_usize()?)
+                }
+            },
+            ("output", "trim_trailing_zeros") => {
+                self.format = self
+                    .format
+                    .clone()
+                    .with_trailing_zeros_trimmed(value.into_bool()?)
+            }
+            ("output", "delimiter") => {
+                let delimiter = value.into_string()?;
+                let mut chars = delimiter.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => self.format = self.format.clone().with_delimiter(c),
+                    _ => {
+                        return Err(format!(
+                            "delimiter must be one character, got '{delimiter}'"
+                        ));
+                    }
+                }
+            }
+            ("logging", "level") => {
+                let level = value.into_string()?;
+                self.log_level = Some(
+                    level
+                        .parse()
+                        .map_err(|_| format!("unknown log level '{level}'"))?,
+                )
+            }
+            ("", key) => return Err(format!("unknown key '{key}'")),
+            (table, key) => return Err(format!("unknown key '{table}.{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// A parsed TOML value
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn into_string(self) -> Result<String, String> {
+        match self {
+            Value::String(s) => Ok(s),
+            other => Err(format!("expected a string, got {other:?}")),
+        }
+    }
+
+    fn into_bool(self) -> Result<bool, String> {
+        match self {
+            Value::Bool(b) => Ok(b),
+            other => Err(format!("expected a boolean, got {other:?}")),
+        }
+    }
+
+    fn into_usize(self) -> Result<usize, String> {
+        match self {
+            Value::Integer(n) => usize::try_from(n).map_err(|_| format!("{n} is out of range")),
+            other => Err(format!("expected an integer, got {other:?}")),
+        }
+    }
+
+    fn into_strings(self) -> Result<Vec<String>, String> {
+        match self {
+            Value::Array(values) => values.into_iter().map(Value::into_string).collect(),
+            other => Err(format!("expected an array of strings, got {other:?}")),
+        }
+    }
+}
+
+/// Every `key = value` in `text` as (line, table, key, value)
+fn parse_toml(text: &str) -> Result<Vec<(usize, String, String, Value)>, AppError> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    let mut lines = text.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let number = index + 1;
+        let error = |e: String| AppError::Config(format!("line {number}: {e}"));
+        let line = without_comment(line);
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            table = header
+                .strip_suffix(']')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| error(format!("malformed table header '{line}'")))?;
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error(format!("expected 'key = value', got '{line}'")))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(error("missing key".to_string()));
+        }
+
+        // Arrays may continue over the following lines until they close
+        let mut value = value.trim().to_string();
+        while value.starts_with('[') && !array_closed(&value) {
+            let Some((_, next)) = lines.next() else {
+                return Err(error("unterminated array".to_string()));
+            };
+            value.push(' ');
+            value.push_str(without_comment(next));
+        }
+
+        let mut parser = ValueParser {
+            rest: value.as_str(),
+        };
+        let parsed = parser.value().map_err(error)?;
+        if !parser.rest.trim().is_empty() {
+            return Err(error(format!(
+                "unexpected '{}' after value",
+                parser.rest.trim()
+            )));
+        }
+        entries.push((number, table.clone(), key.to_string(), parsed));
+    }
+    Ok(entries)
+}
+
+/// `line` without its `# comment` (outside strings) and surrounding whitespace
+fn without_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return line[..i].trim(),
+            None => {}
+        }
+    }
+    line.trim()
+}
+
+/// Whether the brackets in a comment-free `text` balance, ignoring strings
+fn array_closed(text: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => {}
+            },
+        }
+    }
+    depth <= 0
+}
+
+struct ValueParser<'a> {
+    rest: &'a str,
+}
+
+impl ValueParser<'_> {
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        let mut chars = self.rest.chars();
+        match chars.next() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array().map(Value::Array),
+            Some(_) => self.bare(),
+            None => Err("missing value".to_string()),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        let mut out = String::new();
+        let mut chars = self.rest[1..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 2..];
+                    return Ok(out);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    other => {
+                        return Err(format!("unsupported escape '\\{}'", other.unwrap_or(' ')));
+                    }
+                },
+                c => out.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        let end = self.rest[1..]
+            .find('\'')
+            .ok_or_else(|| "unterminated string".to_string())?;
+        let out = self.rest[1..end + 1].to_string();
+        self.rest = &self.rest[end + 2..];
+        Ok(out)
+    }
+
+    fn array(&mut self) -> Result<Vec<Value>, String> {
+        self.rest = &self.rest[1..];
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if let Some(rest) = self.rest.strip_prefix(']') {
+                self.rest = rest;
+                return Ok(values);
+            }
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.rest.chars().next() {
+                Some(',') => self.rest = &self.rest[1..],
+                Some(']') => {}
+                _ => return Err("expected ',' or ']' in array".to_string()),
+            }
+        }
+    }
+
+    fn bare(&mut self) -> Result<Value, String> {
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || c == ',' || c == ']')
+            .unwrap_or(self.rest.len());
+        let (word, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        match word {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => word
+                .replace('_', "")
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("unsupported value '{word}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_section() {
+        let config = RunConfig::from_toml(
+            r#"
+            # Nightly batch
+            inputs = [
+                "a.csv",
+                'b.csv.gz', # archived
+            ]
+
+            [processing]
+            shards = 4
+            combinator = "chain"
+            error_policy = "abort"  # fail fast
+            verify_invariants = true
+
+            [columns]
+            type = "txn_type"
+            client = "customer_id"
+
+            [output]
+            path = "out.csv"
+            decimals = 2
+            delimiter = ";"
+
+            [logging]
+            level = "debug"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.inputs, vec!["a.csv", "b.csv.gz"]);
+        assert_eq!(config.shards, 4);
+        assert!(matches!(config.combinator, StreamCombinator::Chain));
+        assert_eq!(config.error_policy, ErrorPolicyKind::Abort);
+        assert!(config.verify_invariants);
+        assert_eq!(
+            config.columns,
+            ColumnMapping::new()
+                .with_column("txn_type", "type")
+                .with_column("customer_id", "client")
+        );
+        assert_eq!(config.output.as_deref(), Some("out.csv"));
+        assert_eq!(
+            config.format,
+            SnapshotFormat::default()
+                .with_decimals(2)
+                .with_delimiter(';')
+        );
+        assert_eq!(config.log_level, Some(tracing::Level::DEBUG));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn by_timestamp_is_an_alias_of_timestamp() {
+        for name in ["timestamp", "by-timestamp"] {
+            let config =
+                RunConfig::from_toml(&format!("[processing]\ncombinator = \"{name}\"\n")).unwrap();
+
+            assert!(matches!(
+                config.combinator,
+                StreamCombinator::MergeByTimestamp
+            ));
+        }
+        assert!(RunConfig::from_toml("[processing]\ncombinator = \"zip\"\n").is_err());
+    }
+
+    #[test]
+    fn output_can_match_input_precision() {
+        let config =
+            RunConfig::from_toml("[output]\ndecimals = \"input\"\ntrim_trailing_zeros = true\n")
+                .unwrap();
+
+        assert!(config.input_precision);
+        assert_eq!(
+            config.format,
+            SnapshotFormat::default().with_trailing_zeros_trimmed(true)
+        );
+        assert!(RunConfig::from_toml("[output]\ndecimals = \"two\"").is_err());
+    }
+
+    #[test]
+    fn missing_keys_keep_defaults() {
+        let config = RunConfig::from_toml("inputs = [\"tx.csv\"]\n").unwrap();
+
+        assert_eq!(config.shards, 1);
+        assert!(matches!(config.combinator, StreamCombinator::Merge));
+        assert_eq!(config.error_policy, ErrorPolicyKind::Silent);
+        assert!(!config.verify_invariants);
+        assert_eq!(config.output, None);
+        assert_eq!(config.log_level, None);
+    }
+
+    #[test]
+    fn reports_the_offending_line() {
+        let error = |text| RunConfig::from_toml(text).unwrap_err().to_string();
+
+        assert!(
+            error("[processing]\nshard = 4").contains("line 2: unknown key 'processing.shard'")
+        );
+        assert!(error("[processing]\nshards = \"four\"").contains("line 2: expected an integer"));
+        assert!(error("inputs = [\"a.csv\"").contains("line 1: unterminated array"));
+        assert!(error("[processing]\nerror_policy = \"retry\"").contains("unknown error policy"));
+        assert!(
+            error("[columns]\ncustomer = \"customer_id\"").contains("unknown column `customer`")
+        );
+        assert!(RunConfig::from_toml("").unwrap().validate().is_err());
+    }
+}
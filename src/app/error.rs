@@ -4,7 +4,9 @@ use thiserror::Error;
 use crate::domain::DomainError;
 use crate::engine::EngineError;
 use crate::io::IoError;
-use crate::storage::StorageError;
+use crate::storage::{InvariantViolation, StorageError};
+#[cfg(feature = "testkit")]
+use crate::testkit::TestkitError;
 
 /// Top-level application errors unifying all layer errors
 #[derive(Error, Debug)]
@@ -29,6 +31,135 @@ pub enum AppError {
 
     #[error("Invalid arguments: {0}")]
     InvalidArguments(String),
+
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error("Invariant violation: {0}")]
+    Invariant(#[from] InvariantViolation),
+
+    #[cfg(feature = "testkit")]
+    #[error("Testkit error: {0}")]
+    Testkit(#[from] TestkitError),
+
+    /// Stopped by the given signal number (e.g. 2 for SIGINT)
+    #[error("Interrupted by {}", signal_name(*.0))]
+    Interrupted(i32),
+}
+
+/// Broad class of an `AppError`, which decides the process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// Bad arguments or configuration (exit code 2)
+    Usage,
+    /// Reading input or writing output failed (exit code 3)
+    Io,
+    /// Input could not be parsed (exit code 4)
+    Parse,
+    /// Transactions could not be applied, or accounts are inconsistent (exit code 5)
+    Processing,
+    /// Stopped by a signal (exit code 128 + the signal number)
+    Interrupted,
+}
+
+impl ErrorCategory {
+    /// Lowercase name used in machine-readable output (e.g. `usage`)
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCategory::Usage => "usage",
+            ErrorCategory::Io => "io",
+            ErrorCategory::Parse => "parse",
+            ErrorCategory::Processing => "processing",
+            ErrorCategory::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl AppError {
+    /// Which category the error belongs to
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            AppError::Io(_) | AppError::FileNotFound(_) => ErrorCategory::Io,
+            AppError::CsvIo(e) => io_category(e.inner()),
+            AppError::Engine(_)
+            | AppError::Storage(_)
+            | AppError::Domain(_)
+            | AppError::Invariant(_) => ErrorCategory::Processing,
+            AppError::InvalidArguments(_) | AppError::Config(_) => ErrorCategory::Usage,
+            #[cfg(feature = "testkit")]
+            AppError::Testkit(e) => match e {
+                TestkitError::Io(_) => ErrorCategory::Io,
+                TestkitError::InvalidScenario { .. } => ErrorCategory::Parse,
+                TestkitError::ScenariosFailed { .. } => ErrorCategory::Processing,
+            },
+            AppError::Interrupted(_) => ErrorCategory::Interrupted,
+        }
+    }
+
+    /// Process exit code for the error (see `ErrorCategory`)
+    pub fn exit_code(&self) -> i32 {
+        match (self, self.category()) {
+            (AppError::Interrupted(signal), _) => 128 + signal,
+            (_, ErrorCategory::Usage) => 2,
+            (_, ErrorCategory::Io) => 3,
+            (_, ErrorCategory::Parse) => 4,
+            (_, ErrorCategory::Processing) => 5,
+            // Only `AppError::Interrupted` is in this category
+            (_, ErrorCategory::Interrupted) => 130,
+        }
+    }
+
+    /// The error as a single-line JSON object, for orchestration tooling
+    ///
+    /// `{"error":"parse","message":"CSV IO error: ...","exit_code":4}`
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"error":"{}","message":"{}","exit_code":{}}}"#,
+            self.category().name(),
+            escape_json(&self.to_string()),
+            self.exit_code()
+        )
+    }
+}
+
+/// Category of an input error: failed reads are I/O, everything else about
+/// the records themselves is a parse error
+fn io_category(error: &IoError) -> ErrorCategory {
+    match error {
+        IoError::Io(_) => ErrorCategory::Io,
+        IoError::Csv(e) if e.is_io_error() => ErrorCategory::Io,
+        #[cfg(feature = "object-store")]
+        IoError::ObjectStore(_) | IoError::InvalidUrl(_) => ErrorCategory::Io,
+        IoError::Domain(_) | IoError::Storage(_) => ErrorCategory::Processing,
+        _ => ErrorCategory::Parse,
+    }
+}
+
+/// Conventional name of a signal number
+fn signal_name(signal: i32) -> String {
+    match signal {
+        1 => "SIGHUP".to_string(),
+        2 => "SIGINT".to_string(),
+        15 => "SIGTERM".to_string(),
+        _ => format!("signal {signal}"),
+    }
+}
+
+/// Escape `text` for use inside a JSON string
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 #[cfg(test)]
@@ -69,6 +200,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn categories_map_to_distinct_exit_codes() {
+        let usage = AppError::InvalidArguments("missing file".to_string());
+        let io = AppError::FileNotFound("input.csv".to_string());
+        let parse = AppError::CsvIo(IoError::AtRecord {
+            line: 3,
+            byte: 40,
+            record: "deposit,1,x,1.0".to_string(),
+            source: Box::new(IoError::MissingField("tx".to_string())),
+        });
+        let processing = AppError::Engine(EngineError::TransactionNotFound(1));
+
+        assert_eq!(usage.category(), ErrorCategory::Usage);
+        assert_eq!(io.category(), ErrorCategory::Io);
+        assert_eq!(parse.category(), ErrorCategory::Parse);
+        assert_eq!(processing.category(), ErrorCategory::Processing);
+        let codes: Vec<_> = [usage, io, parse, processing]
+            .iter()
+            .map(AppError::exit_code)
+            .collect();
+        assert_eq!(codes, vec![2, 3, 4, 5]);
+
+        let read_failure = AppError::CsvIo(IoError::Io(io::Error::other("disk")));
+        assert_eq!(read_failure.category(), ErrorCategory::Io);
+        assert_eq!(AppError::Interrupted(15).exit_code(), 143);
+        assert_eq!(
+            AppError::Interrupted(2).to_string(),
+            "Interrupted by SIGINT"
+        );
+    }
+
+    #[test]
+    fn json_output_is_one_escaped_object() {
+        let error = AppError::Config("line 2: bad \"shards\"\n".to_string());
+        assert_eq!(
+            error.to_json(),
+            r#"{"error":"usage","message":"Invalid config: line 2: bad \"shards\"\n","exit_code":2}"#
+        );
+    }
+
     #[test]
     fn engine_error_conversion() {
         let engine_err = EngineError::TransactionNotFound(123);
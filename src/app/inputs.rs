@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use super::error::AppError;
+
+/// Expand input arguments into the files to process, in a deterministic order
+///
+/// Each argument is a path, an object-store URL (passed through unchanged)
+/// or a glob whose file name contains `*` (any run of characters) or `?`
+/// (one character), e.g. `data/*.csv`. Arguments keep their order and the
+/// files matched by one glob are sorted by name, so the same command always
+/// yields the same stream order. Wildcards do not match a leading `.`, and a
+/// file given more than once is only kept the first time. A glob matching
+/// no file is reported as `AppError::FileNotFound`.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<String>, AppError> {
+    let mut expanded: Vec<String> = Vec::new();
+    for input in inputs {
+        let paths = match is_glob(input) {
+            true => glob(input)?,
+            false => vec![input.clone()],
+        };
+        for path in paths {
+            if !expanded.contains(&path) {
+                expanded.push(path);
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+fn is_glob(input: &str) -> bool {
+    !input.contains("://") && input.contains(['*', '?'])
+}
+
+/// Files in the pattern's directory whose names match its file name, sorted
+fn glob(pattern: &str) -> Result<Vec<String>, AppError> {
+    let not_found = || AppError::FileNotFound(pattern.to_string());
+    let path = Path::new(pattern);
+    let name_pattern: Vec<char> = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(not_found)?
+        .chars()
+        .collect();
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir.unwrap_or(Path::new("."))).map_err(|_| not_found())? {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let name_chars: Vec<char> = name.chars().collect();
+        let hidden = name.starts_with('.') && name_pattern.first() != Some(&'.');
+        if hidden || !entry.path().is_file() || !matches(&name_pattern, &name_chars) {
+            continue;
+        }
+        paths.push(match dir {
+            Some(dir) => dir.join(&name).display().to_string(),
+            None => name,
+        });
+    }
+
+    if paths.is_empty() {
+        return Err(not_found());
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+/// and `?` exactly one
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', rest)), _) => {
+            matches(rest, name) || (!name.is_empty() && matches(pattern, &name[1..]))
+        }
+        (Some(_), None) => false,
+        (Some(('?', rest)), Some((_, name_rest))) => matches(rest, name_rest),
+        (Some((expected, rest)), Some((actual, name_rest))) => {
+            expected == actual && matches(rest, name_rest)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn globs_expand_sorted_in_argument_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.csv", "a.csv", "c.txt", ".hidden.csv", "a1.csv"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let path = |name: &str| dir.path().join(name).display().to_string();
+
+        let expanded = expand_inputs(&[
+            path("c.txt"),
+            path("*.csv"),
+            path("a?.csv"),
+            "s3://bucket/*.csv".to_string(),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            expanded,
+            vec![
+                path("c.txt"),
+                path("a.csv"),
+                path("a1.csv"),
+                path("b.csv"),
+                "s3://bucket/*.csv".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn glob_without_matches_is_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let pattern = dir.path().join("*.csv").display().to_string();
+
+        let result = expand_inputs(std::slice::from_ref(&pattern));
+
+        assert!(matches!(result, Err(AppError::FileNotFound(p)) if p == pattern));
+        assert_eq!(
+            expand_inputs(&strings(&["missing.csv"])).unwrap(),
+            strings(&["missing.csv"])
+        );
+    }
+
+    #[test]
+    fn wildcards_match_runs_and_single_characters() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert!(matches(&chars("tx-*.csv"), &chars("tx-2024-01.csv")));
+        assert!(matches(&chars("*"), &chars("")));
+        assert!(matches(&chars("day?.csv"), &chars("day7.csv")));
+        assert!(!matches(&chars("day?.csv"), &chars("day17.csv")));
+        assert!(!matches(&chars("*.csv"), &chars("accounts.csv.gz")));
+    }
+}
@@ -1,6 +1,12 @@
 pub mod cli;
+pub mod config;
 pub mod error;
+pub mod inputs;
+pub mod process;
 
 // Re-export commonly used types
-pub use cli::{CliApp, Writers};
-pub use error::AppError;
+pub use cli::{CliApp, ErrorFormat, LogFormat, Writers};
+pub use config::{ErrorPolicyKind, RunConfig};
+pub use error::{AppError, ErrorCategory};
+pub use inputs::expand_inputs;
+pub use process::{AccountRow, ProcessOptions, Snapshot, process_file};
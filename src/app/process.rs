@@ -0,0 +1,196 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use super::config::ErrorPolicyKind;
+use super::error::AppError;
+use crate::domain::{ClientAccount, ClientId, FixedPoint};
+use crate::io::CsvTransactionStream;
+use crate::storage::{
+    ClientAccountManager, ConcurrentAccountManager, ConcurrentTransactionStore, verify_invariants,
+};
+use crate::streaming::{AbortOnError, ErrorPolicy, SilentSkip, SkipErrors, StreamProcessor};
+
+/// Settings for `process_file`
+#[derive(Debug, Clone)]
+pub struct ProcessOptions {
+    pub shards: usize,
+    pub error_policy: ErrorPolicyKind,
+    /// Accept admin transactions (`unlock`, `credit_limit`, `adjustment`, `tag`)
+    pub allow_admin_ops: bool,
+    /// Fail if any account's invariants are violated after processing
+    pub verify_invariants: bool,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            shards: 1,
+            error_policy: ErrorPolicyKind::default(),
+            allow_admin_ops: false,
+            verify_invariants: false,
+        }
+    }
+}
+
+/// One account of a `Snapshot`, in the base currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountRow {
+    pub client_id: ClientId,
+    pub available: FixedPoint,
+    pub held: FixedPoint,
+    pub total: FixedPoint,
+    pub locked: bool,
+}
+
+impl AccountRow {
+    fn of(account: &ClientAccount<FixedPoint>) -> Self {
+        Self {
+            client_id: account.client_id(),
+            available: account.available(),
+            held: account.held(),
+            total: account.total(),
+            locked: account.is_locked(),
+        }
+    }
+}
+
+/// Final accounts of a `process_file` run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Every account, ordered by client id
+    pub accounts: Vec<AccountRow>,
+    /// False if an error stopped processing early (only under `ErrorPolicyKind::Abort`)
+    pub complete: bool,
+}
+
+impl Snapshot {
+    /// The client's account, if it has one
+    pub fn account(&self, client_id: ClientId) -> Option<&AccountRow> {
+        self.accounts
+            .binary_search_by_key(&client_id, |row| row.client_id)
+            .ok()
+            .map(|index| &self.accounts[index])
+    }
+}
+
+/// Process a transactions CSV file (gzip and zstd are detected) and return
+/// the resulting accounts
+///
+/// Runs the same pipeline as the `pay` binary, with in-memory storage that
+/// is dropped once the snapshot is taken; use `StreamProcessor` directly to
+/// keep the storage or combine several inputs.
+///
+/// # Example
+/// ```rust,ignore
+/// let snapshot = pay::process_file("transactions.csv", ProcessOptions::default()).await?;
+/// for row in &snapshot.accounts {
+///     println!("{}: {}", row.client_id, row.available);
+/// }
+/// ```
+pub async fn process_file(
+    path: impl AsRef<Path>,
+    options: ProcessOptions,
+) -> Result<Snapshot, AppError> {
+    let path = path.as_ref();
+    if !path.is_file() {
+        return Err(AppError::FileNotFound(path.display().to_string()));
+    }
+    match options.error_policy {
+        ErrorPolicyKind::Silent => process_with(path, &options, SilentSkip).await,
+        ErrorPolicyKind::Skip => process_with(path, &options, SkipErrors).await,
+        ErrorPolicyKind::Abort => process_with(path, &options, AbortOnError).await,
+    }
+}
+
+async fn process_with<P: ErrorPolicy + Clone + Send + 'static>(
+    path: &Path,
+    options: &ProcessOptions,
+    policy: P,
+) -> Result<Snapshot, AppError> {
+    let stream = CsvTransactionStream::<FixedPoint>::from_file(path).await?;
+    let account_manager = Arc::new(ConcurrentAccountManager::new());
+    let transaction_store = Arc::new(ConcurrentTransactionStore::new());
+
+    let results = StreamProcessor::new(account_manager.clone(), transaction_store, policy)
+        .with_shards(options.shards)
+        .with_admin_ops(options.allow_admin_ops)
+        .add_timestamped_stream_named(path.display().to_string(), stream.timestamped())
+        .process()
+        .await;
+
+    if options.verify_invariants
+        && let Err(violations) = verify_invariants(&*account_manager)
+    {
+        return Err(violations
+            .into_iter()
+            .next()
+            .expect("at least one violation")
+            .into());
+    }
+
+    let mut accounts = Vec::new();
+    account_manager.for_each_account(&mut |account| accounts.push(AccountRow::of(account)));
+    accounts.sort_by_key(|row| row.client_id);
+    Ok(Snapshot {
+        accounts,
+        complete: results.all_succeeded(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_csv(contents: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), contents).unwrap();
+        file
+    }
+
+    #[tokio::test]
+    async fn returns_accounts_ordered_by_client() {
+        let file = write_csv(
+            "type,client,tx,amount\n\
+             deposit,2,1,5.0\n\
+             deposit,1,2,3.0\n\
+             withdrawal,1,3,1.0\n\
+             withdrawal,2,4,9.0\n",
+        );
+
+        let snapshot = process_file(file.path(), ProcessOptions::default())
+            .await
+            .unwrap();
+
+        assert!(snapshot.complete);
+        let clients: Vec<_> = snapshot.accounts.iter().map(|row| row.client_id).collect();
+        assert_eq!(clients, vec![1, 2]);
+        assert_eq!(
+            snapshot.account(1).unwrap().available,
+            FixedPoint::from_raw(20_000)
+        );
+        assert_eq!(
+            snapshot.account(2).unwrap().total,
+            FixedPoint::from_raw(50_000)
+        );
+        assert!(snapshot.account(3).is_none());
+    }
+
+    #[tokio::test]
+    async fn abort_policy_reports_an_incomplete_run() {
+        let file = write_csv("type,client,tx,amount\nwithdrawal,1,1,1.0\ndeposit,1,2,1.0\n");
+        let options = ProcessOptions {
+            error_policy: ErrorPolicyKind::Abort,
+            ..ProcessOptions::default()
+        };
+
+        let snapshot = process_file(file.path(), options).await.unwrap();
+
+        assert!(!snapshot.complete);
+    }
+
+    #[tokio::test]
+    async fn missing_file_is_reported() {
+        let result = process_file("does-not-exist.csv", ProcessOptions::default()).await;
+        assert!(matches!(result, Err(AppError::FileNotFound(_))));
+    }
+}
@@ -1,31 +1,68 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 
 use super::amount::AmountType;
+use super::currency::{CurrencyBalance, CurrencyCode};
+use super::error::DomainError;
+use super::transaction::{ClientId, TransactionId};
+use super::tx_set::TxIdSet;
 
 /// Client account with private fields enforcing invariants
+///
+/// With the `serde` feature the full state round-trips, including open
+/// disputes and holds; empty sets and maps are omitted.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClientAccount<A: AmountType> {
-    client_id: u16,
+    client_id: ClientId,
     available: A,
     held: A,
     locked: bool,
-    disputed_transactions: HashSet<u32>,
+    /// How far withdrawals may take available funds below zero
+    #[cfg_attr(feature = "serde", serde(default))]
+    credit_limit: A,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "TxIdSet::is_empty")
+    )]
+    disputed_transactions: TxIdSet,
+    /// Authorizations whose funds are reserved in held until captured or released
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "TxIdSet::is_empty")
+    )]
+    active_holds: TxIdSet,
+    /// Balances in currencies other than the base currency
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    currency_balances: BTreeMap<CurrencyCode, CurrencyBalance<A>>,
+    /// Segment metadata (e.g. `region`, `tier`, `kyc`), set by `Transaction::SetTag`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    tags: BTreeMap<String, String>,
 }
 
 impl<A: AmountType> ClientAccount<A> {
     /// Create a new account with zero balance
-    pub fn new(client_id: u16) -> Self {
+    pub fn new(client_id: ClientId) -> Self {
         Self {
             client_id,
             available: A::zero(),
             held: A::zero(),
             locked: false,
-            disputed_transactions: HashSet::new(),
+            credit_limit: A::zero(),
+            disputed_transactions: TxIdSet::new(),
+            active_holds: TxIdSet::new(),
+            currency_balances: BTreeMap::new(),
+            tags: BTreeMap::new(),
         }
     }
 
     /// Get the client ID
-    pub fn client_id(&self) -> u16 {
+    pub fn client_id(&self) -> ClientId {
         self.client_id
     }
 
@@ -40,8 +77,56 @@ impl<A: AmountType> ClientAccount<A> {
     }
 
     /// Get total funds (derived: available + held)
+    ///
+    /// # Panics
+    /// Panics if the sum overflows `A`, in debug and release builds alike.
+    /// Operations refuse credits that would make it overflow, so this only
+    /// happens for accounts built outside them; use `checked_total` there.
     pub fn total(&self) -> A {
-        self.available + self.held
+        self.checked_total()
+            .expect("account total overflows the amount type")
+    }
+
+    /// Get total funds, or None if available + held overflows `A`
+    pub fn checked_total(&self) -> Option<A> {
+        self.available.checked_add(self.held)
+    }
+
+    /// Get the credit limit: withdrawals may leave available funds as low as
+    /// `-credit_limit` (zero unless set by `Transaction::SetCreditLimit`)
+    pub fn credit_limit(&self) -> A {
+        self.credit_limit
+    }
+
+    /// Check the account's balance invariants
+    ///
+    /// Held funds and the credit limit are never negative and available +
+    /// held fits in `A`, in the base currency and every other currency.
+    /// Available funds may be negative under some dispute policies or a
+    /// credit limit, so they are not checked.
+    pub fn validate(&self) -> Result<(), DomainError> {
+        if self.held < A::zero() {
+            return Err(DomainError::InvariantViolated("held funds are negative"));
+        }
+        if self.credit_limit < A::zero() {
+            return Err(DomainError::InvariantViolated("credit limit is negative"));
+        }
+        if self.checked_total().is_none() {
+            return Err(DomainError::InvariantViolated("total overflows"));
+        }
+        for balance in self.currency_balances.values() {
+            if balance.held < A::zero() {
+                return Err(DomainError::InvariantViolated(
+                    "held funds in a currency are negative",
+                ));
+            }
+            if balance.available.checked_add(balance.held).is_none() {
+                return Err(DomainError::InvariantViolated(
+                    "total in a currency overflows",
+                ));
+            }
+        }
+        Ok(())
     }
 
     /// Check if account is locked
@@ -50,8 +135,13 @@ impl<A: AmountType> ClientAccount<A> {
     }
 
     /// Check if a transaction is disputed
-    pub fn is_disputed(&self, tx_id: u32) -> bool {
-        self.disputed_transactions.contains(&tx_id)
+    pub fn is_disputed(&self, tx_id: TransactionId) -> bool {
+        self.disputed_transactions.contains(tx_id)
+    }
+
+    /// Check if a transaction is an authorization hold awaiting capture or release
+    pub fn has_active_hold(&self, tx_id: TransactionId) -> bool {
+        self.active_holds.contains(tx_id)
     }
 
     /// Get the number of disputed transactions
@@ -59,6 +149,45 @@ impl<A: AmountType> ClientAccount<A> {
         self.disputed_transactions.len()
     }
 
+    /// Get the balances held in a non-base currency (zero if never used)
+    pub fn currency_balance(&self, currency: CurrencyCode) -> CurrencyBalance<A> {
+        self.currency_balances
+            .get(&currency)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Iterate over non-base currency balances in currency code order
+    pub fn currency_balances(
+        &self,
+    ) -> impl Iterator<Item = (CurrencyCode, CurrencyBalance<A>)> + '_ {
+        self.currency_balances
+            .iter()
+            .map(|(currency, balance)| (*currency, *balance))
+    }
+
+    /// Get the value of a tag (None if the client has no such tag)
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
+    /// Iterate over the client's tags in key order
+    pub fn tags(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.tags
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Disputed transaction ids, in ascending order
+    pub(crate) fn disputed_ids(&self) -> impl Iterator<Item = TransactionId> + '_ {
+        self.disputed_transactions.iter()
+    }
+
+    /// Active hold transaction ids, in ascending order
+    pub(crate) fn hold_ids(&self) -> impl Iterator<Item = TransactionId> + '_ {
+        self.active_holds.iter()
+    }
+
     // Internal mutation methods for use by operations module
     pub(crate) fn set_available(&mut self, amount: A) {
         self.available = amount;
@@ -68,16 +197,63 @@ impl<A: AmountType> ClientAccount<A> {
         self.held = amount;
     }
 
+    /// Replace the base balances, returning the previous ones
+    pub(crate) fn swap_balances(&mut self, balance: CurrencyBalance<A>) -> CurrencyBalance<A> {
+        let previous = CurrencyBalance {
+            available: self.available,
+            held: self.held,
+        };
+        self.available = balance.available;
+        self.held = balance.held;
+        previous
+    }
+
+    pub(crate) fn set_currency_balance(
+        &mut self,
+        currency: CurrencyCode,
+        balance: CurrencyBalance<A>,
+    ) {
+        self.currency_balances.insert(currency, balance);
+    }
+
+    pub(crate) fn set_credit_limit(&mut self, limit: A) {
+        self.credit_limit = limit;
+    }
+
+    /// Set a tag, or remove it when `value` is None
+    pub(crate) fn set_tag(&mut self, key: &str, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                self.tags.insert(key.to_string(), value.to_string());
+            }
+            None => {
+                self.tags.remove(key);
+            }
+        }
+    }
+
     pub(crate) fn lock(&mut self) {
         self.locked = true;
     }
 
-    pub(crate) fn add_disputed(&mut self, tx_id: u32) -> bool {
+    pub(crate) fn unlock(&mut self) {
+        self.locked = false;
+    }
+
+    pub(crate) fn add_disputed(&mut self, tx_id: TransactionId) -> bool {
         self.disputed_transactions.insert(tx_id)
     }
 
-    pub(crate) fn remove_disputed(&mut self, tx_id: u32) -> bool {
-        self.disputed_transactions.remove(&tx_id)
+    pub(crate) fn remove_disputed(&mut self, tx_id: TransactionId) -> bool {
+        self.disputed_transactions.remove(tx_id)
+    }
+
+    pub(crate) fn add_hold(&mut self, tx_id: TransactionId) -> bool {
+        self.active_holds.insert(tx_id)
+    }
+
+    pub(crate) fn remove_hold(&mut self, tx_id: TransactionId) -> bool {
+        self.active_holds.remove(tx_id)
     }
 }
 
@@ -106,6 +282,39 @@ mod tests {
         assert_eq!(account.total(), FixedPoint::from_raw(15_000));
     }
 
+    #[test]
+    fn checked_total_detects_overflow() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        account.set_available(FixedPoint::from_raw(i64::MAX));
+        assert_eq!(
+            account.checked_total(),
+            Some(FixedPoint::from_raw(i64::MAX))
+        );
+
+        account.set_held(FixedPoint::from_raw(1));
+        assert_eq!(account.checked_total(), None);
+        assert_eq!(
+            account.validate(),
+            Err(DomainError::InvariantViolated("total overflows"))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_negative_held() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        assert_eq!(account.validate(), Ok(()));
+
+        // Negative available funds are allowed by some dispute policies
+        account.set_available(FixedPoint::from_raw(-5_000));
+        assert_eq!(account.validate(), Ok(()));
+
+        account.set_held(FixedPoint::from_raw(-1));
+        assert_eq!(
+            account.validate(),
+            Err(DomainError::InvariantViolated("held funds are negative"))
+        );
+    }
+
     #[test]
     fn getters_return_correct_values() {
         let mut account = ClientAccount::<FixedPoint>::new(42);
@@ -137,6 +346,15 @@ mod tests {
         assert!(account.is_locked());
     }
 
+    #[test]
+    fn unlock_clears_locked_flag() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        account.lock();
+
+        account.unlock();
+        assert!(!account.is_locked());
+    }
+
     #[test]
     fn new_account_has_no_disputes() {
         let account = ClientAccount::<FixedPoint>::new(1);
@@ -215,4 +433,22 @@ mod tests {
         account.add_disputed(1);
         assert!(account.is_disputed(1));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_dispute_state() {
+        let mut account = ClientAccount::<FixedPoint>::new(3);
+        account.set_available(FixedPoint::from_raw(10_000));
+        account.set_held(FixedPoint::from_raw(5_000));
+        account.add_disputed(9);
+        account.add_disputed(4);
+
+        let json = serde_json::to_string(&account).unwrap();
+        assert!(json.contains(r#""disputed_transactions":[4,9]"#), "{json}");
+        assert!(!json.contains("active_holds"), "{json}");
+
+        let restored: ClientAccount<FixedPoint> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, account);
+        assert!(restored.is_disputed(4));
+    }
 }
@@ -0,0 +1,279 @@
+use super::account::ClientAccount;
+use super::amount::AmountType;
+use super::currency::{CurrencyCode, apply_in_currency};
+use super::dispute_policy::DisputePolicy;
+use super::error::DomainError;
+use super::fee::{apply_deposit_with_fee, apply_withdrawal_with_fee};
+use super::operations::{
+    apply_adjustment, apply_capture, apply_chargeback_reversal, apply_chargeback_with_policy,
+    apply_deposit, apply_dispute_with_policy, apply_hold, apply_release, apply_resolve_with_policy,
+    apply_set_credit_limit, apply_set_tag, apply_transfer, apply_unlock, apply_withdrawal,
+};
+use super::transaction::TransactionId;
+
+/// A change the engine makes to one account
+///
+/// Each variant carries what its function in `domain::operations` takes, so
+/// applying the same operations to the same accounts always gives the same
+/// result. Storage gets them through `ClientAccountEntry::try_apply`, which
+/// lets backends such as `EventSourcedAccountManager` log operations instead
+/// of account states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountOp<A: AmountType> {
+    Deposit {
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+    Withdrawal {
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+    Hold {
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+    Capture {
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+    Release {
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+    Dispute {
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+        policy: DisputePolicy,
+    },
+    /// Release the `held` part of a dispute
+    Resolve {
+        tx_id: TransactionId,
+        held: A,
+        currency: Option<CurrencyCode>,
+        policy: DisputePolicy,
+    },
+    /// Charge back `amount`, of which the dispute holds `held`
+    Chargeback {
+        tx_id: TransactionId,
+        amount: A,
+        held: A,
+        currency: Option<CurrencyCode>,
+        policy: DisputePolicy,
+    },
+    ChargebackReversal {
+        amount: A,
+        currency: Option<CurrencyCode>,
+        policy: DisputePolicy,
+    },
+    Unlock,
+    SetCreditLimit {
+        limit: A,
+    },
+    SetTag {
+        key: String,
+        value: Option<String>,
+    },
+    Adjustment {
+        amount: A,
+    },
+    /// Replace the account with a saved copy (restoring a checkpoint or
+    /// saved state)
+    Restore(ClientAccount<A>),
+}
+
+impl<A: AmountType> AccountOp<A> {
+    /// Apply the operation to `account`
+    ///
+    /// Returns the amount a dispute moved to held (see
+    /// `apply_dispute_with_policy`); other operations return `None`.
+    pub fn apply(&self, account: &mut ClientAccount<A>) -> Result<Option<A>, DomainError> {
+        let mut held = None;
+        match self {
+            AccountOp::Deposit { amount, currency } => {
+                apply_in_currency(account, *currency, |account| {
+                    apply_deposit(account, *amount)
+                })?
+            }
+            AccountOp::Withdrawal { amount, currency } => {
+                apply_in_currency(account, *currency, |account| {
+                    apply_withdrawal(account, *amount)
+                })?
+            }
+            AccountOp::Hold {
+                tx_id,
+                amount,
+                currency,
+            } => apply_in_currency(account, *currency, |account| {
+                apply_hold(account, *tx_id, *amount)
+            })?,
+            AccountOp::Capture {
+                tx_id,
+                amount,
+                currency,
+            } => apply_in_currency(account, *currency, |account| {
+                apply_capture(account, *tx_id, *amount)
+            })?,
+            AccountOp::Release {
+                tx_id,
+                amount,
+                currency,
+            } => apply_in_currency(account, *currency, |account| {
+                apply_release(account, *tx_id, *amount)
+            })?,
+            AccountOp::Dispute {
+                tx_id,
+                amount,
+                currency,
+                policy,
+            } => apply_in_currency(account, *currency, |account| {
+                held = Some(apply_dispute_with_policy(account, *tx_id, *amount, policy)?);
+                Ok(())
+            })?,
+            AccountOp::Resolve {
+                tx_id,
+                held,
+                currency,
+                policy,
+            } => apply_in_currency(account, *currency, |account| {
+                apply_resolve_with_policy(account, *tx_id, *held, policy)
+            })?,
+            AccountOp::Chargeback {
+                tx_id,
+                amount,
+                held,
+                currency,
+                policy,
+            } => apply_in_currency(account, *currency, |account| {
+                apply_chargeback_with_policy(account, *tx_id, *amount, *held, policy)
+            })?,
+            AccountOp::ChargebackReversal {
+                amount,
+                currency,
+                policy,
+            } => apply_in_currency(account, *currency, |account| {
+                apply_chargeback_reversal(account, *amount, policy)
+            })?,
+            AccountOp::Unlock => apply_unlock(account)?,
+            AccountOp::SetCreditLimit { limit } => apply_set_credit_limit(account, *limit)?,
+            AccountOp::SetTag { key, value } => apply_set_tag(account, key, value.as_deref())?,
+            AccountOp::Adjustment { amount } => apply_adjustment(account, *amount)?,
+            AccountOp::Restore(saved) => *account = saved.clone(),
+        }
+        Ok(held)
+    }
+}
+
+/// A change the engine makes to two accounts at once
+///
+/// Applied through `ClientAccountManager::try_apply_pair`; the first account
+/// is the client's, the second the fee account or transfer recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PairOp<A: AmountType> {
+    /// Deposit `amount` and collect `fee` out of it
+    DepositWithFee {
+        amount: A,
+        fee: A,
+        currency: Option<CurrencyCode>,
+    },
+    /// Withdraw `amount` and collect `fee` on top of it
+    WithdrawalWithFee {
+        amount: A,
+        fee: A,
+        currency: Option<CurrencyCode>,
+    },
+    Transfer {
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+}
+
+impl<A: AmountType> PairOp<A> {
+    /// Apply the operation to `first` and `second`, in the operation's currency
+    pub fn apply(
+        &self,
+        first: &mut ClientAccount<A>,
+        second: &mut ClientAccount<A>,
+    ) -> Result<(), DomainError> {
+        let currency = match self {
+            PairOp::DepositWithFee { currency, .. }
+            | PairOp::WithdrawalWithFee { currency, .. }
+            | PairOp::Transfer { currency, .. } => *currency,
+        };
+        apply_in_currency(first, currency, |first| {
+            apply_in_currency(second, currency, |second| match self {
+                PairOp::DepositWithFee { amount, fee, .. } => {
+                    apply_deposit_with_fee(first, second, *amount, *fee)
+                }
+                PairOp::WithdrawalWithFee { amount, fee, .. } => {
+                    apply_withdrawal_with_fee(first, second, *amount, *fee)
+                }
+                PairOp::Transfer { amount, .. } => apply_transfer(first, second, *amount),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn amount(raw: i64) -> FixedPoint {
+        FixedPoint::from_raw(raw)
+    }
+
+    #[test]
+    fn dispute_returns_the_held_amount() {
+        let mut account = ClientAccount::new(1);
+        let deposit = AccountOp::Deposit {
+            amount: amount(10_000),
+            currency: None,
+        };
+        let dispute = AccountOp::Dispute {
+            tx_id: 7,
+            amount: amount(10_000),
+            currency: None,
+            policy: DisputePolicy::default(),
+        };
+
+        assert_eq!(deposit.apply(&mut account), Ok(None));
+        assert_eq!(dispute.apply(&mut account), Ok(Some(amount(10_000))));
+        assert_eq!(account.held(), amount(10_000));
+        assert!(account.is_disputed(7));
+    }
+
+    #[test]
+    fn operations_apply_in_their_currency() {
+        let eur = "EUR".parse().unwrap();
+        let mut account = ClientAccount::new(1);
+        AccountOp::Deposit {
+            amount: amount(10_000),
+            currency: Some(eur),
+        }
+        .apply(&mut account)
+        .unwrap();
+
+        assert_eq!(account.available(), amount(0));
+        assert_eq!(account.currency_balance(eur).available, amount(10_000));
+    }
+
+    #[test]
+    fn failed_pair_operations_change_neither_account() {
+        let (mut from, mut to) = (ClientAccount::new(1), ClientAccount::new(2));
+        let transfer = PairOp::Transfer {
+            amount: amount(5_000),
+            currency: None,
+        };
+
+        assert_eq!(
+            transfer.apply(&mut from, &mut to),
+            Err(DomainError::InsufficientFunds)
+        );
+        assert_eq!(from, ClientAccount::new(1));
+        assert_eq!(to, ClientAccount::new(2));
+    }
+}
@@ -2,23 +2,106 @@ use std::fmt;
 use std::ops::{Add, Sub};
 
 use super::error::DomainError;
+use super::rounding::{
+    RoundingPolicy, divide_rounded, fits_decimals, format_decimal_str, normalize_decimal_str,
+    push_digits,
+};
 
 /// Trait representing a monetary amount with fixed precision
 pub trait AmountType:
     Copy + Ord + Add<Output = Self> + Sub<Output = Self> + Default + Send + Sync + fmt::Debug
 {
+    /// Number of decimal places stored (the minor unit of the currency of record)
+    const DECIMALS: usize = 4;
+
     /// Parse from decimal string (e.g., "1.5000")
     fn from_decimal_str(s: &str) -> Result<Self, DomainError>;
 
+    /// Parse from decimal string, rounding excess decimal places per `policy`
+    fn from_decimal_str_rounded(s: &str, policy: RoundingPolicy) -> Result<Self, DomainError> {
+        Self::from_decimal_bytes_rounded(s.as_bytes(), policy)
+    }
+
+    /// Parse from the ASCII bytes of a decimal (e.g. a borrowed CSV field)
+    ///
+    /// The default implementation checks the bytes are UTF-8 and defers to
+    /// `from_decimal_str`; implementations should override it to parse
+    /// without allocating.
+    fn from_decimal_bytes(bytes: &[u8]) -> Result<Self, DomainError> {
+        std::str::from_utf8(bytes)
+            .map_err(|_| DomainError::InvalidAmount)
+            .and_then(Self::from_decimal_str)
+    }
+
+    /// Parse from decimal bytes, rounding excess decimal places per `policy`
+    ///
+    /// Only amounts with more than `DECIMALS` places go through the
+    /// allocating rounding path.
+    fn from_decimal_bytes_rounded(
+        bytes: &[u8],
+        policy: RoundingPolicy,
+    ) -> Result<Self, DomainError> {
+        if fits_decimals(bytes, Self::DECIMALS) {
+            return Self::from_decimal_bytes(bytes);
+        }
+        let s = std::str::from_utf8(bytes).map_err(|_| DomainError::InvalidAmount)?;
+        Self::from_decimal_str(&normalize_decimal_str(s, Self::DECIMALS, policy)?)
+    }
+
     /// Convert to decimal string with 4 decimal places
     fn to_decimal_string(&self) -> String;
 
+    /// Convert to a decimal string with `decimals` places (at most `DECIMALS`)
+    ///
+    /// Fewer places than stored are rounded half-even; `trim_trailing_zeros`
+    /// then drops trailing fractional zeros, so `1.5000` is written `1.5` and
+    /// `2.0000` is written `2`. Snapshots use `SnapshotFormat` instead, which
+    /// also chooses the rounding policy and decimal separator.
+    fn to_decimal_string_with(&self, decimals: usize, trim_trailing_zeros: bool) -> String {
+        let decimals = decimals.min(Self::DECIMALS);
+        format_decimal_str(
+            &self.to_decimal_string(),
+            decimals,
+            trim_trailing_zeros,
+            RoundingPolicy::HalfEven,
+        )
+        .expect("half-even rounding of a formatted amount cannot fail")
+    }
+
+    /// Append the `to_decimal_string` text to `out`
+    ///
+    /// Snapshots call this for every amount; implementations should override
+    /// it to write the digits without allocating.
+    fn write_decimal(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.to_decimal_string().as_bytes());
+    }
+
     /// Checked addition, returns None on overflow
     fn checked_add(&self, other: Self) -> Option<Self>;
 
     /// Checked subtraction, returns None on underflow
     fn checked_sub(&self, other: Self) -> Option<Self>;
 
+    /// Multiply by `numerator / denominator`, rounding per `rounding`
+    ///
+    /// Returns None on overflow, a zero denominator, or an inexact result under
+    /// `RoundingPolicy::Reject`.
+    fn checked_mul_ratio(
+        &self,
+        numerator: i64,
+        denominator: i64,
+        rounding: RoundingPolicy,
+    ) -> Option<Self>;
+
+    /// Multiply by a decimal factor (e.g. an FX rate), rounding per `rounding`
+    fn checked_mul(&self, factor: Self, rounding: RoundingPolicy) -> Option<Self>;
+
+    /// Divide by a decimal divisor, rounding per `rounding`
+    fn checked_div(&self, divisor: Self, rounding: RoundingPolicy) -> Option<Self>;
+
+    /// Apply a percentage (e.g. `2.5` for 2.5%), rounding per `rounding`
+    fn checked_percentage(&self, percent: Self, rounding: RoundingPolicy) -> Option<Self>;
+
     /// Zero value
     fn zero() -> Self;
 }
@@ -40,43 +123,57 @@ impl FixedPoint {
     pub fn raw(&self) -> i64 {
         self.0
     }
+
+    /// Value of an unsigned run of ASCII digits (0 when empty), or None if
+    /// it holds anything else or overflows
+    fn parse_digits(digits: &[u8]) -> Option<i64> {
+        digits.iter().try_fold(0i64, |value, &digit| {
+            if !digit.is_ascii_digit() {
+                return None;
+            }
+            value.checked_mul(10)?.checked_add(i64::from(digit - b'0'))
+        })
+    }
+
+    /// Round a widened `numerator / denominator` back to a raw value
+    fn from_wide(numerator: i128, denominator: i128, rounding: RoundingPolicy) -> Option<Self> {
+        divide_rounded(numerator, denominator, rounding)
+            .and_then(|raw| i64::try_from(raw).ok())
+            .map(Self)
+    }
 }
 
 impl AmountType for FixedPoint {
     fn from_decimal_str(s: &str) -> Result<Self, DomainError> {
-        let s = s.trim();
+        Self::from_decimal_bytes(s.as_bytes())
+    }
 
-        // Handle negative sign
-        let (is_negative, s) = if let Some(stripped) = s.strip_prefix('-') {
-            (true, stripped)
-        } else {
-            (false, s)
-        };
+    fn from_decimal_bytes(bytes: &[u8]) -> Result<Self, DomainError> {
+        let bytes = bytes.trim_ascii();
 
-        // Split on decimal point
-        let parts: Vec<&str> = s.split('.').collect();
+        // Handle sign
+        let (is_negative, bytes) = match bytes.split_first() {
+            Some((b'-', rest)) => (true, rest),
+            _ => (false, bytes),
+        };
+        let bytes = bytes.strip_prefix(b"+").unwrap_or(bytes);
 
-        let (integer_part, decimal_part) = match parts.len() {
-            1 => (parts[0], ""),
-            2 => (parts[0], parts[1]),
-            _ => return Err(DomainError::InvalidAmount),
+        // Split on the decimal point; a second one fails the digit check below
+        let (integer_part, decimal_part) = match bytes.iter().position(|&b| b == b'.') {
+            Some(dot) => (&bytes[..dot], &bytes[dot + 1..]),
+            None => (bytes, &[][..]),
         };
 
         // Validate decimal places (max 4)
-        if decimal_part.len() > 4 {
+        if integer_part.is_empty() || decimal_part.len() > 4 {
             return Err(DomainError::InvalidAmount);
         }
 
-        // Parse integer part
-        let integer: i64 = integer_part
-            .parse()
-            .map_err(|_| DomainError::InvalidAmount)?;
+        let integer = Self::parse_digits(integer_part).ok_or(DomainError::InvalidAmount)?;
 
-        // Parse decimal part and pad to 4 digits
-        let decimal_str = format!("{:0<4}", decimal_part);
-        let decimal: i64 = decimal_str
-            .parse()
-            .map_err(|_| DomainError::InvalidAmount)?;
+        // Parse decimal part, scaled up as if padded to 4 digits
+        let decimal = Self::parse_digits(decimal_part).ok_or(DomainError::InvalidAmount)?
+            * 10i64.pow((4 - decimal_part.len()) as u32);
 
         // Combine: integer * 10000 + decimal
         let scaled = integer
@@ -98,6 +195,19 @@ impl AmountType for FixedPoint {
         format!("{}{}.{:04}", sign, integer_part, decimal_part)
     }
 
+    fn write_decimal(&self, out: &mut Vec<u8>) {
+        if self.0 < 0 {
+            out.push(b'-');
+        }
+        let abs_value = self.0.unsigned_abs();
+        push_digits(out, abs_value / Self::SCALE as u64);
+        out.push(b'.');
+        let decimal_part = abs_value % Self::SCALE as u64;
+        for place in [1_000, 100, 10, 1] {
+            out.push(b'0' + (decimal_part / place % 10) as u8);
+        }
+    }
+
     fn checked_add(&self, other: Self) -> Option<Self> {
         self.0.checked_add(other.0).map(Self)
     }
@@ -106,6 +216,43 @@ impl AmountType for FixedPoint {
         self.0.checked_sub(other.0).map(Self)
     }
 
+    fn checked_mul_ratio(
+        &self,
+        numerator: i64,
+        denominator: i64,
+        rounding: RoundingPolicy,
+    ) -> Option<Self> {
+        Self::from_wide(
+            i128::from(self.0) * i128::from(numerator),
+            i128::from(denominator),
+            rounding,
+        )
+    }
+
+    fn checked_mul(&self, factor: Self, rounding: RoundingPolicy) -> Option<Self> {
+        Self::from_wide(
+            i128::from(self.0) * i128::from(factor.0),
+            i128::from(Self::SCALE),
+            rounding,
+        )
+    }
+
+    fn checked_div(&self, divisor: Self, rounding: RoundingPolicy) -> Option<Self> {
+        Self::from_wide(
+            i128::from(self.0) * i128::from(Self::SCALE),
+            i128::from(divisor.0),
+            rounding,
+        )
+    }
+
+    fn checked_percentage(&self, percent: Self, rounding: RoundingPolicy) -> Option<Self> {
+        Self::from_wide(
+            i128::from(self.0) * i128::from(percent.0),
+            i128::from(Self::SCALE) * 100,
+            rounding,
+        )
+    }
+
     fn zero() -> Self {
         Self(0)
     }
@@ -127,6 +274,48 @@ impl Sub for FixedPoint {
     }
 }
 
+/// Serialized as a decimal string (e.g. `"1.5000"`) so no precision is lost
+#[cfg(feature = "serde")]
+impl serde::Serialize for FixedPoint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_decimal_string())
+    }
+}
+
+/// Accepts decimal strings, and integers or floats for hand-written input
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FixedPoint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DecimalVisitor;
+
+        impl serde::de::Visitor<'_> for DecimalVisitor {
+            type Value = FixedPoint;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a decimal amount with at most 4 places")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<FixedPoint, E> {
+                FixedPoint::from_decimal_str(s).map_err(E::custom)
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, n: i64) -> Result<FixedPoint, E> {
+                self.visit_str(&n.to_string())
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, n: u64) -> Result<FixedPoint, E> {
+                self.visit_str(&n.to_string())
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, n: f64) -> Result<FixedPoint, E> {
+                self.visit_str(&n.to_string())
+            }
+        }
+
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,6 +377,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_bytes_matches_str() {
+        for input in ["1.5", " -0.0001 ", "+7", "42.", "1234.5678"] {
+            assert_eq!(
+                FixedPoint::from_decimal_bytes(input.as_bytes()),
+                FixedPoint::from_decimal_str(input),
+                "{input}"
+            );
+        }
+        for input in [
+            "",
+            ".5",
+            "-",
+            "1.2.3",
+            "1,5",
+            "--1",
+            "1.+5",
+            "99999999999999999999",
+        ] {
+            assert_eq!(
+                FixedPoint::from_decimal_bytes(input.as_bytes()),
+                Err(DomainError::InvalidAmount),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            FixedPoint::from_decimal_bytes(b"922337203685478"),
+            Err(DomainError::Overflow)
+        );
+        assert_eq!(
+            FixedPoint::from_decimal_bytes_rounded(b"1.23456", RoundingPolicy::HalfUp),
+            Ok(FixedPoint(12_346))
+        );
+    }
+
     #[test]
     fn reject_too_many_decimal_places() {
         assert!(FixedPoint::from_decimal_str("1.00001").is_err());
@@ -217,6 +441,46 @@ mod tests {
         assert_eq!(FixedPoint(-1).to_decimal_string(), "-0.0001");
     }
 
+    #[test]
+    fn to_string_with_precision_and_trimming() {
+        let amount = FixedPoint(12_350);
+        assert_eq!(amount.to_decimal_string_with(4, false), "1.2350");
+        assert_eq!(amount.to_decimal_string_with(2, false), "1.24");
+        assert_eq!(amount.to_decimal_string_with(0, false), "1");
+        assert_eq!(amount.to_decimal_string_with(4, true), "1.235");
+        assert_eq!(FixedPoint(20_000).to_decimal_string_with(4, true), "2");
+        // More places than stored are not invented
+        assert_eq!(
+            FixedPoint(15_000).to_decimal_string_with(8, false),
+            "1.5000"
+        );
+        assert_eq!(FixedPoint(-1).to_decimal_string_with(2, false), "0.00");
+    }
+
+    #[test]
+    fn write_decimal_matches_to_string() {
+        for raw in [
+            0,
+            1,
+            -1,
+            15_000,
+            -15_000,
+            1_234_567,
+            99_990_000,
+            i64::MAX,
+            i64::MIN,
+        ] {
+            let mut out = Vec::new();
+            FixedPoint(raw).write_decimal(&mut out);
+            let expected = match raw {
+                // `to_decimal_string` cannot negate i64::MIN
+                i64::MIN => "-922337203685477.5808".to_string(),
+                _ => FixedPoint(raw).to_decimal_string(),
+            };
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+    }
+
     #[test]
     fn round_trip_parsing() {
         let values = vec!["1.0000", "1.5000", "0.0001", "123.4567", "0.0000"];
@@ -227,6 +491,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_rounded_normalizes_excess_precision() {
+        assert_eq!(
+            FixedPoint::from_decimal_str_rounded("1.00005", RoundingPolicy::HalfUp).unwrap(),
+            FixedPoint(10_001)
+        );
+        assert_eq!(
+            FixedPoint::from_decimal_str_rounded("1.25", RoundingPolicy::Reject).unwrap(),
+            FixedPoint(12_500)
+        );
+        assert!(FixedPoint::from_decimal_str_rounded("1.00005", RoundingPolicy::Reject).is_err());
+    }
+
     #[test]
     fn checked_add_works() {
         let a = FixedPoint(10_000);
@@ -260,6 +537,69 @@ mod tests {
         assert_eq!(FixedPoint::zero(), FixedPoint(0));
     }
 
+    #[test]
+    fn checked_mul_ratio_rounds() {
+        let amount = FixedPoint(10_000); // 1.0000
+        assert_eq!(
+            amount.checked_mul_ratio(1, 3, RoundingPolicy::HalfUp),
+            Some(FixedPoint(3_333))
+        );
+        assert_eq!(
+            amount.checked_mul_ratio(2, 3, RoundingPolicy::TowardZero),
+            Some(FixedPoint(6_666))
+        );
+        assert_eq!(amount.checked_mul_ratio(1, 3, RoundingPolicy::Reject), None);
+        assert_eq!(amount.checked_mul_ratio(1, 0, RoundingPolicy::HalfUp), None);
+        assert_eq!(
+            FixedPoint(i64::MAX).checked_mul_ratio(2, 1, RoundingPolicy::HalfUp),
+            None
+        );
+    }
+
+    #[test]
+    fn checked_mul_and_div_by_decimals() {
+        let amount = FixedPoint(1_000_000); // 100.0000
+        let rate = FixedPoint(12_345); // 1.2345
+        assert_eq!(
+            amount.checked_mul(rate, RoundingPolicy::Reject),
+            Some(FixedPoint(1_234_500))
+        );
+        assert_eq!(
+            FixedPoint(5).checked_mul(FixedPoint(5_000), RoundingPolicy::HalfEven),
+            Some(FixedPoint(2))
+        );
+        assert_eq!(
+            FixedPoint(5).checked_mul(FixedPoint(5_000), RoundingPolicy::HalfUp),
+            Some(FixedPoint(3))
+        );
+        assert_eq!(
+            amount.checked_div(FixedPoint(30_000), RoundingPolicy::HalfUp),
+            Some(FixedPoint(333_333))
+        );
+        assert_eq!(
+            amount.checked_div(FixedPoint(0), RoundingPolicy::HalfUp),
+            None
+        );
+    }
+
+    #[test]
+    fn checked_percentage_applies_fee() {
+        let amount = FixedPoint(1_000_000); // 100.0000
+        assert_eq!(
+            amount.checked_percentage(FixedPoint(25_000), RoundingPolicy::Reject),
+            Some(FixedPoint(25_000)) // 2.5% of 100 = 2.5
+        );
+        // Less than half a minor unit rounds to zero
+        assert_eq!(
+            FixedPoint(1).checked_percentage(FixedPoint(15_000), RoundingPolicy::HalfUp),
+            Some(FixedPoint(0))
+        );
+        assert_eq!(
+            FixedPoint(-333).checked_percentage(FixedPoint(150_000), RoundingPolicy::HalfUp),
+            Some(FixedPoint(-50))
+        );
+    }
+
     #[test]
     fn add_operator() {
         let a = FixedPoint(10_000);
@@ -285,4 +625,25 @@ mod tests {
     fn default_is_zero() {
         assert_eq!(FixedPoint::default(), FixedPoint(0));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_as_a_decimal_string() {
+        let amount = FixedPoint(-15_000);
+
+        assert_eq!(serde_json::to_string(&amount).unwrap(), "\"-1.5000\"");
+        assert_eq!(
+            serde_json::from_str::<FixedPoint>("\"-1.5\"").unwrap(),
+            amount
+        );
+        assert_eq!(
+            serde_json::from_str::<FixedPoint>("2").unwrap(),
+            FixedPoint(20_000)
+        );
+        assert_eq!(
+            serde_json::from_str::<FixedPoint>("0.25").unwrap(),
+            FixedPoint(2_500)
+        );
+        assert!(serde_json::from_str::<FixedPoint>("\"1.00001\"").is_err());
+    }
 }
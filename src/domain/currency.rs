@@ -0,0 +1,176 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::account::ClientAccount;
+use super::amount::AmountType;
+use super::error::DomainError;
+
+/// ISO 4217-style three-letter currency code (stored uppercase)
+///
+/// Transactions without a currency use the account's base currency, which is
+/// what the original single-currency input format implies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CurrencyCode([u8; 3]);
+
+impl CurrencyCode {
+    /// Get the code as a string slice
+    pub fn as_str(&self) -> &str {
+        // Constructed from ASCII letters only
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+}
+
+impl FromStr for CurrencyCode {
+    type Err = DomainError;
+
+    /// Parse a three-letter code, case-insensitively
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.trim().as_bytes();
+        match bytes {
+            [a, b, c] if bytes.iter().all(u8::is_ascii_alphabetic) => Ok(Self([
+                a.to_ascii_uppercase(),
+                b.to_ascii_uppercase(),
+                c.to_ascii_uppercase(),
+            ])),
+            _ => Err(DomainError::InvalidCurrency),
+        }
+    }
+}
+
+impl fmt::Display for CurrencyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Serialized as the three-letter code
+#[cfg(feature = "serde")]
+impl serde::Serialize for CurrencyCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CurrencyCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Available and held funds in a single non-base currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CurrencyBalance<A: AmountType> {
+    pub available: A,
+    pub held: A,
+}
+
+impl<A: AmountType> CurrencyBalance<A> {
+    /// Get total funds (available + held)
+    pub fn total(&self) -> A {
+        self.available + self.held
+    }
+}
+
+/// Apply an operation to the balances of one currency
+///
+/// `None` operates on the base currency directly. For any other currency the
+/// currency's balances are swapped into the account's available/held fields
+/// for the duration of `op`, so every existing operation (dispute, chargeback,
+/// ...) works unchanged per currency. Lock state and the disputed set are
+/// shared across currencies.
+pub fn apply_in_currency<A, F>(
+    account: &mut ClientAccount<A>,
+    currency: Option<CurrencyCode>,
+    op: F,
+) -> Result<(), DomainError>
+where
+    A: AmountType,
+    F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>,
+{
+    let Some(currency) = currency else {
+        return op(account);
+    };
+
+    let balance = account.currency_balance(currency);
+    let base = account.swap_balances(balance);
+    let result = op(account);
+    let updated = account.swap_balances(base);
+
+    // Only successful operations create or change a currency balance
+    if result.is_ok() {
+        account.set_currency_balance(currency, updated);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, apply_deposit, apply_dispute, apply_withdrawal};
+
+    fn usd() -> CurrencyCode {
+        "usd".parse().unwrap()
+    }
+
+    #[test]
+    fn parses_case_insensitively() {
+        assert_eq!(usd().as_str(), "USD");
+        assert_eq!(" eur ".parse::<CurrencyCode>().unwrap().to_string(), "EUR");
+    }
+
+    #[test]
+    fn rejects_invalid_codes() {
+        assert!("US".parse::<CurrencyCode>().is_err());
+        assert!("USDT".parse::<CurrencyCode>().is_err());
+        assert!("U5D".parse::<CurrencyCode>().is_err());
+    }
+
+    #[test]
+    fn currencies_are_isolated_from_base() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        apply_in_currency(&mut account, Some(usd()), |acc| {
+            apply_deposit(acc, FixedPoint::from_raw(3_000))
+        })
+        .unwrap();
+
+        assert_eq!(account.available(), FixedPoint::from_raw(10_000));
+        assert_eq!(
+            account.currency_balance(usd()).available,
+            FixedPoint::from_raw(3_000)
+        );
+    }
+
+    #[test]
+    fn failed_operation_leaves_currency_untouched() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+
+        let result = apply_in_currency(&mut account, Some(usd()), |acc| {
+            apply_withdrawal(acc, FixedPoint::from_raw(1))
+        });
+
+        assert_eq!(result, Err(DomainError::InsufficientFunds));
+        assert_eq!(account.available(), FixedPoint::from_raw(10_000));
+        assert_eq!(account.currency_balances().count(), 0);
+    }
+
+    #[test]
+    fn dispute_holds_funds_in_its_currency() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        apply_in_currency(&mut account, Some(usd()), |acc| {
+            apply_deposit(acc, FixedPoint::from_raw(5_000))?;
+            apply_dispute(acc, 1, FixedPoint::from_raw(5_000))
+        })
+        .unwrap();
+
+        let balance = account.currency_balance(usd());
+        assert_eq!(balance.held, FixedPoint::from_raw(5_000));
+        assert_eq!(account.held(), FixedPoint::zero());
+        assert!(account.is_disputed(1));
+    }
+}
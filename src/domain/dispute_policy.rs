@@ -0,0 +1,218 @@
+/// Which dispute-lifecycle operations a locked account still accepts
+///
+/// Client-initiated operations (deposits, withdrawals, transfers, holds,
+/// captures and releases) are always rejected on a locked account; only the
+/// dispute lifecycle, which the partner drives, is configurable here. The
+/// default matches the original engine behavior: new disputes and resolves
+/// are rejected, while chargebacks of disputes that were already open still
+/// settle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockedAccountPolicy {
+    /// Accept new disputes (defaults to false)
+    pub allow_disputes: bool,
+    /// Accept resolves of open disputes (defaults to false)
+    pub allow_resolves: bool,
+    /// Accept chargebacks of open disputes (defaults to true)
+    pub allow_chargebacks: bool,
+}
+
+impl Default for LockedAccountPolicy {
+    fn default() -> Self {
+        Self {
+            allow_disputes: false,
+            allow_resolves: false,
+            allow_chargebacks: true,
+        }
+    }
+}
+
+impl LockedAccountPolicy {
+    /// Reject every operation on a locked account
+    pub fn frozen() -> Self {
+        Self {
+            allow_disputes: false,
+            allow_resolves: false,
+            allow_chargebacks: false,
+        }
+    }
+
+    /// Settle disputes that were open at lock time, but accept no new ones
+    pub fn settle_open_disputes() -> Self {
+        Self {
+            allow_disputes: false,
+            allow_resolves: true,
+            allow_chargebacks: true,
+        }
+    }
+
+    /// Accept new disputes on locked accounts
+    pub fn with_disputes(mut self, enabled: bool) -> Self {
+        self.allow_disputes = enabled;
+        self
+    }
+
+    /// Accept resolves on locked accounts
+    pub fn with_resolves(mut self, enabled: bool) -> Self {
+        self.allow_resolves = enabled;
+        self
+    }
+
+    /// Accept chargebacks on locked accounts
+    pub fn with_chargebacks(mut self, enabled: bool) -> Self {
+        self.allow_chargebacks = enabled;
+        self
+    }
+}
+
+/// Dispute semantics selectable per payment scheme
+///
+/// The default matches the original engine behavior: disputes need enough
+/// available funds, locked accounts reject disputes and resolves (see
+/// `LockedAccountPolicy`), and a resolved transaction may be disputed again
+/// any number of times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisputePolicy {
+    /// Allow a dispute to drive available funds negative (defaults to false)
+    pub allow_negative_available: bool,
+    /// Dispute-lifecycle operations accepted on locked accounts
+    pub locked: LockedAccountPolicy,
+    /// Allow a resolved transaction to be disputed again (defaults to true)
+    pub allow_redispute: bool,
+    /// Most disputes a transaction may receive, counting the first (defaults
+    /// to no limit)
+    pub max_disputes: Option<u32>,
+    /// Settle disputes of spent funds against available funds, which may go
+    /// negative (defaults to false)
+    pub allow_negative_balance: bool,
+    /// Unlock the account when a chargeback on it is reversed (defaults to false)
+    pub unlock_on_reversal: bool,
+}
+
+impl Default for DisputePolicy {
+    fn default() -> Self {
+        Self {
+            allow_negative_available: false,
+            locked: LockedAccountPolicy::default(),
+            allow_redispute: true,
+            max_disputes: None,
+            allow_negative_balance: false,
+            unlock_on_reversal: false,
+        }
+    }
+}
+
+impl DisputePolicy {
+    /// Allow disputes to hold more than the available funds
+    ///
+    /// Schemes where the issuer has already refunded the cardholder hold the
+    /// full disputed amount even if the client has spent it.
+    pub fn with_negative_available(mut self, enabled: bool) -> Self {
+        self.allow_negative_available = enabled;
+        self
+    }
+
+    /// Accept disputes and resolves on locked accounts
+    ///
+    /// Shorthand for setting both in the `LockedAccountPolicy`; chargebacks
+    /// are left as they are.
+    pub fn with_locked_accounts(mut self, enabled: bool) -> Self {
+        self.locked = self.locked.with_disputes(enabled).with_resolves(enabled);
+        self
+    }
+
+    /// Choose which operations locked accounts accept
+    pub fn with_locked_account_policy(mut self, policy: LockedAccountPolicy) -> Self {
+        self.locked = policy;
+        self
+    }
+
+    /// Allow resolved transactions to be disputed again
+    pub fn with_redispute(mut self, enabled: bool) -> Self {
+        self.allow_redispute = enabled;
+        self
+    }
+
+    /// Limit how many times a transaction may be disputed, counting the first
+    ///
+    /// For schemes that allow a second presentment but no more, use 2. Once
+    /// the limit is reached further disputes fail with `DisputeLimitReached`;
+    /// `with_redispute(false)` behaves like a limit of 1.
+    pub fn with_max_disputes(mut self, max: u32) -> Self {
+        self.max_disputes = Some(max);
+        self
+    }
+
+    /// Let disputes of already-spent deposits run to completion
+    ///
+    /// A dispute holds whatever part of the amount is still available. A
+    /// chargeback then removes the held funds and takes the shortfall from
+    /// available funds, leaving the balance negative (credit owed by the
+    /// client), and a resolve releases what is held. Without this, such
+    /// disputes fail with `InsufficientFunds`.
+    pub fn with_negative_balance(mut self, enabled: bool) -> Self {
+        self.allow_negative_balance = enabled;
+        self
+    }
+
+    /// Lift the chargeback's lock when the chargeback is reversed
+    ///
+    /// Representment restores the charged-back funds either way; whether
+    /// the account may trade again is up to the scheme. Accounts locked for
+    /// more than one chargeback are unlocked by any reversal.
+    pub fn with_unlock_on_reversal(mut self, enabled: bool) -> Self {
+        self.unlock_on_reversal = enabled;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_original_semantics() {
+        let policy = DisputePolicy::default();
+
+        assert!(!policy.allow_negative_available);
+        assert!(!policy.locked.allow_disputes);
+        assert!(!policy.locked.allow_resolves);
+        assert!(policy.locked.allow_chargebacks);
+        assert!(policy.allow_redispute);
+        assert_eq!(policy.max_disputes, None);
+        assert!(!policy.allow_negative_balance);
+        assert!(!policy.unlock_on_reversal);
+    }
+
+    #[test]
+    fn builders_set_each_rule() {
+        let policy = DisputePolicy::default()
+            .with_negative_available(true)
+            .with_locked_accounts(true)
+            .with_redispute(false)
+            .with_max_disputes(3)
+            .with_negative_balance(true)
+            .with_unlock_on_reversal(true);
+
+        assert!(policy.allow_negative_available);
+        assert!(policy.unlock_on_reversal);
+        assert!(policy.allow_negative_balance);
+        assert!(policy.locked.allow_disputes);
+        assert!(policy.locked.allow_resolves);
+        assert!(!policy.allow_redispute);
+        assert_eq!(policy.max_disputes, Some(3));
+    }
+
+    #[test]
+    fn locked_account_presets() {
+        let frozen =
+            DisputePolicy::default().with_locked_account_policy(LockedAccountPolicy::frozen());
+        assert_eq!(
+            frozen.locked,
+            LockedAccountPolicy::default().with_chargebacks(false)
+        );
+
+        let settle = LockedAccountPolicy::settle_open_disputes();
+        assert!(!settle.allow_disputes);
+        assert!(settle.allow_resolves && settle.allow_chargebacks);
+    }
+}
@@ -20,6 +20,48 @@ pub enum DomainError {
 
     #[error("Transaction is not disputed")]
     NotDisputed,
+
+    #[error("Transaction was already resolved")]
+    AlreadyResolved,
+
+    #[error("Cannot transfer to the same account")]
+    SelfTransfer,
+
+    #[error("Account is not locked")]
+    NotLocked,
+
+    #[error("Invalid currency code")]
+    InvalidCurrency,
+
+    #[error("Transaction has an active hold")]
+    HoldActive,
+
+    #[error("Transaction has no active hold")]
+    NoActiveHold,
+
+    #[error("Fee exceeds the transaction amount")]
+    FeeExceedsAmount,
+
+    #[error("Only deposits can be disputed")]
+    NotDisputable,
+
+    #[error("Transaction was already charged back")]
+    AlreadyChargedBack,
+
+    #[error("Transaction is not charged back")]
+    NotChargedBack,
+
+    #[error("Chargeback was already reversed")]
+    AlreadyReversed,
+
+    #[error("Transaction has reached its dispute limit")]
+    DisputeLimitReached,
+
+    #[error("Invalid tag key")]
+    InvalidTag,
+
+    #[error("Account invariant violated: {0}")]
+    InvariantViolated(&'static str),
 }
 
 #[cfg(test)]
@@ -43,6 +85,27 @@ mod tests {
             DomainError::NotDisputed.to_string(),
             "Transaction is not disputed"
         );
+        assert_eq!(
+            DomainError::AlreadyResolved.to_string(),
+            "Transaction was already resolved"
+        );
+        assert_eq!(
+            DomainError::SelfTransfer.to_string(),
+            "Cannot transfer to the same account"
+        );
+        assert_eq!(DomainError::NotLocked.to_string(), "Account is not locked");
+        assert_eq!(
+            DomainError::HoldActive.to_string(),
+            "Transaction has an active hold"
+        );
+        assert_eq!(
+            DomainError::NoActiveHold.to_string(),
+            "Transaction has no active hold"
+        );
+        assert_eq!(
+            DomainError::InvalidCurrency.to_string(),
+            "Invalid currency code"
+        );
     }
 
     #[test]
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use super::account::ClientAccount;
+use super::amount::AmountType;
+use super::error::DomainError;
+use super::operations::{apply_deposit, apply_withdrawal, can_spend};
+use super::rounding::RoundingPolicy;
+use super::transaction::ClientId;
+
+/// Transaction types a fee can be charged on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeeType {
+    Deposit,
+    Withdrawal,
+}
+
+/// Flat and/or percentage fee on one transaction
+///
+/// The fee is `flat + amount * percent / 100`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fee<A: AmountType> {
+    pub flat: A,
+    /// Percentage of the amount (e.g. `2.5` for 2.5%)
+    pub percent: A,
+}
+
+impl<A: AmountType> Fee<A> {
+    /// A fixed fee per transaction
+    pub fn flat(amount: A) -> Self {
+        Self {
+            flat: amount,
+            percent: A::zero(),
+        }
+    }
+
+    /// A fee proportional to the transaction amount
+    pub fn percentage(percent: A) -> Self {
+        Self {
+            flat: A::zero(),
+            percent,
+        }
+    }
+
+    /// Add a percentage on top of the flat part
+    pub fn with_percentage(mut self, percent: A) -> Self {
+        self.percent = percent;
+        self
+    }
+
+    /// Fee charged on `amount`, or None on overflow (or an inexact
+    /// percentage under `RoundingPolicy::Reject`)
+    pub fn compute(&self, amount: A, rounding: RoundingPolicy) -> Option<A> {
+        let proportional = amount.checked_percentage(self.percent, rounding)?;
+        self.flat.checked_add(proportional)
+    }
+}
+
+/// Fees per transaction type and client tier, credited to one fee account
+///
+/// Clients are placed in tiers with `with_client_tier`, or by a tag on their
+/// account once `with_tier_tag` names it (e.g. a `tier` tag set by the admin
+/// `tag` transaction); an explicit client tier wins over the tag. Clients
+/// without a tier, and tiers without a fee for a transaction type, pay the
+/// schedule's default fee for that type (if any). The fee account is
+/// an ordinary client account, so collected fees appear in snapshots under
+/// its client id; it is never charged fees itself.
+///
+/// # Example
+/// ```rust,ignore
+/// let schedule = FeeSchedule::new(0)
+///     .with_fee(FeeType::Withdrawal, Fee::flat(FixedPoint::from_raw(5_000)))
+///     .with_tier_fee("premium", FeeType::Withdrawal, Fee::percentage(FixedPoint::from_raw(2_500)))
+///     .with_client_tier(42, "premium");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FeeSchedule<A: AmountType> {
+    fee_account: ClientId,
+    defaults: HashMap<FeeType, Fee<A>>,
+    tiers: HashMap<(String, FeeType), Fee<A>>,
+    client_tiers: HashMap<ClientId, String>,
+    tier_tag: Option<String>,
+    rounding: RoundingPolicy,
+}
+
+impl<A: AmountType> FeeSchedule<A> {
+    /// Create an empty schedule crediting fees to `fee_account`
+    ///
+    /// Percentages round half-up to `A`'s precision by default.
+    pub fn new(fee_account: ClientId) -> Self {
+        Self {
+            fee_account,
+            defaults: HashMap::new(),
+            tiers: HashMap::new(),
+            client_tiers: HashMap::new(),
+            tier_tag: None,
+            rounding: RoundingPolicy::HalfUp,
+        }
+    }
+
+    /// Charge `fee` on every transaction of `fee_type` without a tier fee
+    pub fn with_fee(mut self, fee_type: FeeType, fee: Fee<A>) -> Self {
+        self.defaults.insert(fee_type, fee);
+        self
+    }
+
+    /// Charge `fee` on transactions of `fee_type` by clients in `tier`
+    pub fn with_tier_fee(
+        mut self,
+        tier: impl Into<String>,
+        fee_type: FeeType,
+        fee: Fee<A>,
+    ) -> Self {
+        self.tiers.insert((tier.into(), fee_type), fee);
+        self
+    }
+
+    /// Place a client in a tier
+    pub fn with_client_tier(mut self, client_id: ClientId, tier: impl Into<String>) -> Self {
+        self.client_tiers.insert(client_id, tier.into());
+        self
+    }
+
+    /// Take the tier of clients without an explicit tier from their account
+    /// tag `key` (see `ClientAccount::tag`)
+    pub fn with_tier_tag(mut self, key: impl Into<String>) -> Self {
+        self.tier_tag = Some(key.into());
+        self
+    }
+
+    /// Account tag holding client tiers, if set by `with_tier_tag`
+    pub fn tier_tag(&self) -> Option<&str> {
+        self.tier_tag.as_deref()
+    }
+
+    /// Set how percentage fees are rounded
+    pub fn with_rounding(mut self, rounding: RoundingPolicy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Client account that collects fees
+    pub fn fee_account(&self) -> ClientId {
+        self.fee_account
+    }
+
+    /// Fee a client pays on a transaction of `amount` (zero if none applies)
+    ///
+    /// Ignores the tier tag; see `fee_for_account`.
+    pub fn fee_for(
+        &self,
+        fee_type: FeeType,
+        client_id: ClientId,
+        amount: A,
+    ) -> Result<A, DomainError> {
+        self.fee_in_tier(fee_type, client_id, None, amount)
+    }
+
+    /// Fee the account's client pays on a transaction of `amount`, placing
+    /// it in a tier by its tier tag if it has no explicit tier
+    pub fn fee_for_account(
+        &self,
+        fee_type: FeeType,
+        account: &ClientAccount<A>,
+        amount: A,
+    ) -> Result<A, DomainError> {
+        let tagged = self.tier_tag.as_deref().and_then(|key| account.tag(key));
+        self.fee_in_tier(fee_type, account.client_id(), tagged, amount)
+    }
+
+    fn fee_in_tier(
+        &self,
+        fee_type: FeeType,
+        client_id: ClientId,
+        tagged: Option<&str>,
+        amount: A,
+    ) -> Result<A, DomainError> {
+        if client_id == self.fee_account {
+            return Ok(A::zero());
+        }
+
+        let fee = self
+            .client_tiers
+            .get(&client_id)
+            .map(String::as_str)
+            .or(tagged)
+            .and_then(|tier| self.tiers.get(&(tier.to_string(), fee_type)))
+            .or_else(|| self.defaults.get(&fee_type));
+
+        match fee {
+            Some(fee) => fee
+                .compute(amount, self.rounding)
+                .ok_or(DomainError::Overflow),
+            None => Ok(A::zero()),
+        }
+    }
+}
+
+/// Deposit `amount` and move `fee` out of it into the fee account
+///
+/// The client is credited `amount - fee`. Fails without changing either
+/// account if the deposit is invalid or the fee exceeds the amount.
+pub fn apply_deposit_with_fee<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    fee_account: &mut ClientAccount<A>,
+    amount: A,
+    fee: A,
+) -> Result<(), DomainError> {
+    if fee > amount {
+        return Err(DomainError::FeeExceedsAmount);
+    }
+
+    let mut updated = account.clone();
+    apply_deposit(&mut updated, amount)?;
+    charge_fee(&mut updated, fee_account, fee)?;
+    *account = updated;
+    Ok(())
+}
+
+/// Withdraw `amount` and charge `fee` on top of it
+///
+/// The client needs `amount + fee` available, including any credit line.
+/// Fails without changing either account if the withdrawal is invalid or
+/// funds are insufficient.
+pub fn apply_withdrawal_with_fee<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    fee_account: &mut ClientAccount<A>,
+    amount: A,
+    fee: A,
+) -> Result<(), DomainError> {
+    let total = amount.checked_add(fee).ok_or(DomainError::Overflow)?;
+    if !can_spend(account, total) {
+        return Err(DomainError::InsufficientFunds);
+    }
+
+    let mut updated = account.clone();
+    apply_withdrawal(&mut updated, amount)?;
+    charge_fee(&mut updated, fee_account, fee)?;
+    *account = updated;
+    Ok(())
+}
+
+/// Move `fee` from the client's available funds to the fee account's
+///
+/// The fee account is credited even when locked; it belongs to the operator.
+fn charge_fee<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    fee_account: &mut ClientAccount<A>,
+    fee: A,
+) -> Result<(), DomainError> {
+    if fee <= A::zero() {
+        return Ok(());
+    }
+
+    let available = account
+        .available()
+        .checked_sub(fee)
+        .ok_or(DomainError::Overflow)?;
+    let collected = fee_account
+        .available()
+        .checked_add(fee)
+        .filter(|available| available.checked_add(fee_account.held()).is_some())
+        .ok_or(DomainError::Overflow)?;
+
+    account.set_available(available);
+    fee_account.set_available(collected);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn amount(raw: i64) -> FixedPoint {
+        FixedPoint::from_raw(raw)
+    }
+
+    fn funded(client_id: ClientId, raw: i64) -> ClientAccount<FixedPoint> {
+        let mut account = ClientAccount::new(client_id);
+        account.set_available(amount(raw));
+        account
+    }
+
+    #[test]
+    fn fee_combines_flat_and_percentage() {
+        let fee = Fee::flat(amount(5_000)).with_percentage(amount(10_000));
+
+        // 0.5 + 1% of 100.0
+        assert_eq!(
+            fee.compute(amount(1_000_000), RoundingPolicy::HalfUp),
+            Some(amount(15_000))
+        );
+    }
+
+    #[test]
+    fn tier_fees_override_defaults() {
+        let schedule = FeeSchedule::new(0)
+            .with_fee(FeeType::Withdrawal, Fee::flat(amount(10_000)))
+            .with_tier_fee("premium", FeeType::Withdrawal, Fee::flat(amount(1_000)))
+            .with_client_tier(7, "premium");
+
+        let fee = |client_id, fee_type| schedule.fee_for(fee_type, client_id, amount(50_000));
+        assert_eq!(fee(1, FeeType::Withdrawal), Ok(amount(10_000)));
+        assert_eq!(fee(7, FeeType::Withdrawal), Ok(amount(1_000)));
+        assert_eq!(fee(1, FeeType::Deposit), Ok(FixedPoint::zero()));
+        // The fee account itself is never charged
+        assert_eq!(fee(0, FeeType::Withdrawal), Ok(FixedPoint::zero()));
+    }
+
+    #[test]
+    fn tier_tag_places_untiered_clients() {
+        let schedule = FeeSchedule::new(0)
+            .with_fee(FeeType::Withdrawal, Fee::flat(amount(10_000)))
+            .with_tier_fee("premium", FeeType::Withdrawal, Fee::flat(amount(1_000)))
+            .with_tier_fee("staff", FeeType::Withdrawal, Fee::flat(amount(0)))
+            .with_client_tier(7, "staff")
+            .with_tier_tag("tier");
+
+        let tagged = |client_id| {
+            let mut account = ClientAccount::new(client_id);
+            crate::domain::apply_set_tag(&mut account, "tier", Some("premium")).unwrap();
+            account
+        };
+        let fee = |account: &ClientAccount<FixedPoint>| {
+            schedule.fee_for_account(FeeType::Withdrawal, account, amount(50_000))
+        };
+        assert_eq!(fee(&ClientAccount::new(1)), Ok(amount(10_000)));
+        assert_eq!(fee(&tagged(1)), Ok(amount(1_000)));
+        // An explicit tier wins over the tag
+        assert_eq!(fee(&tagged(7)), Ok(FixedPoint::zero()));
+        assert_eq!(
+            schedule.fee_for(FeeType::Withdrawal, 1, amount(50_000)),
+            Ok(amount(10_000))
+        );
+    }
+
+    #[test]
+    fn deposit_with_fee_credits_net_amount() {
+        let mut account = ClientAccount::new(1);
+        let mut fees = ClientAccount::new(0);
+
+        apply_deposit_with_fee(&mut account, &mut fees, amount(100_000), amount(2_000)).unwrap();
+
+        assert_eq!(account.available(), amount(98_000));
+        assert_eq!(fees.available(), amount(2_000));
+    }
+
+    #[test]
+    fn deposit_fee_larger_than_amount_is_rejected() {
+        let mut account = ClientAccount::new(1);
+        let mut fees = ClientAccount::new(0);
+
+        let result = apply_deposit_with_fee(&mut account, &mut fees, amount(1_000), amount(2_000));
+
+        assert_eq!(result, Err(DomainError::FeeExceedsAmount));
+        assert_eq!(account.available(), FixedPoint::zero());
+        assert_eq!(fees.available(), FixedPoint::zero());
+    }
+
+    #[test]
+    fn withdrawal_needs_funds_for_amount_and_fee() {
+        let mut account = funded(1, 10_000);
+        let mut fees = ClientAccount::new(0);
+
+        assert_eq!(
+            apply_withdrawal_with_fee(&mut account, &mut fees, amount(10_000), amount(500)),
+            Err(DomainError::InsufficientFunds)
+        );
+        assert_eq!(account.available(), amount(10_000));
+
+        apply_withdrawal_with_fee(&mut account, &mut fees, amount(9_500), amount(500)).unwrap();
+        assert_eq!(account.available(), FixedPoint::zero());
+        assert_eq!(fees.available(), amount(500));
+    }
+}
@@ -1,14 +1,32 @@
 pub mod account;
+pub mod account_op;
 pub mod amount;
+pub mod currency;
+pub mod dispute_policy;
 pub mod error;
+pub mod fee;
 pub mod operations;
+pub mod rounding;
 pub mod transaction;
+pub mod tx_set;
 
 // Re-export commonly used types
 pub use account::ClientAccount;
+pub use account_op::{AccountOp, PairOp};
 pub use amount::{AmountType, FixedPoint};
+pub use currency::{CurrencyBalance, CurrencyCode, apply_in_currency};
+pub use dispute_policy::{DisputePolicy, LockedAccountPolicy};
 pub use error::DomainError;
+pub use fee::{Fee, FeeSchedule, FeeType, apply_deposit_with_fee, apply_withdrawal_with_fee};
 pub use operations::{
-    apply_chargeback, apply_deposit, apply_dispute, apply_resolve, apply_withdrawal,
+    apply_adjustment, apply_capture, apply_chargeback, apply_chargeback_reversal,
+    apply_chargeback_with_policy, apply_deposit, apply_dispute, apply_dispute_with_policy,
+    apply_hold, apply_release, apply_resolve, apply_resolve_with_policy, apply_set_credit_limit,
+    apply_set_tag, apply_transfer, apply_unlock, apply_withdrawal,
 };
-pub use transaction::{Transaction, TransactionRecord};
+pub use rounding::{InputPrecision, RoundingPolicy};
+pub use transaction::{
+    ClientId, TimestampedTransaction, Transaction, TransactionId, TransactionRecord, TxKind,
+    TxState,
+};
+pub use tx_set::TxIdSet;
@@ -1,6 +1,8 @@
 use super::account::ClientAccount;
 use super::amount::AmountType;
+use super::dispute_policy::DisputePolicy;
 use super::error::DomainError;
+use super::transaction::TransactionId;
 
 /// Apply a deposit to an account
 pub fn apply_deposit<A: AmountType>(
@@ -17,10 +19,11 @@ pub fn apply_deposit<A: AmountType>(
         return Err(DomainError::AccountLocked);
     }
 
-    // Add to available with overflow check
+    // Add to available with overflow check, keeping the total representable
     let new_available = account
         .available()
         .checked_add(amount)
+        .filter(|available| available.checked_add(account.held()).is_some())
         .ok_or(DomainError::Overflow)?;
 
     account.set_available(new_available);
@@ -42,8 +45,8 @@ pub fn apply_withdrawal<A: AmountType>(
         return Err(DomainError::AccountLocked);
     }
 
-    // Check sufficient funds
-    if account.available() < amount {
+    // Check sufficient funds, including any credit line
+    if !can_spend(account, amount) {
         return Err(DomainError::InsufficientFunds);
     }
 
@@ -60,11 +63,25 @@ pub fn apply_withdrawal<A: AmountType>(
 /// Apply a dispute to an account (move funds from available to held)
 pub fn apply_dispute<A: AmountType>(
     account: &mut ClientAccount<A>,
-    tx_id: u32,
+    tx_id: TransactionId,
     amount: A,
 ) -> Result<(), DomainError> {
+    apply_dispute_with_policy(account, tx_id, amount, &DisputePolicy::default()).map(|_| ())
+}
+
+/// Apply a dispute to an account under the given dispute semantics
+///
+/// Returns the amount moved to held, which is less than `amount` when a
+/// spent deposit is only partly held; resolves and chargebacks of the dispute
+/// take that amount.
+pub fn apply_dispute_with_policy<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    tx_id: TransactionId,
+    amount: A,
+    policy: &DisputePolicy,
+) -> Result<A, DomainError> {
     // Check account is not locked
-    if account.is_locked() {
+    if account.is_locked() && !policy.locked.allow_disputes {
         return Err(DomainError::AccountLocked);
     }
 
@@ -73,11 +90,20 @@ pub fn apply_dispute<A: AmountType>(
         return Err(DomainError::AlreadyDisputed);
     }
 
-    // Check sufficient available funds
-    if account.available() < amount {
-        return Err(DomainError::InsufficientFunds);
+    // Authorizations are not settled funds and cannot be disputed
+    if account.has_active_hold(tx_id) {
+        return Err(DomainError::HoldActive);
     }
 
+    // Check sufficient available funds; spent funds can only be partly held
+    let amount = if account.available() >= amount || policy.allow_negative_available {
+        amount
+    } else if policy.allow_negative_balance {
+        account.available().max(A::zero())
+    } else {
+        return Err(DomainError::InsufficientFunds);
+    };
+
     // Move from available to held
     let new_available = account
         .available()
@@ -93,17 +119,31 @@ pub fn apply_dispute<A: AmountType>(
     account.set_held(new_held);
     account.add_disputed(tx_id);
 
-    Ok(())
+    Ok(amount)
 }
 
 /// Apply a resolve to an account (move funds from held back to available)
 pub fn apply_resolve<A: AmountType>(
     account: &mut ClientAccount<A>,
-    tx_id: u32,
+    tx_id: TransactionId,
     amount: A,
+) -> Result<(), DomainError> {
+    apply_resolve_with_policy(account, tx_id, amount, &DisputePolicy::default())
+}
+
+/// Apply a resolve to an account under the given dispute semantics
+///
+/// `held` is the amount the dispute holds (as returned by
+/// `apply_dispute_with_policy`); only that much is released, so other open
+/// disputes keep their held funds.
+pub fn apply_resolve_with_policy<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    tx_id: TransactionId,
+    held: A,
+    policy: &DisputePolicy,
 ) -> Result<(), DomainError> {
     // Check account is not locked
-    if account.is_locked() {
+    if account.is_locked() && !policy.locked.allow_resolves {
         return Err(DomainError::AccountLocked);
     }
 
@@ -113,19 +153,19 @@ pub fn apply_resolve<A: AmountType>(
     }
 
     // Check sufficient held funds
-    if account.held() < amount {
+    if account.held() < held {
         return Err(DomainError::InsufficientFunds);
     }
 
     // Move from held to available
     let new_held = account
         .held()
-        .checked_sub(amount)
+        .checked_sub(held)
         .ok_or(DomainError::Overflow)?;
 
     let new_available = account
         .available()
-        .checked_add(amount)
+        .checked_add(held)
         .ok_or(DomainError::Overflow)?;
 
     account.set_held(new_held);
@@ -138,14 +178,142 @@ pub fn apply_resolve<A: AmountType>(
 /// Apply a chargeback to an account (remove held funds and lock account)
 pub fn apply_chargeback<A: AmountType>(
     account: &mut ClientAccount<A>,
-    tx_id: u32,
+    tx_id: TransactionId,
     amount: A,
 ) -> Result<(), DomainError> {
+    apply_chargeback_with_policy(account, tx_id, amount, amount, &DisputePolicy::default())
+}
+
+/// Apply a chargeback to an account under the given dispute semantics
+///
+/// `held` is the part of `amount` the dispute holds (as returned by
+/// `apply_dispute_with_policy`); it is removed from held funds and the rest
+/// of `amount` from available funds.
+pub fn apply_chargeback_with_policy<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    tx_id: TransactionId,
+    amount: A,
+    held: A,
+    policy: &DisputePolicy,
+) -> Result<(), DomainError> {
+    // Check account is not locked (by default chargebacks still settle)
+    if account.is_locked() && !policy.locked.allow_chargebacks {
+        return Err(DomainError::AccountLocked);
+    }
+
     // Check transaction is disputed
     if !account.is_disputed(tx_id) {
         return Err(DomainError::NotDisputed);
     }
 
+    // Check sufficient held funds; any shortfall may be charged to available
+    if account.held() < held || (held < amount && !policy.allow_negative_balance) {
+        return Err(DomainError::InsufficientFunds);
+    }
+    let from_held = held.min(amount);
+    let shortfall = amount.checked_sub(from_held).ok_or(DomainError::Overflow)?;
+
+    // Remove from held, then from available
+    let new_held = account
+        .held()
+        .checked_sub(from_held)
+        .ok_or(DomainError::Overflow)?;
+
+    let new_available = account
+        .available()
+        .checked_sub(shortfall)
+        .ok_or(DomainError::Overflow)?;
+
+    account.set_held(new_held);
+    account.set_available(new_available);
+    account.lock();
+    account.remove_disputed(tx_id);
+
+    Ok(())
+}
+
+/// Apply a chargeback reversal: the charged-back amount returns to available
+///
+/// Accepted on locked accounts, since the chargeback itself locked them;
+/// the lock is lifted only if `policy.unlock_on_reversal` is set.
+pub fn apply_chargeback_reversal<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    amount: A,
+    policy: &DisputePolicy,
+) -> Result<(), DomainError> {
+    let new_available = account
+        .available()
+        .checked_add(amount)
+        .ok_or(DomainError::Overflow)?;
+
+    account.set_available(new_available);
+    if policy.unlock_on_reversal {
+        account.unlock();
+    }
+
+    Ok(())
+}
+
+/// Apply an authorization hold (reserve available funds into held)
+pub fn apply_hold<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    tx_id: TransactionId,
+    amount: A,
+) -> Result<(), DomainError> {
+    // Validate amount is positive
+    if amount <= A::zero() {
+        return Err(DomainError::InvalidAmount);
+    }
+
+    // Check account is not locked
+    if account.is_locked() {
+        return Err(DomainError::AccountLocked);
+    }
+
+    // Check the authorization is not already held
+    if account.has_active_hold(tx_id) {
+        return Err(DomainError::HoldActive);
+    }
+
+    // Check sufficient funds, including any credit line
+    if !can_spend(account, amount) {
+        return Err(DomainError::InsufficientFunds);
+    }
+
+    // Move from available to held
+    let new_available = account
+        .available()
+        .checked_sub(amount)
+        .ok_or(DomainError::Overflow)?;
+
+    let new_held = account
+        .held()
+        .checked_add(amount)
+        .ok_or(DomainError::Overflow)?;
+
+    account.set_available(new_available);
+    account.set_held(new_held);
+    account.add_hold(tx_id);
+
+    Ok(())
+}
+
+/// Apply a capture to an account (settle held funds as a withdrawal)
+pub fn apply_capture<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    tx_id: TransactionId,
+    amount: A,
+) -> Result<(), DomainError> {
+    // Check account is not locked
+    if account.is_locked() {
+        return Err(DomainError::AccountLocked);
+    }
+
+    // Check the authorization is held
+    if !account.has_active_hold(tx_id) {
+        return Err(DomainError::NoActiveHold);
+    }
+
     // Check sufficient held funds
     if account.held() < amount {
         return Err(DomainError::InsufficientFunds);
@@ -158,16 +326,398 @@ pub fn apply_chargeback<A: AmountType>(
         .ok_or(DomainError::Overflow)?;
 
     account.set_held(new_held);
-    account.lock();
-    account.remove_disputed(tx_id);
+    account.remove_hold(tx_id);
 
     Ok(())
 }
 
+/// Apply a release to an account (return held funds to available)
+pub fn apply_release<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    tx_id: TransactionId,
+    amount: A,
+) -> Result<(), DomainError> {
+    // Check account is not locked
+    if account.is_locked() {
+        return Err(DomainError::AccountLocked);
+    }
+
+    // Check the authorization is held
+    if !account.has_active_hold(tx_id) {
+        return Err(DomainError::NoActiveHold);
+    }
+
+    // Check sufficient held funds
+    if account.held() < amount {
+        return Err(DomainError::InsufficientFunds);
+    }
+
+    // Move from held to available
+    let new_held = account
+        .held()
+        .checked_sub(amount)
+        .ok_or(DomainError::Overflow)?;
+
+    let new_available = account
+        .available()
+        .checked_add(amount)
+        .ok_or(DomainError::Overflow)?;
+
+    account.set_held(new_held);
+    account.set_available(new_available);
+    account.remove_hold(tx_id);
+
+    Ok(())
+}
+
+/// Apply a transfer between two accounts (debit sender, credit receiver)
+///
+/// Both accounts are validated before either is modified, so a failed
+/// transfer leaves both accounts untouched.
+pub fn apply_transfer<A: AmountType>(
+    from: &mut ClientAccount<A>,
+    to: &mut ClientAccount<A>,
+    amount: A,
+) -> Result<(), DomainError> {
+    // Validate amount is positive
+    if amount <= A::zero() {
+        return Err(DomainError::InvalidAmount);
+    }
+
+    // Transfers must move funds between distinct accounts
+    if from.client_id() == to.client_id() {
+        return Err(DomainError::SelfTransfer);
+    }
+
+    // Check neither account is locked
+    if from.is_locked() || to.is_locked() {
+        return Err(DomainError::AccountLocked);
+    }
+
+    // Check sufficient funds on the sending side, including any credit line
+    if !can_spend(from, amount) {
+        return Err(DomainError::InsufficientFunds);
+    }
+
+    let new_from_available = from
+        .available()
+        .checked_sub(amount)
+        .ok_or(DomainError::Overflow)?;
+
+    let new_to_available = to
+        .available()
+        .checked_add(amount)
+        .filter(|available| available.checked_add(to.held()).is_some())
+        .ok_or(DomainError::Overflow)?;
+
+    from.set_available(new_from_available);
+    to.set_available(new_to_available);
+
+    Ok(())
+}
+
+/// Whether `amount` can be withdrawn without taking available funds below
+/// the account's credit limit
+pub(crate) fn can_spend<A: AmountType>(account: &ClientAccount<A>, amount: A) -> bool {
+    account
+        .available()
+        .checked_add(account.credit_limit())
+        .is_none_or(|spendable| spendable >= amount)
+}
+
+/// Apply an administrative credit limit to an account
+///
+/// Withdrawals may then leave available funds as low as `-limit`. Locked
+/// accounts accept new limits too; lowering a limit below the current
+/// overdraft leaves the balance as it is and only blocks further withdrawals.
+pub fn apply_set_credit_limit<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    limit: A,
+) -> Result<(), DomainError> {
+    if limit < A::zero() {
+        return Err(DomainError::InvalidAmount);
+    }
+
+    account.set_credit_limit(limit);
+    Ok(())
+}
+
+/// Apply an administrative tag to an account, or remove it when `value` is None
+///
+/// Keys must be non-empty and contain no `=` (the CSV form is `key=value`).
+/// Locked accounts accept tags too.
+pub fn apply_set_tag<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    key: &str,
+    value: Option<&str>,
+) -> Result<(), DomainError> {
+    if key.trim().is_empty() || key.contains('=') {
+        return Err(DomainError::InvalidTag);
+    }
+
+    account.set_tag(key, value);
+    Ok(())
+}
+
+/// Apply an administrative balance correction to an account
+///
+/// A positive `amount` credits available funds and a negative one debits
+/// them, but never below the account's credit limit. Locked accounts accept
+/// adjustments too, so a frozen balance can be corrected before it is
+/// unlocked.
+pub fn apply_adjustment<A: AmountType>(
+    account: &mut ClientAccount<A>,
+    amount: A,
+) -> Result<(), DomainError> {
+    if amount == A::zero() {
+        return Err(DomainError::InvalidAmount);
+    }
+
+    if amount < A::zero() {
+        let debit = A::zero().checked_sub(amount).ok_or(DomainError::Overflow)?;
+        if !can_spend(account, debit) {
+            return Err(DomainError::InsufficientFunds);
+        }
+    }
+
+    // Keep the total representable either way
+    let new_available = account
+        .available()
+        .checked_add(amount)
+        .filter(|available| available.checked_add(account.held()).is_some())
+        .ok_or(DomainError::Overflow)?;
+
+    account.set_available(new_available);
+    Ok(())
+}
+
+/// Apply an administrative unlock to a locked account
+///
+/// Balances and open disputes are left as they are; only the lock is lifted.
+pub fn apply_unlock<A: AmountType>(account: &mut ClientAccount<A>) -> Result<(), DomainError> {
+    if !account.is_locked() {
+        return Err(DomainError::NotLocked);
+    }
+
+    account.unlock();
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::domain::amount::FixedPoint;
+    use crate::domain::dispute_policy::LockedAccountPolicy;
+
+    #[test]
+    fn dispute_policy_allows_negative_available() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        apply_withdrawal(&mut account, FixedPoint::from_raw(8_000)).unwrap();
+
+        let policy = DisputePolicy::default().with_negative_available(true);
+        apply_dispute_with_policy(&mut account, 1, FixedPoint::from_raw(10_000), &policy).unwrap();
+
+        assert_eq!(account.available(), FixedPoint::from_raw(-8_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(10_000));
+        assert_eq!(account.total(), FixedPoint::from_raw(2_000));
+    }
+
+    #[test]
+    fn negative_balance_charges_back_spent_deposits() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        apply_withdrawal(&mut account, FixedPoint::from_raw(8_000)).unwrap();
+        let amount = FixedPoint::from_raw(10_000);
+
+        assert_eq!(
+            apply_dispute(&mut account.clone(), 1, amount),
+            Err(DomainError::InsufficientFunds)
+        );
+
+        let policy = DisputePolicy::default().with_negative_balance(true);
+        let mut resolved = account.clone();
+        let held = apply_dispute_with_policy(&mut account, 1, amount, &policy).unwrap();
+        assert_eq!(held, FixedPoint::from_raw(2_000));
+        assert_eq!(account.available(), FixedPoint::zero());
+        assert_eq!(account.held(), held);
+
+        apply_dispute_with_policy(&mut resolved, 1, amount, &policy).unwrap();
+        apply_resolve_with_policy(&mut resolved, 1, held, &policy).unwrap();
+        assert_eq!(resolved.available(), FixedPoint::from_raw(2_000));
+        assert_eq!(resolved.held(), FixedPoint::zero());
+
+        assert_eq!(
+            apply_chargeback(&mut account.clone(), 1, amount),
+            Err(DomainError::InsufficientFunds)
+        );
+        assert_eq!(
+            apply_chargeback_with_policy(
+                &mut account.clone(),
+                1,
+                amount,
+                held,
+                &DisputePolicy::default()
+            ),
+            Err(DomainError::InsufficientFunds)
+        );
+        apply_chargeback_with_policy(&mut account, 1, amount, held, &policy).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(-8_000));
+        assert_eq!(account.held(), FixedPoint::zero());
+        assert!(account.is_locked());
+    }
+
+    #[test]
+    fn partly_held_dispute_releases_only_its_own_hold() {
+        let policy = DisputePolicy::default().with_negative_balance(true);
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        apply_deposit(&mut account, FixedPoint::from_raw(5_000)).unwrap();
+
+        // Tx 2 is fully held, leaving only 1.0 of tx 1 to hold
+        let second =
+            apply_dispute_with_policy(&mut account, 2, FixedPoint::from_raw(5_000), &policy)
+                .unwrap();
+        apply_withdrawal(&mut account, FixedPoint::from_raw(9_000)).unwrap();
+        let first =
+            apply_dispute_with_policy(&mut account, 1, FixedPoint::from_raw(10_000), &policy)
+                .unwrap();
+        assert_eq!(first, FixedPoint::from_raw(1_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(6_000));
+
+        // Charging back tx 1 takes its shortfall from available, not tx 2's hold
+        let mut charged_back = account.clone();
+        apply_chargeback_with_policy(
+            &mut charged_back,
+            1,
+            FixedPoint::from_raw(10_000),
+            first,
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(charged_back.available(), FixedPoint::from_raw(-9_000));
+        assert_eq!(charged_back.held(), FixedPoint::from_raw(5_000));
+
+        // Resolving tx 1 leaves tx 2's hold in place
+        apply_resolve_with_policy(&mut account, 1, first, &policy).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(1_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(5_000));
+
+        apply_chargeback_with_policy(
+            &mut account,
+            2,
+            FixedPoint::from_raw(5_000),
+            second,
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(1_000));
+        assert_eq!(account.held(), FixedPoint::zero());
+    }
+
+    #[test]
+    fn dispute_policy_accepts_locked_accounts() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        account.lock();
+
+        let amount = FixedPoint::from_raw(10_000);
+        assert_eq!(
+            apply_dispute(&mut account, 1, amount),
+            Err(DomainError::AccountLocked)
+        );
+
+        let policy = DisputePolicy::default().with_locked_accounts(true);
+        apply_dispute_with_policy(&mut account, 1, amount, &policy).unwrap();
+        apply_resolve_with_policy(&mut account, 1, amount, &policy).unwrap();
+        assert_eq!(account.available(), amount);
+    }
+
+    #[test]
+    fn locked_account_policy_governs_the_dispute_lifecycle() {
+        let amount = FixedPoint::from_raw(10_000);
+        let mut account = ClientAccount::new(1);
+        for tx_id in [1, 2, 3] {
+            apply_deposit(&mut account, amount).unwrap();
+            apply_dispute(&mut account, tx_id, amount).unwrap();
+        }
+        // Chargebacks settle on locked accounts by default; resolves do not
+        apply_chargeback(&mut account, 1, amount).unwrap();
+        apply_chargeback(&mut account, 2, amount).unwrap();
+        assert_eq!(
+            apply_resolve(&mut account, 3, amount),
+            Err(DomainError::AccountLocked)
+        );
+
+        let frozen =
+            DisputePolicy::default().with_locked_account_policy(LockedAccountPolicy::frozen());
+        assert_eq!(
+            apply_chargeback_with_policy(&mut account, 3, amount, amount, &frozen),
+            Err(DomainError::AccountLocked)
+        );
+
+        let settle = DisputePolicy::default()
+            .with_locked_account_policy(LockedAccountPolicy::settle_open_disputes());
+        apply_resolve_with_policy(&mut account, 3, amount, &settle).unwrap();
+        assert_eq!(account.available(), amount);
+        assert_eq!(
+            apply_dispute_with_policy(&mut account, 3, amount, &settle),
+            Err(DomainError::AccountLocked)
+        );
+    }
+
+    #[test]
+    fn hold_then_capture_settles_held_funds() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+
+        apply_hold(&mut account, 2, FixedPoint::from_raw(4_000)).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(6_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(4_000));
+        assert!(account.has_active_hold(2));
+
+        apply_capture(&mut account, 2, FixedPoint::from_raw(4_000)).unwrap();
+        assert_eq!(account.held(), FixedPoint::zero());
+        assert_eq!(account.total(), FixedPoint::from_raw(6_000));
+        assert!(!account.has_active_hold(2));
+
+        assert_eq!(
+            apply_release(&mut account, 2, FixedPoint::from_raw(4_000)),
+            Err(DomainError::NoActiveHold)
+        );
+    }
+
+    #[test]
+    fn hold_then_release_restores_available() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        apply_hold(&mut account, 2, FixedPoint::from_raw(4_000)).unwrap();
+
+        apply_release(&mut account, 2, FixedPoint::from_raw(4_000)).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(10_000));
+        assert_eq!(account.held(), FixedPoint::zero());
+    }
+
+    #[test]
+    fn hold_requires_available_funds_and_blocks_disputes() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+
+        assert_eq!(
+            apply_hold(&mut account, 2, FixedPoint::from_raw(20_000)),
+            Err(DomainError::InsufficientFunds)
+        );
+
+        apply_hold(&mut account, 2, FixedPoint::from_raw(4_000)).unwrap();
+        assert_eq!(
+            apply_hold(&mut account, 2, FixedPoint::from_raw(4_000)),
+            Err(DomainError::HoldActive)
+        );
+        assert_eq!(
+            apply_dispute(&mut account, 2, FixedPoint::from_raw(4_000)),
+            Err(DomainError::HoldActive)
+        );
+    }
 
     #[test]
     fn deposit_increases_available_and_total() {
@@ -389,6 +939,29 @@ mod tests {
         assert!(!account.is_locked());
     }
 
+    #[test]
+    fn chargeback_reversal_restores_funds_and_unlocks_per_policy() {
+        let mut account = ClientAccount::new(1);
+        account.set_held(FixedPoint::from_raw(3_000));
+        account.add_disputed(1);
+        apply_chargeback(&mut account, 1, FixedPoint::from_raw(3_000)).unwrap();
+
+        let mut kept_locked = account.clone();
+        apply_chargeback_reversal(
+            &mut kept_locked,
+            FixedPoint::from_raw(3_000),
+            &DisputePolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(kept_locked.available(), FixedPoint::from_raw(3_000));
+        assert!(kept_locked.is_locked());
+
+        let policy = DisputePolicy::default().with_unlock_on_reversal(true);
+        apply_chargeback_reversal(&mut account, FixedPoint::from_raw(3_000), &policy).unwrap();
+        assert_eq!(account.total(), FixedPoint::from_raw(3_000));
+        assert!(!account.is_locked());
+    }
+
     #[test]
     fn locked_account_rejects_all_mutations() {
         let mut account = ClientAccount::new(1);
@@ -488,4 +1061,217 @@ mod tests {
         assert!(account.is_disputed(2));
         assert!(account.is_disputed(3));
     }
+
+    #[test]
+    fn transfer_moves_available_funds() {
+        let mut from = ClientAccount::new(1);
+        let mut to = ClientAccount::new(2);
+        from.set_available(FixedPoint::from_raw(10_000));
+
+        apply_transfer(&mut from, &mut to, FixedPoint::from_raw(4_000)).unwrap();
+
+        assert_eq!(from.available(), FixedPoint::from_raw(6_000));
+        assert_eq!(to.available(), FixedPoint::from_raw(4_000));
+        assert_eq!(from.total() + to.total(), FixedPoint::from_raw(10_000));
+    }
+
+    #[test]
+    fn transfer_insufficient_funds_fails() {
+        let mut from = ClientAccount::new(1);
+        let mut to = ClientAccount::new(2);
+        from.set_available(FixedPoint::from_raw(1_000));
+
+        let result = apply_transfer(&mut from, &mut to, FixedPoint::from_raw(2_000));
+        assert_eq!(result, Err(DomainError::InsufficientFunds));
+
+        // Both accounts unchanged
+        assert_eq!(from.available(), FixedPoint::from_raw(1_000));
+        assert_eq!(to.available(), FixedPoint::zero());
+    }
+
+    #[test]
+    fn transfer_zero_fails() {
+        let mut from = ClientAccount::new(1);
+        let mut to = ClientAccount::new(2);
+        from.set_available(FixedPoint::from_raw(1_000));
+
+        let result = apply_transfer(&mut from, &mut to, FixedPoint::zero());
+        assert_eq!(result, Err(DomainError::InvalidAmount));
+    }
+
+    #[test]
+    fn transfer_to_same_client_fails() {
+        let mut from = ClientAccount::new(1);
+        let mut to = ClientAccount::new(1);
+        from.set_available(FixedPoint::from_raw(1_000));
+
+        let result = apply_transfer(&mut from, &mut to, FixedPoint::from_raw(500));
+        assert_eq!(result, Err(DomainError::SelfTransfer));
+    }
+
+    #[test]
+    fn transfer_involving_locked_account_fails() {
+        let mut from = ClientAccount::new(1);
+        let mut to = ClientAccount::new(2);
+        from.set_available(FixedPoint::from_raw(10_000));
+        to.lock();
+
+        let result = apply_transfer(&mut from, &mut to, FixedPoint::from_raw(1_000));
+        assert_eq!(result, Err(DomainError::AccountLocked));
+
+        let result = apply_transfer(&mut to, &mut from, FixedPoint::from_raw(1_000));
+        assert_eq!(result, Err(DomainError::AccountLocked));
+
+        assert_eq!(from.available(), FixedPoint::from_raw(10_000));
+    }
+
+    #[test]
+    fn transfer_overflow_on_receiver_leaves_sender_unchanged() {
+        let mut from = ClientAccount::new(1);
+        let mut to = ClientAccount::new(2);
+        from.set_available(FixedPoint::from_raw(10_000));
+        to.set_available(FixedPoint::from_raw(i64::MAX));
+
+        let result = apply_transfer(&mut from, &mut to, FixedPoint::from_raw(1_000));
+        assert_eq!(result, Err(DomainError::Overflow));
+
+        assert_eq!(from.available(), FixedPoint::from_raw(10_000));
+        assert_eq!(to.available(), FixedPoint::from_raw(i64::MAX));
+    }
+
+    #[test]
+    fn deposit_rejected_when_total_would_overflow() {
+        let mut account = ClientAccount::new(1);
+        account.set_available(FixedPoint::from_raw(i64::MAX - 10_000));
+        apply_dispute(&mut account, 1, FixedPoint::from_raw(10_000)).unwrap();
+
+        // Available alone has room, available + held does not
+        let result = apply_deposit(&mut account, FixedPoint::from_raw(20_000));
+        assert_eq!(result, Err(DomainError::Overflow));
+        assert_eq!(account.available(), FixedPoint::from_raw(i64::MAX - 20_000));
+    }
+
+    #[test]
+    fn unlock_reinstates_charged_back_account() {
+        let mut account = ClientAccount::new(1);
+        account.set_available(FixedPoint::from_raw(10_000));
+        apply_dispute(&mut account, 1, FixedPoint::from_raw(3_000)).unwrap();
+        apply_chargeback(&mut account, 1, FixedPoint::from_raw(3_000)).unwrap();
+        assert!(account.is_locked());
+
+        apply_unlock(&mut account).unwrap();
+
+        assert!(!account.is_locked());
+        assert_eq!(account.available(), FixedPoint::from_raw(7_000));
+
+        // Account accepts mutations again
+        apply_deposit(&mut account, FixedPoint::from_raw(1_000)).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(8_000));
+    }
+
+    #[test]
+    fn unlock_on_unlocked_account_fails() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+
+        let result = apply_unlock(&mut account);
+        assert_eq!(result, Err(DomainError::NotLocked));
+    }
+
+    #[test]
+    fn withdrawal_draws_on_credit_limit() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        apply_set_credit_limit(&mut account, FixedPoint::from_raw(50_000)).unwrap();
+
+        apply_withdrawal(&mut account, FixedPoint::from_raw(40_000)).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(-30_000));
+
+        // Only 2.0 of the 5.0 credit line is left
+        let result = apply_withdrawal(&mut account, FixedPoint::from_raw(20_001));
+        assert_eq!(result, Err(DomainError::InsufficientFunds));
+        apply_withdrawal(&mut account, FixedPoint::from_raw(20_000)).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(-50_000));
+        assert_eq!(account.validate(), Ok(()));
+    }
+
+    #[test]
+    fn transfer_draws_on_credit_limit() {
+        let mut from = ClientAccount::new(1);
+        let mut to = ClientAccount::new(2);
+        apply_deposit(&mut from, FixedPoint::from_raw(10_000)).unwrap();
+        apply_set_credit_limit(&mut from, FixedPoint::from_raw(20_000)).unwrap();
+
+        let result = apply_transfer(&mut from, &mut to, FixedPoint::from_raw(30_001));
+        assert_eq!(result, Err(DomainError::InsufficientFunds));
+        apply_transfer(&mut from, &mut to, FixedPoint::from_raw(30_000)).unwrap();
+        assert_eq!(from.available(), FixedPoint::from_raw(-20_000));
+        assert_eq!(to.available(), FixedPoint::from_raw(30_000));
+        assert_eq!(from.validate(), Ok(()));
+    }
+
+    #[test]
+    fn hold_draws_on_credit_limit() {
+        let mut account = ClientAccount::new(1);
+        apply_deposit(&mut account, FixedPoint::from_raw(10_000)).unwrap();
+        apply_set_credit_limit(&mut account, FixedPoint::from_raw(20_000)).unwrap();
+
+        let result = apply_hold(&mut account, 7, FixedPoint::from_raw(30_001));
+        assert_eq!(result, Err(DomainError::InsufficientFunds));
+        apply_hold(&mut account, 7, FixedPoint::from_raw(30_000)).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(-20_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(30_000));
+        assert_eq!(account.validate(), Ok(()));
+    }
+
+    #[test]
+    fn adjustment_credits_and_debits_locked_accounts() {
+        let mut account = ClientAccount::new(1);
+        account.lock();
+
+        apply_adjustment(&mut account, FixedPoint::from_raw(30_000)).unwrap();
+        apply_adjustment(&mut account, FixedPoint::from_raw(-10_000)).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(20_000));
+
+        let result = apply_adjustment(&mut account, FixedPoint::from_raw(-20_001));
+        assert_eq!(result, Err(DomainError::InsufficientFunds));
+        let result = apply_adjustment(&mut account, FixedPoint::zero());
+        assert_eq!(result, Err(DomainError::InvalidAmount));
+        assert_eq!(account.available(), FixedPoint::from_raw(20_000));
+    }
+
+    #[test]
+    fn negative_credit_limit_rejected() {
+        let mut account = ClientAccount::new(1);
+
+        let result = apply_set_credit_limit(&mut account, FixedPoint::from_raw(-1));
+        assert_eq!(result, Err(DomainError::InvalidAmount));
+        assert_eq!(account.credit_limit(), FixedPoint::zero());
+    }
+
+    #[test]
+    fn tags_are_set_replaced_and_removed() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        account.lock();
+
+        apply_set_tag(&mut account, "tier", Some("gold")).unwrap();
+        apply_set_tag(&mut account, "region", Some("eu")).unwrap();
+        apply_set_tag(&mut account, "tier", Some("premium")).unwrap();
+        assert_eq!(account.tag("tier"), Some("premium"));
+        assert_eq!(
+            account.tags().collect::<Vec<_>>(),
+            vec![("region", "eu"), ("tier", "premium")]
+        );
+
+        apply_set_tag(&mut account, "region", None).unwrap();
+        assert_eq!(account.tag("region"), None);
+
+        assert_eq!(
+            apply_set_tag(&mut account, "", Some("x")),
+            Err(DomainError::InvalidTag)
+        );
+        assert_eq!(
+            apply_set_tag(&mut account, "a=b", Some("x")),
+            Err(DomainError::InvalidTag)
+        );
+    }
 }
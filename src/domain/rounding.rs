@@ -0,0 +1,324 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::amount::AmountType;
+use super::error::DomainError;
+
+/// How to handle amounts with more decimal places than the amount type stores
+///
+/// Sources quoting amounts at different precisions (e.g. 2-decimal card feeds and
+/// 6-decimal FX feeds) are normalized to the account's minor unit before parsing,
+/// so every amount is rounded exactly once and in the same way. The same modes
+/// round the results of amount multiplication and division (fees, FX).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingPolicy {
+    /// Reject amounts that cannot be represented exactly (default)
+    #[default]
+    Reject,
+
+    /// Round to nearest, ties away from zero
+    HalfUp,
+
+    /// Round to nearest, ties to even (banker's rounding)
+    HalfEven,
+
+    /// Drop excess digits (round down in magnitude)
+    TowardZero,
+}
+
+/// Whether a decimal has at most `decimals` fractional digits, so it needs
+/// no rounding
+pub(crate) fn fits_decimals(bytes: &[u8], decimals: usize) -> bool {
+    let bytes = bytes.trim_ascii();
+    bytes
+        .iter()
+        .position(|&b| b == b'.')
+        .is_none_or(|dot| bytes.len() - dot - 1 <= decimals)
+}
+
+/// Append the decimal digits of `value` to `out`, without allocating
+pub(crate) fn push_digits(out: &mut Vec<u8>, mut value: u64) {
+    let mut digits = [0u8; 20];
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    out.extend_from_slice(&digits[start..]);
+}
+
+/// Normalize a decimal string to at most `decimals` fractional digits
+///
+/// Strings already within precision are returned unchanged (apart from trimming).
+/// Excess zero digits are dropped; other excess digits are rounded according to
+/// `policy`, and with `RoundingPolicy::Reject` they are an error.
+pub fn normalize_decimal_str(
+    s: &str,
+    decimals: usize,
+    policy: RoundingPolicy,
+) -> Result<String, DomainError> {
+    let s = s.trim();
+
+    let (sign, unsigned) = match s.strip_prefix('-') {
+        Some(stripped) => ("-", stripped),
+        None => ("", s),
+    };
+
+    let (integer_part, decimal_part) = match unsigned.split_once('.') {
+        Some((integer, decimal)) => (integer, decimal),
+        None => return Ok(s.to_string()),
+    };
+
+    if decimal_part.len() <= decimals {
+        return Ok(s.to_string());
+    }
+
+    if !integer_part.bytes().all(|b| b.is_ascii_digit())
+        || !decimal_part.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(DomainError::InvalidAmount);
+    }
+
+    let (kept, excess) = decimal_part.split_at(decimals);
+    let mut digits: Vec<u8> = integer_part.bytes().chain(kept.bytes()).collect();
+
+    // Excess zeros lose nothing, so every policy (including Reject) drops them
+    let exact = excess.bytes().all(|b| b == b'0');
+
+    let first_excess = excess.as_bytes()[0];
+    let rest_nonzero = excess.bytes().skip(1).any(|b| b != b'0');
+    let last_kept_odd = digits.last().is_some_and(|d| (d - b'0') % 2 == 1);
+
+    let round_up = match policy {
+        _ if exact => false,
+        RoundingPolicy::Reject => return Err(DomainError::InvalidAmount),
+        RoundingPolicy::TowardZero => false,
+        RoundingPolicy::HalfUp => first_excess >= b'5',
+        RoundingPolicy::HalfEven => {
+            first_excess > b'5' || (first_excess == b'5' && (rest_nonzero || last_kept_odd))
+        }
+    };
+
+    if round_up && !increment_digits(&mut digits) {
+        digits.insert(0, b'1');
+    }
+
+    // Digits are ASCII by construction
+    let digits = String::from_utf8(digits).map_err(|_| DomainError::InvalidAmount)?;
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    let integer = if integer.is_empty() { "0" } else { integer };
+
+    if decimals == 0 {
+        Ok(format!("{}{}", sign, integer))
+    } else {
+        Ok(format!("{}{}.{}", sign, integer, fraction))
+    }
+}
+
+/// Format a decimal string with exactly `decimals` fractional digits
+///
+/// Excess digits are rounded per `policy` (see `normalize_decimal_str`) and
+/// missing ones are zero-padded; `trim_trailing_zeros` then drops trailing
+/// fractional zeros (`1.5000` -> `1.5`, `2.0000` -> `2`). An amount rounded
+/// to zero is written without a sign.
+pub fn format_decimal_str(
+    s: &str,
+    decimals: usize,
+    trim_trailing_zeros: bool,
+    policy: RoundingPolicy,
+) -> Result<String, DomainError> {
+    let mut value = normalize_decimal_str(s, decimals, policy)?;
+
+    let fraction_len = value.split_once('.').map_or(0, |(_, f)| f.len());
+    if fraction_len < decimals {
+        if fraction_len == 0 {
+            value.push('.');
+        }
+        value.extend(std::iter::repeat_n('0', decimals - fraction_len));
+    }
+
+    if trim_trailing_zeros && value.contains('.') {
+        let trimmed = value.trim_end_matches('0').trim_end_matches('.').len();
+        value.truncate(trimmed);
+    }
+
+    // Small negatives rounded to zero should not print as "-0.00"
+    if value.starts_with('-') && value[1..].bytes().all(|b| b == b'0' || b == b'.') {
+        value.remove(0);
+    }
+
+    Ok(value)
+}
+
+/// The most decimal places seen among input amounts
+///
+/// Pass one to `CsvReaderOptions::with_input_precision` so readers record
+/// every amount they parse, then to `SnapshotFormat::with_input_precision`
+/// to write amounts at the precision the inputs used (e.g. `1.5` rather
+/// than `1.5000` for inputs quoted to one place). Clones share one count,
+/// so several readers can record into it.
+#[derive(Debug, Clone, Default)]
+pub struct InputPrecision(Arc<AtomicUsize>);
+
+impl InputPrecision {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an amount as written in the input
+    ///
+    /// Places beyond those `A` stores are not counted, since they were
+    /// rounded away when the amount was parsed.
+    pub fn record<A: AmountType>(&self, amount: &[u8]) {
+        let amount = amount.trim_ascii();
+        let places = amount
+            .iter()
+            .position(|&b| b == b'.')
+            .map_or(0, |dot| amount.len() - dot - 1);
+        self.0.fetch_max(places.min(A::DECIMALS), Ordering::Relaxed);
+    }
+
+    /// Most decimal places recorded so far (0 before any amount)
+    pub fn decimals(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Divide `numerator` by `denominator`, rounding the quotient per `policy`
+///
+/// Returns None for a zero denominator, or for an inexact quotient under
+/// `RoundingPolicy::Reject`.
+pub fn divide_rounded(numerator: i128, denominator: i128, policy: RoundingPolicy) -> Option<i128> {
+    let quotient = numerator.checked_div(denominator)?;
+    let remainder = numerator % denominator;
+    if remainder == 0 {
+        return Some(quotient);
+    }
+
+    // Compare twice the remainder with the denominator to find ties
+    let half = (remainder.unsigned_abs() * 2).cmp(&denominator.unsigned_abs());
+    let round_away = match policy {
+        RoundingPolicy::Reject => return None,
+        RoundingPolicy::TowardZero => false,
+        RoundingPolicy::HalfUp => half.is_ge(),
+        RoundingPolicy::HalfEven => half.is_gt() || (half.is_eq() && quotient % 2 != 0),
+    };
+
+    match round_away {
+        true => quotient.checked_add(numerator.signum() * denominator.signum()),
+        false => Some(quotient),
+    }
+}
+
+/// Add one to the last digit, propagating carry; returns false if it overflowed the front
+fn increment_digits(digits: &mut [u8]) -> bool {
+    for digit in digits.iter_mut().rev() {
+        if *digit == b'9' {
+            *digit = b'0';
+        } else {
+            *digit += 1;
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn normalize(s: &str, policy: RoundingPolicy) -> String {
+        normalize_decimal_str(s, 4, policy).unwrap()
+    }
+
+    #[test]
+    fn within_precision_is_unchanged() {
+        assert_eq!(normalize("1.25", RoundingPolicy::Reject), "1.25");
+        assert_eq!(normalize("1.2500", RoundingPolicy::HalfUp), "1.2500");
+        assert_eq!(normalize("7", RoundingPolicy::HalfEven), "7");
+    }
+
+    #[test]
+    fn reject_refuses_excess_digits() {
+        assert!(normalize_decimal_str("1.00001", 4, RoundingPolicy::Reject).is_err());
+    }
+
+    #[test]
+    fn excess_zeros_are_exact() {
+        assert_eq!(normalize("1.250000", RoundingPolicy::Reject), "1.2500");
+    }
+
+    #[test]
+    fn half_up_rounds_ties_away_from_zero() {
+        assert_eq!(normalize("1.00005", RoundingPolicy::HalfUp), "1.0001");
+        assert_eq!(normalize("1.00004", RoundingPolicy::HalfUp), "1.0000");
+        assert_eq!(normalize("-1.00005", RoundingPolicy::HalfUp), "-1.0001");
+    }
+
+    #[test]
+    fn half_even_rounds_ties_to_even() {
+        assert_eq!(normalize("1.00005", RoundingPolicy::HalfEven), "1.0000");
+        assert_eq!(normalize("1.00015", RoundingPolicy::HalfEven), "1.0002");
+        assert_eq!(normalize("1.000051", RoundingPolicy::HalfEven), "1.0001");
+    }
+
+    #[test]
+    fn toward_zero_truncates() {
+        assert_eq!(normalize("1.99999", RoundingPolicy::TowardZero), "1.9999");
+        assert_eq!(normalize("-1.99999", RoundingPolicy::TowardZero), "-1.9999");
+    }
+
+    #[test]
+    fn carry_propagates_into_integer_part() {
+        assert_eq!(normalize("9.99995", RoundingPolicy::HalfUp), "10.0000");
+        assert_eq!(normalize(".99995", RoundingPolicy::HalfUp), "1.0000");
+        assert_eq!(
+            normalize_decimal_str("2.5", 0, RoundingPolicy::HalfEven).unwrap(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn divide_rounded_applies_policy() {
+        assert_eq!(divide_rounded(7, 2, RoundingPolicy::HalfUp), Some(4));
+        assert_eq!(divide_rounded(-7, 2, RoundingPolicy::HalfUp), Some(-4));
+        assert_eq!(divide_rounded(7, 2, RoundingPolicy::HalfEven), Some(4));
+        assert_eq!(divide_rounded(5, 2, RoundingPolicy::HalfEven), Some(2));
+        assert_eq!(divide_rounded(-5, 2, RoundingPolicy::HalfEven), Some(-2));
+        assert_eq!(divide_rounded(11, 4, RoundingPolicy::HalfEven), Some(3));
+        assert_eq!(
+            divide_rounded(-19, 10, RoundingPolicy::TowardZero),
+            Some(-1)
+        );
+        assert_eq!(divide_rounded(7, 2, RoundingPolicy::Reject), None);
+        assert_eq!(divide_rounded(8, 2, RoundingPolicy::Reject), Some(4));
+        assert_eq!(divide_rounded(1, 0, RoundingPolicy::HalfUp), None);
+    }
+
+    #[test]
+    fn invalid_digits_are_rejected() {
+        assert!(normalize_decimal_str("1.0000x", 4, RoundingPolicy::HalfUp).is_err());
+        assert!(normalize_decimal_str("a.00001", 4, RoundingPolicy::HalfUp).is_err());
+    }
+
+    #[test]
+    fn input_precision_keeps_the_most_places_stored() {
+        let precision = InputPrecision::new();
+        assert_eq!(precision.decimals(), 0);
+
+        precision.record::<FixedPoint>(b"2");
+        precision.record::<FixedPoint>(b" 1.5 ");
+        precision.clone().record::<FixedPoint>(b"0.25");
+        precision.record::<FixedPoint>(b"3.1");
+        assert_eq!(precision.decimals(), 2);
+
+        // Places rounded away on parsing are not counted
+        precision.record::<FixedPoint>(b"1.123456");
+        assert_eq!(precision.decimals(), 4);
+    }
+}
@@ -1,71 +1,442 @@
 use super::amount::AmountType;
+use super::currency::CurrencyCode;
+use super::error::DomainError;
+
+/// Transaction identifier, globally unique across all inputs
+///
+/// `u32` by default; enable the `wide-tx-ids` feature for `u64` ids.
+#[cfg(not(feature = "wide-tx-ids"))]
+pub type TransactionId = u32;
+
+/// Transaction identifier, globally unique across all inputs
+#[cfg(feature = "wide-tx-ids")]
+pub type TransactionId = u64;
+
+/// Client identifier
+///
+/// `u16` by default (up to 65,535 clients); enable the `wide-client-ids`
+/// feature for `u32` ids.
+#[cfg(not(feature = "wide-client-ids"))]
+pub type ClientId = u16;
+
+/// Client identifier
+#[cfg(feature = "wide-client-ids")]
+pub type ClientId = u32;
 
 /// Transaction types with separate variants for type safety
+///
+/// Funds-moving variants carry an optional currency; `None` is the account's
+/// base currency.
+///
+/// With the `serde` feature, transactions serialize as objects tagged by
+/// `type`, using the CSV type names (e.g. `{"type":"deposit",...}`).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "lowercase")
+)]
 pub enum Transaction<A: AmountType> {
     Deposit {
-        client_id: u16,
-        tx_id: u32,
+        client_id: ClientId,
+        tx_id: TransactionId,
         amount: A,
+        currency: Option<CurrencyCode>,
     },
     Withdrawal {
-        client_id: u16,
-        tx_id: u32,
+        client_id: ClientId,
+        tx_id: TransactionId,
         amount: A,
+        currency: Option<CurrencyCode>,
     },
     Dispute {
-        client_id: u16,
-        tx_id: u32,
+        client_id: ClientId,
+        tx_id: TransactionId,
     },
     Resolve {
-        client_id: u16,
-        tx_id: u32,
+        client_id: ClientId,
+        tx_id: TransactionId,
     },
     Chargeback {
-        client_id: u16,
-        tx_id: u32,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    },
+    /// Representment: the client's bank reversed a chargeback, so the
+    /// charged-back funds return to available
+    #[cfg_attr(feature = "serde", serde(rename = "chargeback_reversal"))]
+    ChargebackReversal {
+        client_id: ClientId,
+        tx_id: TransactionId,
+    },
+    Transfer {
+        from_client: ClientId,
+        to_client: ClientId,
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+    /// Authorization: reserve available funds in held until captured or released
+    Hold {
+        client_id: ClientId,
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+    },
+    /// Settle a hold: the held funds leave the account (like a withdrawal)
+    Capture {
+        client_id: ClientId,
+        tx_id: TransactionId,
+    },
+    /// Cancel a hold: the held funds return to available
+    Release {
+        client_id: ClientId,
+        tx_id: TransactionId,
+    },
+    /// Administrative: reinstate a locked account (requires admin ops to be enabled)
+    Unlock { client_id: ClientId },
+    /// Administrative: let withdrawals take available funds down to `-limit`
+    /// (requires admin ops to be enabled)
+    #[cfg_attr(feature = "serde", serde(rename = "credit_limit"))]
+    SetCreditLimit { client_id: ClientId, limit: A },
+    /// Administrative: correct available funds by a signed `amount`
+    /// (requires admin ops to be enabled)
+    Adjustment {
+        client_id: ClientId,
+        tx_id: TransactionId,
+        amount: A,
+    },
+    /// Administrative: set a client tag such as `tier` or `region`, or remove
+    /// it when `value` is None (requires admin ops to be enabled)
+    #[cfg_attr(feature = "serde", serde(rename = "tag"))]
+    SetTag {
+        client_id: ClientId,
+        key: String,
+        value: Option<String>,
     },
 }
 
 impl<A: AmountType> Transaction<A> {
-    /// Get the client ID for this transaction
-    pub fn client_id(&self) -> u16 {
+    /// Get the client ID for this transaction (the sending client for transfers)
+    pub fn client_id(&self) -> ClientId {
         match self {
             Self::Deposit { client_id, .. } => *client_id,
             Self::Withdrawal { client_id, .. } => *client_id,
             Self::Dispute { client_id, .. } => *client_id,
             Self::Resolve { client_id, .. } => *client_id,
             Self::Chargeback { client_id, .. } => *client_id,
+            Self::ChargebackReversal { client_id, .. } => *client_id,
+            Self::Transfer { from_client, .. } => *from_client,
+            Self::Hold { client_id, .. } => *client_id,
+            Self::Capture { client_id, .. } => *client_id,
+            Self::Release { client_id, .. } => *client_id,
+            Self::Unlock { client_id } => *client_id,
+            Self::SetCreditLimit { client_id, .. } => *client_id,
+            Self::Adjustment { client_id, .. } => *client_id,
+            Self::SetTag { client_id, .. } => *client_id,
+        }
+    }
+
+    /// Get the transaction type as written in the CSV `type` column
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Deposit { .. } => "deposit",
+            Self::Withdrawal { .. } => "withdrawal",
+            Self::Dispute { .. } => "dispute",
+            Self::Resolve { .. } => "resolve",
+            Self::Chargeback { .. } => "chargeback",
+            Self::ChargebackReversal { .. } => "chargeback_reversal",
+            Self::Transfer { .. } => "transfer",
+            Self::Hold { .. } => "hold",
+            Self::Capture { .. } => "capture",
+            Self::Release { .. } => "release",
+            Self::Unlock { .. } => "unlock",
+            Self::SetCreditLimit { .. } => "credit_limit",
+            Self::Adjustment { .. } => "adjustment",
+            Self::SetTag { .. } => "tag",
+        }
+    }
+
+    /// Get the transaction ID (None for administrative operations without one)
+    pub fn tx_id(&self) -> Option<TransactionId> {
+        match self {
+            Self::Deposit { tx_id, .. } => Some(*tx_id),
+            Self::Withdrawal { tx_id, .. } => Some(*tx_id),
+            Self::Dispute { tx_id, .. } => Some(*tx_id),
+            Self::Resolve { tx_id, .. } => Some(*tx_id),
+            Self::Chargeback { tx_id, .. } => Some(*tx_id),
+            Self::ChargebackReversal { tx_id, .. } => Some(*tx_id),
+            Self::Transfer { tx_id, .. } => Some(*tx_id),
+            Self::Hold { tx_id, .. } => Some(*tx_id),
+            Self::Capture { tx_id, .. } => Some(*tx_id),
+            Self::Release { tx_id, .. } => Some(*tx_id),
+            Self::Adjustment { tx_id, .. } => Some(*tx_id),
+            Self::Unlock { .. } | Self::SetCreditLimit { .. } | Self::SetTag { .. } => None,
+        }
+    }
+
+    /// Get the amount of a funds-moving transaction or adjustment, or the new
+    /// credit limit (None for disputes and unlocks)
+    pub fn amount(&self) -> Option<A> {
+        match self {
+            Self::Deposit { amount, .. }
+            | Self::Withdrawal { amount, .. }
+            | Self::Transfer { amount, .. }
+            | Self::Hold { amount, .. }
+            | Self::Adjustment { amount, .. }
+            | Self::SetCreditLimit { limit: amount, .. } => Some(*amount),
+            _ => None,
+        }
+    }
+
+    /// Get the currency of a funds-moving transaction (None for base currency or
+    /// for operations that reference an earlier transaction)
+    pub fn currency(&self) -> Option<CurrencyCode> {
+        match self {
+            Self::Deposit { currency, .. }
+            | Self::Withdrawal { currency, .. }
+            | Self::Transfer { currency, .. }
+            | Self::Hold { currency, .. } => *currency,
+            _ => None,
+        }
+    }
+
+    /// Check if this is an administrative operation (not accepted from partner feeds)
+    pub fn is_admin(&self) -> bool {
+        matches!(
+            self,
+            Self::Unlock { .. }
+                | Self::SetCreditLimit { .. }
+                | Self::Adjustment { .. }
+                | Self::SetTag { .. }
+        )
+    }
+}
+
+/// Transaction paired with an optional event timestamp and sequence number
+///
+/// The timestamp is an opaque, monotonically comparable value (e.g. Unix epoch
+/// milliseconds) used to order transactions across multiple input streams.
+/// The sequence number orders one client's transactions (starting at 1) when
+/// its history is split across streams. `StreamProcessor` notes the input
+/// stream each transaction came from in `source`, and skips replays of an
+/// `idempotency_key` the client has already used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampedTransaction<A: AmountType> {
+    pub timestamp: Option<u64>,
+    pub sequence: Option<u64>,
+    /// Index of the input stream the transaction was read from, when known
+    pub source: Option<usize>,
+    /// Partner-supplied key identifying the submission independently of its tx id
+    pub idempotency_key: Option<String>,
+    pub transaction: Transaction<A>,
+}
+
+impl<A: AmountType> TimestampedTransaction<A> {
+    /// Create a new timestamped transaction
+    pub fn new(transaction: Transaction<A>, timestamp: Option<u64>) -> Self {
+        Self {
+            timestamp,
+            sequence: None,
+            source: None,
+            idempotency_key: None,
+            transaction,
+        }
+    }
+
+    /// Attach an idempotency key
+    pub fn with_idempotency_key(mut self, key: Option<String>) -> Self {
+        self.idempotency_key = key;
+        self
+    }
+
+    /// Attach a per-client sequence number
+    pub fn with_sequence(mut self, sequence: Option<u64>) -> Self {
+        self.sequence = sequence;
+        self
+    }
+}
+
+impl<A: AmountType> From<Transaction<A>> for TimestampedTransaction<A> {
+    fn from(transaction: Transaction<A>) -> Self {
+        Self::new(transaction, None)
+    }
+}
+
+/// Kind of transaction a record was created by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum TxKind {
+    Deposit,
+    Withdrawal,
+    /// Recorded against the sending client
+    Transfer,
+    Hold,
+}
+
+impl TxKind {
+    /// Lowercase name, as in the CSV `type` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxKind::Deposit => "deposit",
+            TxKind::Withdrawal => "withdrawal",
+            TxKind::Transfer => "transfer",
+            TxKind::Hold => "hold",
+        }
+    }
+
+    /// Whether transactions of this kind can be disputed (deposits only)
+    pub fn is_disputable(&self) -> bool {
+        matches!(self, TxKind::Deposit)
+    }
+}
+
+/// Where a recorded transaction is in the dispute lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum TxState {
+    /// Applied and never disputed
+    #[default]
+    Posted,
+    Disputed,
+    Resolved,
+    /// A charged-back transaction cannot be disputed again, only reversed
+    ChargedBack,
+    /// Final: the chargeback was reversed (representment won) and the funds restored
+    Reversed,
+}
+
+impl TxState {
+    /// Lowercase name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxState::Posted => "posted",
+            TxState::Disputed => "disputed",
+            TxState::Resolved => "resolved",
+            TxState::ChargedBack => "chargedback",
+            TxState::Reversed => "reversed",
+        }
+    }
+
+    /// State after a dispute, or why the transaction cannot be disputed
+    pub fn dispute(self, allow_redispute: bool) -> Result<Self, DomainError> {
+        match self {
+            TxState::Posted => Ok(TxState::Disputed),
+            TxState::Resolved if allow_redispute => Ok(TxState::Disputed),
+            TxState::Resolved => Err(DomainError::AlreadyResolved),
+            TxState::Disputed => Err(DomainError::AlreadyDisputed),
+            TxState::ChargedBack | TxState::Reversed => Err(DomainError::AlreadyChargedBack),
+        }
+    }
+
+    /// State after a resolve, or why the transaction cannot be resolved
+    pub fn resolve(self) -> Result<Self, DomainError> {
+        match self {
+            TxState::Disputed => Ok(TxState::Resolved),
+            TxState::ChargedBack | TxState::Reversed => Err(DomainError::AlreadyChargedBack),
+            TxState::Posted | TxState::Resolved => Err(DomainError::NotDisputed),
+        }
+    }
+
+    /// State after a chargeback, or why the transaction cannot be charged back
+    pub fn charge_back(self) -> Result<Self, DomainError> {
+        match self {
+            TxState::Disputed => Ok(TxState::ChargedBack),
+            TxState::ChargedBack | TxState::Reversed => Err(DomainError::AlreadyChargedBack),
+            TxState::Posted | TxState::Resolved => Err(DomainError::NotDisputed),
         }
     }
 
-    /// Get the transaction ID
-    pub fn tx_id(&self) -> u32 {
+    /// State after a chargeback reversal, or why the chargeback cannot be reversed
+    pub fn reverse_chargeback(self) -> Result<Self, DomainError> {
         match self {
-            Self::Deposit { tx_id, .. } => *tx_id,
-            Self::Withdrawal { tx_id, .. } => *tx_id,
-            Self::Dispute { tx_id, .. } => *tx_id,
-            Self::Resolve { tx_id, .. } => *tx_id,
-            Self::Chargeback { tx_id, .. } => *tx_id,
+            TxState::ChargedBack => Ok(TxState::Reversed),
+            TxState::Reversed => Err(DomainError::AlreadyReversed),
+            TxState::Posted | TxState::Disputed | TxState::Resolved => {
+                Err(DomainError::NotChargedBack)
+            }
         }
     }
 }
 
-/// Immutable record of a transaction (for dispute resolution)
+/// Record of a transaction (for dispute resolution)
+///
+/// Amount, client and currency never change once recorded; the engine moves
+/// `state` through the dispute lifecycle, and counts `disputes`, by
+/// re-inserting the record.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TransactionRecord<A: AmountType> {
-    pub client_id: u16,
+    pub client_id: ClientId,
     pub amount: A,
+    /// Currency of the original transaction (disputes resolve in this currency)
+    pub currency: Option<CurrencyCode>,
+    pub kind: TxKind,
+    pub state: TxState,
+    /// Times the transaction has been disputed (see `DisputePolicy::with_max_disputes`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub disputes: u32,
+    /// Amount held by the open dispute when it could hold only part of `amount`
+    /// (a spent deposit under `DisputePolicy::with_negative_balance`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub held: Option<A>,
 }
 
 impl<A: AmountType> TransactionRecord<A> {
-    /// Create a new transaction record
-    pub fn new(client_id: u16, amount: A) -> Self {
+    /// Create a new posted deposit record in the base currency
+    pub fn new(client_id: ClientId, amount: A) -> Self {
         Self {
             client_id,
             amount,
+            currency: None,
+            kind: TxKind::Deposit,
+            state: TxState::Posted,
+            disputes: 0,
+            held: None,
         }
     }
+
+    /// Set the currency of the recorded transaction
+    pub fn with_currency(mut self, currency: Option<CurrencyCode>) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    /// Set the kind of the recorded transaction
+    pub fn with_kind(mut self, kind: TxKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set the dispute state of the recorded transaction
+    pub fn with_state(mut self, state: TxState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Set how many times the recorded transaction has been disputed
+    pub fn with_disputes(mut self, disputes: u32) -> Self {
+        self.disputes = disputes;
+        self
+    }
+
+    /// Set the amount held by the open dispute (`None` when it holds `amount`)
+    pub fn with_held(mut self, held: Option<A>) -> Self {
+        self.held = held;
+        self
+    }
+
+    /// Amount held while the transaction is disputed
+    pub fn held_amount(&self) -> A {
+        self.held.unwrap_or(self.amount)
+    }
 }
 
 #[cfg(test)]
@@ -79,10 +450,11 @@ mod tests {
             client_id: 1,
             tx_id: 100,
             amount: FixedPoint::from_raw(10_000),
+            currency: None,
         };
 
         assert_eq!(tx.client_id(), 1);
-        assert_eq!(tx.tx_id(), 100);
+        assert_eq!(tx.tx_id(), Some(100));
     }
 
     #[test]
@@ -91,10 +463,11 @@ mod tests {
             client_id: 2,
             tx_id: 200,
             amount: FixedPoint::from_raw(5_000),
+            currency: None,
         };
 
         assert_eq!(tx.client_id(), 2);
-        assert_eq!(tx.tx_id(), 200);
+        assert_eq!(tx.tx_id(), Some(200));
     }
 
     #[test]
@@ -105,7 +478,7 @@ mod tests {
         };
 
         assert_eq!(tx.client_id(), 1);
-        assert_eq!(tx.tx_id(), 100);
+        assert_eq!(tx.tx_id(), Some(100));
     }
 
     #[test]
@@ -116,7 +489,7 @@ mod tests {
         };
 
         assert_eq!(tx.client_id(), 1);
-        assert_eq!(tx.tx_id(), 100);
+        assert_eq!(tx.tx_id(), Some(100));
     }
 
     #[test]
@@ -127,7 +500,70 @@ mod tests {
         };
 
         assert_eq!(tx.client_id(), 1);
-        assert_eq!(tx.tx_id(), 100);
+        assert_eq!(tx.tx_id(), Some(100));
+    }
+
+    #[test]
+    fn transfer_reports_sending_client() {
+        let tx = Transaction::Transfer {
+            from_client: 1,
+            to_client: 2,
+            tx_id: 100,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        };
+
+        assert_eq!(tx.client_id(), 1);
+        assert_eq!(tx.tx_id(), Some(100));
+    }
+
+    #[test]
+    fn unlock_is_admin_without_tx_id() {
+        let tx = Transaction::<FixedPoint>::Unlock { client_id: 3 };
+
+        assert_eq!(tx.client_id(), 3);
+        assert_eq!(tx.tx_id(), None);
+        assert!(tx.is_admin());
+    }
+
+    #[test]
+    fn adjustment_is_admin_with_tx_id() {
+        let tx = Transaction::Adjustment {
+            client_id: 3,
+            tx_id: 7,
+            amount: FixedPoint::from_raw(-5_000),
+        };
+
+        assert_eq!(tx.tx_id(), Some(7));
+        assert_eq!(tx.amount(), Some(FixedPoint::from_raw(-5_000)));
+        assert_eq!(tx.type_name(), "adjustment");
+        assert!(tx.is_admin());
+    }
+
+    #[test]
+    fn type_name_matches_csv_type() {
+        let tx = Transaction::<FixedPoint>::Chargeback {
+            client_id: 1,
+            tx_id: 2,
+        };
+
+        assert_eq!(tx.type_name(), "chargeback");
+        assert_eq!(
+            Transaction::<FixedPoint>::Unlock { client_id: 1 }.type_name(),
+            "unlock"
+        );
+    }
+
+    #[test]
+    fn untimestamped_conversion_has_no_timestamp() {
+        let tx = Transaction::<FixedPoint>::Dispute {
+            client_id: 1,
+            tx_id: 100,
+        };
+
+        let timestamped = TimestampedTransaction::from(tx.clone());
+        assert_eq!(timestamped.timestamp, None);
+        assert_eq!(timestamped.transaction, tx);
     }
 
     #[test]
@@ -154,14 +590,95 @@ mod tests {
             client_id: 1,
             tx_id: 1,
             amount: FixedPoint::from_raw(1000),
+            currency: None,
         };
 
         let withdrawal = Transaction::Withdrawal {
             client_id: 1,
             tx_id: 1,
             amount: FixedPoint::from_raw(1000),
+            currency: None,
         };
 
         assert_ne!(deposit, withdrawal);
     }
+
+    #[test]
+    fn dispute_lifecycle_transitions() {
+        assert_eq!(TxState::Posted.dispute(false), Ok(TxState::Disputed));
+        assert_eq!(TxState::Disputed.resolve(), Ok(TxState::Resolved));
+        assert_eq!(TxState::Resolved.dispute(true), Ok(TxState::Disputed));
+        assert_eq!(
+            TxState::Resolved.dispute(false),
+            Err(DomainError::AlreadyResolved)
+        );
+        assert_eq!(TxState::Disputed.charge_back(), Ok(TxState::ChargedBack));
+
+        assert_eq!(TxState::Posted.resolve(), Err(DomainError::NotDisputed));
+        assert_eq!(
+            TxState::Disputed.dispute(true),
+            Err(DomainError::AlreadyDisputed)
+        );
+        assert_eq!(
+            TxState::ChargedBack.dispute(true),
+            Err(DomainError::AlreadyChargedBack)
+        );
+        assert_eq!(
+            TxState::ChargedBack.charge_back(),
+            Err(DomainError::AlreadyChargedBack)
+        );
+
+        assert_eq!(
+            TxState::ChargedBack.reverse_chargeback(),
+            Ok(TxState::Reversed)
+        );
+        assert_eq!(
+            TxState::Disputed.reverse_chargeback(),
+            Err(DomainError::NotChargedBack)
+        );
+        assert_eq!(
+            TxState::Reversed.reverse_chargeback(),
+            Err(DomainError::AlreadyReversed)
+        );
+        assert_eq!(
+            TxState::Reversed.dispute(true),
+            Err(DomainError::AlreadyChargedBack)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_tags_transactions_with_their_csv_type() {
+        let deposit = Transaction::Deposit {
+            client_id: 1,
+            tx_id: 7,
+            amount: FixedPoint::from_raw(15_000),
+            currency: Some("EUR".parse().unwrap()),
+        };
+        let json = serde_json::to_string(&deposit).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"type":"deposit","client_id":1,"tx_id":7,"amount":"1.5000","currency":"EUR"}"#
+        );
+        let restored: Transaction<FixedPoint> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, deposit);
+        assert_eq!(
+            serde_json::from_str::<Transaction<FixedPoint>>(
+                r#"{"type":"chargeback","client_id":1,"tx_id":7}"#
+            )
+            .unwrap(),
+            Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 7,
+            }
+        );
+
+        let record =
+            TransactionRecord::new(1, FixedPoint::from_raw(5)).with_state(TxState::ChargedBack);
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains(r#""state":"chargedback""#), "{json}");
+        let restored: TransactionRecord<FixedPoint> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, record);
+    }
 }
@@ -0,0 +1,153 @@
+use smallvec::SmallVec;
+
+use super::transaction::TransactionId;
+
+/// Transaction ids kept inline before spilling to the heap
+const INLINE: usize = 4;
+
+/// Small sorted set of transaction ids
+///
+/// Accounts rarely have more than a couple of active disputes or holds, so
+/// ids are stored inline (no allocation, cheap clones) and binary searched.
+/// Larger sets spill to the heap and keep working, with O(n) inserts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxIdSet {
+    ids: SmallVec<[TransactionId; INLINE]>,
+}
+
+impl TxIdSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if the set contains `tx_id`
+    pub fn contains(&self, tx_id: TransactionId) -> bool {
+        self.ids.binary_search(&tx_id).is_ok()
+    }
+
+    /// Add `tx_id`, returning false if it was already present
+    pub fn insert(&mut self, tx_id: TransactionId) -> bool {
+        match self.ids.binary_search(&tx_id) {
+            Ok(_) => false,
+            Err(index) => {
+                self.ids.insert(index, tx_id);
+                true
+            }
+        }
+    }
+
+    /// Remove `tx_id`, returning false if it was not present
+    pub fn remove(&mut self, tx_id: TransactionId) -> bool {
+        match self.ids.binary_search(&tx_id) {
+            Ok(index) => {
+                self.ids.remove(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Number of ids in the set
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Iterate over ids in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = TransactionId> + '_ {
+        self.ids.iter().copied()
+    }
+
+    /// Whether the ids are stored on the heap
+    pub fn spilled(&self) -> bool {
+        self.ids.spilled()
+    }
+}
+
+/// Serialized as a sorted sequence of ids
+#[cfg(feature = "serde")]
+impl serde::Serialize for TxIdSet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.ids)
+    }
+}
+
+/// Accepts ids in any order; duplicates collapse
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TxIdSet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut set = Self::new();
+        for tx_id in Vec::<TransactionId>::deserialize(deserializer)? {
+            set.insert(tx_id);
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_contains_remove() {
+        let mut set = TxIdSet::new();
+
+        assert!(set.insert(7));
+        assert!(set.insert(3));
+        assert!(!set.insert(7));
+        assert!(set.contains(3));
+        assert!(set.contains(7));
+        assert!(!set.contains(5));
+        assert_eq!(set.len(), 2);
+
+        assert!(set.remove(3));
+        assert!(!set.remove(3));
+        assert!(!set.contains(3));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn keeps_ids_sorted() {
+        let mut set = TxIdSet::new();
+        for tx_id in [9, 2, 5, 1] {
+            set.insert(tx_id);
+        }
+
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![1, 2, 5, 9]);
+    }
+
+    #[test]
+    fn small_sets_stay_inline_and_large_sets_spill() {
+        let mut set = TxIdSet::new();
+        for tx_id in 0..INLINE as TransactionId {
+            set.insert(tx_id);
+        }
+        assert!(!set.spilled());
+
+        for tx_id in 100..200 {
+            set.insert(tx_id);
+        }
+        assert!(set.spilled());
+        assert_eq!(set.len(), INLINE + 100);
+        assert!(set.contains(150));
+        assert!(set.remove(150));
+        assert!(!set.contains(150));
+    }
+
+    #[test]
+    fn equality_ignores_insertion_order() {
+        let mut a = TxIdSet::new();
+        let mut b = TxIdSet::new();
+        a.insert(1);
+        a.insert(2);
+        b.insert(2);
+        b.insert(1);
+
+        assert_eq!(a, b);
+    }
+}
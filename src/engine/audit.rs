@@ -0,0 +1,126 @@
+use crate::domain::{
+    AmountType, ClientAccount, ClientId, CurrencyCode, Transaction, TransactionId,
+};
+
+/// Operation type recorded in the audit trail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOperation {
+    Deposit,
+    Withdrawal,
+    Dispute,
+    Resolve,
+    Chargeback,
+    ChargebackReversal,
+    Transfer,
+    Hold,
+    Capture,
+    Release,
+    Unlock,
+    SetCreditLimit,
+    Adjustment,
+    SetTag,
+}
+
+impl<A: AmountType> From<&Transaction<A>> for AuditOperation {
+    fn from(tx: &Transaction<A>) -> Self {
+        match tx {
+            Transaction::Deposit { .. } => Self::Deposit,
+            Transaction::Withdrawal { .. } => Self::Withdrawal,
+            Transaction::Dispute { .. } => Self::Dispute,
+            Transaction::Resolve { .. } => Self::Resolve,
+            Transaction::Chargeback { .. } => Self::Chargeback,
+            Transaction::ChargebackReversal { .. } => Self::ChargebackReversal,
+            Transaction::Transfer { .. } => Self::Transfer,
+            Transaction::Hold { .. } => Self::Hold,
+            Transaction::Capture { .. } => Self::Capture,
+            Transaction::Release { .. } => Self::Release,
+            Transaction::Unlock { .. } => Self::Unlock,
+            Transaction::SetCreditLimit { .. } => Self::SetCreditLimit,
+            Transaction::Adjustment { .. } => Self::Adjustment,
+            Transaction::SetTag { .. } => Self::SetTag,
+        }
+    }
+}
+
+impl AuditOperation {
+    /// Lowercase name, matching the CSV `type` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deposit => "deposit",
+            Self::Withdrawal => "withdrawal",
+            Self::Dispute => "dispute",
+            Self::Resolve => "resolve",
+            Self::Chargeback => "chargeback",
+            Self::ChargebackReversal => "chargeback_reversal",
+            Self::Transfer => "transfer",
+            Self::Hold => "hold",
+            Self::Capture => "capture",
+            Self::Release => "release",
+            Self::Unlock => "unlock",
+            Self::SetCreditLimit => "credit_limit",
+            Self::Adjustment => "adjustment",
+            Self::SetTag => "tag",
+        }
+    }
+}
+
+/// Whether the audited transaction was applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Applied,
+    /// Rejected, with the engine error message
+    Rejected(String),
+}
+
+/// Account balances captured before or after an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceSnapshot<A: AmountType> {
+    pub available: A,
+    pub held: A,
+    pub locked: bool,
+}
+
+impl<A: AmountType> BalanceSnapshot<A> {
+    /// Capture an account's balances in one currency (None = base currency)
+    pub fn of(account: &ClientAccount<A>, currency: Option<CurrencyCode>) -> Self {
+        let (available, held) = match currency {
+            None => (account.available(), account.held()),
+            Some(currency) => {
+                let balance = account.currency_balance(currency);
+                (balance.available, balance.held)
+            }
+        };
+
+        Self {
+            available,
+            held,
+            locked: account.is_locked(),
+        }
+    }
+}
+
+/// Structured audit record for one account affected by a transaction
+///
+/// Transfers produce one record per side, with `counterparty` naming the
+/// other account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord<A: AmountType> {
+    pub tx_id: Option<TransactionId>,
+    pub client_id: ClientId,
+    pub counterparty: Option<ClientId>,
+    pub operation: AuditOperation,
+    /// Currency whose balances are captured (None = base currency)
+    pub currency: Option<CurrencyCode>,
+    pub before: BalanceSnapshot<A>,
+    pub after: BalanceSnapshot<A>,
+    pub outcome: AuditOutcome,
+}
+
+/// Receiver of audit records for every applied or rejected transaction
+///
+/// Called synchronously on the processing path, so implementations should be
+/// cheap (e.g. push to a channel or buffered writer).
+pub trait AuditSink<A: AmountType>: Send + Sync {
+    /// Record one audited operation
+    fn record(&self, record: AuditRecord<A>);
+}
@@ -0,0 +1,175 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use super::audit::AuditSink;
+use super::processor::TransactionProcessor;
+use super::validator::TransactionValidator;
+use crate::domain::{AmountType, DisputePolicy};
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsRegistry;
+use crate::storage::{ClientAccountManager, TransactionStoreManager};
+
+/// Collects a `TransactionProcessor`'s optional components before storage is known
+///
+/// Created by `TransactionProcessor::builder()`. Every component is optional
+/// and starts at the default `TransactionProcessor::new` uses; `build` takes
+/// the storage, which fixes the processor's account manager and transaction
+/// store types. A configured builder can be cloned to build one processor
+/// per shard.
+///
+/// # Example
+/// ```rust,ignore
+/// let processor = TransactionProcessor::builder()
+///     .with_admin_ops(true)
+///     .with_validator(Arc::new(MaxAmount(limit)))
+///     .with_dispute_policy(DisputePolicy::default().with_redispute(true))
+///     .build(ConcurrentAccountManager::new(), ConcurrentTransactionStore::new());
+/// ```
+pub struct TransactionProcessorBuilder<A: AmountType, M, T> {
+    allow_admin_ops: bool,
+    audit_sink: Option<Arc<dyn AuditSink<A>>>,
+    validators: Vec<Arc<dyn TransactionValidator<A>>>,
+    dispute_policy: DisputePolicy,
+    #[cfg(feature = "metrics")]
+    metrics: Option<MetricsRegistry>,
+    _storage: PhantomData<fn() -> (M, T)>,
+}
+
+impl<A: AmountType, M, T> Clone for TransactionProcessorBuilder<A, M, T> {
+    fn clone(&self) -> Self {
+        Self {
+            allow_admin_ops: self.allow_admin_ops,
+            audit_sink: self.audit_sink.clone(),
+            validators: self.validators.clone(),
+            dispute_policy: self.dispute_policy,
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<A: AmountType, M, T> Default for TransactionProcessorBuilder<A, M, T> {
+    fn default() -> Self {
+        Self {
+            allow_admin_ops: false,
+            audit_sink: None,
+            validators: Vec::new(),
+            dispute_policy: DisputePolicy::default(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<A, M, T> TransactionProcessorBuilder<A, M, T>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    T: TransactionStoreManager<A>,
+{
+    /// Accept administrative operations (see `TransactionProcessor::with_admin_ops`)
+    pub fn with_admin_ops(mut self, enabled: bool) -> Self {
+        self.allow_admin_ops = enabled;
+        self
+    }
+
+    /// Report transactions to an audit sink (see `TransactionProcessor::with_audit_sink`)
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink<A>>) -> Self {
+        self.audit_sink = Some(sink);
+        self
+    }
+
+    /// Add a validator to the end of the chain (see `TransactionProcessor::with_validator`)
+    pub fn with_validator(mut self, validator: Arc<dyn TransactionValidator<A>>) -> Self {
+        self.validators.push(validator);
+        self
+    }
+
+    /// Select dispute semantics (see `TransactionProcessor::with_dispute_policy`)
+    pub fn with_dispute_policy(mut self, policy: DisputePolicy) -> Self {
+        self.dispute_policy = policy;
+        self
+    }
+
+    /// Report to a metrics registry (see `TransactionProcessor::with_metrics`)
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: MetricsRegistry) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Create the processor over the given storage
+    pub fn build(self, account_manager: M, transaction_store: T) -> TransactionProcessor<A, M, T> {
+        let mut processor = TransactionProcessor::new(account_manager, transaction_store)
+            .with_admin_ops(self.allow_admin_ops)
+            .with_dispute_policy(self.dispute_policy);
+        if let Some(sink) = self.audit_sink {
+            processor = processor.with_audit_sink(sink);
+        }
+        for validator in self.validators {
+            processor = processor.with_validator(validator);
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics {
+            processor = processor.with_metrics(metrics);
+        }
+        processor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::domain::{ClientId, FixedPoint, Transaction, TransactionId};
+    use crate::engine::{BlockedClients, EngineError};
+    use crate::storage::{ConcurrentAccountManager, ConcurrentTransactionStore};
+
+    fn deposit(client_id: ClientId, tx_id: TransactionId) -> Transaction<FixedPoint> {
+        Transaction::Deposit {
+            client_id,
+            tx_id,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        }
+    }
+
+    fn adjustment(tx_id: TransactionId) -> Transaction<FixedPoint> {
+        Transaction::Adjustment {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(5_000),
+        }
+    }
+
+    #[test]
+    fn built_processor_uses_each_component() {
+        let builder = TransactionProcessor::builder()
+            .with_admin_ops(true)
+            .with_validator(Arc::new(BlockedClients(HashSet::from([7]))));
+        let mut processor = builder.clone().build(
+            ConcurrentAccountManager::<FixedPoint>::new(),
+            ConcurrentTransactionStore::new(),
+        );
+
+        processor.process_transaction(deposit(1, 1)).unwrap();
+        processor.process_transaction(adjustment(2)).unwrap();
+        assert!(matches!(
+            processor.process_transaction(deposit(7, 3)),
+            Err(EngineError::Rejected(_))
+        ));
+
+        // The default builder matches `TransactionProcessor::new`
+        let mut processor = TransactionProcessorBuilder::default().build(
+            ConcurrentAccountManager::<FixedPoint>::new(),
+            ConcurrentTransactionStore::new(),
+        );
+        assert!(matches!(
+            processor.process_transaction(adjustment(2)),
+            Err(EngineError::AdminOperationNotAllowed)
+        ));
+    }
+}
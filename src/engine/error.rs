@@ -1,23 +1,32 @@
 use thiserror::Error;
 
-use crate::domain::DomainError;
+use crate::domain::{DomainError, TransactionId};
 use crate::storage::StorageError;
 
 /// Engine-level errors for transaction processing
 #[derive(Error, Debug)]
 pub enum EngineError {
     #[error("Transaction not found: {0}")]
-    TransactionNotFound(u32),
+    TransactionNotFound(TransactionId),
 
     #[error("Transaction not under dispute: {0}")]
-    TransactionNotDisputed(u32),
+    TransactionNotDisputed(TransactionId),
 
     #[error("Transaction already disputed: {0}")]
-    TransactionAlreadyDisputed(u32),
+    TransactionAlreadyDisputed(TransactionId),
 
     #[error("Cannot dispute a withdrawal")]
     CannotDisputeWithdrawal,
 
+    #[error("Administrative operations are not enabled")]
+    AdminOperationNotAllowed,
+
+    #[error("Transaction rejected: {0}")]
+    Rejected(String),
+
+    #[error("Velocity limit exceeded: {0}")]
+    VelocityLimitExceeded(String),
+
     #[error("Domain error: {0}")]
     Domain(#[from] DomainError),
 
@@ -25,6 +34,17 @@ pub enum EngineError {
     Storage(#[from] StorageError),
 }
 
+impl EngineError {
+    /// Whether the transaction failed because the account is locked
+    pub fn is_account_locked(&self) -> bool {
+        matches!(
+            self,
+            EngineError::Domain(DomainError::AccountLocked)
+                | EngineError::Storage(StorageError::DomainError(DomainError::AccountLocked))
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,10 +63,28 @@ mod tests {
             EngineError::TransactionAlreadyDisputed(789).to_string(),
             "Transaction already disputed: 789"
         );
+        assert_eq!(
+            EngineError::Rejected("client 7 is blocked".to_string()).to_string(),
+            "Transaction rejected: client 7 is blocked"
+        );
         assert_eq!(
             EngineError::CannotDisputeWithdrawal.to_string(),
             "Cannot dispute a withdrawal"
         );
+        assert_eq!(
+            EngineError::AdminOperationNotAllowed.to_string(),
+            "Administrative operations are not enabled"
+        );
+    }
+
+    #[test]
+    fn detects_locked_account_errors() {
+        assert!(EngineError::Domain(DomainError::AccountLocked).is_account_locked());
+        assert!(
+            EngineError::Storage(StorageError::DomainError(DomainError::AccountLocked))
+                .is_account_locked()
+        );
+        assert!(!EngineError::Domain(DomainError::InsufficientFunds).is_account_locked());
     }
 
     #[test]
@@ -0,0 +1,62 @@
+use dashmap::DashSet;
+
+use crate::domain::ClientId;
+
+/// Idempotency keys of applied transactions, by client
+///
+/// A transaction submitted with a key that the same client has already used
+/// for an applied transaction is a replay: the processor skips it and reports
+/// success, so a partner re-uploading a file with renumbered tx ids does not
+/// apply it twice. Rejected transactions do not use up their key. Share one
+/// set (behind an `Arc`) between every processor, and every run, that should
+/// recognise the same keys; keys are kept until the set is dropped.
+///
+/// # Example
+/// ```rust,ignore
+/// let keys = Arc::new(IdempotencyKeys::new());
+/// let processor = TransactionProcessor::new(mgr, store).with_idempotency_keys(keys.clone());
+/// ```
+#[derive(Debug, Default)]
+pub struct IdempotencyKeys {
+    keys: DashSet<(ClientId, String)>,
+}
+
+impl IdempotencyKeys {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `client_id` has an applied transaction with `key`
+    pub fn contains(&self, client_id: ClientId, key: &str) -> bool {
+        self.keys.contains(&(client_id, key.to_string()))
+    }
+
+    /// Number of recorded keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Check if no keys have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Record `key` for `client_id`, or return false if it is already recorded
+    pub(crate) fn claim(&self, client_id: ClientId, key: &str) -> bool {
+        self.keys.insert((client_id, key.to_string()))
+    }
+
+    /// Visit every recorded key
+    pub(crate) fn for_each(&self, f: &mut dyn FnMut(ClientId, &str)) {
+        for entry in self.keys.iter() {
+            let (client_id, key) = entry.key();
+            f(*client_id, key);
+        }
+    }
+
+    /// Forget a key whose transaction was rejected
+    pub(crate) fn release(&self, client_id: ClientId, key: &str) {
+        self.keys.remove(&(client_id, key.to_string()));
+    }
+}
@@ -1,6 +1,26 @@
+pub mod audit;
+pub mod builder;
 pub mod error;
+pub mod idempotency;
+pub mod ordering;
 pub mod processor;
+pub mod quarantine;
+pub mod simulation;
+pub mod statement;
+pub mod type_counts;
+pub mod validator;
 
 // Re-export commonly used types
+pub use audit::{AuditOperation, AuditOutcome, AuditRecord, AuditSink, BalanceSnapshot};
+pub use builder::TransactionProcessorBuilder;
 pub use error::EngineError;
+pub use idempotency::IdempotencyKeys;
+pub use ordering::{OrderVerifier, OrderedTransaction, OrderingReport, OrderingViolation};
 pub use processor::TransactionProcessor;
+pub use quarantine::{QuarantineSink, QuarantinedTransaction};
+pub use simulation::SimulationOutcome;
+pub use statement::{
+    Statement, StatementLine, StatementRow, TransactionHistory, generate_statement,
+};
+pub use type_counts::{TransactionTypeCounts, TypeCount};
+pub use validator::{BlockedClients, MaxAmount, SegmentRule, TransactionValidator, VelocityLimit};
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use dashmap::DashMap;
+
+use crate::domain::{AmountType, ClientId, Transaction, TransactionId};
+
+/// Records the order in which each client's transactions are processed, to
+/// check that a topology keeps dependent transactions in order
+///
+/// A dispute must follow the deposit it disputes, a resolve or chargeback the
+/// dispute, a chargeback reversal the chargeback, and a capture or release its
+/// hold. Under
+/// `StreamCombinator::Merge` or with one client's history split across
+/// streams, records can reach the engine out of order; the engine then
+/// rejects the early one (e.g. `TransactionNotFound` for a dispute that
+/// arrived before its deposit) and the result differs from the input's
+/// intent. `ordering_report` lists every such case found so far.
+///
+/// This is a verification mode: every processed transaction is kept, so
+/// memory grows with the input. Transfers are recorded for the sending client.
+/// Share one verifier (behind an `Arc`) between every processor of a run.
+///
+/// # Example
+/// ```rust,ignore
+/// let verifier = Arc::new(OrderVerifier::new());
+/// StreamProcessor::new(mgr, store, SilentSkip)
+///     .with_order_verification(verifier.clone())
+///     .add_stream(deposits)
+///     .add_stream(disputes)
+///     .process()
+///     .await;
+/// let report = verifier.ordering_report();
+/// if !report.is_ordered() {
+///     eprintln!("{report}");
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct OrderVerifier {
+    clients: DashMap<ClientId, ClientOrder>,
+}
+
+/// One processed transaction, as recorded by an `OrderVerifier`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderedTransaction {
+    /// CSV type name (e.g. `dispute`)
+    pub kind: &'static str,
+    /// The transaction's id, or the id it refers to for disputes, resolves,
+    /// chargebacks, captures and releases
+    pub tx_id: Option<TransactionId>,
+    /// Whether the engine applied it
+    pub applied: bool,
+}
+
+/// A transaction processed before the transaction it depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderingViolation {
+    pub client_id: ClientId,
+    pub tx_id: TransactionId,
+    /// Type of the transaction that came too early (e.g. `dispute`)
+    pub early_kind: &'static str,
+    /// Its position in the client's processing order (0-based)
+    pub early_position: usize,
+    /// Type of the transaction it depends on (e.g. `deposit`)
+    pub late_kind: &'static str,
+    /// Its position in the client's processing order (0-based)
+    pub late_position: usize,
+}
+
+impl fmt::Display for OrderingViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "client {}, tx {}: {} (#{}) was processed before {} (#{})",
+            self.client_id,
+            self.tx_id,
+            self.early_kind,
+            self.early_position,
+            self.late_kind,
+            self.late_position
+        )
+    }
+}
+
+/// Result of `OrderVerifier::ordering_report`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderingReport {
+    /// Clients with at least one processed transaction
+    pub clients: usize,
+    /// Processed transactions across all clients
+    pub transactions: usize,
+    /// Every violation, ordered by client and position
+    pub violations: Vec<OrderingViolation>,
+}
+
+impl OrderingReport {
+    /// True if every dependent transaction followed the one it depends on
+    pub fn is_ordered(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for OrderingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} transactions across {} clients, {} out of order",
+            self.transactions,
+            self.clients,
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n  {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What the verifier needs of a transaction, taken before the engine consumes it
+pub(crate) struct Step {
+    kind: &'static str,
+    client_id: ClientId,
+    tx_id: Option<TransactionId>,
+    /// Position in the lifecycle of `tx_id`: 0 for the original transaction,
+    /// 1 for disputes, captures and releases, 2 for resolves and chargebacks,
+    /// 3 for chargeback reversals
+    stage: u8,
+}
+
+impl Step {
+    pub(crate) fn of<A: AmountType>(tx: &Transaction<A>) -> Self {
+        let stage = match tx {
+            Transaction::Dispute { .. }
+            | Transaction::Capture { .. }
+            | Transaction::Release { .. } => 1,
+            Transaction::Resolve { .. } | Transaction::Chargeback { .. } => 2,
+            Transaction::ChargebackReversal { .. } => 3,
+            _ => 0,
+        };
+        Self {
+            kind: tx.type_name(),
+            client_id: tx.client_id(),
+            tx_id: tx.tx_id(),
+            stage,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClientOrder {
+    sequence: Vec<OrderedTransaction>,
+    /// Furthest lifecycle stage processed for each tx id
+    reached: HashMap<TransactionId, u8>,
+    /// First transaction that arrived before the stage it needs, keyed by
+    /// the tx id and that stage
+    waiting: HashMap<(TransactionId, u8), (&'static str, usize)>,
+    violations: Vec<OrderingViolation>,
+}
+
+impl OrderVerifier {
+    /// Create an empty verifier
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a processed transaction
+    pub(crate) fn record(&self, step: Step, applied: bool) {
+        let mut order = self.clients.entry(step.client_id).or_default();
+        let position = order.sequence.len();
+        order.sequence.push(OrderedTransaction {
+            kind: step.kind,
+            tx_id: step.tx_id,
+            applied,
+        });
+        let Some(tx_id) = step.tx_id else {
+            return;
+        };
+
+        let reached = order.reached.get(&tx_id).copied();
+        if step.stage > 0 && reached.is_none_or(|reached| reached < step.stage - 1) {
+            order
+                .waiting
+                .entry((tx_id, step.stage - 1))
+                .or_insert((step.kind, position));
+        }
+        if reached.is_none_or(|reached| reached < step.stage) {
+            order.reached.insert(tx_id, step.stage);
+        }
+        if let Some((early_kind, early_position)) = order.waiting.remove(&(tx_id, step.stage)) {
+            order.violations.push(OrderingViolation {
+                client_id: step.client_id,
+                tx_id,
+                early_kind,
+                early_position,
+                late_kind: step.kind,
+                late_position: position,
+            });
+        }
+    }
+
+    /// Transactions processed for `client_id`, in processing order
+    pub fn sequence(&self, client_id: ClientId) -> Vec<OrderedTransaction> {
+        self.clients
+            .get(&client_id)
+            .map(|order| order.sequence.clone())
+            .unwrap_or_default()
+    }
+
+    /// Every out-of-order case found so far
+    pub fn ordering_report(&self) -> OrderingReport {
+        let mut report = OrderingReport {
+            clients: self.clients.len(),
+            transactions: 0,
+            violations: Vec::new(),
+        };
+        for order in self.clients.iter() {
+            report.transactions += order.sequence.len();
+            report.violations.extend_from_slice(&order.violations);
+        }
+        report
+            .violations
+            .sort_by_key(|violation| (violation.client_id, violation.early_position));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn record(verifier: &OrderVerifier, tx: Transaction<FixedPoint>, applied: bool) {
+        verifier.record(Step::of(&tx), applied);
+    }
+
+    fn deposit(client_id: ClientId, tx_id: TransactionId) -> Transaction<FixedPoint> {
+        Transaction::Deposit {
+            client_id,
+            tx_id,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        }
+    }
+
+    #[test]
+    fn in_order_lifecycle_is_clean() {
+        let verifier = OrderVerifier::new();
+        record(&verifier, deposit(1, 1), true);
+        record(
+            &verifier,
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            },
+            true,
+        );
+        record(
+            &verifier,
+            Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 1,
+            },
+            true,
+        );
+
+        let report = verifier.ordering_report();
+        assert!(report.is_ordered());
+        assert_eq!((report.clients, report.transactions), (1, 3));
+        let kinds: Vec<_> = verifier.sequence(1).iter().map(|tx| tx.kind).collect();
+        assert_eq!(kinds, vec!["deposit", "dispute", "chargeback"]);
+    }
+
+    #[test]
+    fn flags_references_processed_too_early() {
+        let verifier = OrderVerifier::new();
+        record(
+            &verifier,
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 7,
+            },
+            false,
+        );
+        record(&verifier, deposit(1, 7), true);
+        record(
+            &verifier,
+            Transaction::Resolve {
+                client_id: 2,
+                tx_id: 9,
+            },
+            false,
+        );
+        record(&verifier, deposit(2, 9), true);
+        record(
+            &verifier,
+            Transaction::Dispute {
+                client_id: 2,
+                tx_id: 9,
+            },
+            true,
+        );
+        // A dispute of a transaction that never arrives is not an ordering problem
+        record(
+            &verifier,
+            Transaction::Dispute {
+                client_id: 3,
+                tx_id: 99,
+            },
+            false,
+        );
+
+        let report = verifier.ordering_report();
+        assert_eq!(
+            report.violations,
+            vec![
+                OrderingViolation {
+                    client_id: 1,
+                    tx_id: 7,
+                    early_kind: "dispute",
+                    early_position: 0,
+                    late_kind: "deposit",
+                    late_position: 1,
+                },
+                OrderingViolation {
+                    client_id: 2,
+                    tx_id: 9,
+                    early_kind: "resolve",
+                    early_position: 0,
+                    late_kind: "dispute",
+                    late_position: 2,
+                },
+            ]
+        );
+        assert!(!verifier.sequence(1)[0].applied);
+        assert!(
+            report
+                .to_string()
+                .contains("client 1, tx 7: dispute (#0) was processed before deposit (#1)")
+        );
+    }
+}
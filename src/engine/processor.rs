@@ -1,12 +1,32 @@
 use std::marker::PhantomData;
+use std::sync::Arc;
+#[cfg(feature = "metrics")]
+use std::time::Instant;
 use tracing::{debug, warn};
 
+use super::audit::{AuditOutcome, AuditRecord, AuditSink, BalanceSnapshot};
+use super::builder::TransactionProcessorBuilder;
 use super::error::EngineError;
+use super::idempotency::IdempotencyKeys;
+use super::ordering::{OrderVerifier, OrderingReport, Step};
+use super::quarantine::{QuarantineSink, QuarantinedTransaction};
+use super::simulation::SimulationOutcome;
+use super::type_counts::TransactionTypeCounts;
+use super::validator::TransactionValidator;
 use crate::domain::{
-    AmountType, Transaction, TransactionRecord, apply_chargeback, apply_deposit, apply_dispute,
-    apply_resolve, apply_withdrawal,
+    AccountOp, AmountType, ClientAccount, ClientId, CurrencyCode, DisputePolicy, DomainError,
+    FeeSchedule, FeeType, PairOp, Transaction, TransactionId, TransactionRecord, TxKind,
 };
-use crate::storage::{ClientAccountEntry, ClientAccountManager, TransactionStoreManager};
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsRegistry;
+use crate::storage::{
+    ClientAccountEntry, ClientAccountManager, ConcurrentAccountManager, ConcurrentTransactionStore,
+    StorageError, TransactionStoreManager,
+};
+
+/// In-memory processor that runs `TransactionProcessor::simulate` on copies
+type ScratchProcessor<A> =
+    TransactionProcessor<A, ConcurrentAccountManager<A>, ConcurrentTransactionStore<A>>;
 
 /// Transaction processor orchestrating domain operations and storage
 pub struct TransactionProcessor<A, M, T>
@@ -17,6 +37,25 @@ where
 {
     account_manager: M,
     transaction_store: T,
+    allow_admin_ops: bool,
+    audit_sink: Option<Arc<dyn AuditSink<A>>>,
+    validators: Vec<Arc<dyn TransactionValidator<A>>>,
+    dispute_policy: DisputePolicy,
+    fee_schedule: Option<Arc<FeeSchedule<A>>>,
+    skip_locked: bool,
+    locked_skipped: u64,
+    quarantine_sink: Option<Arc<dyn QuarantineSink<A>>>,
+    quarantined: u64,
+    type_counts: TransactionTypeCounts,
+    idempotency_keys: Arc<IdempotencyKeys>,
+    idempotent_replays: u64,
+    order_verifier: Option<Arc<OrderVerifier>>,
+    /// Event timestamp of the transaction being processed, for validators
+    event_time: Option<u64>,
+    /// Whether this is the scratch processor of a simulation
+    simulating: bool,
+    #[cfg(feature = "metrics")]
+    metrics: Option<MetricsRegistry>,
     _phantom: PhantomData<A>,
 }
 
@@ -26,34 +65,509 @@ where
     M: ClientAccountManager<A>,
     T: TransactionStoreManager<A>,
 {
+    /// Start configuring a processor whose storage is given last
+    ///
+    /// See `TransactionProcessorBuilder`; `new` remains the way to create a
+    /// processor with every option at its default.
+    pub fn builder() -> TransactionProcessorBuilder<A, M, T> {
+        TransactionProcessorBuilder::default()
+    }
+
     /// Create a new transaction processor
     pub fn new(account_manager: M, transaction_store: T) -> Self {
         Self {
             account_manager,
             transaction_store,
+            allow_admin_ops: false,
+            audit_sink: None,
+            validators: Vec::new(),
+            dispute_policy: DisputePolicy::default(),
+            fee_schedule: None,
+            skip_locked: false,
+            locked_skipped: 0,
+            quarantine_sink: None,
+            quarantined: 0,
+            type_counts: TransactionTypeCounts::default(),
+            idempotency_keys: Arc::new(IdempotencyKeys::new()),
+            idempotent_replays: 0,
+            order_verifier: None,
+            event_time: None,
+            simulating: false,
+            #[cfg(feature = "metrics")]
+            metrics: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Accept administrative operations such as `Transaction::Unlock` (defaults to false)
+    ///
+    /// Only enable this for processors fed by trusted back-office input; partner
+    /// feeds should be processed with admin operations disabled.
+    pub fn with_admin_ops(mut self, enabled: bool) -> Self {
+        self.allow_admin_ops = enabled;
+        self
+    }
+
+    /// Report every applied or rejected transaction to an audit sink
+    ///
+    /// Balances of the affected accounts are read before and after each
+    /// transaction, so auditing adds two account reads per affected account.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink<A>>) -> Self {
+        self.audit_sink = Some(sink);
+        self
+    }
+
+    /// Select dispute semantics for disputes and resolves (defaults to `DisputePolicy::default()`)
+    pub fn with_dispute_policy(mut self, policy: DisputePolicy) -> Self {
+        self.dispute_policy = policy;
+        self
+    }
+
+    /// Charge deposit and withdrawal fees per `schedule`
+    ///
+    /// Each fee moves to the schedule's fee account in the same atomic update
+    /// as the transaction itself. Deposits credit the client the amount less
+    /// the fee; withdrawals need the amount plus the fee. Disputes still
+    /// refer to the gross deposited amount, and fees are not refunded.
+    pub fn with_fee_schedule(mut self, schedule: Arc<FeeSchedule<A>>) -> Self {
+        self.fee_schedule = Some(schedule);
+        self
+    }
+
+    /// Treat transactions on locked accounts as no-ops instead of errors (defaults to false)
+    ///
+    /// After a chargeback, partners often keep sending traffic for the frozen
+    /// account. When enabled, such transactions leave the account unchanged,
+    /// are counted in `locked_skipped` and return `Ok`, so `AbortOnError`
+    /// pipelines keep running. Audit sinks still see them as rejected.
+    pub fn with_skip_locked(mut self, enabled: bool) -> Self {
+        self.skip_locked = enabled;
+        self
+    }
+
+    /// Number of transactions skipped because their account was locked
+    pub fn locked_skipped(&self) -> u64 {
+        self.locked_skipped
+    }
+
+    /// Divert transactions on locked accounts to a quarantine sink
+    ///
+    /// Each such transaction is handed to `sink` with the rejection reason,
+    /// counted in `quarantined` and returns `Ok`, so it can be re-run after
+    /// an unlock instead of being lost. Takes precedence over
+    /// `with_skip_locked`; audit sinks and type counts still see it as
+    /// rejected, and its idempotency key (if any) stays usable for the re-run.
+    pub fn with_quarantine_sink(mut self, sink: Arc<dyn QuarantineSink<A>>) -> Self {
+        self.quarantine_sink = Some(sink);
+        self
+    }
+
+    /// Number of transactions diverted to the quarantine sink
+    pub fn quarantined(&self) -> u64 {
+        self.quarantined
+    }
+
+    /// Applied and rejected transactions so far, by type
+    ///
+    /// Transactions skipped on locked accounts count as rejected.
+    pub fn type_counts(&self) -> &TransactionTypeCounts {
+        &self.type_counts
+    }
+
+    /// Recognise replays against a shared set of idempotency keys (defaults to
+    /// a set private to this processor)
+    ///
+    /// See `process_transaction_with_key`.
+    pub fn with_idempotency_keys(mut self, keys: Arc<IdempotencyKeys>) -> Self {
+        self.idempotency_keys = keys;
+        self
+    }
+
+    /// Number of transactions skipped as replays of an already used idempotency key
+    pub fn idempotent_replays(&self) -> u64 {
+        self.idempotent_replays
+    }
+
+    /// Record each client's processing order in `verifier` (see `OrderVerifier`)
+    ///
+    /// Every processed transaction is recorded, applied or not, except
+    /// idempotent replays.
+    pub fn with_order_verification(mut self, verifier: Arc<OrderVerifier>) -> Self {
+        self.order_verifier = Some(verifier);
+        self
+    }
+
+    /// Out-of-order transactions found so far, if order verification is enabled
+    pub fn ordering_report(&self) -> Option<OrderingReport> {
+        self.order_verifier
+            .as_ref()
+            .map(|verifier| verifier.ordering_report())
+    }
+
+    /// Add a business rule checked before every transaction is applied
+    ///
+    /// Validators run in the order they were added; the first failure rejects
+    /// the transaction with `EngineError::Rejected`.
+    pub fn with_validator(mut self, validator: Arc<dyn TransactionValidator<A>>) -> Self {
+        self.validators.push(validator);
+        self
+    }
+
+    /// Report transaction counts, errors and latency to a metrics registry
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: MetricsRegistry) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Process a single transaction
     pub fn process_transaction(&mut self, tx: Transaction<A>) -> Result<(), EngineError> {
-        match tx {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.clone() {
+            let kind = tx.type_name();
+            let started = Instant::now();
+            let result = self.skip_locked_transaction(tx);
+            metrics.record_transaction(kind, started.elapsed(), result.as_ref().err());
+            return result;
+        }
+
+        self.skip_locked_transaction(tx)
+    }
+
+    /// Compute what `tx` would do without changing storage
+    ///
+    /// The transaction goes through the same checks and domain operations as
+    /// in `process_transaction` (admin ops, validators, dispute policy, fees),
+    /// but on copies of the accounts and transaction record it reads, for
+    /// pre-validation APIs that tell a caller whether a transaction would be
+    /// accepted and what the balances would become. Locked-account skipping,
+    /// quarantine, auditing, idempotency keys and counters are left out.
+    /// Validators are consulted as for a real transaction, without recording
+    /// it in stateful ones such as `VelocityLimit`. Concurrent updates
+    /// between simulating and processing can change the real outcome.
+    pub fn simulate(&self, tx: Transaction<A>) -> SimulationOutcome<A> {
+        // Client, counterparty and fee account, each once
+        let mut clients = vec![tx.client_id()];
+        let counterparty = match tx {
+            Transaction::Transfer { to_client, .. } => Some(to_client),
+            _ => None,
+        };
+        let fee_account = match (&self.fee_schedule, &tx) {
+            (Some(schedule), Transaction::Deposit { .. } | Transaction::Withdrawal { .. }) => {
+                Some(schedule.fee_account())
+            }
+            _ => None,
+        };
+        for client_id in counterparty.into_iter().chain(fee_account) {
+            if !clients.contains(&client_id) {
+                clients.push(client_id);
+            }
+        }
+
+        let mut scratch = ScratchProcessor::new(
+            ConcurrentAccountManager::new(),
+            ConcurrentTransactionStore::new(),
+        )
+        .with_admin_ops(self.allow_admin_ops)
+        .with_dispute_policy(self.dispute_policy);
+        scratch.validators = self.validators.clone();
+        scratch.fee_schedule = self.fee_schedule.clone();
+        scratch.simulating = true;
+
+        let result = self
+            .copy_for_simulation(&scratch, &clients, &tx)
+            .and_then(|()| scratch.apply_transaction(tx));
+        let accounts = clients
+            .into_iter()
+            .map(|client_id| {
+                scratch
+                    .account_manager
+                    .account(client_id)
+                    .unwrap_or_else(|| ClientAccount::new(client_id))
+            })
+            .collect();
+
+        SimulationOutcome { result, accounts }
+    }
+
+    /// Copy the accounts and the transaction record a simulation reads
+    fn copy_for_simulation(
+        &self,
+        scratch: &ScratchProcessor<A>,
+        clients: &[ClientId],
+        tx: &Transaction<A>,
+    ) -> Result<(), EngineError> {
+        for &client_id in clients {
+            if let Some(account) = self.account_manager.get(client_id)? {
+                scratch
+                    .account_manager
+                    .entry(client_id)?
+                    .try_update(|copy| {
+                        *copy = account;
+                        Ok(())
+                    })?;
+            }
+        }
+        if let Some(tx_id) = tx.tx_id()
+            && let Some(record) = self.transaction_store.get(tx_id)
+        {
+            scratch.transaction_store.insert(tx_id, record);
+        }
+        Ok(())
+    }
+
+    /// Process a single transaction carrying an optional idempotency key
+    ///
+    /// If the client already has an applied transaction with the same key,
+    /// this one is skipped as a replay: nothing changes, it is counted in
+    /// `idempotent_replays` and `Ok` is returned. Otherwise the transaction is
+    /// processed as usual and its key is recorded if it was applied.
+    pub fn process_transaction_with_key(
+        &mut self,
+        tx: Transaction<A>,
+        key: Option<&str>,
+    ) -> Result<(), EngineError> {
+        let Some(key) = key else {
+            return self.process_transaction(tx);
+        };
+
+        let client_id = tx.client_id();
+        if !self.idempotency_keys.claim(client_id, key) {
+            debug!(client_id, key, "Skipped replayed idempotency key");
+            self.idempotent_replays += 1;
+            return Ok(());
+        }
+
+        let quarantined = self.quarantined;
+        let result = self.process_transaction(tx);
+        // A quarantined transaction is re-run later, under the same key
+        if result.is_err() || self.quarantined > quarantined {
+            self.idempotency_keys.release(client_id, key);
+        }
+        result
+    }
+
+    /// Process a transaction with its idempotency key and event timestamp
+    ///
+    /// As `process_transaction_with_key`; the timestamp, if any, is passed to
+    /// validators, for time windows such as `VelocityLimit::with_time_window`.
+    pub fn process_transaction_at(
+        &mut self,
+        tx: Transaction<A>,
+        key: Option<&str>,
+        timestamp: Option<u64>,
+    ) -> Result<(), EngineError> {
+        self.event_time = timestamp;
+        let result = self.process_transaction_with_key(tx, key);
+        self.event_time = None;
+        result
+    }
+
+    fn skip_locked_transaction(&mut self, tx: Transaction<A>) -> Result<(), EngineError> {
+        let client_id = tx.client_id();
+        let kind = tx.type_name();
+        let step = self.order_verifier.as_ref().map(|_| Step::of(&tx));
+        // Only keep a copy of the transaction when it may be quarantined
+        let diverted = self.quarantine_sink.as_ref().map(|_| tx.clone());
+        let result = self.audit_transaction(tx);
+        self.type_counts.record(kind, result.is_ok());
+        if let (Some(verifier), Some(step)) = (&self.order_verifier, step) {
+            verifier.record(step, result.is_ok());
+        }
+        match (result, &self.quarantine_sink, diverted) {
+            (Err(e), Some(sink), Some(transaction)) if e.is_account_locked() => {
+                debug!(client_id, "Quarantined transaction on locked account");
+                sink.quarantine(QuarantinedTransaction {
+                    transaction,
+                    reason: e.to_string(),
+                });
+                self.quarantined += 1;
+                Ok(())
+            }
+            (Err(e), _, _) if self.skip_locked && e.is_account_locked() => {
+                debug!(client_id, "Skipped transaction on locked account");
+                self.locked_skipped += 1;
+                Ok(())
+            }
+            (result, _, _) => result,
+        }
+    }
+
+    fn audit_transaction(&mut self, tx: Transaction<A>) -> Result<(), EngineError> {
+        let Some(sink) = self.audit_sink.clone() else {
+            return self.apply_transaction(tx);
+        };
+
+        // (client, counterparty) for every account the transaction touches
+        let affected = match tx {
+            Transaction::Transfer {
+                from_client,
+                to_client,
+                ..
+            } => vec![
+                (from_client, Some(to_client)),
+                (to_client, Some(from_client)),
+            ],
+            _ => vec![(tx.client_id(), None)],
+        };
+        let operation = (&tx).into();
+        let tx_id = tx.tx_id();
+
+        // Disputes, captures and releases act in the original transaction's currency
+        let currency = match tx {
+            Transaction::Dispute { tx_id, .. }
+            | Transaction::Resolve { tx_id, .. }
+            | Transaction::Chargeback { tx_id, .. }
+            | Transaction::ChargebackReversal { tx_id, .. }
+            | Transaction::Capture { tx_id, .. }
+            | Transaction::Release { tx_id, .. } => self
+                .transaction_store
+                .get(tx_id)
+                .and_then(|record| record.currency),
+            _ => tx.currency(),
+        };
+
+        let before = affected
+            .iter()
+            .map(|(client_id, _)| self.balances(*client_id, currency))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let result = self.apply_transaction(tx);
+        let outcome = match &result {
+            Ok(()) => AuditOutcome::Applied,
+            Err(e) => AuditOutcome::Rejected(e.to_string()),
+        };
+
+        for ((client_id, counterparty), before) in affected.into_iter().zip(before) {
+            sink.record(AuditRecord {
+                tx_id,
+                client_id,
+                counterparty,
+                operation,
+                currency,
+                before,
+                after: self.balances(client_id, currency)?,
+                outcome: outcome.clone(),
+            });
+        }
+
+        result
+    }
+
+    fn balances(
+        &self,
+        client_id: ClientId,
+        currency: Option<CurrencyCode>,
+    ) -> Result<BalanceSnapshot<A>, EngineError> {
+        Ok(BalanceSnapshot::of(
+            &self.account_or_new(client_id)?,
+            currency,
+        ))
+    }
+
+    /// Copy of the client's account, or a new one if it has none yet
+    fn account_or_new(&self, client_id: ClientId) -> Result<ClientAccount<A>, EngineError> {
+        Ok(self
+            .account_manager
+            .get(client_id)?
+            .unwrap_or_else(|| ClientAccount::new(client_id)))
+    }
+
+    fn apply_transaction(&mut self, tx: Transaction<A>) -> Result<(), EngineError> {
+        if tx.is_admin() && !self.allow_admin_ops {
+            warn!(client_id = tx.client_id(), "Rejected admin operation");
+            return Err(EngineError::AdminOperationNotAllowed);
+        }
+
+        // Looked up once, for the validators that check the client's account
+        let mut account = None;
+        for validator in &self.validators {
+            let mut verdict = validator.validate_event(&tx, self.event_time, !self.simulating);
+            if verdict.is_ok() && validator.needs_account() {
+                if account.is_none() {
+                    account = Some(self.account_or_new(tx.client_id())?);
+                }
+                verdict = account
+                    .as_ref()
+                    .map_or(Ok(()), |account| validator.validate_account(&tx, account));
+            }
+            if let Err(reason) = verdict {
+                debug!(client_id = tx.client_id(), %reason, "Transaction rejected by validator");
+                return Err(validator.rejection(reason));
+            }
+        }
+
+        // Accounts to check once the transaction has been applied
+        #[cfg(debug_assertions)]
+        let touched = match tx {
+            Transaction::Transfer {
+                from_client,
+                to_client,
+                ..
+            } => vec![from_client, to_client],
+            _ => vec![tx.client_id()],
+        };
+
+        let result = match tx {
             Transaction::Deposit {
                 client_id,
                 tx_id,
                 amount,
-            } => self.process_deposit(client_id, tx_id, amount),
+                currency,
+            } => self.process_deposit(client_id, tx_id, amount, currency),
             Transaction::Withdrawal {
                 client_id,
                 tx_id,
                 amount,
-            } => self.process_withdrawal(client_id, tx_id, amount),
+                currency,
+            } => self.process_withdrawal(client_id, tx_id, amount, currency),
             Transaction::Dispute { client_id, tx_id } => self.process_dispute(client_id, tx_id),
             Transaction::Resolve { client_id, tx_id } => self.process_resolve(client_id, tx_id),
             Transaction::Chargeback { client_id, tx_id } => {
                 self.process_chargeback(client_id, tx_id)
             }
+            Transaction::ChargebackReversal { client_id, tx_id } => {
+                self.process_chargeback_reversal(client_id, tx_id)
+            }
+            Transaction::Transfer {
+                from_client,
+                to_client,
+                tx_id,
+                amount,
+                currency,
+            } => self.process_transfer(from_client, to_client, tx_id, amount, currency),
+            Transaction::Hold {
+                client_id,
+                tx_id,
+                amount,
+                currency,
+            } => self.process_hold(client_id, tx_id, amount, currency),
+            Transaction::Capture { client_id, tx_id } => self.process_capture(client_id, tx_id),
+            Transaction::Release { client_id, tx_id } => self.process_release(client_id, tx_id),
+            Transaction::Unlock { client_id } => self.process_unlock(client_id),
+            Transaction::SetCreditLimit { client_id, limit } => {
+                self.process_set_credit_limit(client_id, limit)
+            }
+            Transaction::Adjustment {
+                client_id,
+                tx_id,
+                amount,
+            } => self.process_adjustment(client_id, tx_id, amount),
+            Transaction::SetTag {
+                client_id,
+                key,
+                value,
+            } => self.process_set_tag(client_id, &key, value.as_deref()),
+        };
+
+        #[cfg(debug_assertions)]
+        for client_id in touched {
+            if let Ok(Some(account)) = self.account_manager.get(client_id) {
+                debug_assert_eq!(account.validate(), Ok(()), "client {client_id}");
+            }
         }
+
+        result
     }
 
     /// Get reference to account manager for snapshot operations
@@ -61,136 +575,413 @@ where
         &self.account_manager
     }
 
+    /// Get reference to the transaction store
+    pub fn transaction_store(&self) -> &T {
+        &self.transaction_store
+    }
+
     fn process_deposit(
         &mut self,
-        client_id: u16,
-        tx_id: u32,
+        client_id: ClientId,
+        tx_id: TransactionId,
         amount: A,
+        currency: Option<CurrencyCode>,
     ) -> Result<(), EngineError> {
         debug!(client_id, tx_id, "Processing deposit");
 
-        // Apply deposit to account
-        let mut entry = self.account_manager.entry(client_id)?;
-        entry.try_update(|account| apply_deposit(account, amount))?;
-
-        // Record transaction for potential disputes
-        self.transaction_store
-            .insert(tx_id, TransactionRecord::new(client_id, amount));
-
-        Ok(())
+        let fee = self.fee(FeeType::Deposit, client_id, amount)?;
+
+        // Apply the deposit and record it for potential disputes
+        let record = TransactionRecord::new(client_id, amount).with_currency(currency);
+        self.transaction_store.apply_and_record(tx_id, record, || {
+            match fee {
+                // Credit the net amount and collect the fee atomically
+                Some((fee_account, fee)) => {
+                    let op = PairOp::DepositWithFee {
+                        amount,
+                        fee,
+                        currency,
+                    };
+                    self.account_manager
+                        .try_apply_pair(client_id, fee_account, &op)?;
+                }
+                None => {
+                    let mut entry = self.account_manager.entry(client_id)?;
+                    entry.try_apply(&AccountOp::Deposit { amount, currency })?;
+                }
+            }
+            Ok::<_, EngineError>(())
+        })
     }
 
     fn process_withdrawal(
         &mut self,
-        client_id: u16,
-        tx_id: u32,
+        client_id: ClientId,
+        tx_id: TransactionId,
         amount: A,
+        currency: Option<CurrencyCode>,
     ) -> Result<(), EngineError> {
         debug!(client_id, tx_id, "Processing withdrawal");
 
-        // Apply withdrawal to account
+        let fee = self.fee(FeeType::Withdrawal, client_id, amount)?;
+
+        // Record the withdrawal too (it cannot be disputed, but track for completeness)
+        let record = TransactionRecord::new(client_id, amount)
+            .with_currency(currency)
+            .with_kind(TxKind::Withdrawal);
+        self.transaction_store.apply_and_record(tx_id, record, || {
+            match fee {
+                // Debit amount and fee and collect the fee atomically
+                Some((fee_account, fee)) => {
+                    let op = PairOp::WithdrawalWithFee {
+                        amount,
+                        fee,
+                        currency,
+                    };
+                    self.account_manager
+                        .try_apply_pair(client_id, fee_account, &op)?;
+                }
+                None => {
+                    let mut entry = self.account_manager.entry(client_id)?;
+                    entry.try_apply(&AccountOp::Withdrawal { amount, currency })?;
+                }
+            }
+            Ok::<_, EngineError>(())
+        })
+    }
+
+    /// Fee account and non-zero fee owed on a transaction, if any
+    fn fee(
+        &self,
+        fee_type: FeeType,
+        client_id: ClientId,
+        amount: A,
+    ) -> Result<Option<(ClientId, A)>, EngineError> {
+        let Some(schedule) = &self.fee_schedule else {
+            return Ok(None);
+        };
+
+        // Tier tags need the client's account; plain schedules do without
+        let fee = match schedule.tier_tag() {
+            Some(_) => {
+                schedule.fee_for_account(fee_type, &self.account_or_new(client_id)?, amount)?
+            }
+            None => schedule.fee_for(fee_type, client_id, amount)?,
+        };
+        Ok((fee > A::zero()).then(|| (schedule.fee_account(), fee)))
+    }
+
+    fn process_transfer(
+        &mut self,
+        from_client: ClientId,
+        to_client: ClientId,
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+    ) -> Result<(), EngineError> {
+        debug!(from_client, to_client, tx_id, "Processing transfer");
+
+        // Record transaction against the sending client (like a withdrawal)
+        let record = TransactionRecord::new(from_client, amount)
+            .with_currency(currency)
+            .with_kind(TxKind::Transfer);
+
+        // Debit sender and credit receiver atomically (both sides in the same currency)
+        self.transaction_store.apply_and_record(tx_id, record, || {
+            self.account_manager
+                .try_apply_pair(
+                    from_client,
+                    to_client,
+                    &PairOp::Transfer { amount, currency },
+                )
+                .map_err(EngineError::from)
+        })
+    }
+
+    fn process_hold(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+        amount: A,
+        currency: Option<CurrencyCode>,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, tx_id, "Processing hold");
+
+        // Record the authorization so it can be captured or released
+        let record = TransactionRecord::new(client_id, amount)
+            .with_currency(currency)
+            .with_kind(TxKind::Hold);
+
+        // Reserve available funds in held
+        self.transaction_store.apply_and_record(tx_id, record, || {
+            let mut entry = self.account_manager.entry(client_id)?;
+            entry.try_apply(&AccountOp::Hold {
+                tx_id,
+                amount,
+                currency,
+            })?;
+            Ok::<_, EngineError>(())
+        })
+    }
+
+    fn process_capture(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, tx_id, "Processing capture");
+
+        let record = self.hold_record(client_id, tx_id)?;
+
+        // Settle the held funds (they leave the account)
         let mut entry = self.account_manager.entry(client_id)?;
-        entry.try_update(|account| apply_withdrawal(account, amount))?;
+        entry.try_apply(&AccountOp::Capture {
+            tx_id,
+            amount: record.amount,
+            currency: record.currency,
+        })?;
+
+        Ok(())
+    }
+
+    fn process_release(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, tx_id, "Processing release");
 
-        // Record transaction (withdrawals cannot be disputed, but track for completeness)
-        self.transaction_store
-            .insert(tx_id, TransactionRecord::new(client_id, amount));
+        let record = self.hold_record(client_id, tx_id)?;
+
+        // Return the held funds to available
+        let mut entry = self.account_manager.entry(client_id)?;
+        entry.try_apply(&AccountOp::Release {
+            tx_id,
+            amount: record.amount,
+            currency: record.currency,
+        })?;
 
         Ok(())
     }
 
-    fn process_dispute(&mut self, client_id: u16, tx_id: u32) -> Result<(), EngineError> {
-        debug!(client_id, tx_id, "Processing dispute");
+    /// Look up the authorization a capture or release refers to
+    fn hold_record(
+        &self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<TransactionRecord<A>, EngineError> {
+        owned_record(self.transaction_store.get(tx_id), client_id, tx_id, "Hold")
+    }
 
-        // Look up the original transaction
-        let record = self
-            .transaction_store
-            .get(tx_id)
-            .ok_or(EngineError::TransactionNotFound(tx_id))?;
+    fn process_unlock(&mut self, client_id: ClientId) -> Result<(), EngineError> {
+        debug!(client_id, "Processing unlock");
 
-        // Verify transaction belongs to this client
-        if record.client_id != client_id {
-            warn!(
-                client_id,
-                tx_id,
-                record_client_id = record.client_id,
-                "Dispute client mismatch"
-            );
-            return Err(EngineError::TransactionNotFound(tx_id));
-        }
+        let mut entry = self.account_manager.entry(client_id)?;
+        entry.try_apply(&AccountOp::Unlock)?;
 
-        let amount = record.amount;
+        Ok(())
+    }
+
+    fn process_set_credit_limit(
+        &mut self,
+        client_id: ClientId,
+        limit: A,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, "Processing credit limit");
 
-        // Apply dispute to account (move funds to held + track dispute)
         let mut entry = self.account_manager.entry(client_id)?;
-        entry.try_update(|account| apply_dispute(account, tx_id, amount))?;
+        entry.try_apply(&AccountOp::SetCreditLimit { limit })?;
 
         Ok(())
     }
 
-    fn process_resolve(&mut self, client_id: u16, tx_id: u32) -> Result<(), EngineError> {
-        debug!(client_id, tx_id, "Processing resolve");
+    fn process_set_tag(
+        &mut self,
+        client_id: ClientId,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, key, "Processing tag");
 
-        // Look up the original transaction
-        let record = self
-            .transaction_store
-            .get(tx_id)
-            .ok_or(EngineError::TransactionNotFound(tx_id))?;
+        let mut entry = self.account_manager.entry(client_id)?;
+        entry.try_apply(&AccountOp::SetTag {
+            key: key.to_string(),
+            value: value.map(str::to_string),
+        })?;
 
-        // Verify transaction belongs to this client
-        if record.client_id != client_id {
-            warn!(
-                client_id,
-                tx_id,
-                record_client_id = record.client_id,
-                "Resolve client mismatch"
-            );
-            return Err(EngineError::TransactionNotFound(tx_id));
-        }
+        Ok(())
+    }
 
-        let amount = record.amount;
+    fn process_adjustment(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+        amount: A,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, tx_id, "Processing adjustment");
 
-        // Apply resolve to account (move funds from held to available + remove dispute)
+        // Corrections are final: not recorded, so they cannot be disputed
         let mut entry = self.account_manager.entry(client_id)?;
-        entry.try_update(|account| apply_resolve(account, tx_id, amount))?;
+        entry.try_apply(&AccountOp::Adjustment { amount })?;
 
         Ok(())
     }
 
-    fn process_chargeback(&mut self, client_id: u16, tx_id: u32) -> Result<(), EngineError> {
+    fn process_dispute(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, tx_id, "Processing dispute");
+
+        let policy = self.dispute_policy;
+        self.transaction_store.apply_and_update(tx_id, |record| {
+            let record = owned_record(record, client_id, tx_id, "Dispute")?;
+            let amount = record.amount;
+
+            // Only deposits move through the dispute lifecycle; its errors surface
+            // like the account-level checks that back them up
+            if !record.kind.is_disputable() {
+                return Err(StorageError::from(DomainError::NotDisputable).into());
+            }
+            let state = record
+                .state
+                .dispute(policy.allow_redispute)
+                .map_err(StorageError::from)?;
+            if policy
+                .max_disputes
+                .is_some_and(|max| record.disputes >= max)
+            {
+                return Err(StorageError::from(DomainError::DisputeLimitReached).into());
+            }
+
+            // Apply dispute to account (move funds to held + track dispute)
+            let mut entry = self.account_manager.entry(client_id)?;
+            let held = entry
+                .try_apply(&AccountOp::Dispute {
+                    tx_id,
+                    amount,
+                    currency: record.currency,
+                    policy,
+                })?
+                .unwrap_or(amount);
+
+            // A partly held dispute records its hold so it releases no more
+            let disputes = record.disputes + 1;
+            Ok(record
+                .with_state(state)
+                .with_disputes(disputes)
+                .with_held((held != amount).then_some(held)))
+        })
+    }
+
+    fn process_resolve(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, tx_id, "Processing resolve");
+
+        let policy = self.dispute_policy;
+        self.transaction_store.apply_and_update(tx_id, |record| {
+            let record = owned_record(record, client_id, tx_id, "Resolve")?;
+            let held = record.held_amount();
+            let state = record.state.resolve().map_err(StorageError::from)?;
+
+            // Apply resolve to account (move funds from held to available + remove dispute)
+            let mut entry = self.account_manager.entry(client_id)?;
+            entry.try_apply(&AccountOp::Resolve {
+                tx_id,
+                held,
+                currency: record.currency,
+                policy,
+            })?;
+
+            Ok(record.with_state(state).with_held(None))
+        })
+    }
+
+    fn process_chargeback(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<(), EngineError> {
         debug!(client_id, tx_id, "Processing chargeback");
 
-        // Look up the original transaction
-        let record = self
-            .transaction_store
-            .get(tx_id)
-            .ok_or(EngineError::TransactionNotFound(tx_id))?;
+        let policy = self.dispute_policy;
+        self.transaction_store.apply_and_update(tx_id, |record| {
+            let record = owned_record(record, client_id, tx_id, "Chargeback")?;
+            let (amount, held) = (record.amount, record.held_amount());
+            let state = record.state.charge_back().map_err(StorageError::from)?;
 
-        // Verify transaction belongs to this client
-        if record.client_id != client_id {
-            warn!(
-                client_id,
+            // Apply chargeback to account (remove held funds, lock, and remove dispute)
+            let mut entry = self.account_manager.entry(client_id)?;
+            entry.try_apply(&AccountOp::Chargeback {
                 tx_id,
-                record_client_id = record.client_id,
-                "Chargeback client mismatch"
-            );
-            return Err(EngineError::TransactionNotFound(tx_id));
-        }
+                amount,
+                held,
+                currency: record.currency,
+                policy,
+            })?;
 
-        let amount = record.amount;
+            Ok(record.with_state(state).with_held(None))
+        })
+    }
 
-        // Apply chargeback to account (remove held funds, lock, and remove dispute)
-        let mut entry = self.account_manager.entry(client_id)?;
-        entry.try_update(|account| apply_chargeback(account, tx_id, amount))?;
+    fn process_chargeback_reversal(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<(), EngineError> {
+        debug!(client_id, tx_id, "Processing chargeback reversal");
+
+        let policy = self.dispute_policy;
+        self.transaction_store.apply_and_update(tx_id, |record| {
+            let record = owned_record(record, client_id, tx_id, "Chargeback reversal")?;
+            let state = record
+                .state
+                .reverse_chargeback()
+                .map_err(StorageError::from)?;
+
+            // Return the charged-back funds to available (and unlock, per policy)
+            let mut entry = self.account_manager.entry(client_id)?;
+            entry.try_apply(&AccountOp::ChargebackReversal {
+                amount: record.amount,
+                currency: record.currency,
+                policy,
+            })?;
+
+            Ok(record.with_state(state))
+        })
+    }
+}
 
-        Ok(())
+/// The looked-up record of `tx_id`, if it belongs to `client_id`
+///
+/// Records of other clients are reported as missing, like unknown ids.
+fn owned_record<A: AmountType>(
+    record: Option<TransactionRecord<A>>,
+    client_id: ClientId,
+    tx_id: TransactionId,
+    action: &str,
+) -> Result<TransactionRecord<A>, EngineError> {
+    let record = record.ok_or(EngineError::TransactionNotFound(tx_id))?;
+    if record.client_id != client_id {
+        warn!(
+            client_id,
+            tx_id,
+            record_client_id = record.client_id,
+            "{action} client mismatch"
+        );
+        return Err(EngineError::TransactionNotFound(tx_id));
     }
+    Ok(record)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::{DomainError, FixedPoint};
+    use crate::domain::{DomainError, Fee, FixedPoint, TxState};
     use crate::storage::{ClientAccountEntry, ConcurrentAccountManager, ConcurrentTransactionStore, StorageError};
 
     #[test]
@@ -203,6 +994,7 @@ mod tests {
             client_id: 1,
             tx_id: 1,
             amount: FixedPoint::from_raw(10_000),
+            currency: None,
         };
 
         processor.process_transaction(tx).unwrap();
@@ -212,6 +1004,34 @@ mod tests {
         assert_eq!(account.available(), FixedPoint::from_raw(10_000));
     }
 
+    #[test]
+    fn deposit_reusing_a_tx_id_is_rejected() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+        let deposit = |client_id, raw| Transaction::Deposit {
+            client_id,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        };
+
+        processor.process_transaction(deposit(1, 10_000)).unwrap();
+        let result = processor.process_transaction(deposit(2, 50_000));
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DuplicateTransaction(1)))
+        ));
+        // Neither the second client nor the first deposit's record changed
+        assert!(processor.account_manager.get(2).unwrap().is_none());
+        let record = processor.transaction_store.get(1).unwrap();
+        assert_eq!(
+            (record.client_id, record.amount),
+            (1, FixedPoint::from_raw(10_000))
+        );
+    }
+
     #[test]
     fn process_withdrawal_debits_account() {
         let manager = ConcurrentAccountManager::<FixedPoint>::new();
@@ -224,6 +1044,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -233,6 +1054,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 2,
                 amount: FixedPoint::from_raw(3_000),
+                currency: None,
             })
             .unwrap();
 
@@ -252,6 +1074,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(1_000),
+                currency: None,
             })
             .unwrap();
 
@@ -259,6 +1082,7 @@ mod tests {
             client_id: 1,
             tx_id: 2,
             amount: FixedPoint::from_raw(2_000),
+            currency: None,
         });
 
         assert!(result.is_err());
@@ -295,6 +1119,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -326,6 +1151,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -359,6 +1185,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -386,7 +1213,7 @@ mod tests {
     }
 
     #[test]
-    fn resolve_requires_disputed_transaction() {
+    fn only_deposits_can_be_disputed() {
         let manager = ConcurrentAccountManager::<FixedPoint>::new();
         let store = ConcurrentTransactionStore::new();
         let mut processor = TransactionProcessor::new(manager, store);
@@ -396,49 +1223,142 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(4_000),
+                currency: None,
             })
             .unwrap();
 
-        let result = processor.process_transaction(Transaction::Resolve {
+        let result = processor.process_transaction(Transaction::Dispute {
             client_id: 1,
-            tx_id: 1,
+            tx_id: 2,
         });
 
         assert!(matches!(
             result,
-            Err(EngineError::Storage(StorageError::DomainError(DomainError::NotDisputed)))
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::NotDisputable
+            )))
         ));
+        assert_eq!(
+            processor
+                .transaction_store
+                .get(2)
+                .map(|r| (r.kind, r.state)),
+            Some((TxKind::Withdrawal, TxState::Posted))
+        );
+        assert_eq!(
+            processor.account_manager.entry(1).unwrap().read().held(),
+            FixedPoint::zero()
+        );
     }
 
     #[test]
-    fn chargeback_removes_held_and_locks_account() {
+    fn charged_back_transactions_stay_final_after_unlock() {
         let manager = ConcurrentAccountManager::<FixedPoint>::new();
         let store = ConcurrentTransactionStore::new();
-        let mut processor = TransactionProcessor::new(manager, store);
+        let mut processor = TransactionProcessor::new(manager, store).with_admin_ops(true);
 
-        // Deposit and dispute
-        processor
-            .process_transaction(Transaction::Deposit {
+        for tx in [
+            Transaction::Deposit {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
-            })
-            .unwrap();
-
-        processor
-            .process_transaction(Transaction::Dispute {
+                currency: None,
+            },
+            Transaction::Dispute {
                 client_id: 1,
                 tx_id: 1,
-            })
-            .unwrap();
-
-        // Chargeback
-        processor
-            .process_transaction(Transaction::Chargeback {
+            },
+            Transaction::Chargeback {
                 client_id: 1,
                 tx_id: 1,
-            })
-            .unwrap();
+            },
+            Transaction::Unlock { client_id: 1 },
+        ] {
+            processor.process_transaction(tx).unwrap();
+        }
+        assert_eq!(
+            processor.transaction_store.get(1).map(|r| r.state),
+            Some(TxState::ChargedBack)
+        );
+
+        // The account no longer tracks the dispute; the record still knows
+        let result = processor.process_transaction(Transaction::Dispute {
+            client_id: 1,
+            tx_id: 1,
+        });
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::AlreadyChargedBack
+            )))
+        ));
+    }
+
+    #[test]
+    fn resolve_requires_disputed_transaction() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+
+        let result = processor.process_transaction(Transaction::Resolve {
+            client_id: 1,
+            tx_id: 1,
+        });
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(DomainError::NotDisputed)))
+        ));
+    }
+
+    #[test]
+    fn chargeback_removes_held_and_locks_account() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+
+        // Deposit and dispute
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+
+        processor
+            .process_transaction(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+
+        // Chargeback
+        processor
+            .process_transaction(Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
 
         let entry = processor.account_manager.entry(1).unwrap();
         let account = entry.read();
@@ -448,6 +1368,63 @@ mod tests {
         assert!(account.is_locked());
     }
 
+    #[test]
+    fn chargeback_reversal_restores_funds_once() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_dispute_policy(DisputePolicy::default().with_unlock_on_reversal(true));
+
+        let reversal = Transaction::ChargebackReversal {
+            client_id: 1,
+            tx_id: 1,
+        };
+        for tx in [
+            Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            },
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            },
+        ] {
+            processor.process_transaction(tx).unwrap();
+        }
+
+        // Only a charged-back transaction can be reversed
+        assert!(matches!(
+            processor.process_transaction(reversal.clone()),
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::NotChargedBack
+            )))
+        ));
+
+        processor
+            .process_transaction(Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+        processor.process_transaction(reversal.clone()).unwrap();
+
+        let account = processor.account_manager.entry(1).unwrap().read().clone();
+        assert_eq!(account.available(), FixedPoint::from_raw(10_000));
+        assert!(!account.is_locked());
+        assert_eq!(
+            processor.transaction_store.get(1).map(|r| r.state),
+            Some(TxState::Reversed)
+        );
+        assert!(matches!(
+            processor.process_transaction(reversal),
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::AlreadyReversed
+            )))
+        ));
+    }
+
     #[test]
     fn chargeback_requires_disputed_transaction() {
         let manager = ConcurrentAccountManager::<FixedPoint>::new();
@@ -459,6 +1436,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -485,6 +1463,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -507,6 +1486,7 @@ mod tests {
             client_id: 1,
             tx_id: 2,
             amount: FixedPoint::from_raw(5_000),
+            currency: None,
         });
 
         assert!(result.is_err());
@@ -524,6 +1504,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -571,6 +1552,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -611,6 +1593,7 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             })
             .unwrap();
 
@@ -622,4 +1605,1038 @@ mod tests {
 
         assert!(matches!(result, Err(EngineError::TransactionNotFound(1))));
     }
+
+    #[test]
+    fn transfer_moves_funds_between_clients() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+
+        processor
+            .process_transaction(Transaction::Transfer {
+                from_client: 1,
+                to_client: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(4_000),
+                currency: None,
+            })
+            .unwrap();
+
+        let sender = processor.account_manager.entry(1).unwrap().read();
+        let receiver = processor.account_manager.entry(2).unwrap().read();
+        assert_eq!(sender.available(), FixedPoint::from_raw(6_000));
+        assert_eq!(receiver.available(), FixedPoint::from_raw(4_000));
+    }
+
+    #[test]
+    fn transfer_insufficient_funds_fails() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+
+        let result = processor.process_transaction(Transaction::Transfer {
+            from_client: 1,
+            to_client: 2,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(1_000),
+            currency: None,
+        });
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::InsufficientFunds
+            )))
+        ));
+        assert_eq!(
+            processor
+                .account_manager
+                .entry(2)
+                .unwrap()
+                .read()
+                .available(),
+            FixedPoint::zero()
+        );
+    }
+
+    fn lock_client_one(
+        processor: &mut TransactionProcessor<
+            FixedPoint,
+            ConcurrentAccountManager<FixedPoint>,
+            ConcurrentTransactionStore<FixedPoint>,
+        >,
+    ) {
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn skip_locked_counts_locked_traffic_as_no_ops() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store).with_skip_locked(true);
+        lock_client_one(&mut processor);
+
+        processor
+            .process_transaction(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(1_000),
+                currency: None,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 3,
+                amount: FixedPoint::from_raw(5_000),
+                currency: None,
+            })
+            .unwrap();
+
+        // Other errors are still reported
+        let result = processor.process_transaction(Transaction::Withdrawal {
+            client_id: 2,
+            tx_id: 4,
+            amount: FixedPoint::from_raw(1_000),
+            currency: None,
+        });
+        assert!(result.is_err());
+
+        assert_eq!(processor.locked_skipped(), 2);
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert_eq!(account.total(), FixedPoint::zero());
+        assert!(account.is_locked());
+    }
+
+    #[test]
+    fn quarantined_transactions_can_be_rerun_after_unlock() {
+        struct Collect(parking_lot::Mutex<Vec<QuarantinedTransaction<FixedPoint>>>);
+
+        impl QuarantineSink<FixedPoint> for Collect {
+            fn quarantine(&self, quarantined: QuarantinedTransaction<FixedPoint>) {
+                self.0.lock().push(quarantined);
+            }
+        }
+
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let sink = Arc::new(Collect(parking_lot::Mutex::new(Vec::new())));
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_admin_ops(true)
+            .with_skip_locked(true)
+            .with_quarantine_sink(sink.clone());
+        lock_client_one(&mut processor);
+        let deposit = Transaction::Deposit {
+            client_id: 1,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(5_000),
+            currency: None,
+        };
+
+        processor
+            .process_transaction_with_key(deposit.clone(), Some("retry-me"))
+            .unwrap();
+        assert_eq!(
+            (processor.quarantined(), processor.locked_skipped()),
+            (1, 0)
+        );
+        assert_eq!(
+            sink.0.lock().as_slice(),
+            &[QuarantinedTransaction {
+                transaction: deposit.clone(),
+                reason: "Storage error: Domain error: Account is locked".to_string(),
+            }]
+        );
+
+        processor
+            .process_transaction(Transaction::Unlock { client_id: 1 })
+            .unwrap();
+        let rerun = sink.0.lock().pop().unwrap().transaction;
+        processor
+            .process_transaction_with_key(rerun, Some("retry-me"))
+            .unwrap();
+
+        assert_eq!(processor.quarantined(), 1);
+        assert_eq!(processor.idempotent_replays(), 0);
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(5_000));
+    }
+
+    #[test]
+    fn replayed_idempotency_key_is_a_no_op() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+        let deposit = |tx_id| Transaction::Deposit {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        };
+        let withdrawal = |client_id| Transaction::Withdrawal {
+            client_id,
+            tx_id: 3,
+            amount: FixedPoint::from_raw(50_000),
+            currency: None,
+        };
+
+        processor
+            .process_transaction_with_key(deposit(1), Some("batch-7"))
+            .unwrap();
+        // Re-uploaded with a new tx id
+        processor
+            .process_transaction_with_key(deposit(2), Some("batch-7"))
+            .unwrap();
+        // Keys are per client, so another client's "batch-7" is processed (and rejected)
+        assert!(
+            processor
+                .process_transaction_with_key(withdrawal(2), Some("batch-7"))
+                .is_err()
+        );
+        // A rejected transaction does not use up its key
+        assert!(
+            processor
+                .process_transaction_with_key(withdrawal(1), Some("w-1"))
+                .is_err()
+        );
+        processor
+            .process_transaction_with_key(deposit(4), Some("w-1"))
+            .unwrap();
+
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(20_000));
+        assert_eq!(processor.idempotent_replays(), 1);
+    }
+
+    #[test]
+    fn unlock_rejected_without_admin_ops() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+        lock_client_one(&mut processor);
+
+        let result = processor.process_transaction(Transaction::Unlock { client_id: 1 });
+
+        assert!(matches!(result, Err(EngineError::AdminOperationNotAllowed)));
+        assert!(
+            processor
+                .account_manager
+                .entry(1)
+                .unwrap()
+                .read()
+                .is_locked()
+        );
+    }
+
+    #[test]
+    fn credit_limit_requires_admin_ops_and_allows_overdraft() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+        let set_limit = Transaction::SetCreditLimit {
+            client_id: 1,
+            limit: FixedPoint::from_raw(100_000),
+        };
+        let withdraw = Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(60_000),
+            currency: None,
+        };
+
+        let result = processor.process_transaction(set_limit.clone());
+        assert!(matches!(result, Err(EngineError::AdminOperationNotAllowed)));
+
+        let mut processor = processor.with_admin_ops(true);
+        processor.process_transaction(set_limit).unwrap();
+        processor.process_transaction(withdraw).unwrap();
+
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(-60_000));
+        assert_eq!(account.credit_limit(), FixedPoint::from_raw(100_000));
+    }
+
+    #[test]
+    fn tags_drive_segment_rules_and_tier_fees() {
+        use crate::engine::{MaxAmount, SegmentRule};
+
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let schedule = FeeSchedule::new(0)
+            .with_tier_fee(
+                "premium",
+                FeeType::Deposit,
+                Fee::flat(FixedPoint::from_raw(1_000)),
+            )
+            .with_tier_tag("tier");
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_admin_ops(true)
+            .with_fee_schedule(Arc::new(schedule))
+            .with_validator(Arc::new(SegmentRule::new(
+                "kyc",
+                "pending",
+                MaxAmount(FixedPoint::from_raw(50_000)),
+            )));
+        let tag = |key: &str, value: &str| Transaction::SetTag {
+            client_id: 1,
+            key: key.to_string(),
+            value: Some(value.to_string()),
+        };
+        let deposit = |tx_id, raw| Transaction::Deposit {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        };
+
+        processor.process_transaction(deposit(1, 100_000)).unwrap();
+        processor
+            .process_transaction(tag("kyc", "pending"))
+            .unwrap();
+        processor
+            .process_transaction(tag("tier", "premium"))
+            .unwrap();
+
+        let result = processor.process_transaction(deposit(2, 100_000));
+        assert!(matches!(result, Err(EngineError::Rejected(_))));
+        processor.process_transaction(deposit(3, 50_000)).unwrap();
+
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert_eq!(account.tag("kyc"), Some("pending"));
+        // Untiered before the tag, premium (one fee) after it
+        assert_eq!(account.available(), FixedPoint::from_raw(149_000));
+    }
+
+    #[test]
+    fn simulate_reports_outcome_without_changing_storage() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let schedule = FeeSchedule::new(0)
+            .with_fee(FeeType::Withdrawal, Fee::flat(FixedPoint::from_raw(1_000)));
+        let mut processor =
+            TransactionProcessor::new(manager, store).with_fee_schedule(Arc::new(schedule));
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(50_000),
+                currency: None,
+            })
+            .unwrap();
+        let withdraw = |raw| Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        };
+
+        let outcome = processor.simulate(withdraw(20_000));
+        assert!(outcome.is_applied());
+        assert_eq!(
+            outcome.account(1).unwrap().available(),
+            FixedPoint::from_raw(29_000)
+        );
+        assert_eq!(
+            outcome.account(0).unwrap().available(),
+            FixedPoint::from_raw(1_000)
+        );
+
+        let outcome = processor.simulate(withdraw(50_000));
+        assert!(matches!(
+            outcome.result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::InsufficientFunds
+            )))
+        ));
+        assert_eq!(
+            outcome.account(1).unwrap().available(),
+            FixedPoint::from_raw(50_000)
+        );
+
+        let outcome = processor.simulate(Transaction::Dispute {
+            client_id: 1,
+            tx_id: 1,
+        });
+        assert_eq!(
+            outcome.account(1).unwrap().held(),
+            FixedPoint::from_raw(50_000)
+        );
+
+        // Storage is untouched and the withdrawal can still be processed for real
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert_eq!(
+            (account.available(), account.held()),
+            (FixedPoint::from_raw(50_000), FixedPoint::zero())
+        );
+        assert!(!processor.transaction_store.contains(2));
+        assert!(processor.account_manager.get(0).unwrap().is_none());
+        processor.process_transaction(withdraw(20_000)).unwrap();
+    }
+
+    #[test]
+    fn unlock_reinstates_account_with_admin_ops() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store).with_admin_ops(true);
+        lock_client_one(&mut processor);
+
+        processor
+            .process_transaction(Transaction::Unlock { client_id: 1 })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(5_000),
+                currency: None,
+            })
+            .unwrap();
+
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert!(!account.is_locked());
+        assert_eq!(account.available(), FixedPoint::from_raw(5_000));
+    }
+
+    #[derive(Default)]
+    struct RecordingSink(std::sync::Mutex<Vec<AuditRecord<FixedPoint>>>);
+
+    impl AuditSink<FixedPoint> for RecordingSink {
+        fn record(&self, record: AuditRecord<FixedPoint>) {
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    #[test]
+    fn dispute_policy_threads_through_processor() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_dispute_policy(DisputePolicy::default().with_negative_available(true));
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+
+        // With the default policy this dispute fails for insufficient funds
+        processor
+            .process_transaction(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(-10_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(10_000));
+    }
+
+    #[test]
+    fn max_disputes_limits_re_disputes_and_counts_history() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_dispute_policy(DisputePolicy::default().with_max_disputes(2));
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+        for _ in 0..2 {
+            processor
+                .process_transaction(Transaction::Dispute {
+                    client_id: 1,
+                    tx_id: 1,
+                })
+                .unwrap();
+            processor
+                .process_transaction(Transaction::Resolve {
+                    client_id: 1,
+                    tx_id: 1,
+                })
+                .unwrap();
+        }
+
+        let result = processor.process_transaction(Transaction::Dispute {
+            client_id: 1,
+            tx_id: 1,
+        });
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::DisputeLimitReached
+            )))
+        ));
+        let record = processor.transaction_store.get(1).unwrap();
+        assert_eq!(record.disputes, 2);
+        assert_eq!(record.state, TxState::Resolved);
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.held(), FixedPoint::zero());
+    }
+
+    #[test]
+    fn forbidden_redisputes_are_rejected_by_the_transaction_record() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_dispute_policy(DisputePolicy::default().with_redispute(false));
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Resolve {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+
+        let result = processor.process_transaction(Transaction::Dispute {
+            client_id: 1,
+            tx_id: 1,
+        });
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::AlreadyResolved
+            )))
+        ));
+        let record = processor.transaction_store.get(1).unwrap();
+        assert_eq!(record.state, TxState::Resolved);
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert!(!account.is_disputed(1));
+        assert_eq!(account.available(), FixedPoint::from_raw(10_000));
+    }
+
+    #[test]
+    fn negative_balance_lets_chargebacks_of_spent_deposits_complete() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_dispute_policy(DisputePolicy::default().with_negative_balance(true));
+
+        let transactions = [
+            Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            },
+            Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(6_000),
+                currency: None,
+            },
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            },
+            Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 1,
+            },
+        ];
+        for transaction in transactions {
+            processor.process_transaction(transaction).unwrap();
+        }
+
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(-6_000));
+        assert_eq!(account.held(), FixedPoint::zero());
+        assert!(account.is_locked());
+    }
+
+    #[test]
+    fn resolving_a_partly_held_dispute_keeps_other_disputes_held() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_dispute_policy(DisputePolicy::default().with_negative_balance(true));
+        let deposit = |tx_id, raw| Transaction::Deposit {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        };
+
+        let transactions = [
+            deposit(1, 10_000),
+            deposit(2, 5_000),
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 2,
+            },
+            Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 3,
+                amount: FixedPoint::from_raw(9_000),
+                currency: None,
+            },
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            },
+        ];
+        for transaction in transactions {
+            processor.process_transaction(transaction).unwrap();
+        }
+        let record = processor.transaction_store().get(1).unwrap();
+        assert_eq!(record.held, Some(FixedPoint::from_raw(1_000)));
+
+        processor
+            .process_transaction(Transaction::Resolve {
+                client_id: 1,
+                tx_id: 1,
+            })
+            .unwrap();
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(1_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(5_000));
+        assert_eq!(processor.transaction_store().get(1).unwrap().held, None);
+
+        processor
+            .process_transaction(Transaction::Resolve {
+                client_id: 1,
+                tx_id: 2,
+            })
+            .unwrap();
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(6_000));
+        assert_eq!(account.held(), FixedPoint::zero());
+    }
+
+    #[test]
+    fn hold_capture_and_release_flow() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+
+        let hold = |tx_id, raw| Transaction::Hold {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        };
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(100_000),
+                currency: None,
+            })
+            .unwrap();
+        processor.process_transaction(hold(2, 30_000)).unwrap();
+        processor.process_transaction(hold(3, 20_000)).unwrap();
+
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(50_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(50_000));
+
+        processor
+            .process_transaction(Transaction::Capture {
+                client_id: 1,
+                tx_id: 2,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Release {
+                client_id: 1,
+                tx_id: 3,
+            })
+            .unwrap();
+
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(70_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(0));
+
+        // A deposit is not an authorization
+        let result = processor.process_transaction(Transaction::Capture {
+            client_id: 1,
+            tx_id: 1,
+        });
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::NoActiveHold
+            )))
+        ));
+    }
+
+    #[test]
+    fn validator_rejects_before_domain_operations() {
+        use crate::engine::MaxAmount;
+
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store)
+            .with_validator(Arc::new(MaxAmount(FixedPoint::from_raw(100_000))));
+
+        let result = processor.process_transaction(Transaction::Deposit {
+            client_id: 1,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(200_000),
+            currency: None,
+        });
+
+        assert!(matches!(result, Err(EngineError::Rejected(_))));
+        assert!(!processor.transaction_store.contains(1));
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.total(), FixedPoint::from_raw(0));
+    }
+
+    #[test]
+    fn velocity_limit_rejects_with_its_own_error_and_ignores_simulations() {
+        use crate::engine::VelocityLimit;
+
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let limit = VelocityLimit::new(1)
+            .with_transaction_window(2)
+            .with_time_window(60);
+        let mut processor =
+            TransactionProcessor::new(manager, store).with_validator(Arc::new(limit));
+        let deposit = |tx_id| Transaction::Deposit {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(50_000),
+            currency: None,
+        };
+        let withdrawal = |tx_id| Transaction::Withdrawal {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        };
+        processor.process_transaction(deposit(1)).unwrap();
+
+        // A simulated withdrawal is not counted against the real one
+        assert!(processor.simulate(withdrawal(2)).is_applied());
+        processor
+            .process_transaction_at(withdrawal(2), None, Some(1_000))
+            .unwrap();
+        let result = processor.process_transaction_at(withdrawal(3), None, Some(1_030));
+        assert!(matches!(result, Err(EngineError::VelocityLimitExceeded(_))));
+
+        // Clear of both windows: a deposit in between, and a minute later
+        processor.process_transaction(deposit(4)).unwrap();
+        processor
+            .process_transaction_at(withdrawal(5), None, Some(1_060))
+            .unwrap();
+
+        let account = processor.account_manager().entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(80_000));
+    }
+
+    #[test]
+    fn audit_sink_records_applied_and_rejected() {
+        use crate::engine::AuditOperation;
+
+        let sink = Arc::new(RecordingSink::default());
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store).with_audit_sink(sink.clone());
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+        let _ = processor.process_transaction(Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(50_000),
+            currency: None,
+        });
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].operation, AuditOperation::Deposit);
+        assert_eq!(records[0].outcome, AuditOutcome::Applied);
+        assert_eq!(records[0].before.available, FixedPoint::zero());
+        assert_eq!(records[0].after.available, FixedPoint::from_raw(10_000));
+
+        assert_eq!(records[1].tx_id, Some(2));
+        assert!(matches!(records[1].outcome, AuditOutcome::Rejected(_)));
+        assert_eq!(records[1].before, records[1].after);
+    }
+
+    #[test]
+    fn adjustment_requires_admin_ops_and_is_audited() {
+        use crate::engine::AuditOperation;
+
+        let sink = Arc::new(RecordingSink::default());
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store).with_audit_sink(sink.clone());
+        lock_client_one(&mut processor);
+        let adjust = |tx_id, raw| Transaction::Adjustment {
+            client_id: 1,
+            tx_id,
+            amount: FixedPoint::from_raw(raw),
+        };
+
+        let result = processor.process_transaction(adjust(10, 25_000));
+        assert!(matches!(result, Err(EngineError::AdminOperationNotAllowed)));
+
+        let mut processor = processor.with_admin_ops(true);
+        processor.process_transaction(adjust(10, 25_000)).unwrap();
+        processor.process_transaction(adjust(11, -5_000)).unwrap();
+        let result = processor.process_transaction(adjust(12, -30_000));
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::InsufficientFunds
+            )))
+        ));
+
+        // Applied to the locked account, and not recorded for disputes
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert!(account.is_locked());
+        assert_eq!(account.available(), FixedPoint::from_raw(20_000));
+        assert!(!processor.transaction_store.contains(10));
+
+        let records = sink.0.lock().unwrap();
+        let adjustments: Vec<_> = records
+            .iter()
+            .filter(|record| record.operation == AuditOperation::Adjustment)
+            .collect();
+        assert_eq!(adjustments.len(), 4);
+        assert!(matches!(adjustments[0].outcome, AuditOutcome::Rejected(_)));
+        assert_eq!(adjustments[1].tx_id, Some(10));
+        assert_eq!(adjustments[1].before.available, FixedPoint::zero());
+        assert_eq!(adjustments[1].after.available, FixedPoint::from_raw(25_000));
+        assert!(matches!(adjustments[3].outcome, AuditOutcome::Rejected(_)));
+    }
+
+    #[test]
+    fn audit_sink_records_both_sides_of_transfer() {
+        let sink = Arc::new(RecordingSink::default());
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+
+        let mut processor = processor.with_audit_sink(sink.clone());
+        processor
+            .process_transaction(Transaction::Transfer {
+                from_client: 1,
+                to_client: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(4_000),
+                currency: None,
+            })
+            .unwrap();
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            (records[0].client_id, records[0].counterparty),
+            (1, Some(2))
+        );
+        assert_eq!(records[0].after.available, FixedPoint::from_raw(6_000));
+        assert_eq!(
+            (records[1].client_id, records[1].counterparty),
+            (2, Some(1))
+        );
+        assert_eq!(records[1].after.available, FixedPoint::from_raw(4_000));
+    }
+
+    #[test]
+    fn dispute_acts_in_original_currency() {
+        let usd = "USD".parse().unwrap();
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::new();
+        let mut processor = TransactionProcessor::new(manager, store);
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(7_000),
+                currency: Some(usd),
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 2,
+            })
+            .unwrap();
+
+        let account = processor.account_manager.entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(10_000));
+        assert_eq!(account.held(), FixedPoint::zero());
+        assert_eq!(account.currency_balance(usd).available, FixedPoint::zero());
+        assert_eq!(
+            account.currency_balance(usd).held,
+            FixedPoint::from_raw(7_000)
+        );
+    }
+
+    fn fee_processor() -> TransactionProcessor<
+        FixedPoint,
+        ConcurrentAccountManager<FixedPoint>,
+        ConcurrentTransactionStore<FixedPoint>,
+    > {
+        // 1.0 per withdrawal; 1% on deposits for the "standard" tier
+        let schedule = FeeSchedule::new(0)
+            .with_fee(FeeType::Withdrawal, Fee::flat(FixedPoint::from_raw(10_000)))
+            .with_tier_fee(
+                "standard",
+                FeeType::Deposit,
+                Fee::percentage(FixedPoint::from_raw(10_000)),
+            )
+            .with_client_tier(1, "standard");
+
+        TransactionProcessor::new(
+            ConcurrentAccountManager::new(),
+            ConcurrentTransactionStore::new(),
+        )
+        .with_fee_schedule(Arc::new(schedule))
+    }
+
+    #[tokio::test]
+    async fn fees_are_credited_to_the_fee_account() {
+        let mut processor = fee_processor();
+
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(1_000_000),
+                currency: None,
+            })
+            .unwrap();
+        processor
+            .process_transaction(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(50_000),
+                currency: None,
+            })
+            .unwrap();
+
+        // 100.0 deposit less 1.0 fee, then 5.0 withdrawn plus 1.0 fee
+        let client = processor.account_manager.get(1).unwrap().unwrap();
+        assert_eq!(client.available(), FixedPoint::from_raw(930_000));
+        let fees = processor.account_manager.get(0).unwrap().unwrap();
+        assert_eq!(fees.available(), FixedPoint::from_raw(20_000));
+
+        // The fee account is listed in snapshots like any other client
+        let mut snapshot = Vec::new();
+        processor
+            .account_manager
+            .snapshot(&mut snapshot)
+            .await
+            .unwrap();
+        assert!(String::from_utf8(snapshot).unwrap().contains("\n0,2.0000,"));
+    }
+
+    #[test]
+    fn withdrawal_fee_counts_towards_funds_needed() {
+        let mut processor = fee_processor();
+        processor
+            .process_transaction(Transaction::Deposit {
+                client_id: 2,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(50_000),
+                currency: None,
+            })
+            .unwrap();
+
+        let result = processor.process_transaction(Transaction::Withdrawal {
+            client_id: 2,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(50_000),
+            currency: None,
+        });
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Storage(StorageError::DomainError(
+                DomainError::InsufficientFunds
+            )))
+        ));
+        // Client 2 has no tier, so its deposit was free; nothing was collected
+        let client = processor.account_manager.get(2).unwrap().unwrap();
+        assert_eq!(client.available(), FixedPoint::from_raw(50_000));
+        assert_eq!(processor.account_manager.get(0).unwrap(), None);
+    }
 }
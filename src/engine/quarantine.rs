@@ -0,0 +1,23 @@
+use crate::domain::{AmountType, Transaction};
+
+/// A transaction set aside because its account was locked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantinedTransaction<A: AmountType> {
+    /// The transaction as it reached the engine, ready to be re-run
+    pub transaction: Transaction<A>,
+    /// The engine error that rejected it
+    pub reason: String,
+}
+
+/// Receives transactions diverted from locked accounts
+///
+/// Without a quarantine sink, transactions for a client whose account was
+/// locked mid-run (e.g. by a chargeback) are rejected and lost. With one,
+/// they are handed to the sink instead, so they can be re-run once the
+/// account is unlocked. Called synchronously on the processing path and,
+/// behind an `Arc`, from every shard, so implementations must be cheap and
+/// thread-safe. `CsvDeadLetterWriter` implements this trait and writes rows
+/// that can be fed back in as input.
+pub trait QuarantineSink<A: AmountType>: Send + Sync {
+    fn quarantine(&self, quarantined: QuarantinedTransaction<A>);
+}
@@ -0,0 +1,30 @@
+use super::error::EngineError;
+use crate::domain::{AmountType, ClientAccount, ClientId};
+
+/// What a transaction would do, as computed by `TransactionProcessor::simulate`
+///
+/// Nothing in storage changes: the outcome holds copies of the accounts the
+/// transaction touches (its client, a transfer's recipient and the fee
+/// account when a fee applies) as they would be afterwards, or as they are
+/// if the transaction would be rejected.
+#[derive(Debug)]
+pub struct SimulationOutcome<A: AmountType> {
+    /// `Ok` if the transaction would be applied, else the error it would be rejected with
+    pub result: Result<(), EngineError>,
+    /// Resulting accounts, in the order client, counterparty, fee account
+    pub accounts: Vec<ClientAccount<A>>,
+}
+
+impl<A: AmountType> SimulationOutcome<A> {
+    /// Whether the transaction would be applied
+    pub fn is_applied(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Resulting account of a touched client
+    pub fn account(&self, client_id: ClientId) -> Option<&ClientAccount<A>> {
+        self.accounts
+            .iter()
+            .find(|account| account.client_id() == client_id)
+    }
+}
@@ -0,0 +1,320 @@
+use std::io::Write;
+use std::ops::RangeBounds;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use dashmap::DashMap;
+use serde::Serialize;
+
+use super::audit::{AuditOperation, AuditOutcome, AuditRecord, AuditSink};
+use crate::domain::{AmountType, ClientId, CurrencyCode, TransactionId};
+
+/// Per-client history of applied transactions
+///
+/// An `AuditSink` that keeps every applied operation, numbered in processing
+/// order, so statements can be generated afterwards. Rejected transactions
+/// are not kept. The history grows with the input; attach it only where
+/// statements are needed.
+///
+/// # Example
+/// ```rust,ignore
+/// let history = Arc::new(TransactionHistory::new());
+/// let processor = TransactionProcessor::new(mgr, store).with_audit_sink(history.clone());
+/// // ... process transactions ...
+/// let statement = generate_statement(&history, 7, ..);
+/// statement.write_csv(std::io::stdout())?;
+/// ```
+pub struct TransactionHistory<A: AmountType> {
+    entries: DashMap<ClientId, Vec<(u64, AuditRecord<A>)>>,
+    next_sequence: AtomicU64,
+}
+
+impl<A: AmountType> Default for TransactionHistory<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: AmountType> TransactionHistory<A> {
+    /// Create an empty history
+    pub fn new() -> Self {
+        Self {
+            entries: DashMap::new(),
+            next_sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of operations recorded for a client
+    pub fn len(&self, client_id: ClientId) -> usize {
+        self.entries
+            .get(&client_id)
+            .map_or(0, |entries| entries.len())
+    }
+
+    /// Whether no operations were recorded for a client
+    pub fn is_empty(&self, client_id: ClientId) -> bool {
+        self.len(client_id) == 0
+    }
+}
+
+impl<A: AmountType> AuditSink<A> for TransactionHistory<A> {
+    fn record(&self, record: AuditRecord<A>) {
+        if record.outcome != AuditOutcome::Applied {
+            return;
+        }
+
+        // Numbered under the client's entry lock, so each list stays sorted
+        let mut entries = self.entries.entry(record.client_id).or_default();
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        entries.push((sequence, record));
+    }
+}
+
+/// One operation on a statement, with the balances after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementLine<A: AmountType> {
+    /// Position in processing order (shared across clients)
+    pub sequence: u64,
+    pub tx_id: Option<TransactionId>,
+    pub operation: AuditOperation,
+    /// Other side of a transfer
+    pub counterparty: Option<ClientId>,
+    /// Currency of the balances (None = base currency)
+    pub currency: Option<CurrencyCode>,
+    /// Funds moved: the change in total, or in held funds when the total is unchanged
+    pub amount: A,
+    pub available: A,
+    pub held: A,
+    pub locked: bool,
+}
+
+/// Chronological list of a client's operations with running balances
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement<A: AmountType> {
+    pub client_id: ClientId,
+    pub lines: Vec<StatementLine<A>>,
+}
+
+/// Serializable form of a statement line, with amounts as decimal strings
+///
+/// Used for CSV output; serialize `Statement::rows` with any serde format
+/// (e.g. `serde_json`) for JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatementRow {
+    pub sequence: u64,
+    pub client: ClientId,
+    pub tx: Option<TransactionId>,
+    #[serde(rename = "type")]
+    pub operation: &'static str,
+    pub counterparty: Option<ClientId>,
+    pub currency: Option<String>,
+    pub amount: String,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+    pub locked: bool,
+}
+
+impl<A: AmountType> Statement<A> {
+    /// Statement lines as serializable rows
+    pub fn rows(&self) -> Vec<StatementRow> {
+        self.lines
+            .iter()
+            .map(|line| StatementRow {
+                sequence: line.sequence,
+                client: self.client_id,
+                tx: line.tx_id,
+                operation: line.operation.as_str(),
+                counterparty: line.counterparty,
+                currency: line.currency.map(|currency| currency.to_string()),
+                amount: line.amount.to_decimal_string(),
+                available: line.available.to_decimal_string(),
+                held: line.held.to_decimal_string(),
+                total: (line.available + line.held).to_decimal_string(),
+                locked: line.locked,
+            })
+            .collect()
+    }
+
+    /// Write the statement as CSV with a header row
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for row in self.rows() {
+            writer.serialize(row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Build a statement for `client_id` from the operations in `range`
+///
+/// `range` selects operations by their `sequence` number (`..` for the full
+/// history). Lines are in processing order.
+pub fn generate_statement<A: AmountType>(
+    history: &TransactionHistory<A>,
+    client_id: ClientId,
+    range: impl RangeBounds<u64>,
+) -> Statement<A> {
+    let lines = history
+        .entries
+        .get(&client_id)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter(|(sequence, _)| range.contains(sequence))
+                .map(|(sequence, record)| StatementLine {
+                    sequence: *sequence,
+                    tx_id: record.tx_id,
+                    operation: record.operation,
+                    counterparty: record.counterparty,
+                    currency: record.currency,
+                    amount: moved(record),
+                    available: record.after.available,
+                    held: record.after.held,
+                    locked: record.after.locked,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Statement { client_id, lines }
+}
+
+/// Funds moved by an operation (disputes and resolves only move funds into or out of held)
+fn moved<A: AmountType>(record: &AuditRecord<A>) -> A {
+    let before = record.before.available + record.before.held;
+    let after = record.after.available + record.after.held;
+    match before == after {
+        true => difference(record.before.held, record.after.held),
+        false => difference(before, after),
+    }
+}
+
+fn difference<A: AmountType>(a: A, b: A) -> A {
+    a.max(b) - a.min(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::domain::{FixedPoint, Transaction};
+    use crate::engine::TransactionProcessor;
+    use crate::storage::{ConcurrentAccountManager, ConcurrentTransactionStore};
+
+    fn processed_history() -> Arc<TransactionHistory<FixedPoint>> {
+        let history = Arc::new(TransactionHistory::new());
+        let mut processor = TransactionProcessor::new(
+            ConcurrentAccountManager::<FixedPoint>::new(),
+            ConcurrentTransactionStore::new(),
+        )
+        .with_audit_sink(history.clone());
+
+        let deposit = |client_id, tx_id, raw| Transaction::Deposit {
+            client_id,
+            tx_id,
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        };
+        for tx in [
+            deposit(1, 1, 50_000),
+            deposit(2, 2, 10_000),
+            Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 3,
+                amount: FixedPoint::from_raw(15_000),
+                currency: None,
+            },
+            deposit(1, 5, 20_000),
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 5,
+            },
+            Transaction::Resolve {
+                client_id: 1,
+                tx_id: 5,
+            },
+        ] {
+            processor.process_transaction(tx).unwrap();
+        }
+
+        // Rejected: insufficient funds
+        let overdraft = Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 4,
+            amount: FixedPoint::from_raw(1_000_000),
+            currency: None,
+        };
+        assert!(processor.process_transaction(overdraft).is_err());
+
+        history
+    }
+
+    #[test]
+    fn statement_has_running_balances_in_order() {
+        let history = processed_history();
+
+        let statement = generate_statement(&history, 1, ..);
+
+        let summary: Vec<_> = statement
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    line.operation,
+                    line.amount.raw(),
+                    line.available.raw(),
+                    line.held.raw(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (AuditOperation::Deposit, 50_000, 50_000, 0),
+                (AuditOperation::Withdrawal, 15_000, 35_000, 0),
+                (AuditOperation::Deposit, 20_000, 55_000, 0),
+                (AuditOperation::Dispute, 20_000, 35_000, 20_000),
+                (AuditOperation::Resolve, 20_000, 55_000, 0),
+            ]
+        );
+        assert!(
+            statement
+                .lines
+                .windows(2)
+                .all(|w| w[0].sequence < w[1].sequence)
+        );
+        assert_eq!(history.len(2), 1);
+    }
+
+    #[test]
+    fn range_selects_operations_by_sequence() {
+        let history = processed_history();
+
+        // Sequence 0 is client 1's deposit, 1 is client 2's deposit
+        let statement = generate_statement(&history, 1, 2..4);
+
+        let operations: Vec<_> = statement.lines.iter().map(|line| line.operation).collect();
+        assert_eq!(
+            operations,
+            vec![AuditOperation::Withdrawal, AuditOperation::Deposit]
+        );
+        assert!(generate_statement(&history, 9, ..).lines.is_empty());
+    }
+
+    #[test]
+    fn writes_statement_as_csv() {
+        let history = processed_history();
+        let statement = generate_statement(&history, 2, ..);
+
+        let mut out = Vec::new();
+        statement.write_csv(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "sequence,client,tx,type,counterparty,currency,amount,available,held,total,locked\n\
+             1,2,2,deposit,,,1.0000,1.0000,0.0000,1.0000,false\n"
+        );
+    }
+}
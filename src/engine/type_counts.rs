@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+/// Applied and rejected transactions of one type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeCount {
+    /// Transactions that changed account state
+    pub applied: u64,
+    /// Transactions refused by the engine, including skipped locked-account traffic
+    pub rejected: u64,
+}
+
+impl TypeCount {
+    /// Every transaction of this type the engine saw
+    pub fn total(&self) -> u64 {
+        self.applied + self.rejected
+    }
+}
+
+/// Transaction counts by type, keyed by the CSV `type` name (`"deposit"`, ...)
+///
+/// Only transactions that reached the engine are counted; records that could
+/// not be read, or that a transform dropped, are not.
+///
+/// # Example
+/// ```rust,ignore
+/// let counts = results.by_type();
+/// let deposits = counts.get("deposit");
+/// println!("{} of {} deposits applied", deposits.applied, deposits.total());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionTypeCounts {
+    counts: BTreeMap<&'static str, TypeCount>,
+}
+
+impl TransactionTypeCounts {
+    /// Count one transaction of type `kind`
+    pub fn record(&mut self, kind: &'static str, applied: bool) {
+        let count = self.counts.entry(kind).or_default();
+        match applied {
+            true => count.applied += 1,
+            false => count.rejected += 1,
+        }
+    }
+
+    /// Counts for `kind` (zero if none were seen)
+    pub fn get(&self, kind: &str) -> TypeCount {
+        self.counts.get(kind).copied().unwrap_or_default()
+    }
+
+    /// Counts of every type seen, in type name order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, TypeCount)> + '_ {
+        self.counts.iter().map(|(kind, count)| (*kind, *count))
+    }
+
+    /// Counts across all types
+    pub fn total(&self) -> TypeCount {
+        self.counts
+            .values()
+            .fold(TypeCount::default(), |sum, count| TypeCount {
+                applied: sum.applied + count.applied,
+                rejected: sum.rejected + count.rejected,
+            })
+    }
+
+    /// Add another set of counts to this one
+    pub fn merge(&mut self, other: &Self) {
+        for (kind, count) in other.iter() {
+            let sum = self.counts.entry(kind).or_default();
+            sum.applied += count.applied;
+            sum.rejected += count.rejected;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_by_type_and_outcome() {
+        let mut counts = TransactionTypeCounts::default();
+        counts.record("deposit", true);
+        counts.record("deposit", true);
+        counts.record("deposit", false);
+        counts.record("dispute", false);
+
+        assert_eq!(
+            counts.get("deposit"),
+            TypeCount {
+                applied: 2,
+                rejected: 1
+            }
+        );
+        assert_eq!(counts.get("dispute").total(), 1);
+        assert_eq!(counts.get("chargeback"), TypeCount::default());
+        assert_eq!(
+            counts.total(),
+            TypeCount {
+                applied: 2,
+                rejected: 2
+            }
+        );
+        assert_eq!(
+            counts.iter().map(|(kind, _)| kind).collect::<Vec<_>>(),
+            vec!["deposit", "dispute"]
+        );
+    }
+
+    #[test]
+    fn merge_adds_counts() {
+        let mut first = TransactionTypeCounts::default();
+        first.record("deposit", true);
+        let mut second = TransactionTypeCounts::default();
+        second.record("deposit", false);
+        second.record("withdrawal", true);
+
+        first.merge(&second);
+
+        assert_eq!(
+            first.get("deposit"),
+            TypeCount {
+                applied: 1,
+                rejected: 1
+            }
+        );
+        assert_eq!(
+            first.get("withdrawal"),
+            TypeCount {
+                applied: 1,
+                rejected: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{HashSet, VecDeque};
+
+use dashmap::DashMap;
+
+use super::error::EngineError;
+use crate::domain::{AmountType, ClientAccount, ClientId, Transaction};
+
+/// Business rule checked before a transaction reaches the domain operations
+///
+/// Returning `Err(reason)` rejects the transaction with
+/// `EngineError::Rejected(reason)`; accounts and the transaction store are
+/// left untouched. Validators run on every shard, so stateful rules (e.g.
+/// velocity limits) must use interior mutability.
+///
+/// Rules that depend on the client, e.g. on its tags (`ClientAccount::tag`),
+/// return true from `needs_account` and are then also given the account in
+/// `validate_account`. The account is only looked up when some validator
+/// asks for it.
+pub trait TransactionValidator<A: AmountType>: Send + Sync {
+    fn validate(&self, tx: &Transaction<A>) -> Result<(), String>;
+
+    /// Whether `validate_account` should be called (defaults to false)
+    fn needs_account(&self) -> bool {
+        false
+    }
+
+    /// Check `tx` against the client's account as it stands before the
+    /// transaction (a new, empty account if it has none yet); called after
+    /// `validate` passes, and only when `needs_account` is true
+    fn validate_account(
+        &self,
+        _tx: &Transaction<A>,
+        _account: &ClientAccount<A>,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Check `tx` with its event timestamp, if the input carried one
+    /// (defaults to `validate`)
+    ///
+    /// This is what the processor calls. `record` is false when the check
+    /// comes from `TransactionProcessor::simulate`, so stateful rules such as
+    /// `VelocityLimit` must then leave their history unchanged.
+    fn validate_event(
+        &self,
+        tx: &Transaction<A>,
+        _timestamp: Option<u64>,
+        _record: bool,
+    ) -> Result<(), String> {
+        self.validate(tx)
+    }
+
+    /// Error a failed check is reported as (defaults to `EngineError::Rejected`)
+    fn rejection(&self, reason: String) -> EngineError {
+        EngineError::Rejected(reason)
+    }
+}
+
+/// Any thread-safe closure can be used as a validator
+impl<A, F> TransactionValidator<A> for F
+where
+    A: AmountType,
+    F: Fn(&Transaction<A>) -> Result<(), String> + Send + Sync,
+{
+    fn validate(&self, tx: &Transaction<A>) -> Result<(), String> {
+        self(tx)
+    }
+}
+
+/// Reject funds-moving transactions above a maximum amount
+#[derive(Debug, Clone, Copy)]
+pub struct MaxAmount<A: AmountType>(pub A);
+
+impl<A: AmountType> TransactionValidator<A> for MaxAmount<A> {
+    fn validate(&self, tx: &Transaction<A>) -> Result<(), String> {
+        match tx.amount() {
+            Some(amount) if amount > self.0 => Err(format!(
+                "amount {} exceeds maximum {}",
+                amount.to_decimal_string(),
+                self.0.to_decimal_string()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reject every transaction from (or, for transfers, to) a blocked client
+#[derive(Debug, Clone, Default)]
+pub struct BlockedClients(pub HashSet<ClientId>);
+
+impl<A: AmountType> TransactionValidator<A> for BlockedClients {
+    fn validate(&self, tx: &Transaction<A>) -> Result<(), String> {
+        let blocked = |client_id: ClientId| self.0.contains(&client_id);
+
+        match tx {
+            Transaction::Transfer { to_client, .. } if blocked(*to_client) => {
+                Err(format!("client {} is blocked", to_client))
+            }
+            _ if blocked(tx.client_id()) => Err(format!("client {} is blocked", tx.client_id())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Apply a rule only to clients in a segment: those whose tag `key` is `value`
+///
+/// Risk rules often differ by segment, e.g. a lower maximum amount for
+/// clients whose KYC is pending. Tags are set by the admin `tag` transaction
+/// (`Transaction::SetTag`); clients without the tag are not checked.
+///
+/// # Example
+/// ```rust,ignore
+/// let rule = SegmentRule::new("kyc", "pending", MaxAmount(FixedPoint::from_raw(1_000_000)));
+/// let processor = TransactionProcessor::new(mgr, store).with_validator(Arc::new(rule));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SegmentRule<V> {
+    key: String,
+    value: String,
+    rule: V,
+}
+
+impl<V> SegmentRule<V> {
+    /// Check `rule` for clients tagged `key=value`
+    pub fn new(key: impl Into<String>, value: impl Into<String>, rule: V) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+            rule,
+        }
+    }
+}
+
+impl<A: AmountType, V: TransactionValidator<A>> TransactionValidator<A> for SegmentRule<V> {
+    fn validate(&self, _tx: &Transaction<A>) -> Result<(), String> {
+        // Nothing to check until the account shows the client is in the segment
+        Ok(())
+    }
+
+    fn needs_account(&self) -> bool {
+        true
+    }
+
+    fn validate_account(
+        &self,
+        tx: &Transaction<A>,
+        account: &ClientAccount<A>,
+    ) -> Result<(), String> {
+        if account.tag(&self.key) != Some(self.value.as_str()) {
+            return Ok(());
+        }
+
+        self.rule.validate(tx)?;
+        match self.rule.needs_account() {
+            true => self.rule.validate_account(tx, account),
+            false => Ok(()),
+        }
+    }
+
+    fn rejection(&self, reason: String) -> EngineError {
+        self.rule.rejection(reason)
+    }
+}
+
+/// Limit how often each client may withdraw
+///
+/// A withdrawal is rejected with `EngineError::VelocityLimitExceeded` if,
+/// counting it, the client would have more than `max_withdrawals`
+/// withdrawals among its last `n` transactions (`with_transaction_window`)
+/// or within `units` of event time (`with_time_window`). The time window
+/// only applies to transactions whose input carries a timestamp, in the
+/// timestamp's own units (e.g. seconds). Transactions are counted when they
+/// pass this limit, even if a later check rejects them; withdrawals it
+/// rejects are not counted.
+///
+/// The history is shared by every shard using the validator, so give each
+/// client's transactions a single shard (e.g. `ExecutionModel::ActorSharded`)
+/// for a deterministic count. Inside a `SegmentRule`, time windows are not
+/// checked, since segment rules are not given timestamps.
+///
+/// # Example
+/// ```rust,ignore
+/// // At most 3 withdrawals in any 10 transactions or any hour
+/// let limit = VelocityLimit::new(3).with_transaction_window(10).with_time_window(3_600);
+/// let processor = TransactionProcessor::new(mgr, store).with_validator(Arc::new(limit));
+/// ```
+#[derive(Debug, Default)]
+pub struct VelocityLimit {
+    max_withdrawals: usize,
+    transaction_window: Option<usize>,
+    time_window: Option<u64>,
+    history: DashMap<ClientId, ClientHistory>,
+}
+
+/// What `VelocityLimit` remembers about one client
+#[derive(Debug, Default)]
+struct ClientHistory {
+    /// Whether each of the latest transactions was a withdrawal, oldest first
+    recent: VecDeque<bool>,
+    /// Timestamps of the latest timestamped withdrawals, oldest first
+    withdrawn_at: VecDeque<u64>,
+}
+
+impl VelocityLimit {
+    /// Allow each client up to `max_withdrawals` withdrawals per window
+    ///
+    /// Without a window nothing is limited; add one or both.
+    pub fn new(max_withdrawals: usize) -> Self {
+        Self {
+            max_withdrawals,
+            ..Self::default()
+        }
+    }
+
+    /// Count withdrawals among each client's last `transactions` transactions
+    pub fn with_transaction_window(mut self, transactions: usize) -> Self {
+        self.transaction_window = Some(transactions.max(1));
+        self
+    }
+
+    /// Count withdrawals in the last `units` of event time (timestamps in
+    /// `(t - units, t]` for a withdrawal at `t`)
+    pub fn with_time_window(mut self, units: u64) -> Self {
+        self.time_window = Some(units);
+        self
+    }
+
+    fn check(
+        &self,
+        client_id: ClientId,
+        withdrawal: bool,
+        timestamp: Option<u64>,
+        record: bool,
+    ) -> Result<(), String> {
+        let mut history = self.history.entry(client_id).or_default();
+
+        if withdrawal {
+            if let Some(window) = self.transaction_window {
+                let earlier = history.recent.iter().rev().take(window - 1);
+                if earlier.filter(|&&withdrawn| withdrawn).count() >= self.max_withdrawals {
+                    return Err(format!(
+                        "client {client_id} exceeded {} withdrawals in {window} transactions",
+                        self.max_withdrawals
+                    ));
+                }
+            }
+            if let (Some(window), Some(at)) = (self.time_window, timestamp) {
+                let since = at.saturating_sub(window);
+                let earlier = history
+                    .withdrawn_at
+                    .iter()
+                    .filter(|&&t| t > since && t <= at);
+                if earlier.count() >= self.max_withdrawals {
+                    return Err(format!(
+                        "client {client_id} exceeded {} withdrawals in {window} time units",
+                        self.max_withdrawals
+                    ));
+                }
+            }
+        }
+
+        if record {
+            if let Some(window) = self.transaction_window {
+                history.recent.push_back(withdrawal);
+                if history.recent.len() > window {
+                    history.recent.pop_front();
+                }
+            }
+            if let (Some(window), Some(at), true) = (self.time_window, timestamp, withdrawal) {
+                history.withdrawn_at.push_back(at);
+                // Inputs are mostly in time order; drop what no window can reach
+                let since = at.saturating_sub(window);
+                while history.withdrawn_at.front().is_some_and(|&t| t <= since) {
+                    history.withdrawn_at.pop_front();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<A: AmountType> TransactionValidator<A> for VelocityLimit {
+    fn validate(&self, tx: &Transaction<A>) -> Result<(), String> {
+        self.validate_event(tx, None, true)
+    }
+
+    fn validate_event(
+        &self,
+        tx: &Transaction<A>,
+        timestamp: Option<u64>,
+        record: bool,
+    ) -> Result<(), String> {
+        let withdrawal = matches!(tx, Transaction::Withdrawal { .. });
+        self.check(tx.client_id(), withdrawal, timestamp, record)
+    }
+
+    fn rejection(&self, reason: String) -> EngineError {
+        EngineError::VelocityLimitExceeded(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn deposit(client_id: ClientId, raw: i64) -> Transaction<FixedPoint> {
+        Transaction::Deposit {
+            client_id,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        }
+    }
+
+    #[test]
+    fn max_amount_rejects_above_limit() {
+        let rule = MaxAmount(FixedPoint::from_raw(100_000));
+
+        assert!(rule.validate(&deposit(1, 100_000)).is_ok());
+        assert_eq!(
+            rule.validate(&deposit(1, 100_001)),
+            Err("amount 10.0001 exceeds maximum 10.0000".to_string())
+        );
+        assert!(
+            rule.validate(&Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1
+            })
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn blocked_clients_rejects_sender_and_recipient() {
+        let rule = BlockedClients(HashSet::from([7]));
+
+        assert!(rule.validate(&deposit(1, 10_000)).is_ok());
+        assert!(rule.validate(&deposit(7, 10_000)).is_err());
+
+        let transfer = Transaction::Transfer {
+            from_client: 1,
+            to_client: 7,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        };
+        assert_eq!(
+            rule.validate(&transfer),
+            Err("client 7 is blocked".to_string())
+        );
+    }
+
+    #[test]
+    fn segment_rule_only_checks_tagged_clients() {
+        let rule = SegmentRule::new("kyc", "pending", MaxAmount(FixedPoint::from_raw(10_000)));
+        let large = deposit(1, 50_000);
+
+        let mut account = ClientAccount::new(1);
+        assert!(rule.validate(&large).is_ok());
+        assert!(rule.validate_account(&large, &account).is_ok());
+
+        crate::domain::apply_set_tag(&mut account, "kyc", Some("pending")).unwrap();
+        assert!(rule.validate_account(&large, &account).is_err());
+        assert!(rule.validate_account(&deposit(1, 10_000), &account).is_ok());
+
+        crate::domain::apply_set_tag(&mut account, "kyc", Some("verified")).unwrap();
+        assert!(rule.validate_account(&large, &account).is_ok());
+    }
+
+    fn withdrawal(client_id: ClientId) -> Transaction<FixedPoint> {
+        Transaction::Withdrawal {
+            client_id,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(1),
+            currency: None,
+        }
+    }
+
+    #[test]
+    fn velocity_limit_counts_withdrawals_per_transaction_window() {
+        let limit = VelocityLimit::new(2).with_transaction_window(4);
+        let check = |tx: &Transaction<FixedPoint>| limit.validate(tx).is_ok();
+
+        assert!(check(&withdrawal(1)));
+        assert!(check(&deposit(1, 1)));
+        assert!(check(&withdrawal(1)));
+        // Two withdrawals among the last three transactions
+        assert!(!check(&withdrawal(1)));
+        // Other clients have their own history
+        assert!(check(&withdrawal(2)));
+        // The rejected withdrawal was not counted; the first one leaves the window
+        assert!(check(&deposit(1, 1)));
+        assert!(check(&withdrawal(1)));
+        assert!(matches!(
+            TransactionValidator::<FixedPoint>::rejection(&limit, "r".to_string()),
+            EngineError::VelocityLimitExceeded(_)
+        ));
+    }
+
+    #[test]
+    fn velocity_limit_uses_event_time_when_present() {
+        let limit = VelocityLimit::new(1).with_time_window(60);
+        let check = |timestamp| {
+            limit
+                .validate_event(&withdrawal(1), timestamp, true)
+                .is_ok()
+        };
+
+        assert!(check(Some(100)));
+        assert!(!check(Some(159)));
+        assert!(check(Some(160)));
+        // Untimestamped withdrawals are not limited by time
+        assert!(check(None));
+    }
+
+    #[test]
+    fn velocity_limit_previews_leave_history_unchanged() {
+        let limit = VelocityLimit::new(1).with_transaction_window(10);
+
+        for _ in 0..3 {
+            assert!(limit.validate_event(&withdrawal(1), None, false).is_ok());
+        }
+        assert!(limit.validate(&withdrawal(1)).is_ok());
+        assert!(limit.validate_event(&withdrawal(1), None, false).is_err());
+    }
+
+    #[test]
+    fn closures_are_validators() {
+        let rule = |tx: &Transaction<FixedPoint>| {
+            if tx.client_id() == 0 {
+                Err("client 0 is reserved".to_string())
+            } else {
+                Ok(())
+            }
+        };
+
+        assert!(rule.validate(&deposit(1, 1)).is_ok());
+        assert!(rule.validate(&deposit(0, 1)).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+use std::io::Read;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::UInt64Type;
+use arrow_array::{Array, ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::reader::StreamReader;
+use arrow_schema::{ArrowError, DataType};
+use futures::{Stream, stream};
+
+use super::csv_reader::ColumnMapping;
+use super::error::IoError;
+use super::parse::RawTransactionRecord;
+use crate::domain::{AmountType, RoundingPolicy, TimestampedTransaction, Transaction};
+
+/// Boxed stream of record batches, as read from an IPC stream or handed over
+type BatchStream = Pin<Box<dyn Stream<Item = Result<RecordBatch, ArrowError>> + Send>>;
+
+/// Async stream of transactions from Arrow record batches
+///
+/// Batches use the CSV column names (`type`, `client`, `tx`, `amount`, and
+/// optionally `to`, `timestamp`, `seq`, `currency`, `idempotency_key` and
+/// `tag`), renamed through a `ColumnMapping` if the producer names them
+/// differently; other columns are ignored. Columns are cast per batch, so
+/// any integer type works for ids, and amounts may be strings, decimals or
+/// numbers. A null amount, currency, key or tag is the same as an empty CSV
+/// field.
+///
+/// Rows that fail to parse are reported as `IoError::AtRecord`, with `line`
+/// the 1-based row across all batches, and the stream carries on. A batch
+/// missing a required column (or holding one that cannot be cast) yields one
+/// error for the whole batch.
+///
+/// Requires the `arrow` feature.
+///
+/// # Example
+/// ```rust,ignore
+/// let ipc = std::fs::File::open("transactions.arrows")?;
+/// let stream = ArrowTransactionStream::<FixedPoint>::from_ipc(ipc)?
+///     .with_columns(ColumnMapping::new().with_column("customer_id", "client"));
+/// processor.add_stream(stream);
+/// ```
+pub struct ArrowTransactionStream<A>
+where
+    A: AmountType + Unpin,
+{
+    batches: BatchStream,
+    columns: ColumnMapping,
+    rounding: RoundingPolicy,
+    /// Parsed rows of the current batch not yet yielded
+    pending: std::vec::IntoIter<Result<TimestampedTransaction<A>, IoError>>,
+    /// Rows in the batches before the current one
+    rows: u64,
+}
+
+impl<A> ArrowTransactionStream<A>
+where
+    A: AmountType + Unpin,
+{
+    /// Stream the transactions in `batches`
+    pub fn new<S>(batches: S) -> Self
+    where
+        S: Stream<Item = Result<RecordBatch, ArrowError>> + Send + 'static,
+    {
+        Self {
+            batches: Box::pin(batches),
+            columns: ColumnMapping::new(),
+            rounding: RoundingPolicy::Reject,
+            pending: Vec::new().into_iter(),
+            rows: 0,
+        }
+    }
+
+    /// Stream the transactions in batches already in memory
+    pub fn from_batches<I>(batches: I) -> Self
+    where
+        I: IntoIterator<Item = RecordBatch>,
+        I::IntoIter: Send + 'static,
+    {
+        Self::new(stream::iter(batches.into_iter().map(Ok)))
+    }
+
+    /// Stream the transactions in an Arrow IPC stream
+    ///
+    /// The schema is read here; batches are read as the stream is polled,
+    /// blocking the polling task, so pass a file or an in-memory buffer
+    /// rather than a socket.
+    pub fn from_ipc<R>(reader: R) -> Result<Self, IoError>
+    where
+        R: Read + Send + 'static,
+    {
+        let reader = StreamReader::try_new_buffered(reader, None)?;
+        Ok(Self::new(stream::iter(reader)))
+    }
+
+    /// Read columns under other names (e.g. `customer_id` as `client`)
+    pub fn with_columns(mut self, columns: ColumnMapping) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Normalize amounts per `rounding` (defaults to rejecting excess precision)
+    pub fn with_rounding(mut self, rounding: RoundingPolicy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Convert into a stream that keeps the optional `timestamp` column
+    pub fn timestamped(
+        self,
+    ) -> impl Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send + 'static
+    where
+        A: 'static,
+    {
+        stream::unfold(self, |mut stream| async move {
+            let item = futures::future::poll_fn(|cx| stream.poll_timestamped(cx)).await?;
+            Some((item, stream))
+        })
+    }
+
+    fn poll_timestamped(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<TimestampedTransaction<A>, IoError>>> {
+        loop {
+            if let Some(row) = self.pending.next() {
+                return Poll::Ready(Some(row));
+            }
+            match self.batches.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(batch))) => {
+                    self.pending = self.parse_batch(&batch).into_iter();
+                    self.rows += batch.num_rows() as u64;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn parse_batch(&self, batch: &RecordBatch) -> Vec<Result<TimestampedTransaction<A>, IoError>> {
+        let columns = match BatchColumns::new(batch, &self.columns) {
+            Ok(columns) => columns,
+            Err(e) => return vec![Err(e)],
+        };
+        (0..batch.num_rows())
+            .map(|row| {
+                columns
+                    .record(row)
+                    .and_then(|raw| raw.parse_timestamped_rounded(self.rounding))
+                    .map_err(|e| IoError::AtRecord {
+                        line: self.rows + row as u64 + 1,
+                        byte: 0,
+                        record: columns.describe(row),
+                        source: Box::new(e),
+                    })
+            })
+            .collect()
+    }
+}
+
+impl<A> Stream for ArrowTransactionStream<A>
+where
+    A: AmountType + Unpin,
+{
+    type Item = Result<Transaction<A>, IoError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_timestamped(cx)
+            .map(|item| item.map(|result| result.map(|tx| tx.transaction)))
+    }
+}
+
+/// One batch's columns, cast to the types records are built from
+struct BatchColumns {
+    tx_type: StringArray,
+    client: UInt64Array,
+    tx: UInt64Array,
+    amount: Option<StringArray>,
+    to: Option<UInt64Array>,
+    timestamp: Option<UInt64Array>,
+    seq: Option<UInt64Array>,
+    currency: Option<StringArray>,
+    idempotency_key: Option<StringArray>,
+    tag: Option<StringArray>,
+}
+
+impl BatchColumns {
+    fn new(batch: &RecordBatch, mapping: &ColumnMapping) -> Result<Self, IoError> {
+        let column = |name: &str| -> Option<&ArrayRef> {
+            let schema = batch.schema_ref();
+            let index = schema
+                .fields()
+                .iter()
+                .position(|field| mapping.canonical(field.name()) == name)?;
+            Some(batch.column(index))
+        };
+        let text = |name: &str| -> Result<Option<StringArray>, IoError> {
+            column(name)
+                .map(|array| {
+                    let cast = arrow_cast::cast(array, &DataType::Utf8)?;
+                    Ok(cast.as_string::<i32>().clone())
+                })
+                .transpose()
+        };
+        let number = |name: &str| -> Result<Option<UInt64Array>, IoError> {
+            column(name)
+                .map(|array| {
+                    let cast = arrow_cast::cast(array, &DataType::UInt64)?;
+                    Ok(cast.as_primitive::<UInt64Type>().clone())
+                })
+                .transpose()
+        };
+        let missing = |name: &str| IoError::MissingField(format!("column {name}"));
+
+        Ok(Self {
+            tx_type: text("type")?.ok_or_else(|| missing("type"))?,
+            client: number("client")?.ok_or_else(|| missing("client"))?,
+            tx: number("tx")?.ok_or_else(|| missing("tx"))?,
+            amount: text("amount")?,
+            to: number("to")?,
+            timestamp: number("timestamp")?,
+            seq: number("seq")?,
+            currency: text("currency")?,
+            idempotency_key: text("idempotency_key")?,
+            tag: text("tag")?,
+        })
+    }
+
+    /// The record in `row`, as the CSV reader would have read it
+    fn record(&self, row: usize) -> Result<RawTransactionRecord, IoError> {
+        let text = |column: &Option<StringArray>| {
+            column
+                .as_ref()
+                .filter(|column| column.is_valid(row))
+                .map(|column| column.value(row).to_string())
+        };
+        let number = |column: &Option<UInt64Array>| {
+            column
+                .as_ref()
+                .filter(|column| column.is_valid(row))
+                .map(|column| column.value(row))
+        };
+        // Negative and fractional ids were cast to null
+        let id = |column: &UInt64Array, name: &str| {
+            Some(column)
+                .filter(|column| column.is_valid(row))
+                .map(|column| column.value(row))
+                .ok_or_else(|| {
+                    IoError::MissingField(format!("{name} must be a non-negative integer"))
+                })
+        };
+
+        Ok(RawTransactionRecord {
+            tx_type: match self.tx_type.is_valid(row) {
+                true => self.tx_type.value(row).to_string(),
+                false => return Err(IoError::MissingField("type".to_string())),
+            },
+            client: narrow(id(&self.client, "client")?, "client")?,
+            tx: narrow(id(&self.tx, "tx")?, "tx")?,
+            amount: text(&self.amount),
+            to: number(&self.to).map(|to| narrow(to, "to")).transpose()?,
+            timestamp: number(&self.timestamp),
+            seq: number(&self.seq),
+            currency: text(&self.currency),
+            idempotency_key: text(&self.idempotency_key),
+            tag: text(&self.tag),
+        })
+    }
+
+    /// The row's `type,client,tx,amount` fields, comma-joined as in CSV errors
+    fn describe(&self, row: usize) -> String {
+        let text = |column: &StringArray| match column.is_valid(row) {
+            true => column.value(row).to_string(),
+            false => String::new(),
+        };
+        let number = |column: &UInt64Array| match column.is_valid(row) {
+            true => column.value(row).to_string(),
+            false => String::new(),
+        };
+        let amount = self.amount.as_ref().map(text).unwrap_or_default();
+        format!(
+            "{},{},{},{}",
+            text(&self.tx_type),
+            number(&self.client),
+            number(&self.tx),
+            amount
+        )
+    }
+}
+
+/// `value` as a client or transaction id, failing if the id type is narrower
+fn narrow<T: TryFrom<u64>>(value: u64, name: &str) -> Result<T, IoError> {
+    T::try_from(value)
+        .map_err(|_| ArrowError::CastError(format!("{name} {value} is out of range")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{Decimal128Array, DictionaryArray, Int32Array, Int64Array};
+    use arrow_ipc::writer::StreamWriter;
+    use arrow_schema::{Field, Schema};
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn batch(columns: Vec<(&str, ArrayRef)>) -> RecordBatch {
+        RecordBatch::try_from_iter(columns).unwrap()
+    }
+
+    async fn collect(
+        stream: ArrowTransactionStream<FixedPoint>,
+    ) -> Vec<Result<Transaction<FixedPoint>, IoError>> {
+        stream.collect().await
+    }
+
+    #[tokio::test]
+    async fn batches_become_transactions() {
+        let types: DictionaryArray<arrow_array::types::Int32Type> =
+            vec!["deposit", "withdrawal", "dispute"]
+                .into_iter()
+                .collect();
+        let first = batch(vec![
+            ("type", Arc::new(types) as ArrayRef),
+            ("client", Arc::new(Int32Array::from(vec![1, 1, 1]))),
+            ("tx", Arc::new(Int64Array::from(vec![1, 2, 1]))),
+            (
+                "amount",
+                Arc::new(
+                    Decimal128Array::from(vec![Some(15_000), Some(2_500), None])
+                        .with_precision_and_scale(10, 4)
+                        .unwrap(),
+                ),
+            ),
+        ]);
+
+        let stream = ArrowTransactionStream::from_batches(vec![first]);
+        let transactions: Vec<_> = collect(stream)
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            transactions,
+            vec![
+                Transaction::Deposit {
+                    client_id: 1,
+                    tx_id: 1,
+                    amount: FixedPoint::from_raw(15_000),
+                    currency: None,
+                },
+                Transaction::Withdrawal {
+                    client_id: 1,
+                    tx_id: 2,
+                    amount: FixedPoint::from_raw(2_500),
+                    currency: None,
+                },
+                Transaction::Dispute {
+                    client_id: 1,
+                    tx_id: 1,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn bad_rows_are_reported_by_row_across_batches() {
+        let rows = |types: Vec<&str>, clients: Vec<i64>| {
+            batch(vec![
+                ("type", Arc::new(StringArray::from(types)) as ArrayRef),
+                ("client", Arc::new(Int64Array::from(clients))),
+                ("tx", Arc::new(Int64Array::from(vec![1, 2]))),
+                ("amount", Arc::new(StringArray::from(vec!["1.0", "1.0"]))),
+            ])
+        };
+        let stream = ArrowTransactionStream::<FixedPoint>::from_batches(vec![
+            rows(vec!["deposit", "deposit"], vec![1, 2]),
+            rows(vec!["deposit", "refund"], vec![-3, 4]),
+        ]);
+
+        let results = collect(stream).await;
+
+        assert_eq!(results.len(), 4);
+        assert!(results[..2].iter().all(Result::is_ok));
+        let lines: Vec<_> = results[2..]
+            .iter()
+            .map(|result| result.as_ref().unwrap_err().line())
+            .collect();
+        assert_eq!(lines, vec![Some(3), Some(4)]);
+        assert!(matches!(
+            results[2].as_ref().unwrap_err().inner(),
+            IoError::MissingField(_)
+        ));
+        assert!(matches!(
+            results[3].as_ref().unwrap_err().inner(),
+            IoError::InvalidTransactionType(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn columns_can_be_renamed_and_missing_ones_fail_the_batch() {
+        let renamed = batch(vec![
+            (
+                "kind",
+                Arc::new(StringArray::from(vec!["deposit"])) as ArrayRef,
+            ),
+            ("customer_id", Arc::new(Int32Array::from(vec![7]))),
+            ("tx", Arc::new(Int32Array::from(vec![1]))),
+            ("amount", Arc::new(StringArray::from(vec!["2"]))),
+        ]);
+        let mapping = ColumnMapping::new()
+            .with_column("kind", "type")
+            .with_column("customer_id", "client");
+
+        let mapped = ArrowTransactionStream::<FixedPoint>::from_batches(vec![renamed.clone()])
+            .with_columns(mapping);
+        let unmapped = ArrowTransactionStream::<FixedPoint>::from_batches(vec![renamed]);
+
+        assert!(matches!(
+            collect(mapped).await[..],
+            [Ok(Transaction::Deposit { client_id: 7, .. })]
+        ));
+        assert!(matches!(
+            collect(unmapped).await[..],
+            [Err(IoError::MissingField(_))]
+        ));
+    }
+
+    #[tokio::test]
+    async fn ipc_streams_are_read_with_their_timestamps() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("type", DataType::Utf8, false),
+            Field::new("client", DataType::UInt16, false),
+            Field::new("tx", DataType::UInt32, false),
+            Field::new("amount", DataType::Utf8, true),
+            Field::new("timestamp", DataType::UInt64, true),
+        ]));
+        let rows = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["deposit"])),
+                Arc::new(arrow_array::UInt16Array::from(vec![3])),
+                Arc::new(arrow_array::UInt32Array::from(vec![9])),
+                Arc::new(StringArray::from(vec!["0.5"])),
+                Arc::new(UInt64Array::from(vec![1_700_000_000])),
+            ],
+        )
+        .unwrap();
+        let mut ipc = Vec::new();
+        let mut writer = StreamWriter::try_new(&mut ipc, &schema).unwrap();
+        writer.write(&rows).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let stream = ArrowTransactionStream::<FixedPoint>::from_ipc(std::io::Cursor::new(ipc))
+            .unwrap()
+            .timestamped();
+        let transactions: Vec<_> = stream.collect().await;
+
+        let transaction = transactions[0].as_ref().unwrap();
+        assert_eq!(transaction.timestamp, Some(1_700_000_000));
+        assert!(matches!(
+            transaction.transaction,
+            Transaction::Deposit {
+                client_id: 3,
+                tx_id: 9,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader, ReadBuf};
+
+use super::error::IoError;
+
+/// Gzip member header magic bytes
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Zstandard frame magic bytes
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression format of an input source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Plain, uncompressed input
+    #[default]
+    None,
+    /// Gzip (`.gz`), including concatenated members
+    Gzip,
+    /// Zstandard (`.zst`), including concatenated frames
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the format from the leading bytes of the input
+    ///
+    /// Falls back to `None` when no known magic number matches.
+    pub fn detect(prefix: &[u8]) -> Self {
+        if prefix.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if prefix.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Guess the format from a file extension (`.gz`, `.zst`)
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Async reader that decompresses its input on the fly
+///
+/// Decompression is streamed, so multi-GB inputs are never materialized on
+/// disk or in memory.
+pub struct CompressedReader {
+    inner: Pin<Box<dyn AsyncRead + Send>>,
+}
+
+impl CompressedReader {
+    /// Wrap a buffered reader using an explicit compression format
+    pub fn new<R>(reader: R, compression: Compression) -> Self
+    where
+        R: AsyncBufRead + Send + 'static,
+    {
+        let inner: Pin<Box<dyn AsyncRead + Send>> = match compression {
+            Compression::None => Box::pin(reader),
+            Compression::Gzip => {
+                let mut decoder = GzipDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::pin(decoder)
+            }
+            Compression::Zstd => {
+                let mut decoder = ZstdDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::pin(decoder)
+            }
+        };
+
+        Self { inner }
+    }
+
+    /// Wrap a buffered reader, detecting the format from its magic bytes
+    pub async fn detect<R>(mut reader: R) -> Result<Self, IoError>
+    where
+        R: AsyncBufRead + Unpin + Send + 'static,
+    {
+        let compression = Compression::detect(reader.fill_buf().await?);
+        Ok(Self::new(reader, compression))
+    }
+
+    /// Open a file, detecting gzip/zstd compression from its contents
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, IoError> {
+        let file = File::open(path.as_ref()).await?;
+        Self::detect(BufReader::new(file)).await
+    }
+}
+
+impl AsyncRead for CompressedReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.inner.as_mut().poll_read(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::tokio::bufread::{GzipEncoder, ZstdEncoder};
+    use tokio::io::AsyncReadExt;
+
+    const CSV: &[u8] = b"type,client,tx,amount\ndeposit,1,1,1.0\n";
+
+    async fn read_all(mut reader: impl AsyncRead + Unpin) -> Vec<u8> {
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        out
+    }
+
+    async fn gzip(data: &[u8]) -> Vec<u8> {
+        read_all(GzipEncoder::new(data)).await
+    }
+
+    async fn zstd(data: &[u8]) -> Vec<u8> {
+        read_all(ZstdEncoder::new(data)).await
+    }
+
+    #[test]
+    fn detects_format_from_magic_bytes() {
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        assert_eq!(Compression::detect(&ZSTD_MAGIC), Compression::Zstd);
+        assert_eq!(Compression::detect(b"type,client"), Compression::None);
+        assert_eq!(Compression::detect(&[]), Compression::None);
+    }
+
+    #[test]
+    fn guesses_format_from_extension() {
+        assert_eq!(Compression::from_path("tx.csv.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("tx.csv.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("tx.csv"), Compression::None);
+    }
+
+    #[tokio::test]
+    async fn decompresses_detected_gzip_and_zstd() {
+        for compressed in [gzip(CSV).await, zstd(CSV).await, CSV.to_vec()] {
+            let reader = CompressedReader::detect(std::io::Cursor::new(compressed))
+                .await
+                .unwrap();
+            assert_eq!(read_all(reader).await, CSV);
+        }
+    }
+
+    #[tokio::test]
+    async fn decompresses_concatenated_gzip_members() {
+        let mut compressed = gzip(b"type,client,tx,amount\n").await;
+        compressed.extend(gzip(b"deposit,1,1,1.0\n").await);
+
+        let reader = CompressedReader::new(std::io::Cursor::new(compressed), Compression::Gzip);
+        assert_eq!(read_all(reader).await, CSV);
+    }
+}
@@ -0,0 +1,280 @@
+use std::io::{Cursor, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use futures::{StreamExt, stream};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader};
+use tokio::sync::mpsc;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use super::compression::{CompressedReader, Compression};
+use super::csv_reader::{CsvReaderOptions, CsvTransactionStream, TimestampedStream};
+use super::error::IoError;
+use crate::domain::AmountType;
+
+/// Parsed records each chunk may hold before its parser waits
+const DEFAULT_CHUNK_BUFFER: usize = 65_536;
+
+/// How `CsvTransactionStream::from_file_parallel_with_options` splits a file
+///
+/// With `preserve_order` (the default) records come out in file order, so
+/// disputes still follow the deposits they reference; each chunk after the
+/// one being read can only parse `buffer` records ahead, so larger buffers
+/// trade memory for parallelism. Without it, records come out as soon as any
+/// chunk has parsed them, which is only safe when order does not matter
+/// (e.g. with client sequencing or deposit-only loads).
+#[derive(Debug, Clone)]
+pub struct ParallelCsvOptions {
+    /// Number of byte ranges parsed concurrently (minimum 1)
+    pub chunks: usize,
+    /// Yield records in file order
+    pub preserve_order: bool,
+    /// Parsed records buffered per chunk
+    pub buffer: usize,
+    /// Header and amount checks applied to every chunk
+    pub reader: CsvReaderOptions,
+}
+
+impl ParallelCsvOptions {
+    /// Parse `chunks` ranges concurrently, in file order
+    pub fn new(chunks: usize) -> Self {
+        Self {
+            chunks,
+            preserve_order: true,
+            buffer: DEFAULT_CHUNK_BUFFER,
+            reader: CsvReaderOptions::default(),
+        }
+    }
+
+    /// Yield records in file order (defaults to true)
+    pub fn with_preserve_order(mut self, enabled: bool) -> Self {
+        self.preserve_order = enabled;
+        self
+    }
+
+    /// Parsed records buffered per chunk (defaults to 65,536; minimum 1)
+    pub fn with_buffer(mut self, records: usize) -> Self {
+        self.buffer = records;
+        self
+    }
+
+    /// Reader options applied to every chunk
+    pub fn with_reader_options(mut self, options: CsvReaderOptions) -> Self {
+        self.reader = options;
+        self
+    }
+}
+
+impl<A> CsvTransactionStream<A>
+where
+    A: AmountType + Unpin + 'static,
+{
+    /// Parse a file in `chunks` byte ranges concurrently, in file order
+    ///
+    /// See `from_file_parallel_with_options`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let stream = CsvTransactionStream::<FixedPoint>::from_file_parallel("huge.csv", 8).await?;
+    /// ```
+    pub async fn from_file_parallel(
+        path: impl AsRef<Path>,
+        chunks: usize,
+    ) -> Result<Self, IoError> {
+        Self::from_file_parallel_with_options(path, ParallelCsvOptions::new(chunks)).await
+    }
+
+    /// Split a file at line boundaries and parse the ranges on separate tasks
+    ///
+    /// Every range is parsed with the file's header row, which is checked
+    /// once up front; a header rejected by the reader options is returned as
+    /// an error. Ranges split at newlines, so quoted fields must not contain
+    /// line breaks, and the line and byte positions in `IoError::AtRecord`
+    /// are relative to the range the record was in. Compressed files cannot
+    /// be split and are read by a single parser, as with `from_file`.
+    pub async fn from_file_parallel_with_options(
+        path: impl AsRef<Path>,
+        options: ParallelCsvOptions,
+    ) -> Result<Self, IoError> {
+        let path = path.as_ref().to_path_buf();
+        let mut reader = BufReader::new(File::open(&path).await?);
+        if Compression::detect(reader.fill_buf().await?) != Compression::None {
+            let reader = CompressedReader::detect(reader).await?;
+            return Ok(Self::new_with_options(reader.compat(), options.reader));
+        }
+
+        let mut header = Vec::new();
+        reader.read_until(b'\n', &mut header).await?;
+        check_header::<A>(&header, options.reader.clone()).await?;
+
+        let ranges = split_ranges(&path, header.len() as u64, options.chunks.max(1)).await?;
+        let chunks: Vec<_> = ranges
+            .into_iter()
+            .map(|range| parse_chunk::<A>(path.clone(), header.clone(), range, options.clone()))
+            .collect();
+
+        let merged: TimestampedStream<A> = match options.preserve_order {
+            true => Box::pin(stream::iter(chunks).flatten()),
+            false => Box::pin(stream::select_all(chunks)),
+        };
+        Ok(Self::from_timestamped(merged))
+    }
+}
+
+/// Fail if the reader options reject the header row
+async fn check_header<A>(header: &[u8], options: CsvReaderOptions) -> Result<(), IoError>
+where
+    A: AmountType + Unpin + 'static,
+{
+    // A header on its own parses to no records, or to the header error
+    let mut stream =
+        CsvTransactionStream::<A>::new_with_options(Cursor::new(header.to_vec()).compat(), options);
+    match stream.next().await {
+        Some(Err(e)) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Byte ranges after the header, each ending just past a newline
+async fn split_ranges(path: &Path, start: u64, chunks: usize) -> Result<Vec<(u64, u64)>, IoError> {
+    let mut file = BufReader::new(File::open(path).await?);
+    let end = file.get_ref().metadata().await?.len();
+
+    let mut bounds = vec![start];
+    let size = end.saturating_sub(start);
+    for index in 1..chunks as u64 {
+        let target = (start + size * index / chunks as u64).max(*bounds.last().unwrap_or(&start));
+        if target >= end {
+            break;
+        }
+        // Move the cut to just past the next newline
+        file.seek(SeekFrom::Start(target)).await?;
+        let mut rest_of_line = Vec::new();
+        let skipped = file.read_until(b'\n', &mut rest_of_line).await? as u64;
+        bounds.push(target + skipped);
+    }
+    bounds.push(end);
+    bounds.dedup();
+
+    Ok(bounds.windows(2).map(|pair| (pair[0], pair[1])).collect())
+}
+
+/// Parse one byte range on its own task, yielding its records
+fn parse_chunk<A>(
+    path: PathBuf,
+    header: Vec<u8>,
+    (start, end): (u64, u64),
+    options: ParallelCsvOptions,
+) -> TimestampedStream<A>
+where
+    A: AmountType + Unpin + 'static,
+{
+    let (tx, rx) = mpsc::channel(options.buffer.max(1));
+    tokio::spawn(async move {
+        let mut file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(e) => {
+                let _ = tx.send(Err(e.into())).await;
+                return;
+            }
+        };
+        if let Err(e) = file.seek(SeekFrom::Start(start)).await {
+            let _ = tx.send(Err(e.into())).await;
+            return;
+        }
+
+        let reader = Cursor::new(header).chain(BufReader::new(file).take(end - start));
+        let mut records =
+            CsvTransactionStream::<A>::new_with_options(reader.compat(), options.reader)
+                .timestamped();
+        while let Some(record) = records.next().await {
+            // The receiver is gone once the stream is dropped
+            if tx.send(record).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Box::pin(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, TransactionId};
+    use std::io::Write;
+
+    fn csv_file(rows: usize) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "type,client,tx,amount").unwrap();
+        for tx in 1..=rows {
+            writeln!(file, "deposit,{},{tx},1.0", tx % 7 + 1).unwrap();
+        }
+        file.flush().unwrap();
+        file
+    }
+
+    async fn tx_ids(stream: CsvTransactionStream<FixedPoint>) -> Vec<TransactionId> {
+        stream
+            .map(|result| result.unwrap().tx_id().unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn ordered_chunks_yield_every_record_in_file_order() {
+        let file = csv_file(1_000);
+
+        for chunks in [1, 3, 8, 2_000] {
+            let stream = CsvTransactionStream::from_file_parallel(file.path(), chunks)
+                .await
+                .unwrap();
+            assert_eq!(
+                tx_ids(stream).await,
+                (1..=1_000).collect::<Vec<TransactionId>>(),
+                "{chunks} chunks"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unordered_chunks_yield_every_record_once() {
+        let file = csv_file(1_000);
+        let options = ParallelCsvOptions::new(4)
+            .with_preserve_order(false)
+            .with_buffer(16);
+
+        let stream = CsvTransactionStream::from_file_parallel_with_options(file.path(), options)
+            .await
+            .unwrap();
+        let mut ids = tx_ids(stream).await;
+        ids.sort_unstable();
+
+        assert_eq!(ids, (1..=1_000).collect::<Vec<TransactionId>>());
+    }
+
+    #[tokio::test]
+    async fn header_is_checked_once_up_front() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "type,client,amount\ndeposit,1,1.0").unwrap();
+        let options = ParallelCsvOptions::new(2).with_reader_options(CsvReaderOptions::strict());
+
+        let result = CsvTransactionStream::<FixedPoint>::from_file_parallel_with_options(
+            file.path(),
+            options,
+        )
+        .await;
+
+        assert!(matches!(result, Err(IoError::InvalidHeader(_))));
+
+        // Lenient reading still reports the bad record, once
+        let stream = CsvTransactionStream::<FixedPoint>::from_file_parallel(file.path(), 2)
+            .await
+            .unwrap();
+        let records: Vec<_> = stream.collect().await;
+        assert_eq!(records.len(), 1);
+        assert!(records[0].is_err());
+    }
+}
@@ -1,23 +1,37 @@
+#[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use csv_async::AsyncReaderBuilder;
-use futures::{Stream, StreamExt};
+use csv_async::{AsyncReader, AsyncReaderBuilder, ByteRecord, StringRecord};
+use futures::{Stream, stream};
 use futures::io::AsyncRead;
-use tokio::fs::File;
+use parking_lot::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::{AsyncBufReadExt, BufReader};
+#[cfg(not(target_arch = "wasm32"))]
 use tokio_util::compat::TokioAsyncReadCompatExt;
 
+#[cfg(not(target_arch = "wasm32"))]
+use super::compression::{CompressedReader, Compression};
 use super::error::IoError;
-use super::parse::RawTransactionRecord;
-use crate::domain::{AmountType, Transaction};
+use super::parse::BorrowedRecord;
+use crate::domain::{
+    AmountType, InputPrecision, RoundingPolicy, TimestampedTransaction, Transaction,
+};
+
+/// Boxed stream of parsed records including their optional timestamps
+pub(super) type TimestampedStream<A> =
+    Pin<Box<dyn Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send>>;
 
 /// Async stream of transactions from CSV input
 pub struct CsvTransactionStream<A>
 where
     A: AmountType + Unpin,
 {
-    inner: Pin<Box<dyn Stream<Item = Result<Transaction<A>, IoError>> + Send>>,
+    inner: TimestampedStream<A>,
+    position: ReadPosition,
 }
 
 impl<A> CsvTransactionStream<A>
@@ -25,25 +39,99 @@ where
     A: AmountType + Unpin,
 {
     /// Create a new transaction stream from an async reader
+    ///
+    /// Amounts with more decimal places than `A` stores are rejected.
     pub fn new<R>(reader: R) -> Self
     where
         R: AsyncRead + Unpin + Send + 'static,
     {
-        let csv_reader = AsyncReaderBuilder::new()
-            .trim(csv_async::Trim::All)
-            .flexible(true)
-            .create_deserializer(reader);
-
-        let stream = csv_reader
-            .into_deserialize::<RawTransactionRecord>()
-            .map(|result| {
-                result
-                    .map_err(IoError::from)
-                    .and_then(|raw| raw.parse::<A>())
-            });
+        Self::with_rounding(reader, RoundingPolicy::Reject)
+    }
+
+    /// Create a new transaction stream that normalizes amounts per `rounding`
+    ///
+    /// Use when a source quotes amounts at a higher precision than `A` stores.
+    /// Records that fail to parse are reported as `IoError::AtRecord`, carrying
+    /// the line, byte offset and fields of the offending record.
+    pub fn with_rounding<R>(reader: R, rounding: RoundingPolicy) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        Self::new_with_options(
+            reader,
+            CsvReaderOptions {
+                rounding,
+                ..CsvReaderOptions::default()
+            },
+        )
+    }
+
+    /// Create a new transaction stream that validates its input per `options`
+    ///
+    /// A header row rejected by the options is reported as
+    /// `IoError::InvalidHeader` and ends the stream before any record is read.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // Refuse feeds with unknown, duplicate or missing columns
+    /// let stream = CsvTransactionStream::<FixedPoint>::new_with_options(reader, CsvReaderOptions::strict());
+    /// ```
+    pub fn new_with_options<R>(reader: R, options: CsvReaderOptions) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        Self::from_reader(csv_reader(reader), options, ReadPosition::default(), 0)
+    }
+
+    /// Stream the records of `reader`, which stands at `position`, after
+    /// reading past `skip` records unparsed
+    fn from_reader<R>(
+        reader: AsyncReader<R>,
+        options: CsvReaderOptions,
+        position: ReadPosition,
+        skip: u64,
+    ) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let state = RecordState {
+            reader,
+            options,
+            headers: None,
+            record: ByteRecord::new(),
+            skip,
+            position: position.clone(),
+        };
+        let stream = stream::unfold(Some(state), |state| async move {
+            let mut state = state?;
+            // Header errors leave nothing worth reading, so stop there
+            if state.headers.is_none()
+                && let Err(e) = state.load_headers().await
+            {
+                return Some((Err(e), None));
+            }
+            let item = match state.read().await {
+                Ok(true) => state.parse(),
+                Ok(false) => return None,
+                // IO errors leave nothing more to read, so stop there
+                Err(e) if e.is_io_error() => return Some((Err(e.into()), None)),
+                Err(e) => Err(e.into()),
+            };
+            Some((item, Some(state)))
+        });
 
         Self {
             inner: Box::pin(stream),
+            position,
+        }
+    }
+
+    /// Wrap an already-parsed record stream
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) fn from_timestamped(inner: TimestampedStream<A>) -> Self {
+        Self {
+            inner,
+            position: ReadPosition::default(),
         }
     }
 
@@ -51,14 +139,426 @@ where
     ///
     /// Opens the file asynchronously and creates a CSV stream.
     /// This is a convenience method that handles tokio-futures compatibility internally.
+    /// Gzip and zstd inputs are detected from their contents and decompressed on the fly.
     ///
     /// # Example
     /// ```rust,ignore
-    /// let stream = CsvTransactionStream::<FixedPoint>::from_file("transactions.csv").await?;
+    /// let stream = CsvTransactionStream::<FixedPoint>::from_file("transactions.csv.gz").await?;
     /// ```
+    #[cfg(not(target_arch = "wasm32"))]
     pub async fn from_file(path: impl AsRef<Path>) -> Result<Self, IoError> {
-        let file = File::open(path.as_ref()).await?;
-        Ok(Self::new(file.compat()))
+        Self::from_file_with_options(path, CsvReaderOptions::default()).await
+    }
+
+    /// Create a new transaction stream from a file path, validated and
+    /// renamed per `options`
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let options = CsvReaderOptions::default().with_columns(legacy_mapping);
+    /// let stream = CsvTransactionStream::<FixedPoint>::from_file_with_options("legacy.csv", options).await?;
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn from_file_with_options(
+        path: impl AsRef<Path>,
+        options: CsvReaderOptions,
+    ) -> Result<Self, IoError> {
+        let reader = CompressedReader::open(path).await?;
+        Ok(Self::new_with_options(reader.compat(), options))
+    }
+
+    /// Open a file to continue reading it from `position`, as saved in a
+    /// `Checkpoint` of an earlier run
+    ///
+    /// Plain files are read from the header row and then seek straight to
+    /// the byte offset, so a resumed run does not re-read what was already
+    /// processed. Compressed files cannot seek; their first `position.record()`
+    /// records are read past without being parsed.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let checkpoint = Checkpoint::load("transactions.checkpoint")?;
+    /// let input = match checkpoint.position(0) {
+    ///     Some(position) => CsvTransactionStream::from_file_at("transactions.csv", options, position).await?,
+    ///     None => CsvTransactionStream::from_file_with_options("transactions.csv", options).await?,
+    /// };
+    /// StreamProcessor::new(mgr, store, SkipErrors)
+    ///     .resume_from(checkpoint)
+    ///     .add_csv_stream(input)
+    ///     .process()
+    ///     .await;
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn from_file_at(
+        path: impl AsRef<Path>,
+        options: CsvReaderOptions,
+        position: &csv::Position,
+    ) -> Result<Self, IoError> {
+        let mut file = BufReader::new(tokio::fs::File::open(path).await?);
+        let resumed = ReadPosition::new(position.clone());
+        match Compression::detect(file.fill_buf().await?) {
+            Compression::None => {
+                let mut reader = csv_reader(file.compat());
+                let mut seek_to = csv_async::Position::new();
+                seek_to
+                    .set_byte(position.byte())
+                    .set_line(position.line())
+                    .set_record(position.record());
+                reader.seek(seek_to).await?;
+                Ok(Self::from_reader(reader, options, resumed, 0))
+            }
+            compression => {
+                let reader = csv_reader(CompressedReader::new(file, compression).compat());
+                Ok(Self::from_reader(
+                    reader,
+                    options,
+                    resumed,
+                    position.record(),
+                ))
+            }
+        }
+    }
+
+    /// Handle to where the reader stands, for checkpoints (see
+    /// `StreamProcessor::add_csv_stream`)
+    pub fn position(&self) -> ReadPosition {
+        self.position.clone()
+    }
+
+    /// Convert into a stream that keeps the optional `timestamp` and
+    /// `idempotency_key` columns
+    ///
+    /// Use with `StreamProcessor::add_timestamped_stream` and
+    /// `StreamCombinator::MergeByTimestamp` for time-ordered merging.
+    pub fn timestamped(
+        self,
+    ) -> impl Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send + 'static
+    where
+        A: 'static,
+    {
+        self.inner
+    }
+}
+
+/// CSV reader configured as every transaction stream reads its input
+fn csv_reader<R: AsyncRead + Unpin + Send>(reader: R) -> AsyncReader<R> {
+    AsyncReaderBuilder::new()
+        .trim(csv_async::Trim::All)
+        .flexible(true)
+        .create_reader(reader)
+}
+
+/// Where a `CsvTransactionStream` stands in its input
+///
+/// Shared with the stream, which moves it past every record it reads:
+/// `byte` and `line` are where the next record starts, and `record` counts
+/// the records read so far, unreadable ones included (the header row is not
+/// a record). Seeking a new reader of the same input to this position with
+/// `CsvTransactionStream::from_file_at` continues with the next record.
+#[derive(Debug, Clone)]
+pub struct ReadPosition(Arc<Mutex<csv::Position>>);
+
+impl Default for ReadPosition {
+    fn default() -> Self {
+        Self::new(csv::Position::new())
+    }
+}
+
+impl ReadPosition {
+    fn new(position: csv::Position) -> Self {
+        Self(Arc::new(Mutex::new(position)))
+    }
+
+    /// The position after the last record read
+    pub fn get(&self) -> csv::Position {
+        self.0.lock().clone()
+    }
+
+    /// Move past a record read by `reader`
+    fn advance(&self, reader: &csv_async::Position) {
+        let mut position = self.0.lock();
+        let record = position.record() + 1;
+        position
+            .set_byte(reader.byte())
+            .set_line(reader.line())
+            .set_record(record);
+    }
+}
+
+/// Columns the reader understands
+const KNOWN_COLUMNS: [&str; 10] = [
+    "type",
+    "client",
+    "tx",
+    "amount",
+    "to",
+    "timestamp",
+    "seq",
+    "currency",
+    "idempotency_key",
+    "tag",
+];
+
+/// Columns every record needs
+const REQUIRED_COLUMNS: [&str; 3] = ["type", "client", "tx"];
+
+/// Renames a source's header columns to the columns the reader understands
+///
+/// For feeds from systems whose exports cannot be changed: a mapped header
+/// column is read as its canonical column, so `txn_type,customer_id,reference,value`
+/// parses like `type,client,tx,amount`. Unmapped columns keep their names, and
+/// header checks (`CsvReaderOptions::strict_headers`) apply to the renamed row.
+///
+/// # Example
+/// ```rust,ignore
+/// let legacy = ColumnMapping::new()
+///     .with_column("txn_type", "type")
+///     .with_column("customer_id", "client")
+///     .with_column("reference", "tx")
+///     .with_column("value", "amount");
+/// let options = CsvReaderOptions::default().with_columns(legacy);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnMapping {
+    /// (source column, canonical column) pairs
+    renames: Vec<(String, String)>,
+}
+
+impl ColumnMapping {
+    /// Create a mapping that renames nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the `source` column as `canonical` (e.g. `customer_id` as `client`)
+    ///
+    /// Mapping the same source column again replaces the earlier mapping.
+    pub fn with_column(mut self, source: impl Into<String>, canonical: impl Into<String>) -> Self {
+        let (source, canonical) = (source.into(), canonical.into());
+        match self
+            .renames
+            .iter_mut()
+            .find(|(mapped, _)| *mapped == source)
+        {
+            Some(rename) => rename.1 = canonical,
+            None => self.renames.push((source, canonical)),
+        }
+        self
+    }
+
+    /// Check if no column is renamed
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// The column `source` is read as
+    pub fn canonical<'a>(&'a self, source: &'a str) -> &'a str {
+        self.renames
+            .iter()
+            .find(|(mapped, _)| mapped == source)
+            .map_or(source, |(_, canonical)| canonical)
+    }
+
+    /// Fail if a column is mapped to a name the reader does not understand
+    pub fn validate(&self) -> Result<(), IoError> {
+        match self
+            .renames
+            .iter()
+            .find(|(_, canonical)| !KNOWN_COLUMNS.contains(&canonical.as_str()))
+        {
+            Some((source, canonical)) => Err(IoError::InvalidHeader(format!(
+                "column `{source}` is mapped to unknown column `{canonical}`"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The header row with every mapped column renamed
+    fn apply(&self, headers: &StringRecord) -> Result<StringRecord, IoError> {
+        self.validate()?;
+        Ok(headers
+            .iter()
+            .map(|column| self.canonical(column))
+            .collect())
+    }
+}
+
+/// How strictly `CsvTransactionStream` checks its input
+///
+/// The default is the historical, lenient behaviour: any header row is
+/// accepted and surplus fields are ignored. `strict()` turns every check on.
+#[derive(Debug, Clone)]
+pub struct CsvReaderOptions {
+    /// Reject header rows that lack `type`, `client` or `tx`, or that repeat
+    /// or leave a column name empty
+    pub strict_headers: bool,
+    /// Accept columns the reader does not know (with `strict_headers`) and
+    /// records with more fields than the header row
+    pub allow_extra_columns: bool,
+    /// Reject header rows without an `amount` column
+    pub require_amount_column: bool,
+    /// How to treat amounts with more decimal places than `A` stores
+    pub rounding: RoundingPolicy,
+    /// Header columns to read under another name
+    pub columns: ColumnMapping,
+    /// Record the decimal places of every parsed amount here
+    pub input_precision: Option<InputPrecision>,
+}
+
+impl Default for CsvReaderOptions {
+    fn default() -> Self {
+        Self {
+            strict_headers: false,
+            allow_extra_columns: true,
+            require_amount_column: false,
+            rounding: RoundingPolicy::Reject,
+            columns: ColumnMapping::default(),
+            input_precision: None,
+        }
+    }
+}
+
+impl CsvReaderOptions {
+    /// Every check enabled: exact known columns, `amount` required
+    pub fn strict() -> Self {
+        Self {
+            strict_headers: true,
+            allow_extra_columns: false,
+            require_amount_column: true,
+            ..Self::default()
+        }
+    }
+
+    /// Read header columns under the names given by `columns`
+    pub fn with_columns(mut self, columns: ColumnMapping) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Record the decimal places of every amount parsed into `precision`
+    /// (see `SnapshotFormat::with_input_precision`)
+    pub fn with_input_precision(mut self, precision: InputPrecision) -> Self {
+        self.input_precision = Some(precision);
+        self
+    }
+
+    /// Check a header row against these options
+    fn validate_headers(&self, headers: &StringRecord) -> Result<(), IoError> {
+        let invalid = |reason: String| Err(IoError::InvalidHeader(reason));
+
+        if self.strict_headers {
+            for (index, column) in headers.iter().enumerate() {
+                if column.is_empty() {
+                    return invalid(format!("column {} has no name", index + 1));
+                }
+                if headers.iter().take(index).any(|earlier| earlier == column) {
+                    return invalid(format!("duplicate column `{column}`"));
+                }
+                if !self.allow_extra_columns && !KNOWN_COLUMNS.contains(&column) {
+                    return invalid(format!("unknown column `{column}`"));
+                }
+            }
+            if let Some(missing) = REQUIRED_COLUMNS
+                .iter()
+                .find(|required| !headers.iter().any(|column| column == **required))
+            {
+                return invalid(format!("missing column `{missing}`"));
+            }
+        }
+        if self.require_amount_column && !headers.iter().any(|column| column == "amount") {
+            return invalid("missing column `amount`".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Reader state carried between records
+///
+/// Records are read into one reused `ByteRecord` and parsed in place, so a
+/// typical row costs no allocation.
+struct RecordState<R> {
+    reader: AsyncReader<R>,
+    options: CsvReaderOptions,
+    headers: Option<ByteRecord>,
+    record: ByteRecord,
+    /// Records already covered by `position`, read past before the first parse
+    skip: u64,
+    position: ReadPosition,
+}
+
+impl<R: AsyncRead + Unpin + Send> RecordState<R> {
+    /// Read, rename and check the header row
+    async fn load_headers(&mut self) -> Result<(), IoError> {
+        let headers = self.reader.headers().await?;
+        let headers = match self.options.columns.is_empty() {
+            true => headers.clone(),
+            false => self.options.columns.apply(headers)?,
+        };
+        self.options.validate_headers(&headers)?;
+        self.headers = Some(headers.into_byte_record());
+        Ok(())
+    }
+
+    /// Read the next record
+    async fn read(&mut self) -> Result<bool, csv_async::Error> {
+        while self.skip > 0 {
+            self.skip -= 1;
+            if !self.reader.read_byte_record(&mut self.record).await? {
+                return Ok(false);
+            }
+        }
+        let read = self.reader.read_byte_record(&mut self.record).await;
+        match &read {
+            Ok(false) => {}
+            Err(e) if e.is_io_error() => {}
+            _ => self.position.advance(self.reader.position()),
+        }
+        read
+    }
+
+    fn parse<A: AmountType>(&self) -> Result<TimestampedTransaction<A>, IoError> {
+        let columns = self.headers.as_ref().map_or(0, ByteRecord::len);
+        if !self.options.allow_extra_columns && self.record.len() > columns {
+            return Err(self.locate(IoError::ExtraFields {
+                expected: columns,
+                found: self.record.len(),
+            }));
+        }
+
+        self.record
+            .deserialize::<BorrowedRecord>(self.headers.as_ref())
+            .map_err(IoError::from)
+            .and_then(|raw| {
+                let parsed = raw.parse_timestamped_rounded::<A>(self.options.rounding)?;
+                if let (Some(precision), Some(amount)) =
+                    (&self.options.input_precision, raw.amount())
+                {
+                    precision.record::<A>(amount);
+                }
+                Ok(parsed)
+            })
+            .map_err(|e| self.locate(e))
+    }
+
+    /// Attach the current record's position and fields to an error
+    fn locate(&self, error: IoError) -> IoError {
+        let (line, byte) = self
+            .record
+            .position()
+            .map_or((0, 0), |position| (position.line(), position.byte()));
+
+        IoError::AtRecord {
+            line,
+            byte,
+            record: self
+                .record
+                .iter()
+                .map(String::from_utf8_lossy)
+                .collect::<Vec<_>>()
+                .join(","),
+            source: Box::new(error),
+        }
     }
 }
 
@@ -69,14 +569,17 @@ where
     type Item = Result<Transaction<A>, IoError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.inner.as_mut().poll_next(cx)
+        self.inner
+            .as_mut()
+            .poll_next(cx)
+            .map(|item| item.map(|result| result.map(|tx| tx.transaction)))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::FixedPoint;
+    use crate::domain::{ClientId, FixedPoint, TransactionId};
     use futures::StreamExt;
     use futures::io::Cursor;
 
@@ -100,6 +603,7 @@ resolve,1,1,
                 client_id,
                 tx_id,
                 amount,
+                ..
             } => {
                 assert_eq!(client_id, 1);
                 assert_eq!(tx_id, 1);
@@ -115,6 +619,7 @@ resolve,1,1,
                 client_id,
                 tx_id,
                 amount,
+                ..
             } => {
                 assert_eq!(client_id, 2);
                 assert_eq!(tx_id, 2);
@@ -130,6 +635,7 @@ resolve,1,1,
                 client_id,
                 tx_id,
                 amount,
+                ..
             } => {
                 assert_eq!(client_id, 1);
                 assert_eq!(tx_id, 3);
@@ -177,6 +683,7 @@ type,client,tx,amount
                 client_id,
                 tx_id,
                 amount,
+                ..
             } => {
                 assert_eq!(client_id, 1);
                 assert_eq!(tx_id, 1);
@@ -208,8 +715,8 @@ invalid,1,1,1.0
         let reader = Cursor::new(csv_data.as_bytes());
         let mut stream = CsvTransactionStream::<FixedPoint>::new(reader);
 
-        let result = stream.next().await.unwrap();
-        assert!(matches!(result, Err(IoError::InvalidTransactionType(_))));
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert!(matches!(error.inner(), IoError::InvalidTransactionType(_)));
     }
 
     #[tokio::test]
@@ -221,8 +728,8 @@ deposit,1,1,
         let reader = Cursor::new(csv_data.as_bytes());
         let mut stream = CsvTransactionStream::<FixedPoint>::new(reader);
 
-        let result = stream.next().await.unwrap();
-        assert!(matches!(result, Err(IoError::MissingField(_))));
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert!(matches!(error.inner(), IoError::MissingField(_)));
     }
 
     #[tokio::test]
@@ -234,8 +741,82 @@ deposit,1,1,not_a_number
         let reader = Cursor::new(csv_data.as_bytes());
         let mut stream = CsvTransactionStream::<FixedPoint>::new(reader);
 
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert!(matches!(error.inner(), IoError::InvalidAmount(_)));
+    }
+
+    #[tokio::test]
+    async fn tx_ids_beyond_u32_require_wide_ids() {
+        let csv_data = "\
+type,client,tx,amount
+deposit,1,5000000000,1.0
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let mut stream = CsvTransactionStream::<FixedPoint>::new(reader);
+
         let result = stream.next().await.unwrap();
-        assert!(matches!(result, Err(IoError::InvalidAmount(_))));
+        if cfg!(feature = "wide-tx-ids") {
+            assert_eq!(
+                result.unwrap().tx_id(),
+                Some(5_000_000_000u64 as TransactionId)
+            );
+        } else {
+            assert!(matches!(result.unwrap_err().inner(), IoError::CsvAsync(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn client_ids_beyond_u16_require_wide_ids() {
+        let csv_data = "\
+type,client,tx,amount
+deposit,70000,1,1.0
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let mut stream = CsvTransactionStream::<FixedPoint>::new(reader);
+
+        let result = stream.next().await.unwrap();
+        if cfg!(feature = "wide-client-ids") {
+            assert_eq!(result.unwrap().client_id(), 70_000u32 as ClientId);
+        } else {
+            assert!(matches!(result.unwrap_err().inner(), IoError::CsvAsync(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn parse_errors_carry_line_and_record() {
+        let csv_data = "\
+type,client,tx,amount
+deposit,1,1,1.0
+deposit,1,2,1.x
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> = CsvTransactionStream::<FixedPoint>::new(reader).collect().await;
+
+        let error = results[1].as_ref().unwrap_err();
+        assert_eq!(error.line(), Some(3));
+        assert!(matches!(
+            error,
+            IoError::AtRecord { byte: 38, record, .. } if record == "deposit,1,2,1.x"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Line 3: Invalid amount format: 1.x (record: deposit,1,2,1.x)"
+        );
+    }
+
+    #[tokio::test]
+    async fn continues_after_malformed_record() {
+        let csv_data = "\
+type,client,tx,amount
+deposit,1,x,1.0
+deposit,1,2,1.0
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> = CsvTransactionStream::<FixedPoint>::new(reader).collect().await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap_err().line(), Some(2));
+        assert!(results[1].is_ok());
     }
 
     #[tokio::test]
@@ -267,4 +848,293 @@ chargeback,1,1,
         assert_eq!(transactions.len(), 5);
         assert!(transactions.iter().all(|r| r.is_ok()));
     }
+
+    #[tokio::test]
+    async fn reads_transfer_with_destination_column() {
+        let csv_data = "\
+type,client,tx,amount,to
+deposit,1,1,5.0
+transfer,1,2,2.0,7
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let mut stream = CsvTransactionStream::<FixedPoint>::new(reader);
+
+        let tx1 = stream.next().await.unwrap().unwrap();
+        assert!(matches!(tx1, Transaction::Deposit { .. }));
+
+        let tx2 = stream.next().await.unwrap().unwrap();
+        assert!(matches!(
+            tx2,
+            Transaction::Transfer {
+                from_client: 1,
+                to_client: 7,
+                tx_id: 2,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn timestamped_stream_reads_timestamp_column() {
+        let csv_data = "\
+type,client,tx,amount,timestamp
+deposit,1,1,1.0,1000
+dispute,1,1,,
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let mut stream = Box::pin(CsvTransactionStream::<FixedPoint>::new(reader).timestamped());
+
+        let tx1 = stream.next().await.unwrap().unwrap();
+        assert_eq!(tx1.timestamp, Some(1000));
+
+        let tx2 = stream.next().await.unwrap().unwrap();
+        assert_eq!(tx2.timestamp, None);
+        assert!(matches!(tx2.transaction, Transaction::Dispute { .. }));
+    }
+
+    #[tokio::test]
+    async fn with_rounding_mixes_source_precisions() {
+        let csv_data = "\
+type,client,tx,amount
+deposit,1,1,1.25
+deposit,1,2,0.123456
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let stream =
+            CsvTransactionStream::<FixedPoint>::with_rounding(reader, RoundingPolicy::HalfUp);
+        let amounts: Vec<_> = stream
+            .map(|tx| match tx.unwrap() {
+                Transaction::Deposit { amount, .. } => amount,
+                other => panic!("Expected Deposit, got {:?}", other),
+            })
+            .collect()
+            .await;
+
+        assert_eq!(
+            amounts,
+            vec![FixedPoint::from_raw(12_500), FixedPoint::from_raw(1_235)]
+        );
+    }
+
+    #[tokio::test]
+    async fn input_precision_records_parsed_amounts_only() {
+        let csv_data = "\
+type,client,tx,amount
+deposit,1,1,1.5
+deposit,1,2,0.25
+withdrawal,1,3,0.123456
+dispute,1,1,
+";
+        let precision = InputPrecision::new();
+        let options = CsvReaderOptions::default().with_input_precision(precision.clone());
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> = CsvTransactionStream::<FixedPoint>::new_with_options(reader, options)
+            .collect()
+            .await;
+
+        // The 6-place withdrawal is rejected, so it does not count
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 3);
+        assert_eq!(precision.decimals(), 2);
+    }
+
+    async fn header_error(csv_data: &'static str, options: CsvReaderOptions) -> IoError {
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> = CsvTransactionStream::<FixedPoint>::new_with_options(reader, options)
+            .collect()
+            .await;
+
+        // A rejected header ends the stream without reading any record
+        assert_eq!(results.len(), 1);
+        results.into_iter().next().unwrap().unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn strict_options_reject_bad_headers_upfront() {
+        let strict = CsvReaderOptions::strict();
+
+        for (csv_data, reason) in [
+            ("type,client,tx,amount,note\ndeposit,1,1,1.0,x\n", "unknown column `note`"),
+            ("type,client,tx,tx,amount\ndeposit,1,1,1,1.0\n", "duplicate column `tx`"),
+            ("type,client,amount\ndeposit,1,1.0\n", "missing column `tx`"),
+            ("type,client,tx\ndispute,1,1\n", "missing column `amount`"),
+        ] {
+            let error = header_error(csv_data, strict.clone()).await;
+            assert!(matches!(&error, IoError::InvalidHeader(r) if r == reason), "{error}");
+        }
+    }
+
+    #[tokio::test]
+    async fn extra_columns_are_allowed_when_configured() {
+        let csv_data = "\
+type,client,tx,amount,note
+deposit,1,1,1.0,x
+deposit,1,2,1.0,x,surplus
+";
+        let options = CsvReaderOptions {
+            allow_extra_columns: true,
+            ..CsvReaderOptions::strict()
+        };
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> = CsvTransactionStream::<FixedPoint>::new_with_options(reader, options)
+            .collect()
+            .await;
+
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn strict_options_reject_records_with_surplus_fields() {
+        let csv_data = "\
+type,client,tx,amount
+deposit,1,1,1.0,junk
+dispute,1,1
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> =
+            CsvTransactionStream::<FixedPoint>::new_with_options(reader, CsvReaderOptions::strict())
+                .collect()
+                .await;
+
+        let error = results[0].as_ref().unwrap_err();
+        assert_eq!(error.line(), Some(2));
+        assert!(matches!(
+            error.inner(),
+            IoError::ExtraFields {
+                expected: 4,
+                found: 5
+            }
+        ));
+        // Short records (no trailing amount) are still fine
+        assert!(results[1].is_ok());
+    }
+
+    #[tokio::test]
+    async fn default_options_stay_lenient() {
+        let csv_data = "\
+type,client,tx,amount,note,note
+deposit,1,1,1.0,x,y,surplus
+";
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> =
+            CsvTransactionStream::<FixedPoint>::new_with_options(reader, CsvReaderOptions::default())
+                .collect()
+                .await;
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok());
+    }
+
+    #[tokio::test]
+    async fn column_mapping_reads_legacy_headers() {
+        let csv_data = "\
+txn_type,customer_id,reference,value
+deposit,1,1,2.5
+withdrawal,1,2,1.0
+";
+        let legacy = ColumnMapping::new()
+            .with_column("txn_type", "type")
+            .with_column("customer_id", "client")
+            .with_column("reference", "tx")
+            .with_column("value", "amount");
+        let options = CsvReaderOptions::strict().with_columns(legacy);
+        let reader = Cursor::new(csv_data.as_bytes());
+        let results: Vec<_> = CsvTransactionStream::<FixedPoint>::new_with_options(reader, options)
+            .collect()
+            .await;
+
+        assert_eq!(results.len(), 2);
+        match results[0].as_ref().unwrap() {
+            Transaction::Deposit { client_id, amount, .. } => {
+                assert_eq!(*client_id, 1);
+                assert_eq!(*amount, FixedPoint::from_raw(25_000));
+            }
+            other => panic!("expected a deposit, got {other:?}"),
+        }
+        assert!(matches!(results[1], Ok(Transaction::Withdrawal { .. })));
+    }
+
+    #[tokio::test]
+    async fn column_mapping_rejects_unknown_targets() {
+        let mapping = ColumnMapping::new().with_column("value", "amount").with_column("ref", "txid");
+        assert_eq!(mapping.canonical("value"), "amount");
+        assert_eq!(mapping.canonical("memo"), "memo");
+
+        let options = CsvReaderOptions::default().with_columns(mapping);
+        let error = header_error("type,client,ref\ndeposit,1,1\n", options).await;
+        assert!(
+            matches!(&error, IoError::InvalidHeader(r) if r == "column `ref` is mapped to unknown column `txid`"),
+            "{error}"
+        );
+    }
+
+    fn tx_ids(results: &[Result<Transaction<FixedPoint>, IoError>]) -> Vec<TransactionId> {
+        results
+            .iter()
+            .map(|result| match result.as_ref().unwrap() {
+                Transaction::Deposit { tx_id, .. } => *tx_id,
+                other => panic!("expected a deposit, got {other:?}"),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn from_file_at_seeks_past_the_records_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transactions.csv");
+        std::fs::write(
+            &path,
+            "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,1,2,1.0\ndeposit,1,3,1.0\n",
+        )
+        .unwrap();
+
+        let mut stream = CsvTransactionStream::<FixedPoint>::from_file(&path)
+            .await
+            .unwrap();
+        stream.next().await.unwrap().unwrap();
+        stream.next().await.unwrap().unwrap();
+        let position = stream.position().get();
+        assert_eq!((position.record(), position.line()), (2, 4));
+
+        // Records before the position are never read again, even if they changed
+        let mut contents = std::fs::read(&path).unwrap();
+        contents[22..position.byte() as usize].fill(b'#');
+        std::fs::write(&path, contents).unwrap();
+
+        let resumed =
+            CsvTransactionStream::from_file_at(&path, CsvReaderOptions::default(), &position)
+                .await
+                .unwrap();
+        let handle = resumed.position();
+        let results: Vec<_> = resumed.collect().await;
+
+        assert_eq!(tx_ids(&results), [3]);
+        assert_eq!(handle.get().record(), 3);
+    }
+
+    #[tokio::test]
+    async fn from_file_at_reads_past_records_of_compressed_files() {
+        use async_compression::tokio::bufread::GzipEncoder;
+        use tokio::io::AsyncReadExt;
+
+        let csv_data: &[u8] = b"type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,1,2,1.0\n";
+        let mut gzip = Vec::new();
+        GzipEncoder::new(csv_data)
+            .read_to_end(&mut gzip)
+            .await
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transactions.csv.gz");
+        std::fs::write(&path, gzip).unwrap();
+
+        let mut position = csv::Position::new();
+        position.set_byte(38).set_line(3).set_record(1);
+        let results: Vec<_> =
+            CsvTransactionStream::from_file_at(&path, CsvReaderOptions::default(), &position)
+                .await
+                .unwrap()
+                .collect()
+                .await;
+
+        assert_eq!(tx_ids(&results), [2]);
+    }
 }
@@ -1,18 +1,56 @@
 use tokio::io::AsyncWrite;
 
 use super::error::IoError;
+use super::snapshot_filter::SnapshotFilter;
+use super::snapshot_sink::{CsvSnapshotSink, write_snapshot_to, write_snapshot_to_filtered};
 use crate::domain::AmountType;
-use crate::storage::ClientAccountManager;
+use crate::storage::{ClientAccountManager, SnapshotFormat};
 
 /// Write account snapshots to CSV format
+///
+/// See `write_snapshot_to` for other formats and for writing several outputs at once.
 pub async fn write_snapshot<A, M, W>(account_manager: &M, writer: W) -> Result<(), IoError>
 where
     A: AmountType,
     M: ClientAccountManager<A>,
     W: AsyncWrite + Unpin + Send,
 {
-    account_manager.snapshot(writer).await?;
-    Ok(())
+    write_snapshot_to(account_manager, &mut CsvSnapshotSink::new(writer)).await
+}
+
+/// Write account snapshots to CSV format with custom number formatting
+pub async fn write_snapshot_with_format<A, M, W>(
+    account_manager: &M,
+    writer: W,
+    format: &SnapshotFormat,
+) -> Result<(), IoError>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    W: AsyncWrite + Unpin + Send,
+{
+    let mut sink = CsvSnapshotSink::new(writer).with_format(format.clone());
+    write_snapshot_to(account_manager, &mut sink).await
+}
+
+/// Write the accounts selected by `filter` to CSV format
+///
+/// # Example
+/// ```rust,ignore
+/// let filter = SnapshotFilter::default().locked_only();
+/// write_snapshot_with_filter(&account_manager, writer, &filter).await?;
+/// ```
+pub async fn write_snapshot_with_filter<A, M, W>(
+    account_manager: &M,
+    writer: W,
+    filter: &SnapshotFilter,
+) -> Result<(), IoError>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    W: AsyncWrite + Unpin + Send,
+{
+    write_snapshot_to_filtered(account_manager, &mut CsvSnapshotSink::new(writer), filter).await
 }
 
 #[cfg(test)]
@@ -143,4 +181,64 @@ mod tests {
         let result = String::from_utf8(output).unwrap();
         assert!(result.contains("1.2345"));
     }
+
+    #[tokio::test]
+    async fn writes_custom_format() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        {
+            let mut entry = manager.entry(1).unwrap();
+            entry
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(12_345)))
+                .unwrap();
+        }
+
+        let format = SnapshotFormat::default()
+            .with_decimals(2)
+            .with_decimal_separator(',')
+            .with_delimiter(';');
+        let mut output = Vec::new();
+        write_snapshot_with_format(&manager, &mut output, &format)
+            .await
+            .unwrap();
+
+        let result = String::from_utf8(output).unwrap();
+        assert_eq!(
+            result,
+            "client;available;held;total;locked\n1;1,23;0,00;1,23;false\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_only_filtered_accounts() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        for i in 1..=3 {
+            let mut entry = manager.entry(i).unwrap();
+            entry
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+                .unwrap();
+        }
+        {
+            let mut entry = manager.entry(2).unwrap();
+            entry
+                .try_update(|acc| operations::apply_dispute(acc, 1, FixedPoint::from_raw(10_000)))
+                .unwrap();
+            entry
+                .try_update(|acc| {
+                    operations::apply_chargeback(acc, 1, FixedPoint::from_raw(10_000))
+                })
+                .unwrap();
+        }
+
+        let mut output = Vec::new();
+        let filter = SnapshotFilter::default().locked_only();
+        write_snapshot_with_filter(&manager, &mut output, &filter)
+            .await
+            .unwrap();
+
+        let result = String::from_utf8(output).unwrap();
+        assert_eq!(
+            result,
+            "client,available,held,total,locked\n2,0.0000,0.0000,0.0000,true\n"
+        );
+    }
 }
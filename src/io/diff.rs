@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use super::error::IoError;
+use crate::domain::{AmountType, ClientId, DomainError};
+
+/// Balances of one snapshot row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotBalance<A: AmountType> {
+    pub available: A,
+    pub held: A,
+    pub locked: bool,
+}
+
+impl<A: AmountType> SnapshotBalance<A> {
+    /// Available plus held funds
+    pub fn total(&self) -> A {
+        self.available + self.held
+    }
+}
+
+/// How an account changed between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaStatus {
+    /// Only in the new snapshot
+    New,
+    /// Only in the old snapshot
+    Removed,
+    /// Became locked
+    Locked,
+    /// Was locked, no longer is
+    Unlocked,
+    /// Balances changed, lock state did not
+    Changed,
+}
+
+impl DeltaStatus {
+    /// Lowercase name used in the CSV `status` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Removed => "removed",
+            Self::Locked => "locked",
+            Self::Unlocked => "unlocked",
+            Self::Changed => "changed",
+        }
+    }
+}
+
+/// Change to one client's row (one per currency with a currency column)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDelta<A: AmountType> {
+    pub client: ClientId,
+    /// Currency of the row (None = base currency)
+    pub currency: Option<String>,
+    pub before: Option<SnapshotBalance<A>>,
+    pub after: Option<SnapshotBalance<A>>,
+}
+
+impl<A: AmountType> AccountDelta<A> {
+    /// Classify the change (a lock change takes precedence over balance changes)
+    pub fn status(&self) -> DeltaStatus {
+        match (&self.before, &self.after) {
+            (None, _) => DeltaStatus::New,
+            (_, None) => DeltaStatus::Removed,
+            (Some(before), Some(after)) if !before.locked && after.locked => DeltaStatus::Locked,
+            (Some(before), Some(after)) if before.locked && !after.locked => DeltaStatus::Unlocked,
+            _ => DeltaStatus::Changed,
+        }
+    }
+
+    /// Change in available funds (a missing side counts as zero)
+    pub fn available_change(&self) -> Result<A, DomainError> {
+        self.change(|balance| balance.available)
+    }
+
+    /// Change in held funds (a missing side counts as zero)
+    pub fn held_change(&self) -> Result<A, DomainError> {
+        self.change(|balance| balance.held)
+    }
+
+    /// Change in total funds (a missing side counts as zero)
+    pub fn total_change(&self) -> Result<A, DomainError> {
+        self.change(|balance| balance.total())
+    }
+
+    fn change(&self, field: impl Fn(&SnapshotBalance<A>) -> A) -> Result<A, DomainError> {
+        let before = self.before.as_ref().map_or(A::zero(), &field);
+        let after = self.after.as_ref().map_or(A::zero(), &field);
+        after.checked_sub(before).ok_or(DomainError::Overflow)
+    }
+}
+
+/// Per-client differences between two account snapshots
+///
+/// Unchanged rows are omitted; deltas are ordered by client, then currency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotDiff<A: AmountType> {
+    pub deltas: Vec<AccountDelta<A>>,
+}
+
+impl<A: AmountType> SnapshotDiff<A> {
+    /// Whether the snapshots hold the same balances
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Deltas with the given status
+    pub fn with_status(&self, status: DeltaStatus) -> impl Iterator<Item = &AccountDelta<A>> {
+        self.deltas
+            .iter()
+            .filter(move |delta| delta.status() == status)
+    }
+
+    /// Write the diff as CSV: `client,currency,status,available,held,total,locked`
+    ///
+    /// Amount columns are changes (new minus old); `locked` is the lock state
+    /// in the new snapshot (in the old one for removed accounts).
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), IoError> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for delta in &self.deltas {
+            let locked = delta.after.or(delta.before).is_some_and(|b| b.locked);
+            writer.serialize(DiffRow {
+                client: delta.client,
+                currency: delta.currency.as_deref(),
+                status: delta.status().as_str(),
+                available: delta.available_change()?.to_decimal_string(),
+                held: delta.held_change()?.to_decimal_string(),
+                total: delta.total_change()?.to_decimal_string(),
+                locked,
+            })?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct DiffRow<'a> {
+    client: ClientId,
+    currency: Option<&'a str>,
+    status: &'static str,
+    available: String,
+    held: String,
+    total: String,
+    locked: bool,
+}
+
+/// Snapshot row as written by `write_snapshot` (the currency column is optional)
+#[derive(Deserialize)]
+struct SnapshotRow {
+    client: ClientId,
+    #[serde(default)]
+    currency: Option<String>,
+    available: String,
+    held: String,
+    locked: bool,
+}
+
+/// Compare two account snapshots in the standard CSV format
+///
+/// Rows are matched by client (and currency, when snapshots were written with
+/// a currency column). Extra columns such as `total` are ignored; amounts must
+/// use `.` as the decimal separator.
+///
+/// # Example
+/// ```rust,ignore
+/// let diff = diff_snapshots::<FixedPoint, _, _>(File::open("old.csv")?, File::open("new.csv")?)?;
+/// for delta in diff.with_status(DeltaStatus::Locked) {
+///     println!("client {} locked", delta.client);
+/// }
+/// ```
+pub fn diff_snapshots<A, R1, R2>(old: R1, new: R2) -> Result<SnapshotDiff<A>, IoError>
+where
+    A: AmountType,
+    R1: Read,
+    R2: Read,
+{
+    let old = read_snapshot::<A, _>(old)?;
+    let mut new = read_snapshot::<A, _>(new)?;
+
+    let mut deltas = Vec::new();
+    for (key, before) in old {
+        let after = new.remove(&key);
+        if after != Some(before) {
+            deltas.push((key, Some(before), after));
+        }
+    }
+    deltas.extend(new.into_iter().map(|(key, after)| (key, None, Some(after))));
+    deltas.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+
+    Ok(SnapshotDiff {
+        deltas: deltas
+            .into_iter()
+            .map(|((client, currency), before, after)| AccountDelta {
+                client,
+                currency,
+                before,
+                after,
+            })
+            .collect(),
+    })
+}
+
+type SnapshotKey = (ClientId, Option<String>);
+
+fn read_snapshot<A: AmountType, R: Read>(
+    reader: R,
+) -> Result<BTreeMap<SnapshotKey, SnapshotBalance<A>>, IoError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let mut rows = BTreeMap::new();
+    for row in reader.deserialize() {
+        let row: SnapshotRow = row?;
+        let amount = |value: &str| {
+            A::from_decimal_str(value).map_err(|_| IoError::InvalidAmount(value.to_string()))
+        };
+        let balance = SnapshotBalance {
+            available: amount(&row.available)?,
+            held: amount(&row.held)?,
+            locked: row.locked,
+        };
+
+        let currency = row.currency.filter(|currency| !currency.is_empty());
+        if rows.insert((row.client, currency), balance).is_some() {
+            return Err(IoError::InvalidSnapshot(format!(
+                "duplicate row for client {}",
+                row.client
+            )));
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    const OLD: &str = "client,available,held,total,locked\n\
+                       1,1.0000,0.0000,1.0000,false\n\
+                       2,5.0000,1.0000,6.0000,false\n\
+                       3,2.0000,0.0000,2.0000,false\n\
+                       4,3.0000,0.0000,3.0000,false\n";
+    const NEW: &str = "client,available,held,total,locked\n\
+                       1,1.0000,0.0000,1.0000,false\n\
+                       2,5.0000,0.0000,5.0000,true\n\
+                       3,2.5000,0.0000,2.5000,false\n\
+                       5,0.7500,0.0000,0.7500,false\n";
+
+    fn diff(old: &str, new: &str) -> SnapshotDiff<FixedPoint> {
+        diff_snapshots(old.as_bytes(), new.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn reports_changed_new_removed_and_locked_accounts() {
+        let diff = diff(OLD, NEW);
+
+        let statuses: Vec<_> = diff
+            .deltas
+            .iter()
+            .map(|delta| (delta.client, delta.status()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (2, DeltaStatus::Locked),
+                (3, DeltaStatus::Changed),
+                (4, DeltaStatus::Removed),
+                (5, DeltaStatus::New),
+            ]
+        );
+        assert_eq!(
+            diff.deltas[0].held_change(),
+            Ok(FixedPoint::from_raw(-10_000))
+        );
+        assert_eq!(
+            diff.deltas[1].total_change(),
+            Ok(FixedPoint::from_raw(5_000))
+        );
+        assert_eq!(
+            diff.deltas[2].total_change(),
+            Ok(FixedPoint::from_raw(-30_000))
+        );
+        assert_eq!(diff.with_status(DeltaStatus::New).count(), 1);
+    }
+
+    #[test]
+    fn identical_snapshots_have_no_deltas() {
+        assert!(diff(OLD, OLD).is_empty());
+    }
+
+    #[test]
+    fn writes_diff_as_csv() {
+        let mut out = Vec::new();
+        diff(OLD, NEW).write_csv(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,currency,status,available,held,total,locked\n\
+             2,,locked,0.0000,-1.0000,-1.0000,true\n\
+             3,,changed,0.5000,0.0000,0.5000,false\n\
+             4,,removed,-3.0000,0.0000,-3.0000,false\n\
+             5,,new,0.7500,0.0000,0.7500,false\n"
+        );
+    }
+
+    #[test]
+    fn matches_rows_by_currency() {
+        let old = "client,currency,available,held,total,locked\n\
+                   1,,1.0000,0.0000,1.0000,false\n\
+                   1,EUR,2.0000,0.0000,2.0000,false\n";
+        let new = "client,currency,available,held,total,locked\n\
+                   1,,1.0000,0.0000,1.0000,false\n\
+                   1,EUR,3.0000,0.0000,3.0000,false\n";
+
+        let diff = diff(old, new);
+        assert_eq!(diff.deltas.len(), 1);
+        assert_eq!(diff.deltas[0].currency.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn rejects_duplicate_and_malformed_rows() {
+        let duplicate =
+            "client,available,held,total,locked\n1,1.0,0,1.0,false\n1,2.0,0,2.0,false\n";
+        let malformed = "client,available,held,total,locked\n1,abc,0,1.0,false\n";
+
+        assert!(matches!(
+            diff_snapshots::<FixedPoint, _, _>(duplicate.as_bytes(), OLD.as_bytes()),
+            Err(IoError::InvalidSnapshot(_))
+        ));
+        assert!(matches!(
+            diff_snapshots::<FixedPoint, _, _>(malformed.as_bytes(), OLD.as_bytes()),
+            Err(IoError::InvalidAmount(_))
+        ));
+    }
+}
@@ -1,7 +1,7 @@
 use std::io;
 use thiserror::Error;
 
-use crate::domain::DomainError;
+use crate::domain::{ClientId, DomainError};
 use crate::storage::StorageError;
 
 /// IO-level errors for CSV parsing and stream processing
@@ -13,9 +13,25 @@ pub enum IoError {
     #[error("CSV async parsing error: {0}")]
     CsvAsync(#[from] csv_async::Error),
 
+    #[cfg(feature = "tcp")]
+    #[error("JSON parsing error: {0}")]
+    Json(#[from] serde_json::Error),
+
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
 
+    #[cfg(feature = "arrow")]
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    #[cfg(feature = "object-store")]
+    #[error("Object store error: {0}")]
+    ObjectStore(#[from] object_store::Error),
+
+    #[cfg(feature = "object-store")]
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
     #[error("Invalid transaction type: {0}")]
     InvalidTransactionType(String),
 
@@ -25,6 +41,48 @@ pub enum IoError {
     #[error("Invalid amount format: {0}")]
     InvalidAmount(String),
 
+    #[error("Invalid currency code: {0}")]
+    InvalidCurrency(String),
+
+    #[error("Invalid header row: {0}")]
+    InvalidHeader(String),
+
+    #[error("Record has {found} fields but the header has {expected}")]
+    ExtraFields { expected: usize, found: usize },
+
+    #[error("Sequence gap for client {client_id}: expected {expected}, resumed at {found}")]
+    SequenceGap {
+        client_id: ClientId,
+        expected: u64,
+        found: u64,
+    },
+
+    #[error(
+        "Duplicate or stale sequence number for client {client_id}: {found} (expected {expected})"
+    )]
+    StaleSequence {
+        client_id: ClientId,
+        expected: u64,
+        found: u64,
+    },
+
+    #[error("Line {line}: {source} (record: {record})")]
+    AtRecord {
+        /// 1-based line on which the record starts
+        line: u64,
+        /// Byte offset of the record in the (decompressed) input
+        byte: u64,
+        /// The record's fields, comma-joined
+        record: String,
+        source: Box<IoError>,
+    },
+
+    #[error("Invalid checkpoint: {0}")]
+    InvalidCheckpoint(String),
+
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+
     #[error("Domain error: {0}")]
     Domain(#[from] DomainError),
 
@@ -32,6 +90,66 @@ pub enum IoError {
     Storage(#[from] StorageError),
 }
 
+impl IoError {
+    /// The underlying error, without any record position
+    pub fn inner(&self) -> &IoError {
+        match self {
+            IoError::AtRecord { source, .. } => source.inner(),
+            error => error,
+        }
+    }
+
+    /// Line of the offending record, if known
+    pub fn line(&self) -> Option<u64> {
+        match self {
+            IoError::AtRecord { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+
+    /// Whether reading again may succeed: a dropped connection, a timeout or
+    /// an interrupted read, or a generic object-store failure
+    ///
+    /// Parse errors and other malformed input are permanent; so are missing
+    /// files and denied permissions.
+    pub fn is_transient(&self) -> bool {
+        match self.inner() {
+            IoError::Io(e) => is_transient_kind(e.kind()),
+            IoError::Csv(e) => match e.kind() {
+                csv::ErrorKind::Io(e) => is_transient_kind(e.kind()),
+                _ => false,
+            },
+            IoError::CsvAsync(e) => match e.kind() {
+                csv_async::ErrorKind::Io(e) => is_transient_kind(e.kind()),
+                _ => false,
+            },
+            #[cfg(feature = "arrow")]
+            IoError::Arrow(arrow_schema::ArrowError::IoError(_, e)) => is_transient_kind(e.kind()),
+            #[cfg(feature = "object-store")]
+            IoError::ObjectStore(e) => matches!(
+                e,
+                object_store::Error::Generic { .. } | object_store::Error::JoinError { .. }
+            ),
+            _ => false,
+        }
+    }
+}
+
+fn is_transient_kind(kind: io::ErrorKind) -> bool {
+    use io::ErrorKind::*;
+    matches!(
+        kind,
+        ConnectionReset
+            | ConnectionAborted
+            | ConnectionRefused
+            | NotConnected
+            | BrokenPipe
+            | TimedOut
+            | Interrupted
+            | WouldBlock
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,6 +168,54 @@ mod tests {
             IoError::InvalidAmount("xyz".to_string()).to_string(),
             "Invalid amount format: xyz"
         );
+        assert_eq!(
+            IoError::InvalidCurrency("US".to_string()).to_string(),
+            "Invalid currency code: US"
+        );
+        assert_eq!(
+            IoError::SequenceGap {
+                client_id: 1,
+                expected: 2,
+                found: 5
+            }
+            .to_string(),
+            "Sequence gap for client 1: expected 2, resumed at 5"
+        );
+    }
+
+    #[test]
+    fn only_connection_level_failures_are_transient() {
+        let io_error = |kind| IoError::Io(io::Error::from(kind));
+        assert!(io_error(io::ErrorKind::ConnectionReset).is_transient());
+        assert!(io_error(io::ErrorKind::TimedOut).is_transient());
+        assert!(!io_error(io::ErrorKind::NotFound).is_transient());
+        assert!(!IoError::InvalidAmount("x".to_string()).is_transient());
+
+        let at_record = IoError::AtRecord {
+            line: 3,
+            byte: 40,
+            record: String::new(),
+            source: Box::new(io_error(io::ErrorKind::BrokenPipe)),
+        };
+        assert!(at_record.is_transient());
+    }
+
+    #[test]
+    fn record_position_wraps_underlying_error() {
+        let error = IoError::AtRecord {
+            line: 7,
+            byte: 120,
+            record: "refund,1,4,1.0".to_string(),
+            source: Box::new(IoError::InvalidTransactionType("refund".to_string())),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "Line 7: Invalid transaction type: refund (record: refund,1,4,1.0)"
+        );
+        assert_eq!(error.line(), Some(7));
+        assert!(matches!(error.inner(), IoError::InvalidTransactionType(_)));
+        assert_eq!(IoError::MissingField("tx".to_string()).line(), None);
     }
 
     #[test]
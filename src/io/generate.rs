@@ -0,0 +1,231 @@
+use std::io::{self, Write};
+
+use crate::domain::ClientId;
+
+/// Synthetic transaction CSV generator for QA and load testing
+///
+/// Each row's type is drawn from the configured ratios; whatever probability
+/// is left over resolves an open dispute. Clients are numbered `1..=clients`,
+/// transaction ids count up from 1, and disputes only reference deposits made
+/// by the same client, so the dataset exercises the full lifecycle rather
+/// than producing mostly rejected rows; a dispute or resolve draw with
+/// nothing to reference writes no row. Output depends only on the settings
+/// and the seed: the same seed always yields the same file.
+///
+/// # Example
+/// ```rust,ignore
+/// let file = std::io::BufWriter::new(std::fs::File::create("data.csv")?);
+/// DatasetGenerator::new(1_000_000)
+///     .with_clients(10_000)
+///     .with_seed(42)
+///     .write_csv(file)?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetGenerator {
+    rows: usize,
+    clients: ClientId,
+    deposit_ratio: f64,
+    withdrawal_ratio: f64,
+    dispute_ratio: f64,
+    seed: u64,
+}
+
+impl DatasetGenerator {
+    /// Generate `rows` rows for 100 clients: 60% deposits, 30% withdrawals,
+    /// 5% disputes and 5% resolves, seeded with 0
+    pub fn new(rows: usize) -> Self {
+        Self {
+            rows,
+            clients: 100,
+            deposit_ratio: 0.6,
+            withdrawal_ratio: 0.3,
+            dispute_ratio: 0.05,
+            seed: 0,
+        }
+    }
+
+    /// Number of rows to draw
+    pub fn with_rows(mut self, rows: usize) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    /// Spread rows over this many clients (minimum 1)
+    pub fn with_clients(mut self, clients: ClientId) -> Self {
+        self.clients = clients.max(1);
+        self
+    }
+
+    /// Share of rows that are deposits
+    pub fn with_deposit_ratio(mut self, ratio: f64) -> Self {
+        self.deposit_ratio = ratio;
+        self
+    }
+
+    /// Share of rows that are withdrawals
+    pub fn with_withdrawal_ratio(mut self, ratio: f64) -> Self {
+        self.withdrawal_ratio = ratio;
+        self
+    }
+
+    /// Share of rows that dispute an earlier deposit
+    pub fn with_dispute_ratio(mut self, ratio: f64) -> Self {
+        self.dispute_ratio = ratio;
+        self
+    }
+
+    /// Seed for the pseudo-random sequence
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Check that every ratio is between 0 and 1 and they sum to at most 1
+    pub fn validate(&self) -> Result<(), String> {
+        let ratios = [
+            ("deposit", self.deposit_ratio),
+            ("withdraw", self.withdrawal_ratio),
+            ("dispute", self.dispute_ratio),
+        ];
+        for (name, ratio) in ratios {
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(format!("{name} ratio must be between 0 and 1, got {ratio}"));
+            }
+        }
+        let sum: f64 = ratios.iter().map(|(_, ratio)| ratio).sum();
+        if sum > 1.0 + f64::EPSILON {
+            return Err(format!("ratios must sum to at most 1, got {sum}"));
+        }
+        Ok(())
+    }
+
+    /// Write the header and every row as CSV
+    ///
+    /// Rows are written one at a time; wrap files in a `BufWriter`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut rng = SplitMix64(self.seed);
+        // Undisputed and disputed deposits per client, indexed by client - 1
+        let mut deposits = vec![Vec::new(); self.clients as usize];
+        let mut disputed = vec![Vec::new(); self.clients as usize];
+        let mut next_tx: u64 = 1;
+
+        writer.write_all(b"type,client,tx,amount\n")?;
+        for _ in 0..self.rows {
+            let index = (rng.next() % u64::from(self.clients)) as usize;
+            let client_id = index + 1;
+            let draw = rng.next_f64();
+
+            if draw < self.deposit_ratio + self.withdrawal_ratio {
+                let (kind, max_units) = if draw < self.deposit_ratio {
+                    deposits[index].push(next_tx);
+                    ("deposit", 1_000)
+                } else {
+                    ("withdrawal", 100)
+                };
+                let units = rng.next() % max_units;
+                let fraction = rng.next() % 10_000;
+                writeln!(writer, "{kind},{client_id},{next_tx},{units}.{fraction:04}")?;
+                next_tx += 1;
+            } else if draw < self.deposit_ratio + self.withdrawal_ratio + self.dispute_ratio {
+                if let Some(tx_id) = take_random(&mut deposits[index], &mut rng) {
+                    writeln!(writer, "dispute,{client_id},{tx_id},")?;
+                    disputed[index].push(tx_id);
+                }
+            } else if let Some(tx_id) = take_random(&mut disputed[index], &mut rng) {
+                writeln!(writer, "resolve,{client_id},{tx_id},")?;
+                deposits[index].push(tx_id);
+            }
+        }
+        writer.flush()
+    }
+}
+
+/// Remove and return a random element (order is not preserved)
+pub(crate) fn take_random<T>(ids: &mut Vec<T>, rng: &mut SplitMix64) -> Option<T> {
+    if ids.is_empty() {
+        return None;
+    }
+    let index = (rng.next() % ids.len() as u64) as usize;
+    Some(ids.swap_remove(index))
+}
+
+/// SplitMix64: small, fast and identical on every platform
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(generator: &DatasetGenerator) -> String {
+        let mut output = Vec::new();
+        generator.write_csv(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn same_seed_gives_same_dataset() {
+        let generator = DatasetGenerator::new(500).with_clients(7).with_seed(42);
+
+        assert_eq!(generate(&generator), generate(&generator.clone()));
+        assert_ne!(generate(&generator), generate(&generator.with_seed(43)));
+    }
+
+    #[test]
+    fn disputes_reference_the_same_clients_deposits() {
+        let csv = generate(
+            &DatasetGenerator::new(2_000)
+                .with_clients(5)
+                .with_dispute_ratio(0.2)
+                .with_withdrawal_ratio(0.1),
+        );
+
+        let mut deposits = std::collections::HashMap::new();
+        let mut disputes = 0;
+        for line in csv.lines().skip(1) {
+            let fields: Vec<_> = line.split(',').collect();
+            match fields[0] {
+                "deposit" => {
+                    deposits.insert(fields[2], fields[1]);
+                }
+                "dispute" | "resolve" => {
+                    assert_eq!(deposits.get(fields[2]), Some(&fields[1]), "{line}");
+                    disputes += usize::from(fields[0] == "dispute");
+                }
+                _ => {}
+            }
+        }
+        assert!(disputes > 100, "{disputes}");
+    }
+
+    #[test]
+    fn rejects_ratios_summing_past_one() {
+        assert!(DatasetGenerator::new(1).validate().is_ok());
+        assert!(
+            DatasetGenerator::new(1)
+                .with_deposit_ratio(0.9)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            DatasetGenerator::new(1)
+                .with_dispute_ratio(-0.1)
+                .validate()
+                .is_err()
+        );
+    }
+}
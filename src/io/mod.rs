@@ -1,10 +1,45 @@
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod compression;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod csv_parallel;
 pub mod csv_reader;
 pub mod csv_writer;
+pub mod diff;
 pub mod error;
+pub mod generate;
+#[cfg(feature = "object-store")]
+pub mod object_storage;
 pub mod parse;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod partitioned_snapshot;
+pub mod snapshot_filter;
+pub mod snapshot_sink;
+#[cfg(feature = "tcp")]
+pub mod tcp;
 
 // Re-export commonly used types
-pub use csv_reader::CsvTransactionStream;
-pub use csv_writer::write_snapshot;
+#[cfg(feature = "arrow")]
+pub use arrow::ArrowTransactionStream;
+#[cfg(not(target_arch = "wasm32"))]
+pub use compression::{CompressedReader, Compression};
+#[cfg(not(target_arch = "wasm32"))]
+pub use csv_parallel::ParallelCsvOptions;
+pub use csv_reader::{ColumnMapping, CsvReaderOptions, CsvTransactionStream, ReadPosition};
+pub use csv_writer::{write_snapshot, write_snapshot_with_filter, write_snapshot_with_format};
+pub use diff::{AccountDelta, DeltaStatus, SnapshotBalance, SnapshotDiff, diff_snapshots};
 pub use error::IoError;
+pub use generate::DatasetGenerator;
+#[cfg(feature = "object-store")]
+pub use object_storage::{object_store_for, upload_snapshot, upload_snapshot_to};
 pub use parse::RawTransactionRecord;
+#[cfg(not(target_arch = "wasm32"))]
+pub use partitioned_snapshot::{SnapshotPartitioning, write_snapshot_partitioned};
+pub use snapshot_filter::SnapshotFilter;
+pub use snapshot_sink::{
+    CsvSnapshotSink, JsonSnapshotSink, NdjsonSnapshotSink, SnapshotSink, TeeSnapshotSink,
+    write_snapshot_to, write_snapshot_to_filtered,
+};
+#[cfg(feature = "tcp")]
+pub use tcp::{ReconnectPolicy, TcpTransactionStream};
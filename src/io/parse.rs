@@ -1,61 +1,234 @@
 use serde::Deserialize;
 
 use super::error::IoError;
-use crate::domain::{AmountType, Transaction};
+use crate::domain::{
+    AmountType, ClientId, CurrencyCode, RoundingPolicy, TimestampedTransaction, Transaction,
+    TransactionId,
+};
 
 /// Raw CSV record as read from input
-#[derive(Debug, Deserialize)]
+///
+/// Fields are added as the input format grows, so build records with
+/// `..Default::default()` for the columns you do not set.
+#[derive(Debug, Default, Deserialize)]
+#[non_exhaustive]
 pub struct RawTransactionRecord {
     #[serde(rename = "type")]
     pub tx_type: String,
-    pub client: u16,
-    pub tx: u32,
+    pub client: ClientId,
+    pub tx: TransactionId,
     pub amount: Option<String>,
+    /// Destination client (transfers only)
+    #[serde(default)]
+    pub to: Option<ClientId>,
+    /// Optional event timestamp used for time-ordered merging
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+    /// Optional per-client sequence number used for per-client ordering
+    #[serde(default)]
+    pub seq: Option<u64>,
+    /// Optional currency code (deposits, withdrawals and transfers; empty = base currency)
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Optional key identifying a submission across retries (empty = none)
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    /// Client tag as `key=value` (tag operations only; `key=` removes the tag)
+    #[serde(default)]
+    pub tag: Option<String>,
 }
 
 impl RawTransactionRecord {
+    /// Parse this raw record, keeping its optional event timestamp
+    pub fn parse_timestamped<A: AmountType>(self) -> Result<TimestampedTransaction<A>, IoError> {
+        self.parse_timestamped_rounded(RoundingPolicy::Reject)
+    }
+
+    /// Parse this raw record with a rounding policy, keeping its optional event
+    /// timestamp and sequence number
+    pub fn parse_timestamped_rounded<A: AmountType>(
+        self,
+        rounding: RoundingPolicy,
+    ) -> Result<TimestampedTransaction<A>, IoError> {
+        self.borrowed().parse_timestamped_rounded(rounding)
+    }
+
     /// Parse this raw record into a strongly-typed Transaction
+    ///
+    /// Amounts with more decimal places than `A` stores are rejected.
     pub fn parse<A: AmountType>(self) -> Result<Transaction<A>, IoError> {
-        let tx_type_lower = self.tx_type.trim().to_lowercase();
-
-        match tx_type_lower.as_str() {
-            "deposit" => {
-                let amount_str = self.amount.ok_or_else(|| {
-                    IoError::MissingField("amount required for deposit".to_string())
-                })?;
-                let amount = A::from_decimal_str(&amount_str)
-                    .map_err(|_| IoError::InvalidAmount(amount_str))?;
-                Ok(Transaction::Deposit {
-                    client_id: self.client,
-                    tx_id: self.tx,
-                    amount,
-                })
-            }
-            "withdrawal" => {
-                let amount_str = self.amount.ok_or_else(|| {
-                    IoError::MissingField("amount required for withdrawal".to_string())
-                })?;
-                let amount = A::from_decimal_str(&amount_str)
-                    .map_err(|_| IoError::InvalidAmount(amount_str))?;
-                Ok(Transaction::Withdrawal {
-                    client_id: self.client,
-                    tx_id: self.tx,
-                    amount,
-                })
-            }
-            "dispute" => Ok(Transaction::Dispute {
-                client_id: self.client,
-                tx_id: self.tx,
-            }),
-            "resolve" => Ok(Transaction::Resolve {
-                client_id: self.client,
-                tx_id: self.tx,
-            }),
-            "chargeback" => Ok(Transaction::Chargeback {
-                client_id: self.client,
-                tx_id: self.tx,
-            }),
-            _ => Err(IoError::InvalidTransactionType(self.tx_type)),
+        self.parse_rounded(RoundingPolicy::Reject)
+    }
+
+    /// Parse this raw record, normalizing amounts to `A`'s precision per `rounding`
+    pub fn parse_rounded<A: AmountType>(
+        self,
+        rounding: RoundingPolicy,
+    ) -> Result<Transaction<A>, IoError> {
+        self.borrowed().parse_rounded(rounding)
+    }
+
+    /// View this record's fields without copying them
+    fn borrowed(&self) -> BorrowedRecord<'_> {
+        BorrowedRecord {
+            tx_type: &self.tx_type,
+            client: self.client,
+            tx: self.tx,
+            amount: self.amount.as_deref().map(str::as_bytes),
+            to: self.to,
+            timestamp: self.timestamp,
+            seq: self.seq,
+            currency: self.currency.as_deref(),
+            idempotency_key: self.idempotency_key.as_deref(),
+            tag: self.tag.as_deref(),
+        }
+    }
+}
+
+/// CSV record whose text fields borrow from the reader's `ByteRecord`
+///
+/// Parsing one allocates nothing unless the record carries an idempotency key,
+/// needs its amount rounded, or is rejected.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BorrowedRecord<'a> {
+    #[serde(rename = "type")]
+    tx_type: &'a str,
+    client: ClientId,
+    tx: TransactionId,
+    #[serde(borrow)]
+    amount: Option<&'a [u8]>,
+    #[serde(default)]
+    to: Option<ClientId>,
+    #[serde(default)]
+    timestamp: Option<u64>,
+    #[serde(default)]
+    seq: Option<u64>,
+    #[serde(default, borrow)]
+    currency: Option<&'a str>,
+    #[serde(default, borrow)]
+    idempotency_key: Option<&'a str>,
+    #[serde(default, borrow)]
+    tag: Option<&'a str>,
+}
+
+impl BorrowedRecord<'_> {
+    /// The amount field as written in the input
+    pub(crate) fn amount(&self) -> Option<&[u8]> {
+        self.amount
+    }
+
+    /// Parse with a rounding policy, keeping the optional event timestamp,
+    /// sequence number and idempotency key
+    pub(crate) fn parse_timestamped_rounded<A: AmountType>(
+        &self,
+        rounding: RoundingPolicy,
+    ) -> Result<TimestampedTransaction<A>, IoError> {
+        let idempotency_key = self
+            .idempotency_key
+            .filter(|key| !key.trim().is_empty())
+            .map(str::to_string);
+        Ok(
+            TimestampedTransaction::new(self.parse_rounded(rounding)?, self.timestamp)
+                .with_sequence(self.seq)
+                .with_idempotency_key(idempotency_key),
+        )
+    }
+
+    /// Parse into a strongly-typed Transaction, normalizing amounts per `rounding`
+    pub(crate) fn parse_rounded<A: AmountType>(
+        &self,
+        rounding: RoundingPolicy,
+    ) -> Result<Transaction<A>, IoError> {
+        let currency = match self.currency.map(str::trim) {
+            None | Some("") => None,
+            Some(code) => Some(
+                code.parse::<CurrencyCode>()
+                    .map_err(|_| IoError::InvalidCurrency(code.to_string()))?,
+            ),
+        };
+        let amount = |tx_type: &str| -> Result<A, IoError> {
+            let bytes = self
+                .amount
+                .ok_or_else(|| IoError::MissingField(format!("amount required for {tx_type}")))?;
+            A::from_decimal_bytes_rounded(bytes, rounding)
+                .map_err(|_| IoError::InvalidAmount(String::from_utf8_lossy(bytes).into_owned()))
+        };
+        let (client_id, tx_id) = (self.client, self.tx);
+
+        // Compared case-insensitively in place rather than lowercased into a new string
+        let tx_type = self.tx_type.trim();
+        let is = |name: &str| tx_type.eq_ignore_ascii_case(name);
+        if is("deposit") {
+            Ok(Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: amount("deposit")?,
+                currency,
+            })
+        } else if is("withdrawal") {
+            Ok(Transaction::Withdrawal {
+                client_id,
+                tx_id,
+                amount: amount("withdrawal")?,
+                currency,
+            })
+        } else if is("transfer") {
+            let amount = amount("transfer")?;
+            let to_client = self.to.ok_or_else(|| {
+                IoError::MissingField("destination client required for transfer".to_string())
+            })?;
+            Ok(Transaction::Transfer {
+                from_client: client_id,
+                to_client,
+                tx_id,
+                amount,
+                currency,
+            })
+        } else if is("dispute") {
+            Ok(Transaction::Dispute { client_id, tx_id })
+        } else if is("resolve") {
+            Ok(Transaction::Resolve { client_id, tx_id })
+        } else if is("chargeback") {
+            Ok(Transaction::Chargeback { client_id, tx_id })
+        } else if is("chargeback_reversal") {
+            Ok(Transaction::ChargebackReversal { client_id, tx_id })
+        } else if is("hold") {
+            Ok(Transaction::Hold {
+                client_id,
+                tx_id,
+                amount: amount("hold")?,
+                currency,
+            })
+        } else if is("capture") {
+            Ok(Transaction::Capture { client_id, tx_id })
+        } else if is("release") {
+            Ok(Transaction::Release { client_id, tx_id })
+        } else if is("unlock") {
+            Ok(Transaction::Unlock { client_id })
+        } else if is("credit_limit") {
+            Ok(Transaction::SetCreditLimit {
+                client_id,
+                limit: amount("credit_limit")?,
+            })
+        } else if is("adjustment") {
+            Ok(Transaction::Adjustment {
+                client_id,
+                tx_id,
+                amount: amount("adjustment")?,
+            })
+        } else if is("tag") {
+            let tag = self
+                .tag
+                .ok_or_else(|| IoError::MissingField("tag required for tag".to_string()))?;
+            let (key, value) = tag.split_once('=').unwrap_or((tag, ""));
+            let value = value.trim();
+            Ok(Transaction::SetTag {
+                client_id,
+                key: key.trim().to_string(),
+                value: (!value.is_empty()).then(|| value.to_string()),
+            })
+        } else {
+            Err(IoError::InvalidTransactionType(self.tx_type.to_string()))
         }
     }
 }
@@ -72,6 +245,7 @@ mod tests {
             client: 1,
             tx: 100,
             amount: Some("1.5".to_string()),
+            ..Default::default()
         };
 
         let tx = raw.parse::<FixedPoint>().unwrap();
@@ -80,6 +254,7 @@ mod tests {
                 client_id,
                 tx_id,
                 amount,
+                ..
             } => {
                 assert_eq!(client_id, 1);
                 assert_eq!(tx_id, 100);
@@ -96,6 +271,7 @@ mod tests {
             client: 2,
             tx: 200,
             amount: Some("0.5000".to_string()),
+            ..Default::default()
         };
 
         let tx = raw.parse::<FixedPoint>().unwrap();
@@ -104,6 +280,7 @@ mod tests {
                 client_id,
                 tx_id,
                 amount,
+                ..
             } => {
                 assert_eq!(client_id, 2);
                 assert_eq!(tx_id, 200);
@@ -119,7 +296,7 @@ mod tests {
             tx_type: "dispute".to_string(),
             client: 1,
             tx: 100,
-            amount: None,
+            ..Default::default()
         };
 
         let tx = raw.parse::<FixedPoint>().unwrap();
@@ -138,7 +315,7 @@ mod tests {
             tx_type: "resolve".to_string(),
             client: 1,
             tx: 100,
-            amount: None,
+            ..Default::default()
         };
 
         let tx = raw.parse::<FixedPoint>().unwrap();
@@ -157,7 +334,7 @@ mod tests {
             tx_type: "chargeback".to_string(),
             client: 1,
             tx: 100,
-            amount: None,
+            ..Default::default()
         };
 
         let tx = raw.parse::<FixedPoint>().unwrap();
@@ -170,6 +347,224 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_chargeback_reversal() {
+        let raw = RawTransactionRecord {
+            tx_type: "chargeback_reversal".to_string(),
+            client: 1,
+            tx: 100,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            raw.parse::<FixedPoint>().unwrap(),
+            Transaction::ChargebackReversal {
+                client_id: 1,
+                tx_id: 100
+            }
+        );
+    }
+
+    #[test]
+    fn parse_rounded_normalizes_amount() {
+        let raw = RawTransactionRecord {
+            tx_type: "deposit".to_string(),
+            client: 1,
+            tx: 1,
+            amount: Some("2.123456".to_string()),
+            ..Default::default()
+        };
+
+        let tx = raw
+            .parse_rounded::<FixedPoint>(RoundingPolicy::HalfEven)
+            .unwrap();
+        assert!(matches!(
+            tx,
+            Transaction::Deposit { amount, .. } if amount == FixedPoint::from_raw(21_235)
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_excess_precision_by_default() {
+        let raw = RawTransactionRecord {
+            tx_type: "deposit".to_string(),
+            client: 1,
+            tx: 1,
+            amount: Some("2.123456".to_string()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            raw.parse::<FixedPoint>(),
+            Err(IoError::InvalidAmount(_))
+        ));
+    }
+
+    #[test]
+    fn parse_currency() {
+        let raw = RawTransactionRecord {
+            tx_type: "deposit".to_string(),
+            client: 1,
+            tx: 1,
+            amount: Some("1.0".to_string()),
+            currency: Some("eur".to_string()),
+            ..Default::default()
+        };
+
+        let tx = raw.parse::<FixedPoint>().unwrap();
+        assert_eq!(tx.currency(), Some("EUR".parse().unwrap()));
+    }
+
+    #[test]
+    fn parse_invalid_currency() {
+        let raw = RawTransactionRecord {
+            tx_type: "deposit".to_string(),
+            client: 1,
+            tx: 1,
+            amount: Some("1.0".to_string()),
+            currency: Some("EURO".to_string()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            raw.parse::<FixedPoint>(),
+            Err(IoError::InvalidCurrency(code)) if code == "EURO"
+        ));
+    }
+
+    #[test]
+    fn parse_unlock() {
+        let raw = RawTransactionRecord {
+            tx_type: "unlock".to_string(),
+            client: 4,
+            tx: 0,
+            ..Default::default()
+        };
+
+        let tx = raw.parse::<FixedPoint>().unwrap();
+        assert!(matches!(tx, Transaction::Unlock { client_id: 4 }));
+    }
+
+    #[test]
+    fn parse_credit_limit() {
+        let raw = |amount: Option<&str>| RawTransactionRecord {
+            tx_type: "credit_limit".to_string(),
+            client: 4,
+            tx: 0,
+            amount: amount.map(str::to_string),
+            ..Default::default()
+        };
+
+        let tx = raw(Some("250.0")).parse::<FixedPoint>().unwrap();
+        assert_eq!(
+            tx,
+            Transaction::SetCreditLimit {
+                client_id: 4,
+                limit: FixedPoint::from_raw(2_500_000),
+            }
+        );
+        assert!(tx.is_admin());
+        assert!(matches!(
+            raw(None).parse::<FixedPoint>(),
+            Err(IoError::MissingField(_))
+        ));
+    }
+
+    #[test]
+    fn parse_tag_sets_or_removes() {
+        let raw = |tag: Option<&str>| RawTransactionRecord {
+            tx_type: "tag".to_string(),
+            client: 4,
+            tx: 0,
+            tag: tag.map(str::to_string),
+            ..Default::default()
+        };
+        let set_tag = |key: &str, value: Option<&str>| Transaction::<FixedPoint>::SetTag {
+            client_id: 4,
+            key: key.to_string(),
+            value: value.map(str::to_string),
+        };
+
+        let tx = raw(Some("tier = premium")).parse::<FixedPoint>().unwrap();
+        assert_eq!(tx, set_tag("tier", Some("premium")));
+        assert!(tx.is_admin());
+        assert_eq!(
+            raw(Some("tier=")).parse::<FixedPoint>().unwrap(),
+            set_tag("tier", None)
+        );
+        assert!(matches!(
+            raw(None).parse::<FixedPoint>(),
+            Err(IoError::MissingField(_))
+        ));
+    }
+
+    #[test]
+    fn parse_signed_adjustment() {
+        let raw = RawTransactionRecord {
+            tx_type: "adjustment".to_string(),
+            client: 4,
+            tx: 9,
+            amount: Some("-1.25".to_string()),
+            ..Default::default()
+        };
+
+        let tx = raw.parse::<FixedPoint>().unwrap();
+        assert_eq!(
+            tx,
+            Transaction::Adjustment {
+                client_id: 4,
+                tx_id: 9,
+                amount: FixedPoint::from_raw(-12_500),
+            }
+        );
+        assert!(tx.is_admin());
+    }
+
+    #[test]
+    fn parse_hold_capture_release() {
+        let raw = |tx_type: &str, amount: Option<&str>| RawTransactionRecord {
+            tx_type: tx_type.to_string(),
+            client: 2,
+            tx: 9,
+            amount: amount.map(str::to_string),
+            ..Default::default()
+        };
+
+        let hold = raw("hold", Some("1.5")).parse::<FixedPoint>().unwrap();
+        assert_eq!(hold.amount(), Some(FixedPoint::from_raw(15_000)));
+        assert!(matches!(
+            hold,
+            Transaction::Hold {
+                client_id: 2,
+                tx_id: 9,
+                ..
+            }
+        ));
+
+        let capture = raw("capture", None).parse::<FixedPoint>().unwrap();
+        assert!(matches!(
+            capture,
+            Transaction::Capture {
+                client_id: 2,
+                tx_id: 9
+            }
+        ));
+
+        let release = raw("release", None).parse::<FixedPoint>().unwrap();
+        assert!(matches!(
+            release,
+            Transaction::Release {
+                client_id: 2,
+                tx_id: 9
+            }
+        ));
+
+        assert!(matches!(
+            raw("hold", None).parse::<FixedPoint>(),
+            Err(IoError::MissingField(_))
+        ));
+    }
+
     #[test]
     fn parse_case_insensitive() {
         let raw = RawTransactionRecord {
@@ -177,6 +572,7 @@ mod tests {
             client: 1,
             tx: 100,
             amount: Some("1.0".to_string()),
+            ..Default::default()
         };
 
         let tx = raw.parse::<FixedPoint>().unwrap();
@@ -190,6 +586,7 @@ mod tests {
             client: 1,
             tx: 100,
             amount: Some("1.0".to_string()),
+            ..Default::default()
         };
 
         let tx = raw.parse::<FixedPoint>().unwrap();
@@ -202,7 +599,7 @@ mod tests {
             tx_type: "invalid".to_string(),
             client: 1,
             tx: 100,
-            amount: None,
+            ..Default::default()
         };
 
         let result = raw.parse::<FixedPoint>();
@@ -215,7 +612,7 @@ mod tests {
             tx_type: "deposit".to_string(),
             client: 1,
             tx: 100,
-            amount: None,
+            ..Default::default()
         };
 
         let result = raw.parse::<FixedPoint>();
@@ -228,7 +625,7 @@ mod tests {
             tx_type: "withdrawal".to_string(),
             client: 1,
             tx: 100,
-            amount: None,
+            ..Default::default()
         };
 
         let result = raw.parse::<FixedPoint>();
@@ -242,6 +639,7 @@ mod tests {
             client: 1,
             tx: 100,
             amount: Some("not_a_number".to_string()),
+            ..Default::default()
         };
 
         let result = raw.parse::<FixedPoint>();
@@ -255,9 +653,86 @@ mod tests {
             client: 1,
             tx: 100,
             amount: Some("1.123456".to_string()),
+            ..Default::default()
         };
 
         let result = raw.parse::<FixedPoint>();
         assert!(matches!(result, Err(IoError::InvalidAmount(_))));
     }
+
+    #[test]
+    fn parse_transfer() {
+        let raw = RawTransactionRecord {
+            tx_type: "transfer".to_string(),
+            client: 1,
+            tx: 100,
+            amount: Some("2.5".to_string()),
+            to: Some(2),
+            ..Default::default()
+        };
+
+        let tx = raw.parse::<FixedPoint>().unwrap();
+        match tx {
+            Transaction::Transfer {
+                from_client,
+                to_client,
+                tx_id,
+                amount,
+                ..
+            } => {
+                assert_eq!(from_client, 1);
+                assert_eq!(to_client, 2);
+                assert_eq!(tx_id, 100);
+                assert_eq!(amount, FixedPoint::from_raw(25_000));
+            }
+            _ => panic!("Expected Transfer variant"),
+        }
+    }
+
+    #[test]
+    fn parse_transfer_missing_destination() {
+        let raw = RawTransactionRecord {
+            tx_type: "transfer".to_string(),
+            client: 1,
+            tx: 100,
+            amount: Some("2.5".to_string()),
+            ..Default::default()
+        };
+
+        let result = raw.parse::<FixedPoint>();
+        assert!(matches!(result, Err(IoError::MissingField(_))));
+    }
+
+    #[test]
+    fn parse_timestamped_keeps_timestamp() {
+        let raw = RawTransactionRecord {
+            tx_type: "deposit".to_string(),
+            client: 1,
+            tx: 100,
+            amount: Some("1.0".to_string()),
+            timestamp: Some(1_700_000_000),
+            ..Default::default()
+        };
+
+        let tx = raw.parse_timestamped::<FixedPoint>().unwrap();
+        assert_eq!(tx.timestamp, Some(1_700_000_000));
+        assert!(matches!(tx.transaction, Transaction::Deposit { .. }));
+    }
+
+    #[test]
+    fn parse_timestamped_keeps_non_empty_idempotency_key() {
+        let raw = |key: &str| RawTransactionRecord {
+            tx_type: "deposit".to_string(),
+            client: 1,
+            tx: 100,
+            amount: Some("1.0".to_string()),
+            idempotency_key: Some(key.to_string()),
+            ..Default::default()
+        };
+
+        let tx = raw("upload-42").parse_timestamped::<FixedPoint>().unwrap();
+        assert_eq!(tx.idempotency_key.as_deref(), Some("upload-42"));
+        let tx = raw(" ").parse_timestamped::<FixedPoint>().unwrap();
+        assert_eq!(tx.idempotency_key, None);
+    }
 }
@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use futures::future::try_join_all;
+use tokio::fs::File;
+
+use super::error::IoError;
+use super::snapshot_sink::{CsvSnapshotSink, SnapshotSink};
+use crate::domain::{AmountType, ClientAccount};
+use crate::storage::ClientAccountManager;
+
+/// How `write_snapshot_partitioned` divides accounts between files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotPartitioning {
+    /// `n` contiguous client id ranges holding equal numbers of accounts, so
+    /// each file covers ids above those of the file before it
+    ClientRanges(usize),
+    /// `n` partitions by `client_id % n`, the shard that owns each client
+    /// under `ExecutionModel::ActorSharded` with `n` shards
+    Shards(usize),
+}
+
+impl SnapshotPartitioning {
+    /// Number of files written (minimum 1)
+    pub fn partitions(&self) -> usize {
+        match self {
+            Self::ClientRanges(n) | Self::Shards(n) => (*n).max(1),
+        }
+    }
+
+    /// Split accounts sorted by client id, keeping each partition sorted
+    fn split<A: AmountType>(&self, accounts: Vec<ClientAccount<A>>) -> Vec<Vec<ClientAccount<A>>> {
+        let n = self.partitions();
+        let mut parts: Vec<Vec<ClientAccount<A>>> = (0..n).map(|_| Vec::new()).collect();
+        match self {
+            Self::ClientRanges(_) => {
+                let per_part = accounts.len().div_ceil(n).max(1);
+                for (index, account) in accounts.into_iter().enumerate() {
+                    parts[index / per_part].push(account);
+                }
+            }
+            Self::Shards(_) => {
+                for account in accounts {
+                    parts[account.client_id() as usize % n].push(account);
+                }
+            }
+        }
+        parts
+    }
+}
+
+/// Write the accounts to one CSV snapshot per partition in `dir`
+///
+/// Files are named `snapshot-0000.csv`, `snapshot-0001.csv`, ... in
+/// partition order, each with its own header and rows sorted by client id,
+/// so downstream loaders can read them in parallel. Every partition gets a
+/// file, even an empty one. `dir` is created if it does not exist. Returns
+/// the paths written, in partition order.
+///
+/// # Example
+/// ```rust,ignore
+/// let files =
+///     write_snapshot_partitioned(&account_manager, SnapshotPartitioning::Shards(8), "out").await?;
+/// ```
+pub async fn write_snapshot_partitioned<A, M>(
+    account_manager: &M,
+    partitions: SnapshotPartitioning,
+    dir: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>, IoError>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+{
+    let dir = dir.as_ref();
+    let mut accounts = Vec::new();
+    account_manager.for_each_account(&mut |account| accounts.push(account.clone()));
+    accounts.sort_unstable_by_key(|account| account.client_id());
+
+    tokio::fs::create_dir_all(dir).await?;
+    let writes = partitions
+        .split(accounts)
+        .into_iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let path = dir.join(format!("snapshot-{index:04}.csv"));
+            async move {
+                let mut sink = CsvSnapshotSink::new(File::create(&path).await?);
+                SnapshotSink::<A>::begin(&mut sink).await?;
+                for account in &part {
+                    sink.write_account(account).await?;
+                }
+                SnapshotSink::<A>::finish(&mut sink).await?;
+                Ok::<_, IoError>(path)
+            }
+        });
+    try_join_all(writes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ClientId, FixedPoint, operations};
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager};
+
+    fn manager(
+        clients: impl IntoIterator<Item = ClientId>,
+    ) -> ConcurrentAccountManager<FixedPoint> {
+        let manager = ConcurrentAccountManager::new();
+        for client_id in clients {
+            manager
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+                .unwrap();
+        }
+        manager
+    }
+
+    fn clients(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').next().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn client_ranges_are_contiguous_and_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager([9, 2, 7, 4, 1]);
+
+        let files =
+            write_snapshot_partitioned(&manager, SnapshotPartitioning::ClientRanges(2), dir.path())
+                .await
+                .unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("snapshot-0000.csv"));
+        assert_eq!(clients(&files[0]), ["1", "2", "4"]);
+        assert_eq!(clients(&files[1]), ["7", "9"]);
+    }
+
+    #[tokio::test]
+    async fn shards_group_clients_by_modulo() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager([5, 3, 2, 6, 4]);
+
+        let files =
+            write_snapshot_partitioned(&manager, SnapshotPartitioning::Shards(3), dir.path())
+                .await
+                .unwrap();
+
+        assert_eq!(clients(&files[0]), ["3", "6"]);
+        assert_eq!(clients(&files[1]), ["4"]);
+        assert_eq!(clients(&files[2]), ["2", "5"]);
+    }
+
+    #[tokio::test]
+    async fn empty_partitions_still_get_a_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager([1]);
+
+        let files =
+            write_snapshot_partitioned(&manager, SnapshotPartitioning::ClientRanges(3), dir.path())
+                .await
+                .unwrap();
+
+        assert_eq!(files.len(), 3);
+        assert_eq!(
+            std::fs::read_to_string(&files[2]).unwrap(),
+            "client,available,held,total,locked\n"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use crate::domain::{AmountType, ClientAccount, ClientId};
+
+/// Which accounts a snapshot includes
+///
+/// The default includes every account. Each option narrows the selection
+/// further, so an account is written only if it passes all of them.
+///
+/// # Example
+/// ```rust,ignore
+/// // Just the locked accounts among clients 1-1000
+/// let filter = SnapshotFilter::default()
+///     .locked_only()
+///     .with_client_range(1..=1000);
+/// write_snapshot_with_filter(&account_manager, writer, &filter).await?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotFilter {
+    locked_only: bool,
+    non_zero_total: bool,
+    client_range: Option<RangeInclusive<ClientId>>,
+    clients: Option<HashSet<ClientId>>,
+}
+
+impl SnapshotFilter {
+    /// Only locked accounts
+    pub fn locked_only(mut self) -> Self {
+        self.locked_only = true;
+        self
+    }
+
+    /// Only accounts whose base-currency total is not zero
+    pub fn non_zero_total(mut self) -> Self {
+        self.non_zero_total = true;
+        self
+    }
+
+    /// Only clients within `range`
+    pub fn with_client_range(mut self, range: RangeInclusive<ClientId>) -> Self {
+        self.client_range = Some(range);
+        self
+    }
+
+    /// Only the given clients
+    pub fn with_clients(mut self, clients: impl IntoIterator<Item = ClientId>) -> Self {
+        self.clients = Some(clients.into_iter().collect());
+        self
+    }
+
+    /// Whether `account` belongs in the snapshot
+    pub fn matches<A: AmountType>(&self, account: &ClientAccount<A>) -> bool {
+        let client_id = account.client_id();
+        (!self.locked_only || account.is_locked())
+            && (!self.non_zero_total || account.total() != A::zero())
+            && self
+                .client_range
+                .as_ref()
+                .is_none_or(|range| range.contains(&client_id))
+            && self
+                .clients
+                .as_ref()
+                .is_none_or(|clients| clients.contains(&client_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, operations};
+
+    fn account(client_id: ClientId, deposit: i64, locked: bool) -> ClientAccount<FixedPoint> {
+        let mut account = ClientAccount::new(client_id);
+        if deposit > 0 {
+            operations::apply_deposit(&mut account, FixedPoint::from_raw(deposit)).unwrap();
+        }
+        if locked {
+            account.lock();
+        }
+        account
+    }
+
+    #[test]
+    fn default_matches_every_account() {
+        let filter = SnapshotFilter::default();
+
+        assert!(filter.matches(&account(1, 0, false)));
+        assert!(filter.matches(&account(2, 10_000, true)));
+    }
+
+    #[test]
+    fn options_combine() {
+        let filter = SnapshotFilter::default()
+            .locked_only()
+            .non_zero_total()
+            .with_client_range(10..=20);
+
+        assert!(filter.matches(&account(15, 10_000, true)));
+        assert!(!filter.matches(&account(15, 10_000, false)));
+        assert!(!filter.matches(&account(15, 0, true)));
+        assert!(!filter.matches(&account(21, 10_000, true)));
+    }
+
+    #[test]
+    fn explicit_clients() {
+        let filter = SnapshotFilter::default().with_clients([3, 7]);
+
+        assert!(filter.matches(&account(3, 0, false)));
+        assert!(filter.matches(&account(7, 0, false)));
+        assert!(!filter.matches(&account(5, 0, false)));
+    }
+}
@@ -0,0 +1,473 @@
+use async_trait::async_trait;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::error::IoError;
+use super::snapshot_filter::SnapshotFilter;
+use crate::domain::{AmountType, ClientAccount};
+use crate::storage::{ClientAccountManager, SnapshotFormat, SnapshotWriter};
+
+/// Destination for an account snapshot
+///
+/// `write_snapshot_to` calls `begin` once, `write_account` for every account
+/// and `finish` once at the end. Implement it to add output formats or
+/// destinations (e.g. an upload stream); combine sinks with
+/// `TeeSnapshotSink` to write several outputs from one pass over storage.
+#[async_trait]
+pub trait SnapshotSink<A: AmountType>: Send {
+    /// Called before the first account (e.g. to write a header)
+    async fn begin(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+
+    /// Write one account
+    async fn write_account(&mut self, account: &ClientAccount<A>) -> Result<(), IoError>;
+
+    /// Called after the last account; flush any buffered output here
+    async fn finish(&mut self) -> Result<(), IoError>;
+
+    /// Called between `begin` and `finish` after each part of a snapshot
+    /// written in parts (e.g. one per shard); flush here if readers may load
+    /// the output before it is finished
+    async fn end_partition(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+/// Write every account to `sink`
+///
+/// Accounts are copied out of storage first, so no storage lock is held
+/// while the sink writes; every output of a `TeeSnapshotSink` therefore sees
+/// the same accounts.
+///
+/// # Example
+/// ```rust,ignore
+/// let mut sink = TeeSnapshotSink::new()
+///     .with_sink(CsvSnapshotSink::new(tokio::io::stdout()))
+///     .with_sink(JsonSnapshotSink::new(upload_stream));
+/// write_snapshot_to(&account_manager, &mut sink).await?;
+/// ```
+pub async fn write_snapshot_to<A, M, S>(account_manager: &M, sink: &mut S) -> Result<(), IoError>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    S: SnapshotSink<A> + ?Sized,
+{
+    write_snapshot_to_filtered(account_manager, sink, &SnapshotFilter::default()).await
+}
+
+/// Write the accounts selected by `filter` to `sink`
+///
+/// Accounts are filtered while they are copied out of storage, so only the
+/// selected accounts are held in memory.
+pub async fn write_snapshot_to_filtered<A, M, S>(
+    account_manager: &M,
+    sink: &mut S,
+    filter: &SnapshotFilter,
+) -> Result<(), IoError>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    S: SnapshotSink<A> + ?Sized,
+{
+    let mut accounts = Vec::new();
+    account_manager.for_each_account(&mut |account| {
+        if filter.matches(account) {
+            accounts.push(account.clone());
+        }
+    });
+
+    sink.begin().await?;
+    for account in &accounts {
+        sink.write_account(account).await?;
+    }
+    sink.finish().await
+}
+
+/// Snapshot sink writing CSV rows in a `SnapshotFormat`
+///
+/// Rows are buffered and written in 64KB chunks; everything is written by
+/// `finish`.
+pub struct CsvSnapshotSink<W> {
+    output: SnapshotWriter<W>,
+    format: SnapshotFormat,
+}
+
+impl<W: AsyncWrite + Unpin + Send> CsvSnapshotSink<W> {
+    /// Write the standard CSV snapshot format
+    pub fn new(writer: W) -> Self {
+        Self {
+            output: SnapshotWriter::new(writer),
+            format: SnapshotFormat::default(),
+        }
+    }
+
+    /// Use custom number formatting and columns
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Get the underlying writer back (after `finish`, which writes the buffered rows)
+    pub fn into_inner(self) -> W {
+        self.output.into_inner()
+    }
+}
+
+#[async_trait]
+impl<A: AmountType, W: AsyncWrite + Unpin + Send> SnapshotSink<A> for CsvSnapshotSink<W> {
+    async fn begin(&mut self) -> Result<(), IoError> {
+        self.output
+            .buffer()
+            .extend_from_slice(self.format.header().as_bytes());
+        Ok(())
+    }
+
+    async fn write_account(&mut self, account: &ClientAccount<A>) -> Result<(), IoError> {
+        self.format.write_row(account, self.output.buffer())?;
+        self.output.write_if_full().await?;
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), IoError> {
+        self.output.finish().await?;
+        Ok(())
+    }
+}
+
+/// Snapshot sink writing a JSON array with one object per account
+///
+/// Amounts are decimal strings, as in the REST API, written with the number
+/// options of a `SnapshotFormat` (4 fixed decimals by default; columns and
+/// delimiter do not apply). Accounts holding other currencies get a
+/// `currencies` object keyed by currency code:
+///
+/// ```json
+/// [
+/// {"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}
+/// ]
+/// ```
+pub struct JsonSnapshotSink<W> {
+    writer: W,
+    accounts: usize,
+    format: SnapshotFormat,
+}
+
+impl<W: AsyncWrite + Unpin + Send> JsonSnapshotSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            accounts: 0,
+            format: SnapshotFormat::default(),
+        }
+    }
+
+    /// Format amounts with `format`'s decimals, trimming, rounding and separator
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Get the underlying writer back
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// JSON object for one account (all strings are codes or decimals, so nothing needs escaping)
+fn account_json<A: AmountType>(
+    account: &ClientAccount<A>,
+    format: &SnapshotFormat,
+) -> Result<String, IoError> {
+    let mut json = format!(
+        r#"{{"client":{},"available":"{}","held":"{}","total":"{}","locked":{}"#,
+        account.client_id(),
+        format.format_amount(account.available())?,
+        format.format_amount(account.held())?,
+        format.format_amount(account.total())?,
+        account.is_locked(),
+    );
+
+    let currencies = account
+        .currency_balances()
+        .map(|(currency, balance)| {
+            Ok(format!(
+                r#""{}":{{"available":"{}","held":"{}"}}"#,
+                currency,
+                format.format_amount(balance.available)?,
+                format.format_amount(balance.held)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, IoError>>()?;
+    if !currencies.is_empty() {
+        json.push_str(&format!(r#","currencies":{{{}}}"#, currencies.join(",")));
+    }
+
+    json.push('}');
+    Ok(json)
+}
+
+#[async_trait]
+impl<A: AmountType, W: AsyncWrite + Unpin + Send> SnapshotSink<A> for JsonSnapshotSink<W> {
+    async fn begin(&mut self) -> Result<(), IoError> {
+        self.writer.write_all(b"[\n").await?;
+        Ok(())
+    }
+
+    async fn write_account(&mut self, account: &ClientAccount<A>) -> Result<(), IoError> {
+        if self.accounts > 0 {
+            self.writer.write_all(b",\n").await?;
+        }
+        self.writer
+            .write_all(account_json(account, &self.format)?.as_bytes())
+            .await?;
+        self.accounts += 1;
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), IoError> {
+        let end: &[u8] = if self.accounts > 0 { b"\n]\n" } else { b"]\n" };
+        self.writer.write_all(end).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// Snapshot sink writing newline-delimited JSON (NDJSON), one object per account
+///
+/// Objects are those of `JsonSnapshotSink`, with no enclosing array, so each
+/// line can be loaded on its own. Lines are written as accounts arrive and
+/// the writer is flushed at the end of every partition, letting downstream
+/// consumers load a partition before the whole snapshot is done:
+///
+/// ```json
+/// {"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}
+/// {"client":2,"available":"1.0000","held":"0.0000","total":"1.0000","locked":false}
+/// ```
+pub struct NdjsonSnapshotSink<W> {
+    writer: W,
+    format: SnapshotFormat,
+}
+
+impl<W: AsyncWrite + Unpin + Send> NdjsonSnapshotSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            format: SnapshotFormat::default(),
+        }
+    }
+
+    /// Format amounts with `format`'s decimals, trimming, rounding and separator
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Get the underlying writer back
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[async_trait]
+impl<A: AmountType, W: AsyncWrite + Unpin + Send> SnapshotSink<A> for NdjsonSnapshotSink<W> {
+    async fn write_account(&mut self, account: &ClientAccount<A>) -> Result<(), IoError> {
+        let mut line = account_json(account, &self.format)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn end_partition(&mut self) -> Result<(), IoError> {
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), IoError> {
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// Snapshot sink that forwards every call to several sinks in turn
+///
+/// Stops at the first sink that fails.
+pub struct TeeSnapshotSink<'a, A: AmountType> {
+    sinks: Vec<Box<dyn SnapshotSink<A> + 'a>>,
+}
+
+impl<A: AmountType> Default for TeeSnapshotSink<'_, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, A: AmountType> TeeSnapshotSink<'a, A> {
+    pub fn new() -> Self {
+        Self { sinks: Vec::new() }
+    }
+
+    /// Add an output
+    pub fn with_sink(mut self, sink: impl SnapshotSink<A> + 'a) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+}
+
+#[async_trait]
+impl<A: AmountType> SnapshotSink<A> for TeeSnapshotSink<'_, A> {
+    async fn begin(&mut self) -> Result<(), IoError> {
+        for sink in &mut self.sinks {
+            sink.begin().await?;
+        }
+        Ok(())
+    }
+
+    async fn write_account(&mut self, account: &ClientAccount<A>) -> Result<(), IoError> {
+        for sink in &mut self.sinks {
+            sink.write_account(account).await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), IoError> {
+        for sink in &mut self.sinks {
+            sink.finish().await?;
+        }
+        Ok(())
+    }
+
+    async fn end_partition(&mut self) -> Result<(), IoError> {
+        for sink in &mut self.sinks {
+            sink.end_partition().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{CurrencyCode, FixedPoint, apply_in_currency, operations};
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager};
+
+    fn manager() -> ConcurrentAccountManager<FixedPoint> {
+        let manager = ConcurrentAccountManager::new();
+        manager
+            .entry(1)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(15_000)))
+            .unwrap();
+        manager
+    }
+
+    #[tokio::test]
+    async fn tee_writes_csv_and_json_from_one_pass() {
+        let mut csv = Vec::new();
+        let mut json = Vec::new();
+
+        let mut sink = TeeSnapshotSink::new()
+            .with_sink(CsvSnapshotSink::new(&mut csv))
+            .with_sink(JsonSnapshotSink::new(&mut json));
+        write_snapshot_to(&manager(), &mut sink).await.unwrap();
+        drop(sink);
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "client,available,held,total,locked\n1,1.5000,0.0000,1.5000,false\n"
+        );
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "[\n{\"client\":1,\"available\":\"1.5000\",\"held\":\"0.0000\",\"total\":\"1.5000\",\"locked\":false}\n]\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn json_lists_currency_balances() {
+        let eur: CurrencyCode = "EUR".parse().unwrap();
+        let manager = manager();
+        manager
+            .entry(1)
+            .unwrap()
+            .try_update(|acc| {
+                apply_in_currency(acc, Some(eur), |acc| {
+                    operations::apply_deposit(acc, FixedPoint::from_raw(20_000))
+                })
+            })
+            .unwrap();
+
+        let mut sink = JsonSnapshotSink::new(Vec::new());
+        write_snapshot_to(&manager, &mut sink).await.unwrap();
+
+        let json = String::from_utf8(sink.into_inner()).unwrap();
+        assert!(json.contains(r#""currencies":{"EUR":{"available":"2.0000","held":"0.0000"}}"#));
+    }
+
+    #[tokio::test]
+    async fn filtered_snapshot_skips_unselected_accounts() {
+        let manager = manager();
+        manager
+            .entry(2)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+            .unwrap();
+
+        let filter = SnapshotFilter::default().with_clients([2]);
+        let mut sink = JsonSnapshotSink::new(Vec::new());
+        write_snapshot_to_filtered(&manager, &mut sink, &filter)
+            .await
+            .unwrap();
+
+        let json = String::from_utf8(sink.into_inner()).unwrap();
+        assert!(json.contains(r#""client":2"#));
+        assert!(!json.contains(r#""client":1"#));
+    }
+
+    #[tokio::test]
+    async fn ndjson_writes_one_object_per_line() {
+        let manager = manager();
+        manager
+            .entry(2)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+            .unwrap();
+
+        let mut sink = NdjsonSnapshotSink::new(Vec::new());
+        write_snapshot_to(&manager, &mut sink).await.unwrap();
+
+        let ndjson = String::from_utf8(sink.into_inner()).unwrap();
+        let mut lines: Vec<_> = ndjson.lines().collect();
+        lines.sort_unstable();
+        assert_eq!(
+            lines,
+            [
+                r#"{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}"#,
+                r#"{"client":2,"available":"1.0000","held":"0.0000","total":"1.0000","locked":false}"#,
+            ]
+        );
+        assert!(ndjson.ends_with('\n'));
+    }
+
+    #[tokio::test]
+    async fn json_amounts_follow_the_format() {
+        let format = SnapshotFormat::default()
+            .with_decimals(2)
+            .with_trailing_zeros_trimmed(true);
+        let mut sink = NdjsonSnapshotSink::new(Vec::new()).with_format(format);
+        write_snapshot_to(&manager(), &mut sink).await.unwrap();
+
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            "{\"client\":1,\"available\":\"1.5\",\"held\":\"0\",\"total\":\"1.5\",\"locked\":false}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_snapshot_is_an_empty_json_array() {
+        let mut sink = JsonSnapshotSink::new(Vec::new());
+        write_snapshot_to(&ConcurrentAccountManager::<FixedPoint>::new(), &mut sink)
+            .await
+            .unwrap();
+
+        assert_eq!(String::from_utf8(sink.into_inner()).unwrap(), "[\n]\n");
+    }
+}
@@ -0,0 +1,561 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use csv::StringRecord;
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::{Stream, stream};
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+use super::error::IoError;
+use super::parse::RawTransactionRecord;
+use crate::domain::{AmountType, RoundingPolicy, TimestampedTransaction, Transaction};
+
+/// Boxed stream of parsed records including their optional timestamps
+type TimestampedStream<A> =
+    Pin<Box<dyn Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send>>;
+
+/// Column order of CSV lines sent without a header line
+const DEFAULT_COLUMNS: [&str; 4] = ["type", "client", "tx", "amount"];
+
+/// Reconnection behaviour when dialing a gateway
+///
+/// Failed connection attempts are retried with exponential backoff, starting
+/// at `initial_backoff` and doubling up to `max_backoff`. A connection the
+/// gateway closes is redialed after `initial_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failed attempts before giving up (None = retry forever)
+    pub max_retries: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_retries: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Set the delays between attempts
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Give up after `retries` consecutive failed attempts
+    pub fn with_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = Some(retries);
+        self
+    }
+
+    /// Delay before retrying after `failures` consecutive failed attempts
+    fn delay(&self, failures: u32) -> Duration {
+        let factor = 1u32 << failures.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Where the stream gets its connection from
+enum Endpoint {
+    /// Dial a gateway, reconnecting when the connection drops
+    Connect(String),
+    /// A single accepted connection; the stream ends when it closes
+    Accepted(Option<TcpStream>),
+}
+
+/// Settings collected before the stream is first polled
+struct TcpSource {
+    endpoint: Endpoint,
+    reconnect: ReconnectPolicy,
+    rounding: RoundingPolicy,
+    shutdown: BoxFuture<'static, ()>,
+}
+
+/// Async stream of transactions from a newline-delimited TCP feed
+///
+/// Each line holds one record, either as CSV or as a JSON object using the
+/// CSV column names as fields (amounts as decimal strings, as for the REST
+/// server). CSV lines use `type,client,tx,amount` columns unless the peer
+/// first sends a header line starting with `type`, which then applies to
+/// the rest of that connection. Blank lines are ignored.
+///
+/// Lines that fail to parse are reported as `IoError::AtRecord` and the
+/// stream carries on. In `connect` mode, dropped connections are redialed
+/// per the `ReconnectPolicy`; a record cut off by a disconnect is lost, so
+/// gateways should resend from their last acknowledged record. The stream
+/// ends when the shutdown signal fires, when an accepted connection closes,
+/// or when reconnection gives up (after yielding the last connect error).
+///
+/// # Example
+/// ```rust,ignore
+/// let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+/// let stream = TcpTransactionStream::<FixedPoint>::connect("gateway:9000")
+///     .with_reconnect(ReconnectPolicy::default().with_max_retries(10))
+///     .with_shutdown(async move { let _ = stopped.await; });
+/// processor.add_stream(stream);
+/// ```
+pub struct TcpTransactionStream<A>
+where
+    A: AmountType + Unpin,
+{
+    source: Option<TcpSource>,
+    inner: Option<TimestampedStream<A>>,
+}
+
+impl<A> TcpTransactionStream<A>
+where
+    A: AmountType + Unpin,
+{
+    /// Stream records from a gateway at `addr`, reconnecting as needed
+    ///
+    /// The connection is opened when the stream is first polled.
+    pub fn connect(addr: impl Into<String>) -> Self {
+        Self::from_endpoint(Endpoint::Connect(addr.into()))
+    }
+
+    /// Stream records from an established connection until it closes
+    pub fn from_socket(socket: TcpStream) -> Self {
+        Self::from_endpoint(Endpoint::Accepted(Some(socket)))
+    }
+
+    /// Wait for the next connection on `listener` and stream from it
+    pub async fn accept(listener: &TcpListener) -> Result<Self, IoError> {
+        let (socket, peer) = listener.accept().await?;
+        info!("Accepted transaction feed from {}", peer);
+        Ok(Self::from_socket(socket))
+    }
+
+    fn from_endpoint(endpoint: Endpoint) -> Self {
+        Self {
+            source: Some(TcpSource {
+                endpoint,
+                reconnect: ReconnectPolicy::default(),
+                rounding: RoundingPolicy::Reject,
+                shutdown: future::pending().boxed(),
+            }),
+            inner: None,
+        }
+    }
+
+    /// Normalize amounts per `rounding` (defaults to rejecting excess precision)
+    pub fn with_rounding(mut self, rounding: RoundingPolicy) -> Self {
+        if let Some(source) = &mut self.source {
+            source.rounding = rounding;
+        }
+        self
+    }
+
+    /// Set how dropped and failed connections are retried
+    pub fn with_reconnect(mut self, reconnect: ReconnectPolicy) -> Self {
+        if let Some(source) = &mut self.source {
+            source.reconnect = reconnect;
+        }
+        self
+    }
+
+    /// End the stream cleanly once `signal` completes
+    ///
+    /// The current connection is closed; nothing after the signal is read.
+    pub fn with_shutdown(mut self, signal: impl Future<Output = ()> + Send + 'static) -> Self {
+        if let Some(source) = &mut self.source {
+            source.shutdown = signal.boxed();
+        }
+        self
+    }
+
+    /// Convert into a stream that keeps the optional `timestamp` field
+    pub fn timestamped(
+        mut self,
+    ) -> impl Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send + 'static
+    where
+        A: 'static,
+    {
+        self.open()
+    }
+
+    fn open(&mut self) -> TimestampedStream<A> {
+        if let Some(inner) = self.inner.take() {
+            return inner;
+        }
+
+        let state = self.source.take().map(|source| FeedState {
+            source,
+            connection: None,
+            redial: false,
+        });
+        Box::pin(stream::unfold(state, |state| async move {
+            let mut state = state?;
+            let item = state.next().await?;
+            Some((item, Some(state)))
+        }))
+    }
+}
+
+/// One open connection and its CSV column layout
+struct Connection {
+    lines: Lines<BufReader<TcpStream>>,
+    headers: StringRecord,
+    /// Lines read so far on this connection
+    line: u64,
+    /// Byte offset of the current line on this connection
+    byte: u64,
+}
+
+impl Connection {
+    fn new(socket: TcpStream) -> Self {
+        Self {
+            lines: BufReader::new(socket).lines(),
+            headers: StringRecord::from(DEFAULT_COLUMNS.to_vec()),
+            line: 0,
+            byte: 0,
+        }
+    }
+
+    /// Parse one line, or return None for blank and header lines
+    fn parse<A: AmountType>(
+        &mut self,
+        text: &str,
+        rounding: RoundingPolicy,
+    ) -> Option<Result<TimestampedTransaction<A>, IoError>> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        let raw = match text.starts_with('{') {
+            true => serde_json::from_str::<RawTransactionRecord>(text).map_err(IoError::from),
+            false => match Self::read_csv(text) {
+                Ok(record) if Self::is_header(&record) => {
+                    self.headers = record;
+                    return None;
+                }
+                Ok(record) => record
+                    .deserialize::<RawTransactionRecord>(Some(&self.headers))
+                    .map_err(IoError::from),
+                Err(e) => Err(e),
+            },
+        };
+
+        let parsed = raw
+            .and_then(|raw| raw.parse_timestamped_rounded::<A>(rounding))
+            .map_err(|e| IoError::AtRecord {
+                line: self.line,
+                byte: self.byte,
+                record: text.to_string(),
+                source: Box::new(e),
+            });
+        Some(parsed)
+    }
+
+    fn read_csv(text: &str) -> Result<StringRecord, IoError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+
+        let mut record = StringRecord::new();
+        reader.read_record(&mut record)?;
+        Ok(record)
+    }
+
+    fn is_header(record: &StringRecord) -> bool {
+        record
+            .get(0)
+            .is_some_and(|field| field.eq_ignore_ascii_case("type"))
+    }
+}
+
+/// Stream state carried between records
+struct FeedState {
+    source: TcpSource,
+    connection: Option<Connection>,
+    /// Whether a previous connection dropped (redial after a pause)
+    redial: bool,
+}
+
+impl FeedState {
+    /// Read the next record, connecting and reconnecting as needed
+    async fn next<A: AmountType>(&mut self) -> Option<Result<TimestampedTransaction<A>, IoError>> {
+        loop {
+            if self.connection.is_none() {
+                match self.dial().await {
+                    Ok(Some(socket)) => self.connection = Some(Connection::new(socket)),
+                    Ok(None) => return None,
+                    Err(e) => {
+                        self.source.endpoint = Endpoint::Accepted(None);
+                        return Some(Err(e));
+                    }
+                }
+            }
+            let connection = self.connection.as_mut()?;
+
+            let read = tokio::select! {
+                _ = &mut self.source.shutdown => {
+                    info!("Transaction feed shut down");
+                    return None;
+                }
+                read = connection.lines.next_line() => read,
+            };
+
+            match read {
+                Ok(Some(text)) => {
+                    connection.line += 1;
+                    let parsed = connection.parse(&text, self.source.rounding);
+                    connection.byte += text.len() as u64 + 1;
+                    if let Some(parsed) = parsed {
+                        return Some(parsed);
+                    }
+                }
+                Ok(None) => {
+                    info!("Transaction feed closed by peer");
+                    self.connection = None;
+                    self.redial = true;
+                }
+                Err(e) => {
+                    self.connection = None;
+                    self.redial = true;
+                    if matches!(self.source.endpoint, Endpoint::Accepted(_)) {
+                        return Some(Err(e.into()));
+                    }
+                    warn!("Transaction feed connection lost: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Open the next connection, or return None once the feed is finished
+    async fn dial(&mut self) -> Result<Option<TcpStream>, IoError> {
+        let addr = match &mut self.source.endpoint {
+            Endpoint::Accepted(socket) => return Ok(socket.take()),
+            Endpoint::Connect(addr) => addr.clone(),
+        };
+        let reconnect = self.source.reconnect;
+
+        if std::mem::take(&mut self.redial) && !self.pause(reconnect.initial_backoff).await {
+            return Ok(None);
+        }
+
+        let mut failures = 0;
+        loop {
+            let error = tokio::select! {
+                _ = &mut self.source.shutdown => return Ok(None),
+                result = TcpStream::connect(&addr) => match result {
+                    Ok(socket) => {
+                        info!("Connected to transaction feed at {}", addr);
+                        return Ok(Some(socket));
+                    }
+                    Err(e) => e,
+                },
+            };
+
+            failures += 1;
+            if reconnect.max_retries.is_some_and(|max| failures > max) {
+                return Err(error.into());
+            }
+
+            let delay = reconnect.delay(failures);
+            warn!(
+                "Connecting to {} failed ({}); retrying in {:?}",
+                addr, error, delay
+            );
+            if !self.pause(delay).await {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Sleep for `delay`, returning false if shutdown was signalled meanwhile
+    async fn pause(&mut self, delay: Duration) -> bool {
+        tokio::select! {
+            _ = &mut self.source.shutdown => false,
+            _ = tokio::time::sleep(delay) => true,
+        }
+    }
+}
+
+impl<A> Stream for TcpTransactionStream<A>
+where
+    A: AmountType + Unpin,
+{
+    type Item = Result<Transaction<A>, IoError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self.open();
+        let poll = inner
+            .as_mut()
+            .poll_next(cx)
+            .map(|item| item.map(|result| result.map(|tx| tx.transaction)));
+        self.inner = Some(inner);
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+    use futures::StreamExt;
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::oneshot;
+
+    async fn listener() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        (listener, addr)
+    }
+
+    fn fast_reconnect() -> ReconnectPolicy {
+        ReconnectPolicy::default().with_backoff(Duration::from_millis(5), Duration::from_millis(20))
+    }
+
+    #[tokio::test]
+    async fn reads_csv_and_json_lines_until_peer_closes() {
+        let (listener, addr) = listener().await;
+        tokio::spawn(async move {
+            let mut socket = TcpStream::connect(addr).await.unwrap();
+            socket
+                .write_all(
+                    b"deposit,1,1,1.0\n\
+                      {\"type\": \"deposit\", \"client\": 2, \"tx\": 2, \"amount\": \"2.5\"}\n\
+                      \n\
+                      type,client,tx,amount,timestamp\n\
+                      withdrawal,1,3,0.5,42\n\
+                      dispute,1,1\n",
+                )
+                .await
+                .unwrap();
+        });
+
+        let stream = TcpTransactionStream::<FixedPoint>::accept(&listener)
+            .await
+            .unwrap();
+        let records: Vec<_> = stream
+            .timestamped()
+            .map(|record| record.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(records.len(), 4);
+        assert!(matches!(
+            records[1].transaction,
+            Transaction::Deposit {
+                client_id: 2,
+                amount,
+                ..
+            } if amount == FixedPoint::from_raw(25_000)
+        ));
+        assert_eq!(records[2].timestamp, Some(42));
+        assert!(matches!(
+            records[3].transaction,
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn malformed_lines_are_located_and_skipped() {
+        let (listener, addr) = listener().await;
+        tokio::spawn(async move {
+            let mut socket = TcpStream::connect(addr).await.unwrap();
+            socket
+                .write_all(b"deposit,1,1,1.0\nrefund,1,2,1.0\ndeposit,1,3,2.0\n")
+                .await
+                .unwrap();
+        });
+
+        let stream = TcpTransactionStream::<FixedPoint>::accept(&listener)
+            .await
+            .unwrap();
+        let results: Vec<_> = stream.collect().await;
+
+        assert_eq!(results.len(), 3);
+        let error = results[1].as_ref().unwrap_err();
+        assert_eq!(error.line(), Some(2));
+        assert!(matches!(error.inner(), IoError::InvalidTransactionType(_)));
+        assert!(results[2].is_ok());
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_the_gateway_drops_the_connection() {
+        let (listener, addr) = listener().await;
+        tokio::spawn(async move {
+            for line in [&b"deposit,1,1,1.0\n"[..], b"deposit,1,2,2.0\n"] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                socket.write_all(line).await.unwrap();
+            }
+        });
+
+        let stream =
+            TcpTransactionStream::<FixedPoint>::connect(addr).with_reconnect(fast_reconnect());
+        let tx_ids: Vec<_> = stream.take(2).map(|tx| tx.unwrap().tx_id()).collect().await;
+
+        assert_eq!(tx_ids, vec![Some(1), Some(2)]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_ends_an_open_feed() {
+        let (listener, addr) = listener().await;
+        let (stop, stopped) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket.write_all(b"deposit,1,1,1.0\n").await.unwrap();
+            // Keep the connection open until the test ends
+            let _ = stopped.await;
+            drop(socket);
+        });
+
+        let (signal, on_signal) = oneshot::channel::<()>();
+        let mut stream =
+            TcpTransactionStream::<FixedPoint>::connect(addr).with_shutdown(async move {
+                let _ = on_signal.await;
+            });
+
+        assert!(stream.next().await.unwrap().is_ok());
+        signal.send(()).unwrap();
+        assert!(stream.next().await.is_none());
+        let _ = stop.send(());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        // Reserve a port, then close it so connections are refused
+        let (listener, addr) = listener().await;
+        drop(listener);
+
+        let stream = TcpTransactionStream::<FixedPoint>::connect(addr)
+            .with_reconnect(fast_reconnect().with_max_retries(2));
+        let results: Vec<_> = stream.collect().await;
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(IoError::Io(_))));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = ReconnectPolicy::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(500));
+
+        let delays: Vec<_> = (1..=5).map(|failures| policy.delay(failures)).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 500, 500]
+                .map(Duration::from_millis)
+                .to_vec()
+        );
+    }
+}
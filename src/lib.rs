@@ -1,7 +1,22 @@
+#[cfg(not(target_arch = "wasm32"))]
 pub mod app;
 pub mod domain;
 pub mod engine;
 pub mod io;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod prelude;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod storage;
 pub mod streaming;
+pub mod testing;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use app::{ProcessOptions, Snapshot, process_file};
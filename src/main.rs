@@ -1,50 +1,452 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use pay::prelude::*;
+use tracing::level_filters::LevelFilter;
 
 fn main() {
+    // Create shared storage up front so an interrupted run can still snapshot it
+    let snapshot = Snapshot::new(Arc::new(ConcurrentAccountManager::<FixedPoint>::new()));
+    let signal_snapshot = snapshot.clone();
+
     CliApp::new("pay")
         .with_args(parse_args)
-        .run(run_transaction_processor);
+        // Silent unless RUST_LOG or a config file's log level asks for output
+        .with_tracing(LevelFilter::OFF, LogFormat::Pretty)
+        .with_log_level(|command| match command {
+            Command::Configured(config) => config.log_level.map(LevelFilter::from_level),
+            _ => None,
+        })
+        // Only commands that process transactions have a snapshot to write
+        .with_signal_snapshot(move |command| {
+            let sink = SnapshotSink::of(command)?;
+            Some(move |mut writers: Writers| async move {
+                signal_snapshot
+                    .write_partial(&mut writers.stdout, &sink)
+                    .await
+            })
+        })
+        .run(move |writers, command| async move {
+            match command {
+                Command::Process(inputs, combinator) => {
+                    run_transaction_processor(writers, inputs, combinator, snapshot).await
+                }
+                Command::Configured(config) => run_configured(writers, config, snapshot).await,
+                Command::Diff(old, new) => run_diff(writers, old, new).await,
+                Command::Generate(generator, out) => run_generate(writers, generator, out).await,
+                #[cfg(feature = "server")]
+                Command::Serve(addr) => run_server(addr, snapshot.account_manager).await,
+            }
+        });
+}
+
+/// The accounts a run snapshots, shared with the signal hook
+///
+/// Only one snapshot is ever written: the signal hook skips writing once
+/// the run has started its own.
+#[derive(Clone)]
+struct Snapshot {
+    account_manager: Arc<ConcurrentAccountManager<FixedPoint>>,
+    /// Decimals seen in the inputs, for `SnapshotSink::input_precision`
+    precision: InputPrecision,
+    started: Arc<AtomicBool>,
+}
+
+impl Snapshot {
+    fn new(account_manager: Arc<ConcurrentAccountManager<FixedPoint>>) -> Self {
+        Self {
+            account_manager,
+            precision: InputPrecision::new(),
+            started: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Write the run's snapshot to `sink`
+    async fn write(
+        &self,
+        stdout: &mut tokio::io::BufWriter<tokio::io::Stdout>,
+        sink: &SnapshotSink,
+    ) -> Result<(), AppError> {
+        self.started.store(true, Ordering::SeqCst);
+
+        let format = match sink.input_precision {
+            true => sink.format.clone().with_input_precision(&self.precision),
+            false => sink.format.clone(),
+        };
+        match &sink.output {
+            #[cfg(feature = "object-store")]
+            Some(url) if url.contains("://") => {
+                upload_snapshot(&*self.account_manager, url, &format).await?
+            }
+            Some(path) => {
+                let file = tokio::fs::File::create(path).await?;
+                self.account_manager
+                    .snapshot_with_format(tokio::io::BufWriter::new(file), &format)
+                    .await?;
+            }
+            None => {
+                self.account_manager
+                    .snapshot_with_format(stdout, &format)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    /// Write what was processed before a signal, unless the run's own
+    /// snapshot had already started (it would be followed by a second one)
+    async fn write_partial(
+        &self,
+        stdout: &mut tokio::io::BufWriter<tokio::io::Stdout>,
+        sink: &SnapshotSink,
+    ) -> Result<(), AppError> {
+        if self.started.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.write(stdout, sink).await
+    }
+}
+
+/// Where and how a command writes its snapshot
+#[derive(Default)]
+struct SnapshotSink {
+    /// File or object-store URL; stdout when `None`
+    output: Option<String>,
+    format: SnapshotFormat,
+    /// Write as many decimals as the inputs had
+    input_precision: bool,
+}
+
+impl SnapshotSink {
+    /// The sink of a command that processes transactions; `None` for the rest
+    fn of(command: &Command) -> Option<Self> {
+        match command {
+            Command::Process(..) => Some(Self::default()),
+            Command::Configured(config) => Some(Self::configured(config)),
+            #[cfg(feature = "server")]
+            Command::Serve(_) => Some(Self::default()),
+            Command::Diff(..) | Command::Generate(..) => None,
+        }
+    }
+
+    fn configured(config: &RunConfig) -> Self {
+        Self {
+            output: config.output.clone(),
+            format: config.format.clone(),
+            input_precision: config.input_precision,
+        }
+    }
+}
+
+/// What the binary was asked to do
+enum Command {
+    /// Process CSV files, combined as given, and write the snapshot to stdout
+    Process(Vec<String>, StreamCombinator),
+    /// Run the job described by a config file (and flags overriding it)
+    Configured(RunConfig),
+    /// Compare two snapshots and write the per-client changes to stdout
+    Diff(String, String),
+    /// Write a synthetic dataset to a file, or stdout if none is given
+    Generate(DatasetGenerator, Option<String>),
+    /// Run the REST ingestion server on the given address
+    #[cfg(feature = "server")]
+    Serve(String),
 }
 
 /// Parse and validate command-line arguments
-fn parse_args(args: Vec<String>) -> Result<String, AppError> {
-    if args.len() != 2 {
-        return Err(AppError::InvalidArguments(
-            "Usage: pay <transactions.csv>".to_string(),
-        ));
+fn parse_args(args: Vec<String>) -> Result<Command, AppError> {
+    match args.as_slice() {
+        [_, command, flags @ ..] if command == "generate" => parse_generate(flags),
+        [_, flag, path, flags @ ..] if flag == "--config" => parse_configured(path, flags),
+        [_, command, old, new] if command == "diff" => Ok(Command::Diff(old.clone(), new.clone())),
+        #[cfg(feature = "server")]
+        [_, command, addr] if command == "serve" => Ok(Command::Serve(addr.clone())),
+        [_, inputs @ ..] => parse_process(inputs),
+        _ => Err(AppError::InvalidArguments(USAGE.to_string())),
+    }
+}
+
+/// Parse input files (paths or globs such as `data/*.csv`) and `--combinator`
+///
+/// Files are chained in the order given unless `--combinator` says otherwise.
+fn parse_process(args: &[String]) -> Result<Command, AppError> {
+    let usage = || AppError::InvalidArguments(USAGE.to_string());
+
+    let mut inputs = Vec::new();
+    let mut combinator = StreamCombinator::Chain;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--combinator" => {
+                let value = args.next().ok_or_else(usage)?;
+                combinator = value.parse().map_err(AppError::InvalidArguments)?;
+            }
+            flag if flag.starts_with("--") => return Err(usage()),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        return Err(usage());
+    }
+    Ok(Command::Process(expand_inputs(&inputs)?, combinator))
+}
+
+/// Parse `generate` flags, each given as `--name value`
+fn parse_generate(flags: &[String]) -> Result<Command, AppError> {
+    fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, AppError> {
+        value
+            .parse()
+            .map_err(|_| AppError::InvalidArguments(format!("Invalid value for {flag}: {value}")))
+    }
+
+    let mut generator = DatasetGenerator::new(1_000);
+    let mut out = None;
+    for pair in flags.chunks(2) {
+        let [flag, value] = pair else {
+            return Err(AppError::InvalidArguments(USAGE.to_string()));
+        };
+        generator = match flag.as_str() {
+            "--rows" => generator.with_rows(parse(flag, value)?),
+            "--clients" => generator.with_clients(parse(flag, value)?),
+            "--deposit" => generator.with_deposit_ratio(parse(flag, value)?),
+            "--withdraw" => generator.with_withdrawal_ratio(parse(flag, value)?),
+            "--dispute" => generator.with_dispute_ratio(parse(flag, value)?),
+            "--seed" => generator.with_seed(parse(flag, value)?),
+            "--out" => {
+                out = Some(value.clone());
+                generator
+            }
+            _ => return Err(AppError::InvalidArguments(USAGE.to_string())),
+        };
+    }
+    generator.validate().map_err(AppError::InvalidArguments)?;
+    Ok(Command::Generate(generator, out))
+}
+
+/// Load `path`, then apply override flags, each given as `--name value`
+///
+/// `--input` may be repeated; the inputs it gives replace the file's list.
+/// Globs among the inputs are expanded as for plain runs (see `expand_inputs`).
+fn parse_configured(path: &str, flags: &[String]) -> Result<Command, AppError> {
+    fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, AppError> {
+        value
+            .parse()
+            .map_err(|_| AppError::InvalidArguments(format!("Invalid value for {flag}: {value}")))
+    }
+
+    let mut config = RunConfig::load(path)?;
+    let mut inputs = Vec::new();
+    for pair in flags.chunks(2) {
+        let [flag, value] = pair else {
+            return Err(AppError::InvalidArguments(USAGE.to_string()));
+        };
+        match flag.as_str() {
+            "--input" => inputs.push(value.clone()),
+            "--shards" => config.shards = parse(flag, value)?,
+            "--combinator" => config.combinator = parse(flag, value)?,
+            "--error-policy" => config.error_policy = parse(flag, value)?,
+            "--verify-invariants" => config.verify_invariants = parse(flag, value)?,
+            "--output" => config.output = Some(value.clone()),
+            "--decimals" if value == "input" => config.input_precision = true,
+            "--decimals" => {
+                config.input_precision = false;
+                config.format = config.format.with_decimals(parse(flag, value)?)
+            }
+            "--trim-zeros" => {
+                config.format = config
+                    .format
+                    .with_trailing_zeros_trimmed(parse(flag, value)?)
+            }
+            "--log-level" => config.log_level = Some(parse(flag, value)?),
+            _ => return Err(AppError::InvalidArguments(USAGE.to_string())),
+        }
+    }
+    if !inputs.is_empty() {
+        config.inputs = inputs;
+    }
+    config.inputs = expand_inputs(&config.inputs)?;
+    config.validate()?;
+    Ok(Command::Configured(config))
+}
+
+/// Usage text, listing `extra` commands after the ones every build has
+macro_rules! usage {
+    ($($extra:literal)?) => {
+        concat!(
+            "Usage: pay <transactions.csv>... [--combinator merge|chain|timestamp]",
+            " | pay --config <pay.toml> [--input file]... [--shards N] [--combinator merge|chain|timestamp]",
+            " [--error-policy silent|skip|abort] [--verify-invariants true|false] [--output file]",
+            " [--decimals N|input] [--trim-zeros true|false] [--log-level level]",
+            " | pay diff <old.csv> <new.csv>",
+            " | pay generate [--rows N] [--clients N] [--deposit R] [--withdraw R] [--dispute R] [--seed N] [--out file.csv]",
+            $($extra,)?
+            " (any command also takes --error-format text|json)"
+        )
+    };
+}
+
+#[cfg(not(feature = "server"))]
+const USAGE: &str = usage!();
+
+#[cfg(feature = "server")]
+const USAGE: &str = usage!(" | pay serve <addr>");
+
+/// Serve the REST API until interrupted (the signal hook then writes the snapshot)
+#[cfg(feature = "server")]
+async fn run_server(
+    addr: String,
+    account_manager: Arc<ConcurrentAccountManager<FixedPoint>>,
+) -> Result<(), AppError> {
+    let state = pay::server::ServerState::new(account_manager);
+    #[cfg(feature = "metrics")]
+    let state = state.with_metrics(pay::metrics::MetricsRegistry::new());
+
+    pay::server::serve(addr, state).await?;
+    Ok(())
+}
+
+/// Generate a synthetic dataset into `out`, or stdout
+async fn run_generate(
+    mut writers: Writers,
+    generator: DatasetGenerator,
+    out: Option<String>,
+) -> Result<(), AppError> {
+    use tokio::io::AsyncWriteExt;
+
+    match out {
+        Some(path) => {
+            let file = std::fs::File::create(&path)?;
+            generator.write_csv(std::io::BufWriter::new(file))?;
+        }
+        None => {
+            let mut output = Vec::new();
+            generator.write_csv(&mut output)?;
+            writers.stdout.write_all(&output).await?;
+            writers.stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Diff two snapshot files and write the changes as CSV
+async fn run_diff(mut writers: Writers, old: String, new: String) -> Result<(), AppError> {
+    use tokio::io::AsyncWriteExt;
+
+    let open =
+        |path: &String| std::fs::File::open(path).map_err(|_| AppError::FileNotFound(path.clone()));
+    let diff = diff_snapshots::<FixedPoint, _, _>(open(&old)?, open(&new)?)?;
+
+    let mut output = Vec::new();
+    diff.write_csv(&mut output)?;
+    writers.stdout.write_all(&output).await?;
+    writers.stdout.flush().await?;
+
+    Ok(())
+}
+
+/// Run a configured job: every input through the configured topology and policy
+async fn run_configured(
+    writers: Writers,
+    config: RunConfig,
+    snapshot: Snapshot,
+) -> Result<(), AppError> {
+    match config.error_policy {
+        ErrorPolicyKind::Silent => process_configured(writers, config, snapshot, SilentSkip).await,
+        ErrorPolicyKind::Skip => process_configured(writers, config, snapshot, SkipErrors).await,
+        ErrorPolicyKind::Abort => process_configured(writers, config, snapshot, AbortOnError).await,
+    }
+}
+
+async fn process_configured<P: ErrorPolicy + Clone + Send + 'static>(
+    mut writers: Writers,
+    config: RunConfig,
+    snapshot: Snapshot,
+    policy: P,
+) -> Result<(), AppError> {
+    let account_manager = snapshot.account_manager.clone();
+    let transaction_store = Arc::new(ConcurrentTransactionStore::<FixedPoint>::new());
+    let mut processor = StreamProcessor::new(account_manager.clone(), transaction_store, policy)
+        .with_shards(config.shards)
+        .with_stream_combinator(config.combinator);
+    let mut options = CsvReaderOptions::default().with_columns(config.columns.clone());
+    if config.input_precision {
+        options = options.with_input_precision(snapshot.precision.clone());
+    }
+    for input in &config.inputs {
+        let stream = open_input(input, options.clone()).await?;
+        processor = processor.add_timestamped_stream_named(input.clone(), stream.timestamped());
+    }
+    let results = processor.process().await;
+    if !results.all_succeeded() {
+        tracing::warn!("Processing stopped early; writing the accounts processed so far");
     }
-    Ok(args[1].clone())
+    if config.verify_invariants
+        && let Err(violations) = verify_invariants(&*account_manager)
+    {
+        for violation in &violations {
+            tracing::error!(%violation, "Account invariant violated");
+        }
+        return Err(violations
+            .into_iter()
+            .next()
+            .expect("at least one violation")
+            .into());
+    }
+
+    snapshot
+        .write(&mut writers.stdout, &SnapshotSink::configured(&config))
+        .await
+}
+
+/// Open an input file, or an object-store URL (e.g. `s3://bucket/key`) with
+/// the `object-store` feature
+async fn open_input(
+    input: &str,
+    options: CsvReaderOptions,
+) -> Result<CsvTransactionStream<FixedPoint>, AppError> {
+    #[cfg(feature = "object-store")]
+    if input.contains("://") {
+        return Ok(CsvTransactionStream::from_url_with_options(input, options).await?);
+    }
+    Ok(CsvTransactionStream::from_file_with_options(input, options).await?)
 }
 
 /// Main application logic - processes transactions and writes snapshot
 async fn run_transaction_processor(
     mut writers: Writers,
-    input_file: String,
+    inputs: Vec<String>,
+    combinator: StreamCombinator,
+    snapshot: Snapshot,
 ) -> Result<(), AppError> {
-    // Create CSV transaction stream from file
-    // This is a simple single-stream topology (most common case)
-    // For processing multiple streams, see:
-    //   - examples/sequential_topology.rs (chain multiple streams in order)
-    //   - examples/concurrent_topology.rs (merge multiple streams concurrently)
-    let tx_stream = CsvTransactionStream::<FixedPoint>::from_file(&input_file).await?;
-
-    // Create shared storage (wrapped in Arc for StreamProcessor API)
-    let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+    // Transaction store is only needed while processing
     let transaction_store = Arc::new(ConcurrentTransactionStore::<FixedPoint>::new());
 
-    // Process stream with silent error policy (per brief requirements)
+    // Process streams with silent error policy (per brief requirements)
     // "you can ignore it and assume this is an error on our partners side"
     // Use SilentSkip to avoid stderr output during automated scoring
-    let _results = StreamProcessor::new(account_manager.clone(), transaction_store, SilentSkip)
-        .add_stream(tx_stream)
-        .process()
-        .await;
+    let mut processor = StreamProcessor::new(
+        snapshot.account_manager.clone(),
+        transaction_store,
+        SilentSkip,
+    )
+    .with_stream_combinator(combinator);
+
+    // One CSV transaction stream per file, in command-line order
+    for input in &inputs {
+        let tx_stream = open_input(input, CsvReaderOptions::default()).await?;
+        processor = processor.add_timestamped_stream_named(input.clone(), tx_stream.timestamped());
+    }
+
+    // Chained files are applied one after another on this task; merging
+    // needs a shard task to poll the files concurrently
+    let _results = match combinator {
+        StreamCombinator::Chain => processor.process_sequential().await,
+        _ => processor.process().await,
+    };
     // Note: We continue regardless of success/failure per brief's error handling guidance
 
     // Write snapshot to stdout (snapshot() handles flushing)
-    account_manager.snapshot(&mut writers.stdout).await?;
-
-    Ok(())
+    snapshot
+        .write(&mut writers.stdout, &SnapshotSink::default())
+        .await
 }
@@ -0,0 +1,29 @@
+//! Prometheus metrics (requires the `metrics` feature)
+//!
+//! A `MetricsRegistry` is a cheap, cloneable handle shared by every processor
+//! that reports into it:
+//!
+//! - `pay_transactions_total{type}`: transactions processed, by CSV type
+//! - `pay_errors_total{kind}`: rejected transactions and unreadable records, by error kind
+//! - `pay_accounts` / `pay_held_total`: account count and total held funds
+//! - `pay_transaction_duration_seconds`: per-transaction processing latency
+//! - `pay_shard_duration_seconds`: wall-clock time of each stream shard
+//!
+//! `MetricsRegistry::render` produces the Prometheus text exposition format;
+//! with the `server` feature it is served at `GET /metrics`.
+//!
+//! # Example
+//! ```rust,ignore
+//! let metrics = MetricsRegistry::new();
+//! StreamProcessor::new(accounts, store, SkipErrors)
+//!     .with_metrics(metrics.clone())
+//!     .add_stream(stream)
+//!     .process()
+//!     .await;
+//! println!("{}", metrics.render());
+//! ```
+
+mod registry;
+
+pub(crate) use registry::IO_ERROR_KIND;
+pub use registry::{Histogram, MetricsRegistry};
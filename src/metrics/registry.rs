@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use crate::domain::{AmountType, ClientAccount};
+use crate::engine::EngineError;
+use crate::storage::{ClientAccountManager, StorageError};
+
+/// Bucket upper bounds (seconds) for per-transaction latency
+const TRANSACTION_BUCKETS: &[f64] = &[
+    0.000_001, 0.000_005, 0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.01, 0.1,
+];
+
+/// Bucket upper bounds (seconds) for shard duration
+const SHARD_BUCKETS: &[f64] = &[0.001, 0.01, 0.1, 1.0, 10.0, 60.0, 600.0];
+
+/// Error kind reported for records that could not be read or parsed
+pub(crate) const IO_ERROR_KIND: &str = "io";
+
+/// Fixed-bucket histogram of durations
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Per-bucket (non-cumulative) counts, plus a final `+Inf` bucket
+    buckets: Vec<AtomicU64>,
+    sum_nanos: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_nanos: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Record one observation
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(self.bounds.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of observations recorded
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+
+        let mut cumulative = 0;
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        cumulative += self.buckets[self.bounds.len()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {cumulative}");
+
+        let sum = self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {}", self.count());
+    }
+}
+
+struct Metrics {
+    transactions: Mutex<BTreeMap<&'static str, u64>>,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+    accounts: AtomicU64,
+    /// Total held funds, stored as `f64` bits
+    held_total: AtomicU64,
+    /// Estimated storage footprint, from a `MemoryBudget`
+    memory_bytes: AtomicU64,
+    transaction_duration: Histogram,
+    shard_duration: Histogram,
+}
+
+/// Shared handle to the engine's Prometheus metrics
+///
+/// Cloning is cheap; all clones report into the same metrics. Counters and
+/// histograms are updated by processors as they run; the account gauges are
+/// refreshed with `observe_accounts` (done automatically when a
+/// `StreamProcessor` finishes and after every server ingest).
+#[derive(Clone)]
+pub struct MetricsRegistry {
+    inner: Arc<Metrics>,
+}
+
+impl Default for MetricsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Metrics {
+                transactions: Mutex::new(BTreeMap::new()),
+                errors: Mutex::new(BTreeMap::new()),
+                accounts: AtomicU64::new(0),
+                held_total: AtomicU64::new(0f64.to_bits()),
+                memory_bytes: AtomicU64::new(0),
+                transaction_duration: Histogram::new(TRANSACTION_BUCKETS),
+                shard_duration: Histogram::new(SHARD_BUCKETS),
+            }),
+        }
+    }
+
+    /// Record one processed transaction of the given CSV type
+    pub fn record_transaction(
+        &self,
+        kind: &'static str,
+        latency: Duration,
+        error: Option<&EngineError>,
+    ) {
+        *self.inner.transactions.lock().entry(kind).or_default() += 1;
+        self.inner.transaction_duration.observe(latency);
+
+        if let Some(error) = error {
+            self.record_error(error_kind(error));
+        }
+    }
+
+    /// Count one error of the given kind
+    pub fn record_error(&self, kind: &'static str) {
+        *self.inner.errors.lock().entry(kind).or_default() += 1;
+    }
+
+    /// Record how long one shard took to drain its streams
+    pub fn record_shard(&self, duration: Duration) {
+        self.inner.shard_duration.observe(duration);
+    }
+
+    /// Refresh the account count and held-funds gauges
+    pub fn observe_accounts<A: AmountType>(&self, accounts: &impl ClientAccountManager<A>) {
+        let mut count = 0;
+        let mut held = A::zero();
+        accounts.for_each_account(&mut |account: &ClientAccount<A>| {
+            count += 1;
+            held = held.checked_add(account.held()).unwrap_or(held);
+        });
+
+        let held = held.to_decimal_string().parse::<f64>().unwrap_or_default();
+        self.inner.accounts.store(count, Ordering::Relaxed);
+        self.inner
+            .held_total
+            .store(held.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Set the estimated storage footprint gauge
+    pub fn observe_memory(&self, bytes: u64) {
+        self.inner.memory_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Transactions processed of the given CSV type
+    pub fn transactions(&self, kind: &str) -> u64 {
+        self.inner
+            .transactions
+            .lock()
+            .get(kind)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Errors recorded of the given kind
+    pub fn errors(&self, kind: &str) -> u64 {
+        self.inner.errors.lock().get(kind).copied().unwrap_or(0)
+    }
+
+    /// Accounts seen at the last `observe_accounts`
+    pub fn accounts(&self) -> u64 {
+        self.inner.accounts.load(Ordering::Relaxed)
+    }
+
+    /// Total held funds seen at the last `observe_accounts`
+    pub fn held_total(&self) -> f64 {
+        f64::from_bits(self.inner.held_total.load(Ordering::Relaxed))
+    }
+
+    /// Storage footprint seen at the last `observe_memory`
+    pub fn memory_bytes(&self) -> u64 {
+        self.inner.memory_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Per-transaction latency histogram
+    pub fn transaction_duration(&self) -> &Histogram {
+        &self.inner.transaction_duration
+    }
+
+    /// Shard duration histogram
+    pub fn shard_duration(&self) -> &Histogram {
+        &self.inner.shard_duration
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        render_counter(
+            &mut out,
+            "pay_transactions_total",
+            "Transactions processed by type",
+            "type",
+            &self.inner.transactions.lock(),
+        );
+        render_counter(
+            &mut out,
+            "pay_errors_total",
+            "Transaction errors by kind",
+            "kind",
+            &self.inner.errors.lock(),
+        );
+
+        let _ = writeln!(out, "# HELP pay_accounts Client accounts");
+        let _ = writeln!(out, "# TYPE pay_accounts gauge");
+        let _ = writeln!(out, "pay_accounts {}", self.accounts());
+        let _ = writeln!(
+            out,
+            "# HELP pay_held_total Total held funds across accounts"
+        );
+        let _ = writeln!(out, "# TYPE pay_held_total gauge");
+        let _ = writeln!(out, "pay_held_total {}", self.held_total());
+        let _ = writeln!(
+            out,
+            "# HELP pay_memory_bytes Estimated bytes held by account and transaction storage"
+        );
+        let _ = writeln!(out, "# TYPE pay_memory_bytes gauge");
+        let _ = writeln!(out, "pay_memory_bytes {}", self.memory_bytes());
+
+        self.inner.transaction_duration.render(
+            &mut out,
+            "pay_transaction_duration_seconds",
+            "Per-transaction processing latency",
+        );
+        self.inner.shard_duration.render(
+            &mut out,
+            "pay_shard_duration_seconds",
+            "Time for a shard to drain its streams",
+        );
+
+        out
+    }
+}
+
+fn render_counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    values: &BTreeMap<&'static str, u64>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    for (value, count) in values {
+        let _ = writeln!(out, "{name}{{{label}=\"{value}\"}} {count}");
+    }
+}
+
+/// Stable metric label for an engine error
+fn error_kind(error: &EngineError) -> &'static str {
+    match error {
+        EngineError::TransactionNotFound(_) => "transaction_not_found",
+        EngineError::TransactionNotDisputed(_) => "not_disputed",
+        EngineError::TransactionAlreadyDisputed(_) => "already_disputed",
+        EngineError::CannotDisputeWithdrawal => "dispute_withdrawal",
+        EngineError::AdminOperationNotAllowed => "admin_not_allowed",
+        EngineError::Rejected(_) => "rejected",
+        EngineError::VelocityLimitExceeded(_) => "velocity_limit",
+        EngineError::Domain(_) | EngineError::Storage(StorageError::DomainError(_)) => "domain",
+        EngineError::Storage(_) => "storage",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{DomainError, FixedPoint};
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager};
+
+    #[test]
+    fn counts_transactions_and_errors_by_kind() {
+        let metrics = MetricsRegistry::new();
+        let insufficient =
+            EngineError::Storage(StorageError::DomainError(DomainError::InsufficientFunds));
+
+        metrics.record_transaction("deposit", Duration::from_micros(2), None);
+        metrics.record_transaction("deposit", Duration::from_micros(3), None);
+        metrics.record_transaction("withdrawal", Duration::from_micros(2), Some(&insufficient));
+        metrics.record_transaction(
+            "withdrawal",
+            Duration::from_micros(1),
+            Some(&EngineError::VelocityLimitExceeded("too many".to_string())),
+        );
+        metrics.record_error(IO_ERROR_KIND);
+
+        assert_eq!(metrics.transactions("deposit"), 2);
+        assert_eq!(metrics.transactions("withdrawal"), 2);
+        assert_eq!(metrics.errors("domain"), 1);
+        assert_eq!(metrics.errors("velocity_limit"), 1);
+        assert_eq!(metrics.errors("io"), 1);
+        assert_eq!(metrics.transaction_duration().count(), 4);
+    }
+
+    #[test]
+    fn observes_account_gauges() {
+        let metrics = MetricsRegistry::new();
+        let accounts = ConcurrentAccountManager::<FixedPoint>::new();
+        accounts
+            .entry(1)
+            .unwrap()
+            .try_update(|account| {
+                account.set_held(FixedPoint::from_raw(25_000));
+                Ok(())
+            })
+            .unwrap();
+        accounts.entry(2).unwrap().try_update(|_| Ok(())).unwrap();
+
+        metrics.observe_accounts(&accounts);
+
+        assert_eq!(metrics.accounts(), 2);
+        assert_eq!(metrics.held_total(), 2.5);
+    }
+
+    #[test]
+    fn renders_prometheus_text_format() {
+        let metrics = MetricsRegistry::new();
+        metrics.record_transaction("deposit", Duration::from_micros(20), None);
+        metrics.record_shard(Duration::from_millis(5));
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE pay_transactions_total counter\n"));
+        assert!(text.contains("pay_transactions_total{type=\"deposit\"} 1\n"));
+        assert!(text.contains("pay_transaction_duration_seconds_bucket{le=\"0.00001\"} 0\n"));
+        assert!(text.contains("pay_transaction_duration_seconds_bucket{le=\"0.00005\"} 1\n"));
+        assert!(text.contains("pay_transaction_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("pay_shard_duration_seconds_count 1\n"));
+        assert!(text.contains("pay_accounts 0\n"));
+    }
+}
@@ -4,26 +4,65 @@
 
 // Domain types
 pub use crate::domain::{
-    AmountType, ClientAccount, DomainError, FixedPoint, Transaction, TransactionRecord,
+    AccountOp, AmountType, ClientAccount, ClientId, CurrencyBalance, CurrencyCode, DisputePolicy,
+    DomainError, Fee, FeeSchedule, FeeType, FixedPoint, InputPrecision, LockedAccountPolicy,
+    PairOp, RoundingPolicy, TimestampedTransaction, Transaction, TransactionId, TransactionRecord,
+    TxKind, TxState,
 };
 
 // Storage types
 pub use crate::storage::{
-    ClientAccountEntry, ClientAccountManager, ConcurrentAccountManager,
-    ConcurrentTransactionStore, StorageError, TransactionStoreManager,
+    AccountBalance, AccountEvent, BoundedTransactionStore, ClientAccountEntry,
+    ClientAccountManager, ConcurrentAccountManager, ConcurrentTransactionStore,
+    DenseAccountManager, EventSourcedAccountManager, EvictionPolicy, InvariantViolation,
+    QueryHandle, SnapshotFormat, SpillingTransactionStore, StateCounts, StorageError, TierStats,
+    TieredAccountManager, TransactionStoreManager, export_state, import_state, verify_invariants,
 };
+#[cfg(feature = "diagnostics")]
+pub use crate::storage::{ContentionReport, ShardContention};
+#[cfg(feature = "redis")]
+pub use crate::storage::RedisAccountManager;
 
 // Engine types
-pub use crate::engine::{EngineError, TransactionProcessor};
+pub use crate::engine::{
+    AuditOperation, AuditOutcome, AuditRecord, AuditSink, BalanceSnapshot, BlockedClients,
+    EngineError, IdempotencyKeys, MaxAmount, OrderVerifier, OrderingReport, QuarantineSink,
+    QuarantinedTransaction, SegmentRule, SimulationOutcome, Statement, TransactionHistory,
+    TransactionProcessor, TransactionProcessorBuilder, TransactionTypeCounts, TransactionValidator,
+    TypeCount, VelocityLimit, generate_statement,
+};
 
 // IO types
-pub use crate::io::{CsvTransactionStream, IoError, RawTransactionRecord, write_snapshot};
+pub use crate::io::{
+    AccountDelta, ColumnMapping, CsvReaderOptions, CsvSnapshotSink, CsvTransactionStream,
+    DatasetGenerator, DeltaStatus, IoError, JsonSnapshotSink, NdjsonSnapshotSink,
+    RawTransactionRecord, ReadPosition, SnapshotDiff, SnapshotFilter, SnapshotSink,
+    TeeSnapshotSink, diff_snapshots, write_snapshot, write_snapshot_to, write_snapshot_to_filtered,
+    write_snapshot_with_filter, write_snapshot_with_format,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::io::{
+    CompressedReader, Compression, ParallelCsvOptions, SnapshotPartitioning,
+    write_snapshot_partitioned,
+};
+#[cfg(feature = "arrow")]
+pub use crate::io::ArrowTransactionStream;
+#[cfg(feature = "object-store")]
+pub use crate::io::{object_store_for, upload_snapshot, upload_snapshot_to};
+#[cfg(feature = "tcp")]
+pub use crate::io::{ReconnectPolicy, TcpTransactionStream};
 
 // Streaming types
 pub use crate::streaming::{
-    AbortOnError, ErrorPolicy, SilentSkip, SkipErrors,
-    StreamProcessor, StreamCombinator, ShardAssignment,
+    AbortOnError, AccountStats, AsyncCallback, Callback, Checkpoint, CsvDeadLetterWriter,
+    DeadLetter, DeadLetterSink, ErrorPolicy, ExecutionModel, LoggedError, LoggedErrorKind,
+    MemoryBudget, MemoryUsage, Priority, RetryPolicy, ShardAssignment, SilentSkip, SkipErrors,
+    SnapshotMode, StreamCombinator, StreamHandle, StreamProcessor, StreamResult, TransactionFilter,
 };
 
 // App types
-pub use crate::app::{AppError, CliApp, Writers};
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::app::{
+    AccountRow, AppError, CliApp, ErrorCategory, ErrorFormat, ErrorPolicyKind, LogFormat,
+    ProcessOptions, RunConfig, Snapshot, Writers, expand_inputs, process_file,
+};
@@ -0,0 +1,243 @@
+//! Python bindings (requires the `python` feature)
+//!
+//! Builds the `pay` extension module, so settlement files can be checked from
+//! Python without shelling out to the binary:
+//!
+//! ```text
+//! import pay
+//!
+//! engine = pay.Engine()
+//! accounts = engine.process_csv("settlement.csv")
+//! accounts[1]["available"]  # Decimal('1.5000')
+//!
+//! engine.apply({"type": "withdrawal", "client": 1, "tx": 99, "amount": "0.5"})
+//! engine.account(1)["total"]  # Decimal('1.0000')
+//! ```
+//!
+//! Build and install it into the active virtualenv with `maturin develop`
+//! (see `pyproject.toml`). Amounts are `decimal.Decimal` values at four
+//! decimal places, exactly as in the CSV snapshot.
+
+use std::path::PathBuf;
+
+use futures::StreamExt;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::domain::{AmountType, ClientAccount, ClientId, FixedPoint};
+use crate::engine::TransactionProcessor;
+use crate::io::{CsvTransactionStream, IoError, RawTransactionRecord};
+use crate::storage::{ClientAccountManager, ConcurrentAccountManager, ConcurrentTransactionStore};
+
+type Processor = TransactionProcessor<
+    FixedPoint,
+    ConcurrentAccountManager<FixedPoint>,
+    ConcurrentTransactionStore<FixedPoint>,
+>;
+
+/// In-memory payment engine with the default dispute semantics
+#[pyclass(name = "Engine", module = "pay")]
+pub struct Engine {
+    processor: Processor,
+}
+
+#[pymethods]
+impl Engine {
+    /// Create an engine with no accounts; `admin_ops` accepts `unlock`,
+    /// `credit_limit`, `adjustment` and `tag` transactions
+    #[new]
+    #[pyo3(signature = (admin_ops = false))]
+    fn new(admin_ops: bool) -> Self {
+        let processor = TransactionProcessor::new(
+            ConcurrentAccountManager::new(),
+            ConcurrentTransactionStore::new(),
+        )
+        .with_admin_ops(admin_ops);
+        Self { processor }
+    }
+
+    /// Apply every transaction in a CSV file (gzip and zstd are detected)
+    /// and return the resulting accounts
+    ///
+    /// Malformed rows and rejected transactions are skipped, as in the `pay`
+    /// binary.
+    fn process_csv(&mut self, py: Python<'_>, path: PathBuf) -> PyResult<Py<PyDict>> {
+        let processor = &mut self.processor;
+        py.detach(|| {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            runtime.block_on(async {
+                let stream = CsvTransactionStream::<FixedPoint>::from_file(&path).await?;
+                let mut stream = Box::pin(stream.timestamped());
+                while let Some(tx) = stream.next().await {
+                    if let Ok(tx) = tx {
+                        let key = tx.idempotency_key.as_deref();
+                        let _ = processor.process_transaction_with_key(tx.transaction, key);
+                    }
+                }
+                Ok::<_, IoError>(())
+            })
+        })
+        .map_err(|e| PyIOError::new_err(e.to_string()))?;
+
+        self.snapshot(py)
+    }
+
+    /// Apply one transaction given as a dict of CSV columns (`type`, `client`,
+    /// `tx`, and `amount`, `to`, `currency`, `idempotency_key` or `tag` where needed)
+    ///
+    /// Raises `ValueError` if the transaction is malformed or rejected.
+    fn apply(&mut self, tx: &Bound<'_, PyDict>) -> PyResult<()> {
+        let tx = raw_record(tx)?
+            .parse_timestamped::<FixedPoint>()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        self.processor
+            .process_transaction_with_key(tx.transaction, tx.idempotency_key.as_deref())
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// The client's balances, or `None` if it has no account
+    fn account(&self, py: Python<'_>, client_id: ClientId) -> PyResult<Option<Py<PyDict>>> {
+        let account = self
+            .processor
+            .account_manager()
+            .account(client_id)
+            .map(|account| account_dict(py, &account))
+            .transpose()?;
+        Ok(account.map(Bound::unbind))
+    }
+
+    /// Every account, keyed by client id
+    fn snapshot(&self, py: Python<'_>) -> PyResult<Py<PyDict>> {
+        let mut accounts = Vec::new();
+        self.processor
+            .account_manager()
+            .for_each_account(&mut |account| accounts.push(account.clone()));
+
+        let snapshot = PyDict::new(py);
+        for account in &accounts {
+            snapshot.set_item(account.client_id(), account_dict(py, account)?)?;
+        }
+        Ok(snapshot.unbind())
+    }
+}
+
+/// Read a transaction dict into the CSV record it stands for
+fn raw_record(tx: &Bound<'_, PyDict>) -> PyResult<RawTransactionRecord> {
+    let required = |key: &str| {
+        tx.get_item(key)?
+            .ok_or_else(|| PyValueError::new_err(format!("missing '{key}'")))
+    };
+    // Amounts may be given as strings, ints, floats or Decimals
+    let text = |key: &str| -> PyResult<Option<String>> {
+        match tx.get_item(key)? {
+            Some(value) if !value.is_none() => Ok(Some(value.str()?.to_string())),
+            _ => Ok(None),
+        }
+    };
+
+    Ok(RawTransactionRecord {
+        tx_type: required("type")?.extract()?,
+        client: required("client")?.extract()?,
+        tx: match tx.get_item("tx")? {
+            Some(tx_id) => tx_id.extract()?,
+            None => 0,
+        },
+        amount: text("amount")?,
+        to: tx.get_item("to")?.map(|to| to.extract()).transpose()?,
+        currency: text("currency")?,
+        idempotency_key: text("idempotency_key")?,
+        tag: text("tag")?,
+        ..Default::default()
+    })
+}
+
+/// `{"available", "held", "total", "locked"}` for the base currency
+fn account_dict<'py>(
+    py: Python<'py>,
+    account: &ClientAccount<FixedPoint>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let decimal = py.import("decimal")?.getattr("Decimal")?;
+    let amount = |value: FixedPoint| decimal.call1((value.to_decimal_string(),));
+
+    let dict = PyDict::new(py);
+    dict.set_item("available", amount(account.available())?)?;
+    dict.set_item("held", amount(account.held())?)?;
+    dict.set_item("total", amount(account.total())?)?;
+    dict.set_item("locked", account.is_locked())?;
+    Ok(dict)
+}
+
+/// The `pay` Python module
+#[pymodule]
+fn pay(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Engine>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_engine<F>(test: F)
+    where
+        F: for<'py> FnOnce(Python<'py>, Bound<'py, PyAny>),
+    {
+        Python::initialize();
+        Python::attach(|py| {
+            let engine = Bound::new(py, Engine::new(false)).unwrap().into_any();
+            test(py, engine);
+        });
+    }
+
+    #[test]
+    fn applies_transactions_and_reports_decimals() {
+        with_engine(|py, engine| {
+            let deposit = PyDict::new(py);
+            deposit.set_item("type", "deposit").unwrap();
+            deposit.set_item("client", 1).unwrap();
+            deposit.set_item("tx", 1).unwrap();
+            deposit.set_item("amount", "1.5").unwrap();
+            engine.call_method1("apply", (deposit,)).unwrap();
+
+            let account = engine.call_method1("account", (1,)).unwrap();
+            let available = account.get_item("available").unwrap();
+            assert_eq!(available.str().unwrap().to_string(), "1.5000");
+            assert!(engine.call_method1("account", (2,)).unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn rejected_transactions_raise_value_error() {
+        with_engine(|py, engine| {
+            let withdrawal = PyDict::new(py);
+            withdrawal.set_item("type", "withdrawal").unwrap();
+            withdrawal.set_item("client", 1).unwrap();
+            withdrawal.set_item("tx", 1).unwrap();
+            withdrawal.set_item("amount", 5).unwrap();
+
+            let error = engine.call_method1("apply", (withdrawal,)).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn process_csv_returns_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("batch.csv");
+        std::fs::write(
+            &path,
+            "type,client,tx,amount\ndeposit,1,1,2.0\ndeposit,2,2,1.0\nwithdrawal,2,3,5.0\n",
+        )
+        .unwrap();
+
+        with_engine(|_, engine| {
+            let snapshot = engine.call_method1("process_csv", (path,)).unwrap();
+            assert_eq!(snapshot.len().unwrap(), 2);
+            let total = snapshot.get_item(2).unwrap().get_item("total").unwrap();
+            assert_eq!(total.str().unwrap().to_string(), "1.0000");
+        });
+    }
+}
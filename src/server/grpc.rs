@@ -0,0 +1,320 @@
+//! gRPC service over the REST server's state (requires the `grpc` feature)
+//!
+//! `proto/pay.proto` defines the `pay.v1.Pay` service; its RPCs do what the
+//! REST routes do, over the same `ServerState`:
+//!
+//! - `SubmitTransaction` / `SubmitBatch`: like `POST /transactions`
+//! - `GetAccount`: like `GET /accounts/{id}`, with `NOT_FOUND` for unknown clients
+//! - `StreamSnapshot`: every account as its own message
+//!
+//! Both servers can run side by side over one state, sharing its accounts
+//! and transaction records.
+//!
+//! # Example
+//! ```rust,ignore
+//! let state = ServerState::new(Arc::new(ConcurrentAccountManager::new()));
+//! tokio::try_join!(
+//!     pay::server::serve("127.0.0.1:8080", state.clone()),
+//!     pay::server::grpc::serve("127.0.0.1:50051", state),
+//! )?;
+//! ```
+
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+use super::routes::{AccountView, IngestReport, ServerState};
+use crate::io::RawTransactionRecord;
+use crate::storage::ClientAccountManager;
+
+/// Messages, client and server generated from `proto/pay.proto`
+pub mod proto {
+    tonic::include_proto!("pay.v1");
+}
+
+use proto::pay_server::{Pay, PayServer};
+
+/// The `pay.v1.Pay` service over shared server state
+#[derive(Clone)]
+pub struct PayService {
+    state: ServerState,
+}
+
+impl PayService {
+    /// Create the service over shared server state
+    pub fn new(state: ServerState) -> Self {
+        Self { state }
+    }
+}
+
+/// The service ready to add to a `tonic::transport::Server`
+pub fn service(state: ServerState) -> PayServer<PayService> {
+    PayServer::new(PayService::new(state))
+}
+
+/// Bind to `addr` and serve the gRPC API until the task is cancelled
+pub async fn serve(addr: impl ToSocketAddrs, state: ServerState) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    Server::builder()
+        .add_service(service(state))
+        .serve_with_incoming(TcpIncoming::from(listener))
+        .await
+        .map_err(std::io::Error::other)
+}
+
+#[tonic::async_trait]
+impl Pay for PayService {
+    async fn submit_transaction(
+        &self,
+        request: Request<proto::Transaction>,
+    ) -> Result<Response<proto::IngestReport>, Status> {
+        let record = raw_record(request.into_inner())?;
+        Ok(Response::new(self.state.ingest(vec![record]).into()))
+    }
+
+    async fn submit_batch(
+        &self,
+        request: Request<proto::TransactionBatch>,
+    ) -> Result<Response<proto::IngestReport>, Status> {
+        let records = request
+            .into_inner()
+            .transactions
+            .into_iter()
+            .map(raw_record)
+            .collect::<Result<_, _>>()?;
+        Ok(Response::new(self.state.ingest(records).into()))
+    }
+
+    async fn get_account(
+        &self,
+        request: Request<proto::GetAccountRequest>,
+    ) -> Result<Response<proto::Account>, Status> {
+        let client = id(request.into_inner().client, "client")?;
+        self.state
+            .accounts()
+            .account(client)
+            .map(|account| Response::new(AccountView::from(&account).into()))
+            .ok_or_else(|| Status::not_found(format!("client {client} has no account")))
+    }
+
+    type StreamSnapshotStream = BoxStream<'static, Result<proto::Account, Status>>;
+
+    async fn stream_snapshot(
+        &self,
+        _request: Request<proto::SnapshotRequest>,
+    ) -> Result<Response<Self::StreamSnapshotStream>, Status> {
+        // As for CSV snapshots, collect the ids first and read each account
+        // when it is sent, so processing continues while the client reads
+        let mut client_ids = Vec::new();
+        self.state
+            .accounts()
+            .for_each_account(&mut |account| client_ids.push(account.client_id()));
+
+        let state = self.state.clone();
+        let accounts = futures::stream::iter(client_ids).filter_map(move |client| {
+            let account = state.accounts().account(client);
+            async move { account.map(|account| Ok(AccountView::from(&account).into())) }
+        });
+        Ok(Response::new(accounts.boxed()))
+    }
+}
+
+/// The record a `Transaction` message stands for
+fn raw_record(tx: proto::Transaction) -> Result<RawTransactionRecord, Status> {
+    Ok(RawTransactionRecord {
+        tx_type: tx.r#type,
+        client: id(tx.client, "client")?,
+        tx: id(tx.tx, "tx")?,
+        amount: tx.amount,
+        to: tx.to.map(|to| id(to, "to")).transpose()?,
+        currency: tx.currency,
+        idempotency_key: tx.idempotency_key,
+        tag: tx.tag,
+        ..Default::default()
+    })
+}
+
+/// `value` as a client or transaction id, failing if the id type is narrower
+fn id<T: TryFrom<u64>>(value: impl Into<u64>, name: &str) -> Result<T, Status> {
+    let value = value.into();
+    T::try_from(value)
+        .map_err(|_| Status::invalid_argument(format!("{name} {value} is out of range")))
+}
+
+impl From<IngestReport> for proto::IngestReport {
+    fn from(report: IngestReport) -> Self {
+        Self {
+            applied: report.applied as u64,
+            rejected: report
+                .rejected
+                .into_iter()
+                .map(|rejection| proto::Rejection {
+                    index: rejection.index as u64,
+                    error: rejection.error,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<AccountView> for proto::Account {
+    // Widening the client id is a no-op with `wide-client-ids`
+    #[allow(clippy::useless_conversion)]
+    fn from(view: AccountView) -> Self {
+        Self {
+            client: view.client.into(),
+            available: view.available,
+            held: view.held,
+            total: view.total,
+            locked: view.locked,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tonic::Code;
+
+    use super::proto::pay_client::PayClient;
+    use super::*;
+    use crate::storage::ConcurrentAccountManager;
+
+    fn pay_service() -> PayService {
+        PayService::new(ServerState::new(Arc::new(ConcurrentAccountManager::new())))
+    }
+
+    fn transaction(
+        tx_type: &str,
+        client: u32,
+        tx: u64,
+        amount: Option<&str>,
+    ) -> proto::Transaction {
+        proto::Transaction {
+            r#type: tx_type.to_string(),
+            client,
+            tx,
+            amount: amount.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_batch_reports_applied_and_rejected() {
+        let service = pay_service();
+        let batch = proto::TransactionBatch {
+            transactions: vec![
+                transaction("deposit", 1, 1, Some("2.5")),
+                transaction("withdrawal", 1, 2, Some("5.0")),
+                transaction("withdrawal", 1, 3, Some("1.0")),
+            ],
+        };
+
+        let report = service
+            .submit_batch(Request::new(batch))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(report.applied, 2);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].index, 1);
+    }
+
+    #[tokio::test]
+    async fn get_account_after_submit_transaction() {
+        let service = pay_service();
+        let status = service
+            .get_account(Request::new(proto::GetAccountRequest { client: 1 }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        service
+            .submit_transaction(Request::new(transaction("deposit", 1, 1, Some("1.5"))))
+            .await
+            .unwrap();
+
+        let account = service
+            .get_account(Request::new(proto::GetAccountRequest { client: 1 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            account,
+            proto::Account {
+                client: 1,
+                available: "1.5000".to_string(),
+                held: "0.0000".to_string(),
+                total: "1.5000".to_string(),
+                locked: false,
+            }
+        );
+    }
+
+    #[cfg(not(feature = "wide-client-ids"))]
+    #[tokio::test]
+    async fn out_of_range_ids_reject_the_request() {
+        let service = pay_service();
+        let batch = proto::TransactionBatch {
+            transactions: vec![
+                transaction("deposit", 1, 1, Some("1")),
+                transaction("deposit", 70_000, 2, Some("1")),
+            ],
+        };
+
+        let status = service.submit_batch(Request::new(batch)).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "client 70000 is out of range");
+        assert!(service.state.accounts().account(1).is_none());
+    }
+
+    #[tokio::test]
+    async fn snapshot_streams_every_account_over_the_network() {
+        let state = ServerState::new(Arc::new(ConcurrentAccountManager::new()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service(state))
+                .serve_with_incoming(TcpIncoming::from(listener)),
+        );
+
+        let mut client = PayClient::connect(format!("http://{addr}")).await.unwrap();
+        client
+            .submit_batch(proto::TransactionBatch {
+                transactions: vec![
+                    transaction("deposit", 1, 1, Some("2")),
+                    transaction("deposit", 2, 2, Some("3")),
+                    transaction("dispute", 2, 2, None),
+                ],
+            })
+            .await
+            .unwrap();
+
+        let mut accounts: Vec<proto::Account> = client
+            .stream_snapshot(proto::SnapshotRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        accounts.sort_by_key(|account| account.client);
+
+        let balances: Vec<_> = accounts
+            .iter()
+            .map(|account| {
+                (
+                    account.client,
+                    account.available.as_str(),
+                    account.held.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(balances, [(1, "2.0000", "0.0000"), (2, "0.0000", "3.0000")]);
+    }
+}
@@ -0,0 +1,35 @@
+//! REST ingestion server (requires the `server` feature)
+//!
+//! Runs the engine as a microservice over a shared `ConcurrentAccountManager`:
+//!
+//! - `POST /transactions`: apply one transaction or a batch (JSON array)
+//! - `GET /accounts/{id}`: current balances of one client
+//! - `GET /snapshot`: CSV snapshot of all accounts (same format as the CLI)
+//!
+//! `ServerState::export_state` and `import_state` move the storage between
+//! instances for blue/green redeploys.
+//!
+//! With the `grpc` feature, `grpc::serve` offers the same operations as a
+//! tonic service (see `proto/pay.proto`).
+//!
+//! Transactions use the CSV column names as JSON fields, with amounts as
+//! decimal strings:
+//!
+//! ```json
+//! [
+//!   {"type": "deposit", "client": 1, "tx": 1, "amount": "2.5"},
+//!   {"type": "withdrawal", "client": 1, "tx": 2, "amount": "1.0"}
+//! ]
+//! ```
+//!
+//! # Example
+//! ```rust,ignore
+//! let accounts = Arc::new(ConcurrentAccountManager::new());
+//! pay::server::serve("127.0.0.1:8080", ServerState::new(accounts)).await?;
+//! ```
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod routes;
+
+pub use routes::{AccountView, IngestReport, Rejection, ServerState, router, serve};
@@ -0,0 +1,381 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, ToSocketAddrs};
+
+use crate::domain::{AmountType, ClientAccount, ClientId, FixedPoint};
+use crate::engine::TransactionProcessor;
+use crate::io::RawTransactionRecord;
+#[cfg(feature = "metrics")]
+use crate::metrics::{IO_ERROR_KIND, MetricsRegistry};
+use crate::storage::{
+    ClientAccountManager, ConcurrentAccountManager, ConcurrentTransactionStore, StateCounts,
+    StorageError, export_state, import_state,
+};
+
+/// Processor type used by the server, over shared concurrent storage
+type SharedProcessor = TransactionProcessor<
+    FixedPoint,
+    Arc<ConcurrentAccountManager<FixedPoint>>,
+    Arc<ConcurrentTransactionStore<FixedPoint>>,
+>;
+
+/// Shared storage and engine settings for all requests
+#[derive(Clone)]
+pub struct ServerState {
+    accounts: Arc<ConcurrentAccountManager<FixedPoint>>,
+    transactions: Arc<ConcurrentTransactionStore<FixedPoint>>,
+    allow_admin_ops: bool,
+    #[cfg(feature = "metrics")]
+    metrics: Option<MetricsRegistry>,
+}
+
+impl ServerState {
+    /// Create server state over a shared account manager
+    pub fn new(accounts: Arc<ConcurrentAccountManager<FixedPoint>>) -> Self {
+        Self {
+            accounts,
+            transactions: Arc::new(ConcurrentTransactionStore::new()),
+            allow_admin_ops: false,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Accept administrative operations over HTTP (defaults to false)
+    pub fn with_admin_ops(mut self, enabled: bool) -> Self {
+        self.allow_admin_ops = enabled;
+        self
+    }
+
+    /// Report ingest metrics and serve them at `GET /metrics`
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: MetricsRegistry) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Write the accounts and transaction records to `writer` (see `export_state`)
+    ///
+    /// For blue/green redeploys: stop sending traffic to this instance,
+    /// export, and `import_state` into the replacement before it starts
+    /// serving, so disputes of earlier transactions keep working there.
+    pub fn export_state(&self, writer: impl std::io::Write) -> std::io::Result<StateCounts> {
+        export_state(&*self.accounts, &*self.transactions, writer)
+    }
+
+    /// Load accounts and transaction records written by `export_state`
+    pub fn import_state(&self, reader: impl std::io::Read) -> Result<StateCounts, StorageError> {
+        import_state(reader, &*self.accounts, &*self.transactions)
+    }
+
+    /// The shared accounts
+    #[cfg(feature = "grpc")]
+    pub(super) fn accounts(&self) -> &ConcurrentAccountManager<FixedPoint> {
+        &self.accounts
+    }
+
+    /// Apply `records` in order; a rejected record does not stop the batch
+    pub(super) fn ingest(&self, records: Vec<RawTransactionRecord>) -> IngestReport {
+        let mut processor = self.processor();
+        let mut report = IngestReport::default();
+
+        for (index, record) in records.into_iter().enumerate() {
+            let parsed = record.parse::<FixedPoint>();
+
+            #[cfg(feature = "metrics")]
+            if let (Err(_), Some(metrics)) = (&parsed, &self.metrics) {
+                metrics.record_error(IO_ERROR_KIND);
+            }
+
+            let result = parsed
+                .map_err(|e| e.to_string())
+                .and_then(|tx| processor.process_transaction(tx).map_err(|e| e.to_string()));
+
+            match result {
+                Ok(()) => report.applied += 1,
+                Err(error) => report.rejected.push(Rejection { index, error }),
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_accounts(&self.accounts);
+        }
+
+        report
+    }
+
+    /// Processor over the shared storage; cheap enough to create per request
+    fn processor(&self) -> SharedProcessor {
+        let processor = TransactionProcessor::new(self.accounts.clone(), self.transactions.clone())
+            .with_admin_ops(self.allow_admin_ops);
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            return processor.with_metrics(metrics.clone());
+        }
+
+        processor
+    }
+}
+
+/// Request body for `POST /transactions`: a single record or a batch
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TransactionBatch {
+    Many(Vec<RawTransactionRecord>),
+    One(RawTransactionRecord),
+}
+
+/// Response body for `POST /transactions`
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IngestReport {
+    pub applied: usize,
+    pub rejected: Vec<Rejection>,
+}
+
+/// A rejected record, by its position in the request batch
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Rejection {
+    pub index: usize,
+    pub error: String,
+}
+
+/// Response body for `GET /accounts/{id}`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountView {
+    pub client: ClientId,
+    pub available: String,
+    pub held: String,
+    pub total: String,
+    pub locked: bool,
+}
+
+impl<A: AmountType> From<&ClientAccount<A>> for AccountView {
+    fn from(account: &ClientAccount<A>) -> Self {
+        Self {
+            client: account.client_id(),
+            available: account.available().to_decimal_string(),
+            held: account.held().to_decimal_string(),
+            total: account.total().to_decimal_string(),
+            locked: account.is_locked(),
+        }
+    }
+}
+
+/// Build the REST router over shared state
+pub fn router(state: ServerState) -> Router {
+    let router = Router::new()
+        .route("/transactions", post(post_transactions))
+        .route("/accounts/{id}", get(get_account))
+        .route("/snapshot", get(get_snapshot));
+
+    #[cfg(feature = "metrics")]
+    let router = router.route("/metrics", get(get_metrics));
+
+    router.with_state(state)
+}
+
+/// Bind to `addr` and serve the REST API until the task is cancelled
+pub async fn serve(addr: impl ToSocketAddrs, state: ServerState) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, router(state)).await
+}
+
+async fn post_transactions(
+    State(state): State<ServerState>,
+    Json(batch): Json<TransactionBatch>,
+) -> Json<IngestReport> {
+    let records = match batch {
+        TransactionBatch::Many(records) => records,
+        TransactionBatch::One(record) => vec![record],
+    };
+    Json(state.ingest(records))
+}
+
+async fn get_account(
+    State(state): State<ServerState>,
+    Path(client_id): Path<ClientId>,
+) -> Result<Json<AccountView>, StatusCode> {
+    state
+        .accounts
+        .account(client_id)
+        .map(|account| Json(AccountView::from(&account)))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_snapshot(State(state): State<ServerState>) -> impl IntoResponse {
+    let mut output = Vec::new();
+    match state.accounts.snapshot(&mut output).await {
+        Ok(()) => Ok(([(header::CONTENT_TYPE, "text/csv")], output)),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+#[cfg(feature = "metrics")]
+async fn get_metrics(State(state): State<ServerState>) -> impl IntoResponse {
+    match &state.metrics {
+        Some(metrics) => Ok((
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            metrics.render(),
+        )),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Vec<u8>) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    fn post(body: &str) -> Request<Body> {
+        Request::post("/transactions")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn post_batch_reports_applied_and_rejected() {
+        let app = router(ServerState::new(Arc::new(ConcurrentAccountManager::new())));
+
+        let (status, body) = send(
+            &app,
+            post(
+                r#"[
+                    {"type": "deposit", "client": 1, "tx": 1, "amount": "2.5"},
+                    {"type": "withdrawal", "client": 1, "tx": 2, "amount": "5.0"},
+                    {"type": "withdrawal", "client": 1, "tx": 3, "amount": "1.0"}
+                ]"#,
+            ),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let report: IngestReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report.applied, 2);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].index, 1);
+    }
+
+    #[tokio::test]
+    async fn get_account_after_single_post() {
+        let app = router(ServerState::new(Arc::new(ConcurrentAccountManager::new())));
+
+        let (status, _) = send(&app, get("/accounts/1")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        send(
+            &app,
+            post(r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}"#),
+        )
+        .await;
+
+        let (status, body) = send(&app, get("/accounts/1")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_json::from_slice::<AccountView>(&body).unwrap(),
+            AccountView {
+                client: 1,
+                available: "1.5000".to_string(),
+                held: "0.0000".to_string(),
+                total: "1.5000".to_string(),
+                locked: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn get_snapshot_returns_csv() {
+        let app = router(ServerState::new(Arc::new(ConcurrentAccountManager::new())));
+        send(
+            &app,
+            post(r#"{"type": "deposit", "client": 2, "tx": 1, "amount": "3"}"#),
+        )
+        .await;
+
+        let (status, body) = send(&app, get("/snapshot")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "client,available,held,total,locked\n2,3.0000,0.0000,3.0000,false\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn imported_state_keeps_earlier_deposits_disputable() {
+        let blue = ServerState::new(Arc::new(ConcurrentAccountManager::new()));
+        send(
+            &router(blue.clone()),
+            post(r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "2"}"#),
+        )
+        .await;
+        let mut exported = Vec::new();
+        blue.export_state(&mut exported).unwrap();
+
+        let green = ServerState::new(Arc::new(ConcurrentAccountManager::new()));
+        let counts = green.import_state(exported.as_slice()).unwrap();
+        assert_eq!(
+            counts,
+            StateCounts {
+                accounts: 1,
+                records: 1
+            }
+        );
+        let app = router(green);
+        send(&app, post(r#"{"type": "dispute", "client": 1, "tx": 1}"#)).await;
+
+        let (_, body) = send(&app, get("/accounts/1")).await;
+        let view: AccountView = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            (view.available.as_str(), view.held.as_str()),
+            ("0.0000", "2.0000")
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn get_metrics_reports_ingest() {
+        let metrics = MetricsRegistry::new();
+        let state = ServerState::new(Arc::new(ConcurrentAccountManager::new()))
+            .with_metrics(metrics.clone());
+        let app = router(state);
+
+        send(
+            &app,
+            post(
+                r#"[
+                    {"type": "deposit", "client": 1, "tx": 1, "amount": "2.5"},
+                    {"type": "refund", "client": 1, "tx": 2, "amount": "1.0"}
+                ]"#,
+            ),
+        )
+        .await;
+
+        let (status, body) = send(&app, get("/metrics")).await;
+        assert_eq!(status, StatusCode::OK);
+        let text = String::from_utf8(body).unwrap();
+        assert!(text.contains("pay_transactions_total{type=\"deposit\"} 1\n"));
+        assert!(text.contains("pay_errors_total{kind=\"io\"} 1\n"));
+        assert!(text.contains("pay_accounts 1\n"));
+    }
+}
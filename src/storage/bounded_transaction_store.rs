@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::traits::TransactionStoreManager;
+use crate::domain::{AmountType, TransactionId, TransactionRecord};
+
+/// Which record to evict when a bounded store is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently inserted or looked-up record
+    Lru,
+
+    /// Evict the oldest inserted record (lookups do not affect order)
+    Fifo,
+}
+
+/// Size-bounded transaction store with LRU or FIFO eviction
+///
+/// Keeps at most `max_records` records; inserting beyond that evicts one record
+/// according to the eviction policy. Evicted transactions can no longer be
+/// disputed, so size the store to cover the expected dispute window and watch
+/// `evictions()` to tune it.
+pub struct BoundedTransactionStore<A: AmountType> {
+    inner: Mutex<BoundedInner<A>>,
+    max_records: usize,
+    policy: EvictionPolicy,
+    evictions: AtomicU64,
+}
+
+struct BoundedInner<A: AmountType> {
+    /// tx_id -> (record, position in eviction order)
+    records: HashMap<TransactionId, (TransactionRecord<A>, u64)>,
+    /// position -> tx_id, oldest first
+    order: BTreeMap<u64, TransactionId>,
+    next_position: u64,
+}
+
+impl<A: AmountType> BoundedInner<A> {
+    fn next_position(&mut self) -> u64 {
+        let position = self.next_position;
+        self.next_position += 1;
+        position
+    }
+
+    /// Move a record to the most-recent end of the eviction order
+    fn touch(&mut self, tx_id: TransactionId) {
+        let position = self.next_position();
+        if let Some((_, old_position)) = self.records.get_mut(&tx_id) {
+            self.order.remove(old_position);
+            *old_position = position;
+            self.order.insert(position, tx_id);
+        }
+    }
+}
+
+impl<A: AmountType> BoundedTransactionStore<A> {
+    /// Create a store holding at most `max_records` records (minimum 1)
+    pub fn new(max_records: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            inner: Mutex::new(BoundedInner {
+                records: HashMap::new(),
+                order: BTreeMap::new(),
+                next_position: 0,
+            }),
+            max_records: max_records.max(1),
+            policy,
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Maximum number of records kept
+    pub fn capacity(&self) -> usize {
+        self.max_records
+    }
+
+    /// Eviction policy in use
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Number of records currently stored
+    pub fn len(&self) -> usize {
+        self.lock().records.len()
+    }
+
+    /// Check if the store holds no records
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total number of records evicted so far
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BoundedInner<A>> {
+        // A panic while holding the lock cannot leave the maps inconsistent
+        // in a way that matters for lookups, so recover from poisoning
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn insert_record(&self, tx_id: TransactionId, record: TransactionRecord<A>) {
+        let mut inner = self.lock();
+
+        if let Some((existing, _)) = inner.records.get_mut(&tx_id) {
+            *existing = record;
+            inner.touch(tx_id);
+            return;
+        }
+
+        if inner.records.len() >= self.max_records
+            && let Some((_, evicted)) = inner.order.pop_first()
+        {
+            inner.records.remove(&evicted);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let position = inner.next_position();
+        inner.records.insert(tx_id, (record, position));
+        inner.order.insert(position, tx_id);
+    }
+
+    fn get_record(&self, tx_id: TransactionId) -> Option<TransactionRecord<A>> {
+        let mut inner = self.lock();
+        let record = inner.records.get(&tx_id).map(|(r, _)| r.clone())?;
+
+        if self.policy == EvictionPolicy::Lru {
+            inner.touch(tx_id);
+        }
+
+        Some(record)
+    }
+}
+
+impl<A: AmountType> TransactionStoreManager<A> for BoundedTransactionStore<A> {
+    fn insert(&self, tx_id: TransactionId, record: TransactionRecord<A>) {
+        self.insert_record(tx_id, record);
+    }
+
+    fn get(&self, tx_id: TransactionId) -> Option<TransactionRecord<A>> {
+        self.get_record(tx_id)
+    }
+
+    fn contains(&self, tx_id: TransactionId) -> bool {
+        self.lock().records.contains_key(&tx_id)
+    }
+
+    fn for_each_record(&self, visit: &mut dyn FnMut(TransactionId, &TransactionRecord<A>)) {
+        // Visiting does not count as a lookup, so LRU order is unchanged
+        for (tx_id, (record, _)) in &self.lock().records {
+            visit(*tx_id, record);
+        }
+    }
+
+    fn resident_records(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ClientId, FixedPoint};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn record(client_id: ClientId) -> TransactionRecord<FixedPoint> {
+        TransactionRecord::new(client_id, FixedPoint::from_raw(1_000))
+    }
+
+    #[test]
+    fn new_store_is_empty() {
+        let store = BoundedTransactionStore::<FixedPoint>::new(10, EvictionPolicy::Lru);
+        assert!(store.is_empty());
+        assert_eq!(store.capacity(), 10);
+        assert_eq!(store.evictions(), 0);
+    }
+
+    #[test]
+    fn zero_capacity_is_clamped_to_one() {
+        let store = BoundedTransactionStore::<FixedPoint>::new(0, EvictionPolicy::Fifo);
+        assert_eq!(store.capacity(), 1);
+    }
+
+    #[test]
+    fn stays_within_capacity_and_counts_evictions() {
+        let store = BoundedTransactionStore::new(3, EvictionPolicy::Fifo);
+
+        for tx_id in 1..=5 {
+            store.insert(tx_id, record(1));
+        }
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.evictions(), 2);
+        assert!(!store.contains(1));
+        assert!(!store.contains(2));
+        assert!(store.contains(5));
+    }
+
+    #[test]
+    fn fifo_ignores_lookups() {
+        let store = BoundedTransactionStore::new(2, EvictionPolicy::Fifo);
+        store.insert(1, record(1));
+        store.insert(2, record(2));
+
+        // Lookup does not protect tx 1 from eviction
+        assert!(store.get(1).is_some());
+        store.insert(3, record(3));
+
+        assert!(!store.contains(1));
+        assert!(store.contains(2));
+        assert!(store.contains(3));
+    }
+
+    #[test]
+    fn lru_lookup_refreshes_record() {
+        let store = BoundedTransactionStore::new(2, EvictionPolicy::Lru);
+        store.insert(1, record(1));
+        store.insert(2, record(2));
+
+        // Lookup makes tx 2 the eviction candidate
+        assert!(store.get(1).is_some());
+        store.insert(3, record(3));
+
+        assert!(store.contains(1));
+        assert!(!store.contains(2));
+        assert!(store.contains(3));
+    }
+
+    #[test]
+    fn reinserting_existing_id_does_not_evict() {
+        let store = BoundedTransactionStore::new(2, EvictionPolicy::Lru);
+        store.insert(1, record(1));
+        store.insert(2, record(2));
+        store.insert(1, record(9));
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.evictions(), 0);
+        assert_eq!(store.get(1).unwrap().client_id, 9);
+    }
+
+    #[test]
+    fn shared_store_inserts_through_arc() {
+        let store = Arc::new(BoundedTransactionStore::<FixedPoint>::new(
+            1_000,
+            EvictionPolicy::Lru,
+        ));
+
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let store = Arc::clone(&store);
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        store.insert(t * 500 + i, record(1));
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(store.len(), 1_000);
+        assert_eq!(store.evictions(), 1_000);
+    }
+}
@@ -1,21 +1,180 @@
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use dashmap::{DashMap, Entry};
+use dashmap::{DashMap, Entry, SharedValue};
 use tokio::io::AsyncWrite;
 
+#[cfg(feature = "diagnostics")]
+use super::diagnostics::{ContentionReport, ContentionTracker};
 use super::error::StorageError;
+use super::query::QueryHandle;
+use super::snapshot_format::{SnapshotFormat, SnapshotWriter};
 use super::traits::{ClientAccountEntry, ClientAccountManager};
-use crate::domain::{AmountType, ClientAccount, DomainError};
+use crate::domain::{AmountType, ClientAccount, ClientId, DomainError};
 
 /// Concurrent in-memory account manager using DashMap
 pub struct ConcurrentAccountManager<A: AmountType> {
-    accounts: DashMap<u16, ClientAccount<A>>,
+    accounts: Arc<DashMap<ClientId, ClientAccount<A>>>,
+    #[cfg(feature = "diagnostics")]
+    contention: ContentionTracker,
 }
 
 impl<A: AmountType> ConcurrentAccountManager<A> {
     /// Create a new empty concurrent account manager
     pub fn new() -> Self {
+        Self::from_map(DashMap::new())
+    }
+
+    /// Create an empty manager with room for `capacity` accounts spread over
+    /// `shards` lock shards
+    ///
+    /// Pre-sizing avoids rehashing while a known number of clients is loaded.
+    /// More shards reduce lock contention between processing shards; the
+    /// count is rounded up to a power of two (minimum 2), and the default is
+    /// four per CPU.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let mgr = ConcurrentAccountManager::<FixedPoint>::with_capacity_and_shards(65_536, 64);
+    /// ```
+    pub fn with_capacity_and_shards(capacity: usize, shards: usize) -> Self {
+        let shards = shards.max(2).next_power_of_two();
+        Self::from_map(DashMap::with_capacity_and_shard_amount(capacity, shards))
+    }
+
+    fn from_map(accounts: DashMap<ClientId, ClientAccount<A>>) -> Self {
         Self {
-            accounts: DashMap::new(),
+            #[cfg(feature = "diagnostics")]
+            contention: ContentionTracker::new(accounts.shards().len()),
+            accounts: Arc::new(accounts),
+        }
+    }
+
+    /// Access counts and lock waits per DashMap shard, and the busiest
+    /// clients, since the manager was created (requires the `diagnostics`
+    /// feature)
+    ///
+    /// Only accesses through `entry` and `try_update_pair` are counted;
+    /// snapshots and query handles are not.
+    #[cfg(feature = "diagnostics")]
+    pub fn contention_report(&self) -> ContentionReport {
+        self.contention.report()
+    }
+
+    /// `self.accounts.entry(client_id)`, counted under `diagnostics`
+    fn lock_entry(&self, client_id: ClientId) -> Entry<'_, ClientId, ClientAccount<A>> {
+        #[cfg(feature = "diagnostics")]
+        return self.contention.entry(&self.accounts, client_id);
+        #[cfg(not(feature = "diagnostics"))]
+        self.accounts.entry(client_id)
+    }
+
+    /// Copy of an existing account, or None if the client has no account yet
+    pub fn account(&self, client_id: ClientId) -> Option<ClientAccount<A>> {
+        self.accounts
+            .get(&client_id)
+            .map(|entry| entry.value().clone())
+    }
+
+    /// Read-only handle to the live accounts, for use alongside processing
+    pub fn query_handle(&self) -> QueryHandle<A> {
+        QueryHandle::new(self.accounts.clone())
+    }
+}
+
+impl<A: AmountType> ConcurrentAccountManager<A> {
+    /// Pair update for two accounts living in different DashMap shards
+    ///
+    /// Shard locks are always taken in ascending shard order so that two
+    /// concurrent transfers in opposite directions cannot deadlock.
+    fn try_update_pair_across_shards<F>(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        let (first, second) =
+            if self.accounts.determine_map(&first_id) < self.accounts.determine_map(&second_id) {
+                let first = self.lock_entry(first_id);
+                let second = self.lock_entry(second_id);
+                (first, second)
+            } else {
+                let second = self.lock_entry(second_id);
+                let first = self.lock_entry(first_id);
+                (first, second)
+            };
+
+        // Work on copies so a failed update leaves both accounts untouched
+        let mut first_account = Self::entry_account(&first, first_id);
+        let mut second_account = Self::entry_account(&second, second_id);
+        update_fn(&mut first_account, &mut second_account)?;
+
+        first.insert(first_account);
+        second.insert(second_account);
+        Ok(())
+    }
+
+    /// Pair update for two accounts living in the same DashMap shard
+    ///
+    /// A shard lock is not reentrant, so both accounts are read and written
+    /// under a single write guard on the raw shard table.
+    fn try_update_pair_in_shard<F>(
+        &self,
+        shard_idx: usize,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        let hasher = self.accounts.hasher();
+        let lock = &self.accounts.shards()[shard_idx];
+        #[cfg(feature = "diagnostics")]
+        let mut shard = self
+            .contention
+            .write_shard(lock, shard_idx, [first_id, second_id]);
+        #[cfg(not(feature = "diagnostics"))]
+        let mut shard = lock.write();
+
+        let read = |client_id: ClientId| {
+            shard
+                .get(hasher.hash_one(client_id), |(k, _)| *k == client_id)
+                .map(|(_, v)| v.get().clone())
+                .unwrap_or_else(|| ClientAccount::new(client_id))
+        };
+
+        let mut first_account = read(first_id);
+        let mut second_account = read(second_id);
+        update_fn(&mut first_account, &mut second_account)?;
+
+        for account in [first_account, second_account] {
+            let client_id = account.client_id();
+            let hash = hasher.hash_one(client_id);
+            match shard.get_mut(hash, |(k, _)| *k == client_id) {
+                Some((_, v)) => *v.get_mut() = account,
+                None => {
+                    shard.insert(hash, (client_id, SharedValue::new(account)), |(k, _)| {
+                        hasher.hash_one(k)
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry_account(
+        entry: &Entry<'_, ClientId, ClientAccount<A>>,
+        client_id: ClientId,
+    ) -> ClientAccount<A> {
+        match entry {
+            Entry::Occupied(e) => e.get().clone(),
+            Entry::Vacant(_) => ClientAccount::new(client_id),
         }
     }
 }
@@ -28,14 +187,18 @@ impl<A: AmountType> Default for ConcurrentAccountManager<A> {
 
 /// Entry for concurrent access
 pub struct ConcurrentEntry<'a, A: AmountType> {
-    client_id: u16,
-    accounts: &'a DashMap<u16, ClientAccount<A>>,
+    client_id: ClientId,
+    manager: &'a ConcurrentAccountManager<A>,
 }
 
 impl<'a, A: AmountType> ClientAccountEntry<'a, A> for ConcurrentEntry<'a, A> {
     fn read(&self) -> ClientAccount<A> {
-        self.accounts
-            .get(&self.client_id)
+        let accounts = &self.manager.accounts;
+        #[cfg(feature = "diagnostics")]
+        let account = self.manager.contention.get(accounts, self.client_id);
+        #[cfg(not(feature = "diagnostics"))]
+        let account = accounts.get(&self.client_id);
+        account
             .map(|r| r.value().clone())
             .unwrap_or_else(|| ClientAccount::new(self.client_id))
     }
@@ -45,7 +208,7 @@ impl<'a, A: AmountType> ClientAccountEntry<'a, A> for ConcurrentEntry<'a, A> {
         F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>,
     {
         // Use DashMap's entry API correctly
-        let entry = self.accounts.entry(self.client_id);
+        let entry = self.manager.lock_entry(self.client_id);
         match entry {
             Entry::Occupied(mut e) => {
                 let account = e.get_mut();
@@ -69,46 +232,67 @@ impl<A: AmountType> ClientAccountManager<A> for ConcurrentAccountManager<A> {
     where
         Self: 'a;
 
-    fn entry(&self, client_id: u16) -> Result<Self::Entry<'_>, StorageError> {
+    fn entry(&self, client_id: ClientId) -> Result<Self::Entry<'_>, StorageError> {
         Ok(ConcurrentEntry {
             client_id,
-            accounts: &self.accounts,
+            manager: self,
         })
     }
 
-    fn get(&self, _client_id: u16) -> Result<Option<&ClientAccount<A>>, StorageError> {
-        // DashMap doesn't allow direct & access due to internal locking
-        // Return None for now - read() method on Entry is the preferred way
-        Ok(None)
+    fn try_update_pair<F>(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        if first_id == second_id {
+            return Err(DomainError::SelfTransfer.into());
+        }
+
+        let first_shard = self.accounts.determine_map(&first_id);
+        if first_shard == self.accounts.determine_map(&second_id) {
+            self.try_update_pair_in_shard(first_shard, first_id, second_id, update_fn)
+        } else {
+            self.try_update_pair_across_shards(first_id, second_id, update_fn)
+        }
     }
 
-    async fn snapshot<W>(&self, mut writer: W) -> Result<(), StorageError>
+    fn get(&self, client_id: ClientId) -> Result<Option<ClientAccount<A>>, StorageError> {
+        Ok(self.account(client_id))
+    }
+
+    async fn snapshot_with_format<W>(
+        &self,
+        writer: W,
+        format: &SnapshotFormat,
+    ) -> Result<(), StorageError>
     where
         W: AsyncWrite + Unpin + Send,
     {
-        use tokio::io::AsyncWriteExt;
-
-        // Write header
+        let mut writer = SnapshotWriter::new(writer);
         writer
-            .write_all(b"client,available,held,total,locked\n")
-            .await?;
+            .buffer()
+            .extend_from_slice(format.header().as_bytes());
 
-        // Iterate and write each account
-        // DashMap holds brief per-shard locks during iteration
-        for entry in self.accounts.iter() {
-            let account = entry.value();
-            let line = format!(
-                "{},{},{},{},{}\n",
-                account.client_id(),
-                account.available().to_decimal_string(),
-                account.held().to_decimal_string(),
-                account.total().to_decimal_string(),
-                account.is_locked()
-            );
-            writer.write_all(line.as_bytes()).await?;
+        // Collect keys first so no shard lock is held across an await; each
+        // account is then read under its own brief lock, so processing can
+        // continue while the snapshot is written
+        let client_ids: Vec<ClientId> = self.accounts.iter().map(|entry| *entry.key()).collect();
+
+        for client_id in client_ids {
+            if let Some(entry) = self.accounts.get(&client_id) {
+                format.write_row(entry.value(), writer.buffer())?;
+            }
+            // Let other tasks run between chunks on large snapshots
+            if writer.write_if_full().await? {
+                tokio::task::yield_now().await;
+            }
         }
 
-        writer.flush().await?;
+        writer.finish().await?;
         Ok(())
     }
 
@@ -118,34 +302,11 @@ impl<A: AmountType> ClientAccountManager<A> for ConcurrentAccountManager<A> {
         // For now, return empty iterator (snapshot method handles output correctly)
         Box::new(std::iter::empty())
     }
-}
 
-// Implement ClientAccountManager for Arc<ConcurrentAccountManager> to enable sharing
-// This allows multiple threads/tasks to share the same account manager
-#[async_trait]
-impl<A: AmountType> ClientAccountManager<A> for std::sync::Arc<ConcurrentAccountManager<A>> {
-    type Entry<'a>
-        = ConcurrentEntry<'a, A>
-    where
-        Self: 'a;
-
-    fn entry(&self, client_id: u16) -> Result<Self::Entry<'_>, StorageError> {
-        (**self).entry(client_id)
-    }
-
-    fn get(&self, client_id: u16) -> Result<Option<&ClientAccount<A>>, StorageError> {
-        (**self).get(client_id)
-    }
-
-    async fn snapshot<W>(&self, writer: W) -> Result<(), StorageError>
-    where
-        W: AsyncWrite + Unpin + Send,
-    {
-        (**self).snapshot(writer).await
-    }
-
-    fn iter(&self) -> Box<dyn Iterator<Item = &ClientAccount<A>> + Send + '_> {
-        (**self).iter()
+    fn for_each_account(&self, visit: &mut dyn FnMut(&ClientAccount<A>)) {
+        for entry in self.accounts.iter() {
+            visit(entry.value());
+        }
     }
 }
 
@@ -153,9 +314,40 @@ impl<A: AmountType> ClientAccountManager<A> for std::sync::Arc<ConcurrentAccount
 mod tests {
     use super::*;
     use crate::domain::{FixedPoint, operations};
+    use crate::storage::snapshot_format::SNAPSHOT_BUFFER_SIZE;
     use std::sync::Arc;
     use std::thread;
 
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn contention_report_counts_entry_and_pair_accesses() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::with_capacity_and_shards(16, 4);
+        let deposit = |account: &mut ClientAccount<FixedPoint>| {
+            operations::apply_deposit(account, FixedPoint::from_raw(10_000))
+        };
+        for _ in 0..3 {
+            manager.entry(1).unwrap().try_update(deposit).unwrap();
+        }
+        manager.entry(2).unwrap().read();
+        manager.try_update_pair(1, 2, |_, _| Ok(())).unwrap();
+
+        let report = manager.contention_report();
+        assert_eq!(report.shards.len(), 4);
+        assert_eq!(report.total_accesses(), 6);
+        assert_eq!(report.hottest_clients, vec![(1, 4), (2, 2)]);
+    }
+
+    #[test]
+    fn with_capacity_and_shards_rounds_shards_to_a_power_of_two() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::with_capacity_and_shards(4_096, 48);
+        assert_eq!(manager.accounts.shards().len(), 64);
+        assert!(manager.accounts.capacity() >= 4_096);
+
+        let single = ConcurrentAccountManager::<FixedPoint>::with_capacity_and_shards(0, 1);
+        assert_eq!(single.accounts.shards().len(), 2);
+        assert_eq!(single.entry(7).unwrap().read().client_id(), 7);
+    }
+
     #[test]
     fn entry_creates_account_if_not_exists() {
         let manager = ConcurrentAccountManager::<FixedPoint>::new();
@@ -184,6 +376,41 @@ mod tests {
         assert_eq!(account.available(), FixedPoint::from_raw(5_000));
     }
 
+    #[test]
+    fn account_returns_none_until_created() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        assert!(manager.account(1).is_none());
+
+        manager
+            .entry(1)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(5_000)))
+            .unwrap();
+
+        assert_eq!(
+            manager.account(1).unwrap().available(),
+            FixedPoint::from_raw(5_000)
+        );
+    }
+
+    #[test]
+    fn get_returns_existing_accounts_without_creating_entries() {
+        let manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        assert_eq!(manager.get(1).unwrap(), None);
+        assert_eq!(manager.entry(1).unwrap().read().total(), FixedPoint::zero());
+        assert_eq!(manager.get(1).unwrap(), None);
+
+        manager
+            .entry(1)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(5_000)))
+            .unwrap();
+
+        let account = manager.get(1).unwrap().unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(5_000));
+        assert_eq!(manager.get(2).unwrap(), None);
+    }
+
     #[test]
     fn try_update_applies_mutation() {
         let manager = ConcurrentAccountManager::new();
@@ -332,6 +559,128 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn snapshot_writes_every_account_across_chunks() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        // About 32 bytes per row, so several buffer-sized writes
+        let clients = (SNAPSHOT_BUFFER_SIZE / 16) as ClientId + 7;
+        for client_id in 1..=clients {
+            manager
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+                .unwrap();
+        }
+
+        let mut output = Vec::new();
+        manager.snapshot(&mut output).await.unwrap();
+
+        let result = String::from_utf8(output).unwrap();
+        let mut client_ids: Vec<ClientId> = result
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').next().unwrap().parse().unwrap())
+            .collect();
+        client_ids.sort_unstable();
+        assert_eq!(client_ids, (1..=clients).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn try_update_pair_updates_both_accounts() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+
+        // Cover pairs in both the same and different DashMap shards
+        for to in 2..=64 {
+            let mut entry = manager.entry(1).unwrap();
+            entry
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(100)))
+                .unwrap();
+
+            manager
+                .try_update_pair(1, to, |from, to| {
+                    operations::apply_transfer(from, to, FixedPoint::from_raw(100))
+                })
+                .unwrap();
+
+            assert_eq!(
+                manager.entry(to).unwrap().read().available(),
+                FixedPoint::from_raw(100)
+            );
+        }
+
+        assert_eq!(
+            manager.entry(1).unwrap().read().available(),
+            FixedPoint::zero()
+        );
+    }
+
+    #[test]
+    fn try_update_pair_failure_leaves_accounts_untouched() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+
+        for to in 2..=64 {
+            let result = manager.try_update_pair(1, to, |from, to| {
+                operations::apply_transfer(from, to, FixedPoint::from_raw(100))
+            });
+            assert!(matches!(
+                result,
+                Err(StorageError::DomainError(DomainError::InsufficientFunds))
+            ));
+        }
+
+        // Failed transfers must not materialize accounts
+        assert!(manager.accounts.is_empty());
+    }
+
+    #[test]
+    fn try_update_pair_rejects_same_client() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+
+        let result = manager.try_update_pair(1, 1, |_, _| Ok(()));
+        assert!(matches!(
+            result,
+            Err(StorageError::DomainError(DomainError::SelfTransfer))
+        ));
+    }
+
+    #[test]
+    fn concurrent_opposing_transfers_do_not_deadlock() {
+        let manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+
+        for client in 1..=8 {
+            let mut entry = manager.entry(client).unwrap();
+            entry
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(100_000)))
+                .unwrap();
+        }
+
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let manager = Arc::clone(&manager);
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        let a = (i % 8) + 1;
+                        let b = ((i + 1 + t) % 8) + 1;
+                        let (from, to) = if t % 2 == 0 { (a, b) } else { (b, a) };
+                        let _ = manager.try_update_pair(from, to, |from, to| {
+                            operations::apply_transfer(from, to, FixedPoint::from_raw(1))
+                        });
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Transfers conserve the total balance
+        let total: i64 = (1..=8)
+            .map(|client| manager.entry(client).unwrap().read().total().raw())
+            .sum();
+        assert_eq!(total, 800_000);
+    }
+
     // Note: iter() test omitted as DashMap doesn't support returning borrowed references
     // The snapshot() method demonstrates correct iteration
 }
@@ -1,12 +1,18 @@
 use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
 
-use crate::domain::{AmountType, TransactionRecord};
+use crate::domain::{AmountType, TransactionId, TransactionRecord};
+use super::error::StorageError;
 use super::traits::TransactionStoreManager;
 
 /// DashMap-based concurrent transaction store (lock-free, thread-safe)
 /// Transactions are immutable once inserted
+///
+/// `apply_and_record` and `apply_and_update` hold the record's map shard for
+/// the whole step. Lock order: record shard first, then the account locks
+/// taken by the closure, which must not touch this store.
 pub struct ConcurrentTransactionStore<A: AmountType> {
-    records: DashMap<u32, TransactionRecord<A>>,
+    records: DashMap<TransactionId, TransactionRecord<A>>,
 }
 
 impl<A: AmountType> ConcurrentTransactionStore<A> {
@@ -16,52 +22,89 @@ impl<A: AmountType> ConcurrentTransactionStore<A> {
             records: DashMap::new(),
         }
     }
+
+    /// Create an empty store with room for `capacity` records
+    ///
+    /// Use when the input size is known up front (e.g. a row count) to avoid
+    /// repeatedly growing the table while loading millions of transactions.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            records: DashMap::with_capacity(capacity),
+        }
+    }
 }
 
 impl<A: AmountType> TransactionStoreManager<A> for ConcurrentTransactionStore<A> {
-    fn insert(&mut self, tx_id: u32, record: TransactionRecord<A>) {
+    fn insert(&self, tx_id: TransactionId, record: TransactionRecord<A>) {
         self.records.insert(tx_id, record);
     }
 
-    fn get(&self, tx_id: u32) -> Option<TransactionRecord<A>> {
-        self.records.get(&tx_id).map(|r| r.clone())
+    fn apply_and_record<E: From<StorageError>>(
+        &self,
+        tx_id: TransactionId,
+        record: TransactionRecord<A>,
+        apply: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        match self.records.entry(tx_id) {
+            Entry::Occupied(_) => Err(StorageError::DuplicateTransaction(tx_id).into()),
+            Entry::Vacant(slot) => {
+                apply()?;
+                slot.insert(record);
+                Ok(())
+            }
+        }
     }
 
-    fn contains(&self, tx_id: u32) -> bool {
-        self.records.contains_key(&tx_id)
+    fn apply_and_update<E>(
+        &self,
+        tx_id: TransactionId,
+        apply: impl FnOnce(Option<TransactionRecord<A>>) -> Result<TransactionRecord<A>, E>,
+    ) -> Result<(), E> {
+        match self.records.entry(tx_id) {
+            Entry::Occupied(mut slot) => {
+                let record = apply(Some(slot.get().clone()))?;
+                slot.insert(record);
+            }
+            Entry::Vacant(slot) => {
+                slot.insert(apply(None)?);
+            }
+        }
+        Ok(())
     }
-}
 
-impl<A: AmountType> Default for ConcurrentTransactionStore<A> {
-    fn default() -> Self {
-        Self::new()
+    fn get(&self, tx_id: TransactionId) -> Option<TransactionRecord<A>> {
+        self.records.get(&tx_id).map(|r| r.clone())
     }
-}
 
-// Implement TransactionStoreManager for Arc<ConcurrentTransactionStore> to enable sharing
-// This allows multiple threads/tasks to share the same transaction store
-impl<A: AmountType> TransactionStoreManager<A> for std::sync::Arc<ConcurrentTransactionStore<A>> {
-    fn insert(&mut self, tx_id: u32, record: TransactionRecord<A>) {
-        // Arc provides interior mutability via DashMap, so we can insert through &self
-        // We just need to get a reference to the inner store
-        self.records.insert(tx_id, record);
+    fn contains(&self, tx_id: TransactionId) -> bool {
+        self.records.contains_key(&tx_id)
+    }
+
+    fn for_each_record(&self, visit: &mut dyn FnMut(TransactionId, &TransactionRecord<A>)) {
+        for entry in self.records.iter() {
+            visit(*entry.key(), entry.value());
+        }
     }
 
-    fn get(&self, tx_id: u32) -> Option<TransactionRecord<A>> {
-        (**self).get(tx_id)
+    fn resident_records(&self) -> usize {
+        self.records.len()
     }
+}
 
-    fn contains(&self, tx_id: u32) -> bool {
-        (**self).contains(tx_id)
+impl<A: AmountType> Default for ConcurrentTransactionStore<A> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::FixedPoint;
+    use crate::domain::{ClientId, FixedPoint};
     use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::thread;
+    use std::time::Duration;
 
     #[test]
     fn new_store_is_empty() {
@@ -72,7 +115,7 @@ mod tests {
 
     #[test]
     fn insert_and_retrieve_record() {
-        let mut store = ConcurrentTransactionStore::new();
+        let store = ConcurrentTransactionStore::new();
         let record = TransactionRecord::new(1, FixedPoint::from_raw(10_000));
 
         store.insert(100, record.clone());
@@ -83,6 +126,134 @@ mod tests {
         assert_eq!(retrieved.amount, FixedPoint::from_raw(10_000));
     }
 
+    #[test]
+    fn with_capacity_reserves_room_up_front() {
+        let store = ConcurrentTransactionStore::<FixedPoint>::with_capacity(10_000);
+        assert!(store.records.capacity() >= 10_000);
+        assert_eq!(store.resident_records(), 0);
+    }
+
+    #[test]
+    fn apply_and_record_only_records_after_successful_apply() {
+        let store = ConcurrentTransactionStore::new();
+        let record = TransactionRecord::new(1, FixedPoint::from_raw(1000));
+
+        let failed = store.apply_and_record(1, record.clone(), || Err(StorageError::NotFound));
+        assert!(matches!(failed, Err(StorageError::NotFound)));
+        assert!(!store.contains(1));
+
+        let mut applied = false;
+        store
+            .apply_and_record(1, record, || {
+                applied = true;
+                Ok::<_, StorageError>(())
+            })
+            .unwrap();
+        assert!(applied);
+        assert_eq!(store.get(1).unwrap().amount, FixedPoint::from_raw(1000));
+    }
+
+    #[test]
+    fn apply_and_record_applies_each_tx_id_once_across_threads() {
+        let store = Arc::new(ConcurrentTransactionStore::new());
+        let applied = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..8)
+            .map(|client_id| {
+                let store = Arc::clone(&store);
+                let applied = Arc::clone(&applied);
+                thread::spawn(move || {
+                    let record = TransactionRecord::new(client_id, FixedPoint::from_raw(1000));
+                    store.apply_and_record(1, record, || {
+                        applied.fetch_add(1, Ordering::SeqCst);
+                        // Widen the window between the check and the insert
+                        thread::sleep(Duration::from_millis(5));
+                        Ok::<_, StorageError>(())
+                    })
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(applied.load(Ordering::SeqCst), 1);
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(
+            results.iter().all(|result| matches!(
+                result,
+                Ok(()) | Err(StorageError::DuplicateTransaction(1))
+            ))
+        );
+    }
+
+    #[test]
+    fn apply_and_update_serializes_updates_of_one_record() {
+        let store = Arc::new(ConcurrentTransactionStore::new());
+        store.insert(1, TransactionRecord::new(1, FixedPoint::from_raw(1000)));
+
+        // Each update reads the dispute count and writes it back incremented
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                thread::spawn(move || {
+                    store
+                        .apply_and_update(1, |record| {
+                            let record = record.unwrap();
+                            thread::sleep(Duration::from_millis(1));
+                            let disputes = record.disputes + 1;
+                            Ok::<_, StorageError>(record.with_disputes(disputes))
+                        })
+                        .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(store.get(1).unwrap().disputes, 8);
+    }
+
+    #[test]
+    fn apply_and_update_keeps_the_record_when_apply_fails() {
+        let store = ConcurrentTransactionStore::new();
+        let record = TransactionRecord::new(1, FixedPoint::from_raw(1000));
+        store.insert(1, record.clone());
+
+        let failed =
+            store.apply_and_update(1, |_| Err::<TransactionRecord<FixedPoint>, _>("rejected"));
+
+        assert_eq!(failed, Err("rejected"));
+        assert_eq!(store.get(1), Some(record));
+        assert!(
+            store
+                .apply_and_update(2, |_| Err::<TransactionRecord<FixedPoint>, _>("rejected"))
+                .is_err()
+        );
+        assert!(!store.contains(2));
+    }
+
+    #[test]
+    fn apply_and_record_holds_the_record_lock_while_applying() {
+        let store = ConcurrentTransactionStore::new();
+        let record = TransactionRecord::new(1, FixedPoint::from_raw(1000));
+
+        store
+            .apply_and_record(1, record, || {
+                // Another thread cannot even look at the record meanwhile
+                let locked = thread::scope(|scope| {
+                    scope
+                        .spawn(|| store.records.try_get(&1).is_locked())
+                        .join()
+                        .unwrap()
+                });
+                assert!(locked);
+                Ok::<_, StorageError>(())
+            })
+            .unwrap();
+
+        assert!(store.records.try_get(&1).is_present());
+    }
+
     #[test]
     fn get_returns_none_for_nonexistent() {
         let store = ConcurrentTransactionStore::<FixedPoint>::new();
@@ -92,7 +263,7 @@ mod tests {
 
     #[test]
     fn get_returns_clone_not_reference() {
-        let mut store = ConcurrentTransactionStore::new();
+        let store = ConcurrentTransactionStore::new();
         let record = TransactionRecord::new(1, FixedPoint::from_raw(1000));
         store.insert(1, record.clone());
 
@@ -106,7 +277,7 @@ mod tests {
 
     #[test]
     fn multiple_transactions() {
-        let mut store = ConcurrentTransactionStore::new();
+        let store = ConcurrentTransactionStore::new();
 
         store.insert(1, TransactionRecord::new(1, FixedPoint::from_raw(1_000)));
         store.insert(2, TransactionRecord::new(2, FixedPoint::from_raw(2_000)));
@@ -123,7 +294,7 @@ mod tests {
 
     #[test]
     fn concurrent_access_from_multiple_threads() {
-        let mut store = ConcurrentTransactionStore::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::<FixedPoint>::new();
 
         // Pre-populate some transactions
         for i in 0..100 {
@@ -165,11 +336,14 @@ mod tests {
         // a processing session, and multiple processors would have separate stores
         // or access via Arc<RwLock<>> if needed.
 
-        let mut store = ConcurrentTransactionStore::<FixedPoint>::new();
+        let store = ConcurrentTransactionStore::<FixedPoint>::new();
 
         // Sequential writes work fine
         for i in 0..1000 {
-            store.insert(i, TransactionRecord::new((i % 10) as u16, FixedPoint::from_raw(i as i64 * 1000)));
+            store.insert(
+                i,
+                TransactionRecord::new((i % 10) as ClientId, FixedPoint::from_raw(i as i64 * 1000)),
+            );
         }
 
         assert_eq!(store.records.len(), 1000);
@@ -177,7 +351,7 @@ mod tests {
 
     #[test]
     fn immutability_transactions_cannot_be_modified() {
-        let mut store = ConcurrentTransactionStore::new();
+        let store = ConcurrentTransactionStore::new();
         let record = TransactionRecord::new(1, FixedPoint::from_raw(1000));
         store.insert(1, record);
 
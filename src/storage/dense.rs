@@ -0,0 +1,315 @@
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use tokio::io::AsyncWrite;
+
+use super::error::StorageError;
+use super::snapshot_format::{SnapshotFormat, SnapshotWriter};
+use super::traits::{ClientAccountEntry, ClientAccountManager};
+use crate::domain::{AmountType, ClientAccount, ClientId, DomainError};
+
+/// Number of slots needed to cover every possible ClientId client ID
+const SLOT_COUNT: usize = u16::MAX as usize + 1;
+
+/// Dense account manager indexed directly by client ID
+///
+/// Preallocates one lock-protected slot per possible ClientId client ID, so lookups
+/// are a plain index with no hashing. Best suited to dense ID spaces; for sparse
+/// ones `ConcurrentAccountManager` uses far less memory.
+pub struct DenseAccountManager<A: AmountType> {
+    slots: Vec<RwLock<Option<ClientAccount<A>>>>,
+}
+
+impl<A: AmountType> DenseAccountManager<A> {
+    /// Create a new manager with an empty slot for every client ID
+    pub fn new() -> Self {
+        Self {
+            slots: (0..SLOT_COUNT).map(|_| RwLock::new(None)).collect(),
+        }
+    }
+
+    fn slot(&self, client_id: ClientId) -> Result<&RwLock<Option<ClientAccount<A>>>, StorageError> {
+        self.slots
+            .get(client_id as usize)
+            .ok_or(StorageError::ClientOutOfRange(client_id))
+    }
+}
+
+impl<A: AmountType> Default for DenseAccountManager<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Entry for dense slot access
+pub struct DenseEntry<'a, A: AmountType> {
+    client_id: ClientId,
+    slot: &'a RwLock<Option<ClientAccount<A>>>,
+}
+
+impl<'a, A: AmountType> ClientAccountEntry<'a, A> for DenseEntry<'a, A> {
+    fn read(&self) -> ClientAccount<A> {
+        self.slot
+            .read()
+            .clone()
+            .unwrap_or_else(|| ClientAccount::new(self.client_id))
+    }
+
+    fn try_update<F>(&mut self, update_fn: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        let mut slot = self.slot.write();
+        match slot.as_mut() {
+            Some(account) => update_fn(account)?,
+            None => {
+                let mut account = ClientAccount::new(self.client_id);
+                update_fn(&mut account)?;
+                *slot = Some(account);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<A: AmountType> ClientAccountManager<A> for DenseAccountManager<A> {
+    type Entry<'a>
+        = DenseEntry<'a, A>
+    where
+        Self: 'a;
+
+    fn entry(&self, client_id: ClientId) -> Result<Self::Entry<'_>, StorageError> {
+        Ok(DenseEntry {
+            client_id,
+            slot: self.slot(client_id)?,
+        })
+    }
+
+    fn try_update_pair<F>(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        if first_id == second_id {
+            return Err(DomainError::SelfTransfer.into());
+        }
+
+        // Lock in ascending client ID order so opposing pair updates cannot deadlock
+        let (mut first, mut second) = if first_id < second_id {
+            let first = self.slot(first_id)?.write();
+            let second = self.slot(second_id)?.write();
+            (first, second)
+        } else {
+            let second = self.slot(second_id)?.write();
+            let first = self.slot(first_id)?.write();
+            (first, second)
+        };
+
+        // Work on copies so a failed update leaves both accounts untouched
+        let mut first_account = first
+            .clone()
+            .unwrap_or_else(|| ClientAccount::new(first_id));
+        let mut second_account = second
+            .clone()
+            .unwrap_or_else(|| ClientAccount::new(second_id));
+        update_fn(&mut first_account, &mut second_account)?;
+
+        *first = Some(first_account);
+        *second = Some(second_account);
+        Ok(())
+    }
+
+    fn get(&self, client_id: ClientId) -> Result<Option<ClientAccount<A>>, StorageError> {
+        // Clone under a brief read lock; slots cannot hand out references
+        Ok(self.slot(client_id)?.read().clone())
+    }
+
+    async fn snapshot_with_format<W>(
+        &self,
+        writer: W,
+        format: &SnapshotFormat,
+    ) -> Result<(), StorageError>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut writer = SnapshotWriter::new(writer);
+        writer
+            .buffer()
+            .extend_from_slice(format.header().as_bytes());
+
+        // Format each row under a brief read lock; the guard is dropped before awaiting
+        for slot in &self.slots {
+            if let Some(account) = slot.read().as_ref() {
+                format.write_row(account, writer.buffer())?;
+            }
+            writer.write_if_full().await?;
+        }
+
+        writer.finish().await?;
+        Ok(())
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &ClientAccount<A>> + Send + '_> {
+        // Same limitation as ConcurrentAccountManager: slots are lock-protected
+        Box::new(std::iter::empty())
+    }
+
+    fn for_each_account(&self, visit: &mut dyn FnMut(&ClientAccount<A>)) {
+        for slot in &self.slots {
+            if let Some(account) = slot.read().as_ref() {
+                visit(account);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, operations};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn entry_creates_account_if_not_exists() {
+        let manager = DenseAccountManager::<FixedPoint>::new();
+        let last = (SLOT_COUNT - 1) as ClientId;
+        let account = manager.entry(last).unwrap().read();
+
+        assert_eq!(account.client_id(), last);
+        assert_eq!(account.total(), FixedPoint::zero());
+    }
+
+    #[cfg(feature = "wide-client-ids")]
+    #[test]
+    fn clients_beyond_the_slots_are_rejected() {
+        let manager = DenseAccountManager::<FixedPoint>::new();
+        let beyond = SLOT_COUNT as ClientId;
+
+        assert!(
+            matches!(manager.entry(beyond), Err(StorageError::ClientOutOfRange(id)) if id == beyond)
+        );
+        assert!(matches!(
+            manager.get(beyond),
+            Err(StorageError::ClientOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn failed_update_does_not_create_account() {
+        let manager = DenseAccountManager::<FixedPoint>::new();
+        let mut entry = manager.entry(3).unwrap();
+
+        let result =
+            entry.try_update(|acc| operations::apply_withdrawal(acc, FixedPoint::from_raw(1)));
+
+        assert!(result.is_err());
+        assert!(manager.slot(3).unwrap().read().is_none());
+    }
+
+    #[test]
+    fn get_returns_existing_accounts_only() {
+        let manager = DenseAccountManager::<FixedPoint>::new();
+        assert_eq!(manager.get(9).unwrap(), None);
+
+        manager
+            .entry(9)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(2_500)))
+            .unwrap();
+
+        let account = manager.get(9).unwrap().unwrap();
+        assert_eq!(account.client_id(), 9);
+        assert_eq!(account.available(), FixedPoint::from_raw(2_500));
+    }
+
+    #[test]
+    fn concurrent_updates_to_same_client() {
+        let manager = Arc::new(DenseAccountManager::<FixedPoint>::new());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let manager = Arc::clone(&manager);
+                thread::spawn(move || {
+                    for _ in 0..250 {
+                        manager
+                            .entry(1)
+                            .unwrap()
+                            .try_update(|acc| {
+                                operations::apply_deposit(acc, FixedPoint::from_raw(1))
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(
+            manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(1000)
+        );
+    }
+
+    #[test]
+    fn pair_update_is_atomic_and_rejects_self() {
+        let manager = DenseAccountManager::<FixedPoint>::new();
+        manager
+            .entry(2)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+            .unwrap();
+
+        manager
+            .try_update_pair(2, 1, |from, to| {
+                operations::apply_transfer(from, to, FixedPoint::from_raw(4_000))
+            })
+            .unwrap();
+        let failed = manager.try_update_pair(2, 1, |from, to| {
+            operations::apply_transfer(from, to, FixedPoint::from_raw(99_000))
+        });
+
+        assert!(failed.is_err());
+        assert!(manager.try_update_pair(1, 1, |_, _| Ok(())).is_err());
+        assert_eq!(
+            manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(4_000)
+        );
+        assert_eq!(
+            manager.entry(2).unwrap().read().available(),
+            FixedPoint::from_raw(6_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn snapshot_writes_only_touched_accounts() {
+        let manager = DenseAccountManager::<FixedPoint>::new();
+        for client_id in [7, 1] {
+            manager
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+                .unwrap();
+        }
+        // Reading an untouched client must not make it appear in the snapshot
+        let _ = manager.entry(9).unwrap().read();
+
+        let mut output = Vec::new();
+        manager.snapshot(&mut output).await.unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "client,available,held,total,locked\n\
+             1,1.0000,0.0000,1.0000,false\n\
+             7,1.0000,0.0000,1.0000,false\n"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::Ref;
+use dashmap::try_result::TryResult;
+use dashmap::{DashMap, RwLock};
+
+use crate::domain::ClientId;
+
+/// Client ids listed in `ContentionReport::hottest_clients`
+const HOT_CLIENTS: usize = 10;
+
+/// Per-shard and per-client access counters of a `ConcurrentAccountManager`
+/// (requires the `diagnostics` feature)
+///
+/// Every account access first tries its shard lock without blocking; only
+/// when that fails is the wait for the lock timed, so uncontended accesses
+/// pay for a few relaxed atomic increments and nothing else.
+pub(crate) struct ContentionTracker {
+    shards: Vec<ShardCounters>,
+    /// Accesses per client id (ids up to 65,535 with `wide-client-ids`)
+    clients: Vec<AtomicU64>,
+}
+
+#[derive(Default)]
+struct ShardCounters {
+    accesses: AtomicU64,
+    contended: AtomicU64,
+    wait_nanos: AtomicU64,
+}
+
+impl ContentionTracker {
+    /// Tracker for a map with `shards` shards
+    pub(crate) fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards).map(|_| ShardCounters::default()).collect(),
+            clients: (0..=u16::MAX).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Count an access to `client_id` in `shard`
+    fn access(&self, shard: usize, client_id: ClientId) {
+        self.shards[shard].accesses.fetch_add(1, Ordering::Relaxed);
+        if let Some(count) = self.clients.get(client_id as usize) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count a blocked lock acquisition in `shard` that started at `start`
+    fn waited(&self, shard: usize, start: Instant) {
+        let counters = &self.shards[shard];
+        counters.contended.fetch_add(1, Ordering::Relaxed);
+        let nanos = start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+        counters.wait_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// `accounts.get(&client_id)`, counted and timed
+    pub(crate) fn get<'a, V>(
+        &self,
+        accounts: &'a DashMap<ClientId, V>,
+        client_id: ClientId,
+    ) -> Option<Ref<'a, ClientId, V>> {
+        let shard = accounts.determine_map(&client_id);
+        self.access(shard, client_id);
+        match accounts.try_get(&client_id) {
+            TryResult::Present(account) => Some(account),
+            TryResult::Absent => None,
+            TryResult::Locked => {
+                let start = Instant::now();
+                let account = accounts.get(&client_id);
+                self.waited(shard, start);
+                account
+            }
+        }
+    }
+
+    /// `accounts.entry(client_id)`, counted and timed
+    pub(crate) fn entry<'a, V>(
+        &self,
+        accounts: &'a DashMap<ClientId, V>,
+        client_id: ClientId,
+    ) -> Entry<'a, ClientId, V> {
+        let shard = accounts.determine_map(&client_id);
+        self.access(shard, client_id);
+        match accounts.try_entry(client_id) {
+            Some(entry) => entry,
+            None => {
+                let start = Instant::now();
+                let entry = accounts.entry(client_id);
+                self.waited(shard, start);
+                entry
+            }
+        }
+    }
+
+    /// Write-lock raw shard `shard` to access `client_ids`, counted and timed
+    pub(crate) fn write_shard<'a, T>(
+        &self,
+        lock: &'a RwLock<T>,
+        shard: usize,
+        client_ids: [ClientId; 2],
+    ) -> dashmap::RwLockWriteGuard<'a, T> {
+        for client_id in client_ids {
+            self.access(shard, client_id);
+        }
+        match lock.try_write() {
+            Some(guard) => guard,
+            None => {
+                let start = Instant::now();
+                let guard = lock.write();
+                self.waited(shard, start);
+                guard
+            }
+        }
+    }
+
+    /// Counters so far
+    pub(crate) fn report(&self) -> ContentionReport {
+        let shards = self
+            .shards
+            .iter()
+            .enumerate()
+            .map(|(shard, counters)| ShardContention {
+                shard,
+                accesses: counters.accesses.load(Ordering::Relaxed),
+                contended: counters.contended.load(Ordering::Relaxed),
+                wait: Duration::from_nanos(counters.wait_nanos.load(Ordering::Relaxed)),
+            })
+            .collect();
+
+        let mut hottest_clients: Vec<(ClientId, u64)> = self
+            .clients
+            .iter()
+            .enumerate()
+            .map(|(client_id, count)| (client_id as ClientId, count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        hottest_clients.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hottest_clients.truncate(HOT_CLIENTS);
+
+        ContentionReport {
+            shards,
+            hottest_clients,
+        }
+    }
+}
+
+/// Access counts and lock waits of one DashMap shard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardContention {
+    pub shard: usize,
+    /// Account accesses that went through this shard
+    pub accesses: u64,
+    /// Accesses that found the shard locked and had to wait
+    pub contended: u64,
+    /// Total time spent waiting for the shard lock
+    pub wait: Duration,
+}
+
+/// Where a `ConcurrentAccountManager` sees lock contention
+/// (requires the `diagnostics` feature)
+///
+/// # Example
+/// ```rust,ignore
+/// let report = account_manager.contention_report();
+/// eprintln!("{report}");
+/// for shard in report.most_contended(3) {
+///     println!("shard {}: waited {:?}", shard.shard, shard.wait);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentionReport {
+    /// Every shard, by index
+    pub shards: Vec<ShardContention>,
+    /// The most accessed client ids with their access counts, busiest first
+    /// (among ids up to 65,535 with `wide-client-ids`)
+    pub hottest_clients: Vec<(ClientId, u64)>,
+}
+
+impl ContentionReport {
+    /// Accesses across all shards
+    pub fn total_accesses(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.accesses).sum()
+    }
+
+    /// Time spent waiting for shard locks across all shards
+    pub fn total_wait(&self) -> Duration {
+        self.shards.iter().map(|shard| shard.wait).sum()
+    }
+
+    /// Up to `n` shards with the longest total wait, longest first
+    pub fn most_contended(&self, n: usize) -> Vec<ShardContention> {
+        let mut shards: Vec<_> = self
+            .shards
+            .iter()
+            .filter(|shard| shard.contended > 0)
+            .copied()
+            .collect();
+        shards.sort_by(|a, b| b.wait.cmp(&a.wait).then(a.shard.cmp(&b.shard)));
+        shards.truncate(n);
+        shards
+    }
+}
+
+impl fmt::Display for ContentionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} accesses across {} shards, {:?} waiting for locks",
+            self.total_accesses(),
+            self.shards.len(),
+            self.total_wait()
+        )?;
+        for shard in self.most_contended(HOT_CLIENTS) {
+            writeln!(
+                f,
+                "  shard {}: {} accesses, {} contended, {:?} waiting",
+                shard.shard, shard.accesses, shard.contended, shard.wait
+            )?;
+        }
+        let clients: Vec<_> = self
+            .hottest_clients
+            .iter()
+            .map(|(client_id, count)| format!("{client_id} ({count})"))
+            .collect();
+        write!(f, "  hottest clients: {}", clients.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_accesses_by_shard_and_client() {
+        let accounts: DashMap<ClientId, u64> = DashMap::with_shard_amount(4);
+        let tracker = ContentionTracker::new(4);
+
+        for _ in 0..3 {
+            *tracker.entry(&accounts, 7).or_insert(0) += 1;
+        }
+        tracker.get(&accounts, 9);
+
+        let report = tracker.report();
+        assert_eq!(report.total_accesses(), 4);
+        assert!(report.shards[accounts.determine_map(&7)].accesses >= 3);
+        assert_eq!(report.hottest_clients, vec![(7, 3), (9, 1)]);
+        assert!(report.most_contended(4).is_empty());
+    }
+
+    #[test]
+    fn times_waits_on_locked_shards() {
+        let accounts: DashMap<ClientId, u64> = DashMap::with_shard_amount(4);
+        let tracker = ContentionTracker::new(4);
+        let shard = accounts.determine_map(&1);
+
+        std::thread::scope(|scope| {
+            let guard = accounts.shards()[shard].write();
+            let waiter = scope.spawn(|| tracker.get(&accounts, 1).is_none());
+            std::thread::sleep(Duration::from_millis(50));
+            drop(guard);
+            assert!(waiter.join().unwrap());
+        });
+
+        let report = tracker.report();
+        assert_eq!(report.shards[shard].contended, 1);
+        assert!(report.shards[shard].wait > Duration::ZERO);
+        assert_eq!(report.most_contended(1)[0].shard, shard);
+        assert!(report.to_string().contains("hottest clients: 1 (1)"));
+    }
+}
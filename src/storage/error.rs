@@ -1,7 +1,7 @@
 use std::io;
 use thiserror::Error;
 
-use crate::domain::DomainError;
+use crate::domain::{ClientId, DomainError, TransactionId};
 
 /// Storage-level errors
 #[derive(Error, Debug)]
@@ -14,6 +14,15 @@ pub enum StorageError {
 
     #[error("Domain error: {0}")]
     DomainError(#[from] DomainError),
+
+    #[error("Client {0} is beyond the storage's client id range")]
+    ClientOutOfRange(ClientId),
+
+    #[error("Transaction {0} is already recorded")]
+    DuplicateTransaction(TransactionId),
+
+    #[error("Storage does not support {0}")]
+    Unsupported(&'static str),
 }
 
 #[cfg(test)]
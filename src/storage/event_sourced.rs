@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use tokio::io::AsyncWrite;
+
+use super::error::StorageError;
+use super::snapshot_format::SnapshotFormat;
+use super::traits::{ClientAccountEntry, ClientAccountManager};
+use crate::domain::{AccountOp, AmountType, ClientAccount, ClientId, DomainError, PairOp};
+
+/// One successful operation, in log order
+///
+/// Events hold the domain operations the engine applied (deposits,
+/// withdrawals, holds, disputes, ...), not the accounts they left behind;
+/// replaying them through `AccountOp::apply` and `PairOp::apply` rebuilds
+/// every account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountEvent<A: AmountType> {
+    /// `op` was applied to the account of `client_id`
+    Applied {
+        client_id: ClientId,
+        op: AccountOp<A>,
+    },
+    /// `op` was applied to the accounts of `first_id` and `second_id` at once
+    AppliedPair {
+        first_id: ClientId,
+        second_id: ClientId,
+        op: PairOp<A>,
+    },
+}
+
+impl<A: AmountType> AccountEvent<A> {
+    /// Apply the event to `accounts`, creating the accounts it touches
+    fn replay(
+        &self,
+        accounts: &mut HashMap<ClientId, ClientAccount<A>>,
+    ) -> Result<(), DomainError> {
+        match self {
+            AccountEvent::Applied { client_id, op } => {
+                let mut account = current(accounts, *client_id);
+                op.apply(&mut account)?;
+                accounts.insert(*client_id, account);
+            }
+            AccountEvent::AppliedPair {
+                first_id,
+                second_id,
+                op,
+            } => {
+                let mut first = current(accounts, *first_id);
+                let mut second = current(accounts, *second_id);
+                op.apply(&mut first, &mut second)?;
+                accounts.insert(*first_id, first);
+                accounts.insert(*second_id, second);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct EventLog<A: AmountType> {
+    events: Vec<AccountEvent<A>>,
+    /// Current state of every account, derived from `events`
+    accounts: HashMap<ClientId, ClientAccount<A>>,
+}
+
+impl<A: AmountType> EventLog<A> {
+    /// Apply `event` to the accounts and, only if it succeeds, log it
+    fn append(&mut self, event: AccountEvent<A>) -> Result<(), DomainError> {
+        event.replay(&mut self.accounts)?;
+        self.events.push(event);
+        Ok(())
+    }
+}
+
+/// Account manager whose source of truth is an append-only event log
+///
+/// Every domain operation applied through `ClientAccountEntry::try_apply`
+/// or `try_apply_pair` appends an `AccountEvent`; account state is an
+/// in-memory projection of the log that `rebuild` can recompute at any time,
+/// and `project_at` answers "what did accounts look like after N events".
+/// Failed operations append nothing, and updates that are not domain
+/// operations (`try_update` and `try_update_pair` closures) fail with
+/// `StorageError::Unsupported`. All writes go through one lock so the log
+/// has a single, total order, which makes this slower than
+/// `ConcurrentAccountManager` under many shards.
+///
+/// # Example
+/// ```rust,ignore
+/// let accounts = Arc::new(EventSourcedAccountManager::new());
+/// StreamProcessor::new(accounts.clone(), store, SkipErrors)
+///     .add_stream(stream)
+///     .process()
+///     .await;
+///
+/// // Accounts as they were halfway through the run
+/// let earlier = accounts.project_at(accounts.event_count() / 2);
+/// ```
+pub struct EventSourcedAccountManager<A: AmountType> {
+    log: RwLock<EventLog<A>>,
+}
+
+impl<A: AmountType> EventSourcedAccountManager<A> {
+    /// Create a manager with an empty log
+    pub fn new() -> Self {
+        Self {
+            log: RwLock::new(EventLog {
+                events: Vec::new(),
+                accounts: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Create a manager from a previously recorded log, replaying it
+    ///
+    /// Fails if an event does not apply to the accounts the events before it
+    /// leave (e.g. a withdrawal with no deposit before it).
+    pub fn from_events(events: Vec<AccountEvent<A>>) -> Result<Self, StorageError> {
+        let accounts = replay(&events)?;
+        Ok(Self {
+            log: RwLock::new(EventLog { events, accounts }),
+        })
+    }
+
+    /// Number of events in the log
+    pub fn event_count(&self) -> usize {
+        self.log.read().events.len()
+    }
+
+    /// Copy of the events from `start` onwards (e.g. to persist new ones)
+    pub fn events_since(&self, start: usize) -> Vec<AccountEvent<A>> {
+        let log = self.log.read();
+        log.events.get(start..).unwrap_or_default().to_vec()
+    }
+
+    /// Recompute every account by replaying the log from the start
+    pub fn rebuild(&self) {
+        let mut log = self.log.write();
+        log.accounts = replay(&log.events).expect("logged events replay");
+    }
+
+    /// Accounts as they were after the first `event_index` events, by client id
+    ///
+    /// `project_at(0)` is empty and `project_at(event_count())` is the current
+    /// state; larger indices are clamped to the end of the log.
+    pub fn project_at(&self, event_index: usize) -> BTreeMap<ClientId, ClientAccount<A>> {
+        let log = self.log.read();
+        let end = event_index.min(log.events.len());
+        replay(&log.events[..end])
+            .expect("logged events replay")
+            .into_iter()
+            .collect()
+    }
+}
+
+impl<A: AmountType> Default for EventSourcedAccountManager<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copy of the account of `client_id`, or a new one
+fn current<A: AmountType>(
+    accounts: &HashMap<ClientId, ClientAccount<A>>,
+    client_id: ClientId,
+) -> ClientAccount<A> {
+    accounts
+        .get(&client_id)
+        .cloned()
+        .unwrap_or_else(|| ClientAccount::new(client_id))
+}
+
+/// Accounts `events` leave when applied in order to no accounts
+///
+/// Every logged event applied once already, so replaying the log only fails
+/// for events recorded elsewhere (see `from_events`).
+fn replay<A: AmountType>(
+    events: &[AccountEvent<A>],
+) -> Result<HashMap<ClientId, ClientAccount<A>>, DomainError> {
+    let mut accounts = HashMap::new();
+    for event in events {
+        event.replay(&mut accounts)?;
+    }
+    Ok(accounts)
+}
+
+/// Entry for event-sourced account access
+pub struct EventSourcedEntry<'a, A: AmountType> {
+    client_id: ClientId,
+    manager: &'a EventSourcedAccountManager<A>,
+}
+
+impl<'a, A: AmountType> ClientAccountEntry<'a, A> for EventSourcedEntry<'a, A> {
+    fn read(&self) -> ClientAccount<A> {
+        self.manager
+            .log
+            .read()
+            .accounts
+            .get(&self.client_id)
+            .cloned()
+            .unwrap_or_else(|| ClientAccount::new(self.client_id))
+    }
+
+    fn try_update<F>(&mut self, _update_fn: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        Err(StorageError::Unsupported(
+            "account updates that are not domain operations",
+        ))
+    }
+
+    fn try_apply(&mut self, op: &AccountOp<A>) -> Result<Option<A>, StorageError> {
+        let mut log = self.manager.log.write();
+        let mut account = current(&log.accounts, self.client_id);
+        let held = op.apply(&mut account)?;
+
+        log.events.push(AccountEvent::Applied {
+            client_id: self.client_id,
+            op: op.clone(),
+        });
+        log.accounts.insert(self.client_id, account);
+        Ok(held)
+    }
+}
+
+#[async_trait]
+impl<A: AmountType> ClientAccountManager<A> for EventSourcedAccountManager<A> {
+    type Entry<'a>
+        = EventSourcedEntry<'a, A>
+    where
+        Self: 'a;
+
+    fn entry(&self, client_id: ClientId) -> Result<Self::Entry<'_>, StorageError> {
+        Ok(EventSourcedEntry {
+            client_id,
+            manager: self,
+        })
+    }
+
+    fn try_update_pair<F>(
+        &self,
+        _first_id: ClientId,
+        _second_id: ClientId,
+        _update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        Err(StorageError::Unsupported(
+            "account updates that are not domain operations",
+        ))
+    }
+
+    fn try_apply_pair(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        op: &PairOp<A>,
+    ) -> Result<(), StorageError> {
+        if first_id == second_id {
+            return Err(DomainError::SelfTransfer.into());
+        }
+
+        // One lock covers the whole log, so there is no lock order to get wrong
+        self.log.write().append(AccountEvent::AppliedPair {
+            first_id,
+            second_id,
+            op: op.clone(),
+        })?;
+        Ok(())
+    }
+
+    fn get(&self, client_id: ClientId) -> Result<Option<ClientAccount<A>>, StorageError> {
+        Ok(self.log.read().accounts.get(&client_id).cloned())
+    }
+
+    async fn snapshot_with_format<W>(
+        &self,
+        mut writer: W,
+        format: &SnapshotFormat,
+    ) -> Result<(), StorageError>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        // Format every row up front; the lock guard must not be held across awaits
+        let mut output = format.header().into_bytes();
+        for account in self.log.read().accounts.values() {
+            format.write_row(account, &mut output)?;
+        }
+
+        writer.write_all(&output).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &ClientAccount<A>> + Send + '_> {
+        // Accounts live behind the log lock and cannot be borrowed past it
+        Box::new(std::iter::empty())
+    }
+
+    fn for_each_account(&self, visit: &mut dyn FnMut(&ClientAccount<A>)) {
+        for account in self.log.read().accounts.values() {
+            visit(account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn deposit(raw: i64) -> AccountOp<FixedPoint> {
+        AccountOp::Deposit {
+            amount: FixedPoint::from_raw(raw),
+            currency: None,
+        }
+    }
+
+    fn apply(manager: &EventSourcedAccountManager<FixedPoint>, client_id: ClientId, raw: i64) {
+        manager
+            .entry(client_id)
+            .unwrap()
+            .try_apply(&deposit(raw))
+            .unwrap();
+    }
+
+    #[test]
+    fn operations_append_events_and_failures_do_not() {
+        let manager = EventSourcedAccountManager::<FixedPoint>::new();
+        apply(&manager, 1, 10_000);
+        apply(&manager, 1, 5_000);
+
+        let result = manager.entry(1).unwrap().try_apply(&AccountOp::Withdrawal {
+            amount: FixedPoint::from_raw(99_000),
+            currency: None,
+        });
+
+        assert!(result.is_err());
+        assert_eq!(
+            manager.events_since(1),
+            vec![AccountEvent::Applied {
+                client_id: 1,
+                op: deposit(5_000),
+            }]
+        );
+        assert_eq!(
+            manager.get(1).unwrap().map(|acc| acc.available()),
+            Some(FixedPoint::from_raw(15_000))
+        );
+    }
+
+    #[test]
+    fn updates_that_are_not_operations_are_rejected() {
+        let manager = EventSourcedAccountManager::<FixedPoint>::new();
+
+        let single = manager.entry(1).unwrap().try_update(|_| Ok(()));
+        let pair = manager.try_update_pair(1, 2, |_, _| Ok(()));
+
+        assert!(matches!(single, Err(StorageError::Unsupported(_))));
+        assert!(matches!(pair, Err(StorageError::Unsupported(_))));
+        assert_eq!(manager.event_count(), 0);
+    }
+
+    #[test]
+    fn project_at_returns_earlier_states() {
+        let manager = EventSourcedAccountManager::<FixedPoint>::new();
+        apply(&manager, 1, 10_000);
+        apply(&manager, 2, 20_000);
+        apply(&manager, 1, 5_000);
+
+        assert!(manager.project_at(0).is_empty());
+
+        let after_two = manager.project_at(2);
+        assert_eq!(after_two.len(), 2);
+        assert_eq!(after_two[&1].available(), FixedPoint::from_raw(10_000));
+
+        let current = manager.project_at(usize::MAX);
+        assert_eq!(current[&1].available(), FixedPoint::from_raw(15_000));
+    }
+
+    #[test]
+    fn replaying_the_log_reproduces_the_accounts() {
+        let manager = EventSourcedAccountManager::<FixedPoint>::new();
+        apply(&manager, 1, 10_000);
+        manager
+            .try_apply_pair(
+                1,
+                2,
+                &PairOp::Transfer {
+                    amount: FixedPoint::from_raw(4_000),
+                    currency: None,
+                },
+            )
+            .unwrap();
+        manager
+            .entry(1)
+            .unwrap()
+            .try_apply(&AccountOp::Dispute {
+                tx_id: 1,
+                amount: FixedPoint::from_raw(6_000),
+                currency: None,
+                policy: Default::default(),
+            })
+            .unwrap();
+        manager.rebuild();
+
+        let restored = EventSourcedAccountManager::from_events(manager.events_since(0)).unwrap();
+
+        assert_eq!(restored.event_count(), 3);
+        for client_id in [1, 2] {
+            assert_eq!(
+                restored.get(client_id).unwrap(),
+                manager.get(client_id).unwrap()
+            );
+        }
+        assert_eq!(
+            restored.get(1).unwrap().map(|acc| acc.held()),
+            Some(FixedPoint::from_raw(6_000))
+        );
+    }
+
+    #[test]
+    fn from_events_rejects_a_log_that_does_not_replay() {
+        let events = vec![AccountEvent::Applied {
+            client_id: 1,
+            op: AccountOp::Withdrawal {
+                amount: FixedPoint::from_raw(1_000),
+                currency: None,
+            },
+        }];
+
+        assert!(matches!(
+            EventSourcedAccountManager::from_events(events),
+            Err(StorageError::DomainError(DomainError::InsufficientFunds))
+        ));
+    }
+}
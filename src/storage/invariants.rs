@@ -0,0 +1,103 @@
+use std::fmt;
+
+use super::traits::ClientAccountManager;
+use crate::domain::{AmountType, ClientId, DomainError};
+
+/// An account that failed `ClientAccount::validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolation {
+    pub client_id: ClientId,
+    pub error: DomainError,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "client {}: {}", self.client_id, self.error)
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+/// Check every account's balance invariants
+///
+/// Returns every violation found, in the manager's iteration order. Useful
+/// at the end of a test or run to catch corrupted state before it is written.
+///
+/// # Example
+/// ```rust,ignore
+/// if let Err(violations) = verify_invariants(&account_manager) {
+///     for violation in &violations {
+///         tracing::error!(%violation, "Account invariant violated");
+///     }
+/// }
+/// ```
+pub fn verify_invariants<A, M>(account_manager: &M) -> Result<(), Vec<InvariantViolation>>
+where
+    A: AmountType,
+    M: ClientAccountManager<A> + ?Sized,
+{
+    let mut violations = Vec::new();
+    account_manager.for_each_account(&mut |account| {
+        if let Err(error) = account.validate() {
+            violations.push(InvariantViolation {
+                client_id: account.client_id(),
+                error,
+            });
+        }
+    });
+
+    match violations.is_empty() {
+        true => Ok(()),
+        false => Err(violations),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, operations};
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager};
+
+    #[test]
+    fn accounts_built_by_operations_are_valid() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let mut entry = manager.entry(1).unwrap();
+        entry
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(10_000)))
+            .unwrap();
+        entry
+            .try_update(|acc| operations::apply_dispute(acc, 1, FixedPoint::from_raw(10_000)))
+            .unwrap();
+
+        assert_eq!(verify_invariants(&manager), Ok(()));
+    }
+
+    #[test]
+    fn reports_each_invalid_account() {
+        let manager = ConcurrentAccountManager::<FixedPoint>::new();
+        for client_id in 1..=3 {
+            manager
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| {
+                    if client_id != 2 {
+                        acc.set_held(FixedPoint::from_raw(-1));
+                    }
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        let mut violations = verify_invariants(&manager).unwrap_err();
+        violations.sort_by_key(|violation| violation.client_id);
+
+        assert_eq!(
+            violations.iter().map(|v| v.client_id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "client 1: Account invariant violated: held funds are negative"
+        );
+    }
+}
@@ -1,10 +1,37 @@
+pub mod bounded_transaction_store;
 pub mod concurrent;
 pub mod concurrent_transaction_store;
+pub mod dense;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
 pub mod error;
+pub mod event_sourced;
+pub mod invariants;
+pub mod query;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod snapshot_format;
+pub mod spilling_transaction_store;
+pub mod state;
+pub mod tiered;
 pub mod traits;
 
 // Re-export commonly used types
+pub use bounded_transaction_store::{BoundedTransactionStore, EvictionPolicy};
 pub use concurrent::ConcurrentAccountManager;
 pub use concurrent_transaction_store::ConcurrentTransactionStore;
+pub use dense::DenseAccountManager;
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::{ContentionReport, ShardContention};
 pub use error::StorageError;
+pub use event_sourced::{AccountEvent, EventSourcedAccountManager};
+pub use invariants::{InvariantViolation, verify_invariants};
+pub use query::{AccountBalance, QueryHandle};
+#[cfg(feature = "redis")]
+pub use self::redis::RedisAccountManager;
+pub use snapshot_format::SnapshotFormat;
+pub(crate) use snapshot_format::SnapshotWriter;
+pub use spilling_transaction_store::SpillingTransactionStore;
+pub use state::{StateCounts, export_state, import_state};
+pub use tiered::{TierStats, TieredAccountManager};
 pub use traits::{ClientAccountEntry, ClientAccountManager, TransactionStoreManager};
@@ -0,0 +1,207 @@
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use crate::domain::{AmountType, ClientAccount, ClientId};
+
+/// Base-currency balances of one account at the time of a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountBalance<A: AmountType> {
+    pub available: A,
+    pub held: A,
+    pub locked: bool,
+}
+
+impl<A: AmountType> AccountBalance<A> {
+    /// Available plus held funds
+    pub fn total(&self) -> A {
+        self.available + self.held
+    }
+
+    fn of(account: &ClientAccount<A>) -> Self {
+        Self {
+            available: account.available(),
+            held: account.held(),
+            locked: account.is_locked(),
+        }
+    }
+}
+
+/// Read-only view of a `ConcurrentAccountManager`'s live accounts
+///
+/// Cheap to clone and safe to use from other tasks while processing runs,
+/// e.g. to serve a dashboard. Queries never create accounts; each one reads
+/// accounts under brief per-shard read locks, so writers are only held up
+/// while a single account is copied. Results spanning several accounts are
+/// not a consistent snapshot: accounts may change while the query runs.
+///
+/// # Example
+/// ```rust,ignore
+/// let accounts = Arc::new(ConcurrentAccountManager::new());
+/// let query = accounts.query_handle();
+/// tokio::spawn(async move {
+///     loop {
+///         println!("locked: {:?}", query.locked_accounts());
+///         tokio::time::sleep(Duration::from_secs(5)).await;
+///     }
+/// });
+/// ```
+pub struct QueryHandle<A: AmountType> {
+    accounts: Arc<DashMap<ClientId, ClientAccount<A>>>,
+}
+
+impl<A: AmountType> Clone for QueryHandle<A> {
+    fn clone(&self) -> Self {
+        Self {
+            accounts: self.accounts.clone(),
+        }
+    }
+}
+
+impl<A: AmountType> QueryHandle<A> {
+    pub(crate) fn new(accounts: Arc<DashMap<ClientId, ClientAccount<A>>>) -> Self {
+        Self { accounts }
+    }
+
+    /// Current balances of a client, or None if it has no account
+    pub fn balance(&self, client_id: ClientId) -> Option<AccountBalance<A>> {
+        self.accounts
+            .get(&client_id)
+            .map(|entry| AccountBalance::of(entry.value()))
+    }
+
+    /// Whether a client's account is locked (false if it has no account)
+    pub fn is_locked(&self, client_id: ClientId) -> bool {
+        self.accounts
+            .get(&client_id)
+            .is_some_and(|entry| entry.value().is_locked())
+    }
+
+    /// Ids of all locked accounts, in ascending order
+    pub fn locked_accounts(&self) -> Vec<ClientId> {
+        let mut locked: Vec<_> = self
+            .accounts
+            .iter()
+            .filter(|entry| entry.value().is_locked())
+            .map(|entry| *entry.key())
+            .collect();
+        locked.sort_unstable();
+        locked
+    }
+
+    /// The `n` accounts with the largest total funds, largest first
+    ///
+    /// Ties are ordered by ascending client id.
+    pub fn top_n_by_total(&self, n: usize) -> Vec<(ClientId, AccountBalance<A>)> {
+        let mut balances: Vec<_> = self
+            .accounts
+            .iter()
+            .map(|entry| (*entry.key(), AccountBalance::of(entry.value())))
+            .collect();
+        balances
+            .sort_unstable_by_key(|(client_id, balance)| (Reverse(balance.total()), *client_id));
+        balances.truncate(n);
+        balances
+    }
+
+    /// Number of accounts
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    /// Whether there are no accounts yet
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{ClientId, FixedPoint, operations};
+    use crate::storage::{ClientAccountEntry, ClientAccountManager, ConcurrentAccountManager};
+
+    fn manager_with(deposits: &[(ClientId, i64)]) -> ConcurrentAccountManager<FixedPoint> {
+        let manager = ConcurrentAccountManager::new();
+        for &(client_id, raw) in deposits {
+            manager
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(raw)))
+                .unwrap();
+        }
+        manager
+    }
+
+    #[test]
+    fn balance_and_lock_queries_do_not_create_accounts() {
+        let manager = manager_with(&[(1, 15_000)]);
+        manager
+            .entry(1)
+            .unwrap()
+            .try_update(|acc| {
+                acc.lock();
+                Ok(())
+            })
+            .unwrap();
+        let query = manager.query_handle();
+
+        let balance = query.balance(1).unwrap();
+        assert_eq!(balance.total(), FixedPoint::from_raw(15_000));
+        assert!(balance.locked);
+        assert!(query.is_locked(1));
+
+        assert_eq!(query.balance(2), None);
+        assert!(!query.is_locked(2));
+        assert_eq!(query.len(), 1);
+    }
+
+    #[test]
+    fn lists_locked_accounts_in_order() {
+        let manager = manager_with(&[(5, 1), (3, 1), (4, 1)]);
+        for client_id in [5, 3] {
+            manager
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| {
+                    acc.lock();
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        assert_eq!(manager.query_handle().locked_accounts(), vec![3, 5]);
+    }
+
+    #[test]
+    fn top_n_orders_by_total_then_client() {
+        let manager = manager_with(&[(1, 10_000), (2, 30_000), (3, 20_000), (4, 30_000)]);
+
+        let top: Vec<_> = manager
+            .query_handle()
+            .top_n_by_total(3)
+            .into_iter()
+            .map(|(client_id, balance)| (client_id, balance.total().raw()))
+            .collect();
+
+        assert_eq!(top, vec![(2, 30_000), (4, 30_000), (3, 20_000)]);
+    }
+
+    #[test]
+    fn handle_sees_writes_made_after_it_was_taken() {
+        let manager = manager_with(&[]);
+        let query = manager.query_handle().clone();
+        assert!(query.is_empty());
+
+        manager
+            .entry(7)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(100)))
+            .unwrap();
+
+        assert_eq!(
+            query.balance(7).map(|b| b.available),
+            Some(FixedPoint::from_raw(100))
+        );
+    }
+}
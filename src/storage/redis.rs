@@ -0,0 +1,1134 @@
+use std::io;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use r2d2::{Pool, PooledConnection};
+use redis::{Client, Script, ScriptInvocation};
+use tokio::io::AsyncWrite;
+use tracing::warn;
+
+use super::error::StorageError;
+use super::snapshot_format::{SnapshotFormat, SnapshotWriter};
+use super::traits::{ClientAccountEntry, ClientAccountManager};
+use crate::domain::{
+    AccountOp, AmountType, ClientAccount, ClientId, CurrencyBalance, DisputePolicy, DomainError,
+    TransactionId,
+};
+
+/// Apply a deposit, withdrawal, dispute, resolve or chargeback to one account
+///
+/// KEYS are the account and its lock; ARGV is the operation, its amount and
+/// held amount in minor units, the transaction id and three policy flags
+/// (locked accounts accept it, disputes may take available negative, spent
+/// funds are only partly held). Mirrors the functions in `domain::operations`
+/// check for check. Returns `BUSY` while the lock path holds the account and
+/// `FALLBACK` when a balance leaves the range Lua numbers hold exactly, so the
+/// caller retries or applies the operation in Rust; otherwise `OK` (with the
+/// held amount for a dispute) or the name of the `DomainError`.
+const APPLY_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[2]) == 1 then
+  return {'BUSY'}
+end
+local LIMIT = 9007199254740991
+local function fits(...)
+  for _, value in ipairs({...}) do
+    if value < -LIMIT or value > LIMIT then
+      return false
+    end
+  end
+  return true
+end
+local function without(ids, id)
+  local at = string.find(ids, id, 1, true)
+  return string.sub(ids, 1, at) .. string.sub(ids, at + #id)
+end
+
+local fields = redis.call('HMGET', KEYS[1], 'available', 'held', 'credit_limit', 'locked', 'disputed', 'holds')
+local available = tonumber(fields[1] or '0')
+local held = tonumber(fields[2] or '0')
+local credit_limit = tonumber(fields[3] or '0')
+local locked = fields[4] == '1'
+local disputed = fields[5] or ' '
+local holds = fields[6] or ' '
+local op, amount, held_amount = ARGV[1], tonumber(ARGV[2]), tonumber(ARGV[3])
+local tx = ' ' .. ARGV[4] .. ' '
+local allow_locked = ARGV[5] == '1'
+if not fits(available, held, credit_limit, amount, held_amount, available + held, available + credit_limit) then
+  return {'FALLBACK'}
+end
+
+local result = ''
+if op == 'deposit' or op == 'withdrawal' then
+  if amount <= 0 then
+    return {'InvalidAmount'}
+  end
+  if locked then
+    return {'AccountLocked'}
+  end
+  if op == 'deposit' then
+    available = available + amount
+  elseif available + credit_limit < amount then
+    return {'InsufficientFunds'}
+  else
+    available = available - amount
+  end
+else
+  if locked and not allow_locked then
+    return {'AccountLocked'}
+  end
+  local is_disputed = string.find(disputed, tx, 1, true) ~= nil
+  if op == 'dispute' then
+    if is_disputed then
+      return {'AlreadyDisputed'}
+    end
+    if string.find(holds, tx, 1, true) then
+      return {'HoldActive'}
+    end
+    if available < amount and ARGV[6] ~= '1' then
+      if ARGV[7] ~= '1' then
+        return {'InsufficientFunds'}
+      end
+      amount = math.max(available, 0)
+    end
+    available = available - amount
+    held = held + amount
+    disputed = disputed .. ARGV[4] .. ' '
+    result = string.format('%.0f', amount)
+  elseif not is_disputed then
+    return {'NotDisputed'}
+  elseif op == 'resolve' then
+    if held < held_amount then
+      return {'InsufficientFunds'}
+    end
+    held = held - held_amount
+    available = available + held_amount
+    disputed = without(disputed, tx)
+  else
+    if held < held_amount or (held_amount < amount and ARGV[7] ~= '1') then
+      return {'InsufficientFunds'}
+    end
+    local from_held = math.min(held_amount, amount)
+    held = held - from_held
+    available = available - (amount - from_held)
+    locked = true
+    disputed = without(disputed, tx)
+  end
+end
+if not fits(available, held, available + held) then
+  return {'FALLBACK'}
+end
+
+redis.call('HSET', KEYS[1], 'available', string.format('%.0f', available),
+  'held', string.format('%.0f', held), 'locked', locked and '1' or '0')
+if disputed == ' ' then
+  redis.call('HDEL', KEYS[1], 'disputed')
+else
+  redis.call('HSET', KEYS[1], 'disputed', disputed)
+end
+return {'OK', result}
+";
+
+/// Lock accounts and read them, or return nil if any is already locked
+///
+/// KEYS alternate account and lock keys; ARGV is the lock token and its
+/// time to live in milliseconds. Locks are taken all at once, so pair
+/// updates cannot deadlock. Each account is returned as its `HGETALL`,
+/// empty if it does not exist yet.
+const LOCK_SCRIPT: &str = r"
+for i = 2, #KEYS, 2 do
+  if redis.call('EXISTS', KEYS[i]) == 1 then
+    return false
+  end
+end
+local accounts = {}
+for i = 1, #KEYS, 2 do
+  redis.call('SET', KEYS[i + 1], ARGV[1], 'PX', ARGV[2])
+  accounts[#accounts + 1] = redis.call('HGETALL', KEYS[i])
+end
+return accounts
+";
+
+/// Replace the accounts with their updated fields and release their locks
+///
+/// KEYS as for `LOCK_SCRIPT`; ARGV is the lock token followed, for each
+/// account, by its number of field and value arguments and then those
+/// arguments, or the token alone to release the locks without writing.
+/// Nothing is written unless every lock is still held with the token.
+const UNLOCK_SCRIPT: &str = r"
+local held = true
+for i = 2, #KEYS, 2 do
+  if redis.call('GET', KEYS[i]) ~= ARGV[1] then
+    held = false
+  end
+end
+local at = 2
+for i = 1, #KEYS, 2 do
+  if held and #ARGV > 1 then
+    local count = tonumber(ARGV[at])
+    redis.call('DEL', KEYS[i])
+    if count > 0 then
+      redis.call('HSET', KEYS[i], unpack(ARGV, at + 1, at + count))
+    end
+    at = at + count + 1
+  end
+  if redis.call('GET', KEYS[i + 1]) == ARGV[1] then
+    redis.call('DEL', KEYS[i + 1])
+  end
+end
+if held then
+  return 1
+end
+return 0
+";
+
+/// Pause between attempts on an account another processor has locked
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(2);
+
+/// Accounts read per `SCAN` batch when visiting every account
+const SCAN_BATCH: usize = 1000;
+
+/// Account manager keeping accounts in Redis, shared by many processors
+///
+/// Each account is a hash under `<prefix>:account:<client>`, with balances in
+/// minor units, so several processor instances, on one host or many, can
+/// apply transactions to the same accounts. Deposits, withdrawals, disputes,
+/// resolves and chargebacks in the base currency run server-side in one Lua
+/// script (`EVALSHA`), so they take a single round trip and are atomic across
+/// instances. The script repeats the checks of `domain::operations`; a
+/// balance it cannot hold exactly in a Lua number (beyond 2^53 minor units)
+/// sends the operation down the lock path instead.
+///
+/// Every other update (other currencies, holds, tags, fees, transfers, ...)
+/// locks the accounts it touches with a second script, applies the domain
+/// operation locally and stores the result with a third that only writes
+/// while the lock is still held, so pair updates are all or nothing. The Lua
+/// operations wait while an account is locked. A lock outlives a crashed
+/// processor by at most the lock timeout.
+///
+/// Connections come from an `r2d2` pool; accounts are read for snapshots and
+/// `for_each_account` in `SCAN` batches whose `HGETALL`s are pipelined. The
+/// storage traits are synchronous, so calls wait on the network (and on other
+/// processors' locks) in the calling thread; on a multi-threaded tokio runtime
+/// the worker first hands its other tasks to another thread, so shards keep
+/// running, while on a current-thread runtime the whole runtime waits. A
+/// single Redis server (or primary) is assumed: keys are not hash-tagged for
+/// Redis Cluster.
+///
+/// Requires the `redis` feature.
+///
+/// # Example
+/// ```rust,ignore
+/// let accounts = RedisAccountManager::<FixedPoint>::connect("redis://10.0.0.5:6379")?
+///     .with_key_prefix("payments-eu")
+///     .with_pool_size(16);
+///
+/// StreamProcessor::new(accounts, ConcurrentTransactionStore::new(), SkipErrors)
+///     .add_stream(csv_stream)
+///     .process()
+///     .await;
+/// ```
+pub struct RedisAccountManager<A: AmountType> {
+    client: Client,
+    pool: Pool<Client>,
+    key_prefix: String,
+    lock_timeout: Duration,
+    apply_script: Script,
+    lock_script: Script,
+    unlock_script: Script,
+    /// Distinguishes this instance's lock tokens from other processors'
+    instance: u64,
+    next_token: AtomicU64,
+    _phantom: PhantomData<A>,
+}
+
+impl<A: AmountType> RedisAccountManager<A> {
+    /// Connect to the Redis server at `address` (`redis://host:port`, or
+    /// just `host:port`) and load the scripts
+    pub fn connect(address: &str) -> Result<Self, StorageError> {
+        let url = match address.contains("://") {
+            true => address.to_string(),
+            false => format!("redis://{address}"),
+        };
+        let client = Client::open(url).map_err(redis_error)?;
+        let apply_script = Script::new(APPLY_SCRIPT);
+        let lock_script = Script::new(LOCK_SCRIPT);
+        let unlock_script = Script::new(UNLOCK_SCRIPT);
+
+        // Load the scripts in one round trip, which also checks the server
+        // is reachable before the pool starts connecting in the background
+        off_runtime(|| {
+            let mut connection = client.get_connection()?;
+            redis::pipe()
+                .load_script(&apply_script)
+                .load_script(&lock_script)
+                .load_script(&unlock_script)
+                .query::<Vec<String>>(&mut connection)
+        })
+        .map_err(redis_error)?;
+
+        let instance = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default()
+            ^ (u64::from(std::process::id()) << 32);
+
+        Ok(Self {
+            pool: build_pool(&client, 8),
+            client,
+            key_prefix: "pay".to_string(),
+            lock_timeout: Duration::from_secs(5),
+            apply_script,
+            lock_script,
+            unlock_script,
+            instance,
+            next_token: AtomicU64::new(0),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Keep keys under `prefix` (defaults to `pay`), to share a server
+    /// between independent ledgers
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    /// Open at most `size` connections (defaults to 8)
+    ///
+    /// Match it to the number of shards using this manager; a call that
+    /// finds every connection busy waits for one to be returned.
+    pub fn with_pool_size(mut self, size: usize) -> Self {
+        let size = u32::try_from(size).unwrap_or(u32::MAX).max(1);
+        self.pool = build_pool(&self.client, size);
+        self
+    }
+
+    /// How long an update may hold its accounts' locks (defaults to 5 seconds)
+    ///
+    /// Also the longest an update waits for another processor's lock. An
+    /// update whose lock expired before it finished is not written and fails
+    /// with an I/O error.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout.max(Duration::from_millis(1));
+        self
+    }
+
+    fn connection(&self) -> Result<PooledConnection<Client>, StorageError> {
+        self.pool.get().map_err(redis_error)
+    }
+
+    fn account_key(&self, client_id: ClientId) -> String {
+        format!("{}:account:{}", self.key_prefix, client_id)
+    }
+
+    fn lock_key(&self, client_id: ClientId) -> String {
+        format!("{}:lock:{}", self.key_prefix, client_id)
+    }
+
+    /// Invocation of a lock script with each client's account and lock key
+    fn locking<'s>(&self, script: &'s Script, client_ids: &[ClientId]) -> ScriptInvocation<'s> {
+        let mut invocation = script.prepare_invoke();
+        for &client_id in client_ids {
+            invocation
+                .key(self.account_key(client_id))
+                .key(self.lock_key(client_id));
+        }
+        invocation
+    }
+
+    fn timed_out(&self, client_ids: &[ClientId]) -> StorageError {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("accounts {client_ids:?} stayed locked by another processor"),
+        )
+        .into()
+    }
+
+    /// Run `op` in `APPLY_SCRIPT`, or return `None` to leave it to the lock
+    /// path: the script only handles base-currency balances
+    fn apply_in_script(
+        &self,
+        client_id: ClientId,
+        op: &AccountOp<A>,
+    ) -> Option<Result<Option<A>, StorageError>> {
+        let zero = A::zero();
+        let default = DisputePolicy::default();
+        let (name, amount, held, tx_id, policy, allow_locked) = match op {
+            AccountOp::Deposit {
+                amount,
+                currency: None,
+            } => ("deposit", *amount, zero, 0, &default, false),
+            AccountOp::Withdrawal {
+                amount,
+                currency: None,
+            } => ("withdrawal", *amount, zero, 0, &default, false),
+            AccountOp::Dispute {
+                tx_id,
+                amount,
+                currency: None,
+                policy,
+            } => {
+                let allow_locked = policy.locked.allow_disputes;
+                ("dispute", *amount, zero, *tx_id, policy, allow_locked)
+            }
+            AccountOp::Resolve {
+                tx_id,
+                held,
+                currency: None,
+                policy,
+            } => {
+                let allow_locked = policy.locked.allow_resolves;
+                ("resolve", zero, *held, *tx_id, policy, allow_locked)
+            }
+            AccountOp::Chargeback {
+                tx_id,
+                amount,
+                held,
+                currency: None,
+                policy,
+            } => {
+                let allow_locked = policy.locked.allow_chargebacks;
+                ("chargeback", *amount, *held, *tx_id, policy, allow_locked)
+            }
+            _ => return None,
+        };
+
+        let mut invocation = self.apply_script.prepare_invoke();
+        invocation
+            .key(self.account_key(client_id))
+            .key(self.lock_key(client_id))
+            .arg(name)
+            .arg(minor_units(amount))
+            .arg(minor_units(held))
+            .arg(tx_id)
+            .arg(u8::from(allow_locked))
+            .arg(u8::from(policy.allow_negative_available))
+            .arg(u8::from(policy.allow_negative_balance));
+
+        let deadline = Instant::now() + self.lock_timeout;
+        let reply = off_runtime(|| {
+            loop {
+                let reply: Vec<String> = match self.connection() {
+                    Ok(mut connection) => {
+                        invocation.invoke(&mut *connection).map_err(redis_error)?
+                    }
+                    Err(e) => return Err(e),
+                };
+                match reply.first().map(String::as_str) {
+                    // Callers run under `off_runtime`, so only this thread waits
+                    Some("BUSY") if Instant::now() < deadline => {
+                        std::thread::sleep(LOCK_RETRY_DELAY)
+                    }
+                    Some("BUSY") => return Err(self.timed_out(&[client_id])),
+                    _ => return Ok(reply),
+                }
+            }
+        });
+
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => return Some(Err(e)),
+        };
+        let result = match reply.first().map(String::as_str) {
+            Some("FALLBACK") => return None,
+            Some("OK") if name == "dispute" => match reply.get(1) {
+                Some(held) => from_minor_units(held).map(Some),
+                None => Err(invalid("dispute script returned no held amount")),
+            },
+            Some("OK") => Ok(None),
+            Some(error) => Err(script_error(error)),
+            None => Err(invalid("empty reply from the operation script")),
+        };
+        Some(result)
+    }
+
+    /// Lock the clients' accounts and read them, waiting out other
+    /// processors' locks for up to the lock timeout
+    fn lock(
+        &self,
+        client_ids: &[ClientId],
+    ) -> Result<(String, Vec<ClientAccount<A>>), StorageError> {
+        let token = format!(
+            "{:x}-{}",
+            self.instance,
+            self.next_token.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut invocation = self.locking(&self.lock_script, client_ids);
+        invocation
+            .arg(&token)
+            .arg(self.lock_timeout.as_millis() as u64);
+        let deadline = Instant::now() + self.lock_timeout;
+
+        loop {
+            let reply: Option<Vec<Vec<String>>> = invocation
+                .invoke(&mut *self.connection()?)
+                .map_err(redis_error)?;
+            match reply {
+                // Callers run under `off_runtime`, so only this thread waits
+                None if Instant::now() < deadline => std::thread::sleep(LOCK_RETRY_DELAY),
+                None => return Err(self.timed_out(client_ids)),
+                Some(stored) => {
+                    let accounts = stored
+                        .iter()
+                        .zip(client_ids)
+                        .map(|(fields, &client_id)| account_from_fields(client_id, fields))
+                        .collect::<Result<_, _>>();
+                    return match accounts {
+                        Ok(accounts) => Ok((token, accounts)),
+                        Err(e) => {
+                            self.unlock(client_ids, &token, None)?;
+                            Err(e)
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    /// Store `accounts` (if any) and release the locks taken with `token`
+    fn unlock(
+        &self,
+        client_ids: &[ClientId],
+        token: &str,
+        accounts: Option<&[ClientAccount<A>]>,
+    ) -> Result<(), StorageError> {
+        let mut invocation = self.locking(&self.unlock_script, client_ids);
+        invocation.arg(token);
+        for account in accounts.into_iter().flatten() {
+            let fields = account_fields(account);
+            invocation.arg(fields.len() * 2);
+            for (name, value) in fields {
+                invocation.arg(name).arg(value);
+            }
+        }
+
+        let stored: i64 = invocation
+            .invoke(&mut *self.connection()?)
+            .map_err(redis_error)?;
+        match stored {
+            1 => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("lock on accounts {client_ids:?} expired before the update was stored"),
+            )
+            .into()),
+        }
+    }
+
+    /// Lock, update and store the accounts, releasing the locks on failure
+    fn update(
+        &self,
+        client_ids: &[ClientId],
+        update_fn: impl FnOnce(&mut [ClientAccount<A>]) -> Result<(), DomainError>,
+    ) -> Result<(), StorageError> {
+        off_runtime(|| self.update_blocking(client_ids, update_fn))
+    }
+
+    fn update_blocking(
+        &self,
+        client_ids: &[ClientId],
+        update_fn: impl FnOnce(&mut [ClientAccount<A>]) -> Result<(), DomainError>,
+    ) -> Result<(), StorageError> {
+        let (token, mut accounts) = self.lock(client_ids)?;
+        if let Err(e) = update_fn(&mut accounts) {
+            self.unlock(client_ids, &token, None)?;
+            return Err(e.into());
+        }
+        self.unlock(client_ids, &token, Some(&accounts))
+    }
+
+    /// Visit every stored account, a `SCAN` batch at a time
+    fn scan_accounts(&self, visit: &mut dyn FnMut(ClientAccount<A>)) -> Result<(), StorageError> {
+        off_runtime(|| self.scan_accounts_blocking(visit))
+    }
+
+    fn scan_accounts_blocking(
+        &self,
+        visit: &mut dyn FnMut(ClientAccount<A>),
+    ) -> Result<(), StorageError> {
+        let key_start = format!("{}:account:", self.key_prefix);
+        let pattern = format!("{key_start}*");
+        let mut connection = self.connection()?;
+        let mut cursor = 0u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(SCAN_BATCH)
+                .query(&mut *connection)
+                .map_err(redis_error)?;
+
+            if !keys.is_empty() {
+                let mut pipe = redis::pipe();
+                for key in &keys {
+                    pipe.cmd("HGETALL").arg(key);
+                }
+                let stored: Vec<Vec<String>> = pipe.query(&mut *connection).map_err(redis_error)?;
+                for (key, fields) in keys.iter().zip(stored) {
+                    // Accounts deleted since the scan come back empty
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    let client_id = key
+                        .strip_prefix(&key_start)
+                        .and_then(|id| id.parse().ok())
+                        .ok_or_else(|| invalid(format!("{key} is not an account key")))?;
+                    visit(account_from_fields(client_id, &fields)?);
+                }
+            }
+            match next {
+                0 => return Ok(()),
+                next => cursor = next,
+            }
+        }
+    }
+}
+
+fn build_pool(client: &Client, size: u32) -> Pool<Client> {
+    Pool::builder()
+        .max_size(size)
+        .build_unchecked(client.clone())
+}
+
+/// Run blocking Redis work from synchronous storage calls without stalling
+/// the async runtime
+///
+/// On a multi-threaded tokio runtime the calling worker hands its other tasks
+/// to another thread for the duration (`block_in_place`); elsewhere (plain
+/// threads, current-thread runtimes) `work` simply runs. Do not nest calls.
+fn off_runtime<R>(work: impl FnOnce() -> R) -> R {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(work)
+        }
+        _ => work(),
+    }
+}
+
+fn redis_error(e: impl std::error::Error + Send + Sync + 'static) -> StorageError {
+    io::Error::other(e).into()
+}
+
+fn invalid(reason: impl Into<String>) -> StorageError {
+    io::Error::new(io::ErrorKind::InvalidData, reason.into()).into()
+}
+
+/// The `DomainError` named in an `APPLY_SCRIPT` reply
+fn script_error(name: &str) -> StorageError {
+    match name {
+        "InvalidAmount" => DomainError::InvalidAmount.into(),
+        "AccountLocked" => DomainError::AccountLocked.into(),
+        "InsufficientFunds" => DomainError::InsufficientFunds.into(),
+        "AlreadyDisputed" => DomainError::AlreadyDisputed.into(),
+        "HoldActive" => DomainError::HoldActive.into(),
+        "NotDisputed" => DomainError::NotDisputed.into(),
+        other => invalid(format!("unexpected operation script reply {other}")),
+    }
+}
+
+/// `amount` as an integer count of minor units (`12.5` is `125000` with four
+/// decimals), the form the scripts do arithmetic on
+fn minor_units<A: AmountType>(amount: A) -> String {
+    let digits = amount
+        .to_decimal_string_with(A::DECIMALS, false)
+        .replace('.', "");
+    digits
+        .parse::<i128>()
+        .map_or(digits, |units| units.to_string())
+}
+
+/// An amount written by `minor_units`
+fn from_minor_units<A: AmountType>(units: &str) -> Result<A, StorageError> {
+    let value: i128 = units
+        .parse()
+        .map_err(|_| invalid(format!("amount {units} is not in minor units")))?;
+    let digits = format!("{:0width$}", value.unsigned_abs(), width = A::DECIMALS + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - A::DECIMALS);
+    let sign = if value < 0 { "-" } else { "" };
+    let decimal = match fraction.is_empty() {
+        true => format!("{sign}{whole}"),
+        false => format!("{sign}{whole}.{fraction}"),
+    };
+    Ok(A::from_decimal_str(&decimal)?)
+}
+
+/// Transaction ids as `APPLY_SCRIPT` searches them: space separated, with a
+/// space before the first and after the last
+fn id_list(ids: impl Iterator<Item = TransactionId>) -> Option<String> {
+    let list: String = ids.map(|tx_id| format!(" {tx_id}")).collect();
+    (!list.is_empty()).then(|| list + " ")
+}
+
+fn parse_ids(list: &str) -> Result<Vec<TransactionId>, StorageError> {
+    list.split_whitespace()
+        .map(|tx_id| {
+            tx_id
+                .parse()
+                .map_err(|_| invalid(format!("bad transaction id {tx_id}")))
+        })
+        .collect()
+}
+
+/// The hash fields an account is stored as
+///
+/// Balances, the credit limit and the lock flag are always written; empty id
+/// sets are left out. Other currencies are stored as `currency:<code>`
+/// (available and held, space separated) and tags as `tag:<key>`.
+fn account_fields<A: AmountType>(account: &ClientAccount<A>) -> Vec<(String, String)> {
+    let mut fields = vec![
+        ("available".to_string(), minor_units(account.available())),
+        ("held".to_string(), minor_units(account.held())),
+        (
+            "credit_limit".to_string(),
+            minor_units(account.credit_limit()),
+        ),
+        (
+            "locked".to_string(),
+            u8::from(account.is_locked()).to_string(),
+        ),
+    ];
+    if let Some(ids) = id_list(account.disputed_ids()) {
+        fields.push(("disputed".to_string(), ids));
+    }
+    if let Some(ids) = id_list(account.hold_ids()) {
+        fields.push(("holds".to_string(), ids));
+    }
+    for (currency, balance) in account.currency_balances() {
+        fields.push((
+            format!("currency:{}", currency.as_str()),
+            format!(
+                "{} {}",
+                minor_units(balance.available),
+                minor_units(balance.held)
+            ),
+        ));
+    }
+    for (key, value) in account.tags() {
+        fields.push((format!("tag:{key}"), value.to_string()));
+    }
+    fields
+}
+
+/// An account from its `HGETALL` reply (names and values alternating)
+fn account_from_fields<A: AmountType>(
+    client_id: ClientId,
+    fields: &[String],
+) -> Result<ClientAccount<A>, StorageError> {
+    let mut account = ClientAccount::new(client_id);
+    for field in fields.chunks_exact(2) {
+        let (name, value) = (field[0].as_str(), field[1].as_str());
+        match name {
+            "available" => account.set_available(from_minor_units(value)?),
+            "held" => account.set_held(from_minor_units(value)?),
+            "credit_limit" => account.set_credit_limit(from_minor_units(value)?),
+            "locked" => {
+                if value == "1" {
+                    account.lock();
+                }
+            }
+            "disputed" => {
+                for tx_id in parse_ids(value)? {
+                    account.add_disputed(tx_id);
+                }
+            }
+            "holds" => {
+                for tx_id in parse_ids(value)? {
+                    account.add_hold(tx_id);
+                }
+            }
+            _ => {
+                if let Some(code) = name.strip_prefix("currency:") {
+                    let currency = code
+                        .parse()
+                        .map_err(|_| invalid(format!("bad currency {code}")))?;
+                    let (available, held) = value
+                        .split_once(' ')
+                        .ok_or_else(|| invalid(format!("bad {code} balance {value}")))?;
+                    let balance = CurrencyBalance {
+                        available: from_minor_units(available)?,
+                        held: from_minor_units(held)?,
+                    };
+                    account.set_currency_balance(currency, balance);
+                } else if let Some(key) = name.strip_prefix("tag:") {
+                    account.set_tag(key, Some(value));
+                } else {
+                    return Err(invalid(format!("unknown account field {name}")));
+                }
+            }
+        }
+    }
+    Ok(account)
+}
+
+/// Entry for an account stored in Redis
+pub struct RedisEntry<'a, A: AmountType> {
+    manager: &'a RedisAccountManager<A>,
+    client_id: ClientId,
+}
+
+impl<'a, A: AmountType> ClientAccountEntry<'a, A> for RedisEntry<'a, A> {
+    /// Current account from Redis
+    ///
+    /// A read that fails is logged and returns an empty account; use
+    /// `RedisAccountManager::get` to see the error.
+    fn read(&self) -> ClientAccount<A> {
+        match self.manager.get(self.client_id) {
+            Ok(account) => account.unwrap_or_else(|| ClientAccount::new(self.client_id)),
+            Err(e) => {
+                warn!(
+                    client_id = self.client_id,
+                    "Failed to read account from Redis: {}", e
+                );
+                ClientAccount::new(self.client_id)
+            }
+        }
+    }
+
+    fn try_update<F>(&mut self, update_fn: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        self.manager
+            .update(&[self.client_id], |accounts| update_fn(&mut accounts[0]))
+    }
+
+    /// Run the operation in Redis when the operation script handles it,
+    /// and through `try_update` otherwise
+    fn try_apply(&mut self, op: &AccountOp<A>) -> Result<Option<A>, StorageError> {
+        if let Some(result) = self.manager.apply_in_script(self.client_id, op) {
+            return result;
+        }
+        let mut held = None;
+        self.try_update(|account| {
+            held = op.apply(account)?;
+            Ok(())
+        })?;
+        Ok(held)
+    }
+}
+
+#[async_trait]
+impl<A: AmountType> ClientAccountManager<A> for RedisAccountManager<A> {
+    type Entry<'a>
+        = RedisEntry<'a, A>
+    where
+        Self: 'a;
+
+    fn entry(&self, client_id: ClientId) -> Result<Self::Entry<'_>, StorageError> {
+        // Accounts are read and created by the update itself
+        Ok(RedisEntry {
+            manager: self,
+            client_id,
+        })
+    }
+
+    fn try_update_pair<F>(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        if first_id == second_id {
+            return Err(DomainError::SelfTransfer.into());
+        }
+        self.update(&[first_id, second_id], |accounts| {
+            let (first, second) = accounts.split_at_mut(1);
+            update_fn(&mut first[0], &mut second[0])
+        })
+    }
+
+    fn get(&self, client_id: ClientId) -> Result<Option<ClientAccount<A>>, StorageError> {
+        let fields: Vec<String> = off_runtime(|| {
+            redis::cmd("HGETALL")
+                .arg(self.account_key(client_id))
+                .query(&mut *self.connection()?)
+                .map_err(redis_error)
+        })?;
+        match fields.is_empty() {
+            true => Ok(None),
+            false => account_from_fields(client_id, &fields).map(Some),
+        }
+    }
+
+    async fn snapshot_with_format<W>(
+        &self,
+        writer: W,
+        format: &SnapshotFormat,
+    ) -> Result<(), StorageError>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        // Read every account before writing, so no connection is held across an await
+        let mut accounts = Vec::new();
+        self.scan_accounts(&mut |account| accounts.push(account))?;
+        accounts.sort_unstable_by_key(|account| account.client_id());
+
+        let mut writer = SnapshotWriter::new(writer);
+        writer
+            .buffer()
+            .extend_from_slice(format.header().as_bytes());
+        for account in &accounts {
+            format.write_row(account, writer.buffer())?;
+            writer.write_if_full().await?;
+        }
+
+        writer.finish().await?;
+        Ok(())
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &ClientAccount<A>> + Send + '_> {
+        // Accounts live in Redis; there is nothing to borrow
+        Box::new(std::iter::empty())
+    }
+
+    fn for_each_account(&self, visit: &mut dyn FnMut(&ClientAccount<A>)) {
+        if let Err(e) = self.scan_accounts(&mut |account| visit(&account)) {
+            warn!("Failed to read accounts from Redis: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::domain::{FixedPoint, PairOp, Transaction, operations};
+    use crate::storage::ConcurrentTransactionStore;
+    use crate::streaming::{SkipErrors, StreamProcessor};
+
+    fn amount(raw: i64) -> FixedPoint {
+        FixedPoint::from_raw(raw)
+    }
+
+    fn stored(account: &ClientAccount<FixedPoint>) -> Vec<String> {
+        account_fields(account)
+            .into_iter()
+            .flat_map(|(name, value)| [name, value])
+            .collect()
+    }
+
+    #[test]
+    fn stored_accounts_keep_every_field() {
+        let mut account = ClientAccount::<FixedPoint>::new(3);
+        operations::apply_deposit(&mut account, amount(25_000)).unwrap();
+        account.set_held(amount(5_000));
+        account.set_credit_limit(amount(1_000));
+        account.add_disputed(9);
+        account.add_disputed(12);
+        account.add_hold(4);
+        account.set_currency_balance(
+            "EUR".parse().unwrap(),
+            CurrencyBalance {
+                available: amount(-7_500),
+                held: amount(100),
+            },
+        );
+        account.set_tag("tier", Some("gold plus"));
+        account.lock();
+
+        let decoded = account_from_fields(3, &stored(&account)).unwrap();
+
+        assert_eq!(decoded, account);
+    }
+
+    #[test]
+    fn amounts_are_stored_in_minor_units() {
+        assert_eq!(minor_units(amount(125_000)), "125000");
+        assert_eq!(minor_units(amount(-5)), "-5");
+        assert_eq!(minor_units(amount(0)), "0");
+        assert_eq!(from_minor_units::<FixedPoint>("-5").unwrap(), amount(-5));
+        assert_eq!(
+            from_minor_units::<FixedPoint>("125000").unwrap(),
+            amount(125_000)
+        );
+    }
+
+    #[test]
+    fn dispute_ids_are_delimited_for_the_script() {
+        let mut account = ClientAccount::<FixedPoint>::new(1);
+        assert_eq!(id_list(account.disputed_ids()), None);
+        account.add_disputed(7);
+        account.add_disputed(17);
+
+        assert_eq!(id_list(account.disputed_ids()).unwrap(), " 7 17 ");
+    }
+
+    #[test]
+    fn foreign_fields_are_rejected() {
+        let fields = |pairs: &[&str]| pairs.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(account_from_fields::<FixedPoint>(1, &fields(&["owner", "x"])).is_err());
+        assert!(account_from_fields::<FixedPoint>(1, &fields(&["held", "1.5"])).is_err());
+        assert!(account_from_fields::<FixedPoint>(1, &fields(&["currency:EUR", "5"])).is_err());
+    }
+
+    #[test]
+    fn script_errors_map_to_domain_errors() {
+        assert!(matches!(
+            script_error("AlreadyDisputed"),
+            StorageError::DomainError(DomainError::AlreadyDisputed)
+        ));
+        assert!(matches!(script_error("Teapot"), StorageError::IoError(_)));
+    }
+
+    // The tests below need a Redis server; run them with
+    // `REDIS_ADDRESS=127.0.0.1:6379 cargo test --features redis -- --ignored`
+
+    fn manager(test: &str) -> RedisAccountManager<FixedPoint> {
+        let address = std::env::var("REDIS_ADDRESS").unwrap_or_else(|_| "127.0.0.1:6379".into());
+        RedisAccountManager::connect(&address)
+            .unwrap()
+            .with_key_prefix(format!("pay-test-{test}-{}", std::process::id()))
+    }
+
+    #[test]
+    #[ignore]
+    fn updates_round_trip_through_redis() {
+        let manager = manager("round-trip");
+
+        manager
+            .entry(1)
+            .unwrap()
+            .try_apply(&AccountOp::Deposit {
+                amount: amount(30_000),
+                currency: None,
+            })
+            .unwrap();
+        manager
+            .try_apply_pair(
+                1,
+                2,
+                &PairOp::Transfer {
+                    amount: amount(10_000),
+                    currency: None,
+                },
+            )
+            .unwrap();
+        let failed = manager.try_apply_pair(
+            1,
+            2,
+            &PairOp::Transfer {
+                amount: amount(90_000),
+                currency: None,
+            },
+        );
+
+        assert!(failed.is_err());
+        assert_eq!(manager.entry(1).unwrap().read().available(), amount(20_000));
+        assert_eq!(manager.get(2).unwrap().unwrap().available(), amount(10_000));
+        assert_eq!(manager.get(3).unwrap(), None);
+
+        let mut clients = Vec::new();
+        manager.for_each_account(&mut |account| clients.push(account.client_id()));
+        clients.sort_unstable();
+        assert_eq!(clients, vec![1, 2]);
+    }
+
+    #[test]
+    #[ignore]
+    fn scripted_operations_match_the_domain_rules() {
+        let manager = manager("scripted");
+        let policy = DisputePolicy {
+            allow_negative_balance: true,
+            ..DisputePolicy::default()
+        };
+        let ops = [
+            AccountOp::Deposit {
+                amount: amount(50_000),
+                currency: None,
+            },
+            AccountOp::Deposit {
+                amount: amount(0),
+                currency: None,
+            },
+            AccountOp::Withdrawal {
+                amount: amount(80_000),
+                currency: None,
+            },
+            AccountOp::Withdrawal {
+                amount: amount(30_000),
+                currency: None,
+            },
+            AccountOp::Dispute {
+                tx_id: 1,
+                amount: amount(50_000),
+                currency: None,
+                policy,
+            },
+            AccountOp::Dispute {
+                tx_id: 1,
+                amount: amount(50_000),
+                currency: None,
+                policy,
+            },
+            AccountOp::Resolve {
+                tx_id: 2,
+                held: amount(20_000),
+                currency: None,
+                policy,
+            },
+            AccountOp::Chargeback {
+                tx_id: 1,
+                amount: amount(50_000),
+                held: amount(20_000),
+                currency: None,
+                policy,
+            },
+            AccountOp::Deposit {
+                amount: amount(10_000),
+                currency: None,
+            },
+        ];
+
+        let mut local = ClientAccount::new(1);
+        let mut entry = manager.entry(1).unwrap();
+        for op in &ops {
+            let expected = op.apply(&mut local).map_err(StorageError::from);
+            assert_eq!(
+                format!("{:?}", entry.try_apply(op)),
+                format!("{expected:?}"),
+                "{op:?}"
+            );
+            assert_eq!(entry.read(), local, "{op:?}");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ignore]
+    async fn concurrent_shards_share_accounts() {
+        let mut processor = StreamProcessor::new(
+            manager("shards"),
+            ConcurrentTransactionStore::new(),
+            SkipErrors,
+        )
+        .with_shards(4);
+        // Every stream deposits into the same three clients
+        for stream_index in 0..4 {
+            let deposits = (0..30).map(move |i| {
+                Ok(Transaction::Deposit {
+                    client_id: (i % 3 + 1) as ClientId,
+                    tx_id: (stream_index * 100 + i) as TransactionId,
+                    amount: amount(10_000),
+                    currency: None,
+                })
+            });
+            processor = processor.add_stream(stream::iter(deposits));
+        }
+
+        let results = processor.process().await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.stats.accounts, 3);
+        assert_eq!(results.stats.total_available, amount(1_200_000));
+    }
+}
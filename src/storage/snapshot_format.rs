@@ -0,0 +1,452 @@
+use std::io;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::domain::rounding::{format_decimal_str, push_digits};
+use crate::domain::{
+    AmountType, ClientAccount, CurrencyCode, DomainError, InputPrecision, RoundingPolicy,
+};
+
+/// Snapshot bytes buffered before each write to the underlying writer
+pub(crate) const SNAPSHOT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Number formatting for account snapshots
+///
+/// The default reproduces the standard output: `,`-delimited columns with
+/// amounts at 4 fixed decimals (e.g. `1.5000`). Downstream systems that expect
+/// other layouts can reduce precision, trim trailing zeros, match the
+/// precision of the inputs or use a locale decimal separator.
+///
+/// # Example
+/// ```rust,ignore
+/// let format = SnapshotFormat::default()
+///     .with_decimals(2)
+///     .with_decimal_separator(',')
+///     .with_delimiter(';');
+/// account_manager.snapshot_with_format(writer, &format).await?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFormat {
+    decimals: usize,
+    trim_trailing_zeros: bool,
+    decimal_separator: char,
+    delimiter: char,
+    rounding: RoundingPolicy,
+    currency_column: bool,
+    credit_limit_column: bool,
+}
+
+impl Default for SnapshotFormat {
+    fn default() -> Self {
+        Self {
+            decimals: 4,
+            trim_trailing_zeros: false,
+            decimal_separator: '.',
+            delimiter: ',',
+            rounding: RoundingPolicy::HalfEven,
+            currency_column: false,
+            credit_limit_column: false,
+        }
+    }
+}
+
+impl SnapshotFormat {
+    /// Fixed number of decimal places to write (defaults to 4)
+    ///
+    /// Fewer places than the amount type stores are rounded using the
+    /// configured rounding policy; more places are zero-padded.
+    pub fn with_decimals(mut self, decimals: usize) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Write as many decimal places as the inputs had, as recorded by `precision`
+    ///
+    /// Call this once the inputs have been read, since the places are fixed
+    /// when it is called; amounts are written with the most places any input
+    /// amount had (0 if none had decimals), so inputs quoted as `1.5` are
+    /// written back as `1.5` rather than `1.5000`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let precision = InputPrecision::new();
+    /// let options = CsvReaderOptions::default().with_input_precision(precision.clone());
+    /// // ... process streams read with `options` ...
+    /// let format = SnapshotFormat::default().with_input_precision(&precision);
+    /// ```
+    pub fn with_input_precision(self, precision: &InputPrecision) -> Self {
+        self.with_decimals(precision.decimals())
+    }
+
+    /// Drop trailing fractional zeros, e.g. `1.5000` -> `1.5`, `2.0000` -> `2` (defaults to false)
+    pub fn with_trailing_zeros_trimmed(mut self, trim: bool) -> Self {
+        self.trim_trailing_zeros = trim;
+        self
+    }
+
+    /// Decimal separator for amounts (defaults to `.`)
+    pub fn with_decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Column delimiter (defaults to `,`)
+    ///
+    /// Use a different delimiter, such as `;`, together with a `,` decimal separator.
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Rounding applied when writing fewer decimals than stored (defaults to `HalfEven`)
+    pub fn with_rounding(mut self, rounding: RoundingPolicy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Add a `currency` column with one row per (client, currency) (defaults to false)
+    ///
+    /// The base-currency row has an empty currency. Without this column only
+    /// base-currency balances are written, matching the single-currency format.
+    pub fn with_currency_column(mut self, enabled: bool) -> Self {
+        self.currency_column = enabled;
+        self
+    }
+
+    /// Add a trailing `credit_limit` column with each account's credit limit (defaults to false)
+    pub fn with_credit_limit_column(mut self, enabled: bool) -> Self {
+        self.credit_limit_column = enabled;
+        self
+    }
+
+    /// Header line including the trailing newline
+    pub fn header(&self) -> String {
+        let d = self.delimiter;
+        let mut header = format!("client{d}");
+        if self.currency_column {
+            header.push_str(&format!("currency{d}"));
+        }
+        header.push_str(&format!("available{d}held{d}total{d}locked"));
+        if self.credit_limit_column {
+            header.push_str(&format!("{d}credit_limit"));
+        }
+        header.push('\n');
+        header
+    }
+
+    /// Format a single amount
+    pub fn format_amount<A: AmountType>(&self, amount: A) -> Result<String, DomainError> {
+        let mut value = format_decimal_str(
+            &amount.to_decimal_string(),
+            self.decimals,
+            self.trim_trailing_zeros,
+            self.rounding,
+        )?;
+
+        if self.decimal_separator != '.' {
+            value = value.replace('.', &self.decimal_separator.to_string());
+        }
+
+        Ok(value)
+    }
+
+    /// Format one account as snapshot rows, each including the trailing newline
+    ///
+    /// Produces a single row unless the currency column is enabled and the
+    /// account holds non-base currencies.
+    pub fn format_row<A: AmountType>(
+        &self,
+        account: &ClientAccount<A>,
+    ) -> Result<String, DomainError> {
+        let mut rows = Vec::new();
+        self.write_row(account, &mut rows)?;
+        Ok(String::from_utf8(rows).expect("snapshot rows are UTF-8"))
+    }
+
+    /// Append one account's `format_row` rows to `out`
+    ///
+    /// With the amount type's own number of decimals, a `.` separator and
+    /// trailing zeros kept (the defaults), nothing is allocated per row.
+    pub fn write_row<A: AmountType>(
+        &self,
+        account: &ClientAccount<A>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), DomainError> {
+        self.write_balance_row(account, None, account.available(), account.held(), out)?;
+
+        if self.currency_column {
+            for (currency, balance) in account.currency_balances() {
+                self.write_balance_row(
+                    account,
+                    Some(currency),
+                    balance.available,
+                    balance.held,
+                    out,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_balance_row<A: AmountType>(
+        &self,
+        account: &ClientAccount<A>,
+        currency: Option<CurrencyCode>,
+        available: A,
+        held: A,
+        out: &mut Vec<u8>,
+    ) -> Result<(), DomainError> {
+        push_digits(out, u64::from(account.client_id()));
+        self.write_delimiter(out);
+        if self.currency_column {
+            if let Some(currency) = currency {
+                out.extend_from_slice(currency.as_str().as_bytes());
+            }
+            self.write_delimiter(out);
+        }
+        self.write_amount(available, out)?;
+        self.write_delimiter(out);
+        self.write_amount(held, out)?;
+        self.write_delimiter(out);
+        self.write_amount(available + held, out)?;
+        self.write_delimiter(out);
+        out.extend_from_slice(if account.is_locked() {
+            b"true"
+        } else {
+            b"false"
+        });
+        if self.credit_limit_column {
+            self.write_delimiter(out);
+            self.write_amount(account.credit_limit(), out)?;
+        }
+        out.push(b'\n');
+        Ok(())
+    }
+
+    fn write_amount<A: AmountType>(&self, amount: A, out: &mut Vec<u8>) -> Result<(), DomainError> {
+        if self.decimals == A::DECIMALS
+            && !self.trim_trailing_zeros
+            && self.decimal_separator == '.'
+        {
+            amount.write_decimal(out);
+        } else {
+            out.extend_from_slice(self.format_amount(amount)?.as_bytes());
+        }
+        Ok(())
+    }
+
+    fn write_delimiter(&self, out: &mut Vec<u8>) {
+        let mut encoded = [0u8; 4];
+        out.extend_from_slice(self.delimiter.encode_utf8(&mut encoded).as_bytes());
+    }
+}
+
+/// Snapshot output collected in memory and written in `SNAPSHOT_BUFFER_SIZE`
+/// chunks, so a snapshot awaits the writer once per chunk rather than per row
+pub(crate) struct SnapshotWriter<W> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: AsyncWrite + Unpin + Send> SnapshotWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::with_capacity(SNAPSHOT_BUFFER_SIZE),
+        }
+    }
+
+    /// Buffer to append rows to; call `write_if_full` afterwards
+    pub(crate) fn buffer(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+
+    /// Write the buffered bytes once they reach `SNAPSHOT_BUFFER_SIZE`,
+    /// returning whether anything was written
+    pub(crate) async fn write_if_full(&mut self) -> io::Result<bool> {
+        if self.buffer.len() < SNAPSHOT_BUFFER_SIZE {
+            return Ok(false);
+        }
+        self.writer.write_all(&self.buffer).await?;
+        self.buffer.clear();
+        Ok(true)
+    }
+
+    /// Write whatever is buffered and flush the writer
+    pub(crate) async fn finish(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.buffer).await?;
+        self.buffer.clear();
+        self.writer.flush().await
+    }
+
+    /// The underlying writer; bytes not yet written by `finish` are dropped
+    pub(crate) fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn amount(raw: i64) -> FixedPoint {
+        FixedPoint::from_raw(raw)
+    }
+
+    #[test]
+    fn default_matches_standard_output() {
+        let format = SnapshotFormat::default();
+        assert_eq!(format.header(), "client,available,held,total,locked\n");
+        assert_eq!(format.format_amount(amount(15_000)).unwrap(), "1.5000");
+        assert_eq!(format.format_amount(amount(-1)).unwrap(), "-0.0001");
+    }
+
+    #[test]
+    fn two_decimals_rounds_half_even() {
+        let format = SnapshotFormat::default().with_decimals(2);
+        assert_eq!(format.format_amount(amount(12_350)).unwrap(), "1.24");
+        assert_eq!(format.format_amount(amount(12_250)).unwrap(), "1.22");
+        assert_eq!(format.format_amount(amount(10_000)).unwrap(), "1.00");
+    }
+
+    #[test]
+    fn rounding_to_zero_drops_sign() {
+        let format = SnapshotFormat::default().with_decimals(2);
+        assert_eq!(format.format_amount(amount(-1)).unwrap(), "0.00");
+    }
+
+    #[test]
+    fn extra_decimals_are_zero_padded() {
+        let format = SnapshotFormat::default().with_decimals(6);
+        assert_eq!(format.format_amount(amount(15_000)).unwrap(), "1.500000");
+    }
+
+    #[test]
+    fn trims_trailing_zeros() {
+        let format = SnapshotFormat::default().with_trailing_zeros_trimmed(true);
+        assert_eq!(format.format_amount(amount(15_000)).unwrap(), "1.5");
+        assert_eq!(format.format_amount(amount(20_000)).unwrap(), "2");
+        assert_eq!(format.format_amount(amount(0)).unwrap(), "0");
+    }
+
+    #[test]
+    fn input_precision_sets_decimals() {
+        let precision = InputPrecision::new();
+        precision.record::<FixedPoint>(b"1.5");
+        let format = SnapshotFormat::default().with_input_precision(&precision);
+        assert_eq!(format.format_amount(amount(15_000)).unwrap(), "1.5");
+        assert_eq!(format.format_amount(amount(20_000)).unwrap(), "2.0");
+        assert_eq!(format.format_amount(amount(12_500)).unwrap(), "1.2");
+    }
+
+    #[test]
+    fn locale_separator_and_delimiter() {
+        let format = SnapshotFormat::default()
+            .with_decimals(2)
+            .with_decimal_separator(',')
+            .with_delimiter(';');
+        let mut account = ClientAccount::new(7);
+        crate::domain::apply_deposit(&mut account, amount(12_345)).unwrap();
+
+        assert_eq!(format.header(), "client;available;held;total;locked\n");
+        assert_eq!(
+            format.format_row(&account).unwrap(),
+            "7;1,23;0,00;1,23;false\n"
+        );
+    }
+
+    #[test]
+    fn reject_rounding_fails_on_lost_precision() {
+        let format = SnapshotFormat::default()
+            .with_decimals(2)
+            .with_rounding(RoundingPolicy::Reject);
+        assert!(format.format_amount(amount(12_345)).is_err());
+        assert_eq!(format.format_amount(amount(12_300)).unwrap(), "1.23");
+    }
+
+    #[test]
+    fn currency_column_writes_row_per_currency() {
+        let mut account = ClientAccount::<FixedPoint>::new(3);
+        crate::domain::apply_deposit(&mut account, amount(10_000)).unwrap();
+        crate::domain::apply_in_currency(&mut account, Some("USD".parse().unwrap()), |acc| {
+            crate::domain::apply_deposit(acc, amount(25_000))
+        })
+        .unwrap();
+
+        let plain = SnapshotFormat::default();
+        assert_eq!(
+            plain.format_row(&account).unwrap(),
+            "3,1.0000,0.0000,1.0000,false\n"
+        );
+
+        let format = SnapshotFormat::default().with_currency_column(true);
+        assert_eq!(
+            format.header(),
+            "client,currency,available,held,total,locked\n"
+        );
+        assert_eq!(
+            format.format_row(&account).unwrap(),
+            "3,,1.0000,0.0000,1.0000,false\n3,USD,2.5000,0.0000,2.5000,false\n"
+        );
+    }
+
+    #[test]
+    fn fast_path_matches_general_formatting() {
+        let format = SnapshotFormat::default();
+        for raw in [0, 1, -1, 12_345_677, -99_990_000, i64::MAX] {
+            let mut out = Vec::new();
+            format.write_amount(amount(raw), &mut out).unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                format.format_amount(amount(raw)).unwrap()
+            );
+        }
+
+        let mut account = ClientAccount::<FixedPoint>::new(65_535);
+        crate::domain::apply_deposit(&mut account, amount(12_345_677)).unwrap();
+        account.lock();
+        let mut out = Vec::new();
+        format.write_row(&account, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "65535,1234.5677,0.0000,1234.5677,true\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn writer_buffers_until_full() {
+        let mut output = Vec::new();
+        let mut writer = SnapshotWriter::new(&mut output);
+        writer.buffer().extend_from_slice(b"client\n");
+        assert!(!writer.write_if_full().await.unwrap());
+
+        writer.buffer().resize(SNAPSHOT_BUFFER_SIZE, b'x');
+        assert!(writer.write_if_full().await.unwrap());
+        writer.buffer().extend_from_slice(b"tail\n");
+        writer.finish().await.unwrap();
+
+        assert_eq!(output.len(), SNAPSHOT_BUFFER_SIZE + 5);
+        assert!(output.starts_with(b"client\n") && output.ends_with(b"xtail\n"));
+    }
+
+    #[test]
+    fn credit_limit_column_is_last() {
+        let mut account = ClientAccount::<FixedPoint>::new(5);
+        crate::domain::apply_set_credit_limit(&mut account, amount(1_000_000)).unwrap();
+        crate::domain::apply_withdrawal(&mut account, amount(25_000)).unwrap();
+
+        let format = SnapshotFormat::default()
+            .with_credit_limit_column(true)
+            .with_delimiter(';');
+        assert_eq!(
+            format.header(),
+            "client;available;held;total;locked;credit_limit\n"
+        );
+        assert_eq!(
+            format.format_row(&account).unwrap(),
+            "5;-2.5000;0.0000;-2.5000;false;100.0000\n"
+        );
+    }
+}
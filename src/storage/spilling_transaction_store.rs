@@ -0,0 +1,511 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use tracing::{debug, warn};
+
+use super::traits::TransactionStoreManager;
+use crate::domain::{
+    AmountType, ClientId, CurrencyCode, TransactionId, TransactionRecord, TxKind, TxState,
+};
+
+/// On-disk entry: tx id (u64 LE), client (u32 LE), currency (3 bytes, zero if none),
+/// kind (1 byte), state (1 byte), disputes (u32 LE), amount (decimal string, zero-padded),
+/// held amount of a partly held dispute (decimal string, zero-padded, empty if none)
+const AMOUNT_WIDTH: usize = 32;
+const AMOUNT_OFFSET: usize = 8 + 4 + 3 + 2 + 4;
+const HELD_OFFSET: usize = AMOUNT_OFFSET + AMOUNT_WIDTH;
+const ENTRY_SIZE: usize = HELD_OFFSET + AMOUNT_WIDTH;
+
+/// On-disk codes, by position
+const KINDS: [TxKind; 4] = [
+    TxKind::Deposit,
+    TxKind::Withdrawal,
+    TxKind::Transfer,
+    TxKind::Hold,
+];
+const STATES: [TxState; 5] = [
+    TxState::Posted,
+    TxState::Disputed,
+    TxState::Resolved,
+    TxState::ChargedBack,
+    TxState::Reversed,
+];
+
+/// Transaction store that spills older records to disk
+///
+/// Recent records live in memory in two generations. Once the young
+/// generation holds `max_in_memory / 2` records, the old generation is written
+/// to disk as a sorted run file and the young generation takes its place, so
+/// memory stays bounded at roughly `max_in_memory` records however long the
+/// input is. Lookups check memory first, then the runs from newest to oldest
+/// with a binary search, so disputes of spilled transactions still work (at the
+/// cost of a few disk reads).
+///
+/// Run files are written to `dir` and removed when the store is dropped. A
+/// spill briefly blocks other inserts and lookups while the run is written.
+pub struct SpillingTransactionStore<A: AmountType> {
+    dir: PathBuf,
+    generation_size: usize,
+    memory: RwLock<Generations<A>>,
+    runs: RwLock<Vec<SpillRun>>,
+    spilled: AtomicU64,
+}
+
+struct Generations<A: AmountType> {
+    young: DashMap<TransactionId, TransactionRecord<A>>,
+    old: DashMap<TransactionId, TransactionRecord<A>>,
+}
+
+/// One spilled generation, sorted by tx id
+struct SpillRun {
+    path: PathBuf,
+    file: Mutex<File>,
+    len: u64,
+    min: u64,
+    max: u64,
+}
+
+impl<A: AmountType> SpillingTransactionStore<A> {
+    /// Create a store keeping about `max_in_memory` records in memory (minimum 2)
+    ///
+    /// `dir` is created if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>, max_in_memory: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            generation_size: (max_in_memory / 2).max(1),
+            memory: RwLock::new(Generations {
+                young: DashMap::new(),
+                old: DashMap::new(),
+            }),
+            runs: RwLock::new(Vec::new()),
+            spilled: AtomicU64::new(0),
+        })
+    }
+
+    /// Directory holding the run files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of records currently held in memory
+    pub fn in_memory(&self) -> usize {
+        let memory = self.memory.read();
+        memory.young.len() + memory.old.len()
+    }
+
+    /// Total number of records written to disk so far
+    pub fn spilled(&self) -> u64 {
+        self.spilled.load(Ordering::Relaxed)
+    }
+
+    /// Number of run files on disk
+    pub fn run_count(&self) -> usize {
+        self.runs.read().len()
+    }
+
+    fn insert_record(&self, tx_id: TransactionId, record: TransactionRecord<A>) {
+        let young_len = {
+            let memory = self.memory.read();
+            memory.young.insert(tx_id, record);
+            memory.young.len()
+        };
+
+        if young_len >= self.generation_size {
+            self.rotate();
+        }
+    }
+
+    /// Spill the old generation and age the young one
+    fn rotate(&self) {
+        let mut memory = self.memory.write();
+        // Another inserter may have rotated while we waited for the lock
+        if memory.young.len() < self.generation_size {
+            return;
+        }
+
+        if !memory.old.is_empty() {
+            let path = self
+                .dir
+                .join(format!("run-{}.spill", self.runs.read().len()));
+            match SpillRun::write(path, &memory.old) {
+                Ok(run) => {
+                    debug!(records = run.len, path = %run.path.display(), "Spilled transaction records");
+                    self.spilled.fetch_add(run.len, Ordering::Relaxed);
+                    self.runs.write().push(run);
+                }
+                Err(e) => {
+                    // Keep everything in memory rather than lose records
+                    warn!("Failed to spill transaction records: {}", e);
+                    return;
+                }
+            }
+        }
+
+        memory.old = std::mem::take(&mut memory.young);
+    }
+
+    fn get_record(&self, tx_id: TransactionId) -> Option<TransactionRecord<A>> {
+        {
+            let memory = self.memory.read();
+            if let Some(record) = memory.young.get(&tx_id).or_else(|| memory.old.get(&tx_id)) {
+                return Some(record.clone());
+            }
+        }
+
+        // Newer runs shadow older ones for re-inserted ids
+        self.runs.read().iter().rev().find_map(|run| match run.find(tx_id) {
+            Ok(record) => record,
+            Err(e) => {
+                warn!(tx_id, path = %run.path.display(), "Failed to read spilled record: {}", e);
+                None
+            }
+        })
+    }
+
+    fn visit_records(&self, visit: &mut dyn FnMut(TransactionId, &TransactionRecord<A>)) {
+        // Locks are always taken memory first, then runs (as in `rotate`)
+        let memory = self.memory.read();
+
+        // Oldest first, so a later visit of a re-inserted id has the current record
+        for run in self.runs.read().iter() {
+            if let Err(e) = run.for_each(visit) {
+                warn!(path = %run.path.display(), "Failed to read spilled records: {}", e);
+            }
+        }
+        for entry in memory.old.iter().chain(memory.young.iter()) {
+            visit(*entry.key(), entry.value());
+        }
+    }
+}
+
+impl SpillRun {
+    fn write<A: AmountType>(
+        path: PathBuf,
+        records: &DashMap<TransactionId, TransactionRecord<A>>,
+    ) -> io::Result<Self> {
+        let mut entries: Vec<_> = records
+            .iter()
+            .map(|entry| encode(*entry.key(), entry.value()))
+            .collect::<io::Result<_>>()?;
+        entries.sort_unstable_by_key(entry_id);
+
+        let mut writer = BufWriter::new(File::create(&path)?);
+        for entry in &entries {
+            writer.write_all(entry)?;
+        }
+        writer.flush()?;
+
+        Ok(Self {
+            file: Mutex::new(File::open(&path)?),
+            path,
+            len: entries.len() as u64,
+            min: entries.first().map_or(0, entry_id),
+            max: entries.last().map_or(0, entry_id),
+        })
+    }
+
+    fn find<A: AmountType>(
+        &self,
+        tx_id: TransactionId,
+    ) -> io::Result<Option<TransactionRecord<A>>> {
+        let target = id_bits(tx_id);
+        if self.len == 0 || target < self.min || target > self.max {
+            return Ok(None);
+        }
+
+        let mut file = self.file.lock();
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let entry = read_entry(&mut file, mid)?;
+            match entry_id(&entry).cmp(&target) {
+                std::cmp::Ordering::Equal => return decode(&entry).map(|(_, record)| Some(record)),
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        Ok(None)
+    }
+
+    fn for_each<A: AmountType>(
+        &self,
+        visit: &mut dyn FnMut(TransactionId, &TransactionRecord<A>),
+    ) -> io::Result<()> {
+        let mut file = self.file.lock();
+        for index in 0..self.len {
+            let (tx_id, record) = decode(&read_entry(&mut file, index)?)?;
+            visit(tx_id, &record);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SpillRun {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn read_entry(file: &mut File, index: u64) -> io::Result<[u8; ENTRY_SIZE]> {
+    let mut entry = [0; ENTRY_SIZE];
+    file.seek(SeekFrom::Start(index * ENTRY_SIZE as u64))?;
+    file.read_exact(&mut entry)?;
+    Ok(entry)
+}
+
+/// Tx id widened to the on-disk width (a no-op with `wide-tx-ids`)
+#[allow(clippy::useless_conversion)]
+fn id_bits(tx_id: TransactionId) -> u64 {
+    u64::from(tx_id)
+}
+
+/// Client id widened to the on-disk width (a no-op with `wide-client-ids`)
+#[allow(clippy::useless_conversion)]
+fn client_bits(client_id: ClientId) -> u32 {
+    u32::from(client_id)
+}
+
+fn entry_id(entry: &[u8; ENTRY_SIZE]) -> u64 {
+    u64::from_le_bytes(entry[..8].try_into().expect("8-byte id"))
+}
+
+fn encode<A: AmountType>(
+    tx_id: TransactionId,
+    record: &TransactionRecord<A>,
+) -> io::Result<[u8; ENTRY_SIZE]> {
+    let amount = record.amount.to_decimal_string();
+    let held = record
+        .held
+        .map(|held| held.to_decimal_string())
+        .unwrap_or_default();
+    if amount.len() > AMOUNT_WIDTH || held.len() > AMOUNT_WIDTH {
+        return Err(invalid_data("amount too wide to spill"));
+    }
+
+    let mut entry = [0; ENTRY_SIZE];
+    entry[..8].copy_from_slice(&id_bits(tx_id).to_le_bytes());
+    entry[8..12].copy_from_slice(&client_bits(record.client_id).to_le_bytes());
+    if let Some(currency) = record.currency {
+        entry[12..15].copy_from_slice(currency.as_str().as_bytes());
+    }
+    entry[15] = code(&KINDS, record.kind);
+    entry[16] = code(&STATES, record.state);
+    entry[17..21].copy_from_slice(&record.disputes.to_le_bytes());
+    entry[AMOUNT_OFFSET..AMOUNT_OFFSET + amount.len()].copy_from_slice(amount.as_bytes());
+    entry[HELD_OFFSET..HELD_OFFSET + held.len()].copy_from_slice(held.as_bytes());
+    Ok(entry)
+}
+
+fn decode<A: AmountType>(
+    entry: &[u8; ENTRY_SIZE],
+) -> io::Result<(TransactionId, TransactionRecord<A>)> {
+    let tx_id = TransactionId::try_from(entry_id(entry))
+        .map_err(|_| invalid_data("spilled tx id out of range"))?;
+    let client_id = ClientId::try_from(u32::from_le_bytes(
+        entry[8..12].try_into().expect("4-byte client"),
+    ))
+    .map_err(|_| invalid_data("spilled client id out of range"))?;
+
+    let currency = match &entry[12..15] {
+        [0, 0, 0] => None,
+        code => Some(parse_field::<CurrencyCode>(code)?),
+    };
+    let kind = *KINDS
+        .get(entry[15] as usize)
+        .ok_or_else(|| invalid_data("bad spilled kind"))?;
+    let state = *STATES
+        .get(entry[16] as usize)
+        .ok_or_else(|| invalid_data("bad spilled state"))?;
+    let disputes = u32::from_le_bytes(entry[17..21].try_into().expect("4-byte count"));
+    let amount = amount_field(&entry[AMOUNT_OFFSET..HELD_OFFSET])?
+        .ok_or_else(|| invalid_data("bad spilled amount"))?;
+    let held = amount_field(&entry[HELD_OFFSET..])?;
+
+    Ok((
+        tx_id,
+        TransactionRecord::new(client_id, amount)
+            .with_currency(currency)
+            .with_kind(kind)
+            .with_state(state)
+            .with_disputes(disputes)
+            .with_held(held),
+    ))
+}
+
+/// Zero-padded decimal amount, `None` if the field is empty
+fn amount_field<A: AmountType>(field: &[u8]) -> io::Result<Option<A>> {
+    let len = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    if len == 0 {
+        return Ok(None);
+    }
+    std::str::from_utf8(&field[..len])
+        .ok()
+        .and_then(|amount| A::from_decimal_str(amount).ok())
+        .map(Some)
+        .ok_or_else(|| invalid_data("bad spilled amount"))
+}
+
+fn code<V: PartialEq>(values: &[V], value: V) -> u8 {
+    values
+        .iter()
+        .position(|candidate| *candidate == value)
+        .expect("every value has a code") as u8
+}
+
+fn parse_field<V: std::str::FromStr>(bytes: &[u8]) -> io::Result<V> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|field| field.parse().ok())
+        .ok_or_else(|| invalid_data("bad spilled field"))
+}
+
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+impl<A: AmountType> TransactionStoreManager<A> for SpillingTransactionStore<A> {
+    fn insert(&self, tx_id: TransactionId, record: TransactionRecord<A>) {
+        self.insert_record(tx_id, record);
+    }
+
+    fn get(&self, tx_id: TransactionId) -> Option<TransactionRecord<A>> {
+        self.get_record(tx_id)
+    }
+
+    fn contains(&self, tx_id: TransactionId) -> bool {
+        self.get_record(tx_id).is_some()
+    }
+
+    fn for_each_record(&self, visit: &mut dyn FnMut(TransactionId, &TransactionRecord<A>)) {
+        self.visit_records(visit);
+    }
+
+    fn resident_records(&self) -> usize {
+        // Spilled records live on disk
+        self.in_memory()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn record(client_id: ClientId, raw: i64) -> TransactionRecord<FixedPoint> {
+        TransactionRecord::new(client_id, FixedPoint::from_raw(raw))
+    }
+
+    #[test]
+    fn spills_old_records_and_reads_them_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SpillingTransactionStore::new(dir.path(), 4).unwrap();
+
+        for tx_id in 1..=10 {
+            store.insert(tx_id, record(1, tx_id as i64 * 10_000));
+        }
+
+        assert!(store.in_memory() <= 4);
+        assert_eq!(store.spilled(), 8);
+        assert_eq!(store.run_count(), 4);
+        for tx_id in 1..=10 {
+            assert_eq!(store.get(tx_id), Some(record(1, tx_id as i64 * 10_000)));
+        }
+        assert!(!store.contains(11));
+    }
+
+    #[test]
+    fn spilled_records_keep_currency_kind_state_disputes_and_negative_amounts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SpillingTransactionStore::new(dir.path(), 2).unwrap();
+        let eur = record(7, -25_000)
+            .with_currency(Some("EUR".parse().unwrap()))
+            .with_kind(TxKind::Transfer)
+            .with_state(TxState::ChargedBack)
+            .with_disputes(3)
+            .with_held(Some(FixedPoint::from_raw(4_000)));
+
+        store.insert(1, eur.clone());
+        store.insert(2, record(7, 1));
+        store.insert(3, record(7, 1));
+
+        assert_eq!(store.spilled(), 2);
+        assert_eq!(store.get(1), Some(eur));
+    }
+
+    #[test]
+    fn reinserted_ids_shadow_spilled_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SpillingTransactionStore::new(dir.path(), 2).unwrap();
+
+        store.insert(1, record(1, 100));
+        store.insert(2, record(1, 200));
+        store.insert(3, record(1, 300));
+        store.insert(1, record(2, 999));
+
+        assert_eq!(store.get(1), Some(record(2, 999)));
+
+        let mut visited = std::collections::HashMap::new();
+        store.for_each_record(&mut |tx_id, record: &TransactionRecord<FixedPoint>| {
+            visited.insert(tx_id, record.clone());
+        });
+        assert_eq!(visited.len(), 3);
+        assert_eq!(visited[&1], record(2, 999));
+    }
+
+    #[test]
+    fn run_files_are_removed_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SpillingTransactionStore::new(dir.path(), 2).unwrap();
+        for tx_id in 1..=5 {
+            store.insert(tx_id, record(1, 1));
+        }
+        assert!(fs::read_dir(dir.path()).unwrap().count() > 0);
+
+        drop(store);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn concurrent_inserts_through_arc() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(SpillingTransactionStore::new(dir.path(), 64).unwrap());
+
+        let handles: Vec<_> = (0..4)
+            .map(|thread_id| {
+                let store = Arc::clone(&store);
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        let tx_id = thread_id * 1_000 + i;
+                        store.insert(tx_id, record(thread_id as ClientId, i as i64));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for thread_id in 0..4 {
+            for i in 0..250 {
+                let tx_id = thread_id * 1_000 + i;
+                assert_eq!(
+                    store.get(tx_id),
+                    Some(record(thread_id as ClientId, i as i64))
+                );
+            }
+        }
+        assert!(store.spilled() > 0);
+    }
+}
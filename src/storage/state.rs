@@ -0,0 +1,583 @@
+use std::io::{self, Read, Write};
+
+use super::error::StorageError;
+use super::traits::{ClientAccountEntry, ClientAccountManager, TransactionStoreManager};
+use crate::domain::{
+    AccountOp, AmountType, ClientAccount, CurrencyBalance, CurrencyCode, TransactionId,
+    TransactionRecord, TxKind, TxState,
+};
+
+/// Format marker at the start of every state export
+const MAGIC: &[u8; 8] = b"PAYSTATE";
+/// Current format version; version 1 exports (without client tags),
+/// version 2 exports (without partly held disputes) and version 3 exports
+/// (with resolved transaction ids, which are no longer kept) still load
+const VERSION: u8 = 4;
+
+// Entry tags; an export is a sequence of tagged entries closed by `END`
+const END: u8 = 0;
+const ACCOUNT: u8 = 1;
+const RECORD: u8 = 2;
+
+/// Encoded entries are passed to the writer in chunks of about this size
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Accounts and transaction records moved by `export_state` or `import_state`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateCounts {
+    pub accounts: usize,
+    pub records: usize,
+}
+
+/// Write every account and transaction record to `writer` in a compact
+/// binary format, for `import_state` to load into another instance
+///
+/// Accounts keep their balances, credit limit, lock flag, per-currency
+/// balances, tags and their disputed and held transaction ids; records
+/// keep their kind, state, dispute count and the hold of a partly held dispute. Integers are LEB128 varints and
+/// amounts their decimal digits, so an export stays readable by a build with
+/// wide transaction ids or a different amount precision. Use it to hand the
+/// state of a running service to its replacement (blue/green redeploys)
+/// without replaying the full history.
+///
+/// Storage is read without pausing writers, so stop ingestion first for a
+/// consistent export. Pass a buffered writer or not: entries are encoded in
+/// 64KB chunks either way.
+///
+/// # Example
+/// ```rust,ignore
+/// let file = File::create("state.bin")?;
+/// let counts = export_state(&*account_manager, &*transaction_store, file)?;
+/// println!("exported {} accounts, {} records", counts.accounts, counts.records);
+/// ```
+pub fn export_state<A, M, T, W>(
+    accounts: &M,
+    transactions: &T,
+    writer: W,
+) -> io::Result<StateCounts>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    T: TransactionStoreManager<A>,
+    W: Write,
+{
+    let mut encoder = Encoder {
+        writer,
+        buffer: Vec::with_capacity(CHUNK_SIZE),
+        error: None,
+    };
+    let mut counts = StateCounts::default();
+    encoder.buffer.extend_from_slice(MAGIC);
+    encoder.buffer.push(VERSION);
+
+    accounts.for_each_account(&mut |account| {
+        encoder.account(account);
+        counts.accounts += 1;
+    });
+    transactions.for_each_record(&mut |tx_id, record| {
+        encoder.record(tx_id, record);
+        counts.records += 1;
+    });
+
+    encoder.buffer.push(END);
+    encoder.flush_if(true);
+    match encoder.error {
+        Some(e) => Err(e),
+        None => encoder.writer.flush().map(|()| counts),
+    }
+}
+
+/// Load accounts and transaction records written by `export_state`
+///
+/// Imported accounts replace any existing account with the same client id
+/// and records are inserted into `transactions`, so import into empty
+/// storage. Entries are applied as they are read; if the export is truncated
+/// or corrupt, the error is returned and the entries before it stay applied.
+pub fn import_state<A, M, T, R>(
+    reader: R,
+    accounts: &M,
+    transactions: &T,
+) -> Result<StateCounts, StorageError>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    T: TransactionStoreManager<A>,
+    R: Read,
+{
+    let mut decoder = Decoder {
+        reader: io::BufReader::new(reader),
+        version: VERSION,
+    };
+    let mut magic = [0; MAGIC.len()];
+    decoder.reader.read_exact(&mut magic).map_err(truncated)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a pay state export"));
+    }
+    decoder.version = decoder.byte()?;
+    if !(1..=VERSION).contains(&decoder.version) {
+        return Err(invalid(format!(
+            "unsupported state version {}",
+            decoder.version
+        )));
+    }
+
+    let mut counts = StateCounts::default();
+    loop {
+        match decoder.byte()? {
+            ACCOUNT => {
+                let account: ClientAccount<A> = decoder.account()?;
+                accounts
+                    .entry(account.client_id())?
+                    .try_apply(&AccountOp::Restore(account))?;
+                counts.accounts += 1;
+            }
+            RECORD => {
+                let (tx_id, record) = decoder.record()?;
+                transactions.insert(tx_id, record);
+                counts.records += 1;
+            }
+            END => return Ok(counts),
+            tag => return Err(invalid(format!("unknown entry tag {tag}"))),
+        }
+    }
+}
+
+struct Encoder<W> {
+    writer: W,
+    buffer: Vec<u8>,
+    /// First write error; later entries are dropped once one occurs
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Encoder<W> {
+    fn account<A: AmountType>(&mut self, account: &ClientAccount<A>) {
+        self.buffer.push(ACCOUNT);
+        self.varint(u64::from(account.client_id()));
+        self.amount(account.available());
+        self.amount(account.held());
+        self.amount(account.credit_limit());
+        self.buffer.push(u8::from(account.is_locked()));
+        self.ids(account.disputed_ids());
+        self.ids(account.hold_ids());
+
+        let balances: Vec<_> = account.currency_balances().collect();
+        self.varint(balances.len() as u64);
+        for (currency, balance) in balances {
+            self.buffer.extend_from_slice(currency.as_str().as_bytes());
+            self.amount(balance.available);
+            self.amount(balance.held);
+        }
+
+        let tags: Vec<_> = account.tags().collect();
+        self.varint(tags.len() as u64);
+        for (key, value) in tags {
+            self.text(key);
+            self.text(value);
+        }
+        self.flush_if(self.buffer.len() >= CHUNK_SIZE);
+    }
+
+    fn record<A: AmountType>(&mut self, tx_id: TransactionId, record: &TransactionRecord<A>) {
+        self.buffer.push(RECORD);
+        self.varint(id_bits(tx_id));
+        self.varint(u64::from(record.client_id));
+        self.amount(record.amount);
+        match record.currency {
+            Some(currency) => {
+                self.buffer.push(1);
+                self.buffer.extend_from_slice(currency.as_str().as_bytes());
+            }
+            None => self.buffer.push(0),
+        }
+        self.buffer.push(kind_code(record.kind));
+        self.buffer.push(state_code(record.state));
+        self.varint(u64::from(record.disputes));
+        match record.held {
+            Some(held) => {
+                self.buffer.push(1);
+                self.amount(held);
+            }
+            None => self.buffer.push(0),
+        }
+        self.flush_if(self.buffer.len() >= CHUNK_SIZE);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    /// Decimal digits behind a one-byte length
+    fn amount<A: AmountType>(&mut self, amount: A) {
+        let length_at = self.buffer.len();
+        self.buffer.push(0);
+        amount.write_decimal(&mut self.buffer);
+        self.buffer[length_at] = (self.buffer.len() - length_at - 1) as u8;
+    }
+
+    /// UTF-8 bytes behind a varint length
+    fn text(&mut self, text: &str) {
+        self.varint(text.len() as u64);
+        self.buffer.extend_from_slice(text.as_bytes());
+    }
+
+    /// Ascending ids, each stored as its difference from the previous one
+    fn ids(&mut self, ids: impl Iterator<Item = TransactionId>) {
+        let mut ids: Vec<_> = ids.collect();
+        ids.sort_unstable();
+        self.varint(ids.len() as u64);
+        let mut previous = 0;
+        for id in ids {
+            self.varint(id_bits(id - previous));
+            previous = id;
+        }
+    }
+
+    fn flush_if(&mut self, due: bool) {
+        if due && self.error.is_none() {
+            self.error = self.writer.write_all(&self.buffer).err();
+        }
+        if due {
+            self.buffer.clear();
+        }
+    }
+}
+
+struct Decoder<R> {
+    reader: io::BufReader<R>,
+    /// Format version of the export being read
+    version: u8,
+}
+
+impl<R: Read> Decoder<R> {
+    fn account<A: AmountType>(&mut self) -> Result<ClientAccount<A>, StorageError> {
+        let client_id = narrow(self.varint()?, "client id")?;
+        let mut account = ClientAccount::new(client_id);
+        account.set_available(self.amount()?);
+        account.set_held(self.amount()?);
+        account.set_credit_limit(self.amount()?);
+        if self.byte()? != 0 {
+            account.lock();
+        }
+        for tx_id in self.ids()? {
+            account.add_disputed(tx_id);
+        }
+        if self.version < 4 {
+            self.ids()?;
+        }
+        for tx_id in self.ids()? {
+            account.add_hold(tx_id);
+        }
+        for _ in 0..self.varint()? {
+            let currency = self.currency()?;
+            let balance = CurrencyBalance {
+                available: self.amount()?,
+                held: self.amount()?,
+            };
+            account.set_currency_balance(currency, balance);
+        }
+        if self.version >= 2 {
+            for _ in 0..self.varint()? {
+                let key = self.text()?;
+                account.set_tag(&key, Some(&self.text()?));
+            }
+        }
+        Ok(account)
+    }
+
+    fn record<A: AmountType>(
+        &mut self,
+    ) -> Result<(TransactionId, TransactionRecord<A>), StorageError> {
+        let tx_id = narrow(self.varint()?, "transaction id")?;
+        let client_id = narrow(self.varint()?, "client id")?;
+        let amount = self.amount()?;
+        let currency = match self.byte()? {
+            0 => None,
+            _ => Some(self.currency()?),
+        };
+        let kind = kind_of(self.byte()?)?;
+        let state = state_of(self.byte()?)?;
+        let disputes = narrow(self.varint()?, "dispute count")?;
+        let held = if self.version >= 3 && self.byte()? != 0 {
+            Some(self.amount()?)
+        } else {
+            None
+        };
+
+        let record = TransactionRecord::new(client_id, amount)
+            .with_currency(currency)
+            .with_kind(kind)
+            .with_state(state)
+            .with_disputes(disputes)
+            .with_held(held);
+        Ok((tx_id, record))
+    }
+
+    fn byte(&mut self) -> Result<u8, StorageError> {
+        let mut byte = [0];
+        self.reader.read_exact(&mut byte).map_err(truncated)?;
+        Ok(byte[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, StorageError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("varint too long"))
+    }
+
+    fn amount<A: AmountType>(&mut self) -> Result<A, StorageError> {
+        let mut digits = vec![0; usize::from(self.byte()?)];
+        self.reader.read_exact(&mut digits).map_err(truncated)?;
+        Ok(A::from_decimal_bytes(&digits)?)
+    }
+
+    fn text(&mut self) -> Result<String, StorageError> {
+        // Read through `take` so a corrupt length cannot force a huge allocation
+        let length = self.varint()?;
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(length).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != length {
+            return Err(invalid("state export is truncated"));
+        }
+        String::from_utf8(bytes).map_err(|_| invalid("text is not UTF-8"))
+    }
+
+    fn currency(&mut self) -> Result<CurrencyCode, StorageError> {
+        let mut code = [0; 3];
+        self.reader.read_exact(&mut code).map_err(truncated)?;
+        std::str::from_utf8(&code)
+            .ok()
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| invalid("bad currency code"))
+    }
+
+    fn ids(&mut self) -> Result<Vec<TransactionId>, StorageError> {
+        let count = self.varint()?;
+        let mut ids = Vec::new();
+        let mut previous: u64 = 0;
+        for _ in 0..count {
+            previous = previous
+                .checked_add(self.varint()?)
+                .ok_or_else(|| invalid("transaction id overflow"))?;
+            ids.push(narrow(previous, "transaction id")?);
+        }
+        Ok(ids)
+    }
+}
+
+fn kind_code(kind: TxKind) -> u8 {
+    match kind {
+        TxKind::Deposit => 0,
+        TxKind::Withdrawal => 1,
+        TxKind::Transfer => 2,
+        TxKind::Hold => 3,
+    }
+}
+
+fn kind_of(code: u8) -> Result<TxKind, StorageError> {
+    match code {
+        0 => Ok(TxKind::Deposit),
+        1 => Ok(TxKind::Withdrawal),
+        2 => Ok(TxKind::Transfer),
+        3 => Ok(TxKind::Hold),
+        _ => Err(invalid(format!("unknown transaction kind {code}"))),
+    }
+}
+
+fn state_code(state: TxState) -> u8 {
+    match state {
+        TxState::Posted => 0,
+        TxState::Disputed => 1,
+        TxState::Resolved => 2,
+        TxState::ChargedBack => 3,
+        TxState::Reversed => 4,
+    }
+}
+
+fn state_of(code: u8) -> Result<TxState, StorageError> {
+    match code {
+        0 => Ok(TxState::Posted),
+        1 => Ok(TxState::Disputed),
+        2 => Ok(TxState::Resolved),
+        3 => Ok(TxState::ChargedBack),
+        4 => Ok(TxState::Reversed),
+        _ => Err(invalid(format!("unknown transaction state {code}"))),
+    }
+}
+
+/// Tx id widened to 64 bits (a no-op with `wide-tx-ids`)
+#[allow(clippy::useless_conversion)]
+fn id_bits(tx_id: TransactionId) -> u64 {
+    u64::from(tx_id)
+}
+
+/// `value` as a narrower integer, e.g. a wide transaction id in a narrow build
+fn narrow<V: TryFrom<u64>>(value: u64, what: &str) -> Result<V, StorageError> {
+    V::try_from(value).map_err(|_| invalid(format!("{what} {value} is out of range")))
+}
+
+fn invalid(reason: impl Into<String>) -> StorageError {
+    io::Error::new(io::ErrorKind::InvalidData, reason.into()).into()
+}
+
+fn truncated(e: io::Error) -> StorageError {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => invalid("state export is truncated"),
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+    use crate::storage::{ConcurrentAccountManager, ConcurrentTransactionStore};
+
+    fn sorted_accounts(
+        accounts: &ConcurrentAccountManager<FixedPoint>,
+    ) -> Vec<ClientAccount<FixedPoint>> {
+        let mut all = Vec::new();
+        accounts.for_each_account(&mut |account| all.push(account.clone()));
+        all.sort_by_key(ClientAccount::client_id);
+        all
+    }
+
+    fn populated() -> (
+        ConcurrentAccountManager<FixedPoint>,
+        ConcurrentTransactionStore<FixedPoint>,
+    ) {
+        let accounts = ConcurrentAccountManager::new();
+        let transactions = ConcurrentTransactionStore::new();
+        let usd: CurrencyCode = "USD".parse().unwrap();
+
+        accounts
+            .entry(1)
+            .unwrap()
+            .try_update(|account| {
+                account.set_available(FixedPoint::from_raw(12_345));
+                account.set_held(FixedPoint::from_raw(5_000));
+                account.set_credit_limit(FixedPoint::from_raw(1_000));
+                account.add_disputed(7);
+                account.add_disputed(300);
+                account.add_hold(9);
+                account.set_currency_balance(
+                    usd,
+                    CurrencyBalance {
+                        available: FixedPoint::from_raw(-2_500),
+                        held: FixedPoint::zero(),
+                    },
+                );
+                Ok(())
+            })
+            .unwrap();
+        accounts
+            .entry(2)
+            .unwrap()
+            .try_update(|account| {
+                account.lock();
+                account.set_tag("kyc", Some("pending"));
+                account.set_tag("region", Some("südwest"));
+                Ok(())
+            })
+            .unwrap();
+
+        transactions.insert(
+            7,
+            TransactionRecord::new(1, FixedPoint::from_raw(5_000))
+                .with_state(TxState::Disputed)
+                .with_disputes(1)
+                .with_held(Some(FixedPoint::from_raw(2_000))),
+        );
+        transactions.insert(
+            9,
+            TransactionRecord::new(1, FixedPoint::from_raw(1))
+                .with_currency(Some(usd))
+                .with_kind(TxKind::Hold),
+        );
+        (accounts, transactions)
+    }
+
+    #[test]
+    fn export_round_trips_accounts_and_records() {
+        let (accounts, transactions) = populated();
+        let mut exported = Vec::new();
+        let counts = export_state(&accounts, &transactions, &mut exported).unwrap();
+        assert_eq!(
+            counts,
+            StateCounts {
+                accounts: 2,
+                records: 2
+            }
+        );
+
+        let restored_accounts = ConcurrentAccountManager::new();
+        let restored_transactions = ConcurrentTransactionStore::new();
+        let imported = import_state(
+            exported.as_slice(),
+            &restored_accounts,
+            &restored_transactions,
+        )
+        .unwrap();
+
+        assert_eq!(imported, counts);
+        assert_eq!(
+            sorted_accounts(&restored_accounts),
+            sorted_accounts(&accounts)
+        );
+        for tx_id in [7, 9] {
+            assert_eq!(restored_transactions.get(tx_id), transactions.get(tx_id));
+        }
+    }
+
+    #[test]
+    fn version_3_exports_skip_resolved_ids() {
+        let mut exported = MAGIC.to_vec();
+        exported.extend_from_slice(&[3, ACCOUNT, 1]);
+        // Available, held and credit limit, then the lock flag
+        exported.extend_from_slice(&[1, b'2', 1, b'0', 1, b'0', 0]);
+        // No disputed ids, resolved id 4, hold 9, no balances or tags
+        exported.extend_from_slice(&[0, 1, 4, 1, 9, 0, 0, END]);
+
+        let accounts = ConcurrentAccountManager::<FixedPoint>::new();
+        let transactions = ConcurrentTransactionStore::new();
+        import_state(exported.as_slice(), &accounts, &transactions).unwrap();
+
+        let account = accounts.account(1).unwrap();
+        assert_eq!(account.available(), FixedPoint::from_raw(20_000));
+        assert!(account.has_active_hold(9));
+        assert!(!account.has_active_hold(4));
+    }
+
+    #[test]
+    fn truncated_or_foreign_input_is_rejected() {
+        let (accounts, transactions) = populated();
+        let mut exported = Vec::new();
+        export_state(&accounts, &transactions, &mut exported).unwrap();
+
+        let empty = || {
+            (
+                ConcurrentAccountManager::<FixedPoint>::new(),
+                ConcurrentTransactionStore::new(),
+            )
+        };
+        let (a, t) = empty();
+        let error = import_state(&exported[..exported.len() - 3], &a, &t).unwrap_err();
+        assert!(error.to_string().contains("truncated"), "{error}");
+
+        let (a, t) = empty();
+        let error = import_state(&b"client,available\n"[..], &a, &t).unwrap_err();
+        assert!(
+            error.to_string().contains("not a pay state export"),
+            "{error}"
+        );
+    }
+}
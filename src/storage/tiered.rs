@@ -0,0 +1,427 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+
+use super::error::StorageError;
+use super::snapshot_format::SnapshotFormat;
+use super::traits::{ClientAccountEntry, ClientAccountManager};
+use crate::domain::{AmountType, ClientAccount, ClientId, DomainError};
+
+/// Number of hot-tier shards used by `TieredAccountManager::new`
+const DEFAULT_SHARDS: usize = 16;
+
+/// Counters and tier sizes of a `TieredAccountManager`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TierStats {
+    /// Lookups that found the account in the hot tier
+    pub hot_hits: u64,
+    /// Lookups that found the account in the cold tier
+    pub cold_hits: u64,
+    /// Accounts moved from the cold tier to the hot tier
+    pub promotions: u64,
+    /// Accounts moved from the hot tier to the cold tier
+    pub demotions: u64,
+    /// Accounts currently in the hot tier
+    pub hot_accounts: usize,
+    /// Accounts currently in the cold tier
+    pub cold_accounts: usize,
+}
+
+#[derive(Default)]
+struct TierCounters {
+    hot_hits: AtomicU64,
+    cold_hits: AtomicU64,
+    promotions: AtomicU64,
+    demotions: AtomicU64,
+}
+
+/// Where a loaded account came from
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tier {
+    Hot,
+    Cold,
+    New,
+}
+
+/// One shard of the hot tier, with least-recently-updated order
+struct HotShard<A: AmountType> {
+    /// client_id -> (account, position in recency order)
+    accounts: HashMap<ClientId, (ClientAccount<A>, u64)>,
+    /// position -> client_id, least recently updated first
+    order: BTreeMap<u64, ClientId>,
+    next_position: u64,
+}
+
+impl<A: AmountType> HotShard<A> {
+    fn new() -> Self {
+        Self {
+            accounts: HashMap::new(),
+            order: BTreeMap::new(),
+            next_position: 0,
+        }
+    }
+
+    /// Insert or replace an account as the most recently updated
+    fn put(&mut self, account: ClientAccount<A>) {
+        let position = self.next_position;
+        self.next_position += 1;
+
+        let client_id = account.client_id();
+        if let Some((_, old_position)) = self.accounts.insert(client_id, (account, position)) {
+            self.order.remove(&old_position);
+        }
+        self.order.insert(position, client_id);
+    }
+
+    /// Remove the least recently updated account
+    fn pop_coldest(&mut self) -> Option<ClientAccount<A>> {
+        let (_, client_id) = self.order.pop_first()?;
+        self.accounts.remove(&client_id).map(|(account, _)| account)
+    }
+}
+
+/// Account manager with a bounded hot tier in front of a cold backing map
+///
+/// Recently updated accounts live in a small sharded hot tier; once a shard
+/// is over its share of `hot_capacity`, its least recently updated account
+/// is demoted to the cold tier, and a later update promotes it back. With
+/// skewed (zipf-like) traffic the busy accounts stay hot, so most updates
+/// only take one hot-shard lock and the cold map is touched on misses alone.
+/// Reads (`get`, `read`) never promote, and failed updates leave accounts in
+/// the tier they were in. `stats` reports hit, promotion and demotion counts
+/// for sizing the hot tier.
+///
+/// # Example
+/// ```rust,ignore
+/// let accounts = Arc::new(TieredAccountManager::new(4_096));
+/// StreamProcessor::new(accounts.clone(), store, SkipErrors)
+///     .add_stream(stream)
+///     .process()
+///     .await;
+/// println!("{:?}", accounts.stats());
+/// ```
+pub struct TieredAccountManager<A: AmountType> {
+    shards: Vec<Mutex<HotShard<A>>>,
+    shard_capacity: usize,
+    cold: Mutex<HashMap<ClientId, ClientAccount<A>>>,
+    counters: TierCounters,
+}
+
+impl<A: AmountType> TieredAccountManager<A> {
+    /// Create a manager keeping about `hot_capacity` accounts hot
+    pub fn new(hot_capacity: usize) -> Self {
+        Self::with_shards(hot_capacity, DEFAULT_SHARDS)
+    }
+
+    /// Create a manager whose hot tier is split into `shards` locks
+    ///
+    /// Each shard holds up to `hot_capacity / shards` accounts (rounded up,
+    /// minimum 1). Clients map to shard `client_id % shards`.
+    pub fn with_shards(hot_capacity: usize, shards: usize) -> Self {
+        let shards = shards.max(1);
+        Self {
+            shards: (0..shards).map(|_| Mutex::new(HotShard::new())).collect(),
+            shard_capacity: hot_capacity.div_ceil(shards).max(1),
+            cold: Mutex::new(HashMap::new()),
+            counters: TierCounters::default(),
+        }
+    }
+
+    /// Current counters and tier sizes
+    pub fn stats(&self) -> TierStats {
+        TierStats {
+            hot_hits: self.counters.hot_hits.load(Ordering::Relaxed),
+            cold_hits: self.counters.cold_hits.load(Ordering::Relaxed),
+            promotions: self.counters.promotions.load(Ordering::Relaxed),
+            demotions: self.counters.demotions.load(Ordering::Relaxed),
+            hot_accounts: self
+                .shards
+                .iter()
+                .map(|shard| shard.lock().accounts.len())
+                .sum(),
+            cold_accounts: self.cold.lock().len(),
+        }
+    }
+
+    fn shard_index(&self, client_id: ClientId) -> usize {
+        client_id as usize % self.shards.len()
+    }
+
+    /// Copy of an account from whichever tier holds it (the shard stays locked)
+    fn load(&self, shard: &HotShard<A>, client_id: ClientId) -> (ClientAccount<A>, Tier) {
+        if let Some((account, _)) = shard.accounts.get(&client_id) {
+            self.counters.hot_hits.fetch_add(1, Ordering::Relaxed);
+            return (account.clone(), Tier::Hot);
+        }
+        match self.cold.lock().get(&client_id) {
+            Some(account) => {
+                self.counters.cold_hits.fetch_add(1, Ordering::Relaxed);
+                (account.clone(), Tier::Cold)
+            }
+            None => (ClientAccount::new(client_id), Tier::New),
+        }
+    }
+
+    /// Write an updated account to the hot tier, demoting any overflow
+    ///
+    /// Accounts only move between tiers while their shard is locked, and the
+    /// cold lock is always taken after shard locks, never before.
+    fn store(&self, shard: &mut HotShard<A>, account: ClientAccount<A>, tier: Tier) {
+        let client_id = account.client_id();
+        shard.put(account);
+        if tier != Tier::Cold && shard.accounts.len() <= self.shard_capacity {
+            return;
+        }
+
+        let mut cold = self.cold.lock();
+        if tier == Tier::Cold {
+            cold.remove(&client_id);
+            self.counters.promotions.fetch_add(1, Ordering::Relaxed);
+        }
+        while shard.accounts.len() > self.shard_capacity {
+            let Some(demoted) = shard.pop_coldest() else {
+                break;
+            };
+            cold.insert(demoted.client_id(), demoted);
+            self.counters.demotions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn update<F>(&self, client_id: ClientId, update_fn: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        let mut shard = self.shards[self.shard_index(client_id)].lock();
+        let (mut account, tier) = self.load(&shard, client_id);
+        update_fn(&mut account)?;
+        self.store(&mut shard, account, tier);
+        Ok(())
+    }
+
+    /// Every account, copied out with all locks held so the view is consistent
+    fn all_accounts(&self) -> Vec<ClientAccount<A>> {
+        let shards: Vec<_> = self.shards.iter().map(|shard| shard.lock()).collect();
+        let cold = self.cold.lock();
+
+        shards
+            .iter()
+            .flat_map(|shard| shard.accounts.values().map(|(account, _)| account))
+            .chain(cold.values())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Entry for tiered account access
+pub struct TieredEntry<'a, A: AmountType> {
+    client_id: ClientId,
+    manager: &'a TieredAccountManager<A>,
+}
+
+impl<'a, A: AmountType> ClientAccountEntry<'a, A> for TieredEntry<'a, A> {
+    fn read(&self) -> ClientAccount<A> {
+        self.manager
+            .get(self.client_id)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| ClientAccount::new(self.client_id))
+    }
+
+    fn try_update<F>(&mut self, update_fn: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        self.manager.update(self.client_id, update_fn)
+    }
+}
+
+#[async_trait]
+impl<A: AmountType> ClientAccountManager<A> for TieredAccountManager<A> {
+    type Entry<'a>
+        = TieredEntry<'a, A>
+    where
+        Self: 'a;
+
+    fn entry(&self, client_id: ClientId) -> Result<Self::Entry<'_>, StorageError> {
+        Ok(TieredEntry {
+            client_id,
+            manager: self,
+        })
+    }
+
+    fn try_update_pair<F>(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        if first_id == second_id {
+            return Err(DomainError::SelfTransfer.into());
+        }
+
+        let first_index = self.shard_index(first_id);
+        let second_index = self.shard_index(second_id);
+        if first_index == second_index {
+            let mut shard = self.shards[first_index].lock();
+            let (mut first, first_tier) = self.load(&shard, first_id);
+            let (mut second, second_tier) = self.load(&shard, second_id);
+            update_fn(&mut first, &mut second)?;
+
+            self.store(&mut shard, first, first_tier);
+            self.store(&mut shard, second, second_tier);
+            return Ok(());
+        }
+
+        // Shard locks are taken in ascending index order to avoid deadlocks
+        let (mut first_shard, mut second_shard) = if first_index < second_index {
+            let first = self.shards[first_index].lock();
+            (first, self.shards[second_index].lock())
+        } else {
+            let second = self.shards[second_index].lock();
+            (self.shards[first_index].lock(), second)
+        };
+        let (mut first, first_tier) = self.load(&first_shard, first_id);
+        let (mut second, second_tier) = self.load(&second_shard, second_id);
+        update_fn(&mut first, &mut second)?;
+
+        self.store(&mut first_shard, first, first_tier);
+        self.store(&mut second_shard, second, second_tier);
+        Ok(())
+    }
+
+    fn get(&self, client_id: ClientId) -> Result<Option<ClientAccount<A>>, StorageError> {
+        let shard = self.shards[self.shard_index(client_id)].lock();
+        if let Some((account, _)) = shard.accounts.get(&client_id) {
+            return Ok(Some(account.clone()));
+        }
+        Ok(self.cold.lock().get(&client_id).cloned())
+    }
+
+    async fn snapshot_with_format<W>(
+        &self,
+        mut writer: W,
+        format: &SnapshotFormat,
+    ) -> Result<(), StorageError>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        // Format every row up front; the lock guards must not be held across awaits
+        let mut output = format.header().into_bytes();
+        for account in self.all_accounts() {
+            format.write_row(&account, &mut output)?;
+        }
+
+        writer.write_all(&output).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &ClientAccount<A>> + Send + '_> {
+        // Accounts live behind the tier locks and cannot be borrowed past them
+        Box::new(std::iter::empty())
+    }
+
+    fn for_each_account(&self, visit: &mut dyn FnMut(&ClientAccount<A>)) {
+        for account in self.all_accounts() {
+            visit(&account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, operations};
+
+    fn deposit(manager: &TieredAccountManager<FixedPoint>, client_id: ClientId, raw: i64) {
+        manager
+            .entry(client_id)
+            .unwrap()
+            .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(raw)))
+            .unwrap();
+    }
+
+    fn available(manager: &TieredAccountManager<FixedPoint>, client_id: ClientId) -> Option<i64> {
+        manager
+            .get(client_id)
+            .unwrap()
+            .map(|acc| acc.available().raw())
+    }
+
+    #[test]
+    fn least_recently_updated_accounts_are_demoted_and_promoted_back() {
+        let manager = TieredAccountManager::<FixedPoint>::with_shards(2, 1);
+        deposit(&manager, 1, 100);
+        deposit(&manager, 2, 200);
+        deposit(&manager, 1, 100);
+        // Client 2 is now the least recently updated
+        deposit(&manager, 3, 300);
+
+        let stats = manager.stats();
+        assert_eq!(stats.demotions, 1);
+        assert_eq!((stats.hot_accounts, stats.cold_accounts), (2, 1));
+        assert_eq!(available(&manager, 2), Some(200));
+
+        deposit(&manager, 2, 50);
+
+        let stats = manager.stats();
+        assert_eq!(
+            (stats.cold_hits, stats.promotions, stats.demotions),
+            (1, 1, 2)
+        );
+        assert_eq!((stats.hot_accounts, stats.cold_accounts), (2, 1));
+        assert_eq!(available(&manager, 2), Some(250));
+        assert_eq!(available(&manager, 1), Some(200));
+    }
+
+    #[test]
+    fn failed_updates_neither_create_nor_promote() {
+        let manager = TieredAccountManager::<FixedPoint>::with_shards(1, 1);
+        deposit(&manager, 1, 100);
+        deposit(&manager, 2, 100);
+
+        for client_id in [1, 3] {
+            let result = manager
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| operations::apply_withdrawal(acc, FixedPoint::from_raw(1_000)));
+            assert!(result.is_err());
+        }
+
+        let stats = manager.stats();
+        assert_eq!(stats.promotions, 0);
+        assert_eq!((stats.hot_accounts, stats.cold_accounts), (1, 1));
+        assert_eq!(available(&manager, 3), None);
+    }
+
+    #[test]
+    fn pair_updates_span_tiers_and_shards() {
+        let manager = TieredAccountManager::<FixedPoint>::with_shards(2, 2);
+        deposit(&manager, 2, 10_000);
+        deposit(&manager, 4, 1);
+        // Client 2 is cold now; client 1 lives in the other shard
+        for (from, to) in [(2, 1), (1, 2)] {
+            manager
+                .try_update_pair(from, to, |from, to| {
+                    operations::apply_withdrawal(from, FixedPoint::from_raw(1_000))?;
+                    operations::apply_deposit(to, FixedPoint::from_raw(1_000))
+                })
+                .unwrap();
+        }
+
+        let mut total = 0;
+        manager.for_each_account(&mut |acc| total += acc.available().raw());
+        assert_eq!(total, 10_001);
+        assert_eq!(available(&manager, 1), Some(0));
+        assert_eq!(available(&manager, 2), Some(10_000));
+        assert_eq!(manager.stats().promotions, 1);
+    }
+}
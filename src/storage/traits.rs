@@ -1,20 +1,81 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use tokio::io::AsyncWrite;
 
 use super::error::StorageError;
-use crate::domain::{AmountType, ClientAccount, DomainError, TransactionRecord};
+use super::snapshot_format::SnapshotFormat;
+use crate::domain::{
+    AccountOp, AmountType, ClientAccount, ClientId, DomainError, PairOp, TransactionId,
+    TransactionRecord,
+};
 
 /// Trait for managing transaction records (for dispute resolution)
 /// Transactions are immutable once inserted
+///
+/// Stores are shared between shards, so inserts go through a shared
+/// reference; implementations need interior mutability.
 pub trait TransactionStoreManager<A: AmountType>: Send + Sync {
     /// Insert a transaction record (immutable after insertion)
-    fn insert(&mut self, tx_id: u32, record: TransactionRecord<A>);
+    fn insert(&self, tx_id: TransactionId, record: TransactionRecord<A>);
+
+    /// Run `apply` and, only if it succeeds, insert `record` under `tx_id`
+    ///
+    /// The engine's hot path: every deposit, withdrawal, transfer and hold
+    /// updates an account and then records the transaction. An id that is
+    /// already recorded fails with `StorageError::DuplicateTransaction`
+    /// before `apply` runs. The default checks, applies and inserts as
+    /// separate steps; stores shared between threads override it to hold the
+    /// record's lock throughout, so record locks are taken before the account
+    /// locks `apply` takes. `apply` must not access this store.
+    fn apply_and_record<E: From<StorageError>>(
+        &self,
+        tx_id: TransactionId,
+        record: TransactionRecord<A>,
+        apply: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.contains(tx_id) {
+            return Err(StorageError::DuplicateTransaction(tx_id).into());
+        }
+        apply()?;
+        self.insert(tx_id, record);
+        Ok(())
+    }
+
+    /// Run `apply` on the record under `tx_id` and store the record it returns
+    ///
+    /// Disputes, resolves and chargebacks update an account and then the
+    /// disputed record. `apply` gets the current record (None if there is
+    /// none); nothing is stored if it fails. Same lock order and rules as
+    /// `apply_and_record`.
+    fn apply_and_update<E>(
+        &self,
+        tx_id: TransactionId,
+        apply: impl FnOnce(Option<TransactionRecord<A>>) -> Result<TransactionRecord<A>, E>,
+    ) -> Result<(), E> {
+        let record = apply(self.get(tx_id))?;
+        self.insert(tx_id, record);
+        Ok(())
+    }
 
     /// Get a transaction record by ID (returns clone, not reference)
-    fn get(&self, tx_id: u32) -> Option<TransactionRecord<A>>;
+    fn get(&self, tx_id: TransactionId) -> Option<TransactionRecord<A>>;
 
     /// Check if a transaction exists
-    fn contains(&self, tx_id: u32) -> bool;
+    fn contains(&self, tx_id: TransactionId) -> bool;
+
+    /// Visit every stored record, in no particular order (used for checkpoints)
+    fn for_each_record(&self, visit: &mut dyn FnMut(TransactionId, &TransactionRecord<A>));
+
+    /// Number of records held in memory (used for memory accounting)
+    ///
+    /// The default visits and counts every record; stores should override
+    /// it with a cheaper count, and must if they keep records elsewhere.
+    fn resident_records(&self) -> usize {
+        let mut count = 0;
+        self.for_each_record(&mut |_, _| count += 1);
+        count
+    }
 }
 
 /// Trait for managing client accounts with pluggable storage backends
@@ -25,18 +86,70 @@ pub trait ClientAccountManager<A: AmountType>: Send + Sync {
         Self: 'a;
 
     /// Get or create an entry for the given client ID
-    fn entry(&self, client_id: u16) -> Result<Self::Entry<'_>, StorageError>;
+    fn entry(&self, client_id: ClientId) -> Result<Self::Entry<'_>, StorageError>;
 
-    /// Read-only access to an account
-    fn get(&self, client_id: u16) -> Result<Option<&ClientAccount<A>>, StorageError>;
+    /// Atomic read-modify-write across two distinct accounts
+    ///
+    /// The closure receives the accounts in argument order. Implementations must
+    /// acquire any internal locks in a globally consistent order so that
+    /// concurrent pair updates cannot deadlock, and must leave both accounts
+    /// untouched if the closure fails.
+    fn try_update_pair<F>(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>;
+
+    /// Apply a domain operation to two distinct accounts atomically
+    ///
+    /// The default runs `op` through `try_update_pair`; backends that log
+    /// operations override it.
+    fn try_apply_pair(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        op: &PairOp<A>,
+    ) -> Result<(), StorageError> {
+        self.try_update_pair(first_id, second_id, |first, second| op.apply(first, second))
+    }
 
-    /// Async snapshot of all accounts to a writer
+    /// Copy of an existing account, or None if the client has no account yet
+    ///
+    /// Unlike `entry(id)?.read()`, this distinguishes a missing account from
+    /// an empty one.
+    fn get(&self, client_id: ClientId) -> Result<Option<ClientAccount<A>>, StorageError>;
+
+    /// Async snapshot of all accounts to a writer using the default format
     async fn snapshot<W>(&self, writer: W) -> Result<(), StorageError>
+    where
+        W: AsyncWrite + Unpin + Send + 'async_trait,
+    {
+        self.snapshot_with_format(writer, &SnapshotFormat::default())
+            .await
+    }
+
+    /// Async snapshot of all accounts to a writer with custom number formatting
+    async fn snapshot_with_format<W>(
+        &self,
+        writer: W,
+        format: &SnapshotFormat,
+    ) -> Result<(), StorageError>
     where
         W: AsyncWrite + Unpin + Send;
 
     /// Iterate over all accounts
     fn iter(&self) -> Box<dyn Iterator<Item = &ClientAccount<A>> + Send + '_>;
+
+    /// Visit every account in turn
+    ///
+    /// Unlike `iter`, this also works for lock-protected storage: each account
+    /// is only borrowed while its lock is held.
+    fn for_each_account(&self, visit: &mut dyn FnMut(&ClientAccount<A>)) {
+        self.iter().for_each(visit);
+    }
 }
 
 /// Entry pattern for atomic account operations
@@ -48,4 +161,114 @@ pub trait ClientAccountEntry<'a, A: AmountType> {
     fn try_update<F>(&mut self, update_fn: F) -> Result<(), StorageError>
     where
         F: FnOnce(&mut ClientAccount<A>) -> Result<(), DomainError>;
+
+    /// Apply a domain operation atomically
+    ///
+    /// Returns what `AccountOp::apply` returns. The default runs `op` through
+    /// `try_update`; backends that log operations override it.
+    fn try_apply(&mut self, op: &AccountOp<A>) -> Result<Option<A>, StorageError> {
+        let mut held = None;
+        self.try_update(|account| {
+            held = op.apply(account)?;
+            Ok(())
+        })?;
+        Ok(held)
+    }
+}
+
+// Sharing storage behind an Arc keeps the backend's behaviour; this is how
+// the stream processor hands one backend to every shard
+impl<A: AmountType, T: TransactionStoreManager<A>> TransactionStoreManager<A> for Arc<T> {
+    fn insert(&self, tx_id: TransactionId, record: TransactionRecord<A>) {
+        (**self).insert(tx_id, record)
+    }
+
+    fn apply_and_record<E: From<StorageError>>(
+        &self,
+        tx_id: TransactionId,
+        record: TransactionRecord<A>,
+        apply: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        (**self).apply_and_record(tx_id, record, apply)
+    }
+
+    fn apply_and_update<E>(
+        &self,
+        tx_id: TransactionId,
+        apply: impl FnOnce(Option<TransactionRecord<A>>) -> Result<TransactionRecord<A>, E>,
+    ) -> Result<(), E> {
+        (**self).apply_and_update(tx_id, apply)
+    }
+
+    fn get(&self, tx_id: TransactionId) -> Option<TransactionRecord<A>> {
+        (**self).get(tx_id)
+    }
+
+    fn contains(&self, tx_id: TransactionId) -> bool {
+        (**self).contains(tx_id)
+    }
+
+    fn for_each_record(&self, visit: &mut dyn FnMut(TransactionId, &TransactionRecord<A>)) {
+        (**self).for_each_record(visit)
+    }
+
+    fn resident_records(&self) -> usize {
+        (**self).resident_records()
+    }
+}
+
+#[async_trait]
+impl<A: AmountType, M: ClientAccountManager<A>> ClientAccountManager<A> for Arc<M> {
+    type Entry<'a>
+        = M::Entry<'a>
+    where
+        Self: 'a;
+
+    fn entry(&self, client_id: ClientId) -> Result<Self::Entry<'_>, StorageError> {
+        (**self).entry(client_id)
+    }
+
+    fn try_update_pair<F>(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        update_fn: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut ClientAccount<A>, &mut ClientAccount<A>) -> Result<(), DomainError>,
+    {
+        (**self).try_update_pair(first_id, second_id, update_fn)
+    }
+
+    fn try_apply_pair(
+        &self,
+        first_id: ClientId,
+        second_id: ClientId,
+        op: &PairOp<A>,
+    ) -> Result<(), StorageError> {
+        (**self).try_apply_pair(first_id, second_id, op)
+    }
+
+    fn get(&self, client_id: ClientId) -> Result<Option<ClientAccount<A>>, StorageError> {
+        (**self).get(client_id)
+    }
+
+    async fn snapshot_with_format<W>(
+        &self,
+        writer: W,
+        format: &SnapshotFormat,
+    ) -> Result<(), StorageError>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        (**self).snapshot_with_format(writer, format).await
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &ClientAccount<A>> + Send + '_> {
+        (**self).iter()
+    }
+
+    fn for_each_account(&self, visit: &mut dyn FnMut(&ClientAccount<A>)) {
+        (**self).for_each_account(visit)
+    }
 }
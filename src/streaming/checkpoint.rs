@@ -0,0 +1,716 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use parking_lot::{Mutex, RwLock, RwLockReadGuard};
+use tracing::{debug, warn};
+
+use crate::domain::{
+    AccountOp, AmountType, ClientAccount, ClientId, CurrencyBalance, CurrencyCode, TransactionId,
+    TransactionRecord, TxKind, TxState,
+};
+use crate::engine::IdempotencyKeys;
+use crate::io::{IoError, ReadPosition};
+use crate::storage::{
+    ClientAccountEntry, ClientAccountManager, StorageError, TransactionStoreManager,
+};
+
+/// First row of every checkpoint file (format marker and version)
+const MAGIC: [&str; 2] = ["pay-checkpoint", "2"];
+/// Header of version 1 checkpoints, which still load; their account rows
+/// also list resolved transaction ids, which are no longer kept
+const V1_MAGIC: [&str; 2] = ["pay-checkpoint", "1"];
+
+/// Processing progress plus the storage state it produced
+///
+/// `offsets[i]` is the number of records (including rejected ones) consumed
+/// from the `i`-th stream added to the `StreamProcessor`. Streams added with
+/// `add_csv_stream` also keep the reader's position after the last consumed
+/// record, so `CsvTransactionStream::from_file_at` can seek a plain file
+/// straight to it on resume; other streams skip the counted records again.
+/// The accounts and transaction records are a full copy of the storage when
+/// the offsets were taken, so disputes of pre-checkpoint transactions still
+/// work after resuming, and the used idempotency keys are kept so replays
+/// are still recognised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint<A: AmountType> {
+    offsets: Vec<u64>,
+    positions: Vec<Option<csv::Position>>,
+    accounts: Vec<ClientAccount<A>>,
+    records: Vec<(TransactionId, TransactionRecord<A>)>,
+    idempotency_keys: Vec<(ClientId, String)>,
+}
+
+impl<A: AmountType> Checkpoint<A> {
+    /// Copy the current storage state alongside the given stream offsets
+    pub fn capture<M, T>(offsets: Vec<u64>, accounts: &M, transactions: &T) -> Self
+    where
+        M: ClientAccountManager<A>,
+        T: TransactionStoreManager<A>,
+    {
+        let mut captured = Vec::new();
+        accounts.for_each_account(&mut |account: &ClientAccount<A>| captured.push(account.clone()));
+        captured.sort_by_key(ClientAccount::client_id);
+
+        let mut records = Vec::new();
+        transactions.for_each_record(&mut |tx_id, record: &TransactionRecord<A>| {
+            records.push((tx_id, record.clone()))
+        });
+        records.sort_by_key(|(tx_id, _)| *tx_id);
+
+        Self {
+            positions: vec![None; offsets.len()],
+            offsets,
+            accounts: captured,
+            records,
+            idempotency_keys: Vec::new(),
+        }
+    }
+
+    /// Keep the position each stream's reader reached, where known
+    pub fn with_positions(mut self, positions: Vec<Option<csv::Position>>) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    /// Keep a copy of the used idempotency keys
+    pub fn with_idempotency_keys(mut self, keys: &IdempotencyKeys) -> Self {
+        let mut captured = Vec::new();
+        keys.for_each(&mut |client_id, key| captured.push((client_id, key.to_string())));
+        captured.sort_unstable();
+        self.idempotency_keys = captured;
+        self
+    }
+
+    /// Records consumed per stream, in the order the streams were added
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    /// Position of the `stream`-th stream's reader after its last consumed
+    /// record, if it was added with `add_csv_stream`
+    pub fn position(&self, stream: usize) -> Option<&csv::Position> {
+        self.positions.get(stream)?.as_ref()
+    }
+
+    /// Accounts captured in the checkpoint, by client id
+    pub fn accounts(&self) -> &[ClientAccount<A>] {
+        &self.accounts
+    }
+
+    /// Copy the captured accounts and transaction records back into storage
+    pub fn restore<M, T>(&self, accounts: &M, transactions: &T) -> Result<(), StorageError>
+    where
+        M: ClientAccountManager<A>,
+        T: TransactionStoreManager<A>,
+    {
+        for account in &self.accounts {
+            accounts
+                .entry(account.client_id())?
+                .try_apply(&AccountOp::Restore(account.clone()))?;
+        }
+        for (tx_id, record) in &self.records {
+            transactions.insert(*tx_id, record.clone());
+        }
+        Ok(())
+    }
+
+    /// Mark the captured idempotency keys as used in `keys`
+    pub fn restore_idempotency_keys(&self, keys: &IdempotencyKeys) {
+        for (client_id, key) in &self.idempotency_keys {
+            keys.claim(*client_id, key);
+        }
+    }
+
+    /// Write the checkpoint to `path`, atomically replacing any previous one
+    ///
+    /// The checkpoint is written to a temporary file next to `path` and then
+    /// renamed, so a crash mid-write leaves the previous checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), IoError> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+
+        let mut writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(BufWriter::new(File::create(&tmp)?));
+        self.write_rows(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.flush()?;
+
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Read a checkpoint written by `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IoError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(BufReader::new(File::open(path)?));
+
+        let mut rows = reader.records();
+        let v1 = match rows.next().transpose()? {
+            Some(row) if row.iter().eq(MAGIC) => false,
+            Some(row) if row.iter().eq(V1_MAGIC) => true,
+            _ => return Err(invalid("missing pay-checkpoint header")),
+        };
+
+        let mut checkpoint = Self {
+            offsets: Vec::new(),
+            positions: Vec::new(),
+            accounts: Vec::new(),
+            records: Vec::new(),
+            idempotency_keys: Vec::new(),
+        };
+        for row in rows {
+            checkpoint.read_row(&row?, v1)?;
+        }
+        Ok(checkpoint)
+    }
+
+    // Rows: `offsets,n...`, `position,stream,byte,line,record`,
+    // `account,client,available,held,locked,disputed,holds,credit_limit`,
+    // `balance,client,currency,available,held`, `tag,client,key,value`,
+    // `record,tx,client,amount,currency,kind,state` and `key,client,key`
+    fn write_rows<W: Write>(&self, writer: &mut csv::Writer<W>) -> Result<(), csv::Error> {
+        writer.write_record(MAGIC)?;
+
+        let mut offsets = vec!["offsets".to_string()];
+        offsets.extend(self.offsets.iter().map(u64::to_string));
+        writer.write_record(&offsets)?;
+
+        for (stream, position) in self.positions.iter().enumerate() {
+            if let Some(position) = position {
+                writer.write_record([
+                    "position",
+                    &stream.to_string(),
+                    &position.byte().to_string(),
+                    &position.line().to_string(),
+                    &position.record().to_string(),
+                ])?;
+            }
+        }
+
+        for account in &self.accounts {
+            let client = account.client_id().to_string();
+            writer.write_record([
+                "account",
+                &client,
+                &account.available().to_decimal_string(),
+                &account.held().to_decimal_string(),
+                &account.is_locked().to_string(),
+                &join_ids(account.disputed_ids()),
+                &join_ids(account.hold_ids()),
+                &account.credit_limit().to_decimal_string(),
+            ])?;
+
+            for (currency, balance) in account.currency_balances() {
+                writer.write_record([
+                    "balance",
+                    &client,
+                    &currency.to_string(),
+                    &balance.available.to_decimal_string(),
+                    &balance.held.to_decimal_string(),
+                ])?;
+            }
+
+            for (key, value) in account.tags() {
+                writer.write_record(["tag", &client, key, value])?;
+            }
+        }
+
+        for (tx_id, record) in &self.records {
+            writer.write_record([
+                "record",
+                &tx_id.to_string(),
+                &record.client_id.to_string(),
+                &record.amount.to_decimal_string(),
+                &record.currency.map(|c| c.to_string()).unwrap_or_default(),
+                record.kind.as_str(),
+                record.state.as_str(),
+                &record.disputes.to_string(),
+                &record
+                    .held
+                    .map(|held| held.to_decimal_string())
+                    .unwrap_or_default(),
+            ])?;
+        }
+
+        for (client_id, key) in &self.idempotency_keys {
+            writer.write_record(["key", &client_id.to_string(), key])?;
+        }
+
+        Ok(())
+    }
+
+    fn read_row(&mut self, row: &csv::StringRecord, v1: bool) -> Result<(), IoError> {
+        let field = |index: usize| {
+            row.get(index)
+                .ok_or_else(|| invalid(format!("short {} row", &row[0])))
+        };
+
+        match &row[0] {
+            "offsets" => {
+                self.offsets = row
+                    .iter()
+                    .skip(1)
+                    .map(|offset| offset.parse().map_err(|_| invalid("bad offset")))
+                    .collect::<Result<_, _>>()?;
+                self.positions.resize(self.offsets.len(), None);
+            }
+            "position" => {
+                let stream: usize = parse(field(1)?)?;
+                let mut position = csv::Position::new();
+                position
+                    .set_byte(parse(field(2)?)?)
+                    .set_line(parse(field(3)?)?)
+                    .set_record(parse(field(4)?)?);
+                *self
+                    .positions
+                    .get_mut(stream)
+                    .ok_or_else(|| invalid("position row without offset"))? = Some(position);
+            }
+            "account" => {
+                let mut account = ClientAccount::new(parse(field(1)?)?);
+                account.set_available(A::from_decimal_str(field(2)?)?);
+                account.set_held(A::from_decimal_str(field(3)?)?);
+                if parse::<bool>(field(4)?)? {
+                    account.lock();
+                }
+                for tx_id in split_ids(field(5)?)? {
+                    account.add_disputed(tx_id);
+                }
+                // Version 1 rows list resolved ids before the holds
+                let holds = if v1 { 7 } else { 6 };
+                for tx_id in split_ids(field(holds)?)? {
+                    account.add_hold(tx_id);
+                }
+                // Checkpoints from before credit limits were recorded lack one
+                if let Some(limit) = row.get(holds + 1) {
+                    account.set_credit_limit(A::from_decimal_str(limit)?);
+                }
+                self.accounts.push(account);
+            }
+            "balance" => {
+                let client_id: ClientId = parse(field(1)?)?;
+                let currency: CurrencyCode = field(2)?.parse()?;
+                let balance = CurrencyBalance {
+                    available: A::from_decimal_str(field(3)?)?,
+                    held: A::from_decimal_str(field(4)?)?,
+                };
+                self.accounts
+                    .iter_mut()
+                    .rfind(|account| account.client_id() == client_id)
+                    .ok_or_else(|| invalid("balance row without account"))?
+                    .set_currency_balance(currency, balance);
+            }
+            "tag" => {
+                let client_id: ClientId = parse(field(1)?)?;
+                let (key, value) = (field(2)?, field(3)?);
+                self.accounts
+                    .iter_mut()
+                    .rfind(|account| account.client_id() == client_id)
+                    .ok_or_else(|| invalid("tag row without account"))?
+                    .set_tag(key, Some(value));
+            }
+            "record" => {
+                let currency = match field(4)? {
+                    "" => None,
+                    code => Some(code.parse()?),
+                };
+                // Checkpoints from before kinds and states were recorded lack both
+                let kind = match row.get(5) {
+                    Some(name) => named(&TX_KINDS, name, TxKind::as_str)?,
+                    None => TxKind::Deposit,
+                };
+                let state = match row.get(6) {
+                    Some(name) => named(&TX_STATES, name, TxState::as_str)?,
+                    None => TxState::Posted,
+                };
+                // ...and older ones still lack dispute counts
+                let disputes = match row.get(7) {
+                    Some(count) => parse(count)?,
+                    None => 0,
+                };
+                // ...and partly held disputes
+                let held = match row.get(8) {
+                    Some("") | None => None,
+                    Some(held) => Some(A::from_decimal_str(held)?),
+                };
+                let record =
+                    TransactionRecord::new(parse(field(2)?)?, A::from_decimal_str(field(3)?)?)
+                        .with_currency(currency)
+                        .with_kind(kind)
+                        .with_state(state)
+                        .with_disputes(disputes)
+                        .with_held(held);
+                self.records.push((parse(field(1)?)?, record));
+            }
+            "key" => {
+                self.idempotency_keys
+                    .push((parse(field(1)?)?, field(2)?.to_string()));
+            }
+            kind => return Err(invalid(format!("unknown row kind '{kind}'"))),
+        }
+        Ok(())
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> IoError {
+    IoError::InvalidCheckpoint(reason.into())
+}
+
+fn parse<V: std::str::FromStr>(field: &str) -> Result<V, IoError> {
+    field
+        .parse()
+        .map_err(|_| invalid(format!("bad value '{field}'")))
+}
+
+const TX_KINDS: [TxKind; 4] = [
+    TxKind::Deposit,
+    TxKind::Withdrawal,
+    TxKind::Transfer,
+    TxKind::Hold,
+];
+const TX_STATES: [TxState; 5] = [
+    TxState::Posted,
+    TxState::Disputed,
+    TxState::Resolved,
+    TxState::ChargedBack,
+    TxState::Reversed,
+];
+
+/// The value among `values` called `field`
+fn named<V: Copy>(values: &[V], field: &str, name: fn(&V) -> &'static str) -> Result<V, IoError> {
+    values
+        .iter()
+        .copied()
+        .find(|value| name(value) == field)
+        .ok_or_else(|| invalid(format!("bad value '{field}'")))
+}
+
+fn join_ids(ids: impl Iterator<Item = TransactionId>) -> String {
+    let mut ids: Vec<_> = ids.collect();
+    ids.sort_unstable();
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn split_ids(field: &str) -> Result<Vec<TransactionId>, IoError> {
+    field.split_whitespace().map(parse).collect()
+}
+
+/// Shared checkpoint state for one `StreamProcessor::process` run
+///
+/// Shards hold a read guard while consuming and applying a record; taking a
+/// checkpoint holds the write guard, so offsets and storage always agree.
+/// A stream is not polled again until its record has been applied, so the
+/// position its reader reports then is the one just past that record.
+pub(crate) struct Checkpointer<A: AmountType, M, T> {
+    path: PathBuf,
+    interval: u64,
+    offsets: Vec<AtomicU64>,
+    readers: Vec<Option<ReadPosition>>,
+    positions: Vec<Mutex<Option<csv::Position>>>,
+    idempotency_keys: Arc<IdempotencyKeys>,
+    consumed: AtomicU64,
+    next_due: AtomicU64,
+    gate: RwLock<()>,
+    accounts: M,
+    transactions: T,
+    _amount: std::marker::PhantomData<A>,
+}
+
+impl<A, M, T> Checkpointer<A, M, T>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    T: TransactionStoreManager<A>,
+{
+    pub(crate) fn new(
+        path: PathBuf,
+        interval: u64,
+        offsets: Vec<u64>,
+        readers: Vec<Option<ReadPosition>>,
+        idempotency_keys: Arc<IdempotencyKeys>,
+        accounts: M,
+        transactions: T,
+    ) -> Self {
+        let interval = interval.max(1);
+        let positions = readers
+            .iter()
+            .map(|reader| Mutex::new(reader.as_ref().map(ReadPosition::get)))
+            .collect();
+        Self {
+            path,
+            interval,
+            offsets: offsets.into_iter().map(AtomicU64::new).collect(),
+            readers,
+            positions,
+            idempotency_keys,
+            consumed: AtomicU64::new(0),
+            next_due: AtomicU64::new(interval),
+            gate: RwLock::new(()),
+            accounts,
+            transactions,
+            _amount: std::marker::PhantomData,
+        }
+    }
+
+    /// Count one record from `stream`; checkpoints wait until the guard is dropped
+    pub(crate) fn begin_record(&self, stream: usize) -> RwLockReadGuard<'_, ()> {
+        let guard = self.gate.read();
+        self.offsets[stream].fetch_add(1, Ordering::Relaxed);
+        if let Some(reader) = &self.readers[stream] {
+            *self.positions[stream].lock() = Some(reader.get());
+        }
+        self.consumed.fetch_add(1, Ordering::Relaxed);
+        guard
+    }
+
+    /// Write a checkpoint if `interval` records were consumed since the last one
+    pub(crate) fn save_if_due(&self) {
+        let due = self.next_due.load(Ordering::Relaxed);
+        if self.consumed.load(Ordering::Relaxed) < due {
+            return;
+        }
+        // Only one shard takes each checkpoint
+        if self
+            .next_due
+            .compare_exchange(
+                due,
+                due + self.interval,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            self.save();
+        }
+    }
+
+    /// Capture consistent offsets and storage, then write them outside the lock
+    pub(crate) fn save(&self) {
+        let checkpoint = {
+            let _exclusive = self.gate.write();
+            let offsets = self
+                .offsets
+                .iter()
+                .map(|offset| offset.load(Ordering::Relaxed))
+                .collect();
+            let positions = self
+                .positions
+                .iter()
+                .map(|position| position.lock().clone())
+                .collect();
+            Checkpoint::capture(offsets, &self.accounts, &self.transactions)
+                .with_positions(positions)
+                .with_idempotency_keys(&self.idempotency_keys)
+        };
+
+        match checkpoint.save(&self.path) {
+            Ok(()) => debug!(path = %self.path.display(), "Checkpoint written"),
+            Err(e) => warn!(path = %self.path.display(), "Failed to write checkpoint: {}", e),
+        }
+    }
+}
+
+/// A shard's view of the checkpointer
+///
+/// Merge and Chain hand each record straight from its input stream to the
+/// shard loop, so the stream polled last is the source of the record being
+/// processed. Each input is wrapped to note its index when it yields.
+pub(crate) struct ShardCheckpoint<A: AmountType, M, T> {
+    pub(crate) checkpointer: Arc<Checkpointer<A, M, T>>,
+    pub(crate) last_stream: Arc<AtomicUsize>,
+}
+
+impl<A, M, T> ShardCheckpoint<A, M, T>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+    T: TransactionStoreManager<A>,
+{
+    pub(crate) fn begin_record(&self) -> RwLockReadGuard<'_, ()> {
+        self.checkpointer
+            .begin_record(self.last_stream.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn save_if_due(&self) {
+        self.checkpointer.save_if_due();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        FixedPoint, apply_deposit, apply_dispute, apply_set_credit_limit, apply_withdrawal,
+    };
+    use crate::storage::{ConcurrentAccountManager, ConcurrentTransactionStore};
+
+    fn amount(raw: i64) -> FixedPoint {
+        FixedPoint::from_raw(raw)
+    }
+
+    fn populated() -> (
+        ConcurrentAccountManager<FixedPoint>,
+        ConcurrentTransactionStore<FixedPoint>,
+    ) {
+        let accounts = ConcurrentAccountManager::new();
+        let transactions = ConcurrentTransactionStore::new();
+
+        accounts
+            .entry(1)
+            .unwrap()
+            .try_update(|account| {
+                apply_deposit(account, amount(50_000))?;
+                apply_dispute(account, 7, amount(20_000))?;
+                account.set_currency_balance(
+                    "EUR".parse().unwrap(),
+                    CurrencyBalance {
+                        available: amount(10_000),
+                        held: amount(0),
+                    },
+                );
+                account.set_tag("region", Some("eu, west"));
+                Ok(())
+            })
+            .unwrap();
+        accounts
+            .entry(2)
+            .unwrap()
+            .try_update(|account| {
+                apply_set_credit_limit(account, amount(30_000))?;
+                apply_withdrawal(account, amount(12_500))
+            })
+            .unwrap();
+        transactions.insert(
+            7,
+            TransactionRecord::new(1, amount(20_000))
+                .with_state(TxState::Disputed)
+                .with_disputes(2)
+                .with_held(Some(amount(15_000))),
+        );
+        transactions.insert(
+            8,
+            TransactionRecord::new(1, amount(10_000))
+                .with_currency(Some("EUR".parse().unwrap()))
+                .with_kind(TxKind::Withdrawal),
+        );
+
+        (accounts, transactions)
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let (accounts, transactions) = populated();
+        let mut position = csv::Position::new();
+        position.set_byte(4_096).set_line(120).set_record(11);
+        let keys = IdempotencyKeys::new();
+        keys.claim(1, "batch-7, row 3");
+        let checkpoint = Checkpoint::capture(vec![3, 0, 12], &accounts, &transactions)
+            .with_positions(vec![None, None, Some(position)])
+            .with_idempotency_keys(&keys);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.checkpoint");
+        checkpoint.save(&path).unwrap();
+
+        assert_eq!(Checkpoint::load(&path).unwrap(), checkpoint);
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn round_trip_keeps_overdrawn_accounts_and_their_credit_limit() {
+        let (accounts, transactions) = populated();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.checkpoint");
+        Checkpoint::capture(vec![2], &accounts, &transactions)
+            .save(&path)
+            .unwrap();
+
+        let account = Checkpoint::<FixedPoint>::load(&path).unwrap().accounts()[1].clone();
+        assert_eq!(account.available(), amount(-12_500));
+        assert_eq!(account.credit_limit(), amount(30_000));
+    }
+
+    #[test]
+    fn records_without_kind_and_state_load_as_posted_deposits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.checkpoint");
+        std::fs::write(&path, "pay-checkpoint,1\noffsets,1\nrecord,7,1,2.0000,\n").unwrap();
+
+        let checkpoint = Checkpoint::<FixedPoint>::load(&path).unwrap();
+
+        assert_eq!(
+            checkpoint.records,
+            vec![(7, TransactionRecord::new(1, amount(20_000)))]
+        );
+    }
+
+    #[test]
+    fn version_1_account_rows_skip_their_resolved_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.checkpoint");
+        std::fs::write(
+            &path,
+            "pay-checkpoint,1\noffsets,1\naccount,1,2.0000,1.0000,false,7,4,9,0.5000\n",
+        )
+        .unwrap();
+
+        let account = Checkpoint::<FixedPoint>::load(&path).unwrap().accounts()[0].clone();
+
+        assert!(account.is_disputed(7));
+        assert!(account.has_active_hold(9));
+        assert!(!account.has_active_hold(4));
+        assert_eq!(account.credit_limit(), amount(5_000));
+    }
+
+    #[test]
+    fn restore_marks_idempotency_keys_as_used() {
+        let (accounts, transactions) = populated();
+        let keys = IdempotencyKeys::new();
+        keys.claim(2, "upload-1");
+        let checkpoint =
+            Checkpoint::capture(vec![1], &accounts, &transactions).with_idempotency_keys(&keys);
+
+        let restored = IdempotencyKeys::new();
+        checkpoint.restore_idempotency_keys(&restored);
+
+        assert!(restored.contains(2, "upload-1"));
+        assert_eq!(restored.len(), 1);
+    }
+
+    #[test]
+    fn restore_copies_accounts_and_records() {
+        let (accounts, transactions) = populated();
+        let checkpoint = Checkpoint::capture(vec![5], &accounts, &transactions);
+
+        let restored_accounts = ConcurrentAccountManager::new();
+        let restored_transactions = ConcurrentTransactionStore::new();
+        checkpoint
+            .restore(&restored_accounts, &restored_transactions)
+            .unwrap();
+
+        let account = restored_accounts.account(1).unwrap();
+        assert_eq!(account, accounts.account(1).unwrap());
+        assert!(account.is_disputed(7));
+        assert_eq!(restored_transactions.get(8), transactions.get(8));
+    }
+
+    #[test]
+    fn load_rejects_foreign_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("accounts.csv");
+        fs::write(&path, "client,available,held,total,locked\n").unwrap();
+
+        assert!(matches!(
+            Checkpoint::<FixedPoint>::load(&path),
+            Err(IoError::InvalidCheckpoint(_))
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+use std::io::Write;
+
+use parking_lot::Mutex;
+use tracing::warn;
+
+use crate::domain::{AmountType, Transaction};
+use crate::engine::{QuarantineSink, QuarantinedTransaction};
+
+/// A record skipped during stream processing, with the reason it was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter<A: AmountType> {
+    /// The parsed transaction, or None if the row could not be read or parsed
+    pub transaction: Option<Transaction<A>>,
+    /// The IO or engine error message
+    pub reason: String,
+}
+
+/// Receives every record rejected by a `StreamProcessor`
+///
+/// Called from every shard task, so implementations must be thread-safe.
+/// Records are reported regardless of the error policy, so a `SilentSkip`
+/// run still produces a complete rejects report.
+pub trait DeadLetterSink<A: AmountType>: Send + Sync {
+    fn record(&self, letter: DeadLetter<A>);
+}
+
+/// Dead-letter sink that writes rejected records as CSV rows
+///
+/// Columns mirror the input format plus an `error` column:
+/// `type,client,tx,amount,to,currency,tag,error`. Rows that could not be parsed
+/// only carry the error. Also serves as a `QuarantineSink`: the default input
+/// reader ignores the `error` column, so a quarantine file can be processed
+/// again as is once the accounts are unlocked.
+pub struct CsvDeadLetterWriter<W: Write + Send> {
+    writer: Mutex<csv::Writer<W>>,
+}
+
+impl<W: Write + Send> CsvDeadLetterWriter<W> {
+    /// Create a writer and emit the header row
+    pub fn new(writer: W) -> Result<Self, csv::Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record([
+            "type", "client", "tx", "amount", "to", "currency", "tag", "error",
+        ])?;
+
+        Ok(Self {
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Flush buffered rows to the underlying writer
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.writer.lock().flush()
+    }
+}
+
+impl<W: Write + Send> CsvDeadLetterWriter<W> {
+    fn write_row<A: AmountType>(
+        &self,
+        tx: Option<&Transaction<A>>,
+        reason: &str,
+    ) -> csv::Result<()> {
+        let row = tx.map(transaction_fields).unwrap_or_default();
+        let [kind, client, tx_id, amount, to, currency, tag] = &row;
+        self.writer.lock().write_record([
+            kind.as_str(),
+            client,
+            tx_id,
+            amount,
+            to,
+            currency,
+            tag,
+            reason,
+        ])
+    }
+}
+
+impl<A: AmountType, W: Write + Send> DeadLetterSink<A> for CsvDeadLetterWriter<W> {
+    fn record(&self, letter: DeadLetter<A>) {
+        if let Err(e) = self.write_row(letter.transaction.as_ref(), &letter.reason) {
+            warn!("Failed to write dead letter: {}", e);
+        }
+    }
+}
+
+impl<A: AmountType, W: Write + Send> QuarantineSink<A> for CsvDeadLetterWriter<W> {
+    fn quarantine(&self, quarantined: QuarantinedTransaction<A>) {
+        if let Err(e) = self.write_row(Some(&quarantined.transaction), &quarantined.reason) {
+            warn!("Failed to write quarantined transaction: {}", e);
+        }
+    }
+}
+
+/// Render a transaction as input-format fields: type, client, tx, amount, to, currency, tag
+fn transaction_fields<A: AmountType>(tx: &Transaction<A>) -> [String; 7] {
+    let to = match tx {
+        Transaction::Transfer { to_client, .. } => Some(*to_client),
+        _ => None,
+    };
+    let tag = match tx {
+        Transaction::SetTag { key, value, .. } => {
+            format!("{key}={}", value.as_deref().unwrap_or_default())
+        }
+        _ => String::new(),
+    };
+
+    [
+        tx.type_name().to_string(),
+        tx.client_id().to_string(),
+        tx.tx_id().map(|id| id.to_string()).unwrap_or_default(),
+        tx.amount()
+            .map(|a| a.to_decimal_string())
+            .unwrap_or_default(),
+        to.map(|c| c.to_string()).unwrap_or_default(),
+        tx.currency().map(|c| c.to_string()).unwrap_or_default(),
+        tag,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn written(sink: CsvDeadLetterWriter<Vec<u8>>) -> String {
+        let writer = sink.writer.into_inner().into_inner().unwrap();
+        String::from_utf8(writer).unwrap()
+    }
+
+    #[test]
+    fn writes_parsed_and_unparsed_rejects() {
+        let sink = CsvDeadLetterWriter::new(Vec::new()).unwrap();
+
+        sink.record(DeadLetter::<FixedPoint> {
+            transaction: Some(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 7,
+                amount: FixedPoint::from_raw(25_000),
+                currency: None,
+            }),
+            reason: "Domain error: Insufficient funds".to_string(),
+        });
+        sink.record(DeadLetter::<FixedPoint> {
+            transaction: None,
+            reason: "Invalid transaction type: refund".to_string(),
+        });
+
+        assert_eq!(
+            written(sink),
+            "type,client,tx,amount,to,currency,tag,error\n\
+             withdrawal,1,7,2.5000,,,,Domain error: Insufficient funds\n\
+             ,,,,,,,Invalid transaction type: refund\n"
+        );
+    }
+
+    #[test]
+    fn writes_transfer_destination_and_currency() {
+        let sink = CsvDeadLetterWriter::new(Vec::new()).unwrap();
+
+        sink.record(DeadLetter::<FixedPoint> {
+            transaction: Some(Transaction::Transfer {
+                from_client: 2,
+                to_client: 3,
+                tx_id: 9,
+                amount: FixedPoint::from_raw(10_000),
+                currency: Some("USD".parse().unwrap()),
+            }),
+            reason: "Account locked".to_string(),
+        });
+
+        assert!(written(sink).ends_with("transfer,2,9,1.0000,3,USD,,Account locked\n"));
+    }
+
+    #[test]
+    fn writes_tag_as_key_value() {
+        let sink = CsvDeadLetterWriter::new(Vec::new()).unwrap();
+
+        sink.record(DeadLetter::<FixedPoint> {
+            transaction: Some(Transaction::SetTag {
+                client_id: 5,
+                key: "tier".to_string(),
+                value: Some("gold".to_string()),
+            }),
+            reason: "Admin operations are not allowed".to_string(),
+        });
+
+        assert!(written(sink).ends_with("tag,5,,,,,tier=gold,Admin operations are not allowed\n"));
+    }
+}
@@ -0,0 +1,94 @@
+use futures::{Stream, StreamExt, stream};
+use tokio::sync::mpsc;
+
+use crate::domain::{AmountType, TimestampedTransaction};
+use crate::io::IoError;
+
+/// Route every record of `input` to the worker owning its client
+///
+/// Spawns one dispatcher task and returns one stream per worker. A client
+/// always maps to the same worker (`client_id % workers`), and each worker's
+/// channel is FIFO, so every client's records keep their input order. Read
+/// errors carry no client and go to worker 0. Each channel buffers up to
+/// `capacity` records; once a worker stops receiving (its error policy
+/// aborted), dispatch stops and every other worker drains and finishes.
+pub(crate) fn dispatch_by_client<A, S>(
+    mut input: S,
+    workers: usize,
+    capacity: usize,
+) -> Vec<impl Stream<Item = S::Item> + Send + 'static>
+where
+    A: AmountType + 'static,
+    S: Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Unpin + Send + 'static,
+{
+    let workers = workers.max(1);
+    let (senders, receivers): (Vec<_>, Vec<_>) =
+        (0..workers).map(|_| mpsc::channel(capacity.max(1))).unzip();
+
+    tokio::spawn(async move {
+        while let Some(item) = input.next().await {
+            let worker = match &item {
+                Ok(timestamped) => timestamped.transaction.client_id() as usize % workers,
+                Err(_) => 0,
+            };
+            if senders[worker].send(item).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    receivers
+        .into_iter()
+        .map(|rx| {
+            stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|item| (item, rx))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ClientId, FixedPoint, Transaction, TransactionId};
+
+    fn deposit(
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Result<TimestampedTransaction<FixedPoint>, IoError> {
+        Ok(TimestampedTransaction::new(
+            Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: FixedPoint::from_raw(1),
+                currency: None,
+            },
+            None,
+        ))
+    }
+
+    #[tokio::test]
+    async fn clients_stay_on_one_worker_in_order() {
+        let input = stream::iter(vec![
+            deposit(1, 1),
+            deposit(2, 2),
+            deposit(3, 3),
+            deposit(1, 4),
+            Err(IoError::MissingField("tx".to_string())),
+        ]);
+
+        let mut workers = dispatch_by_client(input, 2, 8);
+        let odd: Vec<_> = workers.pop().unwrap().collect().await;
+        let even: Vec<_> = workers.pop().unwrap().collect().await;
+
+        let tx_ids = |items: &[Result<TimestampedTransaction<FixedPoint>, IoError>]| {
+            items
+                .iter()
+                .map(|item| item.as_ref().ok().and_then(|tx| tx.transaction.tx_id()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tx_ids(&odd), vec![Some(1), Some(3), Some(4)]);
+        // Client 2, then the read error
+        assert_eq!(tx_ids(&even), vec![Some(2), None]);
+    }
+}
@@ -1,6 +1,25 @@
+use std::future::Future;
+
+use futures::future::BoxFuture;
+use thiserror::Error;
+use tracing::warn;
+
 use crate::engine::EngineError;
 use crate::io::IoError;
 
+/// Any error reported to an `ErrorPolicy`
+#[derive(Error, Debug)]
+pub enum ProcessingError {
+    #[error(transparent)]
+    Io(#[from] IoError),
+
+    #[error(transparent)]
+    Engine(#[from] EngineError),
+
+    #[error("Memory budget exceeded: about {used} bytes in use, limit is {limit}")]
+    MemoryBudgetExceeded { used: u64, limit: u64 },
+}
+
 /// Policy for handling errors during stream processing
 pub trait ErrorPolicy: Send + Sync {
     /// Handle an IO error (CSV parsing, reading)
@@ -10,6 +29,135 @@ pub trait ErrorPolicy: Send + Sync {
     /// Handle an engine error (transaction processing)
     /// Return true to continue processing, false to abort
     fn handle_engine_error(&self, error: EngineError) -> bool;
+
+    /// Handle storage growing past a `MemoryBudget` (after its eviction hook ran)
+    /// Return true to continue processing, false to abort (the default)
+    fn handle_budget_exceeded(&self, used: u64, limit: u64) -> bool {
+        let _ = (used, limit);
+        false
+    }
+
+    /// Handle any kind of error, possibly asynchronously
+    ///
+    /// Stream processors call this and await the result before reading the
+    /// next record. The default dispatches to the synchronous handlers;
+    /// asynchronous policies (see `AsyncCallback`) override it.
+    fn handle_error(&self, error: ProcessingError) -> BoxFuture<'_, bool> {
+        let proceed = match error {
+            ProcessingError::Io(e) => self.handle_io_error(e),
+            ProcessingError::Engine(e) => self.handle_engine_error(e),
+            ProcessingError::MemoryBudgetExceeded { used, limit } => {
+                self.handle_budget_exceeded(used, limit)
+            }
+        };
+        Box::pin(std::future::ready(proceed))
+    }
+}
+
+/// Policy that asks a closure what to do with each error
+///
+/// The closure returns true to continue processing, false to abort. Shard
+/// tasks share clones of the policy, so keep any state behind an `Arc`.
+///
+/// # Example
+/// ```rust,ignore
+/// // Skip the first 10 errors, then abort
+/// let seen = Arc::new(AtomicUsize::new(0));
+/// let policy = Callback::new(move |error| {
+///     warn!("Skipping: {}", error);
+///     seen.fetch_add(1, Ordering::Relaxed) < 10
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Callback<F> {
+    callback: F,
+}
+
+impl<F> Callback<F>
+where
+    F: Fn(ProcessingError) -> bool + Send + Sync,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F> ErrorPolicy for Callback<F>
+where
+    F: Fn(ProcessingError) -> bool + Send + Sync,
+{
+    fn handle_io_error(&self, error: IoError) -> bool {
+        (self.callback)(error.into())
+    }
+
+    fn handle_engine_error(&self, error: EngineError) -> bool {
+        (self.callback)(error.into())
+    }
+
+    fn handle_budget_exceeded(&self, used: u64, limit: u64) -> bool {
+        (self.callback)(ProcessingError::MemoryBudgetExceeded { used, limit })
+    }
+}
+
+/// Policy that awaits an async closure for each error
+///
+/// Use this when deciding involves I/O, e.g. reporting to a remote service.
+/// The shard waits for the returned future before reading its next record.
+/// Only `handle_error` runs the closure: the synchronous handlers could only
+/// block on the future, which stalls (or deadlocks) a runtime worker, so they
+/// log the error and abort instead.
+///
+/// # Example
+/// ```rust,ignore
+/// let policy = AsyncCallback::new(move |error| {
+///     let client = client.clone();
+///     async move { client.report(error.to_string()).await.is_ok() }
+/// });
+/// ```
+#[derive(Clone)]
+pub struct AsyncCallback<F> {
+    callback: F,
+}
+
+impl<F, Fut> AsyncCallback<F>
+where
+    F: Fn(ProcessingError) -> Fut + Send + Sync,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F, Fut> ErrorPolicy for AsyncCallback<F>
+where
+    F: Fn(ProcessingError) -> Fut + Send + Sync,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    fn handle_io_error(&self, error: IoError) -> bool {
+        reject_sync(error.into())
+    }
+
+    fn handle_engine_error(&self, error: EngineError) -> bool {
+        reject_sync(error.into())
+    }
+
+    fn handle_budget_exceeded(&self, used: u64, limit: u64) -> bool {
+        reject_sync(ProcessingError::MemoryBudgetExceeded { used, limit })
+    }
+
+    fn handle_error(&self, error: ProcessingError) -> BoxFuture<'_, bool> {
+        Box::pin((self.callback)(error))
+    }
+}
+
+/// Abort on an error reported to an async policy through a synchronous handler
+fn reject_sync(error: ProcessingError) -> bool {
+    warn!(
+        "Async error policy called synchronously (aborting): {}",
+        error
+    );
+    false
 }
 
 /// Skip errors and continue processing (log to stderr)
@@ -26,6 +174,11 @@ impl ErrorPolicy for SkipErrors {
         eprintln!("Engine error (skipping): {}", error);
         true
     }
+
+    fn handle_budget_exceeded(&self, used: u64, limit: u64) -> bool {
+        eprintln!("Memory budget exceeded (continuing): {used} of {limit} bytes");
+        true
+    }
 }
 
 /// Abort on first error
@@ -42,6 +195,11 @@ impl ErrorPolicy for AbortOnError {
         eprintln!("Engine error (aborting): {}", error);
         false
     }
+
+    fn handle_budget_exceeded(&self, used: u64, limit: u64) -> bool {
+        eprintln!("Memory budget exceeded (aborting): {used} of {limit} bytes");
+        false
+    }
 }
 
 /// Silent error policy - skip errors without logging
@@ -56,6 +214,10 @@ impl ErrorPolicy for SilentSkip {
     fn handle_engine_error(&self, _error: EngineError) -> bool {
         true
     }
+
+    fn handle_budget_exceeded(&self, _used: u64, _limit: u64) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -104,4 +266,72 @@ mod tests {
         let error = EngineError::Domain(DomainError::InsufficientFunds);
         assert!(policy.handle_engine_error(error));
     }
+
+    #[test]
+    fn callback_decides_per_error() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        let policy = Callback::new(move |error| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            matches!(error, ProcessingError::Engine(_))
+        });
+
+        assert!(policy.handle_engine_error(EngineError::TransactionNotFound(1)));
+        assert!(!policy.handle_io_error(IoError::MissingField("tx".to_string())));
+        assert_eq!(seen.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn async_callback_is_awaited() {
+        let policy = AsyncCallback::new(|error| async move {
+            tokio::task::yield_now().await;
+            !matches!(error, ProcessingError::Io(_))
+        });
+
+        assert!(
+            policy
+                .handle_error(EngineError::TransactionNotFound(1).into())
+                .await
+        );
+        assert!(
+            !policy
+                .handle_error(IoError::MissingField("tx".to_string()).into())
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn async_callback_sync_handlers_abort_without_blocking() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        let policy = AsyncCallback::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            std::future::pending()
+        });
+
+        assert!(!policy.handle_engine_error(EngineError::TransactionNotFound(1)));
+        assert!(!policy.handle_io_error(IoError::MissingField("tx".to_string())));
+        assert!(!policy.handle_budget_exceeded(2, 1));
+        assert_eq!(seen.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn default_handle_error_uses_sync_handlers() {
+        assert!(
+            !AbortOnError
+                .handle_error(EngineError::TransactionNotFound(1).into())
+                .await
+        );
+        assert!(
+            SilentSkip
+                .handle_error(EngineError::TransactionNotFound(1).into())
+                .await
+        );
+    }
 }
@@ -0,0 +1,88 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+
+use crate::domain::{AmountType, Transaction};
+
+/// Whether a logged error came from reading a record or from the engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoggedErrorKind {
+    /// The record could not be read or parsed (`ProcessingError::Io`)
+    Io,
+    /// The engine rejected the transaction (`ProcessingError::Engine`)
+    Engine,
+}
+
+/// An IO or engine error met during a run, kept by `with_error_log`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedError<A: AmountType> {
+    pub kind: LoggedErrorKind,
+    /// The error message, as passed to the `ErrorPolicy`
+    pub message: String,
+    /// The rejected transaction, or None if the record could not be read
+    pub transaction: Option<Transaction<A>>,
+    /// Name of the input stream the record came from, if known
+    pub stream: Option<String>,
+}
+
+/// Errors collected across every shard of a run, up to a capacity
+pub(crate) struct ErrorLog<A: AmountType> {
+    capacity: usize,
+    errors: Mutex<Vec<LoggedError<A>>>,
+    dropped: AtomicU64,
+}
+
+impl<A: AmountType> ErrorLog<A> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            errors: Mutex::new(Vec::new()),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Keep `error`, or count it as dropped once the log is full
+    pub(crate) fn record(&self, error: LoggedError<A>) {
+        let mut errors = self.errors.lock();
+        if errors.len() < self.capacity {
+            errors.push(error);
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The kept errors, in the order they were met, and the number dropped
+    pub(crate) fn take(&self) -> (Vec<LoggedError<A>>, u64) {
+        (
+            std::mem::take(&mut *self.errors.lock()),
+            self.dropped.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn io_error(message: &str) -> LoggedError<FixedPoint> {
+        LoggedError {
+            kind: LoggedErrorKind::Io,
+            message: message.to_string(),
+            transaction: None,
+            stream: None,
+        }
+    }
+
+    #[test]
+    fn errors_past_the_capacity_are_counted() {
+        let log = ErrorLog::new(2);
+        for message in ["a", "b", "c", "d"] {
+            log.record(io_error(message));
+        }
+
+        let (errors, dropped) = log.take();
+        assert_eq!(errors, vec![io_error("a"), io_error("b")]);
+        assert_eq!(dropped, 2);
+    }
+}
@@ -0,0 +1,208 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use futures::stream::SelectAll;
+use futures::{Stream, StreamExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::processor::{ProcessorResults, ShardAssignment, TransactionStream};
+use crate::domain::{AmountType, TimestampedTransaction, Transaction};
+use crate::io::IoError;
+
+/// A stream added through a `StreamHandle`, on its way to its shard
+pub(crate) struct NewStream<A: AmountType> {
+    pub(crate) index: usize,
+    pub(crate) name: String,
+    pub(crate) stream: TransactionStream<A>,
+}
+
+/// Handle to a `StreamProcessor` running in the background
+///
+/// Returned by `StreamProcessor::process_detached`. Streams added here are
+/// numbered after the ones added before processing started, so unnamed
+/// ones continue the `stream-<index>` sequence. Dropping the handle
+/// without calling `finish` lets processing run to completion unobserved.
+pub struct StreamHandle<A: AmountType> {
+    senders: Vec<mpsc::UnboundedSender<NewStream<A>>>,
+    assignment: Arc<ShardAssignment>,
+    next_index: AtomicUsize,
+    task: JoinHandle<ProcessorResults<A>>,
+}
+
+impl<A: AmountType + 'static> StreamHandle<A> {
+    pub(crate) fn new(
+        senders: Vec<mpsc::UnboundedSender<NewStream<A>>>,
+        assignment: Arc<ShardAssignment>,
+        first_index: usize,
+        task: JoinHandle<ProcessorResults<A>>,
+    ) -> Self {
+        Self {
+            senders,
+            assignment,
+            next_index: AtomicUsize::new(first_index),
+            task,
+        }
+    }
+
+    /// Add a stream to the running processor
+    ///
+    /// Returns false if the shard it was assigned to has already stopped
+    /// (its error policy aborted); the stream is then dropped unread.
+    pub fn add_stream<S>(&self, stream: S) -> bool
+    where
+        S: Stream<Item = Result<Transaction<A>, IoError>> + Send + 'static,
+    {
+        self.send(None, Box::pin(timestamped(stream)))
+    }
+
+    /// Add a stream reported under `name` in the results
+    pub fn add_stream_named<S>(&self, name: impl Into<String>, stream: S) -> bool
+    where
+        S: Stream<Item = Result<Transaction<A>, IoError>> + Send + 'static,
+    {
+        self.send(Some(name.into()), Box::pin(timestamped(stream)))
+    }
+
+    /// Add a stream whose transactions carry event timestamps
+    pub fn add_timestamped_stream_named<S>(&self, name: impl Into<String>, stream: S) -> bool
+    where
+        S: Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send + 'static,
+    {
+        self.send(Some(name.into()), Box::pin(stream))
+    }
+
+    fn send(&self, name: Option<String>, stream: TransactionStream<A>) -> bool {
+        let index = self.next_index.fetch_add(1, Ordering::Relaxed);
+        let shard = self.assignment.shard_for(index, None, self.senders.len());
+        let name = name.unwrap_or_else(|| format!("stream-{index}"));
+
+        self.senders[shard]
+            .send(NewStream {
+                index,
+                name,
+                stream,
+            })
+            .is_ok()
+    }
+
+    /// Stop accepting streams and wait for every shard to finish
+    pub async fn finish(self) -> ProcessorResults<A> {
+        drop(self.senders);
+        match self.task.await {
+            Ok(results) => results,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+fn timestamped<A, S>(
+    stream: S,
+) -> impl Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send
+where
+    A: AmountType,
+    S: Stream<Item = Result<Transaction<A>, IoError>> + Send,
+{
+    stream.map(|result| result.map(TimestampedTransaction::from))
+}
+
+/// Merge of every stream received on a shard's channel
+///
+/// `wrap` prepares each new stream (e.g. for tracking) before it joins the
+/// merge. Ends once the channel is closed and every received stream has
+/// ended.
+pub(crate) struct Incoming<A: AmountType, F> {
+    receiver: Option<mpsc::UnboundedReceiver<NewStream<A>>>,
+    streams: SelectAll<TransactionStream<A>>,
+    wrap: F,
+}
+
+impl<A, F> Incoming<A, F>
+where
+    A: AmountType,
+    F: FnMut(NewStream<A>) -> TransactionStream<A>,
+{
+    pub(crate) fn new(receiver: mpsc::UnboundedReceiver<NewStream<A>>, wrap: F) -> Self {
+        Self {
+            receiver: Some(receiver),
+            streams: SelectAll::new(),
+            wrap,
+        }
+    }
+}
+
+impl<A, F> Stream for Incoming<A, F>
+where
+    A: AmountType,
+    F: FnMut(NewStream<A>) -> TransactionStream<A> + Unpin,
+{
+    type Item = Result<TimestampedTransaction<A>, IoError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        // Pick up new streams first; Pending registers for the next arrival
+        while let Some(receiver) = &mut this.receiver {
+            match receiver.poll_recv(cx) {
+                Poll::Ready(Some(new)) => this.streams.push((this.wrap)(new)),
+                Poll::Ready(None) => this.receiver = None,
+                Poll::Pending => break,
+            }
+        }
+
+        match this.streams.poll_next_unpin(cx) {
+            Poll::Ready(Some(item)) => Poll::Ready(Some(item)),
+            // No stream left to read, but more may still arrive
+            Poll::Ready(None) if this.receiver.is_some() => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, TransactionId};
+    use futures::stream;
+
+    fn deposits(count: usize) -> TransactionStream<FixedPoint> {
+        Box::pin(stream::iter((0..count).map(|i| {
+            Ok(TimestampedTransaction::from(Transaction::Deposit {
+                client_id: 1,
+                tx_id: i as TransactionId,
+                amount: FixedPoint::from_raw(1),
+                currency: None,
+            }))
+        })))
+    }
+
+    #[tokio::test]
+    async fn incoming_waits_for_streams_until_the_channel_closes() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut incoming = Incoming::new(rx, |new: NewStream<FixedPoint>| new.stream);
+
+        let send = |count| {
+            tx.send(NewStream {
+                index: 0,
+                name: String::new(),
+                stream: deposits(count),
+            })
+            .unwrap()
+        };
+        send(2);
+        assert!(incoming.next().await.is_some());
+        assert!(incoming.next().await.is_some());
+
+        // Drained, but the channel is still open
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut incoming).poll_next(&mut cx).is_pending());
+
+        send(1);
+        drop(tx);
+        assert_eq!(incoming.count().await, 1);
+    }
+}
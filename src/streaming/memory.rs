@@ -0,0 +1,308 @@
+use std::mem::size_of;
+#[cfg(feature = "metrics")]
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::domain::{AmountType, ClientAccount, ClientId, TransactionId, TransactionRecord};
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsRegistry;
+use crate::storage::{ClientAccountManager, TransactionStoreManager};
+
+/// Bytes assumed per map entry on top of its key and value
+/// (hash-table control bytes, spare capacity and shard bookkeeping)
+const ENTRY_OVERHEAD: u64 = 16;
+
+/// Records processed between two estimates unless configured otherwise
+const DEFAULT_CHECK_INTERVAL: u64 = 100_000;
+
+/// Approximate storage footprint at one point in time
+///
+/// Estimated as entry count × entry size, so heap data behind an entry
+/// (dispute sets, currency codes) is not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Accounts held by the account manager
+    pub accounts: u64,
+    /// Estimated bytes for those accounts
+    pub account_bytes: u64,
+    /// Transaction records held in memory (spilled records are not counted)
+    pub records: u64,
+    /// Estimated bytes for those records
+    pub record_bytes: u64,
+}
+
+impl MemoryUsage {
+    /// Estimate what `accounts` and `store` currently hold
+    pub fn measure<A, M, T>(accounts: &M, store: &T) -> Self
+    where
+        A: AmountType,
+        M: ClientAccountManager<A>,
+        T: TransactionStoreManager<A>,
+    {
+        let mut account_count = 0u64;
+        accounts.for_each_account(&mut |_| account_count += 1);
+        let records = store.resident_records() as u64;
+
+        Self {
+            accounts: account_count,
+            account_bytes: account_count * account_entry_bytes::<A>(),
+            records,
+            record_bytes: records * record_entry_bytes::<A>(),
+        }
+    }
+
+    /// Accounts and records together
+    pub fn total_bytes(&self) -> u64 {
+        self.account_bytes + self.record_bytes
+    }
+}
+
+fn account_entry_bytes<A: AmountType>() -> u64 {
+    (size_of::<ClientId>() + size_of::<ClientAccount<A>>()) as u64 + ENTRY_OVERHEAD
+}
+
+fn record_entry_bytes<A: AmountType>() -> u64 {
+    (size_of::<TransactionId>() + size_of::<TransactionRecord<A>>()) as u64 + ENTRY_OVERHEAD
+}
+
+type EvictionHook = Box<dyn Fn(&MemoryUsage) + Send + Sync>;
+
+/// Approximate memory accounting for a `StreamProcessor`'s storage
+///
+/// Every `check_interval` records (counted across all shards) storage is
+/// measured with `MemoryUsage::measure`. When the estimate is above the
+/// limit, the eviction hook (if any) runs and storage is measured again; if
+/// it is still above, the error policy is asked via
+/// `ErrorPolicy::handle_budget_exceeded` whether to continue. The latest
+/// estimate is always available from `used_bytes`, and is reported as the
+/// `pay_memory_bytes` gauge when the processor has metrics.
+///
+/// # Example
+/// ```rust,ignore
+/// let budget = Arc::new(
+///     MemoryBudget::new(2 << 30)
+///         .with_check_interval(50_000)
+///         .with_eviction(move |_usage| cache.clear()),
+/// );
+///
+/// StreamProcessor::new(mgr, store, SkipErrors)
+///     .with_memory_budget(budget.clone())
+///     .add_stream(stream)
+///     .process()
+///     .await;
+/// println!("last estimate: {} bytes", budget.used_bytes());
+/// ```
+pub struct MemoryBudget {
+    limit: Option<u64>,
+    check_interval: u64,
+    processed: AtomicU64,
+    used: AtomicU64,
+    eviction: Option<EvictionHook>,
+    #[cfg(feature = "metrics")]
+    metrics: OnceLock<MetricsRegistry>,
+}
+
+impl MemoryBudget {
+    /// Budget of `limit_bytes` for accounts and transaction records together
+    pub fn new(limit_bytes: u64) -> Self {
+        Self::with_limit(Some(limit_bytes))
+    }
+
+    /// Track usage without ever reporting it as exceeded
+    pub fn unlimited() -> Self {
+        Self::with_limit(None)
+    }
+
+    fn with_limit(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            processed: AtomicU64::new(0),
+            used: AtomicU64::new(0),
+            eviction: None,
+            #[cfg(feature = "metrics")]
+            metrics: OnceLock::new(),
+        }
+    }
+
+    /// Measure storage every `records` records (defaults to 100,000; minimum 1)
+    ///
+    /// Each measurement visits every account, so very small intervals slow
+    /// processing down.
+    pub fn with_check_interval(mut self, records: u64) -> Self {
+        self.check_interval = records.max(1);
+        self
+    }
+
+    /// Run `evict` when the budget is exceeded, before asking the error policy
+    ///
+    /// The hook is given the usage that exceeded the budget and should free
+    /// what it can (e.g. caches the application owns); usage is measured
+    /// again afterwards.
+    pub fn with_eviction<F>(mut self, evict: F) -> Self
+    where
+        F: Fn(&MemoryUsage) + Send + Sync + 'static,
+    {
+        self.eviction = Some(Box::new(evict));
+        self
+    }
+
+    /// Configured limit, if any
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// Estimated bytes at the last measurement
+    pub fn used_bytes(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Report measurements to `metrics` (the first registry attached wins)
+    #[cfg(feature = "metrics")]
+    pub(crate) fn attach_metrics(&self, metrics: &MetricsRegistry) {
+        let _ = self.metrics.set(metrics.clone());
+    }
+
+    /// Count one processed record, measuring storage when a check is due
+    ///
+    /// Returns the usage (with the limit) when it is still above the limit
+    /// after eviction.
+    pub(crate) fn record_processed<A, M, T>(
+        &self,
+        accounts: &M,
+        store: &T,
+    ) -> Option<(MemoryUsage, u64)>
+    where
+        A: AmountType,
+        M: ClientAccountManager<A>,
+        T: TransactionStoreManager<A>,
+    {
+        let processed = self.processed.fetch_add(1, Ordering::Relaxed) + 1;
+        if !processed.is_multiple_of(self.check_interval) {
+            return None;
+        }
+        self.check(accounts, store)
+    }
+
+    /// Measure storage now; see `record_processed`
+    pub(crate) fn check<A, M, T>(&self, accounts: &M, store: &T) -> Option<(MemoryUsage, u64)>
+    where
+        A: AmountType,
+        M: ClientAccountManager<A>,
+        T: TransactionStoreManager<A>,
+    {
+        let mut usage = self.observe(MemoryUsage::measure(accounts, store));
+        let limit = self.limit?;
+        if usage.total_bytes() > limit
+            && let Some(evict) = &self.eviction
+        {
+            evict(&usage);
+            usage = self.observe(MemoryUsage::measure(accounts, store));
+        }
+        (usage.total_bytes() > limit).then_some((usage, limit))
+    }
+
+    fn observe(&self, usage: MemoryUsage) -> MemoryUsage {
+        self.used.store(usage.total_bytes(), Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.get() {
+            metrics.observe_memory(usage.total_bytes());
+        }
+        usage
+    }
+}
+
+impl std::fmt::Debug for MemoryBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryBudget")
+            .field("limit", &self.limit)
+            .field("check_interval", &self.check_interval)
+            .field("used", &self.used_bytes())
+            .field("eviction", &self.eviction.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, operations};
+    use crate::storage::{
+        ClientAccountEntry, ConcurrentAccountManager, ConcurrentTransactionStore,
+    };
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+
+    fn fill(accounts: &ConcurrentAccountManager<FixedPoint>, clients: ClientId) {
+        for client_id in 1..=clients {
+            accounts
+                .entry(client_id)
+                .unwrap()
+                .try_update(|acc| operations::apply_deposit(acc, FixedPoint::from_raw(1)))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn usage_scales_with_entry_counts() {
+        let accounts = ConcurrentAccountManager::new();
+        let store = ConcurrentTransactionStore::new();
+        fill(&accounts, 3);
+        for tx_id in 1..=5 {
+            store.insert(tx_id, TransactionRecord::new(1, FixedPoint::from_raw(1)));
+        }
+
+        let usage = MemoryUsage::measure(&accounts, &store);
+
+        assert_eq!((usage.accounts, usage.records), (3, 5));
+        assert_eq!(usage.account_bytes, 3 * account_entry_bytes::<FixedPoint>());
+        assert_eq!(
+            usage.total_bytes(),
+            usage.account_bytes + 5 * record_entry_bytes::<FixedPoint>()
+        );
+    }
+
+    #[test]
+    fn checks_only_on_the_interval_and_reports_when_over() {
+        let accounts = ConcurrentAccountManager::new();
+        let store = ConcurrentTransactionStore::<FixedPoint>::new();
+        fill(&accounts, 10);
+        let budget = MemoryBudget::new(1).with_check_interval(3);
+
+        assert!(budget.record_processed(&accounts, &store).is_none());
+        assert!(budget.record_processed(&accounts, &store).is_none());
+        assert_eq!(budget.used_bytes(), 0);
+
+        let (usage, limit) = budget.record_processed(&accounts, &store).unwrap();
+        assert_eq!(limit, 1);
+        assert_eq!(usage.accounts, 10);
+        assert_eq!(budget.used_bytes(), usage.total_bytes());
+
+        // Tracking only: never exceeded
+        let unlimited = MemoryBudget::unlimited().with_check_interval(1);
+        assert!(unlimited.record_processed(&accounts, &store).is_none());
+        assert_eq!(unlimited.used_bytes(), usage.total_bytes());
+    }
+
+    #[test]
+    fn eviction_runs_only_when_over_the_limit() {
+        let accounts = ConcurrentAccountManager::new();
+        let store = ConcurrentTransactionStore::<FixedPoint>::new();
+        fill(&accounts, 2);
+        let evictions = Arc::new(AtomicUsize::new(0));
+        let budget = |limit| {
+            let evictions = evictions.clone();
+            MemoryBudget::new(limit).with_eviction(move |usage| {
+                assert_eq!(usage.accounts, 2);
+                evictions.fetch_add(1, Ordering::Relaxed);
+            })
+        };
+
+        assert!(budget(u64::MAX).check(&accounts, &store).is_none());
+        assert_eq!(evictions.load(Ordering::Relaxed), 0);
+
+        // Nothing was freed, so the breach is still reported
+        assert!(budget(1).check(&accounts, &store).is_some());
+        assert_eq!(evictions.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,189 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+
+use crate::domain::{AmountType, TimestampedTransaction};
+use crate::io::IoError;
+
+type Item<A> = Result<TimestampedTransaction<A>, IoError>;
+
+/// K-way merge of timestamped streams in global timestamp order
+///
+/// Each input stream is assumed to be sorted by timestamp. The merge buffers
+/// one head item per stream and always emits the head with the smallest
+/// timestamp (ties broken by stream index). Items that cannot be ordered
+/// (errors and transactions without a timestamp) are emitted as soon as they
+/// reach the head of their stream, preserving their position within it.
+pub(crate) struct TimestampMerge<A: AmountType, S> {
+    slots: Vec<Slot<A, S>>,
+}
+
+struct Slot<A: AmountType, S> {
+    stream: S,
+    head: Option<Item<A>>,
+    done: bool,
+}
+
+impl<A: AmountType, S> TimestampMerge<A, S>
+where
+    S: Stream<Item = Item<A>> + Unpin,
+{
+    /// Create a merge over the given streams
+    pub(crate) fn new(streams: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            slots: streams
+                .into_iter()
+                .map(|stream| Slot {
+                    stream,
+                    head: None,
+                    done: false,
+                })
+                .collect(),
+        }
+    }
+}
+
+// Buffered heads are never pinned, so the merge is Unpin whenever its streams are
+impl<A: AmountType, S: Unpin> Unpin for TimestampMerge<A, S> {}
+
+/// Ordering key of a buffered head (None = cannot be ordered, emit immediately)
+fn timestamp_of<A: AmountType>(item: &Item<A>) -> Option<u64> {
+    match item {
+        Ok(tx) => tx.timestamp,
+        Err(_) => None,
+    }
+}
+
+impl<A: AmountType, S> Stream for TimestampMerge<A, S>
+where
+    S: Stream<Item = Item<A>> + Unpin,
+{
+    type Item = Item<A>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut pending = false;
+
+        // Fill every empty head slot
+        for slot in this.slots.iter_mut() {
+            if slot.head.is_none() && !slot.done {
+                match slot.stream.poll_next_unpin(cx) {
+                    Poll::Ready(Some(item)) => slot.head = Some(item),
+                    Poll::Ready(None) => slot.done = true,
+                    Poll::Pending => pending = true,
+                }
+            }
+        }
+
+        // Unorderable heads never need to wait for other streams
+        if let Some(slot) = this.slots.iter_mut().find(|slot| {
+            slot.head
+                .as_ref()
+                .is_some_and(|h| timestamp_of(h).is_none())
+        }) {
+            return Poll::Ready(slot.head.take());
+        }
+
+        // A timestamped head can only be emitted once every live stream has a head
+        if pending {
+            return Poll::Pending;
+        }
+
+        let earliest = this
+            .slots
+            .iter_mut()
+            .filter(|slot| slot.head.is_some())
+            .min_by_key(|slot| slot.head.as_ref().and_then(timestamp_of));
+
+        match earliest {
+            Some(slot) => Poll::Ready(slot.head.take()),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, Transaction, TransactionId};
+    use futures::stream;
+
+    fn deposit(tx_id: TransactionId, timestamp: Option<u64>) -> Item<FixedPoint> {
+        Ok(TimestampedTransaction::new(
+            Transaction::Deposit {
+                client_id: 1,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            },
+            timestamp,
+        ))
+    }
+
+    fn tx_ids(items: Vec<Item<FixedPoint>>) -> Vec<TransactionId> {
+        items
+            .into_iter()
+            .map(|item| item.unwrap().transaction.tx_id().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn merges_in_timestamp_order() {
+        let a = stream::iter(vec![deposit(1, Some(10)), deposit(3, Some(30))]);
+        let b = stream::iter(vec![deposit(2, Some(20)), deposit(4, Some(40))]);
+
+        let merged: Vec<_> = TimestampMerge::new(vec![a, b]).collect().await;
+
+        assert_eq!(tx_ids(merged), vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn ties_are_broken_by_stream_index() {
+        let a = stream::iter(vec![deposit(1, Some(10))]);
+        let b = stream::iter(vec![deposit(2, Some(10))]);
+
+        let merged: Vec<_> = TimestampMerge::new(vec![b, a]).collect().await;
+
+        assert_eq!(tx_ids(merged), vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn untimestamped_items_keep_stream_position() {
+        let a = stream::iter(vec![deposit(1, Some(50)), deposit(2, None)]);
+        let b = stream::iter(vec![deposit(3, Some(10)), deposit(4, Some(60))]);
+
+        let merged: Vec<_> = TimestampMerge::new(vec![a, b]).collect().await;
+
+        assert_eq!(tx_ids(merged), vec![3, 1, 2, 4]);
+    }
+
+    #[tokio::test]
+    async fn errors_are_emitted_immediately() {
+        let a = stream::iter(vec![
+            Err(IoError::InvalidTransactionType("bad".to_string())),
+            deposit(2, Some(20)),
+        ]);
+        let b = stream::iter(vec![deposit(1, Some(10))]);
+
+        let mut merged = TimestampMerge::new(vec![a, b]);
+
+        assert!(merged.next().await.unwrap().is_err());
+        assert_eq!(
+            merged.next().await.unwrap().unwrap().transaction.tx_id(),
+            Some(1)
+        );
+        assert_eq!(
+            merged.next().await.unwrap().unwrap().transaction.tx_id(),
+            Some(2)
+        );
+        assert!(merged.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn empty_input_finishes() {
+        let streams: Vec<stream::Iter<std::vec::IntoIter<Item<FixedPoint>>>> = vec![];
+        let merged: Vec<_> = TimestampMerge::new(streams).collect().await;
+        assert!(merged.is_empty());
+    }
+}
@@ -3,14 +3,32 @@
 //! This module provides the `StreamProcessor` API for processing transaction streams
 //! with flexible topology configuration:
 //!
-//! - **Stream Combining**: Chain (sequential) vs Merge (concurrent)
+//! - **Stream Combining**: Chain (sequential), Merge (concurrent), or MergeByTimestamp (time-ordered)
 //! - **Parallel Sharding**: Distribute streams across multiple processor shards
 //! - **Shard Assignment**: RoundRobin, Sequential, or Custom strategies
-//! - **Error Policies**: SkipErrors, AbortOnError, or SilentSkip
+//! - **Execution Models**: Shared storage, or client-owning actor shards fed over channels
+//! - **Error Policies**: SkipErrors, AbortOnError, SilentSkip, or a custom (async) Callback
+//! - **Client Sequencing**: Apply each client's transactions in sequence-number order
+//! - **Transforms**: Filter or rewrite transactions before processing (`TransactionFilter`)
+//! - **Dead Letters**: Report every rejected record to a `DeadLetterSink`
+//! - **Error Log**: Return the run's IO and engine errors with `ProcessorResults::errors`
+//! - **Quarantine**: Keep transactions on locked accounts in a `QuarantineSink` for re-running
+//! - **Checkpoints**: Persist progress and storage, and resume after a crash
+//! - **Periodic Snapshots**: Write full or delta account snapshots while processing runs
+//! - **Shard Snapshots**: Write each shard's final accounts as soon as it finishes
+//! - **Stream Priorities**: Favour live feeds over bulk backfills within a shard
+//! - **Rate Limiting**: Throttle ingestion globally or per shard
+//! - **Retries**: Poll sources again with backoff after transient IO errors (`RetryPolicy`)
+//! - **Stall Detection**: Warn about, or cancel, shards whose sources stop yielding
+//! - **Runtime Streams**: Add streams to a running processor through a `StreamHandle`
+//! - **Memory Budgets**: Estimate storage memory and act when it outgrows a `MemoryBudget`
+//! - **Final Statistics**: `ProcessorResults::stats` summarises accounts after the run
 //!
 //! # Examples
 //!
 //! ## Single Stream
+//! `process_sequential` reads each stream to its end in turn on the calling
+//! task and applies every record directly, with no shards or combinator.
 //! ```rust,ignore
 //! use pay::prelude::*;
 //! use std::sync::Arc;
@@ -20,7 +38,7 @@
 //!
 //! StreamProcessor::new(mgr, store, SkipErrors)
 //!     .add_stream(csv_stream)
-//!     .process()
+//!     .process_sequential()
 //!     .await;
 //! ```
 //!
@@ -36,17 +54,54 @@
 //!     .await;
 //! ```
 
+pub mod checkpoint;
+pub mod dead_letter;
+mod dispatch;
 pub mod error;
+mod error_log;
+mod handle;
+mod memory;
+mod merge;
+mod periodic;
+mod priority;
 mod processor;
+mod rate_limit;
+mod retry;
+mod sequencer;
+mod shard_snapshot;
+mod stats;
+mod tracking;
+pub mod transform;
+mod watchdog;
 
 // Primary streaming API
 pub use processor::{
     StreamProcessor,
+    ExecutionModel,
     ShardAssignment,
     StreamCombinator,
     ProcessorResults,
     ShardResult,
 };
+pub use handle::StreamHandle;
+pub use memory::{MemoryBudget, MemoryUsage};
+pub use periodic::SnapshotMode;
+pub use priority::Priority;
+pub use retry::RetryPolicy;
+pub use stats::AccountStats;
+pub use tracking::StreamResult;
+
+// Pre-processing stages
+pub use transform::{TransactionFilter, Transform};
+
+// Crash recovery
+pub use checkpoint::Checkpoint;
+
+// Rejected-record reporting
+pub use dead_letter::{CsvDeadLetterWriter, DeadLetter, DeadLetterSink};
 
 // Error handling policies
-pub use error::{AbortOnError, ErrorPolicy, SilentSkip, SkipErrors};
+pub use error_log::{LoggedError, LoggedErrorKind};
+pub use error::{
+    AbortOnError, AsyncCallback, Callback, ErrorPolicy, ProcessingError, SilentSkip, SkipErrors,
+};
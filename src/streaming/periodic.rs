@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+use crate::domain::{AmountType, ClientAccount, ClientId};
+use crate::io::{IoError, SnapshotSink};
+use crate::storage::ClientAccountManager;
+
+/// Which accounts each periodic snapshot contains
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SnapshotMode {
+    /// Every account (default)
+    #[default]
+    Full,
+    /// Only the accounts that changed since the previous periodic snapshot
+    /// (every account in the first one)
+    ///
+    /// A copy of every account is kept between snapshots to compare against.
+    Delta,
+}
+
+/// Held (shared) by shards while a record is applied, and exclusively while
+/// a periodic snapshot copies storage
+pub(crate) type SnapshotGate = RwLock<()>;
+
+/// Snapshot writer running alongside the shards
+pub(crate) struct PeriodicSnapshots {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl PeriodicSnapshots {
+    /// Write a snapshot of `accounts` to `sink` every `interval`
+    pub(crate) fn spawn<A, M>(
+        interval: Duration,
+        mode: SnapshotMode,
+        mut sink: Box<dyn SnapshotSink<A>>,
+        gate: Arc<SnapshotGate>,
+        accounts: Arc<M>,
+    ) -> Self
+    where
+        A: AmountType + 'static,
+        M: ClientAccountManager<A> + 'static,
+    {
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut previous = HashMap::new();
+
+            loop {
+                tokio::select! {
+                    _ = &mut stopped => break,
+                    _ = ticker.tick() => {}
+                }
+                let snapshot = capture(&gate, &*accounts, mode, &mut previous);
+                match write(&mut *sink, &snapshot).await {
+                    Ok(()) => debug!(accounts = snapshot.len(), "Periodic snapshot written"),
+                    Err(e) => warn!("Failed to write periodic snapshot: {}", e),
+                }
+            }
+        });
+        Self { stop, task }
+    }
+
+    /// Stop taking snapshots, letting one that is being written finish
+    pub(crate) async fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// Copy the accounts a snapshot contains, ordered by client id
+fn capture<A, M>(
+    gate: &SnapshotGate,
+    accounts: &M,
+    mode: SnapshotMode,
+    previous: &mut HashMap<ClientId, ClientAccount<A>>,
+) -> Vec<ClientAccount<A>>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+{
+    let mut snapshot = Vec::new();
+    {
+        // No record is half-applied while the gate is held
+        let _paused = gate.write();
+        accounts.for_each_account(&mut |account| snapshot.push(account.clone()));
+    }
+
+    if mode == SnapshotMode::Delta {
+        snapshot.retain(|account| previous.get(&account.client_id()) != Some(account));
+        for account in &snapshot {
+            previous.insert(account.client_id(), account.clone());
+        }
+    }
+    snapshot.sort_by_key(ClientAccount::client_id);
+    snapshot
+}
+
+async fn write<A: AmountType>(
+    sink: &mut dyn SnapshotSink<A>,
+    accounts: &[ClientAccount<A>],
+) -> Result<(), IoError> {
+    sink.begin().await?;
+    for account in accounts {
+        sink.write_account(account).await?;
+    }
+    sink.finish().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, apply_deposit};
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager};
+
+    fn deposit(accounts: &ConcurrentAccountManager<FixedPoint>, client_id: ClientId, raw: i64) {
+        let mut entry = accounts.entry(client_id).unwrap();
+        entry
+            .try_update(|account| apply_deposit(account, FixedPoint::from_raw(raw)))
+            .unwrap();
+    }
+
+    fn clients(snapshot: &[ClientAccount<FixedPoint>]) -> Vec<ClientId> {
+        snapshot.iter().map(ClientAccount::client_id).collect()
+    }
+
+    #[test]
+    fn delta_mode_keeps_changed_accounts_only() {
+        let gate = SnapshotGate::default();
+        let accounts = ConcurrentAccountManager::<FixedPoint>::new();
+        let mut previous = HashMap::new();
+        deposit(&accounts, 2, 10_000);
+        deposit(&accounts, 1, 10_000);
+
+        let first = capture(&gate, &accounts, SnapshotMode::Delta, &mut previous);
+        assert_eq!(clients(&first), vec![1, 2]);
+
+        deposit(&accounts, 2, 5_000);
+        let second = capture(&gate, &accounts, SnapshotMode::Delta, &mut previous);
+        assert_eq!(clients(&second), vec![2]);
+        assert_eq!(second[0].available(), FixedPoint::from_raw(15_000));
+
+        assert!(capture(&gate, &accounts, SnapshotMode::Delta, &mut previous).is_empty());
+        let full = capture(&gate, &accounts, SnapshotMode::Full, &mut HashMap::new());
+        assert_eq!(clients(&full), vec![1, 2]);
+    }
+}
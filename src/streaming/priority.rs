@@ -0,0 +1,182 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+
+/// Scheduling priority of an input stream within its shard
+///
+/// Under `StreamCombinator::Merge`, a shard whose streams have different
+/// priorities polls them in a weighted round-robin (High 4 : Normal 2 : Low 1),
+/// so a live dispute feed keeps flowing while a bulk backfill file is being
+/// read. No stream is starved: every ready stream is polled at least once per
+/// round. Under `StreamCombinator::Chain` (and `process_sequential`) the
+/// shard's streams are drained highest priority first, in insertion order
+/// within a priority. `MergeByTimestamp` ignores priorities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Bulk sources such as backfill files
+    Low,
+    /// Default for `add_stream`
+    #[default]
+    Normal,
+    /// Latency-sensitive sources such as live dispute feeds
+    High,
+}
+
+impl Priority {
+    /// Polls per round under a weighted merge
+    pub(crate) fn weight(self) -> usize {
+        match self {
+            Priority::Low => 1,
+            Priority::Normal => 2,
+            Priority::High => 4,
+        }
+    }
+}
+
+impl std::str::FromStr for Priority {
+    type Err = String;
+
+    /// Parse `low`, `normal` or `high`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "low" => Ok(Priority::Low),
+            "normal" => Ok(Priority::Normal),
+            "high" => Ok(Priority::High),
+            _ => Err(format!(
+                "unknown priority '{name}' (expected low, normal or high)"
+            )),
+        }
+    }
+}
+
+/// Concurrent merge that polls streams in a smooth weighted round-robin
+///
+/// Each round visits stream `i` `weight(i)` times, spread out rather than in
+/// bursts (e.g. High, Normal, High, Low, High, Normal, High for one stream of
+/// each). A visit to a stream that is not ready moves on to the next slot, so
+/// pending or finished streams never hold up the others.
+pub(crate) struct PriorityMerge<S> {
+    streams: Vec<Option<S>>,
+    /// Stream index for each slot of a round
+    schedule: Vec<usize>,
+    /// Next slot to visit
+    cursor: usize,
+}
+
+impl<S: Stream + Unpin> PriorityMerge<S> {
+    /// Create a merge over `(priority, stream)` pairs
+    pub(crate) fn new(streams: impl IntoIterator<Item = (Priority, S)>) -> Self {
+        let (priorities, streams): (Vec<_>, Vec<_>) = streams
+            .into_iter()
+            .map(|(priority, stream)| (priority, Some(stream)))
+            .unzip();
+        Self {
+            streams,
+            schedule: schedule(&priorities),
+            cursor: 0,
+        }
+    }
+}
+
+/// One round of the smooth weighted round-robin over `priorities`
+fn schedule(priorities: &[Priority]) -> Vec<usize> {
+    let weights: Vec<_> = priorities.iter().map(|p| p.weight() as i64).collect();
+    let total: i64 = weights.iter().sum();
+    let mut current = vec![0i64; weights.len()];
+    (0..total)
+        .map(|_| {
+            for (current, weight) in current.iter_mut().zip(&weights) {
+                *current += weight;
+            }
+            // Ties go to the earliest stream
+            let (next, _) = current
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, current)| **current)
+                .expect("at least one stream");
+            current[next] -= total;
+            next
+        })
+        .collect()
+}
+
+impl<S: Stream + Unpin> Stream for PriorityMerge<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let slots = this.schedule.len();
+
+        for _ in 0..slots {
+            let slot = this.cursor;
+            this.cursor = (this.cursor + 1) % slots;
+            let index = this.schedule[slot];
+            let Some(stream) = this.streams[index].as_mut() else {
+                continue;
+            };
+            match stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(item)) => return Poll::Ready(Some(item)),
+                Poll::Ready(None) => this.streams[index] = None,
+                Poll::Pending => {}
+            }
+        }
+
+        match this.streams.iter().all(Option::is_none) {
+            true => Poll::Ready(None),
+            false => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[test]
+    fn schedule_spreads_weights() {
+        let round = schedule(&[Priority::High, Priority::Normal, Priority::Low]);
+        assert_eq!(round, vec![0, 1, 0, 2, 0, 1, 0]);
+        assert_eq!(schedule(&[Priority::Normal; 2]), vec![0, 1, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn high_priority_stream_is_polled_more_often() {
+        let live = stream::iter(vec!["live"; 8]);
+        let bulk = stream::iter(vec!["bulk"; 8]);
+
+        let merged: Vec<_> = PriorityMerge::new([(Priority::Low, bulk), (Priority::High, live)])
+            .collect()
+            .await;
+
+        // High 4 : Low 1 until the live stream is drained
+        assert_eq!(merged.len(), 16);
+        assert_eq!(merged[..5].iter().filter(|s| **s == "live").count(), 4);
+        assert_eq!(merged[..10].iter().filter(|s| **s == "live").count(), 8);
+    }
+
+    #[tokio::test]
+    async fn finished_streams_do_not_stall_the_merge() {
+        let short = stream::iter(vec![1]);
+        let long = stream::iter(vec![2, 3, 4]);
+        let empty = stream::iter(Vec::<i32>::new());
+
+        let merged: Vec<_> = PriorityMerge::new([
+            (Priority::High, short),
+            (Priority::Low, long),
+            (Priority::Normal, empty),
+        ])
+        .collect()
+        .await;
+
+        assert_eq!(merged, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn parses_priority_names() {
+        assert_eq!("high".parse::<Priority>(), Ok(Priority::High));
+        assert!("urgent".parse::<Priority>().is_err());
+    }
+}
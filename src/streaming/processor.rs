@@ -1,20 +1,51 @@
+use std::cmp::Reverse;
 use std::marker::PhantomData;
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+#[cfg(feature = "metrics")]
+use std::time::Instant;
 
 use futures::{Stream, StreamExt};
 use futures::stream;
+use tokio::sync::mpsc;
+use tracing::warn;
 
-#[cfg(test)]
-use std::sync::Arc;
-
-use super::error::ErrorPolicy;
-use crate::domain::{AmountType, Transaction};
-use crate::engine::TransactionProcessor;
-use crate::io::IoError;
-use crate::storage::{ClientAccountManager, TransactionStoreManager};
+use super::checkpoint::{Checkpoint, Checkpointer, ShardCheckpoint};
+use super::dead_letter::{DeadLetter, DeadLetterSink};
+use super::dispatch::dispatch_by_client;
+use super::error::{ErrorPolicy, ProcessingError};
+use super::error_log::{ErrorLog, LoggedError, LoggedErrorKind};
+use super::handle::{Incoming, NewStream, StreamHandle};
+use super::memory::MemoryBudget;
+use super::merge::TimestampMerge;
+use super::periodic::{PeriodicSnapshots, SnapshotGate, SnapshotMode};
+use super::priority::{Priority, PriorityMerge};
+use super::rate_limit::{RateLimiter, throttle};
+use super::retry::{RetryPolicy, retry_transient};
+use super::sequencer::ClientSequencer;
+use super::shard_snapshot::{ShardSnapshots, shard_of};
+use super::stats::AccountStats;
+use super::tracking::{StreamRegistry, StreamResult, track};
+use super::transform::Transform;
+use super::watchdog::watchdog;
+use crate::domain::{AmountType, DisputePolicy, FeeSchedule, TimestampedTransaction, Transaction};
+use crate::engine::{
+    AuditSink, EngineError, IdempotencyKeys, OrderVerifier, QuarantineSink, TransactionProcessor,
+    TransactionTypeCounts, TransactionValidator,
+};
+use crate::io::{CsvTransactionStream, IoError, ReadPosition, SnapshotSink};
+#[cfg(feature = "metrics")]
+use crate::metrics::{IO_ERROR_KIND, MetricsRegistry};
+use crate::storage::{ClientAccountManager, StorageError, TransactionStoreManager};
 
 /// Type alias for a boxed transaction stream
-type TransactionStream<A> = Pin<Box<dyn Stream<Item = Result<Transaction<A>, IoError>> + Send>>;
+///
+/// Plain streams are stored with no timestamp so all combinators share one item type.
+pub(crate) type TransactionStream<A> =
+    Pin<Box<dyn Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send>>;
 
 /// Primary API for processing transaction streams
 ///
@@ -23,17 +54,40 @@ type TransactionStream<A> = Pin<Box<dyn Stream<Item = Result<Transaction<A>, IoE
 pub struct StreamProcessor<A, M, T, P>
 where
     A: AmountType,
-    M: ClientAccountManager<A> + Clone + Send + Sync + 'static,
-    T: TransactionStoreManager<A> + Clone + Send + Sync + 'static,
+    M: ClientAccountManager<A> + Send + Sync + 'static,
+    T: TransactionStoreManager<A> + Send + Sync + 'static,
     P: ErrorPolicy + Clone + Send + 'static,
 {
-    account_manager: M,
-    transaction_store: T,
+    account_manager: Arc<M>,
+    transaction_store: Arc<T>,
     error_policy: P,
     num_shards: usize,
     streams: Vec<TransactionStream<A>>,
-    shard_assignment: ShardAssignment,
+    stream_names: Vec<String>,
+    stream_priorities: Vec<Priority>,
+    stream_positions: Vec<Option<ReadPosition>>,
+    shard_assignment: Arc<ShardAssignment>,
+    execution_model: ExecutionModel,
     stream_combinator: StreamCombinator,
+    engine: EngineOptions<A>,
+    dead_letter_sink: Option<Arc<dyn DeadLetterSink<A>>>,
+    error_log: Option<usize>,
+    sequencing: Option<usize>,
+    buffer_size: Option<usize>,
+    shard_concurrency: usize,
+    transforms: Vec<Arc<Transform<A>>>,
+    checkpoints: Option<(PathBuf, u64)>,
+    rate_limit: Option<RateLimit>,
+    retry_policy: Option<RetryPolicy>,
+    stall_timeout: Option<Duration>,
+    cancel_on_stall: bool,
+    resume: Option<Checkpoint<A>>,
+    memory_budget: Option<Arc<MemoryBudget>>,
+    periodic_snapshot: Option<(Duration, Box<dyn SnapshotSink<A>>)>,
+    snapshot_mode: SnapshotMode,
+    shard_snapshot: Option<Box<dyn SnapshotSink<A>>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<MetricsRegistry>,
     _phantom: PhantomData<A>,
 }
 
@@ -51,6 +105,63 @@ pub enum ShardAssignment {
     Custom(Box<dyn Fn(usize) -> usize + Send + Sync>),
 }
 
+impl ShardAssignment {
+    /// Shard for stream `stream_idx` of `total_streams`
+    ///
+    /// Streams added while processing runs have no known total, so
+    /// `Sequential` falls back to round-robin for them.
+    pub(crate) fn shard_for(
+        &self,
+        stream_idx: usize,
+        total_streams: Option<usize>,
+        num_shards: usize,
+    ) -> usize {
+        match (self, total_streams) {
+            (ShardAssignment::Sequential, Some(total_streams)) => {
+                let chunk_size = total_streams.div_ceil(num_shards);
+                (stream_idx / chunk_size).min(num_shards - 1)
+            }
+            (ShardAssignment::RoundRobin | ShardAssignment::Sequential, _) => {
+                stream_idx % num_shards
+            }
+            (ShardAssignment::Custom(f), _) => f(stream_idx) % num_shards,
+        }
+    }
+}
+
+/// Throttling requested via `with_rate_limit` / `with_shard_rate_limit`
+#[derive(Debug, Clone, Copy)]
+enum RateLimit {
+    Global(u32),
+    PerShard(u32),
+}
+
+/// How shards divide the work
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionModel {
+    /// Each shard reads its own streams and applies records to the shared,
+    /// lock-protected storage (default)
+    #[default]
+    SharedStorage,
+
+    /// One reader combines every stream and sends each record over a channel
+    /// to the shard owning its client (`client_id % shards`)
+    ///
+    /// Each client is only ever touched by one shard, so storage locks are
+    /// never contended (only transfers reach into another shard's client)
+    /// and every client's records are applied in input order across all
+    /// streams. `ShardAssignment` is ignored; a shard that aborts stops the
+    /// reader, and the other shards finish what they were sent.
+    ActorSharded,
+}
+
+/// Channel capacity per shard under `ExecutionModel::ActorSharded`, unless
+/// `with_buffer_size` sets one
+const ACTOR_CHANNEL_CAPACITY: usize = 1024;
+
+/// Channel capacity per lane under `with_shard_concurrency`
+const LANE_CHANNEL_CAPACITY: usize = 1024;
+
 /// How to combine multiple streams within a single shard
 #[derive(Debug, Clone, Copy)]
 pub enum StreamCombinator {
@@ -61,20 +172,46 @@ pub enum StreamCombinator {
     /// Chain streams sequentially (one after another)
     /// Good for: Order-dependent streams within a shard
     Chain,
+
+    /// K-way merge ordered by transaction timestamp
+    /// Good for: Multiple time-sorted sources that need global time ordering
+    /// (add them with `add_timestamped_stream`; untimestamped items are not reordered)
+    MergeByTimestamp,
+}
+
+impl std::str::FromStr for StreamCombinator {
+    type Err = String;
+
+    /// Parse `merge`, `chain` or `timestamp`, the names the CLI and config
+    /// files use; `by-timestamp` is accepted as an alias of `timestamp`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "merge" => Ok(StreamCombinator::Merge),
+            "chain" => Ok(StreamCombinator::Chain),
+            "timestamp" | "by-timestamp" => Ok(StreamCombinator::MergeByTimestamp),
+            _ => Err(format!(
+                "unknown combinator '{name}' (expected merge, chain or timestamp)"
+            )),
+        }
+    }
 }
 
 impl<A, M, T, P> StreamProcessor<A, M, T, P>
 where
     A: AmountType + 'static,
-    M: ClientAccountManager<A> + Clone + Send + Sync + 'static,
-    T: TransactionStoreManager<A> + Clone + Send + Sync + 'static,
+    M: ClientAccountManager<A> + Send + Sync + 'static,
+    T: TransactionStoreManager<A> + Send + Sync + 'static,
     P: ErrorPolicy + Clone + Send + 'static,
 {
     /// Create a new stream processor with shared storage
     ///
+    /// Storage is moved behind an `Arc` and shared by every shard, so backends
+    /// only need `Send + Sync`. Pass an `Arc` yourself to keep a handle for
+    /// reading results after processing.
+    ///
     /// # Arguments
-    /// * `account_manager` - Shared account manager (typically Arc<ConcurrentAccountManager>)
-    /// * `transaction_store` - Shared transaction store (typically Arc<ConcurrentTransactionStore>)
+    /// * `account_manager` - Account manager (typically Arc<ConcurrentAccountManager>)
+    /// * `transaction_store` - Transaction store (typically Arc<ConcurrentTransactionStore>)
     /// * `error_policy` - Error handling policy
     ///
     /// # Example
@@ -90,13 +227,36 @@ where
         error_policy: P,
     ) -> Self {
         Self {
-            account_manager,
-            transaction_store,
+            account_manager: Arc::new(account_manager),
+            transaction_store: Arc::new(transaction_store),
             error_policy,
             num_shards: 1,
             streams: Vec::new(),
-            shard_assignment: ShardAssignment::RoundRobin,
+            stream_names: Vec::new(),
+            stream_priorities: Vec::new(),
+            stream_positions: Vec::new(),
+            shard_assignment: Arc::new(ShardAssignment::RoundRobin),
+            execution_model: ExecutionModel::default(),
             stream_combinator: StreamCombinator::Merge,
+            engine: EngineOptions::default(),
+            dead_letter_sink: None,
+            error_log: None,
+            sequencing: None,
+            buffer_size: None,
+            shard_concurrency: 1,
+            transforms: Vec::new(),
+            checkpoints: None,
+            rate_limit: None,
+            retry_policy: None,
+            stall_timeout: None,
+            cancel_on_stall: false,
+            resume: None,
+            memory_budget: None,
+            periodic_snapshot: None,
+            snapshot_mode: SnapshotMode::default(),
+            shard_snapshot: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
             _phantom: PhantomData,
         }
     }
@@ -138,7 +298,21 @@ where
     /// ))
     /// ```
     pub fn with_shard_assignment(mut self, assignment: ShardAssignment) -> Self {
-        self.shard_assignment = assignment;
+        self.shard_assignment = Arc::new(assignment);
+        self
+    }
+
+    /// Select how shards divide the work (defaults to `ExecutionModel::SharedStorage`)
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // 8 client-owning workers fed by one reader
+    /// processor
+    ///     .with_shards(8)
+    ///     .with_execution_model(ExecutionModel::ActorSharded)
+    /// ```
+    pub fn with_execution_model(mut self, model: ExecutionModel) -> Self {
+        self.execution_model = model;
         self
     }
 
@@ -151,232 +325,3317 @@ where
     ///
     /// // Chain: Streams in same shard processed sequentially
     /// processor.with_stream_combinator(StreamCombinator::Chain)
+    ///
+    /// // MergeByTimestamp: Streams in same shard merged in timestamp order
+    /// processor.with_stream_combinator(StreamCombinator::MergeByTimestamp)
     /// ```
     pub fn with_stream_combinator(mut self, combinator: StreamCombinator) -> Self {
         self.stream_combinator = combinator;
         self
     }
 
-    /// Add a stream to process (fluent interface)
+    /// Apply each client's sequenced transactions in sequence-number order
     ///
-    /// Stream will be assigned to a shard based on the shard assignment strategy.
-    /// If multiple streams are assigned to the same shard, they will be combined
-    /// according to the stream combining strategy.
+    /// Transactions with a sequence number (the `seq` CSV column, starting at 1
+    /// per client) are buffered per client until their predecessors arrive,
+    /// holding at most `max_pending` per client before the missing numbers are
+    /// reported as `IoError::SequenceGap` and skipped. Sequencing happens
+    /// within each shard, so all streams touching a client must be assigned to
+    /// the same shard.
     ///
     /// # Example
     /// ```rust,ignore
-    /// StreamProcessor::new(mgr, store, SilentSkip)
-    ///     .add_stream(csv_stream_1)
-    ///     .add_stream(csv_stream_2)
-    ///     .add_stream(csv_stream_3)
-    ///     .process()
-    ///     .await;
+    /// // One client's history split across two files
+    /// processor
+    ///     .with_client_sequencing(1024)
+    ///     .add_timestamped_stream(part_1.timestamped())
+    ///     .add_timestamped_stream(part_2.timestamped())
     /// ```
-    pub fn add_stream<S>(mut self, stream: S) -> Self
-    where
-        S: Stream<Item = Result<Transaction<A>, IoError>> + Send + 'static,
-    {
-        self.streams.push(Box::pin(stream));
+    pub fn with_client_sequencing(mut self, max_pending: usize) -> Self {
+        self.sequencing = Some(max_pending);
         self
     }
 
-    /// Process all streams across parallel shards
+    /// Parse and apply transactions on separate tasks, with up to `n` parsed
+    /// records buffered between them (minimum 1)
     ///
-    /// 1. Assigns streams to shards based on shard assignment strategy
-    /// 2. Combines streams within each shard based on stream combining strategy
-    /// 3. Spawns one task per shard
-    /// 4. Each task processes its combined stream
+    /// By default each shard reads and applies records in one loop, so I/O
+    /// and account updates never overlap. With a buffer, a reader task per
+    /// shard parses ahead while the shard applies records; once the buffer is
+    /// full the reader waits, so memory stays bounded.
     ///
-    /// # Returns
-    /// ProcessorResults containing per-shard results and overall success status
+    /// # Example
+    /// ```rust,ignore
+    /// processor.with_buffer_size(1024)
+    /// ```
+    pub fn with_buffer_size(mut self, n: usize) -> Self {
+        self.buffer_size = Some(n.max(1));
+        self
+    }
+
+    /// Apply up to `k` records of different clients concurrently within each
+    /// shard (defaults to 1; minimum 1)
+    ///
+    /// Each shard routes its combined records to `k` lanes by client
+    /// (`client_id % k`), and every lane applies its records on its own task,
+    /// so one client's records stay in order while unrelated clients proceed
+    /// in parallel. Transfers touch two clients and are only ordered against
+    /// the sender's other records. A lane that aborts stops the shard reading;
+    /// the other lanes finish what they were sent. Ignored by
+    /// `process_sequential`.
     ///
     /// # Example
     /// ```rust,ignore
-    /// let results = StreamProcessor::new(mgr, store, SilentSkip)
-    ///     .with_shards(4)
-    ///     .add_stream(stream1)
-    ///     .add_stream(stream2)
+    /// // One large file touching many clients
+    /// processor.with_shard_concurrency(8).add_stream(csv_stream)
+    /// ```
+    pub fn with_shard_concurrency(mut self, k: usize) -> Self {
+        self.shard_concurrency = k.max(1);
+        self
+    }
+
+    /// Apply at most `tx_per_sec` records per second across all shards (minimum 1)
+    ///
+    /// Every record a shard takes from its streams, including unreadable ones,
+    /// uses up a token from one bucket shared by all shards. The bucket holds
+    /// one second's worth of tokens, so short bursts pass unthrottled. Use it
+    /// to protect remote storage or neighbouring latency-sensitive workloads.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// processor.with_shards(8).with_rate_limit(5_000)
+    /// ```
+    pub fn with_rate_limit(mut self, tx_per_sec: u32) -> Self {
+        self.rate_limit = Some(RateLimit::Global(tx_per_sec));
+        self
+    }
+
+    /// Apply at most `tx_per_sec` records per second in each shard (minimum 1)
+    ///
+    /// Like `with_rate_limit`, but every shard gets its own bucket, so the
+    /// overall rate grows with the number of shards.
+    pub fn with_shard_rate_limit(mut self, tx_per_sec: u32) -> Self {
+        self.rate_limit = Some(RateLimit::PerShard(tx_per_sec));
+        self
+    }
+
+    /// Retry transient source errors before they reach the error policy
+    ///
+    /// Each input stream, including those added at runtime, is polled again
+    /// with backoff after a transient error (a network hiccup reading from
+    /// TCP or object storage, see `IoError::is_transient`). Permanent errors,
+    /// such as unparsable records, and failures outlasting the policy's
+    /// retries go to the error policy as usual.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// processor.with_retry_policy(
+    ///     RetryPolicy::new(5).with_backoff(Duration::from_millis(200), Duration::from_secs(10)),
+    /// )
+    /// ```
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Warn when a shard takes no record from its streams for `timeout`
+    ///
+    /// Network-backed sources can hang without failing; the watchdog logs a
+    /// warning naming the shard once per stall, and carries on waiting unless
+    /// `with_cancel_on_stall` is set. Time spent waiting for streams added
+    /// through a `StreamHandle` counts as a stall too.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// processor
+    ///     .with_stall_timeout(Duration::from_secs(30))
+    ///     .with_cancel_on_stall(true)
+    /// ```
+    pub fn with_stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+
+    /// Stop a shard that stalls instead of only warning (defaults to false)
+    ///
+    /// The shard finishes with what it applied before the stall and reports
+    /// `ShardResult::stalled`; other shards keep running. Has no effect
+    /// without `with_stall_timeout`.
+    pub fn with_cancel_on_stall(mut self, enabled: bool) -> Self {
+        self.cancel_on_stall = enabled;
+        self
+    }
+
+    /// Accept administrative operations from all streams (defaults to false)
+    ///
+    /// Admin operations (e.g. `Transaction::Unlock`) are rejected with
+    /// `EngineError::AdminOperationNotAllowed` unless enabled. Process trusted
+    /// back-office files with a dedicated processor that enables this, rather
+    /// than mixing them with partner feeds.
+    pub fn with_admin_ops(mut self, enabled: bool) -> Self {
+        self.engine.allow_admin_ops = enabled;
+        self
+    }
+
+    /// Skip transactions on locked accounts instead of treating them as errors
+    ///
+    /// Expected traffic for accounts frozen by a chargeback then no longer
+    /// reaches the error policy (so `AbortOnError` keeps running) or the
+    /// dead-letter sink; each shard reports how many it skipped in
+    /// `ShardResult::locked_skipped`. Defaults to false.
+    pub fn with_skip_locked(mut self, enabled: bool) -> Self {
+        self.engine.skip_locked = enabled;
+        self
+    }
+
+    /// Divert transactions on locked accounts, from all shards, to a quarantine sink
+    ///
+    /// Like `with_skip_locked` (which this overrides), such transactions no
+    /// longer reach the error policy or the dead-letter sink, but they are
+    /// kept with their reason so they can be re-run after an unlock. Each
+    /// shard reports how many it diverted in `ShardResult::quarantined`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let quarantine = Arc::new(CsvDeadLetterWriter::new(File::create("quarantine.csv")?)?);
+    ///
+    /// StreamProcessor::new(mgr, store, SkipErrors)
+    ///     .with_quarantine_sink(quarantine.clone())
+    ///     .add_stream(csv_stream)
     ///     .process()
     ///     .await;
     ///
-    /// if results.all_succeeded() {
-    ///     println!("All shards processed successfully");
-    /// }
+    /// quarantine.flush()?;
+    /// // Once the accounts are unlocked, process quarantine.csv as a normal input
     /// ```
-    pub async fn process(self) -> ProcessorResults {
-        let num_streams = self.streams.len();
-
-        if num_streams == 0 {
-            return ProcessorResults {
-                shard_results: vec![],
-                total_streams: 0,
-            };
-        }
+    pub fn with_quarantine_sink(mut self, sink: Arc<dyn QuarantineSink<A>>) -> Self {
+        self.engine.quarantine_sink = Some(sink);
+        self
+    }
 
-        // Destructure self to get ownership of all fields
-        let StreamProcessor {
-            account_manager,
-            transaction_store,
-            error_policy,
-            num_shards,
-            streams,
-            shard_assignment,
-            stream_combinator,
-            _phantom,
-        } = self;
+    /// Recognise replays against a shared set of idempotency keys
+    ///
+    /// Records carrying an `idempotency_key` (e.g. the CSV column of that
+    /// name, read through `add_timestamped_stream`) that their client has
+    /// already used for an applied transaction are skipped as successful
+    /// no-ops and counted in `ShardResult::idempotent_replays`. Every run
+    /// starts with an empty set unless one is shared here, e.g. to recognise
+    /// a partner re-uploading yesterday's file.
+    pub fn with_idempotency_keys(mut self, keys: Arc<IdempotencyKeys>) -> Self {
+        self.engine.idempotency_keys = keys;
+        self
+    }
 
-        // Assign streams to shards
-        let mut shards: Vec<Vec<_>> = (0..num_shards).map(|_| Vec::new()).collect();
-        let total_streams = streams.len();
+    /// Record every shard's per-client processing order in `verifier`
+    ///
+    /// After processing, `verifier.ordering_report()` lists disputes applied
+    /// before their deposit and similar cases, showing whether the chosen
+    /// combinator and shard assignment keep each client's records in order
+    /// for this data. See `OrderVerifier`.
+    pub fn with_order_verification(mut self, verifier: Arc<OrderVerifier>) -> Self {
+        self.engine.order_verifier = Some(verifier);
+        self
+    }
 
-        for (stream_idx, stream) in streams.into_iter().enumerate() {
-            let shard_idx = match &shard_assignment {
-                ShardAssignment::RoundRobin => stream_idx % num_shards,
-                ShardAssignment::Sequential => {
-                    let chunk_size = total_streams.div_ceil(num_shards);
-                    (stream_idx / chunk_size).min(num_shards - 1)
-                }
-                ShardAssignment::Custom(f) => f(stream_idx) % num_shards,
-            };
+    /// Add a filter/map stage applied to every transaction before processing
+    ///
+    /// Stages run in the order they were added; a stage returning `None` drops
+    /// the transaction silently (it is not an error). See `TransactionFilter`
+    /// for by-type and by-client filtering.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // Drop all disputes
+    /// processor.with_transform(|tx| match tx {
+    ///     Transaction::Dispute { .. } => None,
+    ///     tx => Some(tx),
+    /// })
+    /// ```
+    pub fn with_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(Transaction<A>) -> Option<Transaction<A>> + Send + Sync + 'static,
+    {
+        self.transforms.push(Arc::new(transform));
+        self
+    }
 
-            shards[shard_idx].push(stream);
-        }
+    /// Report transaction, error and shard metrics to a registry
+    ///
+    /// Unreadable records are counted as `io` errors, and the account gauges
+    /// are refreshed once every shard has finished.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: MetricsRegistry) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
 
-        // Spawn one task per shard
-        let handles: Vec<_> = shards
-            .into_iter()
-            .enumerate()
-            .map(|(shard_id, shard_streams)| {
-                let mgr = account_manager.clone();
-                let store = transaction_store.clone();
-                let policy = error_policy.clone();
-                let combinator = stream_combinator;
-
-                tokio::spawn(async move {
-                    if shard_streams.is_empty() {
-                        return ShardResult {
-                            shard_id,
-                            streams_processed: 0,
-                            success: true,
-                        };
-                    }
+    /// Track approximate storage memory against a budget
+    ///
+    /// Storage is measured on the budget's check interval (across all shards)
+    /// and once more when processing finishes; see `MemoryBudget`. A shard
+    /// whose check finds the budget exceeded asks its error policy, and stops
+    /// if the policy declines to continue.
+    pub fn with_memory_budget(mut self, budget: Arc<MemoryBudget>) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
 
-                    let stream_count = shard_streams.len();
+    /// Periodically write a `Checkpoint` to `path`
+    ///
+    /// A checkpoint is taken every `interval` records (across all streams) and
+    /// once more when processing finishes. Each one briefly pauses every shard
+    /// while storage is copied. Checkpoints are only exact when each record goes
+    /// straight from its stream to a shard, so they are disabled (with a
+    /// warning) for `StreamCombinator::MergeByTimestamp`, client sequencing,
+    /// buffered pipelines (`with_buffer_size`) and shard concurrency
+    /// (`with_shard_concurrency`).
+    pub fn with_checkpoints(mut self, path: impl Into<PathBuf>, interval: u64) -> Self {
+        self.checkpoints = Some((path.into(), interval));
+        self
+    }
 
-                    // Combine streams within this shard
-                    let combined = match combinator {
-                        StreamCombinator::Merge => {
-                            // Merge streams concurrently
-                            Box::pin(stream::select_all(shard_streams))
-                                as Pin<Box<dyn Stream<Item = _> + Send>>
-                        }
-                        StreamCombinator::Chain => {
-                            // Chain streams sequentially
-                            Box::pin(stream::iter(shard_streams).flatten())
-                                as Pin<Box<dyn Stream<Item = _> + Send>>
-                        }
-                    };
+    /// Write a snapshot of the accounts to `sink` every `interval` while
+    /// processing runs
+    ///
+    /// Each snapshot briefly pauses every shard between records while storage
+    /// is copied, as checkpoints do, so it never shows a record half-applied;
+    /// the sink then writes on its own task while shards continue. Every
+    /// snapshot is framed by the sink's `begin` and `finish`. A failed write is
+    /// logged and the next snapshot is still attempted. No snapshot is taken
+    /// when processing finishes; write the final accounts from storage.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let sink = JsonSnapshotSink::new(File::create("progress.json").await?);
+    /// StreamProcessor::new(mgr, store, SkipErrors)
+    ///     .with_periodic_snapshot(Duration::from_secs(300), sink)
+    ///     .with_snapshot_mode(SnapshotMode::Delta)
+    ///     .add_stream(csv_stream)
+    ///     .process()
+    ///     .await;
+    /// ```
+    pub fn with_periodic_snapshot(
+        mut self,
+        interval: Duration,
+        sink: impl SnapshotSink<A> + 'static,
+    ) -> Self {
+        self.periodic_snapshot = Some((interval.max(Duration::from_millis(1)), Box::new(sink)));
+        self
+    }
 
-                    // Process the combined stream
-                    let processor = TransactionProcessor::new(mgr, store);
-                    let success = Self::process_shard_stream(combined, processor, policy).await;
+    /// Which accounts each periodic snapshot contains (defaults to `SnapshotMode::Full`)
+    pub fn with_snapshot_mode(mut self, mode: SnapshotMode) -> Self {
+        self.snapshot_mode = mode;
+        self
+    }
 
-                    ShardResult {
-                        shard_id,
-                        streams_processed: stream_count,
-                        success,
-                    }
-                })
-            })
-            .collect();
+    /// Write the final accounts to `sink` in parts, as shards finish
+    ///
+    /// Under `ExecutionModel::ActorSharded` each shard's clients are written
+    /// as soon as that shard finishes, so with a streaming sink such as
+    /// `NdjsonSnapshotSink` downstream consumers can start loading results
+    /// while slower shards still run. Each part is ordered by client id and
+    /// followed by the sink's `end_partition`; the snapshot is framed by
+    /// `begin` and `finish`. A transfer into a client whose shard has already
+    /// finished lands after that client was written, so use it with
+    /// transfers only if the later shards hold no such transfers. Under
+    /// shared storage any shard may touch any client, so the same parts (one
+    /// per shard, split by client id as actor shards split them) are written
+    /// together once all shards finish; a sequential run writes a single part.
+    /// A failed write is logged and the rest of the snapshot is skipped.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let sink = NdjsonSnapshotSink::new(File::create("accounts.ndjson").await?);
+    /// StreamProcessor::new(mgr, store, SkipErrors)
+    ///     .with_shards(8)
+    ///     .with_execution_model(ExecutionModel::ActorSharded)
+    ///     .with_shard_snapshot(sink)
+    ///     .add_stream(csv_stream)
+    ///     .process()
+    ///     .await;
+    /// ```
+    pub fn with_shard_snapshot(mut self, sink: impl SnapshotSink<A> + 'static) -> Self {
+        self.shard_snapshot = Some(Box::new(sink));
+        self
+    }
 
-        // Await all tasks
-        let mut shard_results = Vec::new();
-        for handle in handles {
-            shard_results.push(handle.await.unwrap_or(ShardResult {
-                shard_id: 0,
-                streams_processed: 0,
-                success: false,
-            }));
-        }
+    /// Continue a previous run from a checkpoint
+    ///
+    /// Storage and the used idempotency keys are restored from the checkpoint,
+    /// and the records it had already consumed are skipped. Streams must be
+    /// added in the same order as in the run that wrote the checkpoint. A CSV
+    /// stream opened with `CsvTransactionStream::from_file_at` at the
+    /// checkpoint's position and added with `add_csv_stream` already stands
+    /// past those records, so only unreadable records consumed after its last
+    /// readable one are skipped; other streams read past every consumed record.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let checkpoint = Checkpoint::load("run.checkpoint")?;
+    /// let input = match checkpoint.position(0) {
+    ///     Some(position) => CsvTransactionStream::from_file_at("huge.csv", options, position).await?,
+    ///     None => CsvTransactionStream::from_file_with_options("huge.csv", options).await?,
+    /// };
+    /// StreamProcessor::new(mgr, store, SkipErrors)
+    ///     .resume_from(checkpoint)
+    ///     .with_checkpoints("run.checkpoint", 1_000_000)
+    ///     .add_csv_stream(input)
+    ///     .process()
+    ///     .await;
+    /// ```
+    pub fn resume_from(mut self, checkpoint: Checkpoint<A>) -> Self {
+        self.resume = Some(checkpoint);
+        self
+    }
 
-        ProcessorResults {
-            shard_results,
-            total_streams: num_streams,
-        }
+    /// Select dispute semantics for every shard (defaults to `DisputePolicy::default()`)
+    pub fn with_dispute_policy(mut self, policy: DisputePolicy) -> Self {
+        self.engine.dispute_policy = policy;
+        self
     }
 
-    /// Process a single shard's stream
-    async fn process_shard_stream<S>(
-        mut stream: S,
-        mut processor: TransactionProcessor<A, M, T>,
-        policy: P,
-    ) -> bool
-    where
-        S: Stream<Item = Result<Transaction<A>, IoError>> + Unpin,
-    {
-        while let Some(result) = stream.next().await {
-            match result {
-                Ok(transaction) => {
-                    if let Err(e) = processor.process_transaction(transaction)
-                        && !policy.handle_engine_error(e) {
-                        return false;
-                    }
-                }
-                Err(e) => {
-                    if !policy.handle_io_error(e) {
-                        return false;
-                    }
-                }
-            }
-        }
+    /// Charge deposit and withdrawal fees in every shard
+    ///
+    /// See `TransactionProcessor::with_fee_schedule`.
+    pub fn with_fee_schedule(mut self, schedule: Arc<FeeSchedule<A>>) -> Self {
+        self.engine.fee_schedule = Some(schedule);
+        self
+    }
 
-        true
+    /// Report every applied or rejected transaction, from all shards, to an audit sink
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink<A>>) -> Self {
+        self.engine.audit_sink = Some(sink);
+        self
     }
 
-    /// Get reference to account manager
-    pub fn account_manager(&self) -> &M {
-        &self.account_manager
+    /// Add a business rule checked by every shard before a transaction is applied
+    ///
+    /// See `TransactionProcessor::with_validator`.
+    pub fn with_validator(mut self, validator: Arc<dyn TransactionValidator<A>>) -> Self {
+        self.engine.validators.push(validator);
+        self
     }
-}
 
-/// Results from processing streams across multiple shards
-#[derive(Debug)]
-pub struct ProcessorResults {
-    pub shard_results: Vec<ShardResult>,
-    pub total_streams: usize,
-}
+    /// Report every rejected record, from all shards, to a dead-letter sink
+    ///
+    /// Rejects are reported before the error policy runs, so they are captured
+    /// even under `SilentSkip`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let rejects = Arc::new(CsvDeadLetterWriter::new(File::create("rejects.csv")?)?);
+    ///
+    /// StreamProcessor::new(mgr, store, SilentSkip)
+    ///     .with_dead_letter_sink(rejects.clone())
+    ///     .add_stream(csv_stream)
+    ///     .process()
+    ///     .await;
+    ///
+    /// rejects.flush()?;
+    /// ```
+    pub fn with_dead_letter_sink(mut self, sink: Arc<dyn DeadLetterSink<A>>) -> Self {
+        self.dead_letter_sink = Some(sink);
+        self
+    }
 
-/// Result from processing a single shard
-#[derive(Debug)]
-pub struct ShardResult {
+    /// Keep up to `capacity` of the run's IO and engine errors in
+    /// `ProcessorResults::errors`
+    ///
+    /// Each error is kept with its message, the rejected transaction and the
+    /// name of its input stream, whatever the error policy decides, so a
+    /// caller can log or store them after the run without writing a policy.
+    /// Errors past `capacity` are only counted, in
+    /// `ProcessorResults::errors_dropped`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let results = StreamProcessor::new(mgr, store, SilentSkip)
+    ///     .with_error_log(1_000)
+    ///     .add_stream(csv_stream)
+    ///     .process()
+    ///     .await;
+    ///
+    /// for error in &results.errors {
+    ///     warn!("{:?}: {}", error.stream, error.message);
+    /// }
+    /// ```
+    pub fn with_error_log(mut self, capacity: usize) -> Self {
+        self.error_log = Some(capacity);
+        self
+    }
+
+    /// Add a stream to process (fluent interface)
+    ///
+    /// Stream will be assigned to a shard based on the shard assignment strategy.
+    /// If multiple streams are assigned to the same shard, they will be combined
+    /// according to the stream combining strategy.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// StreamProcessor::new(mgr, store, SilentSkip)
+    ///     .add_stream(csv_stream_1)
+    ///     .add_stream(csv_stream_2)
+    ///     .add_stream(csv_stream_3)
+    ///     .process()
+    ///     .await;
+    /// ```
+    pub fn add_stream<S>(self, stream: S) -> Self
+    where
+        S: Stream<Item = Result<Transaction<A>, IoError>> + Send + 'static,
+    {
+        let name = self.default_stream_name();
+        self.add_stream_named(name, stream)
+    }
+
+    /// Add a stream scheduled with `priority` among its shard's streams
+    ///
+    /// Under `StreamCombinator::Merge`, higher-priority streams are polled
+    /// more often than the others in their shard; under `Chain` (and
+    /// `process_sequential`) they are drained first. See `Priority`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// StreamProcessor::new(mgr, store, SilentSkip)
+    ///     .add_stream_with_priority(dispute_feed, Priority::High)
+    ///     .add_stream_with_priority(backfill_csv, Priority::Low)
+    ///     .process()
+    ///     .await;
+    /// ```
+    pub fn add_stream_with_priority<S>(self, stream: S, priority: Priority) -> Self
+    where
+        S: Stream<Item = Result<Transaction<A>, IoError>> + Send + 'static,
+    {
+        let mut processor = self.add_stream(stream);
+        *processor
+            .stream_priorities
+            .last_mut()
+            .expect("stream was just added") = priority;
+        processor
+    }
+
+    /// Add a stream reported under `name` in the results
+    ///
+    /// Each stream's counts, completion and any failure it caused are listed
+    /// in `ShardResult::streams` and `ShardResult::failed_stream`. Unnamed
+    /// streams are called `stream-<index>`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let results = StreamProcessor::new(mgr, store, AbortOnError)
+    ///     .add_stream_named("partnerA", partner_a)
+    ///     .add_stream_named("partnerB", partner_b)
+    ///     .process()
+    ///     .await;
+    ///
+    /// for name in results.failed_streams() {
+    ///     eprintln!("{name} aborted its shard");
+    /// }
+    /// ```
+    pub fn add_stream_named<S>(self, name: impl Into<String>, stream: S) -> Self
+    where
+        S: Stream<Item = Result<Transaction<A>, IoError>> + Send + 'static,
+    {
+        self.add_timestamped_stream_named(
+            name,
+            stream.map(|result| result.map(TimestampedTransaction::from)),
+        )
+    }
+
+    /// Add a stream whose transactions carry event timestamps
+    ///
+    /// Timestamps are only used for ordering by `StreamCombinator::MergeByTimestamp`;
+    /// other combinators treat these streams like any other.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let stream = CsvTransactionStream::<FixedPoint>::from_file("a.csv").await?;
+    ///
+    /// StreamProcessor::new(mgr, store, SilentSkip)
+    ///     .with_stream_combinator(StreamCombinator::MergeByTimestamp)
+    ///     .add_timestamped_stream(stream.timestamped())
+    ///     .process()
+    ///     .await;
+    /// ```
+    pub fn add_timestamped_stream<S>(self, stream: S) -> Self
+    where
+        S: Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send + 'static,
+    {
+        let name = self.default_stream_name();
+        self.add_timestamped_stream_named(name, stream)
+    }
+
+    /// Add a timestamped stream reported under `name` in the results
+    pub fn add_timestamped_stream_named<S>(mut self, name: impl Into<String>, stream: S) -> Self
+    where
+        S: Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send + 'static,
+    {
+        self.streams.push(Box::pin(stream));
+        self.stream_names.push(name.into());
+        self.stream_priorities.push(Priority::Normal);
+        self.stream_positions.push(None);
+        self
+    }
+
+    /// Add a CSV stream whose reader position is kept in checkpoints
+    ///
+    /// Records keep their optional `timestamp` and `idempotency_key` columns,
+    /// as with `add_timestamped_stream`. A checkpoint stores where the reader
+    /// stood after the stream's last consumed record (see
+    /// `Checkpoint::position`), so a resumed run can open the file with
+    /// `CsvTransactionStream::from_file_at` instead of reading it again from
+    /// the start.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// StreamProcessor::new(mgr, store, SkipErrors)
+    ///     .with_checkpoints("run.checkpoint", 1_000_000)
+    ///     .add_csv_stream(CsvTransactionStream::from_file("huge.csv").await?)
+    ///     .process()
+    ///     .await;
+    /// ```
+    pub fn add_csv_stream(self, stream: CsvTransactionStream<A>) -> Self
+    where
+        A: Unpin,
+    {
+        let name = self.default_stream_name();
+        self.add_csv_stream_named(name, stream)
+    }
+
+    /// Add a CSV stream reported under `name`; see `add_csv_stream`
+    pub fn add_csv_stream_named(
+        self,
+        name: impl Into<String>,
+        stream: CsvTransactionStream<A>,
+    ) -> Self
+    where
+        A: Unpin,
+    {
+        let position = stream.position();
+        let mut processor = self.add_timestamped_stream_named(name, stream.timestamped());
+        *processor
+            .stream_positions
+            .last_mut()
+            .expect("stream was just added") = Some(position);
+        processor
+    }
+
+    fn default_stream_name(&self) -> String {
+        format!("stream-{}", self.streams.len())
+    }
+
+    /// Process all streams across parallel shards
+    ///
+    /// 1. Assigns streams to shards based on shard assignment strategy
+    /// 2. Combines streams within each shard based on stream combining strategy
+    /// 3. Spawns one task per shard
+    /// 4. Each task processes its combined stream
+    ///
+    /// # Returns
+    /// ProcessorResults containing per-shard results, overall success status and
+    /// final account statistics
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let results = StreamProcessor::new(mgr, store, SilentSkip)
+    ///     .with_shards(4)
+    ///     .add_stream(stream1)
+    ///     .add_stream(stream2)
+    ///     .process()
+    ///     .await;
+    ///
+    /// if results.all_succeeded() {
+    ///     println!("All shards processed successfully");
+    /// }
+    /// ```
+    pub async fn process(self) -> ProcessorResults<A> {
+        self.run(Vec::new()).await
+    }
+
+    /// Start processing on a background task and return a handle to it
+    ///
+    /// Streams already added are processed as with `process`. More streams
+    /// can be added through the handle while processing runs; each is
+    /// assigned to a shard by the shard assignment strategy (round-robin
+    /// for `Sequential`, whose total is unknown up front) and merged into
+    /// that shard's input as it arrives. Processing ends once
+    /// `StreamHandle::finish` has been called and every stream has ended.
+    /// Checkpoints are not supported, since the set of streams keeps
+    /// changing.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let handle = StreamProcessor::new(mgr, store, SkipErrors)
+    ///     .with_shards(4)
+    ///     .add_stream_named("partnerA", partner_a)
+    ///     .process_detached();
+    ///
+    /// // Later, as a new partner file lands
+    /// handle.add_stream_named("partnerB", CsvTransactionStream::from_file(path).await?);
+    ///
+    /// let results = handle.finish().await;
+    /// ```
+    pub fn process_detached(mut self) -> StreamHandle<A> {
+        if self.checkpoints.take().is_some() {
+            warn!("Checkpoints are not supported when streams are added at runtime");
+        }
+
+        let (senders, receivers) = (0..self.num_shards)
+            .map(|_| mpsc::unbounded_channel())
+            .unzip();
+        let assignment = self.shard_assignment.clone();
+        let first_index = self.streams.len();
+        let task = tokio::spawn(self.run(receivers));
+        StreamHandle::new(senders, assignment, first_index, task)
+    }
+
+    /// Process all streams in order on the calling task
+    ///
+    /// A fast path for the single-shard case: each input is read to its end
+    /// in turn, highest priority first and in insertion order within a
+    /// priority, and every record is applied directly by one
+    /// `TransactionProcessor`, so results are deterministic. No shard task is
+    /// spawned and no combinator is involved, so the shard count, shard
+    /// assignment, execution model, stream combinator, shard concurrency,
+    /// client sequencing and buffering are ignored. Engine options,
+    /// transforms, the error policy, sinks, retries, rate and memory limits,
+    /// stall detection, checkpoints and snapshots apply as in `process`; a
+    /// shard snapshot is written as a single part.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// StreamProcessor::new(mgr, store, SilentSkip)
+    ///     .add_stream(csv_stream)
+    ///     .process_sequential()
+    ///     .await;
+    /// ```
+    pub async fn process_sequential(self) -> ProcessorResults<A> {
+        let StreamProcessor {
+            account_manager,
+            transaction_store,
+            error_policy,
+            streams,
+            stream_names,
+            stream_priorities,
+            stream_positions,
+            engine,
+            dead_letter_sink,
+            error_log,
+            transforms,
+            checkpoints,
+            rate_limit,
+            retry_policy,
+            stall_timeout,
+            cancel_on_stall,
+            resume,
+            memory_budget,
+            periodic_snapshot,
+            snapshot_mode,
+            shard_snapshot,
+            #[cfg(feature = "metrics")]
+            metrics,
+            ..
+        } = self;
+
+        let num_streams = streams.len();
+        if num_streams == 0 {
+            return ProcessorResults {
+                shard_results: vec![],
+                total_streams: 0,
+                stats: AccountStats::collect(&account_manager),
+                errors: Vec::new(),
+                errors_dropped: 0,
+            };
+        }
+
+        #[cfg(feature = "metrics")]
+        if let (Some(budget), Some(metrics)) = (&memory_budget, &metrics) {
+            budget.attach_metrics(metrics);
+        }
+
+        // Continue a previous run: restore its storage and skip what it consumed
+        let resumed = Self::resume_offsets(
+            resume,
+            &*account_manager,
+            &*transaction_store,
+            &engine.idempotency_keys,
+            num_streams,
+        );
+        let offsets = match resumed {
+            Ok(offsets) => offsets,
+            Err(e) => {
+                warn!("Failed to restore checkpoint: {}", e);
+                return ProcessorResults {
+                    shard_results: vec![ShardResult::failed(0)],
+                    total_streams: num_streams,
+                    stats: AccountStats::collect(&account_manager),
+                    errors: Vec::new(),
+                    errors_dropped: 0,
+                };
+            }
+        };
+
+        let checkpointer = checkpoints.map(|(path, interval)| {
+            Checkpointer::new(
+                path,
+                interval,
+                offsets.clone(),
+                stream_positions.clone(),
+                engine.idempotency_keys.clone(),
+                account_manager.clone(),
+                transaction_store.clone(),
+            )
+        });
+        let limiter = rate_limit.map(|limit| match limit {
+            RateLimit::Global(tx_per_sec) | RateLimit::PerShard(tx_per_sec) => {
+                Arc::new(RateLimiter::new(tx_per_sec))
+            }
+        });
+        let error_log = error_log.map(ErrorLog::new);
+        let registry = StreamRegistry::default();
+
+        let snapshot_gate = periodic_snapshot
+            .as_ref()
+            .map(|_| Arc::new(SnapshotGate::default()));
+        let periodic_snapshots =
+            periodic_snapshot
+                .zip(snapshot_gate.clone())
+                .map(|((interval, sink), gate)| {
+                    PeriodicSnapshots::spawn(
+                        interval,
+                        snapshot_mode,
+                        sink,
+                        gate,
+                        account_manager.clone(),
+                    )
+                });
+
+        let mut processor = engine.build(account_manager.clone(), transaction_store.clone());
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = metrics.clone() {
+            processor = processor.with_metrics(metrics);
+        }
+        #[cfg(feature = "metrics")]
+        let started = Instant::now();
+
+        // Highest priority first, in insertion order within a priority
+        let mut inputs: Vec<_> = streams
+            .into_iter()
+            .zip(stream_names)
+            .zip(stream_priorities)
+            .zip(unread(&offsets, &stream_positions))
+            .enumerate()
+            .map(|(index, (((stream, name), priority), unread))| {
+                (
+                    priority,
+                    index,
+                    stream,
+                    unread,
+                    registry.register(index, 0, name),
+                )
+            })
+            .collect();
+        inputs.sort_by_key(|(priority, ..)| Reverse(*priority));
+
+        let stalled = Arc::new(AtomicBool::new(false));
+        let mut aborted = false;
+        let mut failed_stream = None;
+        'inputs: for (_, index, stream, unread, tracker) in inputs {
+            let stream = stream.skip(unread as usize);
+            let mut stream = match retry_policy {
+                Some(policy) => Box::pin(retry_transient(stream, policy)) as TransactionStream<A>,
+                None => Box::pin(stream),
+            };
+            if let Some(timeout) = stall_timeout {
+                stream = Box::pin(watchdog(
+                    stream,
+                    timeout,
+                    cancel_on_stall,
+                    0,
+                    stalled.clone(),
+                ));
+            }
+            if let Some(limiter) = &limiter {
+                stream = Box::pin(throttle(stream, limiter.clone()));
+            }
+
+            while let Some(result) = stream.next().await {
+                tracker.record_read();
+                if let Some(checkpointer) = &checkpointer {
+                    checkpointer.save_if_due();
+                }
+                let error = {
+                    // Checkpoints and snapshots wait until this record has been fully applied
+                    let _consumed = checkpointer.as_ref().map(|c| c.begin_record(index));
+                    let _applying = snapshot_gate.as_deref().map(SnapshotGate::read);
+
+                    match result {
+                        Ok(timestamped) => transforms
+                            .iter()
+                            .try_fold(timestamped.transaction, |tx, transform| transform(tx))
+                            .and_then(|tx| {
+                                let rejected = (dead_letter_sink.is_some() || error_log.is_some())
+                                    .then(|| tx.clone());
+                                let key = timestamped.idempotency_key.as_deref();
+                                let e = processor
+                                    .process_transaction_at(tx, key, timestamped.timestamp)
+                                    .err()?;
+                                tracker.record_rejected();
+                                report_rejected(
+                                    &e,
+                                    rejected,
+                                    Some(tracker.name().to_string()),
+                                    dead_letter_sink.as_deref(),
+                                    error_log.as_ref(),
+                                );
+                                Some(ProcessingError::Engine(e))
+                            }),
+                        Err(e) => {
+                            tracker.record_unreadable();
+                            #[cfg(feature = "metrics")]
+                            if let Some(metrics) = &metrics {
+                                metrics.record_error(IO_ERROR_KIND);
+                            }
+                            report_unreadable(
+                                &e,
+                                Some(tracker.name().to_string()),
+                                dead_letter_sink.as_deref(),
+                                error_log.as_ref(),
+                            );
+                            Some(ProcessingError::Io(e))
+                        }
+                    }
+                };
+
+                // The policy may await, so it runs after the gates are released
+                if let Some(e) = error
+                    && !error_policy.handle_error(e).await
+                {
+                    aborted = true;
+                    failed_stream = Some(tracker.name().to_string());
+                    break 'inputs;
+                }
+
+                if let Some((usage, limit)) = memory_budget.as_ref().and_then(|budget| {
+                    budget.record_processed(&*account_manager, &*transaction_store)
+                }) && !error_policy
+                    .handle_error(ProcessingError::MemoryBudgetExceeded {
+                        used: usage.total_bytes(),
+                        limit,
+                    })
+                    .await
+                {
+                    aborted = true;
+                    break 'inputs;
+                }
+            }
+            if stalled.load(Ordering::Relaxed) {
+                break;
+            }
+            tracker.complete();
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &metrics {
+            metrics.record_shard(started.elapsed());
+        }
+
+        if let Some(periodic_snapshots) = periodic_snapshots {
+            periodic_snapshots.stop().await;
+        }
+        if let Some(sink) = shard_snapshot {
+            let snapshots = ShardSnapshots::begin(sink).await;
+            snapshots.write_parts(&*account_manager, 1).await;
+            snapshots.finish().await;
+        }
+        if let Some(checkpointer) = &checkpointer {
+            checkpointer.save();
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &metrics {
+            metrics.observe_accounts(&account_manager);
+        }
+        if let Some(budget) = &memory_budget {
+            budget.check(&account_manager, &transaction_store);
+        }
+
+        let counters = ShardCounters::of(&processor);
+        let stalled = stalled.load(Ordering::Relaxed);
+        let (errors, errors_dropped) = error_log.map(|log| log.take()).unwrap_or_default();
+        ProcessorResults {
+            shard_results: vec![ShardResult {
+                shard_id: 0,
+                streams_processed: num_streams,
+                success: !aborted && !stalled,
+                locked_skipped: counters.locked_skipped,
+                quarantined: counters.quarantined,
+                idempotent_replays: counters.idempotent_replays,
+                by_type: counters.by_type,
+                streams: registry.shard_results(0),
+                failed_stream,
+                stalled,
+            }],
+            total_streams: num_streams,
+            stats: AccountStats::collect(&account_manager),
+            errors,
+            errors_dropped,
+        }
+    }
+
+    /// Run every shard; `runtime` has one receiver per shard for streams
+    /// added through a `StreamHandle` (empty unless detached)
+    async fn run(self, runtime: Vec<mpsc::UnboundedReceiver<NewStream<A>>>) -> ProcessorResults<A> {
+        let num_streams = self.streams.len();
+
+        if num_streams == 0 && runtime.is_empty() {
+            return ProcessorResults {
+                shard_results: vec![],
+                total_streams: 0,
+                stats: AccountStats::collect(&self.account_manager),
+                errors: Vec::new(),
+                errors_dropped: 0,
+            };
+        }
+
+        // Destructure self to get ownership of all fields
+        let StreamProcessor {
+            account_manager,
+            transaction_store,
+            error_policy,
+            num_shards,
+            streams,
+            stream_names,
+            stream_priorities,
+            stream_positions,
+            shard_assignment,
+            execution_model,
+            stream_combinator,
+            engine,
+            dead_letter_sink,
+            error_log,
+            sequencing,
+            buffer_size,
+            shard_concurrency,
+            transforms,
+            checkpoints,
+            rate_limit,
+            retry_policy,
+            stall_timeout,
+            cancel_on_stall,
+            resume,
+            memory_budget,
+            periodic_snapshot,
+            snapshot_mode,
+            shard_snapshot,
+            #[cfg(feature = "metrics")]
+            metrics,
+            _phantom,
+        } = self;
+
+        #[cfg(feature = "metrics")]
+        if let (Some(budget), Some(metrics)) = (&memory_budget, &metrics) {
+            budget.attach_metrics(metrics);
+        }
+
+        // Continue a previous run: restore its storage and skip what it consumed
+        let resumed = Self::resume_offsets(
+            resume,
+            &*account_manager,
+            &*transaction_store,
+            &engine.idempotency_keys,
+            num_streams,
+        );
+        let offsets = match resumed {
+            Ok(offsets) => offsets,
+            Err(e) => {
+                warn!("Failed to restore checkpoint: {}", e);
+                return ProcessorResults {
+                    shard_results: vec![ShardResult::failed(0)],
+                    total_streams: num_streams,
+                    stats: AccountStats::collect(&account_manager),
+                    errors: Vec::new(),
+                    errors_dropped: 0,
+                };
+            }
+        };
+        let streams: Vec<_> = streams
+            .into_iter()
+            .zip(unread(&offsets, &stream_positions))
+            .map(|(stream, unread)| match unread {
+                0 => stream,
+                _ => Box::pin(stream.skip(unread as usize)) as TransactionStream<A>,
+            })
+            .zip(stream_names)
+            .zip(stream_priorities)
+            .map(|((stream, name), priority)| (name, priority, stream))
+            .collect();
+
+        let checkpointer = match checkpoints {
+            Some(_)
+                if sequencing.is_some()
+                    || buffer_size.is_some()
+                    || shard_concurrency > 1
+                    || execution_model == ExecutionModel::ActorSharded
+                    || matches!(stream_combinator, StreamCombinator::MergeByTimestamp) =>
+            {
+                warn!(
+                    "Checkpoints are not supported with timestamp merging, client sequencing, buffering, shard concurrency or actor sharding"
+                );
+                None
+            }
+            Some((path, interval)) => Some(Arc::new(Checkpointer::new(
+                path,
+                interval,
+                offsets.clone(),
+                stream_positions.clone(),
+                engine.idempotency_keys.clone(),
+                account_manager.clone(),
+                transaction_store.clone(),
+            ))),
+            None => None,
+        };
+        let error_log = error_log.map(|capacity| Arc::new(ErrorLog::new(capacity)));
+
+        // Shards pause between records while a periodic snapshot copies storage
+        let snapshot_gate = periodic_snapshot
+            .as_ref()
+            .map(|_| Arc::new(SnapshotGate::default()));
+        let periodic_snapshots =
+            periodic_snapshot
+                .zip(snapshot_gate.clone())
+                .map(|((interval, sink), gate)| {
+                    PeriodicSnapshots::spawn(
+                        interval,
+                        snapshot_mode,
+                        sink,
+                        gate,
+                        account_manager.clone(),
+                    )
+                });
+
+        // The final snapshot starts before any shard can finish
+        let shard_snapshots = match shard_snapshot {
+            Some(sink) => Some(Arc::new(ShardSnapshots::begin(sink).await)),
+            None => None,
+        };
+
+        let run = Arc::new(RunContext {
+            account_manager: account_manager.clone(),
+            transaction_store: transaction_store.clone(),
+            policy: error_policy,
+            engine,
+            dead_letter_sink,
+            error_log: error_log.clone(),
+            transforms,
+            registry: StreamRegistry::default(),
+            memory_budget: memory_budget.clone(),
+            snapshot_gate,
+            #[cfg(feature = "metrics")]
+            metrics: metrics.clone(),
+            num_shards,
+            execution_model,
+            combinator: stream_combinator,
+            sequencing,
+            buffer_size,
+            concurrency: shard_concurrency,
+            stall_timeout,
+            cancel_on_stall,
+            rate_limit,
+            global_limiter: match rate_limit {
+                Some(RateLimit::Global(tx_per_sec)) => Some(Arc::new(RateLimiter::new(tx_per_sec))),
+                _ => None,
+            },
+            retry_policy,
+            checkpointer: checkpointer.clone(),
+            last_streams: (0..num_shards)
+                .map(|_| Arc::new(AtomicUsize::new(0)))
+                .collect(),
+            last_errors: (0..num_shards)
+                .map(|_| Arc::new(AtomicUsize::new(usize::MAX)))
+                .collect(),
+            added: (0..num_shards)
+                .map(|_| Arc::new(AtomicUsize::new(0)))
+                .collect(),
+        });
+
+        let shard_results = match execution_model {
+            ExecutionModel::SharedStorage => {
+                run.run_shared_storage(
+                    streams,
+                    runtime,
+                    &shard_assignment,
+                    shard_snapshots.as_ref(),
+                )
+                .await
+            }
+            ExecutionModel::ActorSharded => {
+                run.run_actor_sharded(streams, runtime, shard_snapshots.as_ref())
+                    .await
+            }
+        };
+
+        if let Some(periodic_snapshots) = periodic_snapshots {
+            periodic_snapshots.stop().await;
+        }
+        // Every shard task has finished with its handle
+        if let Some(snapshots) = shard_snapshots.and_then(Arc::into_inner) {
+            snapshots.finish().await;
+        }
+        if let Some(checkpointer) = &checkpointer {
+            checkpointer.save();
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &metrics {
+            metrics.observe_accounts(&account_manager);
+        }
+        if let Some(budget) = &memory_budget {
+            budget.check(&account_manager, &transaction_store);
+        }
+
+        let (errors, errors_dropped) = error_log.map(|log| log.take()).unwrap_or_default();
+        ProcessorResults {
+            shard_results,
+            total_streams: run.registry.len(),
+            stats: AccountStats::collect(&account_manager),
+            errors,
+            errors_dropped,
+        }
+    }
+
+    /// Offsets each stream resumes from, after restoring `resume` into storage
+    /// and `idempotency_keys`
+    fn resume_offsets(
+        resume: Option<Checkpoint<A>>,
+        account_manager: &M,
+        transaction_store: &T,
+        idempotency_keys: &IdempotencyKeys,
+        num_streams: usize,
+    ) -> Result<Vec<u64>, StorageError> {
+        let mut offsets = vec![0; num_streams];
+        if let Some(checkpoint) = resume {
+            checkpoint.restore(account_manager, transaction_store)?;
+            checkpoint.restore_idempotency_keys(idempotency_keys);
+            for (offset, consumed) in offsets.iter_mut().zip(checkpoint.offsets()) {
+                *offset = *consumed;
+            }
+        }
+        Ok(offsets)
+    }
+
+    /// Get reference to account manager
+    pub fn account_manager(&self) -> &M {
+        &self.account_manager
+    }
+}
+
+/// Records each resumed stream still has to read past: those the checkpointed
+/// run consumed, less any its reader already stands beyond (see
+/// `CsvTransactionStream::from_file_at`)
+fn unread(offsets: &[u64], positions: &[Option<ReadPosition>]) -> Vec<u64> {
+    offsets
+        .iter()
+        .zip(positions)
+        .map(|(offset, position)| {
+            let read = position
+                .as_ref()
+                .map_or(0, |position| position.get().record());
+            offset.saturating_sub(read)
+        })
+        .collect()
+}
+
+/// Name of input stream `source`, if known
+fn stream_name(registry: &StreamRegistry, source: Option<usize>) -> Option<String> {
+    source
+        .and_then(|index| registry.get(index))
+        .map(|tracker| tracker.name().to_string())
+}
+
+/// Report a transaction the engine rejected to the error log and dead letter sink
+fn report_rejected<A: AmountType>(
+    e: &EngineError,
+    transaction: Option<Transaction<A>>,
+    stream: Option<String>,
+    dead_letter_sink: Option<&dyn DeadLetterSink<A>>,
+    error_log: Option<&ErrorLog<A>>,
+) {
+    if let Some(log) = error_log {
+        log.record(LoggedError {
+            kind: LoggedErrorKind::Engine,
+            message: e.to_string(),
+            transaction: transaction.clone(),
+            stream,
+        });
+    }
+    if let Some(sink) = dead_letter_sink {
+        sink.record(DeadLetter {
+            transaction,
+            reason: e.to_string(),
+        });
+    }
+}
+
+/// Report a record that could not be read to the dead letter sink and error log
+fn report_unreadable<A: AmountType>(
+    e: &IoError,
+    stream: Option<String>,
+    dead_letter_sink: Option<&dyn DeadLetterSink<A>>,
+    error_log: Option<&ErrorLog<A>>,
+) {
+    if let Some(sink) = dead_letter_sink {
+        sink.record(DeadLetter {
+            transaction: None,
+            reason: e.to_string(),
+        });
+    }
+    if let Some(log) = error_log {
+        log.record(LoggedError {
+            kind: LoggedErrorKind::Io,
+            message: e.to_string(),
+            transaction: None,
+            stream,
+        });
+    }
+}
+
+/// Combine a shard's streams (a lone stream needs no combinator)
+fn combine<A: AmountType + 'static>(
+    mut streams: Vec<(Priority, TransactionStream<A>)>,
+    combinator: StreamCombinator,
+) -> TransactionStream<A> {
+    let weighted = streams
+        .first()
+        .is_some_and(|(first, _)| streams.iter().any(|(priority, _)| priority != first));
+    match combinator {
+        _ if streams.len() == 1 => streams.pop().expect("one stream").1,
+        // Merge streams concurrently, polling higher priorities more often
+        StreamCombinator::Merge if weighted => Box::pin(PriorityMerge::new(streams)),
+        StreamCombinator::Merge => Box::pin(stream::select_all(
+            streams.into_iter().map(|(_, stream)| stream),
+        )),
+        // Chain streams sequentially, highest priority first
+        StreamCombinator::Chain => {
+            streams.sort_by_key(|(priority, _)| Reverse(*priority));
+            Box::pin(stream::iter(streams.into_iter().map(|(_, stream)| stream)).flatten())
+        }
+        // Merge streams in global timestamp order
+        StreamCombinator::MergeByTimestamp => Box::pin(TimestampMerge::new(
+            streams.into_iter().map(|(_, stream)| stream),
+        )),
+    }
+}
+
+/// Actor model inputs: one reader combines every stream, including those
+/// added at runtime, and routes each record to the shard owning its client
+fn actor_inputs<A: AmountType + 'static>(
+    streams: Vec<(Priority, TransactionStream<A>)>,
+    incoming: Vec<TransactionStream<A>>,
+    combinator: StreamCombinator,
+    num_shards: usize,
+    capacity: usize,
+) -> Vec<Vec<(Priority, TransactionStream<A>)>> {
+    let mut input = combine(streams, combinator);
+    if !incoming.is_empty() {
+        input = Box::pin(stream::select(input, stream::select_all(incoming)));
+    }
+    dispatch_by_client(input, num_shards, capacity)
+        .into_iter()
+        .map(|worker| vec![(Priority::Normal, Box::pin(worker) as TransactionStream<A>)])
+        .collect()
+}
+
+/// Run every shard on its own task and collect its result, in shard order
+///
+/// A shard whose task panicked reports failure.
+async fn join_shards<A, F>(
+    shards: Vec<Vec<(Priority, TransactionStream<A>)>>,
+    runtime: Vec<Option<TransactionStream<A>>>,
+    registry: &StreamRegistry,
+    run_shard: impl Fn(usize, Vec<(Priority, TransactionStream<A>)>, Option<TransactionStream<A>>) -> F,
+) -> Vec<ShardResult>
+where
+    A: AmountType + 'static,
+    F: Future<Output = ShardResult> + Send + 'static,
+{
+    // Spawn one task per shard
+    let handles: Vec<_> = shards
+        .into_iter()
+        .zip(runtime)
+        .enumerate()
+        .map(|(shard_id, (shard_streams, incoming))| {
+            tokio::spawn(run_shard(shard_id, shard_streams, incoming))
+        })
+        .collect();
+
+    // Await all tasks
+    let mut shard_results = Vec::new();
+    for (shard_id, handle) in handles.into_iter().enumerate() {
+        shard_results.push(handle.await.unwrap_or_else(|_| ShardResult {
+            streams: registry.shard_results(shard_id),
+            ..ShardResult::failed(shard_id)
+        }));
+    }
+    shard_results
+}
+
+/// Drain `stream` on its own task into a bounded channel
+///
+/// The reader task stops when the stream ends or the receiver is dropped
+/// (e.g. when a shard aborts on error).
+fn buffered<S>(mut stream: S, capacity: usize) -> impl Stream<Item = S::Item> + Send
+where
+    S: Stream + Unpin + Send + 'static,
+    S::Item: Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(capacity);
+    tokio::spawn(async move {
+        while let Some(item) = stream.next().await {
+            if tx.send(item).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    })
+}
+
+/// Checkpointer over a run's shared storage
+type RunCheckpointer<A, M, T> = Checkpointer<A, Arc<M>, Arc<T>>;
+
+/// Settings and shared state of one run, shared by all of its shards
+struct RunContext<A, M, T, P>
+where
+    A: AmountType,
+    M: ClientAccountManager<A> + Send + Sync + 'static,
+    T: TransactionStoreManager<A> + Send + Sync + 'static,
+    P: ErrorPolicy + Clone + Send + 'static,
+{
+    account_manager: Arc<M>,
+    transaction_store: Arc<T>,
+    policy: P,
+    engine: EngineOptions<A>,
+    dead_letter_sink: Option<Arc<dyn DeadLetterSink<A>>>,
+    error_log: Option<Arc<ErrorLog<A>>>,
+    transforms: Vec<Arc<Transform<A>>>,
+    registry: StreamRegistry,
+    memory_budget: Option<Arc<MemoryBudget>>,
+    snapshot_gate: Option<Arc<SnapshotGate>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<MetricsRegistry>,
+    num_shards: usize,
+    execution_model: ExecutionModel,
+    combinator: StreamCombinator,
+    sequencing: Option<usize>,
+    buffer_size: Option<usize>,
+    concurrency: usize,
+    stall_timeout: Option<Duration>,
+    cancel_on_stall: bool,
+    rate_limit: Option<RateLimit>,
+    global_limiter: Option<Arc<RateLimiter>>,
+    retry_policy: Option<RetryPolicy>,
+    checkpointer: Option<Arc<RunCheckpointer<A, M, T>>>,
+    /// Index of the stream each shard polled last (see `ShardCheckpoint`)
+    last_streams: Vec<Arc<AtomicUsize>>,
+    /// Index of the stream that last yielded a read error in each shard
+    last_errors: Vec<Arc<AtomicUsize>>,
+    /// Streams each shard received while the run was going
+    added: Vec<Arc<AtomicUsize>>,
+}
+
+impl<A, M, T, P> RunContext<A, M, T, P>
+where
+    A: AmountType + 'static,
+    M: ClientAccountManager<A> + Send + Sync + 'static,
+    T: TransactionStoreManager<A> + Send + Sync + 'static,
+    P: ErrorPolicy + Clone + Send + 'static,
+{
+    /// Shared storage: each shard reads the streams assigned to it and
+    /// applies their records to the shared storage
+    async fn run_shared_storage(
+        self: &Arc<Self>,
+        streams: Vec<(String, Priority, TransactionStream<A>)>,
+        runtime: Vec<mpsc::UnboundedReceiver<NewStream<A>>>,
+        assignment: &ShardAssignment,
+        snapshots: Option<&Arc<ShardSnapshots<A>>>,
+    ) -> Vec<ShardResult> {
+        let total = streams.len();
+        let mut shards: Vec<Vec<_>> = (0..self.num_shards).map(|_| Vec::new()).collect();
+        for (index, (name, priority, stream)) in streams.into_iter().enumerate() {
+            let shard = assignment.shard_for(index, Some(total), self.num_shards);
+            shards[shard].push((priority, self.assigned(index, shard, name, stream)));
+        }
+        let mut incoming: Vec<_> = runtime
+            .into_iter()
+            .enumerate()
+            .map(|(shard, rx)| Some(self.incoming(rx, shard)))
+            .collect();
+        incoming.resize_with(self.num_shards, || None);
+
+        let shard_results = join_shards(
+            shards,
+            incoming,
+            &self.registry,
+            |shard_id, streams, incoming| self.shard(shard_id, None).process(streams, incoming),
+        )
+        .await;
+
+        // Shards may touch any client, so their parts wait for every shard
+        if let Some(snapshots) = snapshots {
+            snapshots
+                .write_parts(&*self.account_manager, self.num_shards)
+                .await;
+        }
+        shard_results
+    }
+
+    /// Actor sharding: one reader combines every stream, including those
+    /// added at runtime, and routes each record to the shard owning its
+    /// client; channels replace the shard buffers
+    async fn run_actor_sharded(
+        self: &Arc<Self>,
+        streams: Vec<(String, Priority, TransactionStream<A>)>,
+        runtime: Vec<mpsc::UnboundedReceiver<NewStream<A>>>,
+        snapshots: Option<&Arc<ShardSnapshots<A>>>,
+    ) -> Vec<ShardResult> {
+        let streams = streams
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, priority, stream))| {
+                (priority, self.assigned(index, 0, name, stream))
+            })
+            .collect();
+        let incoming = runtime.into_iter().map(|rx| self.incoming(rx, 0)).collect();
+        let capacity = self.buffer_size.unwrap_or(ACTOR_CHANNEL_CAPACITY);
+        let shards = actor_inputs(
+            streams,
+            incoming,
+            self.combinator,
+            self.num_shards,
+            capacity,
+        );
+        let runtime = (0..self.num_shards).map(|_| None).collect();
+
+        // Each shard owns its clients, so it writes its own part
+        join_shards(
+            shards,
+            runtime,
+            &self.registry,
+            |shard_id, streams, incoming| {
+                self.shard(shard_id, snapshots.cloned())
+                    .process(streams, incoming)
+            },
+        )
+        .await
+    }
+
+    /// Context of shard `shard_id`, which writes its part to `snapshots`
+    fn shard(
+        self: &Arc<Self>,
+        shard_id: usize,
+        snapshots: Option<Arc<ShardSnapshots<A>>>,
+    ) -> ShardContext<A, M, T, P> {
+        ShardContext {
+            run: self.clone(),
+            shard_id,
+            policy: self.policy.clone(),
+            checkpoint: self
+                .checkpointer
+                .clone()
+                .map(|checkpointer| ShardCheckpoint {
+                    checkpointer,
+                    last_stream: self.last_streams[shard_id].clone(),
+                }),
+            limiter: match self.rate_limit {
+                Some(RateLimit::PerShard(tx_per_sec)) => {
+                    Some(Arc::new(RateLimiter::new(tx_per_sec)))
+                }
+                _ => self.global_limiter.clone(),
+            },
+            snapshots,
+        }
+    }
+
+    /// Input `index` assigned to `shard` before the run started
+    fn assigned(
+        &self,
+        index: usize,
+        shard: usize,
+        name: String,
+        stream: TransactionStream<A>,
+    ) -> TransactionStream<A> {
+        let stream = match self.checkpointer {
+            Some(_) => {
+                let last_stream = self.last_streams[shard].clone();
+                Box::pin(stream.inspect(move |_| last_stream.store(index, Ordering::Relaxed)))
+            }
+            None => stream,
+        };
+        self.tracked(index, shard, name, stream)
+    }
+
+    /// Streams added to `shard` through a `StreamHandle`, tracked once the
+    /// shard receives them
+    fn incoming(
+        self: &Arc<Self>,
+        rx: mpsc::UnboundedReceiver<NewStream<A>>,
+        shard: usize,
+    ) -> TransactionStream<A> {
+        let run = self.clone();
+        Box::pin(Incoming::new(rx, move |new: NewStream<A>| {
+            run.added[shard].fetch_add(1, Ordering::Relaxed);
+            run.tracked(new.index, shard, new.name, new.stream)
+        }))
+    }
+
+    /// Register input `index` of `shard`, retrying its transient errors if asked to
+    fn tracked(
+        &self,
+        index: usize,
+        shard: usize,
+        name: String,
+        stream: TransactionStream<A>,
+    ) -> TransactionStream<A> {
+        let stream = match self.retry_policy {
+            Some(policy) => Box::pin(retry_transient(stream, policy)) as TransactionStream<A>,
+            None => stream,
+        };
+        let tracker = self.registry.register(index, shard, name);
+        Box::pin(track(
+            stream,
+            index,
+            tracker,
+            self.last_errors[shard].clone(),
+        ))
+    }
+
+    /// A processor over the run's storage
+    fn processor(&self) -> TransactionProcessor<A, Arc<M>, Arc<T>> {
+        let processor = self
+            .engine
+            .build(self.account_manager.clone(), self.transaction_store.clone());
+        #[cfg(feature = "metrics")]
+        let processor = match self.metrics.clone() {
+            Some(metrics) => processor.with_metrics(metrics),
+            None => processor,
+        };
+        processor
+    }
+}
+
+/// One shard of a run, or one lane of a shard under `with_shard_concurrency`
+struct ShardContext<A, M, T, P>
+where
+    A: AmountType,
+    M: ClientAccountManager<A> + Send + Sync + 'static,
+    T: TransactionStoreManager<A> + Send + Sync + 'static,
+    P: ErrorPolicy + Clone + Send + 'static,
+{
+    run: Arc<RunContext<A, M, T, P>>,
+    shard_id: usize,
+    policy: P,
+    checkpoint: Option<ShardCheckpoint<A, Arc<M>, Arc<T>>>,
+    limiter: Option<Arc<RateLimiter>>,
+    /// Where the shard writes the snapshot part of the clients it owns
+    snapshots: Option<Arc<ShardSnapshots<A>>>,
+}
+
+impl<A, M, T, P> ShardContext<A, M, T, P>
+where
+    A: AmountType + 'static,
+    M: ClientAccountManager<A> + Send + Sync + 'static,
+    T: TransactionStoreManager<A> + Send + Sync + 'static,
+    P: ErrorPolicy + Clone + Send + 'static,
+{
+    /// Process the shard's streams, then those added to it at runtime
+    async fn process(
+        self,
+        streams: Vec<(Priority, TransactionStream<A>)>,
+        incoming: Option<TransactionStream<A>>,
+    ) -> ShardResult {
+        if streams.is_empty() && incoming.is_none() {
+            return ShardResult::empty(self.shard_id);
+        }
+
+        let stream_count = streams.len();
+        #[cfg(feature = "metrics")]
+        let started = Instant::now();
+
+        let stalled = Arc::new(AtomicBool::new(false));
+        let combined = self.combined(streams, incoming, stalled.clone());
+        let (outcome, counters) = match self.run.concurrency {
+            1 => self.apply_serially(combined).await,
+            lanes => self.apply_in_lanes(combined, lanes).await,
+        };
+
+        let run = &self.run;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &run.metrics {
+            metrics.record_shard(started.elapsed());
+        }
+
+        if let Some(snapshots) = &self.snapshots {
+            snapshots
+                .write_part(&*run.account_manager, |account| {
+                    shard_of(account, run.num_shards) == self.shard_id
+                })
+                .await;
+        }
+
+        let stalled = stalled.load(Ordering::Relaxed);
+        ShardResult {
+            shard_id: self.shard_id,
+            streams_processed: stream_count + run.added[self.shard_id].load(Ordering::Relaxed),
+            success: outcome.is_ok() && !stalled,
+            locked_skipped: counters.locked_skipped,
+            quarantined: counters.quarantined,
+            idempotent_replays: counters.idempotent_replays,
+            by_type: counters.by_type,
+            streams: run.registry.shard_results(self.shard_id),
+            failed_stream: outcome
+                .err()
+                .flatten()
+                .and_then(|index| run.registry.get(index))
+                .map(|tracker| tracker.name().to_string()),
+            stalled,
+        }
+    }
+
+    /// The shard's input: its streams combined, then sequenced, buffered,
+    /// watched for stalls and throttled as configured
+    fn combined(
+        &self,
+        streams: Vec<(Priority, TransactionStream<A>)>,
+        incoming: Option<TransactionStream<A>>,
+        stalled: Arc<AtomicBool>,
+    ) -> TransactionStream<A> {
+        let run = &self.run;
+        let combined = match incoming {
+            None => combine(streams, run.combinator),
+            Some(incoming) if streams.is_empty() => incoming,
+            // Streams added at runtime are merged with the combined initial ones
+            Some(incoming) => Box::pin(stream::select(combine(streams, run.combinator), incoming)),
+        };
+
+        // Restore per-client order across the combined streams
+        let combined = match run.sequencing {
+            Some(max_pending) => Box::pin(ClientSequencer::new(combined, max_pending)),
+            None => combined,
+        };
+
+        #[cfg(feature = "metrics")]
+        let combined = match run.metrics.clone() {
+            Some(metrics) => Box::pin(combined.inspect(move |result| {
+                if result.is_err() {
+                    metrics.record_error(IO_ERROR_KIND);
+                }
+            })),
+            None => combined,
+        };
+
+        // Parse ahead on a separate task, bounded by the buffer; actor shards
+        // read from channels, which buffer already
+        let combined = match run.buffer_size {
+            Some(capacity) if run.execution_model == ExecutionModel::SharedStorage => {
+                Box::pin(buffered(combined, capacity))
+            }
+            _ => combined,
+        };
+
+        // Stop waiting on a source that hangs, if asked to
+        let combined = match run.stall_timeout {
+            Some(timeout) => Box::pin(watchdog(
+                combined,
+                timeout,
+                run.cancel_on_stall,
+                self.shard_id,
+                stalled,
+            )),
+            None => combined,
+        };
+
+        match self.limiter.clone() {
+            Some(limiter) => Box::pin(throttle(combined, limiter)),
+            None => combined,
+        }
+    }
+
+    /// Apply every record with one processor
+    async fn apply_serially(
+        &self,
+        stream: TransactionStream<A>,
+    ) -> (Result<(), Option<usize>>, ShardCounters) {
+        let mut processor = self.run.processor();
+        let outcome = self.apply(stream, &mut processor).await;
+        (outcome, ShardCounters::of(&processor))
+    }
+
+    /// Split the records by client across `lanes` tasks, each applying its
+    /// records with its own processor
+    async fn apply_in_lanes(
+        &self,
+        stream: TransactionStream<A>,
+        lanes: usize,
+    ) -> (Result<(), Option<usize>>, ShardCounters) {
+        let handles: Vec<_> = dispatch_by_client(stream, lanes, LANE_CHANNEL_CAPACITY)
+            .into_iter()
+            .map(|lane| {
+                let context = self.lane();
+                tokio::spawn(async move { context.apply_serially(Box::pin(lane)).await })
+            })
+            .collect();
+
+        // The shard fails with the first lane that aborted
+        let mut outcome = Ok(());
+        let mut counters = ShardCounters::default();
+        for handle in handles {
+            let (lane_outcome, lane_counters) = handle
+                .await
+                .unwrap_or((Err(None), ShardCounters::default()));
+            counters.merge(lane_counters);
+            if outcome.is_ok() {
+                outcome = lane_outcome;
+            }
+        }
+        (outcome, counters)
+    }
+
+    /// Context of one of the shard's lanes, which never checkpoint
+    fn lane(&self) -> Self {
+        Self {
+            run: self.run.clone(),
+            shard_id: self.shard_id,
+            policy: self.policy.clone(),
+            checkpoint: None,
+            limiter: None,
+            snapshots: None,
+        }
+    }
+
+    /// Apply every record of `stream` in turn
+    ///
+    /// Returns the index of the input stream whose error stopped the shard,
+    /// if the error policy aborted (None when the source is unknown).
+    async fn apply(
+        &self,
+        mut stream: TransactionStream<A>,
+        processor: &mut TransactionProcessor<A, Arc<M>, Arc<T>>,
+    ) -> Result<(), Option<usize>> {
+        let run = &*self.run;
+        let checkpoint = self.checkpoint.as_ref();
+        let registry = &run.registry;
+        let dead_letter_sink = run.dead_letter_sink.as_deref();
+        let error_log = run.error_log.as_deref();
+
+        while let Some(result) = stream.next().await {
+            if let Some(checkpoint) = checkpoint {
+                checkpoint.save_if_due();
+            }
+            let error = {
+                // Checkpoints and snapshots wait until this record has been fully applied
+                let _consumed = checkpoint.map(ShardCheckpoint::begin_record);
+                let _applying = run.snapshot_gate.as_deref().map(SnapshotGate::read);
+
+                match result {
+                    Ok(timestamped) => {
+                        let source = timestamped.source;
+                        let timestamp = timestamped.timestamp;
+                        let key = timestamped.idempotency_key;
+                        run.transforms
+                            .iter()
+                            .try_fold(timestamped.transaction, |tx, transform| transform(tx))
+                            .and_then(|tx| {
+                                // Only keep a copy of the transaction when rejects are reported
+                                let rejected = (dead_letter_sink.is_some() || error_log.is_some())
+                                    .then(|| tx.clone());
+                                let e = processor
+                                    .process_transaction_at(tx, key.as_deref(), timestamp)
+                                    .err()?;
+                                report_rejected(
+                                    &e,
+                                    rejected,
+                                    stream_name(registry, source),
+                                    dead_letter_sink,
+                                    error_log,
+                                );
+                                if let Some(tracker) = source.and_then(|index| registry.get(index))
+                                {
+                                    tracker.record_rejected();
+                                }
+                                Some((ProcessingError::Engine(e), source))
+                            })
+                    }
+                    Err(e) => {
+                        // Read errors carry no source; the stream that yielded one last is
+                        // exact unless records are reordered or read ahead (timestamp merging,
+                        // sequencing, buffering, shard concurrency)
+                        let source = Some(run.last_errors[self.shard_id].load(Ordering::Relaxed))
+                            .filter(|&index| registry.get(index).is_some());
+                        report_unreadable(
+                            &e,
+                            stream_name(registry, source),
+                            dead_letter_sink,
+                            error_log,
+                        );
+                        Some((ProcessingError::Io(e), source))
+                    }
+                }
+            };
+
+            // The policy may await, so it runs after the gates are released
+            if let Some((e, source)) = error
+                && !self.policy.handle_error(e).await
+            {
+                return Err(source);
+            }
+
+            if let Some((usage, limit)) = run.memory_budget.as_deref().and_then(|budget| {
+                budget.record_processed(processor.account_manager(), processor.transaction_store())
+            }) && !self
+                .policy
+                .handle_error(ProcessingError::MemoryBudgetExceeded {
+                    used: usage.total_bytes(),
+                    limit,
+                })
+                .await
+            {
+                return Err(None);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Results from processing streams across multiple shards
+#[derive(Debug)]
+pub struct ProcessorResults<A: AmountType> {
+    pub shard_results: Vec<ShardResult>,
+    pub total_streams: usize,
+    /// Account statistics once every shard has finished
+    pub stats: AccountStats<A>,
+    /// IO and engine errors met during the run, in the order each shard met
+    /// them (empty unless `with_error_log` is set)
+    pub errors: Vec<LoggedError<A>>,
+    /// Errors not kept because the error log was full
+    pub errors_dropped: u64,
+}
+
+/// Options every `TransactionProcessor` of a run is built with
+#[derive(Clone)]
+struct EngineOptions<A: AmountType> {
+    allow_admin_ops: bool,
+    skip_locked: bool,
+    quarantine_sink: Option<Arc<dyn QuarantineSink<A>>>,
+    idempotency_keys: Arc<IdempotencyKeys>,
+    order_verifier: Option<Arc<OrderVerifier>>,
+    audit_sink: Option<Arc<dyn AuditSink<A>>>,
+    validators: Vec<Arc<dyn TransactionValidator<A>>>,
+    dispute_policy: DisputePolicy,
+    fee_schedule: Option<Arc<FeeSchedule<A>>>,
+}
+
+impl<A: AmountType> Default for EngineOptions<A> {
+    fn default() -> Self {
+        Self {
+            allow_admin_ops: false,
+            skip_locked: false,
+            quarantine_sink: None,
+            idempotency_keys: Arc::new(IdempotencyKeys::new()),
+            order_verifier: None,
+            audit_sink: None,
+            validators: Vec::new(),
+            dispute_policy: DisputePolicy::default(),
+            fee_schedule: None,
+        }
+    }
+}
+
+impl<A: AmountType> EngineOptions<A> {
+    /// A processor over the run's shared storage
+    fn build<M, T>(
+        &self,
+        account_manager: Arc<M>,
+        transaction_store: Arc<T>,
+    ) -> TransactionProcessor<A, Arc<M>, Arc<T>>
+    where
+        M: ClientAccountManager<A>,
+        T: TransactionStoreManager<A>,
+    {
+        let mut processor = TransactionProcessor::new(account_manager, transaction_store)
+            .with_admin_ops(self.allow_admin_ops)
+            .with_skip_locked(self.skip_locked)
+            .with_idempotency_keys(self.idempotency_keys.clone())
+            .with_dispute_policy(self.dispute_policy);
+        if let Some(sink) = self.audit_sink.clone() {
+            processor = processor.with_audit_sink(sink);
+        }
+        if let Some(sink) = self.quarantine_sink.clone() {
+            processor = processor.with_quarantine_sink(sink);
+        }
+        if let Some(verifier) = self.order_verifier.clone() {
+            processor = processor.with_order_verification(verifier);
+        }
+        if let Some(schedule) = self.fee_schedule.clone() {
+            processor = processor.with_fee_schedule(schedule);
+        }
+        for validator in self.validators.iter().cloned() {
+            processor = processor.with_validator(validator);
+        }
+        processor
+    }
+}
+
+/// Result from processing a single shard
+#[derive(Debug)]
+pub struct ShardResult {
     pub shard_id: usize,
     pub streams_processed: usize,
     pub success: bool,
+    /// Transactions skipped because their account was locked (see `with_skip_locked`)
+    pub locked_skipped: u64,
+    /// Transactions diverted to the quarantine sink (see `with_quarantine_sink`)
+    pub quarantined: u64,
+    /// Transactions skipped as replays of a used idempotency key (see `with_idempotency_keys`)
+    pub idempotent_replays: u64,
+    /// Applied and rejected transactions by type
+    pub by_type: TransactionTypeCounts,
+    /// Outcome of each input stream assigned to this shard
+    pub streams: Vec<StreamResult>,
+    /// Name of the stream whose error aborted the shard, if known
+    pub failed_stream: Option<String>,
+    /// Whether the shard was cancelled after stalling (see `with_cancel_on_stall`)
+    pub stalled: bool,
+}
+
+impl ShardResult {
+    /// Result of a shard that had nothing to process
+    pub fn empty(shard_id: usize) -> Self {
+        Self {
+            shard_id,
+            streams_processed: 0,
+            success: true,
+            locked_skipped: 0,
+            quarantined: 0,
+            idempotent_replays: 0,
+            by_type: TransactionTypeCounts::default(),
+            streams: Vec::new(),
+            failed_stream: None,
+            stalled: false,
+        }
+    }
+
+    /// Result of a shard that failed before processing anything
+    fn failed(shard_id: usize) -> Self {
+        Self {
+            success: false,
+            ..Self::empty(shard_id)
+        }
+    }
+}
+
+/// Counters a shard's processors report in its `ShardResult`
+#[derive(Default)]
+struct ShardCounters {
+    locked_skipped: u64,
+    quarantined: u64,
+    idempotent_replays: u64,
+    by_type: TransactionTypeCounts,
+}
+
+impl ShardCounters {
+    fn of<A, M, T>(processor: &TransactionProcessor<A, M, T>) -> Self
+    where
+        A: AmountType,
+        M: ClientAccountManager<A>,
+        T: TransactionStoreManager<A>,
+    {
+        Self {
+            locked_skipped: processor.locked_skipped(),
+            quarantined: processor.quarantined(),
+            idempotent_replays: processor.idempotent_replays(),
+            by_type: processor.type_counts().clone(),
+        }
+    }
+
+    /// Add another lane's counters
+    fn merge(&mut self, other: Self) {
+        self.locked_skipped += other.locked_skipped;
+        self.quarantined += other.quarantined;
+        self.idempotent_replays += other.idempotent_replays;
+        self.by_type.merge(&other.by_type);
+    }
+}
+
+impl<A: AmountType> ProcessorResults<A> {
+    /// Check if all shards processed successfully
+    pub fn all_succeeded(&self) -> bool {
+        self.shard_results.iter().all(|r| r.success)
+    }
+
+    /// Get total number of shards
+    pub fn total_shards(&self) -> usize {
+        self.shard_results.len()
+    }
+
+    /// Transactions skipped on locked accounts across all shards
+    pub fn locked_skipped(&self) -> u64 {
+        self.shard_results.iter().map(|r| r.locked_skipped).sum()
+    }
+
+    /// Transactions diverted to the quarantine sink across all shards
+    pub fn quarantined(&self) -> u64 {
+        self.shard_results.iter().map(|r| r.quarantined).sum()
+    }
+
+    /// Transactions skipped as idempotent replays across all shards
+    pub fn idempotent_replays(&self) -> u64 {
+        self.shard_results
+            .iter()
+            .map(|r| r.idempotent_replays)
+            .sum()
+    }
+
+    /// Applied and rejected transactions by type across all shards
+    pub fn by_type(&self) -> TransactionTypeCounts {
+        let mut counts = TransactionTypeCounts::default();
+        for result in &self.shard_results {
+            counts.merge(&result.by_type);
+        }
+        counts
+    }
+
+    /// Outcome of every input stream, grouped by shard
+    pub fn streams(&self) -> impl Iterator<Item = &StreamResult> {
+        self.shard_results.iter().flat_map(|r| &r.streams)
+    }
+
+    /// Outcome of the stream added under `name`
+    pub fn stream(&self, name: &str) -> Option<&StreamResult> {
+        self.streams().find(|stream| stream.name == name)
+    }
+
+    /// Names of the streams whose errors aborted a shard
+    pub fn failed_streams(&self) -> impl Iterator<Item = &str> {
+        self.shard_results
+            .iter()
+            .filter_map(|r| r.failed_stream.as_deref())
+    }
+
+    /// Ids of the shards cancelled after stalling
+    pub fn stalled_shards(&self) -> impl Iterator<Item = usize> {
+        self.shard_results
+            .iter()
+            .filter(|r| r.stalled)
+            .map(|r| r.shard_id)
+    }
 }
 
-impl ProcessorResults {
-    /// Check if all shards processed successfully
-    pub fn all_succeeded(&self) -> bool {
-        self.shard_results.iter().all(|r| r.success)
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ClientAccount, ClientId, FixedPoint, TransactionId};
+    use crate::engine::{OrderingReport, TypeCount};
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager, ConcurrentTransactionStore};
+    use crate::streaming::error::{AbortOnError, Callback, SilentSkip, SkipErrors};
+    use futures::stream;
+
+    #[tokio::test]
+    async fn processes_single_stream() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let transactions = vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            Ok(Transaction::Deposit {
+                client_id: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(20_000),
+                currency: None,
+            }),
+        ];
+
+        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+            .add_stream(stream::iter(transactions))
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.total_streams, 1);
+
+        let entry1 = account_manager.entry(1).unwrap();
+        assert_eq!(entry1.read().available(), FixedPoint::from_raw(10_000));
+
+        let entry2 = account_manager.entry(2).unwrap();
+        assert_eq!(entry2.read().available(), FixedPoint::from_raw(20_000));
+    }
+
+    #[tokio::test]
+    async fn processes_multiple_streams_merged() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let stream1 = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+        ]);
+
+        let stream2 = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(20_000),
+                currency: None,
+            }),
+        ]);
+
+        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+            .with_stream_combinator(StreamCombinator::Merge)
+            .add_stream(stream1)
+            .add_stream(stream2)
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.total_streams, 2);
+
+        let entry1 = account_manager.entry(1).unwrap();
+        assert_eq!(entry1.read().available(), FixedPoint::from_raw(10_000));
+
+        let entry2 = account_manager.entry(2).unwrap();
+        assert_eq!(entry2.read().available(), FixedPoint::from_raw(20_000));
+    }
+
+    #[tokio::test]
+    async fn processes_multiple_streams_chained() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let stream1 = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+        ]);
+
+        let stream2 = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(5_000),
+                currency: None,
+            }),
+        ]);
+
+        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+            .with_stream_combinator(StreamCombinator::Chain)
+            .add_stream(stream1)
+            .add_stream(stream2)
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+
+        let entry1 = account_manager.entry(1).unwrap();
+        assert_eq!(entry1.read().available(), FixedPoint::from_raw(15_000));
+    }
+
+    #[tokio::test]
+    async fn processes_with_multiple_shards() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let stream1 = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+        ]);
+
+        let stream2 = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(20_000),
+                currency: None,
+            }),
+        ]);
+
+        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+            .with_shards(2)
+            .add_stream(stream1)
+            .add_stream(stream2)
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.total_shards(), 2);
+
+        let entry1 = account_manager.entry(1).unwrap();
+        assert_eq!(entry1.read().available(), FixedPoint::from_raw(10_000));
+
+        let entry2 = account_manager.entry(2).unwrap();
+        assert_eq!(entry2.read().available(), FixedPoint::from_raw(20_000));
+    }
+
+    #[tokio::test]
+    async fn owned_backends_are_shared_across_shards() {
+        // Neither backend is Clone; the processor shares them between shards itself
+        let account_manager = ConcurrentAccountManager::<FixedPoint>::new();
+        let query = account_manager.query_handle();
+
+        let stream1 = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            Ok(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            }),
+        ]);
+        let stream2 = stream::iter(vec![Ok(Transaction::Deposit {
+            client_id: 2,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(20_000),
+            currency: None,
+        })]);
+
+        let results = StreamProcessor::new(
+            account_manager,
+            ConcurrentTransactionStore::new(),
+            AbortOnError,
+        )
+        .with_shards(2)
+        .add_stream(stream1)
+        .add_stream(stream2)
+        .process()
+        .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(
+            query.balance(1).map(|b| b.held),
+            Some(FixedPoint::from_raw(10_000))
+        );
+        assert_eq!(
+            query.balance(2).map(|b| b.available),
+            Some(FixedPoint::from_raw(20_000))
+        );
+    }
+
+    #[tokio::test]
+    async fn stalled_shard_is_cancelled_and_others_finish() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let deposit = |client_id, tx_id| {
+            Ok(Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+        };
+        // The first stream hangs after its deposit, as a dead connection would
+        let hanging = stream::iter(vec![deposit(1, 1)]).chain(stream::pending());
+        let healthy = stream::iter(vec![deposit(2, 2), deposit(2, 3)]);
+
+        let results = StreamProcessor::new(
+            account_manager.clone(),
+            ConcurrentTransactionStore::new(),
+            AbortOnError,
+        )
+        .with_shards(2)
+        .with_stall_timeout(Duration::from_millis(50))
+        .with_cancel_on_stall(true)
+        .add_stream(hanging)
+        .add_stream(healthy)
+        .process()
+        .await;
+
+        assert!(!results.all_succeeded());
+        assert_eq!(results.stalled_shards().collect::<Vec<_>>(), vec![0]);
+        assert!(results.shard_results[1].success);
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(10_000)
+        );
+        assert_eq!(
+            account_manager.entry(2).unwrap().read().available(),
+            FixedPoint::from_raw(20_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_policy_rides_out_transient_errors_under_abort() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+        let deposit = |tx_id| {
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+        };
+        let dropped = || {
+            Err(IoError::Io(std::io::Error::from(
+                std::io::ErrorKind::TimedOut,
+            )))
+        };
+        let transactions = vec![deposit(1), dropped(), dropped(), deposit(2)];
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_retry_policy(
+                RetryPolicy::new(2)
+                    .with_backoff(Duration::from_millis(1), Duration::from_millis(1)),
+            )
+            .add_stream(stream::iter(transactions))
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(20_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn global_rate_limit_is_shared_by_all_shards() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+        let deposits = |client_id: ClientId| {
+            stream::iter((0..13).map(move |i| {
+                Ok(Transaction::Deposit {
+                    client_id,
+                    tx_id: crate::domain::TransactionId::from(client_id) * 100 + i,
+                    amount: FixedPoint::from_raw(1),
+                    currency: None,
+                })
+            }))
+        };
+        let started = std::time::Instant::now();
+
+        // 26 records at 20/s: 20 from the full bucket, then 6 more at 50ms each
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_shards(2)
+            .with_rate_limit(20)
+            .add_stream(deposits(1))
+            .add_stream(deposits(2))
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert!(started.elapsed() >= std::time::Duration::from_millis(250));
+        assert_eq!(
+            account_manager.entry(2).unwrap().read().available(),
+            FixedPoint::from_raw(13)
+        );
+    }
+
+    #[tokio::test]
+    async fn exceeded_memory_budget_asks_the_error_policy() {
+        // Returns whether every shard succeeded, the balance and the budget's estimate
+        async fn run(continue_over_budget: bool) -> (bool, FixedPoint, u64) {
+            let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+            let budget = Arc::new(MemoryBudget::new(1).with_check_interval(5));
+            let policy = Callback::new(move |e| {
+                continue_over_budget || !matches!(e, ProcessingError::MemoryBudgetExceeded { .. })
+            });
+            let deposits = stream::iter((1..=20).map(|i| {
+                Ok(Transaction::Deposit {
+                    client_id: 1,
+                    tx_id: i,
+                    amount: FixedPoint::from_raw(1),
+                    currency: None,
+                })
+            }));
+
+            let results = StreamProcessor::new(
+                account_manager.clone(),
+                Arc::new(ConcurrentTransactionStore::new()),
+                policy,
+            )
+            .with_memory_budget(budget.clone())
+            .add_stream(deposits)
+            .process()
+            .await;
+
+            let available = account_manager.entry(1).unwrap().read().available();
+            (results.all_succeeded(), available, budget.used_bytes())
+        }
+
+        let (succeeded, available, used) = run(false).await;
+        assert!(!succeeded);
+        assert_eq!(available, FixedPoint::from_raw(5));
+        assert!(used > 0);
+
+        let (succeeded, available, _) = run(true).await;
+        assert!(succeeded);
+        assert_eq!(available, FixedPoint::from_raw(20));
+    }
+
+    #[tokio::test]
+    async fn periodic_delta_snapshots_cover_each_change_once() {
+        /// Client ids written in each snapshot
+        struct RecordingSink(Arc<std::sync::Mutex<Vec<Vec<ClientId>>>>);
+
+        #[async_trait::async_trait]
+        impl SnapshotSink<FixedPoint> for RecordingSink {
+            async fn begin(&mut self) -> Result<(), IoError> {
+                self.0.lock().unwrap().push(Vec::new());
+                Ok(())
+            }
+
+            async fn write_account(
+                &mut self,
+                account: &ClientAccount<FixedPoint>,
+            ) -> Result<(), IoError> {
+                let mut snapshots = self.0.lock().unwrap();
+                snapshots.last_mut().unwrap().push(account.client_id());
+                Ok(())
+            }
+
+            async fn finish(&mut self) -> Result<(), IoError> {
+                Ok(())
+            }
+        }
+
+        let snapshots = Arc::new(std::sync::Mutex::new(Vec::new()));
+        // One deposit per client, arriving more slowly than snapshots are taken
+        let deposits = stream::iter(1..=12).then(|client_id| async move {
+            tokio::time::sleep(Duration::from_millis(15)).await;
+            Ok(Transaction::Deposit {
+                client_id,
+                tx_id: client_id as TransactionId,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+        });
+
+        let results = StreamProcessor::new(
+            Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+            Arc::new(ConcurrentTransactionStore::new()),
+            SkipErrors,
+        )
+        .with_periodic_snapshot(Duration::from_millis(40), RecordingSink(snapshots.clone()))
+        .with_snapshot_mode(SnapshotMode::Delta)
+        .add_stream(deposits)
+        .process()
+        .await;
+
+        assert!(results.all_succeeded());
+        let snapshots = snapshots.lock().unwrap();
+        assert!(snapshots.len() >= 2);
+        let mut written: Vec<_> = snapshots.iter().flatten().copied().collect();
+        let count = written.len();
+        written.sort_unstable();
+        written.dedup();
+        assert_eq!(written.len(), count);
+        assert!(written.iter().all(|client_id| (1..=12).contains(client_id)));
+    }
+
+    #[tokio::test]
+    async fn actor_sharding_keeps_client_order_across_streams() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let deposits = stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            Ok(Transaction::Deposit {
+                client_id: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(20_000),
+                currency: None,
+            }),
+        ]);
+        // Only valid once the first stream's deposits have been applied
+        let follow_ups = stream::iter(vec![
+            Ok(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 3,
+                amount: FixedPoint::from_raw(4_000),
+                currency: None,
+            }),
+            Ok(Transaction::Dispute {
+                client_id: 2,
+                tx_id: 2,
+            }),
+        ]);
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_shards(2)
+            .with_execution_model(ExecutionModel::ActorSharded)
+            .with_stream_combinator(StreamCombinator::Chain)
+            .add_stream_named("deposits", deposits)
+            .add_stream_named("follow-ups", follow_ups)
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.total_shards(), 2);
+        assert_eq!(results.stream("follow-ups").map(|s| s.records), Some(2));
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(6_000)
+        );
+        assert_eq!(
+            account_manager.entry(2).unwrap().read().held(),
+            FixedPoint::from_raw(20_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn shard_snapshot_writes_a_part_per_shard() {
+        /// Client ids written in each part, and whether the snapshot finished
+        #[derive(Default)]
+        struct Parts {
+            current: Vec<ClientId>,
+            written: Vec<Vec<ClientId>>,
+            finished: bool,
+        }
+        struct RecordingSink(Arc<std::sync::Mutex<Parts>>);
+
+        #[async_trait::async_trait]
+        impl SnapshotSink<FixedPoint> for RecordingSink {
+            async fn write_account(
+                &mut self,
+                account: &ClientAccount<FixedPoint>,
+            ) -> Result<(), IoError> {
+                self.0.lock().unwrap().current.push(account.client_id());
+                Ok(())
+            }
+
+            async fn end_partition(&mut self) -> Result<(), IoError> {
+                let mut parts = self.0.lock().unwrap();
+                let part = std::mem::take(&mut parts.current);
+                parts.written.push(part);
+                Ok(())
+            }
+
+            async fn finish(&mut self) -> Result<(), IoError> {
+                self.0.lock().unwrap().finished = true;
+                Ok(())
+            }
+        }
+
+        async fn run(model: ExecutionModel) -> Parts {
+            let parts = Arc::new(std::sync::Mutex::new(Parts::default()));
+            let deposits = stream::iter((1..=6).map(|client_id| {
+                Ok(Transaction::Deposit {
+                    client_id,
+                    tx_id: client_id as TransactionId,
+                    amount: FixedPoint::from_raw(10_000),
+                    currency: None,
+                })
+            }));
+
+            let results = StreamProcessor::new(
+                Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+                Arc::new(ConcurrentTransactionStore::new()),
+                AbortOnError,
+            )
+            .with_shards(2)
+            .with_execution_model(model)
+            .with_shard_snapshot(RecordingSink(parts.clone()))
+            .add_stream(deposits)
+            .process()
+            .await;
+
+            assert!(results.all_succeeded());
+            Arc::try_unwrap(parts).ok().unwrap().into_inner().unwrap()
+        }
+
+        let mut parts = run(ExecutionModel::ActorSharded).await;
+        assert!(parts.finished);
+        parts.written.sort();
+        assert_eq!(parts.written, vec![vec![1, 3, 5], vec![2, 4, 6]]);
+
+        // Shared storage writes the same parts, in shard order, once every shard is done
+        let parts = run(ExecutionModel::SharedStorage).await;
+        assert!(parts.finished);
+        assert_eq!(parts.written, vec![vec![2, 4, 6], vec![1, 3, 5]]);
+    }
+
+    #[tokio::test]
+    async fn shard_concurrency_keeps_each_clients_order() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        // Every withdrawal needs the client's earlier deposit
+        let transactions: Vec<_> = (1..=50)
+            .flat_map(|client_id| {
+                let tx_id = TransactionId::from(client_id) * 2;
+                [
+                    Ok(Transaction::Deposit {
+                        client_id,
+                        tx_id,
+                        amount: FixedPoint::from_raw(10_000),
+                        currency: None,
+                    }),
+                    Ok(Transaction::Withdrawal {
+                        client_id,
+                        tx_id: tx_id + 1,
+                        amount: FixedPoint::from_raw(4_000),
+                        currency: None,
+                    }),
+                ]
+            })
+            .collect();
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_shard_concurrency(4)
+            .add_stream_named("mixed", stream::iter(transactions))
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.stream("mixed").map(|s| s.records), Some(100));
+        for client_id in 1..=50 {
+            assert_eq!(
+                account_manager.entry(client_id).unwrap().read().available(),
+                FixedPoint::from_raw(6_000)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn shard_concurrency_reports_an_aborted_lane() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let transactions = vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            // Insufficient funds
+            Ok(Transaction::Withdrawal {
+                client_id: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+        ];
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_shard_concurrency(2)
+            .add_stream_named("mixed", stream::iter(transactions))
+            .process()
+            .await;
+
+        assert!(!results.all_succeeded());
+        assert_eq!(results.failed_streams().collect::<Vec<_>>(), vec!["mixed"]);
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(10_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn merge_by_timestamp_applies_global_time_order() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let timestamped = |tx, ts| Ok(TimestampedTransaction::new(tx, Some(ts)));
+
+        // The withdrawal only succeeds if the second stream's deposit is applied first
+        let stream1 = stream::iter(vec![
+            timestamped(
+                Transaction::Deposit {
+                    client_id: 1,
+                    tx_id: 1,
+                    amount: FixedPoint::from_raw(10_000),
+                    currency: None,
+                },
+                1,
+            ),
+            timestamped(
+                Transaction::Withdrawal {
+                    client_id: 1,
+                    tx_id: 3,
+                    amount: FixedPoint::from_raw(15_000),
+                    currency: None,
+                },
+                3,
+            ),
+        ]);
+
+        let stream2 = stream::iter(vec![timestamped(
+            Transaction::Deposit {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            },
+            2,
+        )]);
+
+        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+            .with_stream_combinator(StreamCombinator::MergeByTimestamp)
+            .add_timestamped_stream(stream1)
+            .add_timestamped_stream(stream2)
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+
+        let entry = account_manager.entry(1).unwrap();
+        assert_eq!(entry.read().available(), FixedPoint::from_raw(5_000));
+    }
+
+    #[tokio::test]
+    async fn admin_ops_pass_through_when_enabled() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let lock_and_unlock = || {
+            stream::iter(vec![
+                Ok(Transaction::Deposit {
+                    client_id: 1,
+                    tx_id: 1,
+                    amount: FixedPoint::from_raw(10_000),
+                    currency: None,
+                }),
+                Ok(Transaction::Dispute {
+                    client_id: 1,
+                    tx_id: 1,
+                }),
+                Ok(Transaction::Chargeback {
+                    client_id: 1,
+                    tx_id: 1,
+                }),
+                Ok(Transaction::Unlock { client_id: 1 }),
+            ])
+        };
+
+        let results = StreamProcessor::new(account_manager.clone(), store.clone(), AbortOnError)
+            .add_stream(lock_and_unlock())
+            .process()
+            .await;
+        assert!(!results.all_succeeded());
+        assert!(account_manager.entry(1).unwrap().read().is_locked());
+
+        let admin_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let admin_store = Arc::new(ConcurrentTransactionStore::new());
+        let results = StreamProcessor::new(admin_manager.clone(), admin_store, AbortOnError)
+            .with_admin_ops(true)
+            .add_stream(lock_and_unlock())
+            .process()
+            .await;
+        assert!(results.all_succeeded());
+        assert!(!admin_manager.entry(1).unwrap().read().is_locked());
+    }
+
+    #[tokio::test]
+    async fn dead_letter_sink_receives_rejects_under_silent_skip() {
+        use crate::streaming::dead_letter::{DeadLetter, DeadLetterSink};
+        use parking_lot::Mutex;
+
+        #[derive(Default)]
+        struct Collect(Mutex<Vec<DeadLetter<FixedPoint>>>);
+
+        impl DeadLetterSink<FixedPoint> for Collect {
+            fn record(&self, letter: DeadLetter<FixedPoint>) {
+                self.0.lock().push(letter);
+            }
+        }
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+        let sink = Arc::new(Collect::default());
+
+        let overdraw = Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(50_000),
+            currency: None,
+        };
+        let transactions = vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            Ok(overdraw.clone()),
+            Err(IoError::InvalidTransactionType("refund".to_string())),
+        ];
+
+        let results = StreamProcessor::new(account_manager, store, SilentSkip)
+            .with_dead_letter_sink(sink.clone())
+            .add_stream(stream::iter(transactions))
+            .process()
+            .await;
+        assert!(results.all_succeeded());
+
+        let letters = sink.0.lock();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].transaction, Some(overdraw));
+        assert_eq!(letters[1].transaction, None);
+        assert_eq!(letters[1].reason, "Invalid transaction type: refund");
+    }
+
+    #[tokio::test]
+    async fn error_log_returns_errors_with_context() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let dispute = Transaction::Dispute {
+            client_id: 1,
+            tx_id: 9,
+        };
+        let transactions = vec![
+            Ok(dispute.clone()),
+            Err(IoError::InvalidTransactionType("refund".to_string())),
+            Err(IoError::InvalidTransactionType("payout".to_string())),
+        ];
+
+        let results = StreamProcessor::new(account_manager, store, SilentSkip)
+            .with_error_log(2)
+            .add_stream_named("partnerA", stream::iter(transactions))
+            .process()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.errors.len(), 2);
+        assert_eq!(results.errors_dropped, 1);
+        assert_eq!(results.errors[0].kind, LoggedErrorKind::Engine);
+        assert_eq!(results.errors[0].message, "Transaction not found: 9");
+        assert_eq!(results.errors[0].transaction, Some(dispute));
+        assert_eq!(results.errors[0].stream.as_deref(), Some("partnerA"));
+        assert_eq!(results.errors[1].kind, LoggedErrorKind::Io);
+        assert_eq!(results.errors[1].transaction, None);
+    }
+
+    #[tokio::test]
+    async fn validators_apply_to_every_shard() {
+        use crate::engine::BlockedClients;
+        use std::collections::HashSet;
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let deposit = |client_id, tx_id| {
+            stream::iter(vec![Ok(Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })])
+        };
+
+        StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+            .with_shards(2)
+            .with_validator(Arc::new(BlockedClients(HashSet::from([2, 3]))))
+            .add_stream(deposit(1, 1))
+            .add_stream(deposit(2, 2))
+            .add_stream(deposit(3, 3))
+            .process()
+            .await;
+
+        let total = |client_id| account_manager.entry(client_id).unwrap().read().total();
+        assert_eq!(total(1), FixedPoint::from_raw(10_000));
+        assert_eq!(total(2), FixedPoint::from_raw(0));
+        assert_eq!(total(3), FixedPoint::from_raw(0));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn metrics_cover_every_shard() {
+        use crate::metrics::MetricsRegistry;
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+        let metrics = MetricsRegistry::new();
+
+        let deposit = |client_id, tx_id| {
+            Ok(Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+        };
+        let first = vec![
+            deposit(1, 1),
+            Ok(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            }),
+        ];
+        let second = vec![
+            deposit(2, 2),
+            Err(IoError::InvalidTransactionType("refund".to_string())),
+            Ok(Transaction::Withdrawal {
+                client_id: 2,
+                tx_id: 3,
+                amount: FixedPoint::from_raw(50_000),
+                currency: None,
+            }),
+        ];
+
+        StreamProcessor::new(account_manager, store, SilentSkip)
+            .with_shards(2)
+            .with_metrics(metrics.clone())
+            .add_stream(stream::iter(first))
+            .add_stream(stream::iter(second))
+            .process()
+            .await;
+
+        assert_eq!(metrics.transactions("deposit"), 2);
+        assert_eq!(metrics.transactions("dispute"), 1);
+        assert_eq!(metrics.errors("io"), 1);
+        assert_eq!(metrics.errors("domain"), 1);
+        assert_eq!(metrics.shard_duration().count(), 2);
+        assert_eq!(metrics.accounts(), 2);
+        assert_eq!(metrics.held_total(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn resume_after_crash_applies_each_record_once() {
+        use crate::streaming::Checkpoint;
+        use std::time::Duration;
+
+        let records = || {
+            vec![
+                Ok(Transaction::Deposit {
+                    client_id: 1,
+                    tx_id: 1,
+                    amount: FixedPoint::from_raw(10_000),
+                    currency: None,
+                }),
+                Ok(Transaction::Deposit {
+                    client_id: 1,
+                    tx_id: 2,
+                    amount: FixedPoint::from_raw(20_000),
+                    currency: None,
+                }),
+                Ok(Transaction::Deposit {
+                    client_id: 2,
+                    tx_id: 3,
+                    amount: FixedPoint::from_raw(40_000),
+                    currency: None,
+                }),
+                Ok(Transaction::Dispute {
+                    client_id: 1,
+                    tx_id: 1,
+                }),
+            ]
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.checkpoint");
+
+        // First run stalls after three records and is killed before finishing
+        let first_run = StreamProcessor::new(
+            Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+            Arc::new(ConcurrentTransactionStore::new()),
+            AbortOnError,
+        )
+        .with_checkpoints(&path, 2)
+        .add_stream(stream::iter(records().into_iter().take(3)).chain(stream::pending()))
+        .process();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), first_run)
+                .await
+                .is_err()
+        );
+
+        let checkpoint = Checkpoint::<FixedPoint>::load(&path).unwrap();
+        assert_eq!(checkpoint.offsets(), &[2]);
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let results = StreamProcessor::new(
+            account_manager.clone(),
+            Arc::new(ConcurrentTransactionStore::new()),
+            AbortOnError,
+        )
+        .resume_from(checkpoint)
+        .with_checkpoints(&path, 2)
+        .add_stream(stream::iter(records()))
+        .process()
+        .await;
+        assert!(results.all_succeeded());
+
+        // The dispute of tx 1 relies on the restored transaction store
+        let client_1 = account_manager.entry(1).unwrap().read();
+        assert_eq!(client_1.available(), FixedPoint::from_raw(20_000));
+        assert_eq!(client_1.held(), FixedPoint::from_raw(10_000));
+        let client_2 = account_manager.entry(2).unwrap().read();
+        assert_eq!(client_2.total(), FixedPoint::from_raw(40_000));
+
+        // The final checkpoint covers the whole input
+        assert_eq!(
+            Checkpoint::<FixedPoint>::load(&path).unwrap().offsets(),
+            &[4]
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_seeks_csv_inputs_and_keeps_idempotency_keys() {
+        use crate::io::{CsvReaderOptions, CsvTransactionStream};
+        use crate::streaming::Checkpoint;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("transactions.csv");
+        let path = dir.path().join("run.checkpoint");
+        std::fs::write(
+            &input,
+            "type,client,tx,amount,idempotency_key\ndeposit,1,1,1.0,upload-1\ndeposit,1,2,2.0,\n",
+        )
+        .unwrap();
+
+        StreamProcessor::new(
+            Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+            Arc::new(ConcurrentTransactionStore::new()),
+            AbortOnError,
+        )
+        .with_checkpoints(&path, 1_000)
+        .add_csv_stream(CsvTransactionStream::from_file(&input).await.unwrap())
+        .process()
+        .await;
+        let checkpoint = Checkpoint::<FixedPoint>::load(&path).unwrap();
+        let position = checkpoint.position(0).unwrap().clone();
+        assert_eq!((checkpoint.offsets(), position.record()), (&[2][..], 2));
+
+        // More records land; what was processed is garbled, so reading it again would abort
+        let mut contents = std::fs::read(&input).unwrap();
+        contents[38..position.byte() as usize].fill(b'#');
+        contents.extend_from_slice(b"deposit,1,3,1.0,upload-1\ndeposit,2,4,4.0,\n");
+        std::fs::write(&input, contents).unwrap();
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let resumed =
+            CsvTransactionStream::from_file_at(&input, CsvReaderOptions::default(), &position)
+                .await
+                .unwrap();
+        let results = StreamProcessor::new(
+            account_manager.clone(),
+            Arc::new(ConcurrentTransactionStore::new()),
+            AbortOnError,
+        )
+        .resume_from(checkpoint)
+        .with_checkpoints(&path, 1_000)
+        .add_csv_stream(resumed)
+        .process()
+        .await;
+
+        assert!(results.all_succeeded());
+        // tx 3 replays the key of tx 1, used before the checkpoint
+        assert_eq!(results.idempotent_replays(), 1);
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(30_000)
+        );
+        assert_eq!(
+            account_manager.entry(2).unwrap().read().available(),
+            FixedPoint::from_raw(40_000)
+        );
+        let checkpoint = Checkpoint::<FixedPoint>::load(&path).unwrap();
+        assert_eq!(checkpoint.offsets(), &[4]);
+        assert_eq!(checkpoint.position(0).unwrap().record(), 4);
+    }
+
+    #[tokio::test]
+    async fn resume_keeps_the_credit_limit_of_an_overdrawn_account() {
+        use crate::streaming::Checkpoint;
+
+        let withdrawal = |tx_id, raw| {
+            Ok(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id,
+                amount: FixedPoint::from_raw(raw),
+                currency: None,
+            })
+        };
+        let records = || {
+            vec![
+                Ok(Transaction::SetCreditLimit {
+                    client_id: 1,
+                    limit: FixedPoint::from_raw(50_000),
+                }),
+                withdrawal(1, 30_000),
+                withdrawal(2, 15_000),
+            ]
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.checkpoint");
+
+        // Checkpoint once the account is overdrawn, before the second withdrawal
+        StreamProcessor::new(
+            Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+            Arc::new(ConcurrentTransactionStore::new()),
+            AbortOnError,
+        )
+        .with_admin_ops(true)
+        .with_checkpoints(&path, 2)
+        .add_stream(stream::iter(records().into_iter().take(2)))
+        .process()
+        .await;
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let results = StreamProcessor::new(
+            account_manager.clone(),
+            Arc::new(ConcurrentTransactionStore::new()),
+            AbortOnError,
+        )
+        .with_admin_ops(true)
+        .resume_from(Checkpoint::load(&path).unwrap())
+        .add_stream(stream::iter(records()))
+        .process()
+        .await;
+
+        // The second withdrawal still draws on the restored credit line
+        assert!(results.all_succeeded());
+        let client = account_manager.entry(1).unwrap().read();
+        assert_eq!(client.available(), FixedPoint::from_raw(-45_000));
+        assert_eq!(client.credit_limit(), FixedPoint::from_raw(50_000));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn process_sequential_chains_streams_in_order() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        // The withdrawal only succeeds if the first stream is fully applied first
+        let first = vec![Ok(Transaction::Deposit {
+            client_id: 1,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(30_000),
+            currency: None,
+        })];
+        let second = vec![Ok(Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(20_000),
+            currency: None,
+        })];
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_shards(4)
+            .add_stream(stream::iter(first))
+            .add_stream(stream::iter(second))
+            .process_sequential()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.total_shards(), 1);
+        assert_eq!(results.shard_results[0].streams_processed, 2);
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(10_000)
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn process_sequential_drains_high_priority_streams_first() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        // Added first, but the withdrawal needs the high-priority deposit applied
+        let backfill = vec![Ok(Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 2,
+            amount: FixedPoint::from_raw(20_000),
+            currency: None,
+        })];
+        let live = vec![Ok(Transaction::Deposit {
+            client_id: 1,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(30_000),
+            currency: None,
+        })];
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .add_stream_with_priority(stream::iter(backfill), Priority::Low)
+            .add_stream_with_priority(stream::iter(live), Priority::High)
+            .process_sequential()
+            .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(10_000)
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn process_sequential_stops_at_the_failing_stream() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        // The overdrawing withdrawal aborts the run before the second stream
+        let first = vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            Ok(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(20_000),
+                currency: None,
+            }),
+        ];
+        let second = vec![Ok(Transaction::Deposit {
+            client_id: 2,
+            tx_id: 3,
+            amount: FixedPoint::from_raw(5_000),
+            currency: None,
+        })];
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_error_log(10)
+            .add_stream_named("first", stream::iter(first))
+            .add_stream_named("second", stream::iter(second))
+            .process_sequential()
+            .await;
 
-    /// Get total number of shards
-    pub fn total_shards(&self) -> usize {
-        self.shard_results.len()
+        assert!(!results.all_succeeded());
+        assert_eq!(results.failed_streams().collect::<Vec<_>>(), ["first"]);
+        let first = results.stream("first").unwrap();
+        assert_eq!(
+            (first.records, first.errors, first.completed),
+            (2, 1, false)
+        );
+        let second = results.stream("second").unwrap();
+        assert_eq!((second.records, second.completed), (0, false));
+        assert_eq!(results.errors.len(), 1);
+        assert_eq!(results.errors[0].stream.as_deref(), Some("first"));
+        assert_eq!(
+            account_manager.entry(2).unwrap().read().available(),
+            FixedPoint::zero()
+        );
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::domain::FixedPoint;
-    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager, ConcurrentTransactionStore};
-    use crate::streaming::error::{AbortOnError, SilentSkip, SkipErrors};
-    use futures::stream;
 
     #[tokio::test]
-    async fn processes_single_stream() {
+    async fn skip_locked_keeps_abort_on_error_running() {
         let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
         let store = Arc::new(ConcurrentTransactionStore::new());
 
@@ -384,138 +3643,378 @@ mod tests {
             Ok(Transaction::Deposit {
                 client_id: 1,
                 tx_id: 1,
+                amount: FixedPoint::from_raw(20_000),
+                currency: None,
+            }),
+            Ok(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            }),
+            Ok(Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 1,
+            }),
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 2,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             }),
             Ok(Transaction::Deposit {
                 client_id: 2,
-                tx_id: 2,
-                amount: FixedPoint::from_raw(20_000),
+                tx_id: 3,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
             }),
         ];
 
-        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_skip_locked(true)
             .add_stream(stream::iter(transactions))
             .process()
             .await;
 
         assert!(results.all_succeeded());
-        assert_eq!(results.total_streams, 1);
-
-        let entry1 = account_manager.entry(1).unwrap();
-        assert_eq!(entry1.read().available(), FixedPoint::from_raw(10_000));
-
-        let entry2 = account_manager.entry(2).unwrap();
-        assert_eq!(entry2.read().available(), FixedPoint::from_raw(20_000));
+        assert_eq!(results.locked_skipped(), 1);
+        assert_eq!(
+            account_manager.entry(2).unwrap().read().available(),
+            FixedPoint::from_raw(10_000)
+        );
     }
 
     #[tokio::test]
-    async fn processes_multiple_streams_merged() {
+    async fn quarantine_file_can_be_rerun_after_unlock() {
+        use crate::io::CsvTransactionStream;
+        use crate::streaming::dead_letter::CsvDeadLetterWriter;
+
         let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
         let store = Arc::new(ConcurrentTransactionStore::new());
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let quarantine = Arc::new(CsvDeadLetterWriter::new(file.reopen().unwrap()).unwrap());
 
-        let stream1 = stream::iter(vec![
+        let deposit = |client_id, tx_id, raw| {
             Ok(Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: FixedPoint::from_raw(raw),
+                currency: None,
+            })
+        };
+        let transactions = vec![
+            deposit(1, 1, 20_000),
+            Ok(Transaction::Dispute {
                 client_id: 1,
                 tx_id: 1,
-                amount: FixedPoint::from_raw(10_000),
             }),
-        ]);
-
-        let stream2 = stream::iter(vec![
-            Ok(Transaction::Deposit {
-                client_id: 2,
-                tx_id: 2,
-                amount: FixedPoint::from_raw(20_000),
+            Ok(Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 1,
             }),
-        ]);
+            deposit(1, 2, 10_000),
+            deposit(2, 3, 10_000),
+            deposit(1, 4, 5_000),
+        ];
 
-        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
-            .with_stream_combinator(StreamCombinator::Merge)
-            .add_stream(stream1)
-            .add_stream(stream2)
+        let results = StreamProcessor::new(account_manager.clone(), store.clone(), AbortOnError)
+            .with_skip_locked(true)
+            .with_quarantine_sink(quarantine.clone())
+            .with_shard_concurrency(2)
+            .add_stream(stream::iter(transactions))
             .process()
             .await;
 
         assert!(results.all_succeeded());
-        assert_eq!(results.total_streams, 2);
+        assert_eq!((results.quarantined(), results.locked_skipped()), (2, 0));
+        quarantine.flush().unwrap();
 
-        let entry1 = account_manager.entry(1).unwrap();
-        assert_eq!(entry1.read().available(), FixedPoint::from_raw(10_000));
+        TransactionProcessor::new(account_manager.clone(), store.clone())
+            .with_admin_ops(true)
+            .process_transaction(Transaction::Unlock { client_id: 1 })
+            .unwrap();
+        let rerun = CsvTransactionStream::<FixedPoint>::from_file(file.path())
+            .await
+            .unwrap();
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .add_stream(rerun)
+            .process()
+            .await;
 
-        let entry2 = account_manager.entry(2).unwrap();
-        assert_eq!(entry2.read().available(), FixedPoint::from_raw(20_000));
+        assert!(results.all_succeeded());
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(15_000)
+        );
     }
 
     #[tokio::test]
-    async fn processes_multiple_streams_chained() {
+    async fn shared_idempotency_keys_skip_replays_across_runs() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let keys = Arc::new(IdempotencyKeys::new());
+        // The partner re-uploads the same deposit with a fresh tx id
+        let upload = |tx_id| {
+            let deposit = Transaction::Deposit {
+                client_id: 1,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            };
+            let tx = TimestampedTransaction::from(deposit)
+                .with_idempotency_key(Some("batch-7/row-1".to_string()));
+            stream::iter(vec![Ok(tx)])
+        };
+
+        for (tx_id, replays) in [(1, 0), (2, 1)] {
+            let store = Arc::new(ConcurrentTransactionStore::new());
+            let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+                .with_idempotency_keys(keys.clone())
+                .add_timestamped_stream(upload(tx_id))
+                .process()
+                .await;
+            assert!(results.all_succeeded());
+            assert_eq!(results.idempotent_replays(), replays);
+        }
+
+        assert_eq!(keys.len(), 1);
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(10_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn order_verification_flags_merged_dispute_before_deposit() {
+        async fn run(combinator: StreamCombinator) -> OrderingReport {
+            let deposit = |tx_id| {
+                Ok(Transaction::Deposit {
+                    client_id: 1,
+                    tx_id,
+                    amount: FixedPoint::from_raw(10_000),
+                    currency: None,
+                })
+            };
+            let deposits = stream::iter(vec![deposit(1), deposit(2)]);
+            let disputes = stream::iter(vec![Ok(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 2,
+            })]);
+            let verifier = Arc::new(OrderVerifier::new());
+
+            StreamProcessor::new(
+                Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+                Arc::new(ConcurrentTransactionStore::new()),
+                SilentSkip,
+            )
+            .with_order_verification(verifier.clone())
+            .with_stream_combinator(combinator)
+            .add_stream(deposits)
+            .add_stream(disputes)
+            .process()
+            .await;
+            verifier.ordering_report()
+        }
+
+        assert!(run(StreamCombinator::Chain).await.is_ordered());
+
+        // Merging alternates the streams: deposit 1, dispute of 2, deposit 2
+        let report = run(StreamCombinator::Merge).await;
+        assert_eq!(report.transactions, 3);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].early_kind, "dispute");
+        assert_eq!(report.violations[0].late_position, 2);
+    }
+
+    #[tokio::test]
+    async fn buffered_pipeline_preserves_order_and_stops_on_abort() {
         let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
         let store = Arc::new(ConcurrentTransactionStore::new());
 
-        let stream1 = stream::iter(vec![
+        // 100 deposits, then an overdraft that aborts the shard, then one more deposit
+        let mut transactions: Vec<_> = (1..=100)
+            .map(|tx_id| {
+                Ok(Transaction::Deposit {
+                    client_id: 1,
+                    tx_id,
+                    amount: FixedPoint::from_raw(10_000),
+                    currency: None,
+                })
+            })
+            .collect();
+        transactions.push(Ok(Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 101,
+            amount: FixedPoint::from_raw(10_000_000),
+            currency: None,
+        }));
+        transactions.push(Ok(Transaction::Deposit {
+            client_id: 1,
+            tx_id: 102,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        }));
+
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_buffer_size(4)
+            .add_stream(stream::iter(transactions))
+            .process()
+            .await;
+
+        assert!(!results.all_succeeded());
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::from_raw(1_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn callback_policy_skips_first_errors_then_aborts() {
+        use std::sync::atomic::AtomicUsize;
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        // Disputes of unknown transactions fail; the third one aborts
+        let mut transactions: Vec<_> = (1..=3)
+            .map(|tx_id| {
+                Ok(Transaction::Dispute {
+                    client_id: 1,
+                    tx_id,
+                })
+            })
+            .collect();
+        transactions.push(Ok(Transaction::Deposit {
+            client_id: 1,
+            tx_id: 10,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        }));
+
+        let errors = Arc::new(AtomicUsize::new(0));
+        let seen = errors.clone();
+        let policy = Callback::new(move |_| seen.fetch_add(1, Ordering::Relaxed) < 2);
+
+        let results = StreamProcessor::new(account_manager.clone(), store, policy)
+            .add_stream(stream::iter(transactions))
+            .process()
+            .await;
+
+        assert!(!results.all_succeeded());
+        assert_eq!(errors.load(Ordering::Relaxed), 3);
+        assert_eq!(
+            account_manager.entry(1).unwrap().read().available(),
+            FixedPoint::zero()
+        );
+    }
+
+    #[tokio::test]
+    async fn results_include_final_account_stats() {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::new());
+
+        let first = vec![
             Ok(Transaction::Deposit {
                 client_id: 1,
                 tx_id: 1,
-                amount: FixedPoint::from_raw(10_000),
+                amount: FixedPoint::from_raw(30_000),
+                currency: None,
             }),
-        ]);
-
-        let stream2 = stream::iter(vec![
-            Ok(Transaction::Deposit {
+            Ok(Transaction::Dispute {
                 client_id: 1,
+                tx_id: 1,
+            }),
+        ];
+        let second = vec![
+            Ok(Transaction::Deposit {
+                client_id: 2,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(20_000),
+                currency: None,
+            }),
+            Ok(Transaction::Dispute {
+                client_id: 2,
+                tx_id: 2,
+            }),
+            Ok(Transaction::Chargeback {
+                client_id: 2,
                 tx_id: 2,
+            }),
+            Ok(Transaction::Deposit {
+                client_id: 3,
+                tx_id: 3,
                 amount: FixedPoint::from_raw(5_000),
+                currency: None,
             }),
-        ]);
+        ];
 
-        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
-            .with_stream_combinator(StreamCombinator::Chain)
-            .add_stream(stream1)
-            .add_stream(stream2)
+        let results = StreamProcessor::new(account_manager, store, AbortOnError)
+            .with_shards(2)
+            .add_stream(stream::iter(first))
+            .add_stream(stream::iter(second))
             .process()
             .await;
 
         assert!(results.all_succeeded());
-
-        let entry1 = account_manager.entry(1).unwrap();
-        assert_eq!(entry1.read().available(), FixedPoint::from_raw(15_000));
+        assert_eq!(results.stats.accounts, 3);
+        assert_eq!(results.stats.locked, 1);
+        assert_eq!(results.stats.disputed, 1);
+        assert_eq!(results.stats.total_available, FixedPoint::from_raw(5_000));
+        assert_eq!(results.stats.total_held, FixedPoint::from_raw(30_000));
     }
 
     #[tokio::test]
-    async fn processes_with_multiple_shards() {
+    async fn transforms_filter_and_rewrite_in_order() {
+        use crate::streaming::TransactionFilter;
+
         let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
         let store = Arc::new(ConcurrentTransactionStore::new());
 
-        let stream1 = stream::iter(vec![
+        let transactions = vec![
             Ok(Transaction::Deposit {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            Ok(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
             }),
-        ]);
-
-        let stream2 = stream::iter(vec![
             Ok(Transaction::Deposit {
                 client_id: 2,
                 tx_id: 2,
-                amount: FixedPoint::from_raw(20_000),
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
             }),
-        ]);
+        ];
 
-        let results = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
-            .with_shards(2)
-            .add_stream(stream1)
-            .add_stream(stream2)
+        let filter = TransactionFilter::new().without_types(["dispute"]);
+        let results = StreamProcessor::new(account_manager.clone(), store, AbortOnError)
+            .with_transform(move |tx| filter.apply(tx))
+            .with_transform(|tx| match tx {
+                Transaction::Deposit {
+                    client_id,
+                    tx_id,
+                    amount,
+                    currency,
+                } => Some(Transaction::Deposit {
+                    client_id,
+                    tx_id,
+                    amount: amount + amount,
+                    currency,
+                }),
+                tx => Some(tx),
+            })
+            .add_stream(stream::iter(transactions))
             .process()
             .await;
-
         assert!(results.all_succeeded());
-        assert_eq!(results.total_shards(), 2);
-
-        let entry1 = account_manager.entry(1).unwrap();
-        assert_eq!(entry1.read().available(), FixedPoint::from_raw(10_000));
 
-        let entry2 = account_manager.entry(2).unwrap();
-        assert_eq!(entry2.read().available(), FixedPoint::from_raw(20_000));
+        let account = account_manager.entry(1).unwrap().read();
+        assert_eq!(account.available(), FixedPoint::from_raw(20_000));
+        assert_eq!(account.held(), FixedPoint::from_raw(0));
+        assert_eq!(
+            account_manager.entry(2).unwrap().read().total(),
+            FixedPoint::from_raw(20_000)
+        );
     }
 
     #[tokio::test]
@@ -528,12 +4027,14 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             }),
             Err(IoError::InvalidTransactionType("invalid".to_string())),
             Ok(Transaction::Deposit {
                 client_id: 2,
                 tx_id: 2,
                 amount: FixedPoint::from_raw(20_000),
+                currency: None,
             }),
         ];
 
@@ -561,12 +4062,14 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             }),
             Err(IoError::InvalidTransactionType("invalid".to_string())),
             Ok(Transaction::Deposit {
                 client_id: 2,
                 tx_id: 2,
                 amount: FixedPoint::from_raw(20_000),
+                currency: None,
             }),
         ];
 
@@ -596,17 +4099,20 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             }),
             // Try to withdraw more than available (will fail)
             Ok(Transaction::Withdrawal {
                 client_id: 1,
                 tx_id: 2,
                 amount: FixedPoint::from_raw(20_000),
+                currency: None,
             }),
             Ok(Transaction::Deposit {
                 client_id: 2,
                 tx_id: 3,
                 amount: FixedPoint::from_raw(5_000),
+                currency: None,
             }),
         ];
 
@@ -636,17 +4142,20 @@ mod tests {
                 client_id: 1,
                 tx_id: 1,
                 amount: FixedPoint::from_raw(10_000),
+                currency: None,
             }),
             // Try to withdraw more than available (will fail)
             Ok(Transaction::Withdrawal {
                 client_id: 1,
                 tx_id: 2,
                 amount: FixedPoint::from_raw(20_000),
+                currency: None,
             }),
             Ok(Transaction::Deposit {
                 client_id: 2,
                 tx_id: 3,
                 amount: FixedPoint::from_raw(5_000),
+                currency: None,
             }),
         ];
 
@@ -693,4 +4202,232 @@ mod tests {
         assert_eq!(results.total_streams, 0);
         assert_eq!(results.total_shards(), 0);
     }
+
+    #[tokio::test]
+    async fn streams_added_while_running_are_processed() {
+        let deposit = |client_id, tx_id: crate::domain::TransactionId| {
+            Ok(Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+        };
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+
+        // The first stream stays open until told to finish
+        let (first_tx, first_rx) = mpsc::unbounded_channel();
+        let first = stream::unfold(first_rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        });
+        let handle = StreamProcessor::new(
+            account_manager.clone(),
+            Arc::new(ConcurrentTransactionStore::new()),
+            SkipErrors,
+        )
+        .with_shards(2)
+        .add_stream_named("initial", first)
+        .process_detached();
+
+        assert!(
+            handle.add_stream_named("late", stream::iter(vec![deposit(2, 20), deposit(2, 21)]))
+        );
+        assert!(handle.add_stream(stream::iter(vec![deposit(3, 30)])));
+        first_tx.send(deposit(1, 10)).unwrap();
+        drop(first_tx);
+
+        let results = handle.finish().await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.total_streams, 3);
+        assert_eq!(results.stream("late").map(|s| s.records), Some(2));
+        assert!(results.stream("stream-2").is_some_and(|s| s.completed));
+        let streams_per_shard: Vec<_> = results
+            .shard_results
+            .iter()
+            .map(|shard| shard.streams_processed)
+            .collect();
+        assert_eq!(streams_per_shard, vec![2, 1]);
+        for client_id in [1, 2, 3] {
+            assert!(account_manager.account(client_id).is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn named_streams_report_counts_completion_and_failures() {
+        let deposit = |client_id, tx_id| {
+            Ok(Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+        };
+        let overdraft = Ok(Transaction::Withdrawal {
+            client_id: 2,
+            tx_id: 21,
+            amount: FixedPoint::from_raw(50_000),
+            currency: None,
+        });
+
+        let results = StreamProcessor::new(
+            Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+            Arc::new(ConcurrentTransactionStore::new()),
+            AbortOnError,
+        )
+        .with_shards(3)
+        .add_stream_named(
+            "partnerA",
+            stream::iter(vec![deposit(1, 10), deposit(1, 11)]),
+        )
+        .add_stream_named(
+            "partnerB",
+            stream::iter(vec![deposit(2, 20), overdraft, deposit(2, 22)]),
+        )
+        .add_stream(stream::iter(vec![
+            deposit(3, 30),
+            Err(IoError::MissingField("amount".to_string())),
+        ]))
+        .process()
+        .await;
+
+        let partner_a = results.stream("partnerA").unwrap();
+        assert_eq!((partner_a.records, partner_a.errors), (2, 0));
+        assert!(partner_a.completed);
+
+        let partner_b = results.stream("partnerB").unwrap();
+        assert_eq!((partner_b.records, partner_b.errors), (2, 1));
+        assert!(!partner_b.completed);
+
+        let unnamed = results.stream("stream-2").unwrap();
+        assert_eq!((unnamed.records, unnamed.errors), (2, 1));
+
+        let mut failed: Vec<_> = results.failed_streams().collect();
+        failed.sort();
+        assert_eq!(failed, vec!["partnerB", "stream-2"]);
+        assert_eq!(results.streams().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn rejections_are_attributed_to_their_stream_after_merging() {
+        let withdrawal = |tx_id| {
+            Ok(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })
+        };
+
+        let results = StreamProcessor::new(
+            Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+            Arc::new(ConcurrentTransactionStore::new()),
+            SkipErrors,
+        )
+        .add_stream_named(
+            "deposits",
+            stream::iter(vec![Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            })]),
+        )
+        .add_stream_named(
+            "withdrawals",
+            stream::iter(vec![withdrawal(2), withdrawal(3)]),
+        )
+        .process()
+        .await;
+
+        assert!(results.all_succeeded());
+        assert_eq!(results.stream("deposits").unwrap().errors, 0);
+        // Only one of the two withdrawals can be covered by the deposit
+        assert!(results.stream("withdrawals").unwrap().errors >= 1);
+        assert!(results.streams().all(|stream| stream.completed));
+        assert_eq!(results.failed_streams().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn counts_applied_and_rejected_transactions_by_type() {
+        let results = StreamProcessor::new(
+            Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+            Arc::new(ConcurrentTransactionStore::new()),
+            SkipErrors,
+        )
+        .with_shards(2)
+        .add_stream(stream::iter(vec![
+            Ok(Transaction::Deposit {
+                client_id: 1,
+                tx_id: 1,
+                amount: FixedPoint::from_raw(10_000),
+                currency: None,
+            }),
+            Ok(Transaction::Withdrawal {
+                client_id: 1,
+                tx_id: 2,
+                amount: FixedPoint::from_raw(50_000),
+                currency: None,
+            }),
+            Ok(Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            }),
+            Ok(Transaction::Resolve {
+                client_id: 1,
+                tx_id: 99,
+            }),
+        ]))
+        .add_stream(stream::iter(vec![Ok(Transaction::Deposit {
+            client_id: 2,
+            tx_id: 3,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        })]))
+        .process()
+        .await;
+
+        let counts = results.by_type();
+        assert_eq!(
+            counts.get("deposit"),
+            TypeCount {
+                applied: 2,
+                rejected: 0
+            }
+        );
+        assert_eq!(
+            counts.get("withdrawal"),
+            TypeCount {
+                applied: 0,
+                rejected: 1
+            }
+        );
+        assert_eq!(
+            counts.get("dispute"),
+            TypeCount {
+                applied: 1,
+                rejected: 0
+            }
+        );
+        assert_eq!(
+            counts.get("resolve"),
+            TypeCount {
+                applied: 0,
+                rejected: 1
+            }
+        );
+        assert_eq!(
+            counts.total(),
+            TypeCount {
+                applied: 3,
+                rejected: 2
+            }
+        );
+        let per_shard: u64 = results
+            .shard_results
+            .iter()
+            .map(|shard| shard.by_type.total().total())
+            .sum();
+        assert_eq!(per_shard, 5);
+    }
 }
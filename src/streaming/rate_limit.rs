@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use parking_lot::Mutex;
+use tokio::time::Instant;
+
+/// Token bucket refilled at a fixed rate
+///
+/// The bucket holds at most one second's worth of tokens, so after an idle
+/// period up to `per_sec` records pass without waiting.
+pub(crate) struct RateLimiter {
+    per_sec: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `per_sec` acquisitions per second (minimum 1)
+    pub(crate) fn new(per_sec: u32) -> Self {
+        let per_sec = f64::from(per_sec.max(1));
+        Self {
+            per_sec,
+            bucket: Mutex::new(Bucket {
+                tokens: per_sec,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a token is available and take it
+    pub(crate) async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.per_sec);
+                bucket.refilled = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec)
+            };
+            // The lock is released while sleeping, so other shards are not blocked
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Yield items from `stream` no faster than `limiter` allows
+pub(crate) fn throttle<S>(
+    stream: S,
+    limiter: std::sync::Arc<RateLimiter>,
+) -> impl Stream<Item = S::Item> + Send
+where
+    S: Stream + Send,
+    S::Item: Send,
+{
+    stream.then(move |item| {
+        let limiter = limiter.clone();
+        async move {
+            limiter.acquire().await;
+            item
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_once_the_bucket_is_empty() {
+        let limiter = RateLimiter::new(20);
+        let started = Instant::now();
+
+        // The first 20 come out of the full bucket; 5 more take about 250ms
+        for _ in 0..25 {
+            limiter.acquire().await;
+        }
+
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use futures::{Stream, StreamExt, stream};
+use tracing::warn;
+
+use crate::io::IoError;
+
+/// Retries for transient source errors (see `IoError::is_transient`)
+///
+/// A source that fails with a transient error, e.g. a dropped connection,
+/// is polled again after a backoff that starts at `initial_backoff` and
+/// doubles up to `max_backoff`. Only after `max_retries` consecutive
+/// transient failures, or on any permanent error such as a malformed record,
+/// does the error reach the `ErrorPolicy` to be skipped or to abort the run.
+/// A record read successfully resets the count.
+///
+/// Retrying only helps sources that can carry on after a failed read, such
+/// as readers over a network connection that recovers; a source that ends
+/// after its error still has that error reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive transient failures retried before the error is reported
+    pub max_retries: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            max_retries: 3,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retry up to `retries` consecutive transient failures, with the default backoff
+    pub fn new(retries: u32) -> Self {
+        Self {
+            max_retries: retries,
+            ..Self::default()
+        }
+    }
+
+    /// Set the delays between attempts
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Delay before retrying after `failures` consecutive transient failures
+    fn delay(&self, failures: u32) -> Duration {
+        let factor = 1u32 << failures.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Poll `source` again after transient errors, as allowed by `policy`
+pub(crate) fn retry_transient<S, T>(
+    source: S,
+    policy: RetryPolicy,
+) -> impl Stream<Item = Result<T, IoError>> + Send
+where
+    S: Stream<Item = Result<T, IoError>> + Unpin + Send,
+    T: Send,
+{
+    stream::unfold(Some(source), move |source| async move {
+        let mut source = source?;
+        // The latest retried error, reported if the source ends instead of recovering
+        let mut retried = None;
+        let mut failures = 0;
+
+        loop {
+            match source.next().await {
+                Some(Err(e)) if e.is_transient() && failures < policy.max_retries => {
+                    failures += 1;
+                    let delay = policy.delay(failures);
+                    warn!(
+                        "Transient source error ({}); retry {}/{} in {:?}",
+                        e, failures, policy.max_retries, delay
+                    );
+                    retried = Some(e);
+                    tokio::time::sleep(delay).await;
+                }
+                Some(item) => return Some((item, Some(source))),
+                None => return retried.map(|e| (Err(e), None)),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transient() -> Result<u32, IoError> {
+        Err(IoError::Io(std::io::Error::from(
+            std::io::ErrorKind::ConnectionReset,
+        )))
+    }
+
+    fn fast(retries: u32) -> RetryPolicy {
+        RetryPolicy::new(retries).with_backoff(Duration::from_millis(1), Duration::from_millis(2))
+    }
+
+    async fn collect(
+        items: Vec<Result<u32, IoError>>,
+        policy: RetryPolicy,
+    ) -> Vec<Result<u32, IoError>> {
+        retry_transient(stream::iter(items), policy).collect().await
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried_until_the_limit() {
+        let items = vec![
+            Ok(1),
+            transient(),
+            transient(),
+            Ok(2),
+            transient(),
+            transient(),
+            transient(),
+        ];
+
+        let results = collect(items, fast(2)).await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &1);
+        assert_eq!(results[1].as_ref().unwrap(), &2);
+        // The third failure in a row exceeds two retries
+        assert!(results[2].as_ref().unwrap_err().is_transient());
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_pass_straight_through() {
+        let items = vec![Err(IoError::InvalidAmount("x".to_string())), Ok(1)];
+
+        let results = collect(items, fast(5)).await;
+
+        assert!(matches!(results[0], Err(IoError::InvalidAmount(_))));
+        assert_eq!(results[1].as_ref().unwrap(), &1);
+    }
+
+    #[tokio::test]
+    async fn error_before_the_source_ends_is_still_reported() {
+        let results = collect(vec![Ok(1), transient()], fast(3)).await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy::new(10)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(300));
+        let delays: Vec<_> = (1..=4)
+            .map(|failures| policy.delay(failures).as_millis())
+            .collect();
+        assert_eq!(delays, vec![100, 200, 300, 300]);
+    }
+}
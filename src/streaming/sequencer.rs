@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+
+use crate::domain::{AmountType, ClientId, TimestampedTransaction};
+use crate::io::IoError;
+
+type Item<A> = Result<TimestampedTransaction<A>, IoError>;
+
+/// First sequence number expected for every client
+const FIRST_SEQUENCE: u64 = 1;
+
+/// Reorders a combined stream so each client's sequenced transactions apply in order
+///
+/// Transactions carry per-client sequence numbers starting at 1. Out-of-order
+/// arrivals are buffered until their predecessors arrive. When more than
+/// `max_pending` transactions are buffered for one client (or the input ends),
+/// the missing numbers are reported as `IoError::SequenceGap` and processing
+/// resumes at the lowest buffered number. Numbers that were already applied
+/// are reported as `IoError::StaleSequence` and dropped. Transactions without
+/// a sequence number, and errors, pass straight through.
+pub(crate) struct ClientSequencer<A: AmountType, S> {
+    stream: S,
+    max_pending: usize,
+    expected: HashMap<ClientId, u64>,
+    pending: HashMap<ClientId, BTreeMap<u64, TimestampedTransaction<A>>>,
+    ready: VecDeque<Item<A>>,
+    done: bool,
+}
+
+impl<A: AmountType, S> ClientSequencer<A, S>
+where
+    S: Stream<Item = Item<A>> + Unpin,
+{
+    /// Sequence a stream, buffering at most `max_pending` transactions per client
+    pub(crate) fn new(stream: S, max_pending: usize) -> Self {
+        Self {
+            stream,
+            max_pending,
+            expected: HashMap::new(),
+            pending: HashMap::new(),
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn push(&mut self, item: TimestampedTransaction<A>) {
+        let Some(sequence) = item.sequence else {
+            self.ready.push_back(Ok(item));
+            return;
+        };
+
+        let client_id = item.transaction.client_id();
+        let expected = *self.expected.entry(client_id).or_insert(FIRST_SEQUENCE);
+        let pending = self.pending.entry(client_id).or_default();
+
+        if sequence < expected || pending.contains_key(&sequence) {
+            self.ready.push_back(Err(IoError::StaleSequence {
+                client_id,
+                expected,
+                found: sequence,
+            }));
+            return;
+        }
+
+        pending.insert(sequence, item);
+        self.release(client_id, self.max_pending);
+    }
+
+    /// Move every in-order transaction for a client to the ready queue,
+    /// skipping gaps while more than `max_pending` remain buffered
+    fn release(&mut self, client_id: ClientId, max_pending: usize) {
+        let Some(pending) = self.pending.get_mut(&client_id) else {
+            return;
+        };
+        let expected = self.expected.entry(client_id).or_insert(FIRST_SEQUENCE);
+
+        loop {
+            if let Some(item) = pending.remove(expected) {
+                self.ready.push_back(Ok(item));
+                *expected += 1;
+                continue;
+            }
+
+            match pending.first_key_value() {
+                Some((&found, _)) if pending.len() > max_pending => {
+                    self.ready.push_back(Err(IoError::SequenceGap {
+                        client_id,
+                        expected: *expected,
+                        found,
+                    }));
+                    *expected = found;
+                }
+                _ => break,
+            }
+        }
+
+        if pending.is_empty() {
+            self.pending.remove(&client_id);
+        }
+    }
+
+    /// Flush every buffered transaction once the input has ended
+    fn finish(&mut self) {
+        let mut clients: Vec<ClientId> = self.pending.keys().copied().collect();
+        clients.sort_unstable();
+
+        for client_id in clients {
+            self.release(client_id, 0);
+        }
+    }
+}
+
+impl<A: AmountType, S> Stream for ClientSequencer<A, S>
+where
+    S: Stream<Item = Item<A>> + Unpin,
+{
+    type Item = Item<A>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(item) = this.ready.pop_front() {
+                return Poll::Ready(Some(item));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match this.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(item))) => this.push(item),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    this.finish();
+                    this.done = true;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+// Buffered transactions are never pinned, so the sequencer is Unpin whenever its stream is
+impl<A: AmountType, S: Unpin> Unpin for ClientSequencer<A, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{FixedPoint, Transaction, TransactionId};
+    use futures::stream;
+
+    fn deposit(
+        client_id: ClientId,
+        tx_id: TransactionId,
+        sequence: Option<u64>,
+    ) -> Item<FixedPoint> {
+        let tx = Transaction::Deposit {
+            client_id,
+            tx_id,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        };
+        Ok(TimestampedTransaction::from(tx).with_sequence(sequence))
+    }
+
+    async fn sequenced(items: Vec<Item<FixedPoint>>, max_pending: usize) -> Vec<String> {
+        ClientSequencer::new(stream::iter(items), max_pending)
+            .map(|item| match item {
+                Ok(tx) => format!("tx{}", tx.transaction.tx_id().unwrap()),
+                Err(e) => e.to_string(),
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn reorders_each_client_independently() {
+        let items = vec![
+            deposit(1, 12, Some(2)),
+            deposit(2, 21, Some(1)),
+            deposit(1, 11, Some(1)),
+            deposit(1, 13, Some(3)),
+            deposit(3, 30, None),
+        ];
+
+        assert_eq!(
+            sequenced(items, 16).await,
+            vec!["tx21", "tx11", "tx12", "tx13", "tx30"]
+        );
+    }
+
+    #[tokio::test]
+    async fn flags_gap_when_buffer_overflows() {
+        let items = vec![
+            deposit(1, 11, Some(1)),
+            deposit(1, 13, Some(3)),
+            deposit(1, 14, Some(4)),
+        ];
+
+        assert_eq!(
+            sequenced(items, 1).await,
+            vec![
+                "tx11",
+                "Sequence gap for client 1: expected 2, resumed at 3",
+                "tx13",
+                "tx14",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn flags_gap_at_end_of_input() {
+        let items = vec![deposit(1, 12, Some(2))];
+
+        assert_eq!(
+            sequenced(items, 16).await,
+            vec![
+                "Sequence gap for client 1: expected 1, resumed at 2",
+                "tx12"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn drops_stale_and_duplicate_sequence_numbers() {
+        let items = vec![
+            deposit(1, 11, Some(1)),
+            deposit(1, 99, Some(1)),
+            deposit(1, 13, Some(3)),
+            deposit(1, 98, Some(3)),
+            deposit(1, 12, Some(2)),
+        ];
+
+        assert_eq!(
+            sequenced(items, 16).await,
+            vec![
+                "tx11",
+                "Duplicate or stale sequence number for client 1: 1 (expected 2)",
+                "Duplicate or stale sequence number for client 1: 3 (expected 2)",
+                "tx12",
+                "tx13",
+            ]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::domain::{AmountType, ClientAccount};
+use crate::io::{IoError, SnapshotSink};
+use crate::storage::ClientAccountManager;
+
+/// Final snapshot written in parts as shards finish, as configured by
+/// `with_shard_snapshot`
+///
+/// Shards finish in any order, so the sink is shared behind a lock and each
+/// part is written whole. After a failed write the rest of the snapshot is
+/// skipped, since the output is already incomplete.
+pub(crate) struct ShardSnapshots<A: AmountType> {
+    sink: Mutex<Box<dyn SnapshotSink<A>>>,
+    failed: AtomicBool,
+}
+
+impl<A: AmountType> ShardSnapshots<A> {
+    /// Start the snapshot (the sink's `begin`)
+    pub(crate) async fn begin(mut sink: Box<dyn SnapshotSink<A>>) -> Self {
+        let result = sink.begin().await;
+        let snapshots = Self {
+            sink: Mutex::new(sink),
+            failed: AtomicBool::new(false),
+        };
+        snapshots.check(result);
+        snapshots
+    }
+
+    /// Write the accounts selected by `owned`, ordered by client id, as one part
+    pub(crate) async fn write_part<M>(
+        &self,
+        accounts: &M,
+        owned: impl Fn(&ClientAccount<A>) -> bool,
+    ) where
+        M: ClientAccountManager<A>,
+    {
+        let mut part = Vec::new();
+        accounts.for_each_account(&mut |account| {
+            if owned(account) {
+                part.push(account.clone());
+            }
+        });
+        self.write(part).await;
+    }
+
+    /// Write every account as `num_shards` parts, split by `shard_of`, in
+    /// shard order
+    pub(crate) async fn write_parts<M>(&self, accounts: &M, num_shards: usize)
+    where
+        M: ClientAccountManager<A>,
+    {
+        let mut parts = vec![Vec::new(); num_shards];
+        accounts.for_each_account(&mut |account| {
+            parts[shard_of(account, num_shards)].push(account.clone());
+        });
+        for part in parts {
+            self.write(part).await;
+        }
+    }
+
+    /// Write `part`, ordered by client id, followed by the sink's `end_partition`
+    async fn write(&self, mut part: Vec<ClientAccount<A>>) {
+        part.sort_by_key(ClientAccount::client_id);
+
+        let mut sink = self.sink.lock().await;
+        if self.failed.load(Ordering::Relaxed) {
+            return;
+        }
+        let result = async {
+            for account in &part {
+                sink.write_account(account).await?;
+            }
+            sink.end_partition().await
+        }
+        .await;
+        if self.check(result) {
+            debug!(accounts = part.len(), "Snapshot part written");
+        }
+    }
+
+    /// Complete the snapshot (the sink's `finish`)
+    pub(crate) async fn finish(self) {
+        if !self.failed.load(Ordering::Relaxed) {
+            let result = self.sink.into_inner().finish().await;
+            if let Err(e) = result {
+                warn!("Failed to finish shard snapshot: {}", e);
+            }
+        }
+    }
+
+    /// Whether the write succeeded; a failure is logged and stops later writes
+    fn check(&self, result: Result<(), IoError>) -> bool {
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to write shard snapshot, skipping the rest: {}", e);
+                self.failed.store(true, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+/// Shard whose part holds `account` (the shard that owns its client under
+/// actor sharding)
+pub(crate) fn shard_of<A: AmountType>(account: &ClientAccount<A>, num_shards: usize) -> usize {
+    account.client_id() as usize % num_shards
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::domain::{ClientId, FixedPoint, apply_deposit};
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager};
+
+    /// Records every call, failing writes when `fail` is set
+    struct RecordingSink {
+        calls: Arc<parking_lot::Mutex<Vec<String>>>,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl SnapshotSink<FixedPoint> for RecordingSink {
+        async fn begin(&mut self) -> Result<(), IoError> {
+            self.calls.lock().push("begin".to_string());
+            Ok(())
+        }
+
+        async fn write_account(
+            &mut self,
+            account: &ClientAccount<FixedPoint>,
+        ) -> Result<(), IoError> {
+            if self.fail {
+                return Err(std::io::Error::other("disk full").into());
+            }
+            self.calls.lock().push(account.client_id().to_string());
+            Ok(())
+        }
+
+        async fn end_partition(&mut self) -> Result<(), IoError> {
+            self.calls.lock().push("end".to_string());
+            Ok(())
+        }
+
+        async fn finish(&mut self) -> Result<(), IoError> {
+            self.calls.lock().push("finish".to_string());
+            Ok(())
+        }
+    }
+
+    fn accounts(clients: &[ClientId]) -> ConcurrentAccountManager<FixedPoint> {
+        let accounts = ConcurrentAccountManager::new();
+        for &client_id in clients {
+            accounts
+                .entry(client_id)
+                .unwrap()
+                .try_update(|account| apply_deposit(account, FixedPoint::from_raw(10_000)))
+                .unwrap();
+        }
+        accounts
+    }
+
+    async fn run(fail: bool) -> Vec<String> {
+        let calls = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = RecordingSink {
+            calls: calls.clone(),
+            fail,
+        };
+        let accounts = accounts(&[4, 1, 3, 2]);
+
+        let snapshots = ShardSnapshots::begin(Box::new(sink)).await;
+        snapshots
+            .write_part(&accounts, |account| shard_of(account, 2) == 1)
+            .await;
+        snapshots
+            .write_part(&accounts, |account| shard_of(account, 2) == 0)
+            .await;
+        snapshots.finish().await;
+
+        calls.lock().clone()
+    }
+
+    #[tokio::test]
+    async fn parts_hold_the_selected_accounts_in_order() {
+        assert_eq!(
+            run(false).await,
+            ["begin", "1", "3", "end", "2", "4", "end", "finish"]
+        );
+    }
+
+    #[tokio::test]
+    async fn write_parts_splits_accounts_by_shard() {
+        let calls = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = RecordingSink {
+            calls: calls.clone(),
+            fail: false,
+        };
+        let accounts = accounts(&[5, 1, 3, 2, 4]);
+
+        let snapshots = ShardSnapshots::begin(Box::new(sink)).await;
+        snapshots.write_parts(&accounts, 3).await;
+        snapshots.finish().await;
+
+        assert_eq!(
+            calls.lock().clone(),
+            [
+                "begin", "3", "end", "1", "4", "end", "2", "5", "end", "finish"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_part_skips_the_rest() {
+        assert_eq!(run(true).await, ["begin"]);
+    }
+}
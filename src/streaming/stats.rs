@@ -0,0 +1,112 @@
+use tracing::warn;
+
+use crate::domain::{AmountType, ClientAccount};
+use crate::storage::ClientAccountManager;
+
+/// Aggregate account figures at the end of a run
+///
+/// Computed once after every shard has finished, so the numbers are
+/// consistent with the final snapshot. Totals are in the base currency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountStats<A: AmountType> {
+    /// Number of client accounts
+    pub accounts: usize,
+    /// Number of locked (charged back) accounts
+    pub locked: usize,
+    /// Sum of available funds across accounts
+    pub total_available: A,
+    /// Sum of held funds across accounts
+    pub total_held: A,
+    /// Number of transactions currently under dispute
+    pub disputed: usize,
+}
+
+impl<A: AmountType> Default for AccountStats<A> {
+    fn default() -> Self {
+        Self {
+            accounts: 0,
+            locked: 0,
+            total_available: A::zero(),
+            total_held: A::zero(),
+            disputed: 0,
+        }
+    }
+}
+
+impl<A: AmountType> AccountStats<A> {
+    /// Compute statistics over every account in `accounts`
+    ///
+    /// A total that would overflow stops at the last representable sum and
+    /// logs a warning.
+    pub fn collect(accounts: &impl ClientAccountManager<A>) -> Self {
+        let mut stats = Self::default();
+        let mut overflowed = false;
+
+        accounts.for_each_account(&mut |account: &ClientAccount<A>| {
+            stats.accounts += 1;
+            stats.locked += usize::from(account.is_locked());
+            stats.disputed += account.disputed_count();
+
+            match (
+                stats.total_available.checked_add(account.available()),
+                stats.total_held.checked_add(account.held()),
+            ) {
+                (Some(available), Some(held)) => {
+                    stats.total_available = available;
+                    stats.total_held = held;
+                }
+                _ => overflowed = true,
+            }
+        });
+
+        if overflowed {
+            warn!("Account totals overflowed; final statistics are partial");
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+    use crate::storage::{ClientAccountEntry, ConcurrentAccountManager};
+
+    #[test]
+    fn collects_totals_and_counts() {
+        let accounts = ConcurrentAccountManager::<FixedPoint>::new();
+        accounts
+            .entry(1)
+            .unwrap()
+            .try_update(|account| {
+                account.set_available(FixedPoint::from_raw(15_000));
+                account.set_held(FixedPoint::from_raw(5_000));
+                account.add_disputed(7);
+                Ok(())
+            })
+            .unwrap();
+        accounts
+            .entry(2)
+            .unwrap()
+            .try_update(|account| {
+                account.set_available(FixedPoint::from_raw(20_000));
+                account.lock();
+                Ok(())
+            })
+            .unwrap();
+
+        let stats = AccountStats::collect(&accounts);
+
+        assert_eq!(stats.accounts, 2);
+        assert_eq!(stats.locked, 1);
+        assert_eq!(stats.disputed, 1);
+        assert_eq!(stats.total_available, FixedPoint::from_raw(35_000));
+        assert_eq!(stats.total_held, FixedPoint::from_raw(5_000));
+    }
+
+    #[test]
+    fn empty_manager_has_zero_stats() {
+        let accounts = ConcurrentAccountManager::<FixedPoint>::new();
+        assert_eq!(AccountStats::collect(&accounts), AccountStats::default());
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::task::Poll;
+
+use futures::{Stream, StreamExt, stream};
+use parking_lot::RwLock;
+
+use crate::domain::{AmountType, TimestampedTransaction};
+use crate::io::IoError;
+
+/// Outcome of one input stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamResult {
+    /// Name given with `add_stream_named` (or `stream-<index>`)
+    pub name: String,
+    /// Records read, including unreadable ones
+    pub records: u64,
+    /// Records that could not be read or were rejected by the engine
+    pub errors: u64,
+    /// Whether the stream was read to the end
+    pub completed: bool,
+}
+
+/// Live counters for one input stream, shared with the shard processing it
+pub(crate) struct StreamTracker {
+    name: String,
+    records: AtomicU64,
+    errors: AtomicU64,
+    completed: AtomicBool,
+}
+
+impl StreamTracker {
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            records: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            completed: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Count a record read from the stream, readable or not
+    pub(crate) fn record_read(&self) {
+        self.records.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a record the engine rejected
+    pub(crate) fn record_rejected(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a record that could not be read
+    pub(crate) fn record_unreadable(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Note that the stream was read to the end
+    pub(crate) fn complete(&self) {
+        self.completed.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn result(&self) -> StreamResult {
+        StreamResult {
+            name: self.name.clone(),
+            records: self.records.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Trackers of every input stream by index, with the shard reading each
+///
+/// Streams can join while processing runs (see `StreamHandle`), so the
+/// registry grows as they are first polled by their shard.
+#[derive(Default)]
+pub(crate) struct StreamRegistry {
+    streams: RwLock<BTreeMap<usize, (usize, Arc<StreamTracker>)>>,
+}
+
+impl StreamRegistry {
+    /// Add stream `index`, read by `shard`, and return its tracker
+    pub(crate) fn register(&self, index: usize, shard: usize, name: String) -> Arc<StreamTracker> {
+        let tracker = Arc::new(StreamTracker::new(name));
+        self.streams.write().insert(index, (shard, tracker.clone()));
+        tracker
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<Arc<StreamTracker>> {
+        self.streams
+            .read()
+            .get(&index)
+            .map(|(_, tracker)| tracker.clone())
+    }
+
+    /// Number of registered streams
+    pub(crate) fn len(&self) -> usize {
+        self.streams.read().len()
+    }
+
+    /// Results of the streams read by `shard`, in index order
+    pub(crate) fn shard_results(&self, shard: usize) -> Vec<StreamResult> {
+        self.streams
+            .read()
+            .values()
+            .filter(|(owner, _)| *owner == shard)
+            .map(|(_, tracker)| tracker.result())
+            .collect()
+    }
+}
+
+/// Wrap input stream `index` so its records are counted and tagged with their source
+///
+/// Read errors carry no transaction to tag, so the wrapper notes the index
+/// in `last_error` when it yields one.
+pub(crate) fn track<A, S>(
+    stream: S,
+    index: usize,
+    tracker: Arc<StreamTracker>,
+    last_error: Arc<AtomicUsize>,
+) -> impl Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send
+where
+    A: AmountType,
+    S: Stream<Item = Result<TimestampedTransaction<A>, IoError>> + Send,
+{
+    let counters = tracker.clone();
+    let tracked = stream.map(move |result| {
+        counters.record_read();
+        match result {
+            Ok(mut timestamped) => {
+                timestamped.source = Some(index);
+                Ok(timestamped)
+            }
+            Err(e) => {
+                counters.record_unreadable();
+                last_error.store(index, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    });
+
+    let end = stream::poll_fn(move |_| {
+        tracker.complete();
+        Poll::Ready(None)
+    });
+    tracked.chain(end)
+}
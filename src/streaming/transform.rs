@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use crate::domain::{AmountType, ClientId, Transaction};
+
+/// Stage applied to every transaction before processing
+///
+/// Returning `None` drops the transaction; returning `Some` passes it (possibly
+/// rewritten) to the next stage and then to the engine.
+pub type Transform<A> = dyn Fn(Transaction<A>) -> Option<Transaction<A>> + Send + Sync;
+
+/// Selects transactions by type and by client
+///
+/// An empty filter accepts everything. Use with `StreamProcessor::with_transform`:
+///
+/// ```rust,ignore
+/// let filter = TransactionFilter::new()
+///     .without_types(["dispute", "resolve", "chargeback"])
+///     .with_clients([1, 2, 3]);
+///
+/// processor.with_transform(move |tx| filter.apply(tx))
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    clients: Option<HashSet<ClientId>>,
+    types: Option<HashSet<String>>,
+    excluded_types: HashSet<String>,
+}
+
+impl TransactionFilter {
+    /// Create a filter that accepts every transaction
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accept transactions initiated by these clients
+    pub fn with_clients(mut self, clients: impl IntoIterator<Item = ClientId>) -> Self {
+        self.clients = Some(clients.into_iter().collect());
+        self
+    }
+
+    /// Only accept these transaction types (CSV `type` names, e.g. "deposit")
+    pub fn with_types<S: AsRef<str>>(mut self, types: impl IntoIterator<Item = S>) -> Self {
+        self.types = Some(types.into_iter().map(normalize).collect());
+        self
+    }
+
+    /// Drop these transaction types (CSV `type` names, e.g. "dispute")
+    pub fn without_types<S: AsRef<str>>(mut self, types: impl IntoIterator<Item = S>) -> Self {
+        self.excluded_types.extend(types.into_iter().map(normalize));
+        self
+    }
+
+    /// Check whether a transaction passes the filter
+    pub fn accepts<A: AmountType>(&self, tx: &Transaction<A>) -> bool {
+        let type_name = tx.type_name();
+
+        self.clients
+            .as_ref()
+            .is_none_or(|clients| clients.contains(&tx.client_id()))
+            && self
+                .types
+                .as_ref()
+                .is_none_or(|types| types.contains(type_name))
+            && !self.excluded_types.contains(type_name)
+    }
+
+    /// Pass the transaction through if accepted (for use as a transform)
+    pub fn apply<A: AmountType>(&self, tx: Transaction<A>) -> Option<Transaction<A>> {
+        self.accepts(&tx).then_some(tx)
+    }
+}
+
+fn normalize(name: impl AsRef<str>) -> String {
+    name.as_ref().trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn deposit(client_id: ClientId) -> Transaction<FixedPoint> {
+        Transaction::Deposit {
+            client_id,
+            tx_id: 1,
+            amount: FixedPoint::from_raw(10_000),
+            currency: None,
+        }
+    }
+
+    fn dispute(client_id: ClientId) -> Transaction<FixedPoint> {
+        Transaction::Dispute {
+            client_id,
+            tx_id: 1,
+        }
+    }
+
+    #[test]
+    fn empty_filter_accepts_everything() {
+        let filter = TransactionFilter::new();
+
+        assert!(filter.accepts(&deposit(1)));
+        assert!(filter.accepts(&dispute(2)));
+    }
+
+    #[test]
+    fn filters_by_client_and_type() {
+        let filter = TransactionFilter::new()
+            .with_clients([1])
+            .without_types(["Dispute"]);
+
+        assert!(filter.accepts(&deposit(1)));
+        assert!(!filter.accepts(&deposit(2)));
+        assert!(!filter.accepts(&dispute(1)));
+    }
+
+    #[test]
+    fn with_types_restricts_to_listed_types() {
+        let filter = TransactionFilter::new().with_types(["deposit"]);
+
+        assert_eq!(filter.apply(deposit(3)), Some(deposit(3)));
+        assert_eq!(filter.apply(dispute(3)), None);
+    }
+}
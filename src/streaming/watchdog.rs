@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use futures::{Stream, StreamExt, stream};
+use tracing::warn;
+
+/// Watch a shard's stream for stalls, as configured by `with_stall_timeout`
+///
+/// When `stream` yields nothing for `timeout`, a warning is logged once for
+/// that stall. With `cancel`, the stream then ends early and `stalled` is
+/// set, so the shard finishes with what it has applied so far.
+pub(crate) fn watchdog<S>(
+    stream: S,
+    timeout: Duration,
+    cancel: bool,
+    shard_id: usize,
+    stalled: Arc<AtomicBool>,
+) -> impl Stream<Item = S::Item> + Send
+where
+    S: Stream + Unpin + Send,
+    S::Item: Send,
+{
+    stream::unfold(stream, move |mut stream| {
+        let stalled = stalled.clone();
+        async move {
+            let mut warned = false;
+            loop {
+                match tokio::time::timeout(timeout, stream.next()).await {
+                    Ok(item) => return item.map(|item| (item, stream)),
+                    Err(_) => {
+                        if !warned {
+                            warn!(
+                                shard_id,
+                                ?timeout,
+                                "Shard made no progress; its source may be stuck"
+                            );
+                            warned = true;
+                        }
+                        if cancel {
+                            warn!(shard_id, "Cancelling stalled shard");
+                            stalled.store(true, Ordering::Relaxed);
+                            return None;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hanging_after(items: Vec<u32>) -> impl Stream<Item = u32> + Unpin + Send {
+        stream::iter(items).chain(stream::pending())
+    }
+
+    #[tokio::test]
+    async fn cancels_a_stream_that_stops_yielding() {
+        let stalled = Arc::new(AtomicBool::new(false));
+        let items: Vec<_> = watchdog(
+            hanging_after(vec![1, 2]),
+            Duration::from_millis(50),
+            true,
+            0,
+            stalled.clone(),
+        )
+        .collect()
+        .await;
+
+        assert_eq!(items, vec![1, 2]);
+        assert!(stalled.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn keeps_waiting_without_cancel() {
+        let stalled = Arc::new(AtomicBool::new(false));
+        let slow = stream::iter([1, 2]).then(|item| async move {
+            tokio::time::sleep(Duration::from_millis(120)).await;
+            item
+        });
+        let items: Vec<_> = watchdog(
+            Box::pin(slow),
+            Duration::from_millis(50),
+            false,
+            0,
+            stalled.clone(),
+        )
+        .collect()
+        .await;
+
+        assert_eq!(items, vec![1, 2]);
+        assert!(!stalled.load(Ordering::Relaxed));
+    }
+}
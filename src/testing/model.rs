@@ -0,0 +1,328 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::domain::{AmountType, ClientAccount, ClientId, Transaction, TransactionId};
+use crate::engine::TransactionTypeCounts;
+use crate::storage::ClientAccountManager;
+
+/// Base-currency balances of one account, as compared by the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountState<A: AmountType> {
+    pub client_id: ClientId,
+    pub available: A,
+    pub held: A,
+    pub locked: bool,
+}
+
+impl<A: AmountType> AccountState<A> {
+    /// Base-currency balances of `account`
+    pub fn of(account: &ClientAccount<A>) -> Self {
+        Self {
+            client_id: account.client_id(),
+            available: account.available(),
+            held: account.held(),
+            locked: account.is_locked(),
+        }
+    }
+}
+
+/// Every account held by `account_manager`, ordered by client id
+pub fn snapshot_of<A, M>(account_manager: &M) -> Vec<AccountState<A>>
+where
+    A: AmountType,
+    M: ClientAccountManager<A>,
+{
+    let mut accounts = Vec::new();
+    account_manager.for_each_account(&mut |account| accounts.push(AccountState::of(account)));
+    accounts.sort_by_key(|account| account.client_id);
+    accounts
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DepositState {
+    Posted,
+    Disputed,
+    Resolved,
+    ChargedBack,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Deposit<A> {
+    client_id: ClientId,
+    amount: A,
+    state: DepositState,
+}
+
+/// Single-threaded reference implementation of the engine's default semantics
+///
+/// Written independently of `TransactionProcessor` as a plain in-memory state
+/// machine, so it can serve as the oracle when property-testing concurrent
+/// topologies: apply a workload serially here, run the same workload through
+/// shards, lanes or merged streams, and compare `snapshot()` with
+/// `snapshot_of(&account_manager)`. It covers deposits, withdrawals and the
+/// dispute lifecycle under `DisputePolicy::default()` with no fees or
+/// validators; every other transaction type is rejected.
+///
+/// # Example
+/// ```rust,ignore
+/// let workload = WorkloadGenerator::new(10_000).with_seed(seed);
+/// let mut model = ReferenceModel::new();
+/// model.apply_all(workload.generate());
+///
+/// let mut processor = StreamProcessor::new(accounts.clone(), store, SilentSkip).with_shards(8);
+/// for stream in workload.generate_streams(4) {
+///     processor = processor.add_stream(stream::iter(stream.into_iter().map(Ok)));
+/// }
+/// processor.process().await;
+/// assert_eq!(snapshot_of(&*accounts), model.snapshot());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReferenceModel<A: AmountType> {
+    accounts: BTreeMap<ClientId, AccountState<A>>,
+    deposits: HashMap<TransactionId, Deposit<A>>,
+    counts: TransactionTypeCounts,
+}
+
+impl<A: AmountType> Default for ReferenceModel<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: AmountType> ReferenceModel<A> {
+    /// Create a model with no accounts
+    pub fn new() -> Self {
+        Self {
+            accounts: BTreeMap::new(),
+            deposits: HashMap::new(),
+            counts: TransactionTypeCounts::default(),
+        }
+    }
+
+    /// Apply one transaction, returning whether it was accepted
+    pub fn apply(&mut self, tx: Transaction<A>) -> bool {
+        let kind = tx.type_name();
+        let applied = match tx {
+            Transaction::Deposit {
+                client_id,
+                tx_id,
+                amount,
+                currency: None,
+            } => self.deposit(client_id, tx_id, amount),
+            Transaction::Withdrawal {
+                client_id,
+                amount,
+                currency: None,
+                ..
+            } => self.withdraw(client_id, amount),
+            Transaction::Dispute { client_id, tx_id } => self.dispute(client_id, tx_id),
+            Transaction::Resolve { client_id, tx_id } => self.resolve(client_id, tx_id),
+            Transaction::Chargeback { client_id, tx_id } => self.charge_back(client_id, tx_id),
+            _ => false,
+        };
+        self.counts.record(kind, applied);
+        applied
+    }
+
+    /// Apply transactions in order
+    pub fn apply_all(&mut self, transactions: impl IntoIterator<Item = Transaction<A>>) {
+        for tx in transactions {
+            self.apply(tx);
+        }
+    }
+
+    /// Every account, ordered by client id
+    pub fn snapshot(&self) -> Vec<AccountState<A>> {
+        self.accounts.values().copied().collect()
+    }
+
+    /// Accepted and rejected transactions so far, by type
+    pub fn type_counts(&self) -> &TransactionTypeCounts {
+        &self.counts
+    }
+
+    fn deposit(&mut self, client_id: ClientId, tx_id: TransactionId, amount: A) -> bool {
+        if amount <= A::zero() {
+            return false;
+        }
+        let account = self
+            .accounts
+            .get(&client_id)
+            .copied()
+            .unwrap_or(AccountState {
+                client_id,
+                available: A::zero(),
+                held: A::zero(),
+                locked: false,
+            });
+        let Some(available) = account.available.checked_add(amount) else {
+            return false;
+        };
+        if account.locked || available.checked_add(account.held).is_none() {
+            return false;
+        }
+
+        self.accounts.insert(
+            client_id,
+            AccountState {
+                available,
+                ..account
+            },
+        );
+        let state = DepositState::Posted;
+        self.deposits.insert(
+            tx_id,
+            Deposit {
+                client_id,
+                amount,
+                state,
+            },
+        );
+        true
+    }
+
+    fn withdraw(&mut self, client_id: ClientId, amount: A) -> bool {
+        let Some(account) = self.accounts.get_mut(&client_id) else {
+            return false;
+        };
+        if amount <= A::zero() || account.locked || account.available < amount {
+            return false;
+        }
+        account.available = account.available - amount;
+        true
+    }
+
+    fn dispute(&mut self, client_id: ClientId, tx_id: TransactionId) -> bool {
+        let Some((account, deposit)) = self.lookup(client_id, tx_id) else {
+            return false;
+        };
+        let disputable = matches!(deposit.state, DepositState::Posted | DepositState::Resolved);
+        if !disputable || account.locked || account.available < deposit.amount {
+            return false;
+        }
+        account.available = account.available - deposit.amount;
+        account.held = account.held + deposit.amount;
+        deposit.state = DepositState::Disputed;
+        true
+    }
+
+    fn resolve(&mut self, client_id: ClientId, tx_id: TransactionId) -> bool {
+        let Some((account, deposit)) = self.lookup(client_id, tx_id) else {
+            return false;
+        };
+        if deposit.state != DepositState::Disputed || account.locked {
+            return false;
+        }
+        account.held = account.held - deposit.amount;
+        account.available = account.available + deposit.amount;
+        deposit.state = DepositState::Resolved;
+        true
+    }
+
+    fn charge_back(&mut self, client_id: ClientId, tx_id: TransactionId) -> bool {
+        let Some((account, deposit)) = self.lookup(client_id, tx_id) else {
+            return false;
+        };
+        if deposit.state != DepositState::Disputed {
+            return false;
+        }
+        account.held = account.held - deposit.amount;
+        account.locked = true;
+        deposit.state = DepositState::ChargedBack;
+        true
+    }
+
+    /// The client's account and its deposit `tx_id`
+    fn lookup(
+        &mut self,
+        client_id: ClientId,
+        tx_id: TransactionId,
+    ) -> Option<(&mut AccountState<A>, &mut Deposit<A>)> {
+        let deposit = self
+            .deposits
+            .get_mut(&tx_id)
+            .filter(|deposit| deposit.client_id == client_id)?;
+        let account = self.accounts.get_mut(&client_id)?;
+        Some((account, deposit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::FixedPoint;
+
+    fn amount(raw: i64) -> FixedPoint {
+        FixedPoint::from_raw(raw)
+    }
+
+    fn deposit(client_id: ClientId, tx_id: TransactionId, raw: i64) -> Transaction<FixedPoint> {
+        Transaction::Deposit {
+            client_id,
+            tx_id,
+            amount: amount(raw),
+            currency: None,
+        }
+    }
+
+    #[test]
+    fn dispute_lifecycle() {
+        let mut model = ReferenceModel::new();
+        model.apply_all([
+            deposit(1, 1, 100_000),
+            deposit(1, 2, 50_000),
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 1,
+            },
+            Transaction::Resolve {
+                client_id: 1,
+                tx_id: 1,
+            },
+            Transaction::Dispute {
+                client_id: 1,
+                tx_id: 2,
+            },
+            Transaction::Chargeback {
+                client_id: 1,
+                tx_id: 2,
+            },
+        ]);
+
+        assert_eq!(
+            model.snapshot(),
+            vec![AccountState {
+                client_id: 1,
+                available: amount(100_000),
+                held: amount(0),
+                locked: true,
+            }]
+        );
+        assert!(!model.apply(deposit(1, 3, 10_000)));
+        assert_eq!(model.type_counts().get("deposit").rejected, 1);
+    }
+
+    #[test]
+    fn rejections_leave_state_unchanged() {
+        let mut model = ReferenceModel::new();
+
+        assert!(!model.apply(Transaction::Withdrawal {
+            client_id: 1,
+            tx_id: 1,
+            amount: amount(10_000),
+            currency: None,
+        }));
+        assert!(model.apply(deposit(2, 2, 10_000)));
+        assert!(!model.apply(Transaction::Dispute {
+            client_id: 3,
+            tx_id: 2
+        }));
+        assert!(!model.apply(Transaction::Resolve {
+            client_id: 2,
+            tx_id: 2
+        }));
+        assert!(!model.apply(Transaction::Unlock { client_id: 2 }));
+
+        assert_eq!(model.snapshot().len(), 1);
+        assert_eq!(model.type_counts().total().applied, 1);
+    }
+}
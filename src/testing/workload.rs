@@ -0,0 +1,308 @@
+use crate::domain::{ClientId, FixedPoint, Transaction, TransactionId};
+use crate::io::generate::{SplitMix64, take_random};
+
+/// Seeded generator of transaction workloads
+///
+/// Each transaction's type is drawn from the configured ratios; whatever
+/// probability is left over charges back an open dispute. Clients are
+/// numbered `1..=clients` and picked with a Zipf distribution, so a few hot
+/// clients see most of the traffic (an exponent of 0 picks uniformly).
+/// Disputes only reference the same client's deposits, and resolves and
+/// chargebacks only reference its open disputes; a draw with nothing to
+/// reference produces no transaction. The output depends only on the
+/// settings and the seed.
+///
+/// # Example
+/// ```rust,ignore
+/// let transactions = WorkloadGenerator::new(10_000)
+///     .with_clients(500)
+///     .with_zipf_exponent(1.2)
+///     .with_seed(7)
+///     .generate();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkloadGenerator {
+    transactions: usize,
+    clients: ClientId,
+    deposit_ratio: f64,
+    withdrawal_ratio: f64,
+    dispute_ratio: f64,
+    resolve_ratio: f64,
+    zipf_exponent: f64,
+    seed: u64,
+}
+
+impl WorkloadGenerator {
+    /// Draw `transactions` transactions for 100 uniformly picked clients: 50%
+    /// deposits, 30% withdrawals, 10% disputes, 5% resolves and 5%
+    /// chargebacks, seeded with 0
+    pub fn new(transactions: usize) -> Self {
+        Self {
+            transactions,
+            clients: 100,
+            deposit_ratio: 0.5,
+            withdrawal_ratio: 0.3,
+            dispute_ratio: 0.1,
+            resolve_ratio: 0.05,
+            zipf_exponent: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// Number of transactions to draw
+    pub fn with_transactions(mut self, transactions: usize) -> Self {
+        self.transactions = transactions;
+        self
+    }
+
+    /// Spread transactions over this many clients (minimum 1)
+    pub fn with_clients(mut self, clients: ClientId) -> Self {
+        self.clients = clients.max(1);
+        self
+    }
+
+    /// Share of draws that are deposits
+    pub fn with_deposit_ratio(mut self, ratio: f64) -> Self {
+        self.deposit_ratio = ratio;
+        self
+    }
+
+    /// Share of draws that are withdrawals
+    pub fn with_withdrawal_ratio(mut self, ratio: f64) -> Self {
+        self.withdrawal_ratio = ratio;
+        self
+    }
+
+    /// Share of draws that dispute an earlier deposit
+    pub fn with_dispute_ratio(mut self, ratio: f64) -> Self {
+        self.dispute_ratio = ratio;
+        self
+    }
+
+    /// Share of draws that resolve an open dispute
+    pub fn with_resolve_ratio(mut self, ratio: f64) -> Self {
+        self.resolve_ratio = ratio;
+        self
+    }
+
+    /// Skew of client selection: client `k` is picked with weight `1 / k^exponent`
+    pub fn with_zipf_exponent(mut self, exponent: f64) -> Self {
+        self.zipf_exponent = exponent;
+        self
+    }
+
+    /// Seed for the pseudo-random sequence
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Check that every ratio is between 0 and 1, they sum to at most 1 and
+    /// the Zipf exponent is not negative
+    pub fn validate(&self) -> Result<(), String> {
+        let ratios = [
+            ("deposit", self.deposit_ratio),
+            ("withdraw", self.withdrawal_ratio),
+            ("dispute", self.dispute_ratio),
+            ("resolve", self.resolve_ratio),
+        ];
+        for (name, ratio) in ratios {
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(format!("{name} ratio must be between 0 and 1, got {ratio}"));
+            }
+        }
+        let sum: f64 = ratios.iter().map(|(_, ratio)| ratio).sum();
+        if sum > 1.0 + f64::EPSILON {
+            return Err(format!("ratios must sum to at most 1, got {sum}"));
+        }
+        if self.zipf_exponent.is_nan() || self.zipf_exponent < 0.0 {
+            return Err(format!(
+                "zipf exponent must not be negative, got {}",
+                self.zipf_exponent
+            ));
+        }
+        Ok(())
+    }
+
+    /// Draw the workload in order
+    pub fn generate(&self) -> Vec<Transaction<FixedPoint>> {
+        let mut rng = SplitMix64(self.seed);
+        let clients = ZipfClients::new(self.clients, self.zipf_exponent);
+        // Undisputed and disputed deposits per client, indexed by client - 1
+        let mut deposits: Vec<Vec<TransactionId>> = vec![Vec::new(); self.clients as usize];
+        let mut disputed: Vec<Vec<TransactionId>> = vec![Vec::new(); self.clients as usize];
+        let mut next_tx: TransactionId = 1;
+        let mut transactions = Vec::with_capacity(self.transactions);
+
+        let withdrawals = self.deposit_ratio + self.withdrawal_ratio;
+        let disputes = withdrawals + self.dispute_ratio;
+        let resolves = disputes + self.resolve_ratio;
+
+        for _ in 0..self.transactions {
+            let index = clients.pick(&mut rng);
+            let client_id = index as ClientId + 1;
+            let draw = rng.next_f64();
+
+            let tx = if draw < withdrawals {
+                let (max_raw, deposit) = match draw < self.deposit_ratio {
+                    true => (10_000_000, true),
+                    false => (1_000_000, false),
+                };
+                let amount = FixedPoint::from_raw((rng.next() % max_raw) as i64 + 1);
+                let tx_id = next_tx;
+                next_tx += 1;
+                if deposit {
+                    deposits[index].push(tx_id);
+                    Transaction::Deposit {
+                        client_id,
+                        tx_id,
+                        amount,
+                        currency: None,
+                    }
+                } else {
+                    Transaction::Withdrawal {
+                        client_id,
+                        tx_id,
+                        amount,
+                        currency: None,
+                    }
+                }
+            } else if draw < disputes {
+                let Some(tx_id) = take_random(&mut deposits[index], &mut rng) else {
+                    continue;
+                };
+                disputed[index].push(tx_id);
+                Transaction::Dispute { client_id, tx_id }
+            } else if draw < resolves {
+                let Some(tx_id) = take_random(&mut disputed[index], &mut rng) else {
+                    continue;
+                };
+                deposits[index].push(tx_id);
+                Transaction::Resolve { client_id, tx_id }
+            } else {
+                let Some(tx_id) = take_random(&mut disputed[index], &mut rng) else {
+                    continue;
+                };
+                Transaction::Chargeback { client_id, tx_id }
+            };
+            transactions.push(tx);
+        }
+        transactions
+    }
+
+    /// Draw the workload split into `streams` streams (minimum 1) by client
+    ///
+    /// Every client's transactions land in the same stream in their drawn
+    /// order, so any interleaving of the streams yields the same final
+    /// balances as the serial workload.
+    pub fn generate_streams(&self, streams: usize) -> Vec<Vec<Transaction<FixedPoint>>> {
+        let streams = streams.max(1);
+        let mut split = vec![Vec::new(); streams];
+        for tx in self.generate() {
+            split[tx.client_id() as usize % streams].push(tx);
+        }
+        split
+    }
+}
+
+/// Cumulative Zipf weights over client indexes
+struct ZipfClients {
+    cumulative: Vec<f64>,
+}
+
+impl ZipfClients {
+    fn new(clients: ClientId, exponent: f64) -> Self {
+        let mut sum = 0.0;
+        let cumulative = (1..=clients)
+            .map(|rank| {
+                sum += 1.0 / f64::from(rank).powf(exponent);
+                sum
+            })
+            .collect();
+        Self { cumulative }
+    }
+
+    /// Client index in `0..clients`
+    fn pick(&self, rng: &mut SplitMix64) -> usize {
+        let total = self.cumulative.last().copied().unwrap_or(0.0);
+        let target = rng.next_f64() * total;
+        self.cumulative
+            .partition_point(|weight| *weight <= target)
+            .min(self.cumulative.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_workload() {
+        let generator = WorkloadGenerator::new(1_000).with_clients(20).with_seed(9);
+
+        assert_eq!(generator.generate(), generator.clone().generate());
+        assert_ne!(
+            generator.generate(),
+            generator.clone().with_seed(10).generate()
+        );
+    }
+
+    #[test]
+    fn zipf_exponent_skews_towards_low_client_ids() {
+        let count_first = |exponent| {
+            WorkloadGenerator::new(5_000)
+                .with_clients(50)
+                .with_zipf_exponent(exponent)
+                .generate()
+                .iter()
+                .filter(|tx| tx.client_id() == 1)
+                .count()
+        };
+
+        // Uniform: ~2% of the traffic; Zipf(1.5): ~40%
+        assert!(count_first(0.0) < 250, "{}", count_first(0.0));
+        assert!(count_first(1.5) > 1_500, "{}", count_first(1.5));
+    }
+
+    #[test]
+    fn streams_keep_each_clients_order() {
+        let generator = WorkloadGenerator::new(2_000).with_clients(30).with_seed(3);
+        let streams = generator.generate_streams(4);
+
+        assert_eq!(
+            streams.iter().map(Vec::len).sum::<usize>(),
+            generator.generate().len()
+        );
+        for client_id in 1..=30 {
+            let serial: Vec<_> = generator
+                .generate()
+                .into_iter()
+                .filter(|tx| tx.client_id() == client_id)
+                .collect();
+            let stream = &streams[client_id as usize % 4];
+            let split: Vec<_> = stream
+                .iter()
+                .filter(|tx| tx.client_id() == client_id)
+                .cloned()
+                .collect();
+            assert_eq!(serial, split);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(WorkloadGenerator::new(1).validate().is_ok());
+        assert!(
+            WorkloadGenerator::new(1)
+                .with_resolve_ratio(0.5)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            WorkloadGenerator::new(1)
+                .with_zipf_exponent(-1.0)
+                .validate()
+                .is_err()
+        );
+    }
+}
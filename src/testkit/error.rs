@@ -0,0 +1,40 @@
+use std::io;
+use thiserror::Error;
+
+/// Testkit errors (scenario loading and suite results)
+#[derive(Error, Debug)]
+pub enum TestkitError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid scenario at line {line}: {message}")]
+    InvalidScenario { line: usize, message: String },
+
+    #[error("{failed} of {total} scenarios failed")]
+    ScenariosFailed { failed: usize, total: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_display_formats_correctly() {
+        assert_eq!(
+            TestkitError::InvalidScenario {
+                line: 3,
+                message: "unknown key".to_string()
+            }
+            .to_string(),
+            "Invalid scenario at line 3: unknown key"
+        );
+        assert_eq!(
+            TestkitError::ScenariosFailed {
+                failed: 1,
+                total: 4
+            }
+            .to_string(),
+            "1 of 4 scenarios failed"
+        );
+    }
+}
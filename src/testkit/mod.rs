@@ -0,0 +1,32 @@
+//! Declarative acceptance testing (requires the `testkit` feature)
+//!
+//! A scenario file describes an engine topology, one or more CSV input streams
+//! and the expected snapshot:
+//!
+//! ```text
+//! # Lines starting with '#' are comments
+//! name: chargeback locks account
+//! shards: 2
+//! combinator: chain
+//!
+//! --- input
+//! type,client,tx,amount
+//! deposit,1,1,10.0
+//! dispute,1,1,
+//! chargeback,1,1,
+//!
+//! --- expect
+//! client,available,held,total,locked
+//! 1,0.0000,0.0000,0.0000,true
+//! ```
+//!
+//! Each `--- input` section becomes a separate stream. Snapshot rows are
+//! compared ignoring order. Run suites with the `pay-testkit` binary:
+//! `cargo run --features testkit --bin pay-testkit -- testkit/scenarios`.
+
+pub mod error;
+pub mod scenario;
+
+// Re-export commonly used types
+pub use error::TestkitError;
+pub use scenario::{Scenario, ScenarioOutcome, Topology};
@@ -0,0 +1,312 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use futures::io::Cursor;
+
+use super::error::TestkitError;
+use crate::domain::FixedPoint;
+use crate::io::CsvTransactionStream;
+use crate::storage::{ClientAccountManager, ConcurrentAccountManager, ConcurrentTransactionStore};
+use crate::streaming::{SilentSkip, StreamCombinator, StreamProcessor};
+
+/// Engine topology a scenario runs against
+#[derive(Debug, Clone, Copy)]
+pub struct Topology {
+    pub shards: usize,
+    pub combinator: StreamCombinator,
+    pub admin_ops: bool,
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Self {
+            shards: 1,
+            combinator: StreamCombinator::Merge,
+            admin_ops: false,
+        }
+    }
+}
+
+/// A declarative acceptance scenario: topology, input streams and expected snapshot
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub topology: Topology,
+    pub inputs: Vec<String>,
+    pub expected: String,
+}
+
+/// Result of running a scenario
+#[derive(Debug, Clone)]
+pub struct ScenarioOutcome {
+    pub name: String,
+    /// Expected rows missing from the actual snapshot
+    pub missing: Vec<String>,
+    /// Actual rows not present in the expected snapshot
+    pub unexpected: Vec<String>,
+}
+
+impl ScenarioOutcome {
+    /// Check if the actual snapshot matched the expectation
+    pub fn passed(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+enum Section {
+    Header,
+    Input,
+    Expect,
+}
+
+impl Scenario {
+    /// Parse a scenario from its text form
+    ///
+    /// `default_name` is used when the scenario has no `name:` key.
+    pub fn parse(text: &str, default_name: &str) -> Result<Self, TestkitError> {
+        let mut name = default_name.to_string();
+        let mut topology = Topology::default();
+        let mut inputs: Vec<String> = Vec::new();
+        let mut expected: Option<String> = None;
+        let mut section = Section::Header;
+
+        for (idx, raw_line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = raw_line.trim();
+            let invalid = |message: String| TestkitError::InvalidScenario {
+                line: line_no,
+                message,
+            };
+
+            if let Some(marker) = line.strip_prefix("---") {
+                match marker.trim() {
+                    "input" => {
+                        inputs.push(String::new());
+                        section = Section::Input;
+                    }
+                    "expect" if expected.is_none() => {
+                        expected = Some(String::new());
+                        section = Section::Expect;
+                    }
+                    "expect" => return Err(invalid("duplicate expect section".to_string())),
+                    other => return Err(invalid(format!("unknown section '{}'", other))),
+                }
+                continue;
+            }
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match section {
+                Section::Header => {
+                    let (key, value) = line
+                        .split_once(':')
+                        .ok_or_else(|| invalid(format!("expected 'key: value', got '{}'", line)))?;
+                    let value = value.trim();
+                    match key.trim() {
+                        "name" => name = value.to_string(),
+                        "shards" => {
+                            topology.shards =
+                                value.parse().ok().filter(|n| *n > 0).ok_or_else(|| {
+                                    invalid(format!("invalid shard count '{}'", value))
+                                })?;
+                        }
+                        "combinator" => {
+                            topology.combinator = match value {
+                                "merge" => StreamCombinator::Merge,
+                                "chain" => StreamCombinator::Chain,
+                                "merge_by_timestamp" => StreamCombinator::MergeByTimestamp,
+                                _ => {
+                                    return Err(invalid(format!("unknown combinator '{}'", value)));
+                                }
+                            };
+                        }
+                        "admin_ops" => {
+                            topology.admin_ops = value
+                                .parse()
+                                .map_err(|_| invalid(format!("invalid bool '{}'", value)))?;
+                        }
+                        other => return Err(invalid(format!("unknown key '{}'", other))),
+                    }
+                }
+                Section::Input => push_line(inputs.last_mut(), raw_line),
+                Section::Expect => push_line(expected.as_mut(), raw_line),
+            }
+        }
+
+        let line = text.lines().count();
+        if inputs.is_empty() {
+            return Err(TestkitError::InvalidScenario {
+                line,
+                message: "missing input section".to_string(),
+            });
+        }
+        let expected = expected.ok_or_else(|| TestkitError::InvalidScenario {
+            line,
+            message: "missing expect section".to_string(),
+        })?;
+
+        Ok(Self {
+            name,
+            topology,
+            inputs,
+            expected,
+        })
+    }
+
+    /// Load a scenario file (the file stem is the default name)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TestkitError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let default_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::parse(&text, &default_name)
+    }
+
+    /// Run the scenario against a fresh engine and compare snapshots
+    pub async fn run(&self) -> Result<ScenarioOutcome, TestkitError> {
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let store = Arc::new(ConcurrentTransactionStore::<FixedPoint>::new());
+
+        let mut processor = StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+            .with_shards(self.topology.shards)
+            .with_stream_combinator(self.topology.combinator)
+            .with_admin_ops(self.topology.admin_ops);
+
+        for input in &self.inputs {
+            let reader = Cursor::new(input.clone().into_bytes());
+            processor = processor.add_timestamped_stream(
+                CsvTransactionStream::<FixedPoint>::new(reader).timestamped(),
+            );
+        }
+        processor.process().await;
+
+        let mut output = Vec::new();
+        account_manager
+            .snapshot(&mut output)
+            .await
+            .map_err(std::io::Error::other)?;
+        let actual = String::from_utf8_lossy(&output);
+
+        let mut expected_rows = rows(&self.expected);
+        let mut unexpected = Vec::new();
+        for row in rows(&actual) {
+            match expected_rows.iter().position(|e| *e == row) {
+                Some(pos) => {
+                    expected_rows.swap_remove(pos);
+                }
+                None => unexpected.push(row),
+            }
+        }
+
+        Ok(ScenarioOutcome {
+            name: self.name.clone(),
+            missing: expected_rows,
+            unexpected,
+        })
+    }
+}
+
+fn push_line(buffer: Option<&mut String>, line: &str) {
+    if let Some(buffer) = buffer {
+        buffer.push_str(line);
+        buffer.push('\n');
+    }
+}
+
+/// Non-empty, trimmed snapshot lines (including the header)
+fn rows(snapshot: &str) -> Vec<String> {
+    snapshot
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHARGEBACK: &str = "\
+name: chargeback locks account
+shards: 2
+combinator: chain
+
+--- input
+type,client,tx,amount
+deposit,1,1,10.0
+dispute,1,1,
+chargeback,1,1,
+
+--- input
+type,client,tx,amount
+deposit,2,2,3.0
+
+--- expect
+client,available,held,total,locked
+2,3.0000,0.0000,3.0000,false
+1,0.0000,0.0000,0.0000,true
+";
+
+    #[test]
+    fn parses_header_and_sections() {
+        let scenario = Scenario::parse(CHARGEBACK, "default").unwrap();
+
+        assert_eq!(scenario.name, "chargeback locks account");
+        assert_eq!(scenario.topology.shards, 2);
+        assert!(matches!(
+            scenario.topology.combinator,
+            StreamCombinator::Chain
+        ));
+        assert_eq!(scenario.inputs.len(), 2);
+        assert!(scenario.expected.starts_with("client,available"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_with_line_number() {
+        let result = Scenario::parse("name: x\nworkers: 3\n", "default");
+
+        assert!(matches!(
+            result,
+            Err(TestkitError::InvalidScenario { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn requires_input_and_expect() {
+        assert!(Scenario::parse("--- expect\n", "x").is_err());
+        assert!(Scenario::parse("--- input\ntype,client,tx,amount\n", "x").is_err());
+    }
+
+    #[tokio::test]
+    async fn passing_scenario_ignores_row_order() {
+        let outcome = Scenario::parse(CHARGEBACK, "default")
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        assert!(outcome.passed(), "{:?}", outcome);
+    }
+
+    #[tokio::test]
+    async fn failing_scenario_reports_row_differences() {
+        let text = CHARGEBACK.replace(
+            "1,0.0000,0.0000,0.0000,true",
+            "1,10.0000,0.0000,10.0000,false",
+        );
+        let outcome = Scenario::parse(&text, "default")
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        assert!(!outcome.passed());
+        assert_eq!(outcome.missing, vec!["1,10.0000,0.0000,10.0000,false"]);
+        assert_eq!(outcome.unexpected, vec!["1,0.0000,0.0000,0.0000,true"]);
+    }
+}
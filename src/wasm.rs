@@ -0,0 +1,148 @@
+//! Browser bindings (requires the `wasm` feature)
+//!
+//! Builds a WebAssembly module for checking partner files in the browser,
+//! with the same parsing and dispute semantics as the `pay` binary:
+//!
+//! ```text
+//! import init, { processCsv, validateCsv } from "./pkg/pay.js";
+//!
+//! await init();
+//! const accounts = JSON.parse(processCsv(text));  // [{"client":1,"available":"1.5000",...}]
+//! const problems = JSON.parse(validateCsv(text)); // [{"line":3,"error":"..."}]
+//! ```
+//!
+//! Build it with `wasm-pack build --target web --features wasm`. Files,
+//! signals and compressed inputs are not available on `wasm32`; everything
+//! here runs synchronously on the calling thread.
+
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use crate::domain::{FixedPoint, RoundingPolicy};
+use crate::engine::TransactionProcessor;
+use crate::io::parse::BorrowedRecord;
+use crate::io::{IoError, JsonSnapshotSink, write_snapshot_to};
+use crate::storage::{ConcurrentAccountManager, ConcurrentTransactionStore};
+
+type Processor = TransactionProcessor<
+    FixedPoint,
+    ConcurrentAccountManager<FixedPoint>,
+    ConcurrentTransactionStore<FixedPoint>,
+>;
+
+/// Process a transactions CSV and return the resulting accounts as a JSON
+/// array, in the `JsonSnapshotSink` format
+///
+/// Malformed rows and rejected transactions are skipped, as in the `pay`
+/// binary.
+#[wasm_bindgen(js_name = processCsv)]
+pub fn process_csv(csv: &str) -> Result<String, JsError> {
+    snapshot_json(csv).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Check a transactions CSV and return the rows that would not be applied,
+/// as a JSON array of `{"line", "error"}` objects (empty if every row applies)
+#[wasm_bindgen(js_name = validateCsv)]
+pub fn validate_csv(csv: &str) -> Result<String, JsError> {
+    problems_json(csv).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// A row that failed to parse or was rejected by the engine
+struct Problem {
+    line: u64,
+    error: String,
+}
+
+fn snapshot_json(csv: &str) -> Result<String, IoError> {
+    let (processor, _) = run(csv)?;
+    let mut sink = JsonSnapshotSink::new(Vec::new());
+    futures::executor::block_on(write_snapshot_to(processor.account_manager(), &mut sink))?;
+    String::from_utf8(sink.into_inner())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+}
+
+fn problems_json(csv: &str) -> Result<String, IoError> {
+    let (_, problems) = run(csv)?;
+    let problems: Vec<_> = problems
+        .into_iter()
+        .map(|problem| json!({ "line": problem.line, "error": problem.error }))
+        .collect();
+    Ok(serde_json::Value::Array(problems).to_string())
+}
+
+/// Apply every row of `csv` to a fresh engine, collecting the rows that were
+/// not applied
+fn run(csv: &str) -> Result<(Processor, Vec<Problem>), IoError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+    let headers = reader.byte_headers()?.clone();
+    let mut processor = TransactionProcessor::new(
+        ConcurrentAccountManager::new(),
+        ConcurrentTransactionStore::new(),
+    );
+
+    let mut problems = Vec::new();
+    let mut record = csv::ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        let result = record
+            .deserialize::<BorrowedRecord>(Some(&headers))
+            .map_err(IoError::from)
+            .and_then(|raw| raw.parse_timestamped_rounded::<FixedPoint>(RoundingPolicy::Reject))
+            .map_err(|e| e.to_string())
+            .and_then(|tx| {
+                let key = tx.idempotency_key.as_deref();
+                processor
+                    .process_transaction_with_key(tx.transaction, key)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(error) = result {
+            problems.push(Problem {
+                line: record.position().map_or(0, |position| position.line()),
+                error,
+            });
+        }
+    }
+    Ok((processor, problems))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "type,client,tx,amount\n\
+                       deposit,1,1,1.5\n\
+                       withdrawal,1,2,5.0\n\
+                       deposit,2,x,1.0\n";
+
+    #[test]
+    fn snapshot_is_json() {
+        let json = snapshot_json(CSV).unwrap();
+        let accounts: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(accounts.as_array().unwrap().len(), 1);
+        assert_eq!(accounts[0]["client"], 1);
+        assert_eq!(accounts[0]["available"], "1.5000");
+    }
+
+    #[test]
+    fn problems_name_the_line() {
+        let json = problems_json(CSV).unwrap();
+        let problems: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let lines: Vec<_> = problems
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|problem| problem["line"].as_u64().unwrap())
+            .collect();
+        assert_eq!(lines, vec![3, 4]);
+        assert!(
+            problems[0]["error"]
+                .as_str()
+                .unwrap()
+                .contains("Insufficient funds")
+        );
+    }
+}
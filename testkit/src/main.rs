@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use pay::prelude::*;
+use pay::testkit::{Scenario, TestkitError};
+use tokio::io::AsyncWriteExt;
+
+/// Declarative acceptance test runner
+///
+/// Runs every `.scenario` file given on the command line (directories are
+/// searched non-recursively) and reports which snapshots did not match.
+///
+/// Run with: cargo run --features testkit --bin pay-testkit -- testkit/scenarios
+fn main() {
+    CliApp::new("pay-testkit")
+        .with_args(parse_args)
+        .run(run_scenarios);
+}
+
+/// Collect scenario files from the given file and directory arguments
+fn parse_args(args: Vec<String>) -> Result<Vec<PathBuf>, AppError> {
+    if args.len() < 2 {
+        return Err(AppError::InvalidArguments(
+            "Usage: pay-testkit <scenario-file-or-dir>...".to_string(),
+        ));
+    }
+
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            let mut found: Vec<PathBuf> = std::fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "scenario"))
+                .collect();
+            found.sort();
+            paths.extend(found);
+        } else if path.is_file() {
+            paths.push(path.to_path_buf());
+        } else {
+            return Err(AppError::FileNotFound(arg.clone()));
+        }
+    }
+
+    Ok(paths)
+}
+
+async fn run_scenarios(mut writers: Writers, paths: Vec<PathBuf>) -> Result<(), AppError> {
+    let mut failed = 0;
+
+    for path in &paths {
+        let outcome = Scenario::from_file(path)?.run().await?;
+
+        if outcome.passed() {
+            writers
+                .stdout
+                .write_all(format!("PASS {}\n", outcome.name).as_bytes())
+                .await?;
+            continue;
+        }
+
+        failed += 1;
+        let mut report = format!("FAIL {} ({})\n", outcome.name, path.display());
+        for row in &outcome.missing {
+            report.push_str(&format!("  - expected: {}\n", row));
+        }
+        for row in &outcome.unexpected {
+            report.push_str(&format!("  + actual:   {}\n", row));
+        }
+        writers.stdout.write_all(report.as_bytes()).await?;
+    }
+
+    writers
+        .stdout
+        .write_all(format!("\n{} passed, {} failed\n", paths.len() - failed, failed).as_bytes())
+        .await?;
+    writers.stdout.flush().await?;
+
+    if failed > 0 {
+        return Err(TestkitError::ScenariosFailed {
+            failed,
+            total: paths.len(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
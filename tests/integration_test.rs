@@ -186,3 +186,341 @@ dispute,1,1,
     // Total: 80.0, Held: 50.0, Available: 30.0
     assert!(output.contains("1,30.0000,50.0000,80.0000,false"));
 }
+
+#[tokio::test]
+async fn transfer_between_clients() {
+    let input = "\
+type,client,tx,amount,to
+deposit,1,1,100.0
+transfer,1,2,40.0,2
+transfer,2,3,50.0,1
+";
+
+    let output = process_csv(input).await;
+
+    // Second transfer exceeds client 2's balance and is ignored
+    assert!(output.contains("1,60.0000,0.0000,60.0000,false"));
+    assert!(output.contains("2,40.0000,0.0000,40.0000,false"));
+}
+
+#[tokio::test]
+async fn multi_currency_balances_per_currency() {
+    let input = "\
+type,client,tx,amount,to,timestamp,currency
+deposit,1,1,10.0,,,
+deposit,1,2,5.0,,,usd
+deposit,1,3,1.5,,,USD
+dispute,1,2,,,,
+withdrawal,1,4,2.0,,,USD
+withdrawal,1,5,1.0,,,EUR
+";
+    let reader = Cursor::new(input.to_string().into_bytes());
+    let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+    let store = Arc::new(ConcurrentTransactionStore::new());
+
+    StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+        .add_stream(CsvTransactionStream::<FixedPoint>::new(reader))
+        .process()
+        .await;
+
+    let mut output = Vec::new();
+    account_manager
+        .snapshot_with_format(
+            &mut output,
+            &SnapshotFormat::default().with_currency_column(true),
+        )
+        .await
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    // The dispute holds USD funds, so the USD withdrawal fails; the EUR
+    // withdrawal fails without creating an EUR balance
+    assert_eq!(
+        output,
+        "client,currency,available,held,total,locked\n\
+         1,,10.0000,0.0000,10.0000,false\n\
+         1,USD,1.5000,5.0000,6.5000,false\n"
+    );
+}
+
+#[tokio::test]
+async fn event_sourced_storage_replays_the_operations_applied() {
+    let input = "\
+type,client,tx,amount,to
+deposit,1,1,10.0,
+deposit,2,2,5.0,
+transfer,1,3,4.0,2
+withdrawal,2,4,1.0,
+dispute,2,2,,
+chargeback,2,2,,
+withdrawal,1,5,50.0,
+";
+    let reader = Cursor::new(input.to_string().into_bytes());
+    let account_manager = Arc::new(EventSourcedAccountManager::<FixedPoint>::new());
+    let store = Arc::new(ConcurrentTransactionStore::new());
+
+    StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+        .add_stream(CsvTransactionStream::<FixedPoint>::new(reader))
+        .process()
+        .await;
+
+    // The failed withdrawal is not logged
+    let events = account_manager.events_since(0);
+    assert_eq!(events.len(), 6);
+    assert_eq!(
+        events[2],
+        AccountEvent::AppliedPair {
+            first_id: 1,
+            second_id: 2,
+            op: PairOp::Transfer {
+                amount: FixedPoint::from_raw(40_000),
+                currency: None,
+            },
+        }
+    );
+
+    let replayed = EventSourcedAccountManager::from_events(events).unwrap();
+    for client_id in [1, 2] {
+        assert_eq!(
+            replayed.get(client_id).unwrap(),
+            account_manager.get(client_id).unwrap()
+        );
+    }
+    let charged_back = replayed.get(2).unwrap().unwrap();
+    assert_eq!(charged_back.available(), FixedPoint::from_raw(30_000));
+    assert!(charged_back.is_locked());
+}
+
+#[tokio::test]
+async fn reads_gzip_compressed_file() {
+    use async_compression::tokio::bufread::GzipEncoder;
+    use tokio::io::AsyncReadExt;
+
+    let input = b"type,client,tx,amount\ndeposit,1,1,2.5\nwithdrawal,1,2,1.0\n";
+    let mut compressed = Vec::new();
+    GzipEncoder::new(&input[..])
+        .read_to_end(&mut compressed)
+        .await
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("transactions.csv.gz");
+    std::fs::write(&path, compressed).unwrap();
+
+    let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+    let store = Arc::new(ConcurrentTransactionStore::new());
+
+    StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+        .add_stream(
+            CsvTransactionStream::<FixedPoint>::from_file(&path)
+                .await
+                .unwrap(),
+        )
+        .process()
+        .await;
+
+    let mut output = Vec::new();
+    write_snapshot(&*account_manager, &mut output)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "client,available,held,total,locked\n1,1.5000,0.0000,1.5000,false\n"
+    );
+}
+
+#[tokio::test]
+async fn client_sequencing_orders_split_history() {
+    // Client 1's withdrawal (seq 2) is in the first file, its deposit (seq 1) in the second
+    let part_1 = "type,client,tx,amount,seq\nwithdrawal,1,2,1.0,2\n";
+    let part_2 = "type,client,tx,amount,seq\ndeposit,1,1,3.0,1\n";
+    let stream = |csv: &str| {
+        CsvTransactionStream::<FixedPoint>::new(Cursor::new(csv.to_string().into_bytes()))
+            .timestamped()
+    };
+
+    let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+    let store = Arc::new(ConcurrentTransactionStore::new());
+
+    StreamProcessor::new(account_manager.clone(), store, SilentSkip)
+        .with_stream_combinator(StreamCombinator::Chain)
+        .with_client_sequencing(16)
+        .add_timestamped_stream(stream(part_1))
+        .add_timestamped_stream(stream(part_2))
+        .process()
+        .await;
+
+    let mut output = Vec::new();
+    write_snapshot(&*account_manager, &mut output)
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "client,available,held,total,locked\n1,2.0000,0.0000,2.0000,false\n"
+    );
+}
+
+#[tokio::test]
+async fn hold_capture_release_from_csv() {
+    let input = "\
+type,client,tx,amount
+deposit,1,1,10.0
+hold,1,2,4.0
+hold,1,3,1.5
+capture,1,2,
+release,1,3,
+hold,1,4,2.0
+";
+    let output = process_csv(input).await;
+
+    // 4.0 captured, 1.5 released, 2.0 still held
+    assert_eq!(
+        output,
+        "client,available,held,total,locked\n1,4.0000,2.0000,6.0000,false\n"
+    );
+}
+
+#[tokio::test]
+async fn resume_from_checkpoint_skips_processed_records() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("transactions.csv");
+    let checkpoint_path = dir.path().join("transactions.checkpoint");
+    std::fs::write(
+        &input,
+        "type,client,tx,amount\ndeposit,1,1,5.0\nwithdrawal,1,2,1.0\ndeposit,2,3,2.0\n",
+    )
+    .unwrap();
+
+    // A complete run leaves a final checkpoint covering every record
+    StreamProcessor::new(
+        Arc::new(ConcurrentAccountManager::<FixedPoint>::new()),
+        Arc::new(ConcurrentTransactionStore::new()),
+        SilentSkip,
+    )
+    .with_checkpoints(&checkpoint_path, 1_000)
+    .add_stream(CsvTransactionStream::from_file(&input).await.unwrap())
+    .process()
+    .await;
+
+    // Resuming restores the balances without reapplying any record
+    let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+    StreamProcessor::new(
+        account_manager.clone(),
+        Arc::new(ConcurrentTransactionStore::new()),
+        SilentSkip,
+    )
+    .resume_from(Checkpoint::load(&checkpoint_path).unwrap())
+    .add_stream(CsvTransactionStream::from_file(&input).await.unwrap())
+    .process()
+    .await;
+
+    let mut output = Vec::new();
+    write_snapshot(&*account_manager, &mut output)
+        .await
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("1,4.0000,0.0000,4.0000,false\n"));
+    assert!(output.contains("2,2.0000,0.0000,2.0000,false\n"));
+}
+
+#[tokio::test]
+async fn diff_between_end_of_day_snapshots() {
+    let day_one = process_csv(
+        "type,client,tx,amount\n\
+         deposit,1,1,5.0\n\
+         deposit,2,2,3.0\n",
+    )
+    .await;
+    let day_two = process_csv(
+        "type,client,tx,amount\n\
+         deposit,1,1,5.0\n\
+         deposit,2,2,3.0\n\
+         dispute,2,2,\n\
+         chargeback,2,2,\n\
+         deposit,3,3,1.5\n",
+    )
+    .await;
+
+    let diff = diff_snapshots::<FixedPoint, _, _>(day_one.as_bytes(), day_two.as_bytes()).unwrap();
+
+    let statuses: Vec<_> = diff
+        .deltas
+        .iter()
+        .map(|delta| (delta.client, delta.status()))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![(2, DeltaStatus::Locked), (3, DeltaStatus::New)]
+    );
+    assert_eq!(
+        diff.deltas[0].total_change().unwrap(),
+        FixedPoint::from_raw(-30_000)
+    );
+}
+
+#[tokio::test]
+async fn generated_dataset_processes_reproducibly() {
+    let generate = || {
+        let mut csv = Vec::new();
+        DatasetGenerator::new(5_000)
+            .with_clients(50)
+            .with_seed(7)
+            .write_csv(&mut csv)
+            .unwrap();
+        String::from_utf8(csv).unwrap()
+    };
+    let csv = generate();
+    assert_eq!(csv, generate());
+
+    let mut first = process_csv(&csv)
+        .await
+        .lines()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let mut second = process_csv(&csv)
+        .await
+        .lines()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    first.sort();
+    second.sort();
+
+    // Header plus one row per client
+    assert_eq!(first.len(), 51);
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn concurrent_topologies_match_the_reference_model() {
+    use futures::stream;
+    use pay::testing::{ReferenceModel, WorkloadGenerator, snapshot_of};
+
+    for seed in 0..8 {
+        let workload = WorkloadGenerator::new(3_000)
+            .with_clients(40)
+            .with_zipf_exponent(1.1)
+            .with_seed(seed);
+        let mut model = ReferenceModel::new();
+        model.apply_all(workload.generate());
+
+        let account_manager = Arc::new(ConcurrentAccountManager::<FixedPoint>::new());
+        let mut processor = StreamProcessor::new(
+            account_manager.clone(),
+            Arc::new(ConcurrentTransactionStore::new()),
+            SilentSkip,
+        )
+        .with_shards(4)
+        .with_shard_concurrency(3);
+        for transactions in workload.generate_streams(3) {
+            processor = processor.add_stream(stream::iter(transactions.into_iter().map(Ok)));
+        }
+        let results = processor.process().await;
+
+        assert_eq!(
+            snapshot_of(&*account_manager),
+            model.snapshot(),
+            "seed {seed}"
+        );
+        assert_eq!(&results.by_type(), model.type_counts(), "seed {seed}");
+    }
+}